@@ -6,6 +6,7 @@ from typing import List, Dict, Any
 
 from PySide6.QtCore import Qt
 from PySide6.QtWidgets import (
+    QApplication,
     QWidget,
     QVBoxLayout,
     QHBoxLayout,
@@ -17,7 +18,7 @@ from PySide6.QtWidgets import (
     QMessageBox,
 )
 
-from pybackend.rust_bridge import run_wifi_scan
+from pybackend.rust_bridge import stream_wifi_scan
 
 
 class ScanTab(QWidget):
@@ -102,6 +103,22 @@ class ScanTab(QWidget):
     def _clear_table(self):
         self.table.setRowCount(0)
 
+    def _append_row(self, ap: Dict[str, Any]):
+        row_idx = self.table.rowCount()
+        self.table.insertRow(row_idx)
+
+        ssid = ap.get("ssid", "")
+        bssid = ap.get("bssid", "")
+        freq = ap.get("freq_mhz", "")
+        sig = ap.get("signal_dbm", "")
+        ch = ap.get("channel", "")
+
+        self.table.setItem(row_idx, 0, QTableWidgetItem(str(ssid)))
+        self.table.setItem(row_idx, 1, QTableWidgetItem(str(bssid)))
+        self.table.setItem(row_idx, 2, QTableWidgetItem(str(freq)))
+        self.table.setItem(row_idx, 3, QTableWidgetItem(str(sig)))
+        self.table.setItem(row_idx, 4, QTableWidgetItem(str(ch)))
+
     # ------------------------------------------------------------------ Slots
 
     def _on_floor_changed(self, idx: int):
@@ -133,8 +150,15 @@ class ScanTab(QWidget):
 
         room_name = room.get("name", f"Room {r_idx + 1}")
 
+        # Paint each AP as soon as the backend has parsed it instead of
+        # waiting for the whole dump.
+        self._clear_table()
+        rows: List[Dict[str, Any]] = []
         try:
-            rows = run_wifi_scan(room_name)
+            for ap in stream_wifi_scan(room_name):
+                rows.append(ap)
+                self._append_row(ap)
+                QApplication.processEvents()
         except Exception as e:
             QMessageBox.critical(self, "Scan Error", str(e))
             return
@@ -142,24 +166,6 @@ class ScanTab(QWidget):
         # Save scan data to that specific room
         room["scan_data"] = rows
 
-        # Update table
-        self._clear_table()
-        for ap in rows:
-            row_idx = self.table.rowCount()
-            self.table.insertRow(row_idx)
-
-            ssid = ap.get("ssid", "")
-            bssid = ap.get("bssid", "")
-            freq = ap.get("freq_mhz", "")
-            sig = ap.get("signal_dbm", "")
-            ch = ap.get("channel", "")
-
-            self.table.setItem(row_idx, 0, QTableWidgetItem(str(ssid)))
-            self.table.setItem(row_idx, 1, QTableWidgetItem(str(bssid)))
-            self.table.setItem(row_idx, 2, QTableWidgetItem(str(freq)))
-            self.table.setItem(row_idx, 3, QTableWidgetItem(str(sig)))
-            self.table.setItem(row_idx, 4, QTableWidgetItem(str(ch)))
-
         # Let other tabs update summaries / per-room info
         self.main_window.results_tab.refresh_from_state()
         self.main_window.summary_tab.refresh_from_state()
@@ -3,12 +3,12 @@
 from __future__ import annotations
 
 from collections import Counter
-from typing import Dict, Any, List, Optional
+from typing import Dict, Any, List, Optional, Tuple
 
 from PySide6.QtCore import Qt
 from PySide6.QtWidgets import QWidget, QVBoxLayout, QLabel, QTextEdit
 
-from pybackend.rust_bridge import compute_best_channel, get_connected_bssid
+from pybackend.rust_bridge import band_of, compute_best_channel, get_connected_bssid
 
 
 class SummaryTab(QWidget):
@@ -41,9 +41,10 @@ class SummaryTab(QWidget):
 
     def _find_my_channel(
         self, all_scan_data: List[Dict[str, Any]], connected_bssid: Optional[str]
-    ) -> Optional[int]:
+    ) -> Optional[Tuple[Optional[str], int]]:
         """
-        Look up the channel of the currently connected BSSID in the scan data.
+        Look up the (band, channel) of the currently connected BSSID in the
+        scan data.
         """
         if not connected_bssid:
             return None
@@ -55,7 +56,7 @@ class SummaryTab(QWidget):
             if not bssid or ch is None:
                 continue
             if str(bssid).lower() == target:
-                return int(ch)
+                return band_of(ap.get("freq_mhz")), int(ch)
 
         return None
 
@@ -118,12 +119,13 @@ class SummaryTab(QWidget):
             if best_global is None:
                 lines.append(f"  Recommended channel: ERROR ({best_err})")
             else:
-                if my_ch is not None and int(my_ch) == int(best_global):
+                best_band, best_ch = best_global
+                if my_ch == (best_band, best_ch):
                     lines.append(
                         "  Recommended channel: **On the best channel already!**"
                     )
                 else:
-                    lines.append(f"  Recommended channel: {best_global}")
+                    lines.append(f"  Recommended channel: {best_ch} ({best_band})")
 
             lines.append("")
 
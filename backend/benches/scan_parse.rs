@@ -0,0 +1,73 @@
+// benches/scan_parse.rs
+//
+// GET_SCAN parsing on the 300-BSS dump in fixtures/scan_dump_synthetic.bin
+// (src/scandump.rs format).
+//
+// That dump is synthesized by examples/record_scan_dump.rs, not captured off
+// the air: it mixes bands, security modes, hidden and non-UTF-8 SSIDs and
+// optional elements the way a dense block of flats would, but no kernel
+// wrote it. To benchmark a real one, record it and point SCAN_DUMP at it:
+//
+//   iw dev wlan0 scan >/dev/null
+//   cargo run --release --example record_scan_dump -- /tmp/dump.bin wlan0
+//   SCAN_DUMP=/tmp/dump.bin cargo bench --bench scan_parse
+//
+//   before:   decode every reply through neli's attribute tree, then build
+//             an owned String SSID per row (the old path)
+//   in_place: walk the reply payload directly, interned SSIDs (current)
+//
+// Run with: cargo bench --bench scan_parse
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wifi_backend::bench_api::{decode, decode_dump, ie_list, parse_bss, parse_scan_payload, Attr};
+
+fn load_dump() -> Vec<Vec<u8>> {
+    match std::env::var_os("SCAN_DUMP") {
+        Some(path) => decode_dump(&std::fs::read(path).unwrap()).unwrap(),
+        None => decode_dump(include_bytes!("../fixtures/scan_dump_synthetic.bin")).unwrap(),
+    }
+}
+
+// The beacon / probe response elements of every BSS in the dump.
+fn dump_ies(payloads: &[Vec<u8>]) -> Vec<std::sync::Arc<[u8]>> {
+    payloads.iter().filter_map(|p| parse_scan_payload(p)?.ies).collect()
+}
+
+fn before(payloads: &[Vec<u8>]) -> usize {
+    let mut n = 0;
+    for p in payloads {
+        let genl = decode(p).unwrap();
+        let handle = genl.get_attr_handle();
+        if let Some(bss) = handle.get_attribute(Attr::AttrBss) {
+            let row = parse_bss(bss.nla_payload.as_ref());
+            let ssid: Option<String> = row.ssid.as_deref().map(str::to_owned);
+            n += ssid.map_or(0, |s| s.len()) + row.channel.unwrap_or(0) as usize;
+        }
+    }
+    n
+}
+
+fn in_place(payloads: &[Vec<u8>]) -> usize {
+    let mut n = 0;
+    for p in payloads {
+        if let Some(row) = parse_scan_payload(p) {
+            n += row.ssid.map_or(0, |s| s.len()) + row.channel.unwrap_or(0) as usize;
+        }
+    }
+    n
+}
+
+fn bench_scan_parse(c: &mut Criterion) {
+    let payloads = load_dump();
+    let ies = dump_ies(&payloads);
+
+    let mut g = c.benchmark_group(format!("scan_parse_{}_bss", payloads.len()));
+    g.bench_function("before", |b| b.iter(|| before(black_box(&payloads))));
+    g.bench_function("in_place", |b| b.iter(|| in_place(black_box(&payloads))));
+    g.finish();
+
+    c.bench_function("ie_list", |b| b.iter(|| ies.iter().map(|ies| ie_list(black_box(ies)).len()).sum::<usize>()));
+}
+
+criterion_group!(benches, bench_scan_parse);
+criterion_main!(benches);
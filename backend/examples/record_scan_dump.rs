@@ -0,0 +1,373 @@
+// examples/record_scan_dump.rs
+//
+// Write a GET_SCAN dump file (src/scandump.rs) for benches/scan_parse.rs.
+//
+//   cargo run --release --example record_scan_dump -- OUT [IFACE]
+//       the kernel's BSS table for IFACE (default: the first Wi-Fi
+//       interface), as the kernel sends it; run `iw dev IFACE scan` first
+//       for a fresh one
+//   cargo run --release --example record_scan_dump -- --synthetic OUT
+//       fixtures/scan_dump_synthetic.bin: 300 made-up BSSs, not a capture
+//
+// The synthetic dump stands in until a real recording is checked in. It
+// mixes what a dense neighbourhood has and the old uniform bench didn't:
+// all three bands, hidden and non-UTF-8 SSIDs, open / WPA / WPA2 / WPA3 /
+// OWE / enterprise, beacons with and without HT / VHT / HE / EHT, WPS,
+// vendor, RNR, Multiple BSSID, mesh and Interworking elements, and the
+// attribute sets different drivers send.
+
+use std::ffi::CString;
+use wifi_backend::bench_api::{encode_dump, record_dump};
+
+const N_BSS: usize = 300;
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (out, payloads) = match args.as_slice() {
+        [flag, out] if flag == "--synthetic" => (out, synthetic_dump()),
+        [out] => (out, record_dump(None)?),
+        [out, iface] => {
+            let name = CString::new(iface.as_str())?;
+            // SAFETY: a NUL-terminated interface name.
+            let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+            if ifindex == 0 {
+                anyhow::bail!("no interface {iface}");
+            }
+            (out, record_dump(Some(ifindex))?)
+        }
+        _ => anyhow::bail!("usage: record_scan_dump [--synthetic] OUT [IFACE]"),
+    };
+    std::fs::write(out, encode_dump(&payloads))?;
+    println!("{}: {} BSSs", out, payloads.len());
+    Ok(())
+}
+
+// xorshift64*, so the fixture comes out the same every time.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn bytes(&mut self, n: usize) -> Vec<u8> {
+        (0..n).map(|_| self.next() as u8).collect()
+    }
+}
+
+fn push_attr(out: &mut Vec<u8>, ty: u16, payload: &[u8]) {
+    let len = 4 + payload.len();
+    out.extend_from_slice(&(len as u16).to_ne_bytes());
+    out.extend_from_slice(&ty.to_ne_bytes());
+    out.extend_from_slice(payload);
+    while !out.len().is_multiple_of(4) {
+        out.push(0);
+    }
+}
+
+fn push_ie(out: &mut Vec<u8>, id: u8, data: &[u8]) {
+    out.push(id);
+    out.push(data.len() as u8);
+    out.extend_from_slice(data);
+}
+
+fn rsn(ciphers: &[u8], akms: &[u8], caps: u16) -> Vec<u8> {
+    let mut v = vec![1, 0, 0x00, 0x0f, 0xac, ciphers[0]];
+    for list in [ciphers, akms] {
+        v.extend_from_slice(&(list.len() as u16).to_le_bytes());
+        for &s in list {
+            v.extend_from_slice(&[0x00, 0x0f, 0xac, s]);
+        }
+    }
+    v.extend_from_slice(&caps.to_le_bytes());
+    v
+}
+
+fn ssid(rng: &mut Rng, i: usize) -> Vec<u8> {
+    const NAMES: [&str; 12] = [
+        "FRITZ!Box 7590 XY", "Vodafone-8A2C", "NETGEAR42", "eduroam", "TP-Link_5G_1F3A", "xfinitywifi",
+        "DIRECT-7b-HP M281 LaserJet", "Telekom_FON", "linksys", "HomeMesh", "Guest", "iPhone von Kim",
+    ];
+    match rng.below(20) {
+        // Hidden: empty, or the length kept and zeroed.
+        0 => Vec::new(),
+        1 => vec![0; 1 + rng.below(12)],
+        // GBK / Latin-1 names that aren't UTF-8.
+        2 => vec![0xc4, 0xe3, 0xba, 0xc3, b'-', b'0' + (i % 10) as u8],
+        3 => b"Caf\xe9 Wi-Fi".to_vec(),
+        4 => vec![b'x'; 32],
+        5..=9 => format!("{}-{i}", NAMES[rng.below(NAMES.len())]).into_bytes(),
+        _ => NAMES[rng.below(NAMES.len())].as_bytes().to_vec(),
+    }
+}
+
+// 2.4 GHz (channels 1-13), 5 GHz (UNII-1 to -3, DFS included) or 6 GHz.
+fn channel(rng: &mut Rng) -> (u32, u8, u8) {
+    const CH5: [u8; 25] = [
+        36, 40, 44, 48, 52, 56, 60, 64, 100, 104, 108, 112, 116, 120, 124, 128, 132, 136, 140, 144, 149, 153, 157,
+        161, 165,
+    ];
+    match rng.below(10) {
+        0..=3 => {
+            let ch = [1, 6, 11, 1, 6, 11, 2, 3, 4, 5, 7, 8, 9, 10, 12, 13][rng.below(16)];
+            (2407 + 5 * ch as u32, ch, 1)
+        }
+        4..=8 => {
+            let ch = CH5[rng.below(CH5.len())];
+            (5000 + 5 * ch as u32, ch, 2)
+        }
+        _ => {
+            let ch = 1 + 4 * rng.below(59) as u8;
+            (5950 + 5 * ch as u32, ch, 4)
+        }
+    }
+}
+
+fn beacon_ies(rng: &mut Rng, i: usize, ch: u8, band: u8) -> (Vec<u8>, u16) {
+    let mut ies = Vec::new();
+    let mut capability = 0x0001u16;
+    let mesh = band != 4 && rng.chance(3);
+    // Mesh points beacon a wildcard SSID and name the mesh in Mesh ID.
+    push_ie(&mut ies, 0, &if mesh { Vec::new() } else { ssid(rng, i) });
+    match (band, rng.below(3)) {
+        // 802.11b only, or b/g.
+        (1, 0) => push_ie(&mut ies, 1, &[0x82, 0x84, 0x8b, 0x96]),
+        (1, _) => {
+            push_ie(&mut ies, 1, &[0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24]);
+            push_ie(&mut ies, 50, &[0x30, 0x48, 0x60, 0x6c]);
+        }
+        _ => push_ie(&mut ies, 1, &[0x8c, 0x12, 0x98, 0x24, 0xb0, 0x48, 0x60, 0x6c]),
+    }
+    if band == 1 {
+        push_ie(&mut ies, 3, &[ch]);
+    }
+    push_ie(&mut ies, 5, &[rng.below(3) as u8, 1 + rng.below(3) as u8, 0, 0]);
+    if rng.chance(70) {
+        let country: &[u8] = match rng.below(4) {
+            0 => b"US \x01\x0b\x1e\x24\x04\x17\x95\x05\x1e",
+            1 => b"DE \x01\x0d\x14\x24\x04\x17\x34\x04\x17\x64\x0b\x1e",
+            // An operating triplet and the triplets of its class.
+            2 => b"GB \x01\x0d\x14\xc9\x83\x00\x01\x18\x17",
+            _ => b"JPX",
+        };
+        push_ie(&mut ies, 7, country);
+    }
+    if rng.chance(40) {
+        push_ie(&mut ies, 11, &[rng.below(40) as u8, 0, rng.below(256) as u8, 0, 0]);
+    }
+    if band != 4 {
+        push_ie(&mut ies, 42, &[0]);
+    }
+
+    // Security: the Privacy bit plus RSN / WPA elements. 6 GHz allows
+    // only SAE, OWE and enterprise.
+    const WPA1: [u8; 22] = [
+        0x00, 0x50, 0xf2, 1, 1, 0, 0x00, 0x50, 0xf2, 2, 1, 0, 0x00, 0x50, 0xf2, 2, 1, 0, 0x00, 0x50, 0xf2, 2,
+    ];
+    let security = if band == 4 { [6, 7, 8][rng.below(3)] } else { rng.below(12) };
+    if security >= 2 {
+        capability |= 0x0010;
+    }
+    match security {
+        // Open, or WEP.
+        0..=2 => {}
+        3 => push_ie(&mut ies, 221, &WPA1),
+        4 => {
+            push_ie(&mut ies, 48, &rsn(&[4, 2], &[2], 0x000c));
+            push_ie(&mut ies, 221, &WPA1);
+        }
+        // WPA2/WPA3 transition, WPA3 with FT, enterprise, OWE.
+        5 => push_ie(&mut ies, 48, &rsn(&[4], &[2, 8], 0x008c)),
+        6 => push_ie(&mut ies, 48, &rsn(&[4], &[8, 24], 0x00cc)),
+        7 => push_ie(&mut ies, 48, &rsn(&[4], &[1, 3, 5], 0x0028)),
+        8 => push_ie(&mut ies, 48, &rsn(&[4], &[18], 0x00c0)),
+        _ => push_ie(&mut ies, 48, &rsn(&[4], &[2], 0x000c)),
+    }
+
+    // HT / VHT on 2.4 and 5 GHz, less often on old 2.4 GHz APs.
+    let ht = band != 4 && (band == 2 || rng.chance(80));
+    if ht {
+        let intolerant = if band == 1 && rng.chance(10) { 0x40 } else { 0x00 };
+        let mut cap = vec![0xef, 0x09 | intolerant, 0x17, 0xff];
+        cap.extend(if rng.chance(50) { [0xff, 0, 0, 0] } else { [0xff, 0xff, 0, 0] });
+        cap.resize(26, 0);
+        push_ie(&mut ies, 45, &cap);
+        let forty = band == 2 || rng.chance(30);
+        let offset = if !forty { 0x00 } else if ch <= 7 || (band == 2 && (ch / 4) % 2 == 1) { 0x05 } else { 0x07 };
+        let mut op = vec![ch, offset];
+        op.resize(22, 0);
+        push_ie(&mut ies, 61, &op);
+    }
+    let ext_caps_len = [3, 8, 10, 11][rng.below(4)];
+    let mut ext = rng.bytes(ext_caps_len);
+    ext[0] &= 0xfd;
+    push_ie(&mut ies, 127, &ext);
+    if band == 2 && rng.chance(85) {
+        push_ie(&mut ies, 191, &[0xb2, 0x79, 0x91, 0x33, 0xfa, 0xff, 0, 0, 0xfa, 0xff, 0, 0]);
+        let centre = match ch {
+            36..=48 => 42,
+            52..=64 => 58,
+            100..=112 => 106,
+            116..=128 => 122,
+            132..=144 => 138,
+            _ => 155,
+        };
+        let (width, ccfs1) = if rng.chance(20) && ch < 132 { (1, if ch < 100 { 50 } else { 114 }) } else { (1, 0) };
+        push_ie(&mut ies, 192, &[width, centre, ccfs1, 0xfc, 0xff]);
+    }
+    // HE (Wi-Fi 6) and EHT (Wi-Fi 7); 6 GHz carries its channel in HE
+    // operation.
+    if band == 4 || rng.chance(45) {
+        let mut he_cap = vec![35];
+        he_cap.extend(rng.bytes(17));
+        he_cap.extend([0xfa, 0xff, 0xfa, 0xff]);
+        push_ie(&mut ies, 255, &he_cap);
+        let mut he_op = vec![36, 0xf4, 0x01, if band == 4 { 0x02 } else { 0x00 }, 0x2a, 0xfc, 0xff];
+        if band == 4 {
+            let ccfs0 = (ch - 1) / 16 * 16 + 7;
+            he_op.extend([ch, 2, ccfs0, 0, 6]);
+        }
+        push_ie(&mut ies, 255, &he_op);
+        if band != 1 {
+            push_ie(&mut ies, 255, &[59, 0x01, 0x00]);
+        }
+        if rng.chance(25) {
+            let mut eht = vec![108];
+            eht.extend(rng.bytes(14));
+            push_ie(&mut ies, 255, &eht);
+            let mld = [0x02, 0x4d, 0x4c, (i >> 8) as u8 & 0x3f, i as u8 & 0xf0, 0];
+            let basic_ml = [&[107, 0xb0, 0x01, 13][..], &mld, &[0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00]].concat();
+            push_ie(&mut ies, 255, &basic_ml);
+        }
+    }
+    // A 2.4 / 5 GHz radio of a tri-band AP listing its 6 GHz BSS.
+    if band != 4 && rng.chance(15) {
+        let mut rnr = vec![0x00, 13, 131, 1 + 4 * rng.below(59) as u8, 0];
+        rnr.extend(rng.bytes(6));
+        rnr.extend(rng.bytes(4));
+        rnr.extend([0x42, 0xee]);
+        push_ie(&mut ies, 201, &rnr);
+    }
+    if rng.chance(5) {
+        // Two nontransmitted profiles, BSSID-Index 1 and 2.
+        let profile = |idx: u8| {
+            let ssid = [&[0x00, 0x05][..], b"guest"].concat();
+            [&[0x00, 0x10, 0x00, 0x02, 0x11, 0x04][..], &ssid, &[0x55, 0x03, idx, 0x01, 0x01]].concat()
+        };
+        let (p1, p2) = (profile(1), profile(2));
+        let mut mbssid = vec![2, 0, p1.len() as u8];
+        mbssid.extend(&p1);
+        mbssid.extend([0, p2.len() as u8]);
+        mbssid.extend(&p2);
+        push_ie(&mut ies, 71, &mbssid);
+    }
+    if mesh {
+        push_ie(&mut ies, 114, b"meshnet");
+        push_ie(&mut ies, 113, &[1, 1, 0, 0, 1, 2 << 1 | 1, 1]);
+    }
+    if rng.chance(8) {
+        push_ie(&mut ies, 107, &[0x13, 2, 1, 0x02, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        push_ie(&mut ies, 221, &[0x50, 0x6f, 0x9a, 0x10, 0x20]);
+    }
+
+    // Vendor elements: WMM nearly always, then WPS, P2P, Multi-AP and
+    // vendor OUIs of various lengths.
+    if rng.chance(90) {
+        push_ie(&mut ies, 221, &[
+            0x00, 0x50, 0xf2, 0x02, 0x01, 0x01, 0x80, 0x00, 0x03, 0xa4, 0x00, 0x00, 0x27, 0xa4, 0x00, 0x00, 0x42,
+            0x43, 0x5e, 0x00, 0x62, 0x32, 0x2f, 0x00,
+        ]);
+    }
+    if rng.chance(30) {
+        let mut wps = vec![0x00, 0x50, 0xf2, 0x04, 0x10, 0x4a, 0x00, 0x01, 0x10, 0x10, 0x44, 0x00, 0x01, 0x02];
+        if rng.chance(30) {
+            wps.extend([0x10, 0x57, 0x00, 0x01, 0x01]);
+        }
+        if rng.chance(50) {
+            wps.extend([0x10, 0x3b, 0x00, 0x01, 0x03, 0x10, 0x47, 0x00, 0x10]);
+            wps.extend(rng.bytes(16));
+            wps.extend([0x10, 0x21, 0x00, 0x0c]);
+            wps.extend(b"Manufacturer");
+            wps.extend([0x10, 0x23, 0x00, 0x0d]);
+            wps.extend(b"Router Model1");
+            wps.extend([0x10, 0x08, 0x00, 0x02, 0x20, 0x08]);
+        }
+        push_ie(&mut ies, 221, &wps);
+    }
+    if rng.chance(4) {
+        push_ie(&mut ies, 221, &[0x50, 0x6f, 0x9a, 0x09, 0x02, 0x02, 0x00, 0x25, 0x00, 0x0d, 0x1b, 0x00]);
+    }
+    if rng.chance(6) {
+        push_ie(&mut ies, 221, &[0x50, 0x6f, 0x9a, 0x1b, 0x06, 0x01, 0x20]);
+    }
+    for _ in 0..rng.below(4) {
+        // Broadcom, Ralink, Atheros, Qualcomm, Apple, Epigram.
+        const OUIS: [[u8; 3]; 6] = [
+            [0x00, 0x10, 0x18], [0x00, 0x0c, 0x43], [0x00, 0x03, 0x7f], [0x8c, 0xfd, 0xf0], [0x00, 0x17, 0xf2],
+            [0x00, 0x90, 0x4c],
+        ];
+        let mut v = OUIS[rng.below(OUIS.len())].to_vec();
+        let len = rng.below(40);
+        v.extend(rng.bytes(len));
+        push_ie(&mut ies, 221, &v);
+    }
+    (ies, capability)
+}
+
+// One GET_SCAN reply payload per BSS: genl header + IFINDEX + WDEV +
+// GENERATION + nested BSS, with the attributes varying like drivers do.
+fn synthetic_dump() -> Vec<Vec<u8>> {
+    let mut rng = Rng(0x5eed_cafe_f00d_d00d);
+    (0..N_BSS)
+        .map(|i| {
+            let (freq, ch, band) = channel(&mut rng);
+            let (ies, capability) = beacon_ies(&mut rng, i, ch, band);
+
+            let mut bss = Vec::new();
+            let mut bssid = rng.bytes(6);
+            bssid[0] &= 0xfe;
+            push_attr(&mut bss, 1, &bssid);
+            push_attr(&mut bss, 2, &freq.to_ne_bytes());
+            push_attr(&mut bss, 3, &rng.next().to_ne_bytes());
+            push_attr(&mut bss, 4, &(if rng.chance(90) { 100u16 } else { 200 }).to_ne_bytes());
+            push_attr(&mut bss, 5, &(capability | 0x0400).to_ne_bytes());
+            push_attr(&mut bss, 6, &ies);
+            // Some drivers add the last beacon's elements next to the
+            // probe response's.
+            if rng.chance(60) {
+                push_attr(&mut bss, 11, &ies);
+            }
+            if rng.chance(95) {
+                push_attr(&mut bss, 7, &(-3000 - rng.below(6500) as i32).to_ne_bytes());
+            } else {
+                push_attr(&mut bss, 8, &[rng.below(100) as u8]);
+            }
+            push_attr(&mut bss, 10, &(rng.below(30_000) as u32).to_ne_bytes());
+            if rng.chance(50) {
+                push_attr(&mut bss, 15, &(rng.next() >> 8).to_ne_bytes());
+            }
+            if i == 17 {
+                push_attr(&mut bss, 9, &1u32.to_ne_bytes());
+            }
+            push_attr(&mut bss, 13, &[0, 0, 0, 0]);
+
+            let mut msg = vec![34, 1, 0, 0];
+            push_attr(&mut msg, 46, &(4711u32).to_ne_bytes());
+            push_attr(&mut msg, 3, &3u32.to_ne_bytes());
+            push_attr(&mut msg, 153, &1u64.to_ne_bytes());
+            push_attr(&mut msg, 47 | 0x8000, &bss);
+            msg
+        })
+        .collect()
+}
@@ -0,0 +1,87 @@
+#!/usr/bin/env python3
+# backend/scripts/build_oui_db.py
+"""
+Build the binary OUI database wifi_backend memory-maps (see src/oui.rs for
+the layout) from the IEEE MA-L registry CSV.
+
+    python3 build_oui_db.py                    # download + write default path
+    python3 build_oui_db.py oui.csv out.bin    # from a local copy
+
+The output is written to a temp file and renamed into place, so a running
+process that has the old file mapped keeps a consistent view.
+"""
+
+from __future__ import annotations
+
+import csv
+import io
+import os
+import struct
+import sys
+import tempfile
+import urllib.request
+from pathlib import Path
+
+IEEE_CSV_URL = "https://standards-oui.ieee.org/oui/oui.csv"
+DEFAULT_OUT = Path.home() / ".local/share/wifi_backend/oui.bin"
+
+MAGIC = b"OUI1"
+ENTRY = struct.Struct("<3sBI")
+
+
+def read_csv(text: str) -> dict[bytes, str]:
+    out: dict[bytes, str] = {}
+    for row in csv.DictReader(io.StringIO(text)):
+        assignment = row.get("Assignment", "").strip()
+        name = " ".join(row.get("Organization Name", "").split())
+        if len(assignment) != 6 or not name:
+            continue
+        try:
+            oui = bytes.fromhex(assignment)
+        except ValueError:
+            continue
+        # Names are length-prefixed with one byte.
+        out[oui] = name.encode("utf-8")[:255].decode("utf-8", "ignore")
+    return out
+
+
+def build(entries: dict[bytes, str]) -> bytes:
+    ouis = sorted(entries)
+    names = bytearray()
+    table = bytearray()
+    names_start = 8 + len(ouis) * ENTRY.size
+
+    for oui in ouis:
+        name = entries[oui].encode("utf-8")
+        table += ENTRY.pack(oui, len(name), names_start + len(names))
+        names += name
+
+    return MAGIC + struct.pack("<I", len(ouis)) + bytes(table) + bytes(names)
+
+
+def main(argv: list[str]) -> int:
+    if len(argv) > 1:
+        text = Path(argv[1]).read_text(encoding="utf-8", errors="replace")
+    else:
+        with urllib.request.urlopen(IEEE_CSV_URL, timeout=60) as resp:
+            text = resp.read().decode("utf-8", errors="replace")
+    out = Path(argv[2]) if len(argv) > 2 else DEFAULT_OUT
+
+    entries = read_csv(text)
+    if not entries:
+        print("no OUI entries found", file=sys.stderr)
+        return 1
+    blob = build(entries)
+
+    out.parent.mkdir(parents=True, exist_ok=True)
+    fd, tmp = tempfile.mkstemp(dir=out.parent, prefix=out.name)
+    with os.fdopen(fd, "wb") as f:
+        f.write(blob)
+    os.replace(tmp, out)
+
+    print(f"{out}: {len(entries)} OUIs, {len(blob) // 1024} KiB")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main(sys.argv))
@@ -0,0 +1,340 @@
+// src/airtime.rs
+//
+// Airtime fairness per own AP radio: how the time on air is split across
+// its clients, flagging clients that monopolize the radio.
+//
+// Two sources, merged per radio:
+//   - two GET_STATION dumps `window` apart. Drivers that account
+//     TX/RX duration give airtime directly; otherwise it's estimated from
+//     the byte counters and the last bitrate.
+//   - a monitor-mode capture (pcap.rs). Each frame costs its preamble plus
+//     its bits at the radiotap rate; management and control frames are
+//     overhead of the radio, not of a client.
+// Duration-accounted station dumps win over a capture, a capture over
+// bitrate estimates.
+
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::lib_rust::format_mac;
+use crate::pcap::{Pcap, Phy};
+use crate::progress::Progress;
+use crate::stamp::Stamp;
+use crate::stations::{self, ApRadio, Station};
+
+/// A client with at least this share of its radio's client airtime, while
+/// others are active too, is monopolizing it.
+pub const HOG_SHARE: f32 = 0.5;
+/// A client below this effective rate holding a large share (>= SLOW_SHARE)
+/// of airtime is slowing everyone else down.
+pub const SLOW_MBPS: f32 = 12.0;
+pub const SLOW_SHARE: f32 = 0.25;
+
+#[derive(Debug, Clone)]
+pub struct ClientAirtime {
+    pub mac: [u8; 6],
+    pub airtime_us: u64,
+    pub bytes: u64,
+    /// Share of the radio's client airtime.
+    pub share: f32,
+    /// Bytes moved per unit of airtime, in Mbit/s.
+    pub rate_mbps: Option<f32>,
+    pub hog: bool,
+    pub slow: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApAirtime {
+    pub bssid: [u8; 6],
+    pub freq_mhz: Option<u32>,
+    /// "durations", "bitrate" or "capture".
+    pub source: &'static str,
+    pub window_s: f64,
+    /// End of the window.
+    pub at: Stamp,
+    /// Airtime of all clients.
+    pub airtime_us: u64,
+    /// Management / control frames (captures only).
+    pub overhead_us: u64,
+    /// Fraction of the window the radio was busy with the above.
+    pub busy: f32,
+    /// Busiest first.
+    pub clients: Vec<ClientAirtime>,
+    pub alerts: Vec<String>,
+}
+
+impl ApAirtime {
+    fn new(bssid: [u8; 6], freq_mhz: Option<u32>, source: &'static str, window_s: f64) -> Self {
+        ApAirtime {
+            bssid,
+            freq_mhz,
+            source,
+            window_s,
+            at: Stamp::now(),
+            airtime_us: 0,
+            overhead_us: 0,
+            busy: 0.0,
+            clients: Vec::new(),
+            alerts: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, mac: [u8; 6], airtime_us: u64, bytes: u64) {
+        match self.clients.iter_mut().find(|c| c.mac == mac) {
+            Some(c) => {
+                c.airtime_us += airtime_us;
+                c.bytes += bytes;
+            }
+            None => self.clients.push(ClientAirtime {
+                mac,
+                airtime_us,
+                bytes,
+                share: 0.0,
+                rate_mbps: None,
+                hog: false,
+                slow: false,
+            }),
+        }
+    }
+
+    // Shares, flags and alerts once all airtime is in.
+    fn finish(&mut self) {
+        self.clients.retain(|c| c.airtime_us > 0);
+        self.clients.sort_by_key(|c| std::cmp::Reverse(c.airtime_us));
+        self.airtime_us = self.clients.iter().map(|c| c.airtime_us).sum();
+        if self.window_s > 0.0 {
+            let busy = (self.airtime_us + self.overhead_us) as f64 / (self.window_s * 1e6);
+            self.busy = busy.min(1.0) as f32;
+        }
+
+        let active = self.clients.len();
+        for c in &mut self.clients {
+            c.share = c.airtime_us as f32 / self.airtime_us as f32;
+            c.rate_mbps = (c.bytes > 0).then(|| c.bytes as f32 * 8.0 / c.airtime_us as f32);
+            c.hog = active >= 2 && c.share >= HOG_SHARE;
+            c.slow = active >= 2
+                && c.share >= SLOW_SHARE
+                && c.rate_mbps.is_some_and(|r| r < SLOW_MBPS);
+            if c.hog {
+                self.alerts.push(format!(
+                    "{} uses {:.0}% of airtime",
+                    format_mac(&c.mac),
+                    c.share * 100.0
+                ));
+            } else if c.slow {
+                self.alerts.push(format!(
+                    "{} uses {:.0}% of airtime at {:.1} Mbit/s",
+                    format_mac(&c.mac),
+                    c.share * 100.0,
+                    c.rate_mbps.unwrap_or(0.0)
+                ));
+            }
+        }
+    }
+}
+
+// Counter growth between two dumps; None if it went backwards (the client
+// reassociated) or either side is missing.
+fn delta(before: Option<u64>, after: Option<u64>) -> Option<u64> {
+    after?.checked_sub(before?)
+}
+
+/// Airtime per radio from two station dumps `window_s` apart.
+pub fn from_stations(
+    radios: &[ApRadio],
+    before: &[Station],
+    after: &[Station],
+    window_s: f64,
+) -> Vec<ApAirtime> {
+    let mut aps: BTreeMap<[u8; 6], ApAirtime> = BTreeMap::new();
+    for st in after {
+        let Some(prev) = before.iter().find(|p| p.mac == st.mac && p.bssid == st.bssid) else {
+            continue;
+        };
+        let freq = st
+            .freq_mhz
+            .or_else(|| radios.iter().find(|r| r.bssid == st.bssid).map(|r| r.freq_mhz));
+        let ap = aps
+            .entry(st.bssid)
+            .or_insert_with(|| ApAirtime::new(st.bssid, freq, "bitrate", window_s));
+
+        let tx_bytes = delta(prev.tx_bytes, st.tx_bytes).unwrap_or(0);
+        let rx_bytes = delta(prev.rx_bytes, st.rx_bytes).unwrap_or(0);
+        let tx_dur = delta(prev.tx_duration_us, st.tx_duration_us);
+        let rx_dur = delta(prev.rx_duration_us, st.rx_duration_us);
+
+        let airtime = if tx_dur.is_some() || rx_dur.is_some() {
+            ap.source = "durations";
+            tx_dur.unwrap_or(0) + rx_dur.unwrap_or(0)
+        } else {
+            // bytes * 8 bits at kbit/s is bytes * 8000 / kbps microseconds.
+            let est = |bytes: u64, kbps: Option<u32>| match kbps {
+                Some(k) if k > 0 => bytes * 8000 / k as u64,
+                _ => 0,
+            };
+            est(tx_bytes, st.tx_bitrate_kbps) + est(rx_bytes, st.rx_bitrate_kbps)
+        };
+        ap.add(st.mac, airtime, tx_bytes + rx_bytes);
+    }
+
+    let mut out: Vec<ApAirtime> = aps.into_values().collect();
+    out.iter_mut().for_each(ApAirtime::finish);
+    out
+}
+
+/// Sample this machine's AP interfaces twice, `window` apart, reporting
+/// progress about once a second while waiting.
+pub fn sample_local(window: Duration, progress: &mut Progress) -> Result<Vec<ApAirtime>> {
+    let (_, before) = stations::local_stations()?;
+    let start = Instant::now();
+    loop {
+        let elapsed = start.elapsed();
+        let pct = elapsed.as_secs_f32() * 100.0 / window.as_secs_f32().max(f32::EPSILON);
+        progress.report(pct, "sample", &format!("{:.0} of {:.0} s", elapsed.as_secs_f32(), window.as_secs_f32()))?;
+        if elapsed >= window {
+            break;
+        }
+        std::thread::sleep((window - elapsed).min(Duration::from_secs(1)));
+    }
+    let (radios, after) = stations::local_stations()?;
+    Ok(from_stations(&radios, &before, &after, window.as_secs_f64()))
+}
+
+// Time on air of one frame, in microseconds.
+fn frame_airtime(len: u32, rate_kbps: u32, phy: Option<Phy>) -> u64 {
+    let preamble = match phy {
+        Some(Phy::Dsss { short_preamble: true }) => 96,
+        Some(Phy::Dsss { short_preamble: false }) => 192,
+        Some(Phy::HtVht) => 36,
+        Some(Phy::Ofdm) | None => 20,
+    };
+    preamble + len as u64 * 8000 / rate_kbps.max(1) as u64
+}
+
+// The report for `bssid`, created on first sight; None if it isn't ours
+// (or is a broadcast address from a wildcard probe).
+fn bss_entry<'a>(
+    aps: &'a mut BTreeMap<[u8; 6], ApAirtime>,
+    own: &[[u8; 6]],
+    bssid: [u8; 6],
+    freq: Option<u32>,
+) -> Option<&'a mut ApAirtime> {
+    let group = bssid[0] & 1 != 0 || bssid == [0; 6];
+    if group || (!own.is_empty() && !own.contains(&bssid)) {
+        return None;
+    }
+    let ap = aps
+        .entry(bssid)
+        .or_insert_with(|| ApAirtime::new(bssid, freq, "capture", 0.0));
+    ap.freq_mhz = ap.freq_mhz.or(freq);
+    Some(ap)
+}
+
+fn addr(data: &[u8], off: usize) -> Option<[u8; 6]> {
+    data.get(off..off + 6)?.try_into().ok()
+}
+
+/// Airtime per radio from a monitor-mode capture. `own` limits the report
+/// to those BSSIDs; empty keeps every BSS in the capture.
+pub fn from_capture(path: &Path, own: &[[u8; 6]]) -> Result<Vec<ApAirtime>> {
+    let pcap = Pcap::open(path)?;
+    let mut aps: BTreeMap<[u8; 6], ApAirtime> = BTreeMap::new();
+    let (mut first, mut last) = (u64::MAX, 0u64);
+    let mut rated = 0usize;
+    // Control frames are charged to the BSS one of their addresses belongs
+    // to, once the BSSs are known. ACK and CTS carry only the receiver.
+    let mut control: Vec<([u8; 6], Option<[u8; 6]>, u64)> = Vec::new();
+
+    for f in pcap.frames() {
+        first = first.min(f.ts_us);
+        last = last.max(f.ts_us);
+        let Some(rate) = f.rate_kbps else {
+            continue;
+        };
+        rated += 1;
+        let Some(&fc0) = f.data.first() else {
+            continue;
+        };
+        let ds = f.data.get(1).copied().unwrap_or(0) & 0x03;
+        let airtime = frame_airtime(f.len, rate, f.phy);
+        let freq = f.freq_mhz.map(u32::from);
+        let (Some(a1), a2, a3) = (addr(f.data, 4), addr(f.data, 10), addr(f.data, 16)) else {
+            continue;
+        };
+
+        match (fc0 >> 2) & 0x03 {
+            // Management: addr3 is the BSSID.
+            0 => {
+                if let Some(ap) = a3.and_then(|b| bss_entry(&mut aps, own, b, freq)) {
+                    ap.overhead_us += airtime;
+                }
+            }
+            // Control.
+            1 => control.push((a1, a2, airtime)),
+            // Data.
+            2 => {
+                let (bssid, client, group) = match ds {
+                    1 => (a1, a2, false),
+                    2 => (a2.unwrap_or_default(), Some(a1), a1[0] & 1 != 0),
+                    0 => (a3.unwrap_or_default(), a2, false),
+                    // WDS / mesh backhaul: the peer node is the "client".
+                    _ => (a1, a2, false),
+                };
+                let body = (f.len as u64).saturating_sub(24);
+                if let Some(ap) = bss_entry(&mut aps, own, bssid, freq) {
+                    match client {
+                        Some(c) if !group && c != bssid => ap.add(c, airtime, body),
+                        _ => ap.overhead_us += airtime,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if first != u64::MAX && rated == 0 {
+        bail!("{}: no radiotap rates in capture, can't compute airtime", path.display());
+    }
+    for (ra, ta, airtime) in control {
+        let bss = [Some(ra), ta].into_iter().flatten().find_map(|a| {
+            if aps.contains_key(&a) {
+                return Some(a);
+            }
+            aps.values().find(|ap| ap.clients.iter().any(|c| c.mac == a)).map(|ap| ap.bssid)
+        });
+        if let Some(ap) = bss.and_then(|b| aps.get_mut(&b)) {
+            ap.overhead_us += airtime;
+        }
+    }
+
+    let window_s = last.saturating_sub(first) as f64 / 1e6;
+    let at = Stamp::from_wall(last as f64 / 1e6);
+    let mut out: Vec<ApAirtime> = aps.into_values().collect();
+    for ap in &mut out {
+        ap.window_s = window_s;
+        ap.at = at;
+        ap.finish();
+    }
+    Ok(out)
+}
+
+/// One report per radio: duration-accounted station dumps first, then the
+/// capture, then bitrate estimates.
+pub fn merge(stations: Vec<ApAirtime>, capture: Vec<ApAirtime>) -> Vec<ApAirtime> {
+    let mut out: Vec<ApAirtime> = Vec::new();
+    for ap in capture {
+        match stations.iter().find(|s| s.bssid == ap.bssid) {
+            Some(s) if s.source == "durations" => {}
+            _ => out.push(ap),
+        }
+    }
+    for ap in stations {
+        if !out.iter().any(|c| c.bssid == ap.bssid) {
+            out.push(ap);
+        }
+    }
+    out.sort_by(|a, b| b.busy.total_cmp(&a.busy).then(a.bssid.cmp(&b.bssid)));
+    out
+}
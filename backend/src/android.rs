@@ -0,0 +1,309 @@
+// src/android.rs
+//
+// Injected provider (Backend::Android) for stock, non-rooted Android, where
+// the app sandbox reaches neither nl80211 nor wpa_supplicant's socket and
+// wificond's binder interface is for system apps only. The app reads
+// WifiManager.getScanResults() and getConnectionInfo() itself and pushes
+// them here, from Python (push_android_scan) or from its JNI glue through
+// wifi_backend_android_push(); scans, the connected BSSID and link_info()
+// then serve the latest push, so compute_best_channel and the rest work
+// unchanged. Nothing is triggered: the app calls WifiManager.startScan(),
+// which Android throttles to four scans per two minutes, and pushes when
+// SCAN_RESULTS_AVAILABLE_ACTION arrives. Surveys, the regulatory domain,
+// the radio's capabilities, stations and events have no source here.
+//
+// Push (field names as in android.net.wifi.ScanResult / WifiInfo):
+//   {
+//     "results": [{BSSID, SSID?, frequency, level?, timestamp?,
+//                  capabilities?, channelWidth?, centerFreq0?, ies?}, ...],
+//     "connection": {BSSID, rssi?, linkSpeed?, txLinkSpeedMbps?,
+//                    rxLinkSpeedMbps?} | null
+//   }
+//
+// `timestamp` is ScanResult's, microseconds since boot. `ies` is hex, the
+// elements of ScanResult.getInformationElements() (API 30) as id, length,
+// bytes; without it the width comes from channelWidth / centerFreq0 and
+// the security from the capabilities string ("[WPA2-PSK-CCMP][ESS]").
+// A connection BSSID of 02:00:00:00:00:00 (what Android reports without
+// the location permission) counts as not connected.
+//
+// Exposes:
+//   - push_json(text) -> Result<usize>, pushed() -> bool
+//   - wifi_backend_android_push(json) (C ABI, for JNI)
+//   - scan_all_bss_async(on_row), get_connected_bssid(), link(), interfaces()
+//   - AndroidBackend, the WifiBackend of all of the above
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::ffi::{c_char, CStr};
+use std::sync::Mutex;
+use tracing::debug;
+
+use crate::ies::{Operation, Security};
+use crate::lib_rust::{freq_to_channel, parse_mac, BssRow, NoTrigger, RowSink, ScanOptions};
+use crate::link::LinkInfo;
+use crate::netlink::{WifiIface, IFTYPE_STATION};
+use crate::provider::{ScanFuture, WifiBackend};
+
+struct Pushed {
+    rows: Vec<BssRow>,
+    connected: Option<LinkInfo>,
+}
+
+static PUSHED: Mutex<Option<Pushed>> = Mutex::new(None);
+
+// ScanResult.CHANNEL_WIDTH_* -> MHz (80+80 counts as 160, as in ies.rs).
+const CHANNEL_WIDTHS: [u32; 6] = [20, 40, 80, 160, 160, 320];
+const CHANNEL_WIDTH_80P80: u64 = 4;
+
+// Capability Information bits, for is_ibss() and the WEP fallback.
+const CAP_ESS: u16 = 1 << 0;
+const CAP_IBSS: u16 = 1 << 1;
+const CAP_PRIVACY: u16 = 1 << 4;
+
+fn not_pushed() -> anyhow::Error {
+    anyhow!("android backend selected but no scan pushed (push_android_scan)")
+}
+
+fn num(v: &Value, key: &str) -> Result<Option<f64>> {
+    match v.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(x) => x.as_f64().map(Some).ok_or_else(|| anyhow!("{key}: expected a number")),
+    }
+}
+
+fn text<'a>(v: &'a Value, key: &str) -> Result<Option<&'a str>> {
+    match v.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => bail!("{key}: expected a string"),
+    }
+}
+
+fn hex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        bail!("ies: odd number of hex digits");
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| anyhow!("ies: bad hex at {i}"))
+        })
+        .collect()
+}
+
+// CLOCK_BOOTTIME, the clock of ScanResult.timestamp, in microseconds.
+fn boottime_us() -> f64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid timespec to write to.
+    unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) };
+    ts.tv_sec as f64 * 1e6 + ts.tv_nsec as f64 / 1e3
+}
+
+// The security in ScanResult.capabilities, e.g. "[RSN-PSK+SAE-CCMP][ESS]".
+fn security_of(caps: &str) -> Security {
+    let has = |s: &str| caps.contains(s);
+    if has("EAP") {
+        Security::Enterprise
+    } else if has("OWE") && !has("OWE_TRANSITION") {
+        Security::Owe
+    } else if has("SAE") && has("PSK") {
+        Security::Wpa2Wpa3
+    } else if has("SAE") {
+        Security::Wpa3
+    } else if has("[WPA2-") || has("[RSN-") {
+        Security::Wpa2
+    } else if has("[WPA-") {
+        Security::Wpa
+    } else if has("WEP") {
+        Security::Wep
+    } else {
+        Security::Open
+    }
+}
+
+fn capability_of(caps: &str, security: Security) -> u16 {
+    let mut cap = if caps.contains("[IBSS]") { CAP_IBSS } else { CAP_ESS };
+    if security != Security::Open {
+        cap |= CAP_PRIVACY;
+    }
+    cap
+}
+
+// The operating channel from channelWidth / centerFreq0.
+fn operation_of(freq: u32, width: u64, center_freq: Option<u32>) -> Option<Operation> {
+    let width_mhz = *CHANNEL_WIDTHS.get(width as usize)?;
+    let primary = freq_to_channel(freq);
+    let center = center_freq.filter(|_| width_mhz > 20 && width != CHANNEL_WIDTH_80P80);
+    Some(Operation {
+        width_mhz,
+        primary,
+        secondary: center.filter(|_| width_mhz == 40).map(|c| if c > freq { 1 } else { -1 }),
+        center: center.and_then(freq_to_channel),
+    })
+}
+
+fn row(v: &Value, now_us: f64) -> Result<BssRow> {
+    let ies = text(v, "ies")?.map(hex).transpose()?;
+    let freq = num(v, "frequency")?.map(|f| f as u32);
+    let mut row = BssRow::from_parts(
+        text(v, "BSSID")?.map(parse_mac).transpose()?,
+        freq,
+        num(v, "level")?.map(|l| l as f32),
+        ies.as_deref(),
+    );
+    // Android reports a hidden network's SSID as "", which only the IEs
+    // can improve on.
+    if let Some(ssid) = text(v, "SSID")?.filter(|s| !s.is_empty() || ies.is_none()) {
+        row.set_ssid(ssid.as_bytes());
+    }
+    let seen_ms = num(v, "timestamp")?.map(|t| ((now_us - t) / 1000.0).max(0.0) as u32);
+    row = row.seen_ms_ago(seen_ms);
+    // The IEs say it better when there are any.
+    if ies.is_some() {
+        return Ok(row);
+    }
+    if let Some(caps) = text(v, "capabilities")? {
+        let security = security_of(caps);
+        row.capability = Some(capability_of(caps, security));
+        row = row.with_security(security);
+    }
+    let op = match (freq, num(v, "channelWidth")?) {
+        (Some(f), Some(w)) => operation_of(f, w as u64, num(v, "centerFreq0")?.map(|c| c as u32)),
+        _ => None,
+    };
+    Ok(match op {
+        Some(op) => row.with_operation(op),
+        None => row,
+    })
+}
+
+fn connection(v: &Value) -> Result<Option<LinkInfo>> {
+    let Some(bssid) = text(v, "BSSID")?.map(parse_mac).transpose()? else {
+        return Ok(None);
+    };
+    if bssid == [2, 0, 0, 0, 0, 0] {
+        return Ok(None);
+    }
+    let mbps = |key: &str| -> Result<Option<u32>> {
+        // WifiInfo.LINK_SPEED_UNKNOWN is -1.
+        Ok(num(v, key)?.filter(|&s| s >= 0.0).map(|s| s as u32 * 1000))
+    };
+    Ok(Some(LinkInfo {
+        bssid,
+        signal_dbm: num(v, "rssi")?.map(|r| r as f32),
+        tx_bitrate_kbps: mbps("txLinkSpeedMbps")?.or(mbps("linkSpeed")?),
+        rx_bitrate_kbps: mbps("rxLinkSpeedMbps")?,
+        ..Default::default()
+    }))
+}
+
+/// Replace the pushed scan and connection with those in `text` (format
+/// above); returns the number of BSSs.
+pub fn push_json(text: &str) -> Result<usize> {
+    let doc: Value = serde_json::from_str(text)?;
+    let now_us = boottime_us();
+    let rows = match doc.get("results") {
+        Some(Value::Array(results)) => results.iter().map(|v| row(v, now_us)).collect::<Result<Vec<_>>>()?,
+        None | Some(Value::Null) => Vec::new(),
+        Some(_) => bail!("results: expected a list"),
+    };
+    let connected = match doc.get("connection") {
+        None | Some(Value::Null) => None,
+        Some(v) => connection(v)?,
+    };
+    let n = rows.len();
+    debug!(bss = n, connected = connected.is_some(), "scan pushed");
+    *PUSHED.lock().unwrap_or_else(|e| e.into_inner()) = Some(Pushed { rows, connected });
+    Ok(n)
+}
+
+/// Whether anything was pushed yet.
+pub fn pushed() -> bool {
+    PUSHED.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// C entry point for the app's JNI glue: push_json() on a NUL-terminated
+/// UTF-8 string, e.g. from a `native` method taking a String. Returns the
+/// number of BSSs, or -1 if the push was rejected.
+///
+/// # Safety
+/// `json` must be null or point to a NUL-terminated string that stays
+/// valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn wifi_backend_android_push(json: *const c_char) -> i32 {
+    if json.is_null() {
+        return -1;
+    }
+    // SAFETY: non-null and NUL-terminated per the contract above.
+    let Ok(text) = unsafe { CStr::from_ptr(json) }.to_str() else {
+        return -1;
+    };
+    push_json(text).map_or(-1, |n| n.min(i32::MAX as usize) as i32)
+}
+
+/// The pushed scan results, like the real providers' scan_all_bss_async().
+/// None of them were heard by this call, so they are all `cached`.
+pub async fn scan_all_bss_async(mut on_row: RowSink) -> Result<(Vec<BssRow>, Option<NoTrigger>)> {
+    let mut rows = PUSHED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .ok_or_else(not_pushed)?
+        .rows
+        .clone();
+    for row in &mut rows {
+        on_row(row);
+    }
+    Ok((rows, Some(NoTrigger::Backend)))
+}
+
+pub fn get_connected_bssid() -> Result<Option<[u8; 6]>> {
+    Ok(link()?.map(|l| l.bssid))
+}
+
+/// The pushed connection, like nl_raw::link_async().
+pub fn link() -> Result<Option<LinkInfo>> {
+    Ok(PUSHED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .ok_or_else(not_pushed)?
+        .connected
+        .clone())
+}
+
+/// The one station interface the pushes come from.
+pub fn interfaces() -> Result<Vec<WifiIface>> {
+    Ok(vec![WifiIface {
+        ifindex: 0,
+        name: "wlan0".into(),
+        mac: None,
+        iftype: IFTYPE_STATION,
+        wiphy: None,
+    }])
+}
+
+/// Backend::Android; what has no source here is WifiBackend's nl80211
+/// default, which fails on stock Android.
+pub struct AndroidBackend;
+
+impl WifiBackend for AndroidBackend {
+    fn scan<'a>(&'a self, _ifindex: Option<u32>, _opts: &'a ScanOptions, on_row: RowSink) -> ScanFuture<'a> {
+        Box::pin(scan_all_bss_async(on_row))
+    }
+
+    fn connected_bssid(&self, _ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+        get_connected_bssid()
+    }
+
+    fn link(&self, _ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+        link()
+    }
+
+    fn interfaces(&self) -> Result<Vec<WifiIface>> {
+        interfaces()
+    }
+}
@@ -0,0 +1,207 @@
+// src/anomaly.rs
+//
+// Rogue AP / evil-twin checks on a scan, against the scans before it in
+// history.rs:
+//
+//   - our SSID from a BSSID whose OUI none of its earlier BSSIDs had, and
+//     that isn't a radio of one of them (same_device())
+//   - an SSID now offered with weaker security than it ever had before,
+//     e.g. a WPA2 network suddenly heard open
+//   - one BSSID heard on more than one channel in the same scan; a real
+//     AP gives each radio its own
+//
+// Nothing here is proof: a new mesh node from another vendor or an AP
+// reconfigured by its owner look the same. Severity says how unusual it
+// is, not who did it.
+//
+// Exposes:
+//   - detect(rows, history, own_ssids, connected) -> Vec<Finding>
+//   - Finding, Kind, Severity
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::history::StoredScan;
+use crate::ies::Security;
+use crate::lib_rust::{same_device, BssRow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    /// One of our SSIDs from a vendor (OUI) its known BSSIDs don't have.
+    UnknownOui { oui: [u8; 3] },
+    /// Weaker security than the SSID had in every earlier scan.
+    SecurityDowngrade { was: Security, now: Security },
+    /// The BSSID on several channels at once, frequencies ascending.
+    DuplicateBssid { freqs: Vec<u32> },
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::UnknownOui { .. } => "unknown_oui",
+            Kind::SecurityDowngrade { .. } => "security_downgrade",
+            Kind::DuplicateBssid { .. } => "duplicate_bssid",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub kind: Kind,
+    pub severity: Severity,
+    pub bssid: [u8; 6],
+    pub ssid: Option<Arc<str>>,
+    /// The strongest row of the BSSID in the scan.
+    pub row: BssRow,
+}
+
+/// What in `rows` looks like a rogue AP or evil twin, most severe first.
+/// `history` is the scans to compare with, taken before `rows`; with none
+/// only duplicate BSSIDs can be told. `own_ssids` are the networks to
+/// guard against impostors, the one of `connected` when empty.
+pub fn detect(rows: &[BssRow], history: &[StoredScan], own_ssids: &[Vec<u8>], connected: Option<&BssRow>) -> Vec<Finding> {
+    let mut own: HashSet<&[u8]> = own_ssids.iter().map(Vec::as_slice).collect();
+    if own.is_empty() {
+        if let Some(c) = connected.filter(|c| !c.ssid_bytes.is_empty()) {
+            own.insert(&c.ssid_bytes);
+        }
+    }
+
+    // Per SSID: the BSSIDs it was heard from and its weakest security.
+    let mut known: HashMap<&[u8], (HashSet<[u8; 6]>, Security)> = HashMap::new();
+    for r in history.iter().flat_map(|s| s.rows.iter()) {
+        let Some(bssid) = r.bssid.filter(|_| named(r)) else { continue };
+        let sec = r.security();
+        let (bssids, weakest) = known.entry(&r.ssid_bytes).or_insert_with(|| (HashSet::new(), sec));
+        bssids.insert(bssid);
+        if sec.strength() < weakest.strength() {
+            *weakest = sec;
+        }
+    }
+    // The BSS we are on is ours by definition, history or not.
+    if let Some(c) = connected.filter(|c| own.contains(c.ssid_bytes.as_slice())) {
+        if let Some(bssid) = c.bssid {
+            known
+                .entry(&c.ssid_bytes)
+                .or_insert_with(|| (HashSet::new(), c.security()))
+                .0
+                .insert(bssid);
+        }
+    }
+
+    let mut out = duplicates(rows);
+    for r in strongest(rows).into_values() {
+        let Some(bssid) = r.bssid.filter(|_| named(r)) else { continue };
+        let Some((bssids, weakest)) = known.get(r.ssid_bytes.as_slice()) else {
+            continue;
+        };
+        let sec = r.security();
+        if sec.strength() < weakest.strength() {
+            out.push(finding(
+                Kind::SecurityDowngrade { was: *weakest, now: sec },
+                if sec.is_insecure() { Severity::High } else { Severity::Medium },
+                r,
+            ));
+        }
+        if own.contains(r.ssid_bytes.as_slice()) && !bssids.is_empty() {
+            let oui = oui_of(&bssid);
+            let familiar = bssids.contains(&bssid)
+                || bssids.iter().any(|b| oui_of(b) == oui || same_device(b, &bssid));
+            if !familiar {
+                // An impostor of our network with weaker security is the
+                // classic evil twin.
+                let severity = if sec.strength() < weakest.strength() { Severity::High } else { Severity::Medium };
+                out.push(finding(Kind::UnknownOui { oui }, severity, r));
+            }
+        }
+    }
+
+    out.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then(a.bssid.cmp(&b.bssid))
+            .then_with(|| a.kind.name().cmp(b.kind.name()))
+    });
+    out
+}
+
+// BSSIDs on more than one frequency. Both heard by this scan is High; an
+// entry from the kernel's cache may be the AP's old channel after a switch.
+fn duplicates(rows: &[BssRow]) -> Vec<Finding> {
+    let mut by_bssid: HashMap<[u8; 6], Vec<&BssRow>> = HashMap::new();
+    for r in rows {
+        if let (Some(bssid), Some(_)) = (r.bssid, r.freq_mhz) {
+            by_bssid.entry(bssid).or_default().push(r);
+        }
+    }
+    let mut out = Vec::new();
+    for seen in by_bssid.into_values() {
+        let mut freqs: Vec<u32> = seen.iter().filter_map(|r| r.freq_mhz).collect();
+        freqs.sort_unstable();
+        freqs.dedup();
+        if freqs.len() < 2 {
+            continue;
+        }
+        let live: HashSet<u32> = seen.iter().filter(|r| !r.cached).filter_map(|r| r.freq_mhz).collect();
+        let severity = if live.len() >= 2 { Severity::High } else { Severity::Low };
+        let Some(row) = seen.into_iter().max_by(|a, b| signal(a).total_cmp(&signal(b))) else {
+            continue;
+        };
+        out.push(finding(Kind::DuplicateBssid { freqs }, severity, row));
+    }
+    out
+}
+
+fn finding(kind: Kind, severity: Severity, r: &BssRow) -> Finding {
+    Finding {
+        kind,
+        severity,
+        bssid: r.bssid.unwrap_or_default(),
+        ssid: r.ssid.clone(),
+        row: r.clone(),
+    }
+}
+
+// Each BSSID's strongest row.
+fn strongest(rows: &[BssRow]) -> HashMap<[u8; 6], &BssRow> {
+    let mut map: HashMap<[u8; 6], &BssRow> = HashMap::new();
+    for r in rows {
+        let Some(bssid) = r.bssid else { continue };
+        let kept = map.entry(bssid).or_insert(r);
+        if signal(r) > signal(kept) {
+            *kept = r;
+        }
+    }
+    map
+}
+
+fn named(r: &BssRow) -> bool {
+    !r.hidden && !r.ssid_bytes.is_empty()
+}
+
+// The OUI without the locally administered bit, which mesh nodes set on
+// the BSSIDs they derive from their base address.
+fn oui_of(mac: &[u8; 6]) -> [u8; 3] {
+    [mac[0] & !0x02, mac[1], mac[2]]
+}
+
+fn signal(r: &BssRow) -> f32 {
+    r.signal_dbm.unwrap_or(f32::NEG_INFINITY)
+}
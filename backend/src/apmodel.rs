@@ -0,0 +1,271 @@
+// src/apmodel.rs
+//
+// AP model fingerprints from beacon contents. Firmware builds emit the same
+// IEs in the same order with the same capability bits, so that set makes a
+// stable signature for a model family (per band: the 5 GHz radio of a
+// device carries VHT elements its 2.4 GHz radio doesn't).
+//
+//   - signature(ies) -> text form, for debugging and building the table
+//   - fingerprint(ies) -> 64-bit FNV-1a of the signature
+//   - model_name(fp) -> friendly name from the models table
+//   - generation(ies) -> Wi-Fi 4/5/6/7 from the capability elements;
+//     Generation labels a BSS with it, 6E and legacy included
+//   - learn(fp, name) -> add/replace an entry and write the table back
+//
+// The table is a JSON object {"<16 hex digits>": "Model name"}, read on
+// first use from $WIFI_BACKEND_AP_MODELS or
+// ~/.local/share/wifi_backend/ap_models.json; load() switches to another.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::lib_rust::IeList;
+
+const IE_TIM: u8 = 5;
+const IE_HT_CAP: u8 = 45;
+const IE_EXT_CAP: u8 = 127;
+const IE_VHT_CAP: u8 = 191;
+const IE_VENDOR: u8 = 221;
+const IE_EXTENSION: u8 = 255;
+
+const EXT_HE_CAP: u8 = 35;
+const EXT_EHT_CAP: u8 = 108;
+
+const OUI_MICROSOFT: [u8; 3] = [0x00, 0x50, 0xf2];
+const MS_TYPE_WPS: u8 = 4;
+
+/// Text signature of a BSS's IEs: element ids in order (vendor elements as
+/// OUI + type, extension elements as their ext id), then the capability
+/// bits of HT, VHT, extended capabilities and HE MAC.
+///
+/// TIM and WPS are left out: one is only in beacons, the other often only
+/// in probe responses, and a scan result may come from either.
+pub fn signature(ies: &IeList) -> String {
+    let mut s = String::new();
+    let mut caps = String::new();
+    for ie in ies.iter() {
+        let d = ie.data;
+        match ie.id {
+            IE_TIM => continue,
+            IE_VENDOR => {
+                let Some(h) = d.get(..4) else { continue };
+                if h[..3] == OUI_MICROSOFT && h[3] == MS_TYPE_WPS {
+                    continue;
+                }
+                let _ = write!(s, "221:{:02x}{:02x}{:02x}.{},", h[0], h[1], h[2], h[3]);
+            }
+            IE_EXTENSION => {
+                let Some(&ext) = d.first() else { continue };
+                let _ = write!(s, "255:{ext},");
+                if ext == EXT_HE_CAP {
+                    caps.push_str("|he:");
+                    hex(&mut caps, d.get(1..7).unwrap_or_default());
+                }
+            }
+            id => {
+                let _ = write!(s, "{id},");
+                let bits = match id {
+                    IE_HT_CAP => Some(("|ht:", d.get(..2))),
+                    IE_VHT_CAP => Some(("|vht:", d.get(..4))),
+                    IE_EXT_CAP => Some(("|ext:", Some(d))),
+                    _ => None,
+                };
+                if let Some((tag, bytes)) = bits {
+                    caps.push_str(tag);
+                    hex(&mut caps, bytes.unwrap_or_default());
+                }
+            }
+        }
+    }
+    s.pop();
+    s + &caps
+}
+
+fn hex(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(out, "{b:02x}");
+    }
+}
+
+/// FNV-1a of signature(); what the models table is keyed by.
+pub fn fingerprint(ies: &IeList) -> Option<u64> {
+    if ies.is_empty() {
+        return None;
+    }
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in signature(ies).bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    Some(h)
+}
+
+/// Wi-Fi generation the AP advertises (4, 5, 6 or 7), from its
+/// capability elements.
+pub fn generation(ies: &IeList) -> Option<u8> {
+    let ext = |id: u8| {
+        ies.iter()
+            .any(|ie| ie.id == IE_EXTENSION && ie.data.first() == Some(&id))
+    };
+    let has = |id: u8| ies.iter().any(|ie| ie.id == id);
+    if ext(EXT_EHT_CAP) {
+        Some(7)
+    } else if ext(EXT_HE_CAP) {
+        Some(6)
+    } else if has(IE_VHT_CAP) {
+        Some(5)
+    } else if has(IE_HT_CAP) {
+        Some(4)
+    } else {
+        None
+    }
+}
+
+/// What a BSS is, for showing how modern a neighbourhood is: Wi-Fi 6 on
+/// 6 GHz is 6E, and a BSS with IEs but no HT capabilities is legacy
+/// (802.11a/b/g).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Generation {
+    Legacy,
+    WiFi4,
+    WiFi5,
+    WiFi6,
+    WiFi6E,
+    WiFi7,
+}
+
+impl Generation {
+    /// From generation() and whether the BSS is on 6 GHz.
+    pub fn of(generation: Option<u8>, on_6ghz: bool) -> Generation {
+        match generation {
+            Some(7) => Generation::WiFi7,
+            Some(6) if on_6ghz => Generation::WiFi6E,
+            Some(6) => Generation::WiFi6,
+            Some(5) => Generation::WiFi5,
+            Some(4) => Generation::WiFi4,
+            _ => Generation::Legacy,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Generation::Legacy => "legacy",
+            Generation::WiFi4 => "wifi4",
+            Generation::WiFi5 => "wifi5",
+            Generation::WiFi6 => "wifi6",
+            Generation::WiFi6E => "wifi6e",
+            Generation::WiFi7 => "wifi7",
+        }
+    }
+}
+
+pub fn format_fp(fp: u64) -> String {
+    format!("{fp:016x}")
+}
+
+pub fn parse_fp(s: &str) -> Result<u64> {
+    u64::from_str_radix(s.trim(), 16).with_context(|| format!("bad fingerprint {s:?}"))
+}
+
+struct Models {
+    path: Option<PathBuf>,
+    names: HashMap<u64, String>,
+}
+
+// None until first use, which reads the default path if it exists.
+static MODELS: Mutex<Option<Models>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Models>> {
+    MODELS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn default_path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os("WIFI_BACKEND_AP_MODELS") {
+        return Some(p.into());
+    }
+    let home = std::env::var_os("HOME")?;
+    Some(Path::new(&home).join(".local/share/wifi_backend/ap_models.json"))
+}
+
+fn read(path: &Path) -> Result<HashMap<u64, String>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let Value::Object(map) = serde_json::from_str(&text).with_context(|| format!("parse {}", path.display()))?
+    else {
+        bail!("{}: expected a JSON object", path.display());
+    };
+    map.into_iter()
+        .map(|(k, v)| {
+            let Value::String(name) = v else {
+                bail!("{}: value for {k} is not a string", path.display());
+            };
+            Ok((parse_fp(&k)?, name))
+        })
+        .collect()
+}
+
+fn with_models<T>(f: impl FnOnce(&mut Models) -> T) -> T {
+    let mut guard = lock();
+    let models = guard.get_or_insert_with(|| {
+        let path = default_path();
+        let names = path.as_deref().and_then(|p| read(p).ok()).unwrap_or_default();
+        Models { path, names }
+    });
+    f(models)
+}
+
+/// Use the table at `path` from now on. Returns the number of entries.
+pub fn load(path: &Path) -> Result<usize> {
+    let names = read(path)?;
+    let n = names.len();
+    *lock() = Some(Models {
+        path: Some(path.to_owned()),
+        names,
+    });
+    Ok(n)
+}
+
+/// Name `fp` and write the table back to where it was loaded from.
+pub fn learn(fp: u64, name: &str) -> Result<()> {
+    with_models(|m| {
+        m.names.insert(fp, name.to_owned());
+        let Some(path) = &m.path else {
+            bail!("no models file to write (set $WIFI_BACKEND_AP_MODELS or $HOME)");
+        };
+        let mut entries: Vec<(&u64, &String)> = m.names.iter().collect();
+        entries.sort();
+        let obj: Map<String, Value> = entries
+            .into_iter()
+            .map(|(fp, name)| (format_fp(*fp), Value::String(name.clone())))
+            .collect();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Replace rather than rewrite, so a crash never leaves half a file.
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&Value::Object(obj))?)?;
+        std::fs::rename(&tmp, path).with_context(|| format!("write {}", path.display()))
+    })
+}
+
+/// Model name recorded for `fp`, if any.
+pub fn model_name(fp: u64) -> Option<String> {
+    with_models(|m| m.names.get(&fp).cloned())
+}
+
+/// What to call an AP: its recorded model if the fingerprint is known,
+/// else vendor and generation, e.g. "NETGEAR Wi-Fi 6".
+pub fn friendly_name(fp: Option<u64>, vendor: Option<&str>, gen: Option<u8>) -> Option<String> {
+    if let Some(name) = fp.and_then(model_name) {
+        return Some(name);
+    }
+    match (vendor, gen) {
+        (Some(v), Some(g)) => Some(format!("{v} Wi-Fi {g}")),
+        (Some(v), None) => Some(v.to_owned()),
+        (None, Some(g)) => Some(format!("Wi-Fi {g} AP")),
+        (None, None) => None,
+    }
+}
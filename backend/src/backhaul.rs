@@ -0,0 +1,365 @@
+// src/backhaul.rs
+//
+// Health of the wireless backhaul between mesh nodes. Link samples (RSSI,
+// MCS, cumulative TX packet / retry counters) are kept per directed link
+// over time, and each link's recent state is compared with its own
+// baseline so a backhaul that slowly gets worse is flagged even when its
+// absolute numbers still look fine.
+//
+// Samples come from:
+//   - sample_local(): this machine's AP-mode interfaces, when it is a node
+//     itself and the peers are associated to it (stations::local_stations)
+//   - record(): anything else collecting them, e.g. the Python SSH
+//     collector running `iw station dump` on each node
+//
+// Exposes record(), sample_local(), health() -> Vec<LinkHealth>, clear(),
+// and classify(): which nodes are wired and which hang off another node
+// over the air, inferred from which links are being seen at all.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::lib_rust::format_mac;
+use crate::stations;
+
+// Per link: a day of samples at one every 30 s.
+const MAX_SAMPLES: usize = 2880;
+// "Now" is the median of the last RECENT samples; the baseline is every
+// older one, once there are at least MIN_BASELINE of them.
+const RECENT: usize = 5;
+const MIN_BASELINE: usize = 10;
+
+/// RSSI below this is a weak backhaul whatever its history.
+pub const WEAK_DBM: f32 = -75.0;
+// Drops from the baseline that count as degradation.
+const RSSI_DROP_DB: f32 = 6.0;
+const MCS_DROP: f32 = 2.0;
+const RETRY_RISE: f32 = 0.10;
+/// Retry rate (retries per packet sent) that is bad on its own.
+pub const HIGH_RETRY_RATE: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkSample {
+    /// Seconds since the Unix epoch.
+    pub at: f64,
+    pub rssi_dbm: Option<f32>,
+    pub mcs: Option<u8>,
+    /// Cumulative counters; the retry rate comes from their deltas.
+    pub tx_packets: Option<u64>,
+    pub tx_retries: Option<u64>,
+    /// Channel the link runs on, when known.
+    pub freq_mhz: Option<u32>,
+}
+
+/// Current vs. baseline state of one link. `*_now` / `*_base` are
+/// medians; None where there was no data.
+#[derive(Debug, Clone, Default)]
+pub struct LinkHealth {
+    pub from: String,
+    pub to: String,
+    pub samples: usize,
+    pub last_at: f64,
+    pub rssi_now: Option<f32>,
+    pub rssi_base: Option<f32>,
+    pub mcs_now: Option<f32>,
+    pub mcs_base: Option<f32>,
+    pub retry_now: Option<f32>,
+    pub retry_base: Option<f32>,
+    /// Why the link is flagged, e.g. "rssi dropped 9 dB (-58 -> -67)".
+    pub alerts: Vec<String>,
+}
+
+impl LinkHealth {
+    pub fn status(&self) -> &'static str {
+        if self.alerts.is_empty() {
+            "ok"
+        } else {
+            "degraded"
+        }
+    }
+}
+
+type Links = BTreeMap<(String, String), VecDeque<LinkSample>>;
+
+static LINKS: Mutex<Links> = Mutex::new(BTreeMap::new());
+
+fn lock() -> std::sync::MutexGuard<'static, Links> {
+    LINKS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Add a sample for the link `from` -> `to` (node names or MACs).
+pub fn record(from: &str, to: &str, sample: LinkSample) {
+    let mut links = lock();
+    let q = links.entry((from.to_owned(), to.to_owned())).or_default();
+    if q.len() >= MAX_SAMPLES {
+        q.pop_front();
+    }
+    q.push_back(sample);
+}
+
+/// Sample the local AP interfaces' links to `peers` (the other nodes'
+/// backhaul MACs). Returns how many links were sampled.
+pub fn sample_local(peers: &[[u8; 6]]) -> Result<usize> {
+    let (_, stations) = stations::local_stations()?;
+    let at = now();
+    let mut n = 0;
+    for st in stations.iter().filter(|st| peers.contains(&st.mac)) {
+        record(
+            &format_mac(&st.bssid),
+            &format_mac(&st.mac),
+            LinkSample {
+                at,
+                rssi_dbm: st.signal_dbm,
+                mcs: st.mcs,
+                tx_packets: st.tx_packets,
+                tx_retries: st.tx_retries,
+                freq_mhz: st.freq_mhz,
+            },
+        );
+        n += 1;
+    }
+    Ok(n)
+}
+
+pub fn clear() {
+    lock().clear();
+}
+
+pub fn median(mut v: Vec<f32>) -> Option<f32> {
+    if v.is_empty() {
+        return None;
+    }
+    v.sort_by(f32::total_cmp);
+    let mid = v.len() / 2;
+    Some(if v.len().is_multiple_of(2) { (v[mid - 1] + v[mid]) / 2.0 } else { v[mid] })
+}
+
+// Retry rate between consecutive samples; counters that went backwards
+// (driver reset, reassociation) or didn't move give nothing.
+fn retry_rates(samples: &[LinkSample]) -> Vec<f32> {
+    samples
+        .windows(2)
+        .filter_map(|w| {
+            let packets = w[1].tx_packets?.checked_sub(w[0].tx_packets?)?;
+            let retries = w[1].tx_retries?.checked_sub(w[0].tx_retries?)?;
+            (packets > 0).then(|| retries as f32 / packets as f32)
+        })
+        .collect()
+}
+
+fn assess(from: &str, to: &str, q: &VecDeque<LinkSample>) -> LinkHealth {
+    let samples: Vec<LinkSample> = q.iter().copied().collect();
+    let split = samples.len().saturating_sub(RECENT);
+    let (base, recent) = samples.split_at(split);
+    // Retry deltas for the recent window start from the last baseline
+    // sample.
+    let recent_deltas = &samples[split.saturating_sub(1)..];
+    let has_base = base.len() >= MIN_BASELINE;
+
+    let rssi = |s: &[LinkSample]| median(s.iter().filter_map(|x| x.rssi_dbm).collect());
+    let mcs = |s: &[LinkSample]| median(s.iter().filter_map(|x| x.mcs.map(f32::from)).collect());
+
+    let mut h = LinkHealth {
+        from: from.to_owned(),
+        to: to.to_owned(),
+        samples: samples.len(),
+        last_at: samples.last().map_or(0.0, |s| s.at),
+        rssi_now: rssi(recent),
+        mcs_now: mcs(recent),
+        retry_now: median(retry_rates(recent_deltas)),
+        ..Default::default()
+    };
+    if has_base {
+        h.rssi_base = rssi(base);
+        h.mcs_base = mcs(base);
+        h.retry_base = median(retry_rates(base));
+    }
+
+    if let Some(now) = h.rssi_now {
+        if now < WEAK_DBM {
+            h.alerts.push(format!("weak signal ({now:.0} dBm)"));
+        }
+        if let Some(base) = h.rssi_base.filter(|b| b - now >= RSSI_DROP_DB) {
+            h.alerts.push(format!("rssi dropped {:.0} dB ({base:.0} -> {now:.0})", base - now));
+        }
+    }
+    if let (Some(now), Some(base)) = (h.mcs_now, h.mcs_base) {
+        if base - now >= MCS_DROP {
+            h.alerts.push(format!("mcs dropped ({base:.0} -> {now:.0})"));
+        }
+    }
+    if let Some(now) = h.retry_now {
+        if now >= HIGH_RETRY_RATE {
+            h.alerts.push(format!("high retry rate ({:.0}%)", now * 100.0));
+        } else if let Some(base) = h.retry_base.filter(|b| now - b >= RETRY_RISE) {
+            h.alerts.push(format!(
+                "retries up ({:.0}% -> {:.0}%)",
+                base * 100.0,
+                now * 100.0
+            ));
+        }
+    }
+    h
+}
+
+/// Every link's health, degraded links first.
+pub fn health() -> Vec<LinkHealth> {
+    let links = lock();
+    let mut out: Vec<LinkHealth> = links
+        .iter()
+        .map(|((from, to), q)| assess(from, to, q))
+        .collect();
+    out.sort_by_key(|h| h.alerts.is_empty());
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uplink {
+    Wired,
+    Wireless,
+    Unknown,
+}
+
+impl Uplink {
+    pub fn name(self) -> &'static str {
+        match self {
+            Uplink::Wired => "wired",
+            Uplink::Wireless => "wireless",
+            Uplink::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "wired" => Ok(Uplink::Wired),
+            "wireless" => Ok(Uplink::Wireless),
+            "unknown" => Ok(Uplink::Unknown),
+            other => anyhow::bail!("unknown uplink {other:?} (expected wired / wireless / unknown)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeBackhaul {
+    pub node: String,
+    pub uplink: Uplink,
+    /// Node a wireless node's backhaul goes to.
+    pub parent: Option<String>,
+    pub rssi_dbm: Option<f32>,
+    pub freq_mhz: Option<u32>,
+    /// What decided it: "override", "gateway", "links", "no_links",
+    /// "no_data".
+    pub source: &'static str,
+}
+
+/// Wired or wireless backhaul for each of `nodes` (as named in the link
+/// samples).
+///
+/// `overrides` come from something that knows (a controller's uplink type,
+/// the bridge table over SSH) and always win; `gateway` is wired. Else a
+/// node with a backhaul link seen in the last `max_age_s` seconds is
+/// wireless, and, while links are being monitored at all, a node with none
+/// is wired. Wireless nodes get the parent on their strongest path to a
+/// wired node.
+pub fn classify(nodes: &[String], gateway: Option<&str>, overrides: &[(String, Uplink)], max_age_s: f64) -> Vec<NodeBackhaul> {
+    let cutoff = now() - max_age_s;
+    let idx = |name: &str| nodes.iter().position(|n| n == name);
+
+    // Recent links between known nodes, undirected, strongest direction.
+    let mut edges: BTreeMap<(usize, usize), (f32, Option<u32>)> = BTreeMap::new();
+    let mut monitored = false;
+    for ((from, to), q) in lock().iter() {
+        let recent: Vec<&LinkSample> = q.iter().filter(|s| s.at >= cutoff).collect();
+        if recent.is_empty() {
+            continue;
+        }
+        monitored = true;
+        let (Some(a), Some(b)) = (idx(from), idx(to)) else {
+            continue;
+        };
+        let Some(rssi) = median(recent.iter().filter_map(|s| s.rssi_dbm).collect()) else {
+            continue;
+        };
+        let freq = recent.iter().rev().find_map(|s| s.freq_mhz);
+        let e = edges.entry((a.min(b), a.max(b))).or_insert((rssi, freq));
+        if rssi > e.0 {
+            *e = (rssi, freq.or(e.1));
+        }
+    }
+
+    let mut out: Vec<NodeBackhaul> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let has_links = edges.keys().any(|&(a, b)| a == i || b == i);
+            let (uplink, source) = if let Some((_, u)) = overrides.iter().find(|(n, _)| n == node) {
+                (*u, "override")
+            } else if gateway == Some(node.as_str()) {
+                (Uplink::Wired, "gateway")
+            } else if has_links {
+                (Uplink::Wireless, "links")
+            } else if monitored {
+                (Uplink::Wired, "no_links")
+            } else {
+                (Uplink::Unknown, "no_data")
+            };
+            NodeBackhaul {
+                node: node.clone(),
+                uplink,
+                parent: None,
+                rssi_dbm: None,
+                freq_mhz: None,
+                source,
+            }
+        })
+        .collect();
+
+    // Grow trees out of the wired nodes along the strongest links (Prim
+    // with several roots); wireless nodes out of reach of any wired one
+    // just take their strongest neighbour.
+    let mut placed: Vec<bool> = out.iter().map(|n| n.uplink != Uplink::Wireless).collect();
+    loop {
+        let next = edges
+            .iter()
+            .filter_map(|(&(a, b), &e)| match (placed[a], placed[b]) {
+                (true, false) if out[a].uplink != Uplink::Unknown => Some((a, b, e)),
+                (false, true) if out[b].uplink != Uplink::Unknown => Some((b, a, e)),
+                _ => None,
+            })
+            .max_by(|x, y| x.2 .0.total_cmp(&y.2 .0));
+        let Some((parent, child, (rssi, freq))) = next else {
+            break;
+        };
+        placed[child] = true;
+        out[child].parent = Some(nodes[parent].clone());
+        out[child].rssi_dbm = Some(rssi);
+        out[child].freq_mhz = freq;
+    }
+    for i in 0..out.len() {
+        if placed[i] {
+            continue;
+        }
+        let best = edges
+            .iter()
+            .filter_map(|(&(a, b), &e)| match (a == i, b == i) {
+                (true, _) => Some((b, e)),
+                (_, true) => Some((a, e)),
+                _ => None,
+            })
+            .max_by(|x, y| x.1 .0.total_cmp(&y.1 .0));
+        if let Some((p, (rssi, freq))) = best {
+            out[i].parent = Some(nodes[p].clone());
+            out[i].rssi_dbm = Some(rssi);
+            out[i].freq_mhz = freq;
+        }
+    }
+    out
+}
@@ -0,0 +1,92 @@
+// src/cancel.rs
+//
+// Cooperative cancellation for the long-running calls. Python holds a
+// CancelToken (lib.rs) and flips it from any thread; the operation checks
+// it at its safe points (between scans, between chunks of history, in
+// planner search loops, via Progress::report) and returns Cancelled.
+// Futures that can hang on the kernel (a scan that never completes) are
+// raced against the flag with run().
+
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+// How often run() looks at the flag while the future is pending.
+const POLL: Duration = Duration::from_millis(50);
+
+/// The error a cancelled operation returns.
+#[derive(Debug, Clone, Copy)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Shared flag; clones see the same state. The default never cancels.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Option<Arc<AtomicBool>>);
+
+impl Cancel {
+    pub fn new() -> Self {
+        Cancel(Some(Arc::new(AtomicBool::new(false))))
+    }
+
+    /// A token nobody can cancel.
+    pub fn none() -> Self {
+        Cancel(None)
+    }
+
+    pub fn cancel(&self) {
+        if let Some(flag) = &self.0 {
+            flag.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn reset(&self) {
+        if let Some(flag) = &self.0 {
+            flag.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.as_ref().is_some_and(|f| f.load(Ordering::Relaxed))
+    }
+
+    /// Err(Cancelled) once cancelled; call at safe points.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Drive `fut`, giving up with Cancelled as soon as the flag is set.
+    /// `fut` is dropped mid-flight then, so it must be safe to abandon
+    /// (netlink requests are: the command task discards the reply).
+    pub async fn run<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(flag) = &self.0 else {
+            return fut.await;
+        };
+        let mut fut = std::pin::pin!(fut);
+        let mut tick = tokio::time::interval(POLL);
+        std::future::poll_fn(|cx| {
+            if flag.load(Ordering::Relaxed) {
+                return Poll::Ready(Err(Cancelled.into()));
+            }
+            if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                return Poll::Ready(res);
+            }
+            // Keep a timer registered so we're woken to look again.
+            while tick.poll_tick(cx).is_ready() {}
+            Poll::Pending
+        })
+        .await
+    }
+}
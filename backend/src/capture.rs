@@ -0,0 +1,346 @@
+// src/capture.rs
+//
+// Monitor-mode capture, for checking the IE parser against Wireshark and
+// debugging APs a scan misclassifies. A designated interface is switched
+// to monitor mode (NL80211_CMD_SET_INTERFACE, with the link taken down
+// around the change), optionally tuned to one frequency, and the beacons
+// and probe responses it hears are read off an AF_PACKET socket into a
+// radiotap pcap (pcap::Writer) for the requested time. The interface gets
+// its old type and link state back afterwards, on errors and cancel too.
+//
+// Built with the capture feature only: it needs CAP_NET_ADMIN and
+// CAP_NET_RAW, and takes the interface off its network while it runs.
+// Without the feature capture() fails with WifiError::Unsupported. In
+// dry-run mode (lib_rust::dry_run()) it checks the interface, frequency
+// and output directory and returns what it would change instead.
+//
+// Exposes:
+//   - CaptureOptions, CaptureStats, CapturePlan, Capture
+//   - capture(opts, path, cancel) -> Result<Capture>
+
+use anyhow::Result;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cancel::Cancel;
+
+#[cfg(not(feature = "capture"))]
+use crate::error::WifiError;
+
+#[cfg(feature = "capture")]
+use {
+    crate::error::WifiError,
+    crate::lib_rust::{backend, dry_run, freq_band, freq_to_channel},
+    crate::netlink::{block_on, ifindex_attrs, list_interfaces, Nl80211, WifiIface},
+    crate::nl_raw::{Attr, Cmd},
+    crate::pcap,
+    anyhow::Context,
+    neli::genl::Nlattr,
+    std::fs::File,
+    std::io::{self, BufWriter, ErrorKind},
+    std::os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    std::time::{Instant, SystemTime, UNIX_EPOCH},
+    tracing::{debug, warn},
+};
+
+/// NL80211_IFTYPE_MONITOR.
+pub const IFTYPE_MONITOR: u32 = 6;
+
+// Management frame subtypes (frame control bits 4..7).
+#[cfg(feature = "capture")]
+const SUBTYPE_PROBE_RESP: u8 = 5;
+#[cfg(feature = "capture")]
+const SUBTYPE_BEACON: u8 = 8;
+
+// How often a quiet socket lets the loop check the deadline and cancel.
+#[cfg(feature = "capture")]
+const POLL: Duration = Duration::from_millis(200);
+
+/// What to capture.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "capture"), allow(dead_code))]
+pub struct CaptureOptions {
+    /// Interface to put in monitor mode, by name.
+    pub iface: String,
+    pub duration: Duration,
+    /// Frequency to stay on, MHz; None leaves the radio where it is.
+    pub freq_mhz: Option<u32>,
+}
+
+/// What a capture wrote.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaptureStats {
+    pub beacons: u64,
+    pub probe_responses: u64,
+    /// Frames heard but not written: other types, broken radiotap.
+    pub skipped: u64,
+}
+
+impl CaptureStats {
+    /// Records in the file.
+    pub fn frames(&self) -> u64 {
+        self.beacons + self.probe_responses
+    }
+}
+
+/// What a capture changes on the interface: it goes from `iftype` to
+/// IFTYPE_MONITOR (and back afterwards), tuned to `freq_mhz` if given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturePlan {
+    pub iface: String,
+    pub ifindex: u32,
+    /// NL80211_IFTYPE_* it has now.
+    pub iftype: u32,
+    pub freq_mhz: Option<u32>,
+}
+
+/// What capture() did: the pcap written, or in dry-run mode only the
+/// checked plan.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "capture"), allow(dead_code))]
+pub enum Capture {
+    Written(CaptureStats),
+    DryRun(CapturePlan),
+}
+
+#[cfg(not(feature = "capture"))]
+pub fn capture(_opts: &CaptureOptions, _path: &Path, _cancel: &Cancel) -> Result<Capture> {
+    Err(WifiError::Unsupported("wifi_backend was built without the capture feature".into()).into())
+}
+
+/// Put `opts.iface` in monitor mode and write the beacons and probe
+/// responses it hears for `opts.duration` to `path` as a radiotap pcap.
+/// A cancel stops early; the file keeps what was captured by then. In
+/// dry-run mode nothing is touched and nothing written: the checked plan
+/// comes back instead.
+#[cfg(feature = "capture")]
+pub fn capture(opts: &CaptureOptions, path: &Path, cancel: &Cancel) -> Result<Capture> {
+    let nl = Nl80211::shared()?;
+    let iface = block_on(list_interfaces(&nl))?
+        .into_iter()
+        .find(|i| i.name == opts.iface)
+        .ok_or_else(|| WifiError::NoInterface(format!("no Wi-Fi interface named {:?}", opts.iface)))?;
+    if let Some(freq) = opts.freq_mhz {
+        check_freq(&iface, freq)?;
+    }
+    if dry_run() {
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        if !dir.is_dir() {
+            anyhow::bail!("no directory {} to write the capture to", dir.display());
+        }
+        debug!(iface = %iface.name, freq = ?opts.freq_mhz, "dry run: capture not started");
+        return Ok(Capture::DryRun(CapturePlan {
+            iface: iface.name,
+            ifindex: iface.ifindex,
+            iftype: iface.iftype,
+            freq_mhz: opts.freq_mhz,
+        }));
+    }
+    let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+
+    let _monitor = Monitor::enter(&nl, &iface)?;
+    if let Some(freq) = opts.freq_mhz {
+        let mut attrs = ifindex_attrs(iface.ifindex)?;
+        attrs.push(Nlattr::new(false, false, Attr::AttrWiphyFreq, freq)?);
+        block_on(nl.request(Cmd::CmdSetWiphy, attrs)).with_context(|| format!("tune {} to {freq} MHz", iface.name))?;
+    }
+    let sock = packet_socket(iface.ifindex)?;
+    debug!(iface = %iface.name, freq = ?opts.freq_mhz, secs = opts.duration.as_secs_f64(), "capture started");
+
+    let mut out = pcap::Writer::new(BufWriter::new(file))?;
+    let mut stats = CaptureStats::default();
+    let mut buf = vec![0u8; 65_536];
+    let deadline = Instant::now() + opts.duration;
+    while Instant::now() < deadline && !cancel.is_cancelled() {
+        // SAFETY: recv(2) into a buffer we own, at most its length.
+        let n = unsafe { libc::recv(sock.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+        if n < 0 {
+            let e = io::Error::last_os_error();
+            match e.kind() {
+                ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted => continue,
+                _ => return Err(e).context("read from the capture socket"),
+            }
+        }
+        let frame = &buf[..n as usize];
+        match mgmt_subtype(frame) {
+            Some(SUBTYPE_BEACON) => stats.beacons += 1,
+            Some(SUBTYPE_PROBE_RESP) => stats.probe_responses += 1,
+            _ => {
+                stats.skipped += 1;
+                continue;
+            }
+        }
+        out.write(now_us(), frame)?;
+    }
+    out.finish()?;
+    debug!(
+        beacons = stats.beacons,
+        probe_responses = stats.probe_responses,
+        skipped = stats.skipped,
+        "capture done"
+    );
+    Ok(Capture::Written(stats))
+}
+
+// `freq` must be a channel the interface's radio has and hasn't disabled
+// (as far as its capabilities can be read).
+#[cfg(feature = "capture")]
+fn check_freq(iface: &WifiIface, freq: u32) -> Result<()> {
+    let Some(ch) = freq_to_channel(freq) else {
+        anyhow::bail!("{freq} MHz is not a Wi-Fi channel");
+    };
+    match backend().provider().phy_caps(Some(iface.ifindex)) {
+        Ok(caps) if !caps.usable(freq_band(freq), ch) => {
+            anyhow::bail!("{}'s radio can't use {freq} MHz", iface.name)
+        }
+        _ => Ok(()),
+    }
+}
+
+// Subtype of a radiotap-framed management frame; None for other types.
+#[cfg(feature = "capture")]
+fn mgmt_subtype(data: &[u8]) -> Option<u8> {
+    let rt_len = u16::from_le_bytes([*data.get(2)?, *data.get(3)?]) as usize;
+    let fc = *data.get(rt_len)?;
+    (fc & 0x0c == 0).then_some(fc >> 4)
+}
+
+#[cfg(feature = "capture")]
+fn now_us() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_micros() as u64)
+}
+
+// An interface switched to monitor mode; dropping it switches it back.
+#[cfg(feature = "capture")]
+struct Monitor<'a> {
+    nl: &'a Nl80211,
+    ifindex: u32,
+    name: String,
+    iftype: u32,
+    was_up: bool,
+}
+
+#[cfg(feature = "capture")]
+impl<'a> Monitor<'a> {
+    fn enter(nl: &'a Nl80211, iface: &WifiIface) -> Result<Monitor<'a>> {
+        let monitor = Monitor {
+            nl,
+            ifindex: iface.ifindex,
+            name: iface.name.clone(),
+            iftype: iface.iftype,
+            was_up: link_flags(&iface.name)? & libc::IFF_UP != 0,
+        };
+        if iface.iftype != IFTYPE_MONITOR {
+            // Most drivers refuse to change the type of a running interface.
+            set_link_up(&iface.name, false)?;
+            block_on(set_iftype(nl, iface.ifindex, IFTYPE_MONITOR))
+                .with_context(|| format!("put {} in monitor mode", iface.name))?;
+        }
+        set_link_up(&iface.name, true)?;
+        Ok(monitor)
+    }
+}
+
+#[cfg(feature = "capture")]
+impl Drop for Monitor<'_> {
+    fn drop(&mut self) {
+        let restore = || -> Result<()> {
+            if self.iftype != IFTYPE_MONITOR {
+                set_link_up(&self.name, false)?;
+                block_on(set_iftype(self.nl, self.ifindex, self.iftype))?;
+            }
+            set_link_up(&self.name, self.was_up)
+        };
+        if let Err(e) = restore() {
+            warn!(iface = %self.name, "couldn't restore the interface after the capture: {e:#}");
+        }
+    }
+}
+
+#[cfg(feature = "capture")]
+async fn set_iftype(nl: &Nl80211, ifindex: u32, iftype: u32) -> Result<()> {
+    let mut attrs = ifindex_attrs(ifindex)?;
+    attrs.push(Nlattr::new(false, false, Attr::AttrIftype, iftype)?);
+    nl.request(Cmd::CmdSetInterface, attrs).await?;
+    Ok(())
+}
+
+// SIOCGIFFLAGS / SIOCSIFFLAGS go through any socket; a UDP one will do.
+#[cfg(feature = "capture")]
+fn ifreq_ioctl(name: &str, request: libc::c_ulong, flags: Option<libc::c_int>) -> Result<libc::c_int> {
+    // SAFETY: plain socket(2) call; the fd is owned by the OwnedFd.
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error()).context("open ioctl socket");
+    }
+    let sock = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: ifreq is plain old data; all zeroes is a valid value.
+    let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+    if name.len() >= req.ifr_name.len() {
+        anyhow::bail!("interface name {name:?} is too long");
+    }
+    for (dst, &src) in req.ifr_name.iter_mut().zip(name.as_bytes()) {
+        *dst = src as libc::c_char;
+    }
+    if let Some(f) = flags {
+        req.ifr_ifru.ifru_flags = f as libc::c_short;
+    }
+    // SAFETY: the kernel reads and writes `req`, which outlives the call.
+    if unsafe { libc::ioctl(sock.as_raw_fd(), request as _, &mut req) } < 0 {
+        return Err(io::Error::last_os_error()).with_context(|| format!("ioctl on {name}"));
+    }
+    // SAFETY: ifru_flags is what SIOCGIFFLAGS fills in.
+    Ok(unsafe { req.ifr_ifru.ifru_flags } as libc::c_int)
+}
+
+#[cfg(feature = "capture")]
+fn link_flags(name: &str) -> Result<libc::c_int> {
+    ifreq_ioctl(name, libc::SIOCGIFFLAGS as libc::c_ulong, None)
+}
+
+#[cfg(feature = "capture")]
+fn set_link_up(name: &str, up: bool) -> Result<()> {
+    let flags = link_flags(name)?;
+    let want = if up { flags | libc::IFF_UP } else { flags & !libc::IFF_UP };
+    if want != flags {
+        ifreq_ioctl(name, libc::SIOCSIFFLAGS as libc::c_ulong, Some(want))
+            .with_context(|| format!("bring {name} {}", if up { "up" } else { "down" }))?;
+    }
+    Ok(())
+}
+
+// Raw AF_PACKET socket bound to `ifindex`, every protocol, timing out
+// every POLL so the capture loop gets to look at the clock.
+#[cfg(feature = "capture")]
+fn packet_socket(ifindex: u32) -> Result<OwnedFd> {
+    let proto = (libc::ETH_P_ALL as u16).to_be();
+    // SAFETY: plain socket(2) call; the fd is owned by the OwnedFd.
+    let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, proto as libc::c_int) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error()).context("open packet socket (needs CAP_NET_RAW)");
+    }
+    let sock = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: sockaddr_ll is plain old data; all zeroes is a valid value.
+    let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+    addr.sll_family = libc::AF_PACKET as u16;
+    addr.sll_protocol = proto;
+    addr.sll_ifindex = ifindex as libc::c_int;
+    let len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+    // SAFETY: bind(2) reads `len` bytes of `addr`.
+    if unsafe { libc::bind(fd, (&addr as *const libc::sockaddr_ll).cast(), len) } < 0 {
+        return Err(io::Error::last_os_error()).context("bind packet socket");
+    }
+
+    let tv = libc::timeval {
+        tv_sec: 0,
+        tv_usec: POLL.as_micros() as libc::suseconds_t,
+    };
+    let len = std::mem::size_of::<libc::timeval>() as libc::socklen_t;
+    // SAFETY: setsockopt(2) reads `len` bytes of `tv`.
+    if unsafe { libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO, (&tv as *const libc::timeval).cast(), len) } < 0 {
+        return Err(io::Error::last_os_error()).context("set packet socket timeout");
+    }
+    Ok(sock)
+}
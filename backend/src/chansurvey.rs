@@ -0,0 +1,122 @@
+// src/chansurvey.rs
+//
+// Channel survey (NL80211_CMD_GET_SURVEY): per frequency, the noise floor
+// and how long the radio has listened on the channel and found it busy.
+// Busy time counts everything on the air, including stations and non-Wi-Fi
+// sources that send no beacons, so channel scoring adds it on top of the
+// beacon-derived weight (lib_rust::channel_weights / channel_breakdown).
+//
+// The kernel's counters are cumulative. A channel's busy fraction is taken
+// over the time since the previous dump when the counters moved, else over
+// everything counted so far. Off-channel counters only move while
+// scanning, so a survey right after a triggered scan covers every channel.
+//
+// Exposes:
+//   - survey(backend, ifindex) -> Result<Vec<ChannelSurvey>>, kept as the
+//     latest
+//   - refresh(backend, ifindex): survey() where it is only an extra input
+//   - recent_busy() -> busy fraction per (band, channel) of a fresh survey
+//   - clear()
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::lib_rust::{freq_band, freq_to_channel, Backend};
+use crate::perf;
+
+/// How much a channel that is busy all the time adds to its weight: as
+/// much as one AP heard at -50 dBm (see ChannelConfig::ap_weight).
+pub const BUSY_WEIGHT: f32 = 50.0;
+
+// Older surveys aren't used for scoring.
+const MAX_AGE: Duration = Duration::from_secs(120);
+// Shorter windows between two dumps are too noisy for a fraction.
+const MIN_WINDOW_MS: u64 = 50;
+
+/// One frequency of a survey dump. Times are cumulative milliseconds.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelSurvey {
+    pub freq_mhz: u32,
+    pub noise_dbm: Option<f32>,
+    /// The radio is operating on this channel.
+    pub in_use: bool,
+    pub active_ms: Option<u64>,
+    pub busy_ms: Option<u64>,
+    /// Busy on the secondary channel (40 MHz and wider).
+    pub ext_busy_ms: Option<u64>,
+    pub rx_ms: Option<u64>,
+    pub tx_ms: Option<u64>,
+    pub scan_ms: Option<u64>,
+    /// Share of the active time the channel was busy with anything but our
+    /// own transmissions, 0.0 to 1.0.
+    pub busy: Option<f32>,
+}
+
+struct Last {
+    at: Instant,
+    surveys: Vec<ChannelSurvey>,
+}
+
+static LAST: Mutex<Option<Last>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Last>> {
+    LAST.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Dump the survey of `ifindex`'s radio (the first Wi-Fi interface if
+/// None), from `b`'s provider.
+pub fn survey(b: Backend, ifindex: Option<u32>) -> Result<Vec<ChannelSurvey>> {
+    let start = Instant::now();
+    let mut surveys = b.provider().survey(ifindex)?;
+    perf::record("survey", b.name(), start.elapsed());
+
+    let mut last = lock();
+    let prev = last.as_ref().map_or(&[][..], |l| &l.surveys[..]);
+    for s in &mut surveys {
+        let before = prev.iter().find(|p| p.freq_mhz == s.freq_mhz);
+        s.busy = busy_fraction(s, before);
+    }
+    *last = Some(Last {
+        at: Instant::now(),
+        surveys: surveys.clone(),
+    });
+    Ok(surveys)
+}
+
+/// survey(), ignoring failures: drivers without survey support (and
+/// fixtures without surveys) just leave scoring to the beacons.
+pub fn refresh(b: Backend, ifindex: Option<u32>) {
+    let _ = survey(b, ifindex);
+}
+
+fn busy_fraction(now: &ChannelSurvey, before: Option<&ChannelSurvey>) -> Option<f32> {
+    let others = |s: &ChannelSurvey| s.busy_ms.map(|b| b.saturating_sub(s.tx_ms.unwrap_or(0)));
+    let (active, busy) = (now.active_ms?, others(now)?);
+    // Counters that went backwards were reset (interface restarted).
+    let window = before.and_then(|b| {
+        let (a0, b0) = (b.active_ms?, others(b)?);
+        (active >= a0 + MIN_WINDOW_MS && busy >= b0).then(|| (active - a0, busy - b0))
+    });
+    let (active, busy) = window.unwrap_or((active, busy));
+    (active > 0).then(|| (busy as f32 / active as f32).min(1.0))
+}
+
+/// Busy fraction per (freq_band(), channel) from the latest survey, if it
+/// is recent enough to score with.
+pub fn recent_busy() -> HashMap<(u8, u32), f32> {
+    let guard = lock();
+    let Some(last) = guard.as_ref().filter(|l| l.at.elapsed() <= MAX_AGE) else {
+        return HashMap::new();
+    };
+    last.surveys
+        .iter()
+        .filter_map(|s| Some(((freq_band(s.freq_mhz), freq_to_channel(s.freq_mhz)?), s.busy?)))
+        .collect()
+}
+
+/// Forget the latest survey, so scoring goes by beacons alone again.
+pub fn clear() {
+    *lock() = None;
+}
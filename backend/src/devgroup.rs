@@ -0,0 +1,393 @@
+// src/devgroup.rs
+//
+// Which BSSIDs are radios of one device (lib_rust::same_device), for the
+// mesh planners, topology, survey, steering and fingerprinting. Asked in
+// this order, the first that knows answers:
+//   1. devices listed from Python, each with its BSSIDs: a listed BSSID
+//      only groups with the others of its device
+//   2. Multiple BSSID sets (IE 71) learned from scans: a transmitting BSS
+//      beacons for the 2^n addresses sharing all but its low n bits
+//   3. pairs learned from scans: a BSS listing another as co-located in
+//      its Reduced Neighbor Report, links sharing an MLD address, and the
+//      same SSID from nearby addresses of one OUI on different bands (the
+//      radios of a dual- or tri-band AP numbered in a row)
+//   4. a callback from Python, which may return None to pass
+//   5. the rule for either address's OUI, else the default Rule
+//
+// Vendors derive their extra BSSIDs differently, so no one rule fits:
+// Ubiquiti changes the first and last byte, most consumer routers count
+// up the last byte, others set the locally administered bit and count in
+// the first byte.
+//
+// Exposes:
+//   - same_device(a, b) -> bool
+//   - Rule, Grouping, SameDeviceFn; set() / current()
+//   - learn(rows): remember the Multiple BSSID sets and device pairs a scan
+//     showed; mbssid_sets() / pairs() list them
+//   - groups(rows) -> Vec<Device>: the rows' BSSIDs by device, what tied
+//     each together
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+
+use crate::ies::{self, MultipleBssid};
+use crate::lib_rust::{freq_band, ie_list, BssRow};
+
+/// How BSSIDs of one device relate, for addresses nothing else decides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rule {
+    /// Bytes 1..=4 match: only the first and last byte differ (Ubiquiti).
+    #[default]
+    MiddleBytes,
+    /// The first five bytes match and the last only differs in its low
+    /// nibble: consecutive BSSIDs.
+    LastNibble,
+    /// Everything but the first byte matches: extra BSSIDs made by setting
+    /// the locally administered bit and counting in the bits above it.
+    LocalAdmin,
+    /// Only identical BSSIDs.
+    Exact,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::MiddleBytes => "middle_bytes",
+            Rule::LastNibble => "last_nibble",
+            Rule::LocalAdmin => "local_admin",
+            Rule::Exact => "exact",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Rule> {
+        Ok(match name {
+            "middle_bytes" => Rule::MiddleBytes,
+            "last_nibble" => Rule::LastNibble,
+            "local_admin" => Rule::LocalAdmin,
+            "exact" => Rule::Exact,
+            _ => bail!(
+                "unknown grouping rule {name:?} (expected \"middle_bytes\", \"last_nibble\", \"local_admin\" or \"exact\")"
+            ),
+        })
+    }
+
+    pub fn matches(self, a: &[u8; 6], b: &[u8; 6]) -> bool {
+        match self {
+            Rule::MiddleBytes => a[1..=4] == b[1..=4],
+            Rule::LastNibble => a[..5] == b[..5] && a[5] >> 4 == b[5] >> 4,
+            Rule::LocalAdmin => a[1..] == b[1..] && a[0] & 1 == 0 && b[0] & 1 == 0,
+            Rule::Exact => a == b,
+        }
+    }
+}
+
+/// Asked before the rules; Some(answer) settles it, None passes.
+pub type SameDeviceFn = Arc<dyn Fn(&[u8; 6], &[u8; 6]) -> Option<bool> + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct Grouping {
+    pub rule: Rule,
+    /// Rules by OUI, ahead of `rule`.
+    pub vendors: Vec<([u8; 3], Rule)>,
+    /// Known devices, each its BSSIDs.
+    pub devices: Vec<Vec<[u8; 6]>>,
+    pub callback: Option<SameDeviceFn>,
+}
+
+impl Grouping {
+    fn same_device(&self, a: &[u8; 6], b: &[u8; 6]) -> bool {
+        if a == b {
+            return true;
+        }
+        let listed = |m: &[u8; 6]| self.devices.iter().position(|d| d.contains(m));
+        match (listed(a), listed(b)) {
+            (Some(i), Some(j)) => return i == j,
+            (Some(_), None) | (None, Some(_)) => return false,
+            (None, None) => {}
+        }
+        if in_one_set(a, b) || paired(a, b).is_some() {
+            return true;
+        }
+        if let Some(same) = self.callback.as_ref().and_then(|f| f(a, b)) {
+            return same;
+        }
+        let vendor = |m: &[u8; 6]| {
+            self.vendors
+                .iter()
+                .find(|(oui, _)| oui[..] == m[..3])
+                .map(|(_, rule)| *rule)
+        };
+        vendor(a).or_else(|| vendor(b)).unwrap_or(self.rule).matches(a, b)
+    }
+
+    fn listed(&self, m: &[u8; 6]) -> bool {
+        self.devices.iter().any(|d| d.contains(m))
+    }
+
+    // Why `a` and `b` are one device, given what this scan showed about
+    // them; None if they aren't. Listed devices overrule the scan.
+    fn evidence(&self, a: &[u8; 6], b: &[u8; 6], scan: Option<&'static str>) -> Option<&'static str> {
+        if self.listed(a) || self.listed(b) {
+            return self.same_device(a, b).then_some("listed");
+        }
+        if scan.is_some() {
+            return scan;
+        }
+        if in_one_set(a, b) {
+            return Some("mbssid");
+        }
+        self.same_device(a, b).then(|| paired(a, b).unwrap_or("rule"))
+    }
+}
+
+static GROUPING: Mutex<Option<Arc<Grouping>>> = Mutex::new(None);
+
+/// Group BSSIDs by `g` from now on.
+pub fn set(g: Grouping) {
+    *GROUPING.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(g));
+}
+
+/// The grouping in use; Grouping::default() unless set.
+pub fn current() -> Arc<Grouping> {
+    GROUPING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(Default::default)
+        .clone()
+}
+
+/// Whether `a` and `b` are likely radios of one device (order above).
+pub fn same_device(a: &[u8; 6], b: &[u8; 6]) -> bool {
+    // Cloned out so a callback may call back in.
+    current().same_device(a, b)
+}
+
+// Transmitted BSSIDs and their sets, from scans. Dropped if it ever grows
+// past MBSSID_MAX.
+const MBSSID_MAX: usize = 1024;
+static MBSSID: Mutex<Vec<([u8; 6], MultipleBssid)>> = Mutex::new(Vec::new());
+
+/// Remember the Multiple BSSID sets and the device pairs `rows` show.
+pub fn learn(rows: &[BssRow]) {
+    let found = scan_pairs(&rows.iter().collect::<Vec<_>>());
+    if !found.is_empty() {
+        let mut pairs = PAIRS.lock().unwrap_or_else(|e| e.into_inner());
+        for (a, b, why) in found {
+            if pairs.iter().any(|&(x, y, _)| (x, y) == (a, b)) {
+                continue;
+            }
+            if pairs.len() >= PAIRS_MAX {
+                pairs.clear();
+            }
+            pairs.push((a, b, why));
+        }
+    }
+
+    let mut sets = MBSSID.lock().unwrap_or_else(|e| e.into_inner());
+    for r in rows {
+        let (Some(tx), Some(set)) = (r.bssid, r.multiple_bssid()) else {
+            continue;
+        };
+        match sets.iter_mut().find(|(t, _)| *t == tx) {
+            Some(known) => known.1 = set,
+            None => {
+                if sets.len() >= MBSSID_MAX {
+                    sets.clear();
+                }
+                sets.push((tx, set));
+            }
+        }
+    }
+}
+
+/// The Multiple BSSID sets learned so far: transmitted BSSID, the set's
+/// size (2^n) and the nontransmitted BSSIDs its beacons listed.
+pub fn mbssid_sets() -> Vec<([u8; 6], u32, Vec<[u8; 6]>)> {
+    MBSSID
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(tx, set)| (*tx, 1 << set.max_indicator, set.bssids(tx)))
+        .collect()
+}
+
+fn in_one_set(a: &[u8; 6], b: &[u8; 6]) -> bool {
+    MBSSID
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|(tx, set)| set.contains(tx, a) && set.contains(tx, b))
+}
+
+/// Two BSSIDs of one device, the lower first, and why: "rnr", "mld" or
+/// "pattern".
+pub type Pair = ([u8; 6], [u8; 6], &'static str);
+
+// The pairs scans showed. Dropped if it ever grows past PAIRS_MAX.
+const PAIRS_MAX: usize = 4096;
+static PAIRS: Mutex<Vec<Pair>> = Mutex::new(Vec::new());
+
+// Radios of one AP numbered in a row are at most this far apart (the
+// locally administered bit aside).
+const PATTERN_SPAN: u64 = 16;
+
+/// The device pairs learned so far.
+pub fn pairs() -> Vec<Pair> {
+    PAIRS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn paired(a: &[u8; 6], b: &[u8; 6]) -> Option<&'static str> {
+    let key = if a < b { (*a, *b) } else { (*b, *a) };
+    PAIRS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|&&(x, y, _)| (x, y) == key)
+        .map(|&(_, _, why)| why)
+}
+
+// The pairs `rows` show: RNR co-location, shared MLD addresses, and the
+// dual-band numbering pattern.
+fn scan_pairs(rows: &[&BssRow]) -> Vec<Pair> {
+    let mut out = Vec::new();
+    let mut add = |a: [u8; 6], b: [u8; 6], why| {
+        let key = if a < b { (a, b) } else { (b, a) };
+        if a != b && !out.iter().any(|&(x, y, _)| (x, y) == key) {
+            out.push((key.0, key.1, why));
+        }
+    };
+    for r in rows {
+        let (Some(bssid), Some(ies)) = (r.bssid, r.ies.as_deref()) else {
+            continue;
+        };
+        for n in ies::parse_rnr(&ie_list(ies)) {
+            if let Some(other) = n.bssid.filter(|_| n.co_located) {
+                add(bssid, other, "rnr");
+            }
+        }
+    }
+    for (i, a) in rows.iter().enumerate() {
+        let Some(x) = a.bssid else { continue };
+        for b in &rows[i + 1..] {
+            let Some(y) = b.bssid else { continue };
+            if a.mld_addr().is_some_and(|m| b.mld_addr() == Some(m)) {
+                add(x, y, "mld");
+            } else if pattern_pair(a, b) {
+                add(x, y, "pattern");
+            }
+        }
+    }
+    out
+}
+
+// The same SSID from addresses of one OUI close together, on different
+// bands.
+fn pattern_pair(a: &BssRow, b: &BssRow) -> bool {
+    let (Some(x), Some(y), Some(fa), Some(fb)) = (a.bssid, b.bssid, a.freq_mhz, b.freq_mhz) else {
+        return false;
+    };
+    // Counted with the locally administered bit cleared.
+    let addr = |m: [u8; 6]| m.iter().fold(0u64, |acc, &b| acc << 8 | b as u64) & !(0x02 << 40);
+    !a.hidden
+        && !a.ssid_bytes.is_empty()
+        && a.ssid_bytes == b.ssid_bytes
+        && freq_band(fa) != freq_band(fb)
+        && addr(x).abs_diff(addr(y)) <= PATTERN_SPAN
+}
+
+/// The radios of one device among a scan's rows.
+#[derive(Debug, Clone)]
+pub struct Device {
+    /// One row per BSSID (its strongest), by band, then strongest first.
+    pub radios: Vec<BssRow>,
+    /// What tied the radios together: "listed", "mbssid", "rnr", "mld",
+    /// "pattern" or "rule" (same_device()'s rules and callback). Empty for
+    /// a lone radio.
+    pub evidence: Vec<&'static str>,
+}
+
+impl Device {
+    pub fn bssids(&self) -> Vec<[u8; 6]> {
+        self.radios.iter().filter_map(|r| r.bssid).collect()
+    }
+
+    pub fn best_dbm(&self) -> Option<f32> {
+        self.radios.iter().filter_map(|r| r.signal_dbm).reduce(f32::max)
+    }
+}
+
+/// `rows` grouped by device, strongest first: radios same_device() pairs,
+/// or that this scan shows to be one device, join a group.
+pub fn groups(rows: &[BssRow]) -> Vec<Device> {
+    let mut radios: Vec<&BssRow> = Vec::new();
+    for r in rows.iter().filter(|r| r.bssid.is_some()) {
+        match radios.iter_mut().find(|k| k.bssid == r.bssid) {
+            Some(kept) => {
+                if r.signal_dbm > kept.signal_dbm {
+                    *kept = r;
+                }
+            }
+            None => radios.push(r),
+        }
+    }
+    let found = scan_pairs(&radios);
+    let g = current();
+
+    let mut parent: Vec<usize> = (0..radios.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut evidence: Vec<Vec<&'static str>> = vec![Vec::new(); radios.len()];
+    for i in 0..radios.len() {
+        for j in i + 1..radios.len() {
+            let (Some(a), Some(b)) = (radios[i].bssid, radios[j].bssid) else {
+                continue;
+            };
+            let key = if a < b { (a, b) } else { (b, a) };
+            let scan = found.iter().find(|&&(x, y, _)| (x, y) == key).map(|&(_, _, why)| why);
+            let Some(why) = g.evidence(&a, &b, scan) else {
+                continue;
+            };
+            let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+            if ri != rj {
+                parent[rj] = ri;
+                let moved = std::mem::take(&mut evidence[rj]);
+                evidence[ri].extend(moved);
+            }
+            if !evidence[ri].contains(&why) {
+                evidence[ri].push(why);
+            }
+        }
+    }
+
+    let mut devices: Vec<Device> = Vec::new();
+    let mut at: Vec<Option<usize>> = vec![None; radios.len()];
+    for (i, radio) in radios.iter().enumerate() {
+        let r = root(&mut parent, i);
+        let d = *at[r].get_or_insert_with(|| {
+            devices.push(Device {
+                radios: Vec::new(),
+                evidence: std::mem::take(&mut evidence[r]),
+            });
+            devices.len() - 1
+        });
+        devices[d].radios.push((*radio).clone());
+    }
+    let signal = |r: &BssRow| r.signal_dbm.unwrap_or(f32::NEG_INFINITY);
+    for d in &mut devices {
+        d.radios.sort_by(|a, b| {
+            let band = |r: &BssRow| r.freq_mhz.map_or(u8::MAX, freq_band);
+            band(a).cmp(&band(b)).then(signal(b).total_cmp(&signal(a)))
+        });
+    }
+    devices.sort_by(|a, b| {
+        let best = |d: &Device| d.best_dbm().unwrap_or(f32::NEG_INFINITY);
+        best(b).total_cmp(&best(a)).then_with(|| a.bssids().cmp(&b.bssids()))
+    });
+    devices
+}
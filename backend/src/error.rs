@@ -0,0 +1,102 @@
+// src/error.rs
+//
+// Failures callers handle differently, typed so Python gets a distinct
+// exception class for each (see map_pyerr in lib.rs) instead of a bare
+// RuntimeError. They travel as anyhow errors like everything else
+// (bail!(WifiError::ScanTimeout)), so added context doesn't hide them;
+// kind_of() finds one anywhere in the chain. Kernel error replies are
+// NetlinkError and classified by errno (EPERM is PermissionDenied, ...),
+// as are OS permission errors raised untyped deep in the netlink code.
+//
+// Exposes:
+//   - WifiError, ErrorKind
+//   - kind_of(&anyhow::Error) -> Option<ErrorKind>
+
+use crate::netlink::NlError;
+
+#[derive(Debug)]
+pub enum WifiError {
+    /// No (matching) Wi-Fi interface, or it went away (ENODEV).
+    NoInterface(String),
+    /// Not allowed to do this (EPERM / EACCES, SELinux, no CAP_NET_ADMIN).
+    PermissionDenied(String),
+    /// The scan didn't finish in time.
+    ScanTimeout,
+    /// The driver or supplicant gave up on the scan.
+    ScanAborted(String),
+    /// Any other error reply from the kernel.
+    NetlinkError(NlError),
+    /// The driver, backend or build can't do this (EOPNOTSUPP).
+    Unsupported(String),
+}
+
+impl std::fmt::Display for WifiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WifiError::NoInterface(m)
+            | WifiError::PermissionDenied(m)
+            | WifiError::ScanAborted(m)
+            | WifiError::Unsupported(m) => f.write_str(m),
+            WifiError::ScanTimeout => f.write_str("scan timeout"),
+            WifiError::NetlinkError(e) => e.fmt(f),
+        }
+    }
+}
+
+// The NlError stays in the chain, so netlink::errno_of() sees through.
+impl std::error::Error for WifiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WifiError::NetlinkError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// WifiError without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NoInterface,
+    PermissionDenied,
+    ScanTimeout,
+    ScanAborted,
+    NetlinkError,
+    Unsupported,
+}
+
+impl WifiError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            WifiError::NoInterface(_) => ErrorKind::NoInterface,
+            WifiError::PermissionDenied(_) => ErrorKind::PermissionDenied,
+            WifiError::ScanTimeout => ErrorKind::ScanTimeout,
+            WifiError::ScanAborted(_) => ErrorKind::ScanAborted,
+            WifiError::NetlinkError(e) => errno_kind(e.errno),
+            WifiError::Unsupported(_) => ErrorKind::Unsupported,
+        }
+    }
+}
+
+fn errno_kind(errno: i32) -> ErrorKind {
+    match errno {
+        libc::EPERM | libc::EACCES => ErrorKind::PermissionDenied,
+        libc::ENODEV => ErrorKind::NoInterface,
+        libc::EOPNOTSUPP => ErrorKind::Unsupported,
+        _ => ErrorKind::NetlinkError,
+    }
+}
+
+/// What `e` amounts to: the first WifiError, kernel error reply or OS
+/// permission error in its chain. None for everything else (bad
+/// arguments, cancellation, ...).
+pub fn kind_of(e: &anyhow::Error) -> Option<ErrorKind> {
+    e.chain().find_map(|c| {
+        if let Some(w) = c.downcast_ref::<WifiError>() {
+            Some(w.kind())
+        } else {
+            c.downcast_ref::<std::io::Error>()
+                .filter(|io| io.kind() == std::io::ErrorKind::PermissionDenied)
+                .map(|_| ErrorKind::PermissionDenied)
+        }
+    })
+}
@@ -0,0 +1,145 @@
+// src/ess.rs
+//
+// Scan rows grouped by network (ESS, one SSID): its BSSIDs, the bands it
+// uses with the strongest signal on each, and whether it looks like a
+// multi-AP mesh rather than a single router. Every consumer of the flat
+// scan list otherwise rebuilds this.
+//
+// Networks are told apart by their SSID bytes, so names that only differ
+// in bytes the display string can't show stay apart. Hidden BSSs have no
+// name to group by and are left out.
+//
+// Exposes:
+//   - group(rows) -> Vec<Ess>
+//   - Ess, EssBand
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ies;
+use crate::lib_rust::{freq_band, ie_list, same_device, BssRow};
+
+/// An ESS on one band.
+#[derive(Debug, Clone)]
+pub struct EssBand {
+    /// freq_band() of its BSSs.
+    pub band: u8,
+    pub bssids: usize,
+    pub best_dbm: Option<f32>,
+    /// Channels its BSSs are on, ascending.
+    pub channels: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ess {
+    pub ssid: Arc<str>,
+    pub ssid_bytes: Vec<u8>,
+    /// Strongest first.
+    pub bssids: Vec<[u8; 6]>,
+    /// 2.4 GHz first.
+    pub bands: Vec<EssBand>,
+    pub best_dbm: Option<f32>,
+    /// Devices serving it: its BSSIDs grouped by same_device() and shared
+    /// MLD address.
+    pub devices: usize,
+    /// Served by more than one device, or advertising EasyMesh (the
+    /// Multi-AP element). Two neighbours who picked the same name look
+    /// the same.
+    pub mesh: bool,
+}
+
+/// The named networks in `rows`, strongest first. A BSSID on several rows
+/// (scan_merged(), one per radio) counts once, with its strongest.
+pub fn group(rows: &[BssRow]) -> Vec<Ess> {
+    let mut by_ssid: HashMap<&[u8], Vec<&BssRow>> = HashMap::new();
+    for r in rows {
+        if r.hidden || r.ssid_bytes.is_empty() || r.bssid.is_none() {
+            continue;
+        }
+        let members = by_ssid.entry(&r.ssid_bytes).or_default();
+        match members.iter_mut().find(|m| m.bssid == r.bssid) {
+            Some(kept) => {
+                if signal(r) > signal(kept) {
+                    *kept = r;
+                }
+            }
+            None => members.push(r),
+        }
+    }
+    let mut out: Vec<Ess> = by_ssid.into_values().map(ess_of).collect();
+    out.sort_by(|a, b| {
+        let best = |e: &Ess| e.best_dbm.unwrap_or(f32::NEG_INFINITY);
+        best(b).total_cmp(&best(a)).then_with(|| a.ssid_bytes.cmp(&b.ssid_bytes))
+    });
+    out
+}
+
+fn ess_of(mut members: Vec<&BssRow>) -> Ess {
+    members.sort_by(|a, b| signal(b).total_cmp(&signal(a)).then(a.bssid.cmp(&b.bssid)));
+    let mut bands: Vec<EssBand> = Vec::new();
+    for r in &members {
+        let Some(band) = r.freq_mhz.map(freq_band) else {
+            continue;
+        };
+        let i = match bands.iter().position(|b| b.band == band) {
+            Some(i) => i,
+            None => {
+                bands.push(EssBand {
+                    band,
+                    bssids: 0,
+                    best_dbm: None,
+                    channels: Vec::new(),
+                });
+                bands.len() - 1
+            }
+        };
+        let b = &mut bands[i];
+        b.bssids += 1;
+        // Strongest first, so the first signal is the best.
+        b.best_dbm = b.best_dbm.or(r.signal_dbm);
+        if let Some(ch) = r.channel.filter(|c| !b.channels.contains(c)) {
+            b.channels.push(ch);
+        }
+    }
+    for b in &mut bands {
+        b.channels.sort_unstable();
+    }
+    bands.sort_by_key(|b| b.band);
+
+    let devices = device_count(&members);
+    let easy_mesh = members
+        .iter()
+        .any(|r| r.ies.as_deref().is_some_and(|ies| ies::has_multi_ap(&ie_list(ies))));
+    Ess {
+        ssid: members[0].ssid.clone().unwrap_or_default(),
+        ssid_bytes: members[0].ssid_bytes.clone(),
+        bssids: members.iter().filter_map(|r| r.bssid).collect(),
+        bands,
+        best_dbm: members[0].signal_dbm,
+        devices,
+        mesh: devices > 1 || easy_mesh,
+    }
+}
+
+// A BSSID joins the first device one of whose radios shares its MLD
+// address or same_device() pairs it with.
+fn device_count(members: &[&BssRow]) -> usize {
+    let mut devices: Vec<Vec<&BssRow>> = Vec::new();
+    for &r in members {
+        let (Some(bssid), mld) = (r.bssid, r.mld_addr()) else {
+            continue;
+        };
+        let sibling = |o: &&BssRow| {
+            (mld.is_some() && o.mld_addr() == mld) || o.bssid.is_some_and(|b| same_device(&b, &bssid))
+        };
+        match devices.iter_mut().find(|d| d.iter().any(sibling)) {
+            Some(d) => d.push(r),
+            None => devices.push(vec![r]),
+        }
+    }
+    devices.len()
+}
+
+fn signal(r: &BssRow) -> f32 {
+    r.signal_dbm.unwrap_or(f32::NEG_INFINITY)
+}
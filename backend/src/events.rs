@@ -0,0 +1,236 @@
+// src/events.rs
+//
+// nl80211 multicast events (the "scan", "mlme", "regulatory" and "config"
+// groups the shared event socket joins) as typed WifiEvents, so a roam or
+// disconnect is noticed when it happens instead of at the next poll. Only
+// the events below are passed on; the rest of the traffic on those groups
+// (auth / assoc frames, new stations, ...) is skipped.
+//
+// Each Subscription sees every event from when it was opened. Under
+// Backend::Mock the fixture's events come first, then a ScanFinished for
+// every mock scan.
+//
+// Exposes:
+//   - WifiEvent { at, ifindex, kind: EventKind }, EventKind::name()
+//   - Subscription::open(backend) / next(timeout) -> Result<Option<WifiEvent>>
+//   - watch(backend, f) -> Result<u64>: f called with every event on a
+//     thread of its own, until unwatch(id) / unwatch_all()
+
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::lib_rust::{vec_to_mac, Backend};
+use crate::netlink::{block_on, msg_ifindex, Genl, Nl80211};
+use crate::nl_raw::{Attr, Cmd};
+use crate::stamp::Stamp;
+
+// How often watcher threads check whether they were stopped.
+const WATCH_POLL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    ScanStarted,
+    ScanFinished,
+    ScanAborted,
+    /// Association finished; `status` is the IEEE 802.11 status code, 0 on
+    /// success.
+    Connected { bssid: Option<[u8; 6]>, status: u16 },
+    /// `reason` is the IEEE 802.11 reason code; `by_ap` when the AP (not
+    /// this side) ended it.
+    Disconnected { reason: Option<u16>, by_ap: bool },
+    /// Moved to another AP of the same network.
+    Roamed { bssid: Option<[u8; 6]> },
+    /// The AP moved the BSS to another channel.
+    ChannelSwitch { freq_mhz: Option<u32> },
+    /// The regulatory domain changed.
+    RegChange,
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::ScanStarted => "scan_started",
+            EventKind::ScanFinished => "scan_finished",
+            EventKind::ScanAborted => "scan_aborted",
+            EventKind::Connected { .. } => "connected",
+            EventKind::Disconnected { .. } => "disconnected",
+            EventKind::Roamed { .. } => "roamed",
+            EventKind::ChannelSwitch { .. } => "channel_switch",
+            EventKind::RegChange => "reg_change",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WifiEvent {
+    pub at: Stamp,
+    /// The interface it happened on; None for wiphy-wide events.
+    pub ifindex: Option<u32>,
+    pub kind: EventKind,
+}
+
+/// The WifiEvent an nl80211 multicast message stands for, if any.
+pub fn from_genl(genl: &Genl) -> Option<WifiEvent> {
+    let attrs = genl.get_attr_handle();
+    let mac = || attrs.get_attribute(Attr::AttrMac).and_then(|a| vec_to_mac(a.nla_payload.as_ref()));
+    let u16_of = |attr| attrs.get_attr_payload_as::<u16>(attr).ok();
+    let kind = match genl.cmd {
+        Cmd::CmdTriggerScan => EventKind::ScanStarted,
+        Cmd::CmdNewScanResults => EventKind::ScanFinished,
+        Cmd::CmdScanAborted => EventKind::ScanAborted,
+        Cmd::CmdConnect => EventKind::Connected {
+            bssid: mac(),
+            // Absent on success with some drivers.
+            status: u16_of(Attr::AttrStatusCode).unwrap_or(0),
+        },
+        Cmd::CmdDisconnect => EventKind::Disconnected {
+            reason: u16_of(Attr::AttrReasonCode),
+            // A flag attribute: present or not.
+            by_ap: attrs.get_attribute(Attr::AttrDisconnectedByAp).is_some(),
+        },
+        Cmd::CmdRoam => EventKind::Roamed { bssid: mac() },
+        Cmd::CmdChSwitchNotify => EventKind::ChannelSwitch {
+            freq_mhz: attrs.get_attr_payload_as::<u32>(Attr::AttrWiphyFreq).ok(),
+        },
+        Cmd::CmdRegChange => EventKind::RegChange,
+        _ => return None,
+    };
+    Some(WifiEvent {
+        at: Stamp::now(),
+        ifindex: msg_ifindex(genl),
+        kind,
+    })
+}
+
+enum Source {
+    Nl(broadcast::Receiver<Arc<Genl>>),
+    Mock(broadcast::Receiver<WifiEvent>),
+}
+
+/// A stream of events from when it was opened.
+pub struct Subscription {
+    source: Source,
+    // Fixture events not handed out yet.
+    pending: VecDeque<WifiEvent>,
+    /// Events dropped because this subscriber fell too far behind.
+    pub lost: u64,
+}
+
+impl Subscription {
+    pub fn open(b: Backend) -> Result<Subscription> {
+        b.provider().events()
+    }
+
+    /// nl80211's events, decoded.
+    pub fn netlink() -> Result<Subscription> {
+        Ok(Subscription {
+            source: Source::Nl(Nl80211::shared()?.subscribe()),
+            pending: VecDeque::new(),
+            lost: 0,
+        })
+    }
+
+    /// `pending` first, then what comes in on `rx`.
+    pub fn channel(pending: Vec<WifiEvent>, rx: broadcast::Receiver<WifiEvent>) -> Subscription {
+        Subscription {
+            source: Source::Mock(rx),
+            pending: pending.into(),
+            lost: 0,
+        }
+    }
+
+    /// The next event, waiting up to `timeout` (forever if None); Ok(None)
+    /// if none came in time.
+    pub fn next(&mut self, timeout: Option<Duration>) -> Result<Option<WifiEvent>> {
+        if let Some(ev) = self.pending.pop_front() {
+            return Ok(Some(ev));
+        }
+        block_on(async {
+            let recv = self.recv();
+            match timeout {
+                Some(t) => Ok(tokio::time::timeout(t, recv).await.ok().transpose()?),
+                None => recv.await.map(Some),
+            }
+        })
+    }
+
+    async fn recv(&mut self) -> Result<WifiEvent> {
+        loop {
+            let res = match &mut self.source {
+                Source::Nl(rx) => rx.recv().await.map(|genl| from_genl(&genl)),
+                Source::Mock(rx) => rx.recv().await.map(Some),
+            };
+            match res {
+                Ok(Some(ev)) => return Ok(ev),
+                Ok(None) => {}
+                Err(RecvError::Lagged(n)) => self.lost += n,
+                Err(RecvError::Closed) => bail!("event source closed"),
+            }
+        }
+    }
+}
+
+struct Watcher {
+    id: u64,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+static WATCHERS: Mutex<Vec<Watcher>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn lock() -> std::sync::MutexGuard<'static, Vec<Watcher>> {
+    WATCHERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Call `f` with every event from now on, on a thread of its own. Returns
+/// the id to unwatch() with. The thread ends if the event source does.
+pub fn watch(b: Backend, mut f: impl FnMut(WifiEvent) + Send + 'static) -> Result<u64> {
+    let mut sub = Subscription::open(b)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop = stop.clone();
+        std::thread::Builder::new().name("wifi-events".into()).spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                match sub.next(Some(WATCH_POLL)) {
+                    Ok(Some(ev)) => f(ev),
+                    Ok(None) => {}
+                    Err(_) => break,
+                }
+            }
+        })?
+    };
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    lock().push(Watcher { id, stop, thread });
+    Ok(id)
+}
+
+/// Stop the watch() `id`, waiting for its thread to end; false if there is
+/// no such watch.
+pub fn unwatch(id: u64) -> bool {
+    let w = {
+        let mut ws = lock();
+        let Some(i) = ws.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        ws.remove(i)
+    };
+    w.stop.store(true, Ordering::Relaxed);
+    let _ = w.thread.join();
+    true
+}
+
+pub fn unwatch_all() {
+    let ws = std::mem::take(&mut *lock());
+    for w in &ws {
+        w.stop.store(true, Ordering::Relaxed);
+    }
+    for w in ws {
+        let _ = w.thread.join();
+    }
+}
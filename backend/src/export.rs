@@ -0,0 +1,373 @@
+// src/export.rs
+//
+// Scans as documents for other programs (the desktop tool reads them
+// without going through Python dicts). The JSON document is versioned:
+//
+//   {
+//     "schema": 1,
+//     "generator": "wifi_backend/<version>",
+//     "taken_at": {wall, mono},
+//     "connected_bssid": "aa:bb:cc:dd:ee:ff" | null,
+//     "rows": [{ssid, ssid_bytes, hidden, bssid, freq_mhz, signal_dbm,
+//               channel, seen, age_ms, cached, capability, beacon_interval,
+//               ies, iface, status, band, security, width_mhz, wifi_gen,
+//               generation, max_phy_rate_mbps, estimated_throughput_mbps},
+//              ...]
+//   }
+//
+// A row is BssRow's serde form (MACs as text, `ies` as hex, stamps as
+// {wall, mono} like Stamp) plus what is derived from it, for readers that
+// don't parse IEs: band, security, width_mhz, wifi_gen, generation
+// ("legacy", "wifi4" ... "wifi6e", "wifi7"), max_phy_rate_mbps and
+// estimated_throughput_mbps. Those are ignored on import and worked out
+// from the IEs again. Fields are only ever added within a schema version;
+// renaming or removing one bumps it.
+//
+// The CSV is for spreadsheets: one line per BSS with SSID, BSSID, band,
+// channel, width, RSSI, security and when it was last seen (UTC), UTF-8
+// with a byte order mark so Excel doesn't guess the encoding. SSIDs are
+// arbitrary bytes from the air, so control characters become \xNN (and
+// a backslash \\), a leading = + - @ gets a ' so it isn't run as a
+// formula, and the field is quoted when it holds a comma, quote or
+// outer space.
+//
+// The WiGLE CSV is their upload format 1.4: a "WigleWifi-1.4,..." line,
+// the column names, then MAC, SSID, AuthMode (Android's capabilities
+// string, BssRow::auth_mode()), FirstSeen (UTC), Channel, RSSI, the
+// position given for the whole scan and Type "WIFI". WiGLE reads SSIDs
+// verbatim, so they are only quoted, with control characters as spaces
+// since its parser goes by lines.
+//
+// Exposes:
+//   - SCHEMA_VERSION, ScanDocument
+//   - to_json(snapshot, pretty) -> Result<String>
+//   - from_json(text) -> Result<ScanSnapshot>
+//   - to_csv(snapshot) -> String, write_csv(snapshot, path) -> Result<usize>
+//   - Position, to_wigle_csv(snapshot, pos) -> String,
+//     write_wigle_csv(snapshot, pos, path) -> Result<usize>
+//   - serde helpers for BssRow: mac, ies_hex
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::lib_rust::{band_name, format_mac, freq_band, freq_to_channel, BssRow, ScanSnapshot};
+use crate::stamp::Stamp;
+
+/// Bumped when a field of the document changes meaning or goes away.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanDocument {
+    pub schema: u32,
+    #[serde(default)]
+    pub generator: String,
+    pub taken_at: Stamp,
+    #[serde(default, with = "mac")]
+    pub connected_bssid: Option<[u8; 6]>,
+    pub rows: Vec<RowDoc>,
+}
+
+/// A row and what is derived from it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RowDoc {
+    #[serde(flatten)]
+    pub row: BssRow,
+    #[serde(flatten, skip_deserializing)]
+    pub derived: Derived,
+}
+
+/// Worked out from a row, for the document's readers; not read back.
+#[derive(Debug, Default, Serialize)]
+pub struct Derived {
+    pub band: Option<&'static str>,
+    pub security: &'static str,
+    pub width_mhz: Option<u32>,
+    pub wifi_gen: Option<u8>,
+    pub generation: Option<&'static str>,
+    pub max_phy_rate_mbps: Option<f32>,
+    pub estimated_throughput_mbps: Option<f32>,
+}
+
+impl Derived {
+    fn of(row: &BssRow) -> Derived {
+        Derived {
+            band: row.freq_mhz.map(|f| band_name(freq_band(f))),
+            security: row.security().name(),
+            width_mhz: row.channel_width(),
+            wifi_gen: row.wifi_generation(),
+            generation: row.generation().map(|g| g.name()),
+            max_phy_rate_mbps: row.max_phy_rate_mbps(),
+            estimated_throughput_mbps: row.estimated_throughput_mbps(),
+        }
+    }
+}
+
+impl ScanDocument {
+    pub fn of(snap: &ScanSnapshot) -> ScanDocument {
+        ScanDocument {
+            schema: SCHEMA_VERSION,
+            generator: concat!("wifi_backend/", env!("CARGO_PKG_VERSION")).into(),
+            taken_at: snap.at,
+            connected_bssid: snap.connected,
+            rows: snap
+                .rows
+                .iter()
+                .map(|r| RowDoc {
+                    row: r.clone(),
+                    derived: Derived::of(r),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `snap` as a JSON document (format above).
+pub fn to_json(snap: &ScanSnapshot, pretty: bool) -> Result<String> {
+    let doc = ScanDocument::of(snap);
+    Ok(if pretty {
+        serde_json::to_string_pretty(&doc)?
+    } else {
+        serde_json::to_string(&doc)?
+    })
+}
+
+/// The snapshot in a to_json() document. Its stamps keep their wall time;
+/// the monotonic side is worked out again, since the exporter's monotonic
+/// clock means nothing here.
+pub fn from_json(text: &str) -> Result<ScanSnapshot> {
+    let doc: ScanDocument = serde_json::from_str(text).context("scan document")?;
+    if doc.schema > SCHEMA_VERSION {
+        bail!(
+            "scan document has schema {}; this wifi_backend reads up to {SCHEMA_VERSION}",
+            doc.schema
+        );
+    }
+    let rows = doc
+        .rows
+        .into_iter()
+        .map(|d| {
+            let mut row = d.row;
+            row.channel = row.channel.or_else(|| row.freq_mhz.and_then(freq_to_channel));
+            row.seen = row.seen.map(|s| Stamp::from_wall(s.wall));
+            // Documents from before ssid_bytes only have the text.
+            if row.ssid_bytes.is_empty() && !row.hidden {
+                if let Some(ssid) = row.ssid.clone() {
+                    row.ssid_bytes = ssid.as_bytes().to_vec();
+                }
+            }
+            row
+        })
+        .collect();
+    Ok(ScanSnapshot {
+        rows: Arc::new(rows),
+        connected: doc.connected_bssid,
+        at: Stamp::from_wall(doc.taken_at.wall),
+    })
+}
+
+const CSV_HEADER: &str = "SSID,BSSID,Band,Channel,Width (MHz),RSSI (dBm),Security,Last seen (UTC)";
+
+/// `snap` as CSV (format above), strongest first like scan results are
+/// usually read.
+pub fn to_csv(snap: &ScanSnapshot) -> String {
+    let mut rows: Vec<&BssRow> = snap.rows.iter().collect();
+    rows.sort_by(|a, b| b.signal_dbm.unwrap_or(f32::MIN).total_cmp(&a.signal_dbm.unwrap_or(f32::MIN)));
+
+    let mut out = String::from("\u{feff}");
+    out.push_str(CSV_HEADER);
+    out.push_str("\r\n");
+    for r in rows {
+        let opt = |v: Option<String>| v.unwrap_or_default();
+        let _ = write!(
+            out,
+            "{},{},{},{},{},{},{},{}\r\n",
+            csv_text(r.ssid.as_deref().unwrap_or("")),
+            opt(r.bssid.as_ref().map(format_mac)),
+            r.freq_mhz.map_or("", |f| band_name(freq_band(f))),
+            opt(r.channel.map(|c| c.to_string())),
+            opt(r.channel_width().map(|w| w.to_string())),
+            opt(r.signal_dbm.map(|s| format!("{s:.0}"))),
+            r.security().name(),
+            opt(r.seen.map(|s| s.utc())),
+        );
+    }
+    out
+}
+
+/// to_csv() into `path`; returns the number of BSSs.
+pub fn write_csv(snap: &ScanSnapshot, path: &Path) -> Result<usize> {
+    std::fs::write(path, to_csv(snap)).with_context(|| format!("write {}", path.display()))?;
+    Ok(snap.rows.len())
+}
+
+// Free text (an SSID) as one CSV field, escaped as described above.
+fn csv_text(s: &str) -> Cow<'_, str> {
+    let plain = |c: char| !c.is_control() && !matches!(c, '\\' | ',' | '"');
+    let formula = s.starts_with(['=', '+', '-', '@']);
+    if s.chars().all(plain) && !formula && s.trim() == s {
+        return Cow::Borrowed(s);
+    }
+    let mut text = String::with_capacity(s.len() + 8);
+    if formula {
+        text.push('\'');
+    }
+    for c in s.chars() {
+        match c {
+            '\\' => text.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(text, "\\x{:02x}", c as u32);
+            }
+            c => text.push(c),
+        }
+    }
+    if text.contains([',', '"']) || text.trim() != text {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Owned(text)
+    }
+}
+
+/// Where a scan was taken, for WiGLE.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Position {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt_m: Option<f64>,
+    pub accuracy_m: Option<f64>,
+}
+
+const WIGLE_HEADER: &str =
+    "MAC,SSID,AuthMode,FirstSeen,Channel,RSSI,CurrentLatitude,CurrentLongitude,AltitudeMeters,AccuracyMeters,Type";
+
+/// `snap`, heard at `pos`, in WiGLE's CSV upload format (see above). Rows
+/// without a BSSID are left out; FirstSeen is when the BSS was last heard,
+/// else when the scan was taken.
+pub fn to_wigle_csv(snap: &ScanSnapshot, pos: &Position) -> String {
+    let mut out = format!(
+        "WigleWifi-1.4,appRelease={},model={},release={},device=wifi_backend,display=,board=,brand=\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+    );
+    out.push_str(WIGLE_HEADER);
+    out.push('\n');
+    for r in snap.rows.iter() {
+        let Some(mac) = r.bssid else { continue };
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{:.7},{:.7},{:.1},{:.1},WIFI",
+            format_mac(&mac),
+            wigle_text(r.ssid.as_deref().unwrap_or("")),
+            r.auth_mode(),
+            r.seen.unwrap_or(snap.at).utc(),
+            r.channel.map(|c| c.to_string()).unwrap_or_default(),
+            r.signal_dbm.map_or(-100, |s| s.round() as i32),
+            pos.lat,
+            pos.lon,
+            pos.alt_m.unwrap_or(0.0),
+            pos.accuracy_m.unwrap_or(0.0),
+        );
+    }
+    out
+}
+
+/// to_wigle_csv() into `path`; returns the number of BSSs written.
+pub fn write_wigle_csv(snap: &ScanSnapshot, pos: &Position, path: &Path) -> Result<usize> {
+    std::fs::write(path, to_wigle_csv(snap, pos)).with_context(|| format!("write {}", path.display()))?;
+    Ok(snap.rows.iter().filter(|r| r.bssid.is_some()).count())
+}
+
+// An SSID for WiGLE: verbatim but for control characters, quoted when it
+// needs to be.
+fn wigle_text(s: &str) -> Cow<'_, str> {
+    let s: Cow<str> = if s.contains(char::is_control) {
+        Cow::Owned(s.chars().map(|c| if c.is_control() { ' ' } else { c }).collect())
+    } else {
+        Cow::Borrowed(s)
+    };
+    if s.contains([',', '"']) || s.trim() != s {
+        Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+    } else {
+        s
+    }
+}
+
+/// serde for Option<[u8; 6]> as "aa:bb:cc:dd:ee:ff".
+pub mod mac {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::lib_rust::{format_mac, parse_mac};
+
+    pub fn serialize<S: Serializer>(mac: &Option<[u8; 6]>, s: S) -> Result<S::Ok, S::Error> {
+        match mac {
+            Some(m) => s.serialize_str(&format_mac(m)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<[u8; 6]>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|s| parse_mac(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// serde for the IE blob as lowercase hex.
+pub mod ies_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::sync::Arc;
+
+    pub fn serialize<S: Serializer>(ies: &Option<Arc<[u8]>>, s: S) -> Result<S::Ok, S::Error> {
+        match ies {
+            Some(ies) => s.serialize_str(&super::to_hex(ies)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Arc<[u8]>>, D::Error> {
+        let Some(text) = Option::<String>::deserialize(d)? else {
+            return Ok(None);
+        };
+        super::from_hex("ies", &text).map(|v| Some(Arc::from(v))).map_err(D::Error::custom)
+    }
+}
+
+/// serde for the raw SSID as lowercase hex, "" when there is none.
+pub mod ssid_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ssid: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&super::to_hex(ssid))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let text = Option::<String>::deserialize(d)?.unwrap_or_default();
+        super::from_hex("ssid_bytes", &text).map_err(D::Error::custom)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        let _ = write!(text, "{b:02x}");
+    }
+    text
+}
+
+fn from_hex(what: &str, text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err(format!("{what}: odd number of hex digits"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("{what}: bad hex at {i}"))
+        })
+        .collect()
+}
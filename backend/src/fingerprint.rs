@@ -0,0 +1,318 @@
+// src/fingerprint.rs
+//
+// Recognise neighbouring whole-home mesh systems (eero, Orbi, Deco, UniFi,
+// Google/Nest Wifi, generic EasyMesh) in a scan and group their radios into
+// one system each, so six BSSIDs from one tri-band Orbi read as one network
+// rather than six.
+//
+// Evidence, per device (radios grouped with same_device, plus the links of
+// a Wi-Fi 7 multi-link AP, which share an MLD address):
+//   - OUI of the BSSID (globally administered addresses only)
+//   - OUIs of the vendor-specific IEs, and the Wi-Fi Alliance Multi-AP IE
+//   - BSSID patterns: several devices sharing an SSID, or one device
+//     running radios on three or more channels (tri-band)
+//   - beacon fingerprints (apmodel.rs): devices whose radios on the same
+//     band have different IE layouts are not the same system
+//
+// Vendor names come from the OUI database when it is installed, with a
+// short built-in table for the vendors we care about otherwise. Rows handed
+// back from Python have no IEs, so only the OUI and pattern evidence apply.
+
+use std::collections::BTreeSet;
+
+use crate::ies::{has_multi_ap, vendor_ouis};
+use crate::lib_rust::{freq_band, ie_list, same_device, BssRow};
+use crate::oui;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MeshKind {
+    Eero,
+    Orbi,
+    Deco,
+    UniFi,
+    GoogleWifi,
+    /// Multi-AP IE but no vendor we know.
+    EasyMesh,
+    Unknown,
+}
+
+impl MeshKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MeshKind::Eero => "eero",
+            MeshKind::Orbi => "orbi",
+            MeshKind::Deco => "deco",
+            MeshKind::UniFi => "unifi",
+            MeshKind::GoogleWifi => "google_wifi",
+            MeshKind::EasyMesh => "easymesh",
+            MeshKind::Unknown => "unknown",
+        }
+    }
+}
+
+// Used when no OUI database is installed.
+const KNOWN_OUIS: &[([u8; 3], MeshKind)] = &[
+    ([0x00, 0x14, 0x6c], MeshKind::Orbi),
+    ([0x00, 0x15, 0x6d], MeshKind::UniFi),
+    ([0x00, 0x1a, 0x11], MeshKind::GoogleWifi),
+    ([0x00, 0x1d, 0x0f], MeshKind::Deco),
+    ([0x00, 0x27, 0x22], MeshKind::UniFi),
+    ([0xf8, 0xbb, 0xbf], MeshKind::Eero),
+];
+
+fn kind_of_vendor(name: &str) -> Option<MeshKind> {
+    let name = name.to_ascii_lowercase();
+    [
+        ("eero", MeshKind::Eero),
+        ("netgear", MeshKind::Orbi),
+        ("tp-link", MeshKind::Deco),
+        ("tp link", MeshKind::Deco),
+        ("ubiquiti", MeshKind::UniFi),
+        ("google", MeshKind::GoogleWifi),
+    ]
+    .into_iter()
+    .find(|(k, _)| name.contains(k))
+    .map(|(_, kind)| kind)
+}
+
+// Mesh vendor behind an OUI, with the name to show as evidence.
+fn kind_of_oui(o: [u8; 3]) -> Option<(MeshKind, String)> {
+    if let Some(name) = oui::vendor(&[o[0], o[1], o[2], 0, 0, 0]) {
+        return kind_of_vendor(&name).map(|k| (k, name));
+    }
+    KNOWN_OUIS
+        .iter()
+        .find(|(k, _)| *k == o)
+        .map(|&(_, kind)| (kind, kind.name().to_owned()))
+}
+
+/// One radio of a neighbouring system.
+#[derive(Debug, Clone)]
+pub struct Radio {
+    pub bssid: [u8; 6],
+    pub ssid: Option<String>,
+    pub freq_mhz: Option<u32>,
+    pub channel: Option<u32>,
+    pub signal_dbm: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshSystem {
+    pub kind: MeshKind,
+    /// Non-empty SSIDs broadcast by any of its radios.
+    pub ssids: Vec<String>,
+    /// Radios grouped per device (node).
+    pub devices: Vec<Vec<Radio>>,
+    /// Human-readable reasons, e.g. "oui:NETGEAR", "tri_band".
+    pub evidence: Vec<String>,
+    pub strongest_dbm: Option<f32>,
+}
+
+#[derive(Default)]
+struct Device {
+    radios: Vec<Radio>,
+    votes: Vec<MeshKind>,
+    evidence: BTreeSet<String>,
+    multi_ap: bool,
+    /// (band, fingerprint) of each radio that had IEs.
+    fps: BTreeSet<(u8, u64)>,
+    /// MLD addresses of its multi-link radios.
+    mlds: BTreeSet<[u8; 6]>,
+}
+
+impl Device {
+    fn kind(&self) -> Option<MeshKind> {
+        majority(&self.votes)
+    }
+
+    fn tri_band(&self) -> bool {
+        let channels: BTreeSet<u32> = self.radios.iter().filter_map(|r| r.channel).collect();
+        let has_24 = self.radios.iter().any(|r| r.freq_mhz.map(freq_band) == Some(1));
+        channels.len() >= 3 && has_24
+    }
+
+    // Same model family, as far as the beacons tell: on every band both
+    // were seen on with IEs, they share a fingerprint.
+    fn same_model(&self, other: &Device) -> bool {
+        let bands: BTreeSet<u8> = self.fps.iter().map(|&(b, _)| b).collect();
+        bands.into_iter().all(|band| {
+            let theirs: Vec<u64> = other.fps.iter().filter(|&&(b, _)| b == band).map(|&(_, f)| f).collect();
+            theirs.is_empty() || self.fps.iter().any(|&(b, f)| b == band && theirs.contains(&f))
+        })
+    }
+}
+
+fn majority(votes: &[MeshKind]) -> Option<MeshKind> {
+    let mut counts: Vec<(MeshKind, usize)> = Vec::new();
+    for &v in votes {
+        match counts.iter_mut().find(|(k, _)| *k == v) {
+            Some(c) => c.1 += 1,
+            None => counts.push((v, 1)),
+        }
+    }
+    counts.into_iter().max_by_key(|&(k, n)| (n, std::cmp::Reverse(k))).map(|(k, _)| k)
+}
+
+/// Group the foreign radios of `rows` into mesh systems. `own` BSSIDs (and
+/// other radios of those devices) are skipped. Devices that don't look
+/// like part of a mesh (one device, not tri-band, no Multi-AP IE) are not
+/// reported. Strongest system first.
+pub fn neighbor_systems(rows: &[BssRow], own: &[[u8; 6]]) -> Vec<MeshSystem> {
+    // Radios per device, with the evidence each radio carries.
+    let mut devices: Vec<Device> = Vec::new();
+    let own_mlds: BTreeSet<[u8; 6]> = rows
+        .iter()
+        .filter(|r| r.bssid.is_some_and(|b| own.contains(&b)))
+        .filter_map(BssRow::mld_addr)
+        .collect();
+    for r in rows {
+        let Some(b) = r.bssid else { continue };
+        let mld = r.mld_addr();
+        if own.iter().any(|o| *o == b || same_device(o, &b)) || mld.is_some_and(|m| own_mlds.contains(&m)) {
+            continue;
+        }
+        let i = match devices.iter().position(|d| {
+            d.radios.iter().any(|x| same_device(&x.bssid, &b)) || mld.is_some_and(|m| d.mlds.contains(&m))
+        }) {
+            Some(i) => i,
+            None => {
+                devices.push(Device::default());
+                devices.len() - 1
+            }
+        };
+        let d = &mut devices[i];
+        if let Some(m) = mld {
+            d.mlds.insert(m);
+            d.evidence.insert("mlo".into());
+        }
+
+        if b[0] & 0x02 == 0 {
+            if let Some((kind, name)) = kind_of_oui([b[0], b[1], b[2]]) {
+                d.votes.push(kind);
+                d.evidence.insert(format!("oui:{name}"));
+            }
+        }
+        if let Some(ies) = r.ies.as_deref() {
+            let ies = ie_list(ies);
+            for o in vendor_ouis(&ies) {
+                if let Some((kind, name)) = kind_of_oui(o) {
+                    d.votes.push(kind);
+                    d.evidence.insert(format!("vendor_ie:{name}"));
+                }
+            }
+            if has_multi_ap(&ies) {
+                d.multi_ap = true;
+                d.evidence.insert("multi_ap".into());
+            }
+            if let (Some(fp), Some(f)) = (r.fingerprint(), r.freq_mhz) {
+                d.fps.insert((freq_band(f), fp));
+            }
+        }
+
+        d.radios.push(Radio {
+            bssid: b,
+            ssid: r.ssid.as_deref().map(str::to_owned),
+            freq_mhz: r.freq_mhz,
+            channel: r.channel,
+            signal_dbm: r.signal_dbm,
+        });
+    }
+
+    // Devices sharing an SSID are one system, unless they are clearly from
+    // different vendors or models.
+    let n = devices.len();
+    let ssids: Vec<BTreeSet<&str>> = devices
+        .iter()
+        .map(|d| {
+            d.radios
+                .iter()
+                .filter_map(|r| r.ssid.as_deref())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .collect();
+    let kinds: Vec<Option<MeshKind>> = devices.iter().map(Device::kind).collect();
+
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..n {
+        for j in i + 1..n {
+            let compatible = match (kinds[i], kinds[j]) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            } && devices[i].same_model(&devices[j]);
+            if compatible && !ssids[i].is_disjoint(&ssids[j]) {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[ri] = rj;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: Vec<Option<usize>> = vec![None; n];
+    for i in 0..n {
+        let r = root(&mut parent, i);
+        match group_of[r] {
+            Some(g) => groups[g].push(i),
+            None => {
+                group_of[r] = Some(groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    for members in groups {
+        let tri_band = members.iter().any(|&i| devices[i].tri_band());
+        let multi_ap = members.iter().any(|&i| devices[i].multi_ap);
+        if members.len() < 2 && !tri_band && !multi_ap {
+            continue;
+        }
+
+        let mut evidence: BTreeSet<String> = BTreeSet::new();
+        let mut votes = Vec::new();
+        let mut names: BTreeSet<String> = BTreeSet::new();
+        for &i in &members {
+            evidence.extend(devices[i].evidence.iter().cloned());
+            votes.extend_from_slice(&devices[i].votes);
+            names.extend(ssids[i].iter().map(|s| s.to_string()));
+        }
+        if members.len() >= 2 {
+            evidence.insert(format!("shared_ssid:{}_nodes", members.len()));
+        }
+        if tri_band {
+            evidence.insert("tri_band".into());
+        }
+
+        let kind = majority(&votes).unwrap_or(if multi_ap { MeshKind::EasyMesh } else { MeshKind::Unknown });
+        let devices: Vec<Vec<Radio>> = members
+            .iter()
+            .map(|&i| devices[i].radios.clone())
+            .collect();
+        let strongest_dbm = devices
+            .iter()
+            .flatten()
+            .filter_map(|r| r.signal_dbm)
+            .max_by(f32::total_cmp);
+
+        out.push(MeshSystem {
+            kind,
+            ssids: names.into_iter().collect(),
+            devices,
+            evidence: evidence.into_iter().collect(),
+            strongest_dbm,
+        });
+    }
+
+    out.sort_by(|a, b| {
+        let s = |m: &MeshSystem| m.strongest_dbm.unwrap_or(f32::MIN);
+        s(b).total_cmp(&s(a))
+    });
+    out
+}
@@ -0,0 +1,299 @@
+// src/geo.rs
+//
+// Location tagging for outdoor / campus surveys. While a position fix is
+// set, every scan that goes through lib_rust is also logged as geotagged
+// observations: one point per BSS, where we were and how loud it was.
+//
+// Exposes:
+//   - set_fix(Fix) / clear_fix() / fix()
+//   - record(rows): called for every scan, a no-op without a fix
+//   - observations() / clear() / len()
+//   - estimate_aps() -> Vec<ApEstimate>: signal-weighted centroid per BSSID
+//   - geojson() / kml(): observation points and estimated AP positions
+//
+// The log is bounded (oldest dropped first) and lives in memory only.
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use crate::ies::Security;
+use crate::lib_rust::{format_mac, BssRow};
+use crate::stamp::Stamp;
+
+// A long walk at one scan every few seconds with ~50 APs in view.
+const MAX_OBSERVATIONS: usize = 200_000;
+
+/// Where we are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    pub lat: f64,
+    pub lon: f64,
+    /// Metres above sea level.
+    pub alt: Option<f64>,
+    /// Horizontal accuracy, metres.
+    pub accuracy_m: Option<f64>,
+    pub at: Stamp,
+}
+
+/// One BSS heard at one place.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub fix: Fix,
+    pub bssid: [u8; 6],
+    pub ssid: Option<Arc<str>>,
+    pub freq_mhz: Option<u32>,
+    pub channel: Option<u32>,
+    pub signal_dbm: Option<f32>,
+    pub security: Security,
+    pub at: Stamp,
+}
+
+/// Estimated position of one BSS from its observations.
+#[derive(Debug, Clone)]
+pub struct ApEstimate {
+    pub bssid: [u8; 6],
+    pub ssid: Option<Arc<str>>,
+    pub channel: Option<u32>,
+    pub lat: f64,
+    pub lon: f64,
+    pub observations: usize,
+    pub best_dbm: Option<f32>,
+}
+
+struct Geo {
+    fix: Option<Fix>,
+    log: VecDeque<Observation>,
+}
+
+static GEO: Mutex<Geo> = Mutex::new(Geo {
+    fix: None,
+    log: VecDeque::new(),
+});
+
+fn lock() -> std::sync::MutexGuard<'static, Geo> {
+    GEO.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Tag the following scans with `fix`.
+pub fn set_fix(fix: Fix) {
+    lock().fix = Some(fix);
+}
+
+/// Stop tagging scans; the log is kept.
+pub fn clear_fix() {
+    lock().fix = None;
+}
+
+pub fn fix() -> Option<Fix> {
+    lock().fix
+}
+
+/// Log `rows` at the current fix, if there is one.
+pub fn record(rows: &[BssRow]) {
+    let mut g = lock();
+    let Some(fix) = g.fix else {
+        return;
+    };
+    let now = Stamp::now();
+    for r in rows {
+        let Some(bssid) = r.bssid else { continue };
+        if g.log.len() >= MAX_OBSERVATIONS {
+            g.log.pop_front();
+        }
+        g.log.push_back(Observation {
+            fix,
+            bssid,
+            ssid: r.ssid.clone(),
+            freq_mhz: r.freq_mhz,
+            channel: r.channel,
+            signal_dbm: r.signal_dbm,
+            security: r.security(),
+            at: r.seen.unwrap_or(now),
+        });
+    }
+}
+
+/// Everything logged so far, oldest first.
+pub fn observations() -> Vec<Observation> {
+    lock().log.iter().cloned().collect()
+}
+
+pub fn len() -> usize {
+    lock().log.len()
+}
+
+pub fn clear() {
+    lock().log.clear();
+}
+
+/// Each BSSID's position as the centroid of where it was heard, weighted by
+/// received power in mW so the loud (close) sightings dominate. Sorted by
+/// BSSID.
+pub fn estimate_aps(obs: &[Observation]) -> Vec<ApEstimate> {
+    // (weight sum, weighted lat, weighted lon, estimate)
+    let mut acc: BTreeMap<[u8; 6], (f64, f64, f64, ApEstimate)> = BTreeMap::new();
+    for o in obs {
+        let w = o.signal_dbm.map_or(1e-9, |s| 10f64.powf(s as f64 / 10.0));
+        let e = acc.entry(o.bssid).or_insert_with(|| {
+            (
+                0.0,
+                0.0,
+                0.0,
+                ApEstimate {
+                    bssid: o.bssid,
+                    ssid: None,
+                    channel: None,
+                    lat: 0.0,
+                    lon: 0.0,
+                    observations: 0,
+                    best_dbm: None,
+                },
+            )
+        });
+        e.0 += w;
+        e.1 += w * o.fix.lat;
+        e.2 += w * o.fix.lon;
+        let est = &mut e.3;
+        est.observations += 1;
+        if o.ssid.is_some() {
+            est.ssid.clone_from(&o.ssid);
+        }
+        // Channel of the loudest sighting.
+        if o.signal_dbm > est.best_dbm {
+            est.best_dbm = o.signal_dbm;
+            est.channel = o.channel.or(est.channel);
+        } else if est.channel.is_none() {
+            est.channel = o.channel;
+        }
+    }
+    acc.into_values()
+        .map(|(w, lat, lon, mut est)| {
+            est.lat = lat / w;
+            est.lon = lon / w;
+            est
+        })
+        .collect()
+}
+
+fn coords(lat: f64, lon: f64, alt: Option<f64>) -> Value {
+    match alt {
+        Some(a) => json!([lon, lat, a]),
+        None => json!([lon, lat]),
+    }
+}
+
+/// GeoJSON FeatureCollection of the observations (properties.kind
+/// "observation") and/or the estimated AP positions (kind "ap").
+pub fn geojson(obs: &[Observation], points: bool, aps: bool) -> Value {
+    let mut features = Vec::new();
+    if points {
+        features.extend(obs.iter().map(|o| {
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "Point", "coordinates": coords(o.fix.lat, o.fix.lon, o.fix.alt)},
+                "properties": {
+                    "kind": "observation",
+                    "bssid": format_mac(&o.bssid),
+                    "ssid": o.ssid.as_deref(),
+                    "freq_mhz": o.freq_mhz,
+                    "channel": o.channel,
+                    "signal_dbm": o.signal_dbm,
+                    "security": o.security.name(),
+                    "accuracy_m": o.fix.accuracy_m,
+                    "at": o.at.wall,
+                },
+            })
+        }));
+    }
+    if aps {
+        features.extend(estimate_aps(obs).iter().map(|a| {
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "Point", "coordinates": coords(a.lat, a.lon, None)},
+                "properties": {
+                    "kind": "ap",
+                    "bssid": format_mac(&a.bssid),
+                    "ssid": a.ssid.as_deref(),
+                    "channel": a.channel,
+                    "observations": a.observations,
+                    "best_dbm": a.best_dbm,
+                },
+            })
+        }));
+    }
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn placemark(out: &mut String, name: &str, desc: &str, lat: f64, lon: f64, alt: Option<f64>) {
+    let _ = write!(
+        out,
+        "<Placemark><name>{}</name><description>{}</description><Point><coordinates>{lon},{lat}",
+        xml_escape(name),
+        xml_escape(desc),
+    );
+    if let Some(a) = alt {
+        let _ = write!(out, ",{a}");
+    }
+    out.push_str("</coordinates></Point></Placemark>\n");
+}
+
+/// KML document with an "Access points" folder (estimated positions) and
+/// an "Observations" folder, for Google Earth and friends.
+pub fn kml(obs: &[Observation], points: bool, aps: bool) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\"><Document><name>Wi-Fi survey</name>\n",
+    );
+    let label = |ssid: &Option<Arc<str>>, bssid: &[u8; 6]| match ssid.as_deref() {
+        Some(s) if !s.is_empty() => s.to_owned(),
+        _ => format_mac(bssid),
+    };
+    if aps {
+        out.push_str("<Folder><name>Access points</name>\n");
+        for a in estimate_aps(obs) {
+            let desc = format!(
+                "{} ch {} best {} dBm, {} observations",
+                format_mac(&a.bssid),
+                a.channel.map_or("?".into(), |c| c.to_string()),
+                a.best_dbm.map_or("?".into(), |s| s.to_string()),
+                a.observations,
+            );
+            placemark(&mut out, &label(&a.ssid, &a.bssid), &desc, a.lat, a.lon, None);
+        }
+        out.push_str("</Folder>\n");
+    }
+    if points {
+        out.push_str("<Folder><name>Observations</name>\n");
+        for o in obs {
+            let desc = format!(
+                "{} ch {} {} dBm {}",
+                format_mac(&o.bssid),
+                o.channel.map_or("?".into(), |c| c.to_string()),
+                o.signal_dbm.map_or("?".into(), |s| s.to_string()),
+                o.security.name(),
+            );
+            placemark(&mut out, &label(&o.ssid, &o.bssid), &desc, o.fix.lat, o.fix.lon, o.fix.alt);
+        }
+        out.push_str("</Folder>\n");
+    }
+    out.push_str("</Document></kml>\n");
+    out
+}
@@ -0,0 +1,249 @@
+// src/gpsd.rs
+//
+// gpsd client and wardriving mode. A background thread keeps a connection
+// to gpsd (JSON protocol, ?WATCH) and hands every TPV report with a 2D/3D
+// fix to geo::set_fix(), so scans are geotagged without Python feeding
+// coordinates. When gpsd loses the fix, goes quiet or drops the connection
+// the fix is cleared rather than left stale, and the client reconnects.
+//
+// With `scan_every` set it also drives the survey itself: one scan per
+// interval while there is a fix (a walk / drive survey), feeding the
+// GeoJSON / KML exports and the WiGLE CSV.
+//
+// Exposes:
+//   - start(Config) -> Result<()> / stop()
+//   - status() -> GpsStatus
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::geo::{self, Fix};
+use crate::lib_rust::scan_all_bss;
+use crate::stamp::Stamp;
+
+// gpsd reports about once a second; this long without a TPV means the
+// fix can't be trusted any more.
+const FIX_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+// How often the threads look at the stop flag.
+const TICK: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    /// Scan this often while there is a fix; None leaves scanning to the
+    /// caller.
+    pub scan_every: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GpsStatus {
+    pub running: bool,
+    pub connected: bool,
+    pub fix: Option<Fix>,
+    /// gpsd's mode: 0/1 no fix, 2 = 2D, 3 = 3D.
+    pub mode: u8,
+    pub scans: u64,
+    pub scan_errors: u64,
+    pub last_error: Option<String>,
+}
+
+struct Client {
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+    status: Arc<Mutex<GpsStatus>>,
+}
+
+static CLIENT: Mutex<Option<Client>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Client>> {
+    CLIENT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn lock_status(s: &Mutex<GpsStatus>) -> std::sync::MutexGuard<'_, GpsStatus> {
+    s.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Connect to gpsd (and start scanning, per `cfg`), replacing a client
+/// started before. Fails only if the address doesn't resolve; gpsd being
+/// down is retried in the background and shows in status().
+pub fn start(cfg: Config) -> Result<()> {
+    if cfg.scan_every.is_some_and(|d| d.is_zero()) {
+        bail!("scan interval must be positive");
+    }
+    let addr = (cfg.host.as_str(), cfg.port)
+        .to_socket_addrs()
+        .with_context(|| format!("resolve {}:{}", cfg.host, cfg.port))?
+        .next()
+        .with_context(|| format!("{}:{} has no address", cfg.host, cfg.port))?;
+    stop();
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let status = Arc::new(Mutex::new(GpsStatus {
+        running: true,
+        ..Default::default()
+    }));
+    let mut threads = Vec::new();
+    {
+        let (stop_flag, status) = (stop_flag.clone(), status.clone());
+        threads.push(std::thread::spawn(move || reader(addr, &stop_flag, &status)));
+    }
+    if let Some(every) = cfg.scan_every {
+        let (stop_flag, status) = (stop_flag.clone(), status.clone());
+        threads.push(std::thread::spawn(move || scanner(every, &stop_flag, &status)));
+    }
+    *lock() = Some(Client {
+        stop: stop_flag,
+        threads,
+        status,
+    });
+    Ok(())
+}
+
+/// Disconnect, stop scanning and clear the fix.
+pub fn stop() {
+    let Some(c) = lock().take() else {
+        return;
+    };
+    c.stop.store(true, Ordering::Relaxed);
+    for t in c.threads {
+        let _ = t.join();
+    }
+    geo::clear_fix();
+}
+
+pub fn status() -> GpsStatus {
+    match lock().as_ref() {
+        Some(c) => lock_status(&c.status).clone(),
+        None => GpsStatus::default(),
+    }
+}
+
+// Sleep `d` in TICK steps; false if asked to stop meanwhile.
+fn nap(d: Duration, stop: &AtomicBool) -> bool {
+    let until = Instant::now() + d;
+    while Instant::now() < until {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        std::thread::sleep(TICK.min(until - Instant::now()));
+    }
+    !stop.load(Ordering::Relaxed)
+}
+
+fn lose_fix(status: &Mutex<GpsStatus>) {
+    geo::clear_fix();
+    let mut s = lock_status(status);
+    s.fix = None;
+    s.mode = 0;
+}
+
+fn reader(addr: std::net::SocketAddr, stop: &AtomicBool, status: &Mutex<GpsStatus>) {
+    let mut backoff = Duration::from_secs(1);
+    while !stop.load(Ordering::Relaxed) {
+        if let Err(e) = session(addr, stop, status) {
+            lock_status(status).last_error = Some(format!("{e:#}"));
+        } else {
+            backoff = Duration::from_secs(1);
+        }
+        lock_status(status).connected = false;
+        lose_fix(status);
+        if !nap(backoff, stop) {
+            break;
+        }
+        backoff = (backoff * 2).min(RECONNECT_MAX);
+    }
+    lock_status(status).running = false;
+}
+
+// One connection: watch, then apply TPV reports until stopped or gpsd
+// goes away.
+fn session(addr: std::net::SocketAddr, stop: &AtomicBool, status: &Mutex<GpsStatus>) -> Result<()> {
+    let mut sock = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).with_context(|| format!("connect gpsd at {addr}"))?;
+    sock.set_read_timeout(Some(TICK))?;
+    sock.write_all(b"?WATCH={\"enable\":true,\"json\":true}\n")?;
+    lock_status(status).connected = true;
+
+    let mut lines = BufReader::new(sock);
+    let mut line = String::new();
+    let mut last_tpv = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        match lines.read_line(&mut line) {
+            Ok(0) => bail!("gpsd closed the connection"),
+            Ok(_) => {
+                if let Some((mode, fix)) = parse_tpv(&line) {
+                    last_tpv = Instant::now();
+                    match fix {
+                        Some(fix) => {
+                            geo::set_fix(fix);
+                            let mut s = lock_status(status);
+                            s.fix = Some(fix);
+                            s.mode = mode;
+                        }
+                        None => lose_fix(status),
+                    }
+                }
+                line.clear();
+            }
+            // A partial line stays in `line` and is completed next time.
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e).context("read from gpsd"),
+        }
+        if last_tpv.elapsed() > FIX_TIMEOUT && lock_status(status).fix.is_some() {
+            lose_fix(status);
+        }
+    }
+    Ok(())
+}
+
+/// gpsd's mode and the fix of one JSON report, if it is a TPV; the fix is
+/// None when the report has no 2D/3D position.
+pub fn parse_tpv(line: &str) -> Option<(u8, Option<Fix>)> {
+    let v: Value = serde_json::from_str(line.trim()).ok()?;
+    if v.get("class")?.as_str()? != "TPV" {
+        return None;
+    }
+    let mode = v.get("mode").and_then(Value::as_u64).unwrap_or(0).min(3) as u8;
+    let num = |k: &str| v.get(k).and_then(Value::as_f64);
+    let fix = match (mode >= 2, num("lat"), num("lon")) {
+        (true, Some(lat), Some(lon)) => Some(Fix {
+            lat,
+            lon,
+            // altMSL since gpsd 3.20, alt before.
+            alt: if mode == 3 { num("altMSL").or(num("alt")) } else { None },
+            // eph is the 95% horizontal error; older gpsd only has epx/epy.
+            accuracy_m: num("eph").or_else(|| Some(num("epx")?.max(num("epy")?))),
+            at: Stamp::now(),
+        }),
+        _ => None,
+    };
+    Some((mode, fix))
+}
+
+fn scanner(every: Duration, stop: &AtomicBool, status: &Mutex<GpsStatus>) {
+    while nap(Duration::ZERO, stop) {
+        let started = Instant::now();
+        if geo::fix().is_some() {
+            let res = scan_all_bss();
+            let mut s = lock_status(status);
+            match res {
+                Ok(_) => s.scans += 1,
+                Err(e) => {
+                    s.scan_errors += 1;
+                    s.last_error = Some(format!("scan: {e:#}"));
+                }
+            }
+        }
+        if !nap(every.saturating_sub(started.elapsed()).max(TICK), stop) {
+            break;
+        }
+    }
+}
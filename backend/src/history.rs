@@ -0,0 +1,182 @@
+// src/history.rs
+//
+// In-memory scan history. Every scan that goes through lib_rust is recorded
+// here (bounded, oldest dropped first) so long-running analysis can happen
+// in Rust instead of Python looping over compute_best_channel().
+//
+// Exposes:
+//   - record(rows) / note_connected(mac) / clear() / len()
+//     (record() also feeds history_db when a database is open)
+//   - score_history(window, progress) -> Result<HistoryScore>
+
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::progress::Progress;
+use crate::{history_db, pool};
+use crate::lib_rust::{best_channel_from_rows, channel_weights, BssRow, ChannelConfig, ChannelLimits};
+
+// Enough for a day of polling every ~10 s.
+const MAX_SCANS: usize = 10_000;
+// Scans scored between progress reports.
+const PROGRESS_CHUNK: usize = 500;
+
+/// One stored scan.
+#[derive(Debug, Clone)]
+pub struct StoredScan {
+    pub at: SystemTime,
+    pub rows: Arc<Vec<BssRow>>,
+    /// Last BSSID we were associated with when this scan was taken.
+    pub connected: Option<[u8; 6]>,
+}
+
+struct History {
+    scans: VecDeque<StoredScan>,
+    connected: Option<[u8; 6]>,
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History {
+    scans: VecDeque::new(),
+    connected: None,
+});
+
+fn lock() -> std::sync::MutexGuard<'static, History> {
+    HISTORY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Store a completed scan (and queue it for the SQLite copy, if open).
+pub fn record(rows: &[BssRow]) {
+    let mut h = lock();
+    if h.scans.len() >= MAX_SCANS {
+        h.scans.pop_front();
+    }
+    let scan = StoredScan {
+        at: SystemTime::now(),
+        // IE blobs would dominate memory over thousands of scans. The
+        // security is parsed first so it stays cached for anomaly.rs.
+        rows: Arc::new(
+            rows.iter()
+                .map(|r| {
+                    r.security();
+                    r.without_ies()
+                })
+                .collect(),
+        ),
+        connected: h.connected,
+    };
+    history_db::submit(&scan);
+    h.scans.push_back(scan);
+}
+
+/// Remember the BSSID we are associated with for the following scans.
+pub fn note_connected(mac: Option<[u8; 6]>) {
+    lock().connected = mac;
+}
+
+pub fn clear() {
+    lock().scans.clear();
+}
+
+pub fn len() -> usize {
+    lock().scans.len()
+}
+
+/// The most recent `window` scans (all of them if None), oldest first.
+pub fn recent(window: Option<usize>) -> Vec<StoredScan> {
+    let h = lock();
+    let n = window.unwrap_or(h.scans.len()).min(h.scans.len());
+    h.scans.iter().skip(h.scans.len() - n).cloned().collect()
+}
+
+/// Aggregate of the best-channel pipeline run over many stored scans.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryScore {
+    pub scans: usize,
+    /// Unix timestamps (seconds) of the oldest and newest scan scored.
+    pub first_at: Option<f64>,
+    pub last_at: Option<f64>,
+    /// How often each (band, channel) was the recommendation.
+    pub recommendations: HashMap<(u8, u32), u32>,
+    /// The most frequent recommendation (None if there were no scans).
+    pub best_channel: Option<(u8, u32)>,
+    /// Mean interference weight per (band, channel) over all scans.
+    pub mean_weight: HashMap<(u8, u32), f32>,
+}
+
+/// Run the scoring pipeline over the last `window` stored scans, in
+/// parallel on the batch pool, and aggregate the results. Scans are scored
+/// in chunks so progress can be reported between them.
+pub fn score_history(window: Option<usize>, progress: &mut Progress) -> Result<HistoryScore> {
+    progress.cancel().check()?;
+    let scans = recent(window);
+
+    let mut recommendations: HashMap<(u8, u32), u32> = HashMap::new();
+    let mut weight_sums: HashMap<(u8, u32), f32> = HashMap::new();
+    let mut done = 0;
+    for chunk in scans.chunks(PROGRESS_CHUNK) {
+        let (recs, sums) = pool::install(|| {
+            chunk
+                .par_iter()
+                .map(|s| {
+                    // Today's channel survey says nothing about old scans.
+                    let best = best_channel_from_rows(
+                        &s.rows,
+                        s.connected.as_ref(),
+                        &HashMap::new(),
+                        &ChannelLimits::NONE,
+                        &ChannelConfig::DEFAULT,
+                    );
+                    let weights = channel_weights(&s.rows, s.connected.as_ref(), &ChannelConfig::DEFAULT);
+                    (best.into_iter().map(|pick| (pick, 1u32)).collect::<HashMap<_, _>>(), weights)
+                })
+                .reduce(
+                    || (HashMap::new(), HashMap::new()),
+                    |(mut recs, mut sums), (r, w)| {
+                        for (pick, n) in r {
+                            *recs.entry(pick).or_insert(0) += n;
+                        }
+                        for (k, v) in w {
+                            *sums.entry(k).or_insert(0.0) += v;
+                        }
+                        (recs, sums)
+                    },
+                )
+        });
+        for (pick, n) in recs {
+            *recommendations.entry(pick).or_insert(0) += n;
+        }
+        for (k, v) in sums {
+            *weight_sums.entry(k).or_insert(0.0) += v;
+        }
+        done += chunk.len();
+        let msg = format!("{done}/{} scans", scans.len());
+        progress.report(done as f32 * 100.0 / scans.len() as f32, "score", &msg)?;
+    }
+
+    let n = scans.len();
+    // Ties go to the lower band and channel so the answer is stable.
+    let best_channel = recommendations
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(&pick, _)| pick);
+    let mean_weight = weight_sums
+        .into_iter()
+        .map(|(k, sum)| (k, sum / n as f32))
+        .collect();
+
+    let unix = |s: &StoredScan| {
+        s.at.duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+    };
+
+    Ok(HistoryScore {
+        scans: n,
+        first_at: scans.first().map(unix),
+        last_at: scans.last().map(unix),
+        recommendations,
+        best_channel,
+        mean_weight,
+    })
+}
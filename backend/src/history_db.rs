@@ -0,0 +1,253 @@
+// src/history_db.rs
+//
+// Optional on-disk copy of the scan history in SQLite. Scans never touch the
+// database themselves: history::record() hands each scan to a bounded
+// channel with try_send, and one writer thread drains it in batches, one
+// transaction per batch, with the database in WAL mode so readers (the UI,
+// sqlite3 on the side) don't stall it either.
+//
+// When the queue is full the scan is dropped and counted rather than
+// making the scanner wait; stats() reports queue depth, drops and batch
+// timings so sustained monitoring can be checked for backpressure.
+//
+// Latency probes (probe.rs) go through the same queue into their own
+// table, so RTT and loss can be joined with the scans by time.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Instant, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::history::StoredScan;
+use crate::lib_rust::format_mac;
+use crate::probe::Sample;
+
+// Records queued for the writer before new ones are dropped.
+const QUEUE_CAP: usize = 256;
+// Most records written per transaction.
+const MAX_BATCH: usize = 64;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS scans (
+        id        INTEGER PRIMARY KEY,
+        at        REAL NOT NULL,
+        connected TEXT
+    );
+    CREATE TABLE IF NOT EXISTS bss (
+        scan_id    INTEGER NOT NULL REFERENCES scans(id),
+        bssid      TEXT,
+        ssid       TEXT,
+        freq_mhz   INTEGER,
+        signal_dbm REAL,
+        channel    INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS bss_scan ON bss(scan_id);
+    CREATE TABLE IF NOT EXISTS latency (
+        at     REAL NOT NULL,
+        target TEXT NOT NULL,
+        rtt_ms REAL
+    );
+    CREATE INDEX IF NOT EXISTS latency_at ON latency(at);
+";
+
+// What the writer stores: a scan, or one latency probe (rtt_ms NULL when
+// lost).
+enum Record {
+    Scan(StoredScan),
+    Latency(Sample),
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicU64,
+    written: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+    batches: AtomicU64,
+    last_batch_us: AtomicU64,
+    max_batch_us: AtomicU64,
+}
+
+struct Writer {
+    tx: SyncSender<Record>,
+    thread: JoinHandle<()>,
+    path: PathBuf,
+    counters: Arc<Counters>,
+}
+
+static WRITER: Mutex<Option<Writer>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Writer>> {
+    WRITER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start persisting every recorded scan to the SQLite file at `path`,
+/// replacing (and flushing) any database opened before.
+pub fn open(path: &Path) -> Result<()> {
+    let conn = Connection::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mode: String = conn.query_row("PRAGMA journal_mode=WAL", [], |r| r.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        bail!("{}: could not enable WAL (got {mode})", path.display());
+    }
+    conn.execute_batch("PRAGMA synchronous=NORMAL;")?;
+    conn.execute_batch(SCHEMA)?;
+
+    close();
+
+    let (tx, rx) = mpsc::sync_channel(QUEUE_CAP);
+    let counters = Arc::new(Counters::default());
+    let c = counters.clone();
+    let thread = std::thread::Builder::new()
+        .name("wifi-history-db".into())
+        .spawn(move || writer_loop(conn, rx, &c))?;
+
+    *lock() = Some(Writer {
+        tx,
+        thread,
+        path: path.to_owned(),
+        counters,
+    });
+    Ok(())
+}
+
+/// Stop the writer after it has flushed everything already queued.
+pub fn close() {
+    let Some(w) = lock().take() else {
+        return;
+    };
+    drop(w.tx);
+    let _ = w.thread.join();
+}
+
+/// Queue a scan for the writer, if a database is open. Never blocks.
+pub fn submit(scan: &StoredScan) {
+    send(Record::Scan(scan.clone()));
+}
+
+/// Queue a latency probe, like submit().
+pub fn submit_latency(sample: &Sample) {
+    send(Record::Latency(*sample));
+}
+
+fn send(rec: Record) {
+    let guard = lock();
+    let Some(w) = guard.as_ref() else {
+        return;
+    };
+    match w.tx.try_send(rec) {
+        Ok(()) => {
+            w.counters.queued.fetch_add(1, Ordering::Relaxed);
+        }
+        Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+            w.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DbStats {
+    pub path: Option<PathBuf>,
+    /// Records (scans and latency probes) waiting for the writer right now.
+    pub pending: u64,
+    pub capacity: usize,
+    pub written: u64,
+    /// Records lost to a failed transaction.
+    pub failed: u64,
+    /// Records dropped because the queue was full.
+    pub dropped: u64,
+    pub batches: u64,
+    pub last_batch_ms: f64,
+    pub max_batch_ms: f64,
+}
+
+pub fn stats() -> DbStats {
+    let guard = lock();
+    let Some(w) = guard.as_ref() else {
+        return DbStats::default();
+    };
+    let c = &w.counters;
+    let get = |a: &AtomicU64| a.load(Ordering::Relaxed);
+    DbStats {
+        path: Some(w.path.clone()),
+        pending: get(&c.queued).saturating_sub(get(&c.written) + get(&c.failed)),
+        capacity: QUEUE_CAP,
+        written: get(&c.written),
+        failed: get(&c.failed),
+        dropped: get(&c.dropped),
+        batches: get(&c.batches),
+        last_batch_ms: get(&c.last_batch_us) as f64 / 1000.0,
+        max_batch_ms: get(&c.max_batch_us) as f64 / 1000.0,
+    }
+}
+
+fn writer_loop(mut conn: Connection, rx: Receiver<Record>, c: &Counters) {
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    // Block for the first record, then take whatever else is already queued.
+    while let Ok(first) = rx.recv() {
+        batch.push(first);
+        while batch.len() < MAX_BATCH {
+            match rx.try_recv() {
+                Ok(s) => batch.push(s),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        let start = Instant::now();
+        let res = write_batch(&mut conn, &batch);
+        let us = start.elapsed().as_micros() as u64;
+
+        let n = batch.len() as u64;
+        match res {
+            Ok(()) => c.written.fetch_add(n, Ordering::Relaxed),
+            Err(e) => {
+                warn!(rows = n, "history db write failed: {e:#}");
+                c.failed.fetch_add(n, Ordering::Relaxed)
+            }
+        };
+        c.batches.fetch_add(1, Ordering::Relaxed);
+        c.last_batch_us.store(us, Ordering::Relaxed);
+        c.max_batch_us.fetch_max(us, Ordering::Relaxed);
+        batch.clear();
+    }
+}
+
+fn write_batch(conn: &mut Connection, batch: &[Record]) -> Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut scan_stmt = tx.prepare_cached("INSERT INTO scans (at, connected) VALUES (?1, ?2)")?;
+        let mut bss_stmt = tx.prepare_cached(
+            "INSERT INTO bss (scan_id, bssid, ssid, freq_mhz, signal_dbm, channel)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let mut latency_stmt = tx.prepare_cached("INSERT INTO latency (at, target, rtt_ms) VALUES (?1, ?2, ?3)")?;
+        for rec in batch {
+            let s = match rec {
+                Record::Scan(s) => s,
+                Record::Latency(p) => {
+                    latency_stmt.execute(params![p.at.wall, p.target.to_string(), p.rtt_ms])?;
+                    continue;
+                }
+            };
+            let at = s.at.duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64());
+            scan_stmt.execute(params![at, s.connected.as_ref().map(format_mac)])?;
+            let scan_id = tx.last_insert_rowid();
+            for r in s.rows.iter() {
+                bss_stmt.execute(params![
+                    scan_id,
+                    r.bssid.as_ref().map(format_mac),
+                    r.ssid.as_deref(),
+                    r.freq_mhz,
+                    r.signal_dbm,
+                    r.channel,
+                ])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,1458 @@
+// src/ies.rs
+//
+// Decoders for the information elements we only look at on demand (see
+// BssRow::security / channel_width / country). Each takes the already
+// split IeList and only reads the one or two elements it needs.
+//
+//   - RSN (48) / WPA vendor IE (221, 00:50:F2 type 1) -> Security, and
+//                                                          the AKMs and
+//                                                          ciphers as
+//                                                          Android spells
+//                                                          them; PMF
+//                                                          from the RSN
+//                                                          capabilities
+//   - HT / VHT / HE operation (61, 192, 255 ext 36)    -> width, primary,
+//                                                          secondary, centre
+//   - Country (7)                                       -> ISO alpha-2 code,
+//                                                          the channels it lists
+//   - TIM (5)                                           -> DTIM period
+//   - IBSS Parameter Set (6)                            -> ad-hoc flag
+//   - HT capabilities (45) / 20/40 BSS Coexistence (72) -> 40 MHz intolerance
+//   - Basic Multi-Link (255, ext 107)                   -> MLD MAC address
+//   - Multiple BSSID (71)                               -> the BSSIDs a
+//                                                          transmitting BSS
+//                                                          beacons for
+//   - Reduced Neighbor Report (201)                     -> neighbour APs
+//                                                          (6 GHz ones of
+//                                                          Wi-Fi 6E APs)
+//   - BSS Load (11)                                     -> stations, channel use
+//   - (Extended) Supported Rates (1, 50), HT / VHT / HE
+//     / EHT capabilities (45, 191, 255 ext 35 / 108)    -> PhyRates
+//   - Vendor specific (221)                             -> OUIs, Multi-AP flag,
+//                                                          Wi-Fi Direct (P2P) flag,
+//                                                          WPS state and lock
+//                                                          (00:50:F2 type 4)
+//   - Interworking (107) / HS2.0 Indication (221,
+//     50:6F:9A type 0x10)                               -> Passpoint, access
+//                                                          network type, venue
+//   - Mesh ID (114) / Mesh Configuration (113)          -> 802.11s mesh point,
+//                                                          its path selection
+
+use crate::lib_rust::{ie_list, IeList};
+
+const IE_IBSS_PARAMS: u8 = 6;
+const IE_SUPP_RATES: u8 = 1;
+const IE_TIM: u8 = 5;
+const IE_COUNTRY: u8 = 7;
+const IE_BSS_LOAD: u8 = 11;
+const IE_HT_CAP: u8 = 45;
+const IE_RSN: u8 = 48;
+const IE_EXT_SUPP_RATES: u8 = 50;
+const IE_HT_OPERATION: u8 = 61;
+const IE_BSS_COEXISTENCE: u8 = 72;
+const IE_MULTIPLE_BSSID: u8 = 71;
+const IE_MULTIPLE_BSSID_INDEX: u8 = 85;
+const IE_INTERWORKING: u8 = 107;
+const IE_VHT_CAP: u8 = 191;
+const IE_VHT_OPERATION: u8 = 192;
+const IE_MESH_CONFIG: u8 = 113;
+const IE_MESH_ID: u8 = 114;
+const IE_RNR: u8 = 201;
+const IE_VENDOR: u8 = 221;
+const IE_EXTENSION: u8 = 255;
+const EXT_HE_CAP: u8 = 35;
+const EXT_HE_OPERATION: u8 = 36;
+const EXT_MULTI_LINK: u8 = 107;
+const EXT_EHT_CAP: u8 = 108;
+
+const OUI_IEEE: [u8; 3] = [0x00, 0x0f, 0xac];
+const OUI_MICROSOFT: [u8; 3] = [0x00, 0x50, 0xf2];
+const OUI_WFA: [u8; 3] = [0x50, 0x6f, 0x9a];
+const MS_TYPE_WPS: u8 = 0x04;
+const WFA_TYPE_P2P: u8 = 0x09;
+const WFA_TYPE_HS20: u8 = 0x10;
+const WFA_TYPE_MULTI_AP: u8 = 0x1b;
+
+/// What a BSS advertises for authentication.
+///
+/// parse_security() never returns Wep: WEP only shows up in the capability
+/// field's Privacy bit, not in an IE, so BssRow::security() decides it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    /// WPA2/WPA3 transition mode (PSK and SAE both offered).
+    Wpa2Wpa3,
+    Wpa3,
+    /// 802.1X / EAP, any generation.
+    Enterprise,
+    /// Opportunistic Wireless Encryption ("Enhanced Open").
+    Owe,
+}
+
+impl Security {
+    pub fn name(self) -> &'static str {
+        match self {
+            Security::Open => "open",
+            Security::Wep => "wep",
+            Security::Wpa => "wpa",
+            Security::Wpa2 => "wpa2",
+            Security::Wpa2Wpa3 => "wpa2/wpa3",
+            Security::Wpa3 => "wpa3",
+            Security::Enterprise => "enterprise",
+            Security::Owe => "owe",
+        }
+    }
+
+    /// Unencrypted, or encrypted in a way that is broken in practice (WEP,
+    /// WPA/TKIP). OWE is unauthenticated but encrypted and doesn't count.
+    pub fn is_insecure(self) -> bool {
+        matches!(self, Security::Open | Security::Wep | Security::Wpa)
+    }
+
+    /// Rough ordering by protection, for telling a downgrade: open, WEP,
+    /// WPA, OWE, WPA2, WPA2/WPA3, then WPA3 and enterprise alike.
+    pub fn strength(self) -> u8 {
+        match self {
+            Security::Open => 0,
+            Security::Wep => 1,
+            Security::Wpa => 2,
+            Security::Owe => 3,
+            Security::Wpa2 => 4,
+            Security::Wpa2Wpa3 => 5,
+            Security::Wpa3 | Security::Enterprise => 6,
+        }
+    }
+}
+
+// AKM suite selectors out of an RSN / WPA element body that starts with the
+// 2-byte version: version, group cipher (4), pairwise count + suites,
+// AKM count + suites.
+fn akm_suites(body: &[u8]) -> impl Iterator<Item = [u8; 4]> + '_ {
+    let mut akms: &[u8] = &[];
+    if let Some(pcount) = body.get(6..8) {
+        let pcount = u16::from_le_bytes([pcount[0], pcount[1]]) as usize;
+        let akm_at = 8 + pcount * 4;
+        if let Some(acount) = body.get(akm_at..akm_at + 2) {
+            let acount = u16::from_le_bytes([acount[0], acount[1]]) as usize;
+            let start = akm_at + 2;
+            akms = body.get(start..start + acount * 4).unwrap_or(&[]);
+        }
+    }
+    akms.chunks_exact(4).map(|c| [c[0], c[1], c[2], c[3]])
+}
+
+// Pairwise cipher suite selectors, like akm_suites().
+fn pairwise_suites(body: &[u8]) -> impl Iterator<Item = [u8; 4]> + '_ {
+    let count = body.get(6..8).map_or(0, |c| u16::from_le_bytes([c[0], c[1]]) as usize);
+    body.get(8..8 + count * 4)
+        .unwrap_or(&[])
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+}
+
+fn akm_name(akm: u8) -> Option<&'static str> {
+    Some(match akm {
+        1 => "EAP",
+        2 => "PSK",
+        3 => "FT/EAP",
+        4 => "FT/PSK",
+        5 => "EAP-SHA256",
+        6 => "PSK-SHA256",
+        8 => "SAE",
+        9 => "FT/SAE",
+        11 => "EAP-SUITE-B",
+        12 => "EAP-SUITE-B-192",
+        18 => "OWE",
+        24 => "SAE_EXT_KEY",
+        25 => "FT/SAE_EXT_KEY",
+        _ => return None,
+    })
+}
+
+fn cipher_name(cipher: u8) -> Option<&'static str> {
+    Some(match cipher {
+        1 => "WEP40",
+        2 => "TKIP",
+        4 => "CCMP",
+        5 => "WEP104",
+        8 => "GCMP",
+        9 => "GCMP-256",
+        10 => "CCMP-256",
+        _ => return None,
+    })
+}
+
+// "[WPA2-PSK+SAE-CCMP]" for one RSN / WPA element body (after the OUI
+// and type for WPA), as Android's ScanResult.capabilities has it.
+fn auth_bracket(proto: &str, body: &[u8], oui: [u8; 3]) -> String {
+    let names = |sel: &mut dyn Iterator<Item = [u8; 4]>, name: fn(u8) -> Option<&'static str>| {
+        sel.filter(|s| s[..3] == oui)
+            .filter_map(|s| name(s[3]))
+            .collect::<Vec<_>>()
+            .join("+")
+    };
+    let akms = names(&mut akm_suites(body), akm_name);
+    let ciphers = names(&mut pairwise_suites(body), cipher_name);
+    let mut s = format!("[{proto}");
+    for part in [akms, ciphers] {
+        if !part.is_empty() {
+            s.push('-');
+            s.push_str(&part);
+        }
+    }
+    s.push(']');
+    s
+}
+
+/// The RSN and WPA elements the way Android's ScanResult.capabilities (and
+/// WiGLE's AuthMode) spell them, e.g. "[WPA2-PSK-CCMP][WPA-PSK-TKIP]";
+/// empty without either.
+pub fn auth_mode(ies: &IeList) -> String {
+    let mut s = String::new();
+    if let Some(rsn) = ies.iter().find(|ie| ie.id == IE_RSN) {
+        s.push_str(&auth_bracket("WPA2", rsn.data, OUI_IEEE));
+    }
+    let wpa1 = ies.iter().find(|ie| {
+        ie.id == IE_VENDOR && ie.data.len() >= 4 && ie.data[..3] == OUI_MICROSOFT && ie.data[3] == 1
+    });
+    if let Some(wpa) = wpa1 {
+        s.push_str(&auth_bracket("WPA", &wpa.data[4..], OUI_MICROSOFT));
+    }
+    s
+}
+
+pub fn parse_security(ies: &IeList) -> Security {
+    if let Some(rsn) = ies.iter().find(|ie| ie.id == IE_RSN) {
+        let (mut psk, mut sae, mut eap, mut owe) = (false, false, false, false);
+        for akm in akm_suites(rsn.data) {
+            if akm[..3] != OUI_IEEE {
+                continue;
+            }
+            match akm[3] {
+                1 | 3 | 5 | 11 | 12 | 13 => eap = true,
+                2 | 4 | 6 => psk = true,
+                8 | 9 | 24 | 25 => sae = true,
+                18 => owe = true,
+                _ => {}
+            }
+        }
+        return match (eap, sae, psk, owe) {
+            (true, ..) => Security::Enterprise,
+            (_, true, true, _) => Security::Wpa2Wpa3,
+            (_, true, false, _) => Security::Wpa3,
+            (_, _, _, true) => Security::Owe,
+            _ => Security::Wpa2,
+        };
+    }
+
+    let wpa1 = ies.iter().any(|ie| {
+        ie.id == IE_VENDOR && ie.data.len() >= 4 && ie.data[..3] == OUI_MICROSOFT && ie.data[3] == 1
+    });
+    if wpa1 {
+        Security::Wpa
+    } else {
+        Security::Open
+    }
+}
+
+/// Management Frame Protection (802.11w) as the RSN capabilities
+/// advertise it. Without it anyone can forge deauthentication frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pmf {
+    Disabled,
+    Capable,
+    Required,
+}
+
+impl Pmf {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pmf::Disabled => "disabled",
+            Pmf::Capable => "capable",
+            Pmf::Required => "required",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pmf> {
+        match name {
+            "disabled" => Some(Pmf::Disabled),
+            "capable" => Some(Pmf::Capable),
+            "required" => Some(Pmf::Required),
+            _ => None,
+        }
+    }
+}
+
+// RSN Capabilities field, after the AKM suites; absent in short elements.
+fn rsn_capabilities(body: &[u8]) -> Option<u16> {
+    let pcount = u16::from_le_bytes(body.get(6..8)?.try_into().ok()?) as usize;
+    let akm_at = 8 + pcount * 4;
+    let acount = u16::from_le_bytes(body.get(akm_at..akm_at + 2)?.try_into().ok()?) as usize;
+    let caps_at = akm_at + 2 + acount * 4;
+    Some(u16::from_le_bytes(body.get(caps_at..caps_at + 2)?.try_into().ok()?))
+}
+
+/// PMF from the RSN element's capabilities: MFPR (bit 7) required, MFPC
+/// (bit 6) capable. None without an RSN element; an element cut short
+/// before its capabilities means disabled.
+pub fn parse_pmf(ies: &IeList) -> Option<Pmf> {
+    let rsn = ies.iter().find(|ie| ie.id == IE_RSN)?;
+    let caps = rsn_capabilities(rsn.data).unwrap_or(0);
+    Some(if caps & 1 << 7 != 0 {
+        Pmf::Required
+    } else if caps & 1 << 6 != 0 {
+        Pmf::Capable
+    } else {
+        Pmf::Disabled
+    })
+}
+
+/// Whether TKIP is among the pairwise ciphers of the RSN element or a WPA
+/// element: a WPA/WPA2 mixed-mode AP still lets clients use it.
+pub fn offers_tkip(ies: &IeList) -> bool {
+    const TKIP: u8 = 2;
+    ies.iter().any(|ie| match ie.id {
+        IE_RSN => pairwise_suites(ie.data).any(|s| s[..3] == OUI_IEEE && s[3] == TKIP),
+        IE_VENDOR if ie.data.len() >= 4 && ie.data[..3] == OUI_MICROSOFT && ie.data[3] == 1 => {
+            pairwise_suites(&ie.data[4..]).any(|s| s[..3] == OUI_MICROSOFT && s[3] == TKIP)
+        }
+        _ => false,
+    })
+}
+
+/// What a BSS advertises in its WPS element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Wps {
+    /// Wi-Fi Protected Setup State 2: set up, as opposed to still waiting
+    /// for its first configuration.
+    pub configured: bool,
+    /// AP Setup Locked: it stopped taking PINs, usually after too many
+    /// wrong ones.
+    pub locked: bool,
+    /// The Config Methods allow a PIN (label, display or keypad), or
+    /// aren't advertised, as beacons often leave them out.
+    pub pin: bool,
+}
+
+impl Wps {
+    /// Open to PIN brute force (Pixie Dust, Reaver): taking PINs and not
+    /// locked.
+    pub fn pin_vulnerable(&self) -> bool {
+        self.pin && !self.locked
+    }
+}
+
+// WPS attributes (big-endian type, length, value).
+const WPS_CONFIG_METHODS: u16 = 0x1008;
+const WPS_STATE: u16 = 0x1044;
+const WPS_AP_SETUP_LOCKED: u16 = 0x1057;
+// Config Methods bits for the PIN methods: label, display, keypad.
+const WPS_PIN_METHODS: u16 = 0x0004 | 0x0008 | 0x0100;
+
+/// The WPS element (Microsoft OUI, type 4), None without one. Long ones
+/// are split over several elements, read as one.
+pub fn parse_wps(ies: &IeList) -> Option<Wps> {
+    let mut body: Vec<u8> = Vec::new();
+    let mut found = false;
+    for ie in ies.iter().filter(|ie| {
+        ie.id == IE_VENDOR && ie.data.get(..4) == Some(&[OUI_MICROSOFT[0], OUI_MICROSOFT[1], OUI_MICROSOFT[2], MS_TYPE_WPS])
+    }) {
+        found = true;
+        body.extend_from_slice(&ie.data[4..]);
+    }
+    if !found {
+        return None;
+    }
+    let mut wps = Wps {
+        pin: true,
+        ..Wps::default()
+    };
+    let mut d = body.as_slice();
+    while let [t0, t1, l0, l1, rest @ ..] = d {
+        let len = u16::from_be_bytes([*l0, *l1]) as usize;
+        let Some(value) = rest.get(..len) else { break };
+        match (u16::from_be_bytes([*t0, *t1]), value) {
+            (WPS_STATE, &[state, ..]) => wps.configured = state == 2,
+            (WPS_AP_SETUP_LOCKED, &[locked, ..]) => wps.locked = locked != 0,
+            (WPS_CONFIG_METHODS, &[hi, lo, ..]) => wps.pin = u16::from_be_bytes([hi, lo]) & WPS_PIN_METHODS != 0,
+            _ => {}
+        }
+        d = &rest[len..];
+    }
+    Some(wps)
+}
+
+/// Where a BSS sits in the spectrum, from its operation elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    /// Operating width in MHz (20/40/80/160). 80+80 is reported as 160.
+    pub width_mhz: u32,
+    /// Primary 20 MHz channel as the AP announces it.
+    pub primary: Option<u32>,
+    /// Side of the primary the secondary 20 MHz channel is on: 1 above,
+    /// -1 below. None for 20 MHz or when not announced.
+    pub secondary: Option<i8>,
+    /// Centre channel of the whole operating channel when 40 MHz or wider
+    /// and contiguous; None for 80+80.
+    pub center: Option<u32>,
+}
+
+// VHT-style channel width (VHT operation, HE 6 GHz operation info after
+// mapping) plus its two centre frequency segments, for 80 MHz and up.
+fn wide_operation(width: u8, ccfs0: u8, ccfs1: u8) -> Option<(u32, Option<u32>)> {
+    let (c0, c1) = (ccfs0 as u32, ccfs1 as u32);
+    match width {
+        // 80, or 160 / 80+80 signalled through CCFS1.
+        1 if c1 != 0 && c1.abs_diff(c0) == 8 => Some((160, Some(c1))),
+        1 if c1 != 0 && c1.abs_diff(c0) > 8 => Some((160, None)),
+        1 => Some((80, Some(c0))),
+        // Deprecated explicit 160 and 80+80.
+        2 => Some((160, Some(c0))),
+        3 => Some((160, None)),
+        _ => None,
+    }
+}
+
+/// Channel width, primary/secondary and centre channel from the HT
+/// operation (61), VHT operation (192) and HE operation (255, ext 36)
+/// elements. The HE element only adds something on 6 GHz, where there are
+/// no HT/VHT elements and it carries the channel itself.
+pub fn parse_operation(ies: &IeList) -> Option<Operation> {
+    let ht = ies.iter().find(|ie| ie.id == IE_HT_OPERATION);
+    let mut op = match ht.map(|ie| ie.data) {
+        // Primary channel, then secondary channel offset (bits 0-1: 1
+        // above, 3 below) and "STA channel width" (bit 2, 40 MHz allowed).
+        Some(&[primary, info, ..]) => {
+            let secondary = match info & 0x03 {
+                1 => Some(1),
+                3 => Some(-1),
+                _ => None,
+            };
+            let forty = info & 0x04 != 0 && secondary.is_some();
+            Some(Operation {
+                width_mhz: if forty { 40 } else { 20 },
+                primary: Some(primary as u32),
+                secondary: secondary.filter(|_| forty),
+                center: secondary
+                    .filter(|_| forty)
+                    .and_then(|s| (primary as i32 + 2 * s as i32).try_into().ok()),
+            })
+        }
+        _ => None,
+    };
+
+    if let (Some(o), Some(vht)) = (op.as_mut(), ies.iter().find(|ie| ie.id == IE_VHT_OPERATION)) {
+        if let [width, ccfs0, ccfs1, ..] = *vht.data {
+            if let Some((w, center)) = wide_operation(width, ccfs0, ccfs1) {
+                o.width_mhz = w;
+                o.center = center;
+            }
+        }
+    }
+
+    if op.is_none() {
+        op = parse_he_6ghz(ies);
+    }
+    op
+}
+
+// 6 GHz Operation Information out of the HE operation element.
+fn parse_he_6ghz(ies: &IeList) -> Option<Operation> {
+    let he = ies.iter().find(|ie| ie.id == IE_EXTENSION && ie.data.first() == Some(&EXT_HE_OPERATION))?;
+    // ext id, HE Operation Parameters (3), BSS Color (1), Basic HE-MCS (2),
+    // then the optional VHT Operation Information (3, bit 14) and
+    // Co-Hosted BSS indicator (1, bit 15) before the 6 GHz info (bit 17).
+    let d = he.data;
+    let params = u32::from_le_bytes([*d.get(1)?, *d.get(2)?, *d.get(3)?, 0]);
+    if params & 1 << 17 == 0 {
+        return None;
+    }
+    let mut at = 7;
+    if params & 1 << 14 != 0 {
+        at += 3;
+    }
+    if params & 1 << 15 != 0 {
+        at += 1;
+    }
+    let [primary, control, ccfs0, ccfs1, ..] = *d.get(at..)? else {
+        return None;
+    };
+    let (width_mhz, center) = match control & 0x03 {
+        0 => (20, None),
+        1 => (40, Some(ccfs0 as u32)),
+        2 => (80, Some(ccfs0 as u32)),
+        _ => wide_operation(1, ccfs0, ccfs1)?,
+    };
+    let primary = primary as u32;
+    Some(Operation {
+        width_mhz,
+        primary: Some(primary),
+        // 6 GHz 40 MHz channels pair 1+5, 9+13, ...: the secondary 20 is
+        // the other half of the pair the primary is in.
+        secondary: (width_mhz >= 40 && primary >= 1).then(|| if ((primary - 1) / 4).is_multiple_of(2) { 1 } else { -1 }),
+        center,
+    })
+}
+
+/// ISO 3166 alpha-2 code from the Country element, e.g. "US".
+pub fn parse_country(ies: &IeList) -> Option<[u8; 2]> {
+    let ie = ies.iter().find(|ie| ie.id == IE_COUNTRY)?;
+    let cc: [u8; 2] = ie.data.get(..2)?.try_into().ok()?;
+    cc.iter().all(u8::is_ascii_alphabetic).then_some(cc)
+}
+
+/// The Country element in full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountryInfo {
+    pub code: [u8; 2],
+    /// Third byte of the country string: b'I' indoor, b'O' outdoor, b' '
+    /// any, b'X' not country-specific.
+    pub environment: u8,
+    /// 2.4 and 5 GHz channels the subband triplets list, ascending. 6 GHz
+    /// APs describe theirs by operating class instead and list none.
+    pub channels: Vec<u32>,
+}
+
+/// Country code, environment and the channels of the subband triplets
+/// (first channel, number of channels, max power), a step of 1 apart on
+/// 2.4 GHz and 4 on 5 GHz. Triplets whose first byte is 201 or more
+/// introduce operating classes and are skipped with the ones after them.
+pub fn parse_country_info(ies: &IeList) -> Option<CountryInfo> {
+    let ie = ies.iter().find(|ie| ie.id == IE_COUNTRY)?;
+    let code = parse_country(ies)?;
+    let environment = *ie.data.get(2)?;
+    let mut channels: Vec<u32> = Vec::new();
+    for t in ie.data[3..].chunks_exact(3) {
+        let (first, count) = (t[0] as u32, t[1] as u32);
+        if first >= 201 {
+            break;
+        }
+        let step = if first <= 14 { 1 } else { 4 };
+        channels.extend((0..count).map(|i| first + i * step));
+    }
+    channels.sort_unstable();
+    channels.dedup();
+    Some(CountryInfo {
+        code,
+        environment,
+        channels,
+    })
+}
+
+/// What an AP reports about its own load in the BSS Load element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BssLoad {
+    pub stations: u16,
+    /// Fraction of time the AP sensed the medium busy, 0.0 to 1.0.
+    pub utilization: f32,
+}
+
+/// Station count and channel utilization from the BSS Load element.
+pub fn parse_bss_load(ies: &IeList) -> Option<BssLoad> {
+    let ie = ies.iter().find(|ie| ie.id == IE_BSS_LOAD)?;
+    let [lo, hi, util, ..] = *ie.data else {
+        return None;
+    };
+    Some(BssLoad {
+        stations: u16::from_le_bytes([lo, hi]),
+        utilization: util as f32 / 255.0,
+    })
+}
+
+/// OUIs of the vendor-specific elements, in order, without duplicates.
+/// The generic Microsoft (WPA/WMM/WPS) and Wi-Fi Alliance ones say nothing
+/// about who made the AP and are left out.
+pub fn vendor_ouis(ies: &IeList) -> Vec<[u8; 3]> {
+    let mut out: Vec<[u8; 3]> = Vec::new();
+    for ie in ies.iter().filter(|ie| ie.id == IE_VENDOR) {
+        let Some(oui) = ie.data.get(..3).and_then(|o| <[u8; 3]>::try_from(o).ok()) else {
+            continue;
+        };
+        if oui != OUI_MICROSOFT && oui != OUI_WFA && oui != OUI_IEEE && !out.contains(&oui) {
+            out.push(oui);
+        }
+    }
+    out
+}
+
+/// Whether the BSS carries the Wi-Fi Alliance Multi-AP element, i.e. is
+/// part of an EasyMesh network.
+pub fn has_multi_ap(ies: &IeList) -> bool {
+    ies.iter()
+        .any(|ie| ie.id == IE_VENDOR && ie.data.get(..4) == Some(&[OUI_WFA[0], OUI_WFA[1], OUI_WFA[2], WFA_TYPE_MULTI_AP]))
+}
+
+/// Whether the BSS carries the Wi-Fi Alliance P2P element: a Wi-Fi Direct
+/// group owner (Chromecast, Miracast, printers) rather than an access point.
+pub fn has_p2p(ies: &IeList) -> bool {
+    ies.iter()
+        .any(|ie| ie.id == IE_VENDOR && ie.data.get(..4) == Some(&[OUI_WFA[0], OUI_WFA[1], OUI_WFA[2], WFA_TYPE_P2P]))
+}
+
+/// Whether the BSS carries an IBSS Parameter Set, which only ad-hoc
+/// networks send. Stands in for the capability field's IBSS bit when the
+/// backend doesn't report it.
+pub fn has_ibss_params(ies: &IeList) -> bool {
+    ies.iter().any(|ie| ie.id == IE_IBSS_PARAMS)
+}
+
+// HT Capabilities Information: the station forbids 40 MHz BSSs near it.
+const HT_CAP_40_INTOLERANT: u16 = 1 << 14;
+// 20/40 BSS Coexistence: Forty MHz Intolerant, 20 MHz BSS Width Request.
+const COEX_40_INTOLERANT: u8 = 1 << 1;
+const COEX_20_REQUEST: u8 = 1 << 2;
+
+/// Whether the BSS asks the 2.4 GHz BSSs around it to stay at 20 MHz: the
+/// Forty MHz Intolerant bit of its HT capabilities, or the intolerant /
+/// 20 MHz width request bits of a 20/40 BSS Coexistence element.
+pub fn forty_mhz_intolerant(ies: &IeList) -> bool {
+    ies.iter().any(|ie| match ie.id {
+        IE_HT_CAP => ie.data.get(..2).is_some_and(|b| u16::from_le_bytes([b[0], b[1]]) & HT_CAP_40_INTOLERANT != 0),
+        IE_BSS_COEXISTENCE => ie.data.first().is_some_and(|&b| b & (COEX_40_INTOLERANT | COEX_20_REQUEST) != 0),
+        _ => false,
+    })
+}
+
+/// The DTIM period from the TIM element: every how many beacons the AP
+/// sends buffered broadcast traffic, and so how often a dozing client has
+/// to wake for it. None without one (probe responses carry no TIM) and for
+/// the reserved period 0.
+pub fn parse_dtim_period(ies: &IeList) -> Option<u8> {
+    let ie = ies.iter().find(|ie| ie.id == IE_TIM)?;
+    ie.data.get(1).copied().filter(|&p| p != 0)
+}
+
+/// The rates a BSS supports, as its rate and capability elements list
+/// them; phyrate::max_rate_mbps() turns them into Mbit/s.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhyRates {
+    /// Fastest 802.11a/b/g rate, Mbit/s.
+    pub legacy_mbps: Option<f32>,
+    /// HT spatial streams (MCS 0-7 each); 0 without HT.
+    pub ht_streams: u8,
+    pub ht_sgi20: bool,
+    pub ht_sgi40: bool,
+    /// Rx VHT-MCS map: 2 bits per stream count, 0 for MCS 0-7, 1 for
+    /// 0-8, 2 for 0-9, 3 not supported.
+    pub vht_mcs_map: Option<u16>,
+    pub vht_sgi80: bool,
+    pub vht_sgi160: bool,
+    /// Rx HE-MCS map up to 80 MHz: 0 for MCS 0-7, 1 for 0-9, 2 for 0-11,
+    /// 3 not supported.
+    pub he_mcs_map: Option<u16>,
+    /// EHT capabilities present.
+    pub eht: bool,
+}
+
+/// Supported Rates (1) and Extended Supported Rates (50), the Supported
+/// MCS Set of HT Capabilities (45), and the Rx MCS maps of VHT (191) and
+/// HE (255, ext 35) Capabilities.
+pub fn parse_phy_rates(ies: &IeList) -> PhyRates {
+    // 500 kbit/s units, bit 7 marking basic rates; values past 54 Mbit/s
+    // are BSS membership selectors (HT, VHT, SAE H2E, ...).
+    let legacy_mbps = ies
+        .iter()
+        .filter(|ie| ie.id == IE_SUPP_RATES || ie.id == IE_EXT_SUPP_RATES)
+        .flat_map(|ie| ie.data.iter())
+        .map(|b| b & 0x7f)
+        .filter(|r| (1..=108).contains(r))
+        .max()
+        .map(|r| r as f32 / 2.0);
+    let mut p = PhyRates {
+        legacy_mbps,
+        ..PhyRates::default()
+    };
+    // HT Capabilities Info (2, short GI at 20 / 40 MHz in bits 5 / 6),
+    // A-MPDU Parameters (1), then the Supported MCS Set: one byte of MCS
+    // bits per spatial stream for MCS 0-31.
+    if let Some(ht) = ies.iter().find(|ie| ie.id == IE_HT_CAP) {
+        if let (Some(&info), Some(mcs)) = (ht.data.first(), ht.data.get(3..7)) {
+            p.ht_streams = mcs.iter().rposition(|&m| m != 0).map_or(0, |i| i as u8 + 1);
+            p.ht_sgi20 = info & 1 << 5 != 0;
+            p.ht_sgi40 = info & 1 << 6 != 0;
+        }
+    }
+    // VHT Capabilities Info (4, short GI at 80 / 160 MHz in bits 5 / 6),
+    // then the Rx VHT-MCS map.
+    if let Some(vht) = ies.iter().find(|ie| ie.id == IE_VHT_CAP) {
+        if let [info, _, _, _, lo, hi, ..] = *vht.data {
+            p.vht_mcs_map = Some(u16::from_le_bytes([lo, hi]));
+            p.vht_sgi80 = info & 1 << 5 != 0;
+            p.vht_sgi160 = info & 1 << 6 != 0;
+        }
+    }
+    let ext = |id: u8| ies.iter().find(|ie| ie.id == IE_EXTENSION && ie.data.first() == Some(&id));
+    // ext id, HE MAC Capabilities (6), HE PHY Capabilities (11), then the
+    // Rx HE-MCS map for 80 MHz and less.
+    if let Some(&[lo, hi]) = ext(EXT_HE_CAP).and_then(|he| he.data.get(18..20)) {
+        p.he_mcs_map = Some(u16::from_le_bytes([lo, hi]));
+    }
+    p.eht = ext(EXT_EHT_CAP).is_some();
+    p
+}
+
+/// MLD MAC address from the Basic Multi-Link element of a Wi-Fi 7 AP. Every
+/// link (BSS) of one multi-link AP carries the same address, whatever band
+/// it is on and whatever its own BSSID.
+pub fn parse_mld_addr(ies: &IeList) -> Option<[u8; 6]> {
+    let ie = ies.iter().find(|ie| ie.id == IE_EXTENSION && ie.data.first() == Some(&EXT_MULTI_LINK))?;
+    // ext id, Multi-Link Control (type in bits 0-2, 0 = Basic), then the
+    // Common Info: its length, then the MLD MAC address.
+    let d = ie.data;
+    if d.get(1)? & 0x07 != 0 || *d.get(3)? < 7 {
+        return None;
+    }
+    d.get(4..10)?.try_into().ok()
+}
+
+/// The Multiple BSSID element(s) of a transmitting BSS: one radio
+/// beaconing for up to 2^max_indicator BSSIDs, the others (nontransmitted)
+/// only described in its beacon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipleBssid {
+    /// n: the set is the 2^n addresses that share all but the low n bits
+    /// of the transmitted BSSID.
+    pub max_indicator: u8,
+    /// BSSID-Index of each nontransmitted profile listed, in order.
+    pub indexes: Vec<u8>,
+}
+
+impl MultipleBssid {
+    /// Whether `addr` is in the set of transmitted BSSID `tx`.
+    pub fn contains(&self, tx: &[u8; 6], addr: &[u8; 6]) -> bool {
+        let mask = !((1u64 << self.max_indicator) - 1);
+        mac_u64(tx) & mask == mac_u64(addr) & mask
+    }
+
+    /// The nontransmitted BSSIDs of `tx`'s profiles: the low n bits of the
+    /// address plus the index, modulo 2^n.
+    pub fn bssids(&self, tx: &[u8; 6]) -> Vec<[u8; 6]> {
+        let n = self.max_indicator;
+        let low = (1u64 << n) - 1;
+        let base = mac_u64(tx);
+        self.indexes
+            .iter()
+            .map(|&i| {
+                let addr = (base & !low) | ((base & low).wrapping_add(i as u64) & low);
+                addr.to_be_bytes()[2..].try_into().unwrap_or(*tx)
+            })
+            .collect()
+    }
+}
+
+fn mac_u64(mac: &[u8; 6]) -> u64 {
+    mac.iter().fold(0, |acc, &b| acc << 8 | b as u64)
+}
+
+/// Multiple BSSID elements (an AP may split its profiles over several):
+/// MaxBSSID Indicator, then subelements; each Nontransmitted BSSID Profile
+/// (subelement 0) holds that BSS's elements, its Multiple BSSID-Index
+/// among them.
+pub fn parse_multiple_bssid(ies: &IeList) -> Option<MultipleBssid> {
+    let mut out: Option<MultipleBssid> = None;
+    for ie in ies.iter().filter(|ie| ie.id == IE_MULTIPLE_BSSID) {
+        let Some((&n, mut subs)) = ie.data.split_first() else {
+            continue;
+        };
+        if !(1..=8).contains(&n) {
+            continue;
+        }
+        let set = out.get_or_insert_with(|| MultipleBssid {
+            max_indicator: n,
+            indexes: Vec::new(),
+        });
+        while let [id, len, rest @ ..] = subs {
+            let Some(body) = rest.get(..*len as usize) else {
+                break;
+            };
+            if *id == 0 {
+                let index = ie_list(body)
+                    .iter()
+                    .find(|e| e.id == IE_MULTIPLE_BSSID_INDEX)
+                    .and_then(|e| e.data.first().copied());
+                if let Some(i) = index.filter(|&i| i > 0 && !set.indexes.contains(&i)) {
+                    set.indexes.push(i);
+                }
+            }
+            subs = &rest[*len as usize..];
+        }
+    }
+    out
+}
+
+/// One AP listed in a Reduced Neighbor Report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RnrNeighbor {
+    pub op_class: u8,
+    /// Primary channel in `op_class`.
+    pub channel: u8,
+    pub bssid: Option<[u8; 6]>,
+    /// CRC-32 of the SSID.
+    pub short_ssid: Option<u32>,
+    /// BSS Parameters: it has the reporting BSS's SSID.
+    pub same_ssid: bool,
+    /// BSS Parameters: it is a radio of the reporting AP.
+    pub co_located: bool,
+}
+
+// BSS Parameters bits.
+const RNR_SAME_SSID: u8 = 1 << 1;
+const RNR_CO_LOCATED: u8 = 1 << 6;
+
+/// Every AP of the Reduced Neighbor Report elements: Neighbor AP
+/// Information fields, each a TBTT Information Header (type, count - 1,
+/// length), operating class, channel, then `count` TBTT Information
+/// fields whose length says which of BSSID, short SSID and BSS Parameters
+/// follow the TBTT offset.
+pub fn parse_rnr(ies: &IeList) -> Vec<RnrNeighbor> {
+    let mut out = Vec::new();
+    for ie in ies.iter().filter(|ie| ie.id == IE_RNR) {
+        let mut d = ie.data;
+        while let [hdr, len, op_class, channel, rest @ ..] = d {
+            let count = (hdr >> 4) as usize + 1;
+            let len = *len as usize;
+            let Some(infos) = rest.get(..count * len) else {
+                break;
+            };
+            // Type 0 is the only one defined; others can only be skipped.
+            if hdr & 0x03 == 0 {
+                for info in infos.chunks_exact(len.max(1)) {
+                    let (bssid, short_ssid, params) = match len {
+                        2 => (None, None, info.get(1)),
+                        5 | 6 => (None, info.get(1..5), info.get(5)),
+                        7..=10 => (info.get(1..7), None, info.get(7)),
+                        11.. => (info.get(1..7), info.get(7..11), info.get(11)),
+                        _ => (None, None, None),
+                    };
+                    let params = params.copied().unwrap_or(0);
+                    out.push(RnrNeighbor {
+                        op_class: *op_class,
+                        channel: *channel,
+                        bssid: bssid.and_then(|b| b.try_into().ok()),
+                        short_ssid: short_ssid.and_then(|s| s.try_into().ok()).map(u32::from_le_bytes),
+                        same_ssid: params & RNR_SAME_SSID != 0,
+                        co_located: params & RNR_CO_LOCATED != 0,
+                    });
+                }
+            }
+            d = &rest[count * len..];
+        }
+    }
+    out
+}
+
+/// Frequency and width (MHz) of `channel` in a 6 GHz global operating
+/// class (131 to 137); None for other bands.
+pub fn op_class_6ghz(op_class: u8, channel: u8) -> Option<(u32, u32)> {
+    let width = match op_class {
+        131 | 136 => 20,
+        132 => 40,
+        133 => 80,
+        134 | 135 => 160,
+        137 => 320,
+        _ => return None,
+    };
+    let freq = if op_class == 136 { 5935 } else { 5950 + 5 * channel as u32 };
+    (5925..=7125).contains(&freq).then_some((freq, width))
+}
+
+/// Active path selection protocol of an 802.11s mesh.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathSelection {
+    /// Hybrid Wireless Mesh Protocol, the mandatory default.
+    #[default]
+    Hwmp,
+    /// Vendor specific (identifier 255), e.g. a B.A.T.M.A.N. overlay.
+    Vendor,
+    Other(u8),
+}
+
+impl PathSelection {
+    fn from_id(id: u8) -> PathSelection {
+        match id {
+            1 => PathSelection::Hwmp,
+            255 => PathSelection::Vendor,
+            _ => PathSelection::Other(id),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PathSelection::Hwmp => "hwmp",
+            PathSelection::Vendor => "vendor",
+            PathSelection::Other(_) => "unknown",
+        }
+    }
+
+    /// Inverse of name(); "unknown" can't say which identifier it was.
+    pub fn from_name(name: &str) -> Option<PathSelection> {
+        match name {
+            "hwmp" => Some(PathSelection::Hwmp),
+            "vendor" => Some(PathSelection::Vendor),
+            _ => None,
+        }
+    }
+}
+
+/// What an 802.11s mesh point advertises in its Mesh Configuration
+/// element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MeshConfig {
+    pub path_selection: PathSelection,
+    /// Path selection metric identifier: 1 is the airtime metric.
+    pub metric: u8,
+    /// Authentication protocol identifier: 0 none (open mesh), 1 SAE,
+    /// 2 IEEE 802.1X.
+    pub auth: u8,
+    /// Peerings the mesh point has (Mesh Formation Info, up to 63).
+    pub peerings: u8,
+    /// It has a path to a mesh gate, i.e. to a wired network.
+    pub gate: bool,
+    /// It accepts more mesh peerings.
+    pub accepting_peers: bool,
+}
+
+/// Whether the BSS carries a Mesh ID element: an 802.11s mesh point, which
+/// beacons the wildcard SSID and names its mesh there instead.
+pub fn has_mesh_id(ies: &IeList) -> bool {
+    ies.iter().any(|ie| ie.id == IE_MESH_ID)
+}
+
+/// The Mesh ID, the 802.11s counterpart of the SSID. None without the
+/// element; a mesh point always sends it, possibly empty.
+pub fn parse_mesh_id(ies: &IeList) -> Option<Vec<u8>> {
+    ies.iter().find(|ie| ie.id == IE_MESH_ID).map(|ie| ie.data.to_vec())
+}
+
+/// The Mesh Configuration element: path selection protocol and metric,
+/// congestion control, synchronization and authentication identifiers
+/// (one byte each), then Mesh Formation Info (bit 0 connected to a gate,
+/// bits 1-6 the number of peerings) and Mesh Capability (bit 0 accepting
+/// additional peerings).
+pub fn parse_mesh_config(ies: &IeList) -> Option<MeshConfig> {
+    let ie = ies.iter().find(|ie| ie.id == IE_MESH_CONFIG)?;
+    let [path, metric, _, _, auth, formation, capability, ..] = *ie.data else {
+        return None;
+    };
+    Some(MeshConfig {
+        path_selection: PathSelection::from_id(path),
+        metric,
+        auth,
+        peerings: formation >> 1 & 0x3f,
+        gate: formation & 1 != 0,
+        accepting_peers: capability & 1 != 0,
+    })
+}
+
+/// What a BSS advertises in its Interworking element (802.11u).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interworking {
+    /// Access Network Type, 0 to 15 (access_network_name()).
+    pub access_network_type: u8,
+    /// It says it reaches the internet.
+    pub internet: bool,
+    /// Venue group and type, when announced (venue_group_name()).
+    pub venue: Option<(u8, u8)>,
+    /// Homogeneous ESS identifier: one per operator network, shared by
+    /// all its APs.
+    pub hessid: Option<[u8; 6]>,
+}
+
+/// The Interworking element: Access Network Options (type in bits 0-3,
+/// Internet in bit 4), then Venue Info (2) and HESSID (6), each optional,
+/// told apart by the length.
+pub fn parse_interworking(ies: &IeList) -> Option<Interworking> {
+    let ie = ies.iter().find(|ie| ie.id == IE_INTERWORKING)?;
+    let (&options, rest) = ie.data.split_first()?;
+    let (venue, hessid) = match rest.len() {
+        2 => (rest.get(..2), None),
+        6 => (None, rest.get(..6)),
+        8.. => (rest.get(..2), rest.get(2..8)),
+        _ => (None, None),
+    };
+    Some(Interworking {
+        access_network_type: options & 0x0f,
+        internet: options & 1 << 4 != 0,
+        venue: venue.map(|v| (v[0], v[1])),
+        hessid: hessid.and_then(|h| h.try_into().ok()),
+    })
+}
+
+/// Access Network Type as 802.11u names it.
+pub fn access_network_name(t: u8) -> &'static str {
+    match t {
+        0 => "private",
+        1 => "private_guest",
+        2 => "chargeable_public",
+        3 => "free_public",
+        4 => "personal_device",
+        5 => "emergency_only",
+        14 => "test",
+        15 => "wildcard",
+        _ => "reserved",
+    }
+}
+
+/// Venue group as 802.11u names it.
+pub fn venue_group_name(group: u8) -> &'static str {
+    match group {
+        0 => "unspecified",
+        1 => "assembly",
+        2 => "business",
+        3 => "educational",
+        4 => "industrial",
+        5 => "institutional",
+        6 => "mercantile",
+        7 => "residential",
+        8 => "storage",
+        9 => "utility",
+        10 => "vehicular",
+        11 => "outdoor",
+        _ => "reserved",
+    }
+}
+
+/// The Passpoint release (1 to 3) of the Hotspot 2.0 Indication element,
+/// None without one: its first byte's bits 4-7 count from 0 for release 1.
+pub fn parse_hs20(ies: &IeList) -> Option<u8> {
+    let ie = ies
+        .iter()
+        .find(|ie| ie.id == IE_VENDOR && ie.data.get(..4) == Some(&[OUI_WFA[0], OUI_WFA[1], OUI_WFA[2], WFA_TYPE_HS20]))?;
+    Some(ie.data.get(4).map_or(0, |conf| conf >> 4) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An IE blob out of (id, body) pairs.
+    fn blob(elems: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (id, body) in elems {
+            out.push(*id);
+            out.push(body.len() as u8);
+            out.extend_from_slice(body);
+        }
+        out
+    }
+
+    // An RSN element body with CCMP as the group cipher.
+    fn rsn(ciphers: &[u8], akms: &[u8], caps: Option<u16>) -> Vec<u8> {
+        let mut v = vec![1, 0, 0x00, 0x0f, 0xac, 4];
+        for list in [ciphers, akms] {
+            v.extend_from_slice(&(list.len() as u16).to_le_bytes());
+            for &s in list {
+                v.extend_from_slice(&[0x00, 0x0f, 0xac, s]);
+            }
+        }
+        if let Some(caps) = caps {
+            v.extend_from_slice(&caps.to_le_bytes());
+        }
+        v
+    }
+
+    // The WPA vendor element body: TKIP group and pairwise, PSK.
+    fn wpa1() -> Vec<u8> {
+        let mut v = vec![0x00, 0x50, 0xf2, 1, 1, 0, 0x00, 0x50, 0xf2, 2];
+        v.extend_from_slice(&[1, 0, 0x00, 0x50, 0xf2, 2]);
+        v.extend_from_slice(&[1, 0, 0x00, 0x50, 0xf2, 2]);
+        v
+    }
+
+    fn security(elems: &[(u8, &[u8])]) -> Security {
+        parse_security(&ie_list(&blob(elems)))
+    }
+
+    #[test]
+    fn security_follows_the_rsn_akms() {
+        assert_eq!(security(&[]), Security::Open);
+        assert_eq!(security(&[(IE_VENDOR, &wpa1())]), Security::Wpa);
+        assert_eq!(security(&[(IE_RSN, &rsn(&[4], &[2], None))]), Security::Wpa2);
+        assert_eq!(security(&[(IE_RSN, &rsn(&[4], &[2, 8], None))]), Security::Wpa2Wpa3);
+        assert_eq!(security(&[(IE_RSN, &rsn(&[4], &[8], None))]), Security::Wpa3);
+        assert_eq!(security(&[(IE_RSN, &rsn(&[4], &[1, 2], None))]), Security::Enterprise);
+        assert_eq!(security(&[(IE_RSN, &rsn(&[4], &[18], None))]), Security::Owe);
+        // RSN wins over a WPA element next to it.
+        assert_eq!(security(&[(IE_VENDOR, &wpa1()), (IE_RSN, &rsn(&[4, 2], &[8], None))]), Security::Wpa3);
+    }
+
+    #[test]
+    fn rsn_suites_past_the_end_are_not_read() {
+        // The AKM count says 3, one suite follows.
+        let mut body = rsn(&[4], &[8], None);
+        body[12] = 3;
+        assert_eq!(security(&[(IE_RSN, &body)]), Security::Wpa2);
+
+        // The pairwise count runs past the end, so the AKMs can't be found.
+        let mut body = rsn(&[4], &[8], None);
+        body[6] = 9;
+        assert_eq!(security(&[(IE_RSN, &body)]), Security::Wpa2);
+
+        // Cut off inside the last AKM suite.
+        let body = rsn(&[4], &[8], None);
+        assert_eq!(security(&[(IE_RSN, &body[..body.len() - 2])]), Security::Wpa2);
+
+        // Version only, and a vendor AKM we don't know.
+        assert_eq!(security(&[(IE_RSN, &[1, 0])]), Security::Wpa2);
+        let mut body = rsn(&[4], &[], None);
+        body[8] = 1;
+        body.extend_from_slice(&[0x00, 0x40, 0x96, 8]);
+        assert_eq!(security(&[(IE_RSN, &body)]), Security::Wpa2);
+    }
+
+    #[test]
+    fn country_code_is_two_letters() {
+        let cc = |body: &[u8]| parse_country(&ie_list(&blob(&[(IE_COUNTRY, body)])));
+        assert_eq!(cc(b"US "), Some(*b"US"));
+        assert_eq!(cc(b"DE"), Some(*b"DE"));
+        assert_eq!(cc(b"D"), None);
+        assert_eq!(cc(b"1A "), None);
+        assert_eq!(cc(b""), None);
+    }
+
+    #[test]
+    fn pmf_and_tkip_come_from_the_rsn_element() {
+        let pmf = |body: &[u8]| parse_pmf(&ie_list(&blob(&[(IE_RSN, body)])));
+        assert_eq!(pmf(&rsn(&[4], &[8], Some(0x00c0))), Some(Pmf::Required));
+        assert_eq!(pmf(&rsn(&[4], &[2], Some(0x0040))), Some(Pmf::Capable));
+        assert_eq!(pmf(&rsn(&[4], &[2], Some(0))), Some(Pmf::Disabled));
+        // No capabilities field, or suites that run past the end.
+        assert_eq!(pmf(&rsn(&[4], &[2], None)), Some(Pmf::Disabled));
+        let mut body = rsn(&[4], &[2], Some(0x00c0));
+        body[12] = 2;
+        assert_eq!(pmf(&body), Some(Pmf::Disabled));
+        assert_eq!(parse_pmf(&ie_list(&blob(&[(IE_VENDOR, &wpa1())]))), None);
+
+        let tkip = |elems: &[(u8, &[u8])]| offers_tkip(&ie_list(&blob(elems)));
+        assert!(tkip(&[(IE_RSN, &rsn(&[4, 2], &[2], None))]));
+        assert!(!tkip(&[(IE_RSN, &rsn(&[4], &[2], None))]));
+        assert!(tkip(&[(IE_RSN, &rsn(&[4], &[2], None)), (IE_VENDOR, &wpa1())]));
+        // Only the group cipher is TKIP.
+        let mut body = rsn(&[4], &[2], None);
+        body[5] = 2;
+        assert!(!tkip(&[(IE_RSN, &body)]));
+    }
+
+    #[test]
+    fn country_info_reads_the_subband_triplets() {
+        let info = |body: &[u8]| parse_country_info(&ie_list(&blob(&[(IE_COUNTRY, body)])));
+        let mut body = b"DEI".to_vec();
+        body.extend_from_slice(&[1, 13, 20, 36, 4, 23]);
+        let i = info(&body).unwrap();
+        assert_eq!((i.code, i.environment), (*b"DE", b'I'));
+        assert_eq!(i.channels, (1..=13).chain([36, 40, 44, 48]).collect::<Vec<u32>>());
+
+        // Shorter than one triplet: no channels; without the environment
+        // byte: nothing.
+        for body in [&b"US "[..], b"US \x01", b"US \x01\x0b"] {
+            assert_eq!(info(body).unwrap().channels, Vec::<u32>::new());
+        }
+        assert_eq!(info(b"US"), None);
+
+        // An operating triplet (first byte 201+) ends the subband list:
+        // the triplets after it belong to its operating class.
+        let mut body = b"US ".to_vec();
+        body.extend_from_slice(&[1, 11, 30, 201, 131, 0, 1, 24, 30]);
+        assert_eq!(info(&body).unwrap().channels, (1..=11).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn interworking_fields_are_told_apart_by_length() {
+        let iw = |body: &[u8]| parse_interworking(&ie_list(&blob(&[(IE_INTERWORKING, body)])));
+        let hessid = [0x02, 1, 2, 3, 4, 5];
+        assert_eq!(
+            iw(&[0x13]),
+            Some(Interworking {
+                access_network_type: 3,
+                internet: true,
+                venue: None,
+                hessid: None,
+            })
+        );
+        assert_eq!(iw(&[0x02, 2, 1]).map(|i| (i.internet, i.venue, i.hessid)), Some((false, Some((2, 1)), None)));
+        let with_hessid = [&[0x00][..], &hessid].concat();
+        assert_eq!(iw(&with_hessid).map(|i| (i.venue, i.hessid)), Some((None, Some(hessid))));
+        let both = [&[0x00, 7, 0][..], &hessid].concat();
+        assert_eq!(iw(&both).map(|i| (i.venue, i.hessid)), Some((Some((7, 0)), Some(hessid))));
+        // Neither length: options only.
+        assert_eq!(iw(&[0x01, 2, 1, 9]).map(|i| (i.venue, i.hessid)), Some((None, None)));
+        assert_eq!(iw(&[]), None);
+    }
+
+    #[test]
+    fn hs20_release_counts_from_one() {
+        let hs20 = |body: &[u8]| parse_hs20(&ie_list(&blob(&[(IE_VENDOR, body)])));
+        assert_eq!(hs20(&[0x50, 0x6f, 0x9a, 0x10, 0x20]), Some(3));
+        assert_eq!(hs20(&[0x50, 0x6f, 0x9a, 0x10, 0x00]), Some(1));
+        assert_eq!(hs20(&[0x50, 0x6f, 0x9a, 0x10]), Some(1));
+        assert_eq!(hs20(&[0x50, 0x6f, 0x9a, 0x1b, 0x20]), None);
+    }
+
+    // One Neighbor AP Information field: TBTT header for `infos`, all of
+    // length `len`.
+    fn nai(op_class: u8, channel: u8, len: u8, infos: &[&[u8]]) -> Vec<u8> {
+        let mut v = vec![(infos.len() as u8 - 1) << 4, len, op_class, channel];
+        for info in infos {
+            v.extend_from_slice(info);
+        }
+        v
+    }
+
+    #[test]
+    fn rnr_reads_every_tbtt_info_length() {
+        let bssid = [0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0x01];
+        let full = [&[0][..], &bssid, &0x1234_5678u32.to_le_bytes(), &[RNR_SAME_SSID | RNR_CO_LOCATED]].concat();
+        let mut body = nai(131, 37, 12, &[&full, &full]);
+        body.extend(nai(131, 5, 8, &[&[&[0][..], &bssid, &[0]].concat()]));
+        body.extend(nai(133, 21, 6, &[&[0, 0xef, 0xbe, 0xad, 0xde, RNR_SAME_SSID]]));
+        body.extend(nai(131, 1, 1, &[&[0]]));
+        let found = parse_rnr(&ie_list(&blob(&[(IE_RNR, &body)])));
+
+        assert_eq!(found.len(), 5);
+        assert_eq!(
+            found[0],
+            RnrNeighbor {
+                op_class: 131,
+                channel: 37,
+                bssid: Some(bssid),
+                short_ssid: Some(0x1234_5678),
+                same_ssid: true,
+                co_located: true,
+            }
+        );
+        assert_eq!(found[1], found[0]);
+        let n = found[2];
+        assert_eq!((n.channel, n.bssid, n.short_ssid, n.same_ssid), (5, Some(bssid), None, false));
+        assert_eq!((found[3].bssid, found[3].short_ssid, found[3].same_ssid), (None, Some(0xdead_beef), true));
+        assert_eq!((found[4].channel, found[4].bssid, found[4].short_ssid), (1, None, None));
+    }
+
+    #[test]
+    fn rnr_stops_at_a_truncated_field_and_skips_unknown_types() {
+        let info = [&[0][..], &[0x02, 1, 1, 1, 1, 1], &[0]].concat();
+        // Says two TBTT infos, carries one.
+        let mut short = nai(131, 5, 8, &[&info]);
+        short[0] = 0x10;
+        assert!(parse_rnr(&ie_list(&blob(&[(IE_RNR, &short)]))).is_empty());
+
+        // TBTT Information Field Type 1 is skipped whole, the next field
+        // still read.
+        let mut body = nai(131, 5, 8, &[&info]);
+        body[0] |= 0x01;
+        body.extend(nai(131, 21, 8, &[&info]));
+        let found = parse_rnr(&ie_list(&blob(&[(IE_RNR, &body)])));
+        assert_eq!(found.iter().map(|n| n.channel).collect::<Vec<_>>(), [21]);
+    }
+
+    #[test]
+    fn op_class_6ghz_maps_channels_and_widths() {
+        assert_eq!(op_class_6ghz(131, 5), Some((5975, 20)));
+        assert_eq!(op_class_6ghz(133, 7), Some((5985, 80)));
+        assert_eq!(op_class_6ghz(136, 2), Some((5935, 20)));
+        assert_eq!(op_class_6ghz(137, 31), Some((6105, 320)));
+        assert_eq!(op_class_6ghz(131, 250), None);
+        assert_eq!(op_class_6ghz(115, 36), None);
+    }
+
+    #[test]
+    fn vendor_elements_say_multi_ap_and_p2p() {
+        let ies = blob(&[
+            (IE_VENDOR, &[0x50, 0x6f, 0x9a, WFA_TYPE_MULTI_AP, 0x06, 0x01, 0x20]),
+            (IE_VENDOR, &[0x00, 0x10, 0x18, 0x02, 0x00]),
+            (IE_VENDOR, &[0x00, 0x50, 0xf2, 0x02, 0x01]),
+            (IE_VENDOR, &[0x00, 0x10, 0x18, 0x01]),
+            (IE_VENDOR, &[0x8c, 0xfd]),
+        ]);
+        let ies = ie_list(&ies);
+        assert!(has_multi_ap(&ies));
+        assert!(!has_p2p(&ies));
+        assert_eq!(vendor_ouis(&ies), [[0x00, 0x10, 0x18]]);
+
+        let p2p = blob(&[(IE_VENDOR, &[0x50, 0x6f, 0x9a, WFA_TYPE_P2P, 0x02, 0x02, 0x00, 0x25, 0x00])]);
+        let p2p = ie_list(&p2p);
+        assert!(has_p2p(&p2p));
+        assert!(!has_multi_ap(&p2p));
+        // The type byte is missing.
+        assert!(!has_multi_ap(&ie_list(&blob(&[(IE_VENDOR, &[0x50, 0x6f, 0x9a])]))));
+    }
+
+    #[test]
+    fn mesh_config_and_id() {
+        // HWMP, airtime, SAE, 3 peerings and a gate, accepting peers.
+        let cfg = [1, 1, 0, 1, 1, 3 << 1 | 1, 1];
+        let ies = blob(&[(IE_MESH_ID, b"mesh0"), (IE_MESH_CONFIG, &cfg)]);
+        let ies = ie_list(&ies);
+        assert!(has_mesh_id(&ies));
+        assert_eq!(parse_mesh_id(&ies).as_deref(), Some(&b"mesh0"[..]));
+        assert_eq!(
+            parse_mesh_config(&ies),
+            Some(MeshConfig {
+                path_selection: PathSelection::Hwmp,
+                metric: 1,
+                auth: 1,
+                peerings: 3,
+                gate: true,
+                accepting_peers: true,
+            })
+        );
+
+        let vendor = blob(&[(IE_MESH_ID, b""), (IE_MESH_CONFIG, &[255, 1, 0, 0, 0, 0, 0])]);
+        let vendor = ie_list(&vendor);
+        assert_eq!(parse_mesh_id(&vendor), Some(Vec::new()));
+        assert_eq!(parse_mesh_config(&vendor).map(|c| c.path_selection), Some(PathSelection::Vendor));
+        assert_eq!(parse_mesh_config(&ie_list(&blob(&[(IE_MESH_CONFIG, &cfg[..6])]))), None);
+        assert!(!has_mesh_id(&ie_list(&blob(&[(IE_MESH_CONFIG, &cfg)]))));
+    }
+
+    // A WPS element body out of (attribute type, value) pairs.
+    fn wps(attrs: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut v = vec![0x00, 0x50, 0xf2, MS_TYPE_WPS];
+        for (t, value) in attrs {
+            v.extend_from_slice(&t.to_be_bytes());
+            v.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            v.extend_from_slice(value);
+        }
+        v
+    }
+
+    #[test]
+    fn wps_state_lock_and_pin_methods() {
+        let parse = |bodies: &[&[u8]]| {
+            let elems: Vec<(u8, &[u8])> = bodies.iter().map(|b| (IE_VENDOR, *b)).collect();
+            parse_wps(&ie_list(&blob(&elems)))
+        };
+        let version = (0x104a, &[0x10][..]);
+
+        let w = parse(&[&wps(&[version, (WPS_STATE, &[2])])]).unwrap();
+        assert_eq!((w.configured, w.locked, w.pin), (true, false, true));
+        assert!(w.pin_vulnerable());
+
+        let w = parse(&[&wps(&[version, (WPS_STATE, &[1]), (WPS_AP_SETUP_LOCKED, &[1])])]).unwrap();
+        assert_eq!((w.configured, w.locked), (false, true));
+        assert!(!w.pin_vulnerable());
+
+        // Push button only.
+        let w = parse(&[&wps(&[(WPS_CONFIG_METHODS, &[0x00, 0x80])])]).unwrap();
+        assert!(!w.pin);
+
+        // Split over two elements, the lock in the second.
+        let whole = wps(&[(WPS_STATE, &[2]), (WPS_AP_SETUP_LOCKED, &[1])]);
+        let second = [&whole[..4], &whole[9..]].concat();
+        assert!(parse(&[&whole[..9], &second]).unwrap().locked);
+
+        // An attribute longer than what's left ends the walk.
+        let mut cut = wps(&[(WPS_STATE, &[2]), (WPS_AP_SETUP_LOCKED, &[1])]);
+        cut[11] = 9;
+        assert!(!parse(&[&cut]).unwrap().locked);
+
+        assert_eq!(parse(&[&[0x00, 0x50, 0xf2, 1, 1, 0]]), None);
+    }
+
+    #[test]
+    fn dtim_period_from_the_tim() {
+        let dtim = |body: &[u8]| parse_dtim_period(&ie_list(&blob(&[(IE_TIM, body)])));
+        assert_eq!(dtim(&[0, 3, 0, 0]), Some(3));
+        assert_eq!(dtim(&[2, 1, 0, 0]), Some(1));
+        assert_eq!(dtim(&[0, 0, 0, 0]), None);
+        assert_eq!(dtim(&[0]), None);
+        assert_eq!(parse_dtim_period(&ie_list(&[])), None);
+    }
+
+    #[test]
+    fn mld_address_from_the_basic_multi_link_element() {
+        let mld = [0x02, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let ml = |ctrl: u8, info_len: u8, addr: &[u8]| {
+            let body = [&[EXT_MULTI_LINK, ctrl, 0x00, info_len][..], addr, &[0x01]].concat();
+            parse_mld_addr(&ie_list(&blob(&[(IE_EXTENSION, &body)])))
+        };
+        assert_eq!(ml(0x00, 8, &mld), Some(mld));
+        // Probe Request (type 1) and Reconfiguration (type 2) variants.
+        assert_eq!(ml(0x01, 8, &mld), None);
+        assert_eq!(ml(0x02, 8, &mld), None);
+        // Common Info too short to hold the address, or cut off.
+        assert_eq!(ml(0x00, 6, &mld), None);
+        assert_eq!(ml(0x00, 8, &mld[..4]), None);
+    }
+
+    fn operation(elems: &[(u8, &[u8])]) -> Option<Operation> {
+        parse_operation(&ie_list(&blob(elems)))
+    }
+
+    #[test]
+    fn ht_and_vht_operation_give_width_and_centre() {
+        // Secondary above, 40 MHz allowed.
+        let ht40 = [36, 0x05, 0, 0, 0];
+        assert_eq!(
+            operation(&[(IE_HT_OPERATION, &ht40)]),
+            Some(Operation {
+                width_mhz: 40,
+                primary: Some(36),
+                secondary: Some(1),
+                center: Some(38),
+            })
+        );
+        // Secondary below but 40 MHz not allowed: 20 MHz.
+        let ht20 = [6, 0x03, 0, 0, 0];
+        assert_eq!(
+            operation(&[(IE_HT_OPERATION, &ht20)]).map(|o| (o.width_mhz, o.secondary, o.center)),
+            Some((20, None, None))
+        );
+        let wide = |vht: &[u8]| {
+            operation(&[(IE_HT_OPERATION, &ht40), (IE_VHT_OPERATION, vht)]).map(|o| (o.width_mhz, o.center))
+        };
+        assert_eq!(wide(&[1, 42, 0, 0, 0]), Some((80, Some(42))));
+        assert_eq!(wide(&[1, 42, 50, 0, 0]), Some((160, Some(50))));
+        assert_eq!(wide(&[1, 42, 106, 0, 0]), Some((160, None)));
+        assert_eq!(wide(&[2, 50, 0, 0, 0]), Some((160, Some(50))));
+        // VHT width 0 leaves the HT 40 MHz, a cut-off element too.
+        assert_eq!(wide(&[0, 0, 0, 0, 0]), Some((40, Some(38))));
+        assert_eq!(wide(&[1, 42]), Some((40, Some(38))));
+        assert_eq!(operation(&[(IE_VHT_OPERATION, &[1, 42, 0, 0, 0])]), None);
+    }
+
+    #[test]
+    fn he_operation_carries_the_6ghz_channel() {
+        // HE Operation Parameters with the 6 GHz Operation Information
+        // present (bit 17), BSS color, Basic HE-MCS, then primary,
+        // control (width), CCFS0, CCFS1, min rate.
+        let he = |params: u32, info: &[u8]| {
+            let p = params.to_le_bytes();
+            let body = [&[EXT_HE_OPERATION, p[0], p[1], p[2], 0x01, 0xfc, 0xff][..], info].concat();
+            operation(&[(IE_EXTENSION, &body)])
+        };
+        assert_eq!(
+            he(1 << 17, &[37, 2, 39, 0, 6]),
+            Some(Operation {
+                width_mhz: 80,
+                primary: Some(37),
+                secondary: Some(-1),
+                center: Some(39),
+            })
+        );
+        let wide = he(1 << 17, &[5, 3, 7, 15, 6]).map(|o| (o.width_mhz, o.secondary, o.center));
+        assert_eq!(wide, Some((160, Some(-1), Some(15))));
+        assert_eq!(he(1 << 17, &[5, 0, 5, 0, 6]).map(|o| (o.width_mhz, o.secondary)), Some((20, None)));
+        // A VHT Operation Information (bit 14) comes first.
+        assert_eq!(he(1 << 17 | 1 << 14, &[0, 0, 0, 21, 1, 23, 0, 6]).map(|o| o.primary), Some(Some(21)));
+        // No 6 GHz information, or cut short.
+        assert_eq!(he(0, &[37, 2, 39, 0, 6]), None);
+        assert_eq!(he(1 << 17, &[37, 2]), None);
+    }
+
+    #[test]
+    fn bss_load_stations_and_utilization() {
+        let load = |body: &[u8]| parse_bss_load(&ie_list(&blob(&[(IE_BSS_LOAD, body)])));
+        assert_eq!(
+            load(&[0x03, 0x01, 51, 0, 0]),
+            Some(BssLoad {
+                stations: 259,
+                utilization: 0.2,
+            })
+        );
+        assert_eq!(load(&[0, 0, 255, 0, 0]).map(|l| l.utilization), Some(1.0));
+        assert_eq!(load(&[3, 0]), None);
+    }
+}
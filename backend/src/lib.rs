@@ -2,97 +2,5218 @@
 //
 // PyO3 wrapper for the wifi_backend module.
 // Exports to Python:
-//   - scan() -> list[dict]
-//   - compute_channels() -> dict[channel -> count]
-//   - compute_best_channel() -> int
-//   - connected_bssid() -> str | None
+//   - CancelToken(): cancel() / reset() / cancelled, passed as `cancel=`
+//     to the long-running calls below
+//   - ScanOptions(passive=False, freqs=None, band=None, ssids=None,
+//     flush=False, random_mac=False, mac=None, mac_mask=None,
+//     busy_retries=3, busy_backoff=0.25, join_busy=False,
+//     cached_only=False), passed as `options=` to the scans;
+//     random_mac_supported() -> bool; last_scan_info() -> dict | None
+//   - WifiSession(backend=None, iface=None): scan() / scan_dicts() /
+//     compute_channels() / compute_best_channel() /
+//     compute_best_channel_plan() / channel_scores() /
+//     interference_matrix() / connected_bssid() / link_info() /
+//     snapshot() / survey() / phy_capabilities() on an interface looked
+//     up once
+//   - list_interfaces() -> list[dict]: name, ifindex, mac, type, wiphy; the
+//     names go to the `iface=` arguments below
+//   - snapshot(cancel=None) -> ScanSnapshot: rows() / channels() /
+//     best_channel() / best_channel_plan() / best_channel_for_band() /
+//     channel_scores() / interference_matrix() /
+//     connected_entry() / hidden_network_count() / rnr_neighbors() /
+//     group_by_ssid() / suggest_roam_target() / device_groups() /
+//     security_report() / regulatory_mismatches() on one scan; to_json(pretty=False) -> str / ScanSnapshot.from_json(text) /
+//     export_csv(path) -> int / export_wigle_csv(path, lat, lon, alt=None,
+//     accuracy_m=None) -> int
+//   - scan_json(pretty=False, cancel=None, iface=None) -> str: a snapshot
+//     as a versioned JSON document (export.rs)
+//   - diff_scans(old, new, min_rssi_delta=10.0) -> dict: BSSs appeared /
+//     disappeared, channel and signal changes between two snapshots
+//   - hidden_network_count(channel=None, cancel=None, iface=None) -> int
+//   - rnr_neighbors(cancel=None, iface=None) -> list[dict]: 6 GHz BSSs
+//     known from Reduced Neighbor Reports, counted on 6 GHz
+//   - group_by_ssid(cancel=None, iface=None) -> list[dict]: networks with
+//     their BSSIDs, bands, strongest signal per band and a mesh guess
+//   - suggest_roam_target(hysteresis_db=8.0, cancel=None, iface=None) ->
+//     dict | None: the best BSS of the connected network, and whether we
+//     stick to a distant node
+//   - device_groups(cancel=None, iface=None) -> list[dict]: the scan's
+//     BSSIDs by physical device, each radio with its channel
+//   - detect_anomalies(own_ssids=[], window=None, cancel=None, iface=None)
+//     -> list[dict]: rogue AP / evil-twin findings against the scan
+//     history, with severity
+//   - security_report(cancel=None, iface=None) -> list[dict]: per network
+//     the weakest security, PMF, WPS and a grade
+//   - regulatory_mismatches(cancel=None, iface=None) -> dict: the
+//     countries APs claim against our regulatory domain
+//   - BssEntry: one BSS, typed attributes, to_dict()
+//   - scan(cancel=None, options=None, iface=None) -> list[BssEntry]
+//   - scan_dicts(details=False, fields=None, cancel=None, options=None, iface=None) -> list[dict]
+//   - scan_merged(details=False, fields=None, cancel=None, options=None) ->
+//     list[dict]: every radio's scan, de-duplicated, "iface" per row
+//   - scan_stream(details=False, fields=None, cancel=None, options=None) ->
+//     iterator of the same dicts, as they are parsed
+//   - scan_n(times=3, interval=1.0, details=False, fields=None, progress=None, cancel=None) -> list[dict]
+//   - scan_average(n=3, interval_ms=1000, alpha=0.3, progress=None, cancel=None) -> ScanSnapshot:
+//     RSSI smoothed per BSSID (EWMA) over n scans
+//   - compute_channels(band=None, detailed=False, iface=None) -> dict[channel -> count] | list[dict]
+//   - ChannelConfig(threshold_dbm=-80.0, margin=10.0, prefer_band=None,
+//     floor_dbm=-100.0, max_age_ms=None, legacy_weight=1.0), passed as
+//     `config=` to the best-channel calls
+//   - compute_best_channel(candidates=None, config=None, iface=None, snapshot=None)
+//     -> (band, channel); candidates are channel numbers or (band, channel)
+//   - compute_best_channel_plan(candidates=None, config=None, iface=None,
+//     snapshot=None) -> (band, channel, width_mhz): the same channel and the
+//     width to run it at
+//   - channel_scores(candidates=None, config=None, iface=None, snapshot=None) -> list[dict]:
+//     every channel compute_best_channel() weighed, ranked, the pick marked
+//   - interference_matrix(band=None, config=None, iface=None, snapshot=None) -> list[dict]:
+//     per band, what the APs on each observed channel add to each
+//     candidate channel's weight
+//   - survey() -> list[dict]: noise floor and busy time per frequency
+//   - regulatory_domain() -> dict: country, rules and per-channel
+//     restrictions; the best-channel calls keep to its legal channels
+//   - phy_capabilities() -> list[dict]: the radio's bands, channels,
+//     HT / VHT / HE / EHT support and widths; likewise kept to
+//   - connected_bssid(iface=None) -> str | None
+//   - link_info(iface=None) -> dict | None: signal, bitrates, MCS, retries,
+//     drops and expected / estimated throughput of the link to the
+//     connected AP
+//   - scan_async() / scan_dicts_async() / compute_channels_async() /
+//     compute_best_channel_async() / channel_scores_async() /
+//     connected_bssid_async() / link_info_async() -> asyncio.Future: the
+//     calls above, same arguments, run off the asyncio event loop
+//   - LinkMonitor(interval=1.0, capacity=600, iface=None, backend=None):
+//     get_samples(since=None) / stop() / clear() / status() / running;
+//     link_info() polled in the background into a ring
+//   - subscribe_events(backend=None) -> EventStream: scan / connect /
+//     disconnect / roam events as they happen, next(timeout=None) / close();
+//     add_event_callback(callback, backend=None) -> int /
+//     remove_event_callback(id=None) -> bool
+//   - set_backend(name) / get_backend() -> str
+//   - set_p2p_policy(policy) / get_p2p_policy() -> str / set_exclude_ibss(exclude)
+//   - set_dry_run(enabled) / get_dry_run() -> bool: while on,
+//     capture_beacons() (and pybackend's validate_channel_change()) only
+//     check their inputs and report what they would change
+//   - set_device_grouping(rule="middle_bytes", vendors=None, devices=None,
+//     callback=None) / device_grouping() -> dict / same_device(a, b) ->
+//     bool: which BSSIDs count as one device (devgroup.rs)
+//   - set_log_level(level) / get_log_level() -> int: what the Rust side
+//     logs to the "wifi_backend" logger (scan triggers, retries, netlink
+//     errors); WARNING and above by default
+//   - load_mock_fixture(path) -> int / mock_fault(op, error, call=None)
+//   - push_android_scan(results, connection=None) -> int
+//   - score_history(window=None, progress=None, cancel=None) -> dict, history_len(), clear_history()
+//   - assign_mesh_channels_24(nodes, own_bssids=[], node_bssids=None, cancel=None) -> list[int]
+//   - mesh_node_rssi(nodes, node_bssids) -> list[list[float | None]]
+//   - neighbor_mesh_systems(scan=None, own_bssids=[]) -> list[dict]
+//   - band_steering(stations=None, radios=None, include_unknown=False) -> list[dict]
+//   - record_backhaul_sample(from_node, to_node, ...) / sample_backhaul(peers) -> int
+//   - backhaul_health() -> list[dict] / clear_backhaul()
+//   - classify_backhaul(nodes, gateway=None, overrides={}, max_age=300.0) -> list[dict]
+//   - airtime_report(window=5.0, pcap=None, own_bssids=[], progress=None, cancel=None) -> list[dict]
+//   - capture_beacons(iface, path, seconds=10.0, freq=None, cancel=None) -> dict:
+//     monitor-mode beacon / probe response capture to a radiotap pcap
+//     (capture.rs; capture feature builds only)
+//   - survey_start(own_ssids=[], node_names={}) /
+//     survey_sample(room=None, scan=None, connected_bssid=None, progress=None, cancel=None) -> dict
+//   - survey_report() -> list[dict] / survey_stop() -> list[dict]
+//   - assign_mesh_channels_5(nodes, own_bssids=[], node_bssids=None, follow=None, dfs=False,
+//     cancel=None) -> list[int]
+//   - assign_mesh_channels_6(nodes, own_bssids=[], node_bssids=None, follow=None,
+//     cancel=None) -> list[int]
+//   - plan_mesh_channels(nodes, own_bssids=[], node_bssids=None, bands=None,
+//     follow=None, dfs=False, cancel=None) -> list[dict]: channel and
+//     width per node per band, planned jointly
+//   - mesh_topology(scans, connected_bssid=None, own_ssids=[], format="json") -> str
+//   - set_location(lat, lon, alt=None, accuracy_m=None) / clear_location() / location() -> dict | None
+//   - export_geojson(observations=True, aps=True) -> str / export_kml(...) -> str
+//   - geo_len() -> int / clear_geo()
+//   - gpsd_start(host="127.0.0.1", port=2947, scan_interval=None) / gpsd_stop() / gpsd_status() -> dict
+//   - set_floor_plan(aps, rooms=[], exponent=3.0) / clear_floor_plan() /
+//     locate(scan=None, ranges=None) -> dict
+//   - probe_start(target=None, interval=1.0, timeout=1.0) / probe_stop() / probe_status() -> dict
+//   - latency_report(window=None) -> dict / clear_latency()
+//   - open_history_db(path) / close_history_db() / history_db_stats() -> dict
+//   - open_ring(capacity=4096) -> str / close_ring()
+//   - perf_stats() -> dict / reset_perf_stats()
+//   - about() -> dict: version, features, providers, capabilities
+//   - WifiError(RuntimeError) and its subclasses NoInterfaceError,
+//     PermissionDeniedError, ScanTimeoutError, ScanAbortedError,
+//     NetlinkError, UnsupportedError: what the calls above raise for
+//     Wi-Fi failures (error.rs); other failures stay RuntimeError
+//   - configure_threads(threads=None, pin=False, nice=None) / thread_config() -> dict
+//   - oui_vendor(bssid) -> str | None / load_oui_db(path) -> int
+//   - load_ap_models(path) -> int / learn_ap_model(fingerprint, name)
+//
+// Anything that talks to the kernel (or waits on the mock) runs inside
+// py.allow_threads, so a 4 s scan doesn't stall other Python threads; the
+// GIL is only held to read the arguments and build the results.
+
+// pyo3 0.22's #[pyfunction] expansion converts PyErr into PyErr, which
+// newer clippy flags on every function returning PyResult.
+#![allow(clippy::useless_conversion)]
 
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::{IntoPyDict, PyBytes, PyCFunction, PyDict, PyList};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{mpsc, Arc};
 
+mod airtime;
+mod android;
+mod anomaly;
+mod apmodel;
+mod backhaul;
+mod cancel;
+mod capture;
+mod chansurvey;
+mod devgroup;
+mod error;
+mod ess;
+mod events;
+mod export;
+mod fingerprint;
+mod geo;
+mod gpsd;
+mod history;
+mod history_db;
+mod ies;
 mod lib_rust;
+mod link;
+mod logs;
+mod linkmon;
+mod locate;
+mod mock;
+mod netlink;
+mod nl_raw;
+#[cfg(feature = "backend-neli-wifi")]
+mod nl_wifi;
+mod oui;
+mod pcap;
+mod perf;
+mod phycaps;
+mod phyrate;
+mod plan;
+mod pool;
+mod probe;
+mod progress;
+mod provider;
+mod regcheck;
+mod regdom;
+mod ring;
+mod rnr;
+mod roam;
+mod scandiff;
+mod scandump;
+mod secaudit;
+mod stamp;
+mod stations;
+mod steer;
+mod survey;
+mod topology;
+mod wpa_ctrl;
 use lib_rust::{
+    backend,
+    band_from_name,
+    band_name,
+    channel_breakdown,
     compute_best_channel_internal,
     compute_channels_internal,
     format_mac,
+    parse_mac,
     get_connected_bssid,
     scan_all_bss,
+    scan_all_bss_until,
+    scan_with,
+    scan_n as scan_n_internal,
+    scan_stream as scan_stream_internal,
+    set_backend as set_backend_internal,
+    Backend,
+    ChannelConfig,
+    ChannelLimits,
+    BssRow,
+    P2pPolicy,
+    ScanEvent,
 };
+use error::ErrorKind;
+use stamp::Stamp;
+
+// Parsing entry points for benches/; not part of the Python API.
+#[doc(hidden)]
+pub mod bench_api {
+    pub use crate::lib_rust::{ie_list, BssRow};
+    pub use crate::netlink::decode;
+    pub use crate::nl_raw::{parse_bss, parse_scan_payload, Attr};
+    pub use crate::scandump::{decode as decode_dump, encode as encode_dump, record as record_dump};
+}
+
+// The mock provider for Rust tests: load a fixture (or canned rows with
+// load_rows), select Backend::Mock, then call the same entry points the
+// pyfunctions use, or MockBackend's WifiBackend methods directly.
+#[doc(hidden)]
+pub mod test_api {
+    pub use crate::lib_rust::{get_connected_bssid, scan_all_bss, set_backend, Backend, BssRow, ScanOptions};
+    pub use crate::mock::{inject, load, load_rows, load_str, MockBackend, Op};
+    pub use crate::provider::WifiBackend;
+    pub use crate::stations::local_stations;
+}
 
 fn map_pyerr<T>(res: anyhow::Result<T>) -> PyResult<T> {
-    res.map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    // Python exceptions that passed through Rust (progress callbacks) are
+    // re-raised as they were.
+    res.map_err(|e| match e.downcast::<PyErr>() {
+        Ok(err) => err,
+        Err(e) => py_error(&e),
+    })
 }
 
-/// Python: scan() -> List[Dict]
-/// Each dict: {ssid, bssid, freq_mhz, signal_dbm, channel}
-#[pyfunction]
-fn scan(py: Python<'_>) -> PyResult<PyObject> {
-    let rows = map_pyerr(scan_all_bss())?;
+// The Python exception classes of error::WifiError. All derive from
+// WifiError, which derives from RuntimeError, so code catching
+// RuntimeError keeps working.
+// pyo3 0.22's create_exception! checks its own "gil-refs" feature, which
+// check-cfg doesn't know in this crate.
+#[allow(unexpected_cfgs)]
+mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyRuntimeError;
 
-    let list = PyList::empty_bound(py);
+    create_exception!(wifi_backend, WifiError, PyRuntimeError, "A Wi-Fi operation failed.");
+    create_exception!(wifi_backend, NoInterfaceError, WifiError, "No (matching) Wi-Fi interface, or it went away.");
+    create_exception!(wifi_backend, PermissionDeniedError, WifiError, "Not privileged enough (EPERM / EACCES).");
+    create_exception!(wifi_backend, ScanTimeoutError, WifiError, "The scan didn't finish in time.");
+    create_exception!(wifi_backend, ScanAbortedError, WifiError, "The driver or supplicant aborted the scan.");
+    create_exception!(wifi_backend, NetlinkError, WifiError, "Any other error reply from the kernel.");
+    create_exception!(wifi_backend, UnsupportedError, WifiError, "The driver, backend or build can't do this.");
+}
 
-    for r in rows {
-        let d = PyDict::new_bound(py);
+// `e` as the exception of its error::ErrorKind; RuntimeError without one.
+fn py_error(e: &anyhow::Error) -> PyErr {
+    use exceptions::*;
+    let msg = e.to_string();
+    match error::kind_of(e) {
+        Some(ErrorKind::NoInterface) => NoInterfaceError::new_err(msg),
+        Some(ErrorKind::PermissionDenied) => PermissionDeniedError::new_err(msg),
+        Some(ErrorKind::ScanTimeout) => ScanTimeoutError::new_err(msg),
+        Some(ErrorKind::ScanAborted) => ScanAbortedError::new_err(msg),
+        Some(ErrorKind::NetlinkError) => NetlinkError::new_err(msg),
+        Some(ErrorKind::Unsupported) => UnsupportedError::new_err(msg),
+        None => PyRuntimeError::new_err(msg),
+    }
+}
 
-        if let Some(ref ssid) = r.ssid {
-            d.set_item("ssid", ssid)?;
-        }
-        if let Some(ref mac) = r.bssid {
-            d.set_item("bssid", format_mac(mac))?;
-        }
-        if let Some(freq) = r.freq_mhz {
-            d.set_item("freq_mhz", freq)?;
-        }
-        if let Some(sig) = r.signal_dbm {
-            d.set_item("signal_dbm", sig)?;
+// Progress sink calling `progress(percent, stage, message)` in Python,
+// taking the GIL for each call. An exception from the callback stops the
+// operation and is re-raised from it.
+fn py_progress(cb: Option<PyObject>) -> progress::Progress<'static> {
+    match cb {
+        Some(cb) => progress::Progress::new(move |pct, stage, msg| {
+            Python::with_gil(|py| cb.call1(py, (pct, stage, msg)).map(drop))?;
+            Ok(())
+        }),
+        None => progress::Progress::none(),
+    }
+}
+
+/// Python: CancelToken()
+/// Pass as `cancel=` to scans, scan_n, survey samples, history scoring,
+/// airtime reports and the mesh channel planners. cancel(), from any
+/// thread, makes the call raise RuntimeError("operation cancelled") at its
+/// next safe point; reset() makes the token usable again.
+#[pyclass(module = "wifi_backend")]
+#[derive(Clone)]
+struct CancelToken {
+    inner: cancel::Cancel,
+}
+
+#[pymethods]
+impl CancelToken {
+    #[new]
+    fn new() -> Self {
+        CancelToken {
+            inner: cancel::Cancel::new(),
         }
-        if let Some(ch) = r.channel {
-            d.set_item("channel", ch)?;
+    }
+
+    fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    fn reset(&self) {
+        self.inner.reset();
+    }
+
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
+
+/// Python: ScanOptions(passive: bool = False, freqs: List[int] | None = None,
+///                     band: str | None = None, ssids: List[str] | None = None,
+///                     flush: bool = False, random_mac: bool = False,
+///                     mac: str | None = None, mac_mask: str | None = None,
+///                     busy_retries: int = 3, busy_backoff: float = 0.25,
+///                     join_busy: bool = False, cached_only: bool = False)
+/// Pass as `options=` to scan(), scan_dicts() and scan_stream(). passive
+/// listens for beacons without probing; freqs (MHz) or band ("2.4GHz",
+/// "5GHz", "6GHz") limit the channels scanned; ssids are probed for
+/// instead of the wildcard (hidden networks answer those); flush drops the
+/// kernel's older BSS entries so APs that went away don't show up.
+/// random_mac sends the probe requests from a random address so they
+/// don't reveal the device's: fully random, or with mac and mac_mask the
+/// bits set in mac_mask come from mac (e.g. to keep a prefix). The scan
+/// raises RuntimeError if the driver can't (see random_mac_supported());
+/// the kernel also refuses while the interface is connected. When the
+/// radio is already scanning (wpa_supplicant, Android), the scan is
+/// started again up to busy_retries times, busy_backoff seconds later,
+/// then twice that and so on, before raising RuntimeError; with join_busy
+/// it waits for the scan in progress and returns its results instead.
+/// cached_only skips the scan and returns the kernel's cached BSS table,
+/// which needs no CAP_NET_ADMIN; a scan the kernel refuses for lack of
+/// privileges (EPERM) does the same; last_scan_info() tells such a scan
+/// apart and how old its rows are.
+/// Only the "raw-nl80211" backend triggers scans and so honours passive,
+/// flush, random_mac, the busy settings and the channel list (the mock
+/// honours the busy settings for its "ebusy" faults, "wpa-supplicant"
+/// everything but random_mac); every backend
+/// returns only the BSSs
+/// on freqs / band with one of ssids. Such filtered scans aren't added to
+/// the scan history. Raises RuntimeError for contradicting options
+/// (passive with ssids, a frequency outside band, mac without
+/// random_mac).
+#[pyclass(module = "wifi_backend")]
+#[derive(Clone)]
+struct ScanOptions {
+    inner: lib_rust::ScanOptions,
+}
+
+#[pymethods]
+impl ScanOptions {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        passive=false, freqs=None, band=None, ssids=None, flush=false,
+        random_mac=false, mac=None, mac_mask=None, busy_retries=3,
+        busy_backoff=0.25, join_busy=false, cached_only=false
+    ))]
+    fn new(
+        passive: bool,
+        freqs: Option<Vec<u32>>,
+        band: Option<&str>,
+        ssids: Option<Vec<String>>,
+        flush: bool,
+        random_mac: bool,
+        mac: Option<&str>,
+        mac_mask: Option<&str>,
+        busy_retries: u32,
+        busy_backoff: f64,
+        join_busy: bool,
+        cached_only: bool,
+    ) -> PyResult<Self> {
+        if !(0.0..=60.0).contains(&busy_backoff) {
+            return Err(PyRuntimeError::new_err("busy_backoff must be between 0 and 60 seconds"));
         }
+        let parse = |s: Option<&str>| s.map(|s| map_pyerr(parse_mac(s))).transpose();
+        let random_mac = match (random_mac, parse(mac)?, parse(mac_mask)?) {
+            (false, None, None) => None,
+            (false, ..) => return Err(PyRuntimeError::new_err("mac and mac_mask need random_mac=True")),
+            (true, None, None) => Some(lib_rust::RandomMac::default()),
+            (true, Some(addr), Some(mask)) => Some(lib_rust::RandomMac { addr, mask }),
+            (true, ..) => return Err(PyRuntimeError::new_err("mac and mac_mask go together")),
+        };
+        let inner = lib_rust::ScanOptions {
+            passive,
+            freqs: freqs.unwrap_or_default(),
+            band: band.map(|b| map_pyerr(band_from_name(b))).transpose()?,
+            ssids: ssids.unwrap_or_default().into_iter().map(String::into_bytes).collect(),
+            flush,
+            random_mac,
+            busy: lib_rust::BusyPolicy {
+                retries: busy_retries,
+                backoff: std::time::Duration::from_secs_f64(busy_backoff),
+                join: join_busy,
+            },
+            cached_only,
+        };
+        map_pyerr(inner.validate())?;
+        Ok(ScanOptions { inner })
+    }
 
-        list.append(d)?;
+    #[getter]
+    fn passive(&self) -> bool {
+        self.inner.passive
     }
 
-    Ok(list.into_py(py))
+    #[getter]
+    fn freqs(&self) -> Vec<u32> {
+        self.inner.freqs.clone()
+    }
+
+    #[getter]
+    fn band(&self) -> Option<&'static str> {
+        self.inner.band.map(band_name)
+    }
+
+    #[getter]
+    fn ssids(&self) -> Vec<String> {
+        self.inner.ssids.iter().map(|s| String::from_utf8_lossy(s).into_owned()).collect()
+    }
+
+    #[getter]
+    fn flush(&self) -> bool {
+        self.inner.flush
+    }
+
+    #[getter]
+    fn random_mac(&self) -> bool {
+        self.inner.random_mac.is_some()
+    }
+
+    #[getter]
+    fn mac(&self) -> Option<String> {
+        self.inner.random_mac.filter(|r| r.mask != [0; 6]).map(|r| format_mac(&r.addr))
+    }
+
+    #[getter]
+    fn mac_mask(&self) -> Option<String> {
+        self.inner.random_mac.filter(|r| r.mask != [0; 6]).map(|r| format_mac(&r.mask))
+    }
+
+    #[getter]
+    fn busy_retries(&self) -> u32 {
+        self.inner.busy.retries
+    }
+
+    #[getter]
+    fn busy_backoff(&self) -> f64 {
+        self.inner.busy.backoff.as_secs_f64()
+    }
+
+    #[getter]
+    fn join_busy(&self) -> bool {
+        self.inner.busy.join
+    }
+
+    #[getter]
+    fn cached_only(&self) -> bool {
+        self.inner.cached_only
+    }
+
+    fn __repr__(&self) -> String {
+        let o = &self.inner;
+        let b = |v: bool| if v { "True" } else { "False" };
+        format!(
+            "ScanOptions(passive={}, freqs={:?}, band={}, ssids={:?}, flush={}, random_mac={}, \
+             busy_retries={}, busy_backoff={}, join_busy={}, cached_only={})",
+            b(o.passive),
+            o.freqs,
+            o.band.map_or("None".into(), |x| format!("{:?}", band_name(x))),
+            self.ssids(),
+            b(o.flush),
+            b(o.random_mac.is_some()),
+            o.busy.retries,
+            o.busy.backoff.as_secs_f64(),
+            b(o.busy.join),
+            b(o.cached_only)
+        )
+    }
+}
+
+fn options_of(options: Option<ScanOptions>) -> lib_rust::ScanOptions {
+    options.map(|o| o.inner).unwrap_or_default()
 }
 
-/// Python: compute_channels() -> Dict[int, int]
+/// Python: random_mac_supported() -> bool
+/// Whether the Wi-Fi driver can send scan probe requests from a random
+/// MAC address (ScanOptions(random_mac=True)).
 #[pyfunction]
-fn compute_channels(py: Python<'_>) -> PyResult<PyObject> {
-    let map = map_pyerr(compute_channels_internal())?;
+fn random_mac_supported(py: Python<'_>) -> PyResult<bool> {
+    map_pyerr(py.allow_threads(lib_rust::random_mac_supported))
+}
 
+/// Python: last_scan_info() -> Dict | None
+/// How fresh the last scan was: {at, mono, triggered, reason, newest_age,
+/// oldest_age}. at / mono are when it started; triggered is False when it
+/// only read the kernel's cached BSS table, reason then saying why
+/// ("cached_only", "not_permitted" when the kernel refused to scan,
+/// "backend" for neli-wifi, which never scans). newest_age / oldest_age
+/// are the seconds between the start and its newest / oldest sighting (0
+/// for BSSs heard during the scan). None before the first scan.
+#[pyfunction]
+fn last_scan_info(py: Python<'_>) -> PyResult<PyObject> {
+    let Some(info) = lib_rust::last_scan() else {
+        return Ok(py.None());
+    };
     let d = PyDict::new_bound(py);
-    for (ch, count) in map {
-        d.set_item(ch, count)?;
+    set_stamp(&d, "at", "mono", Some(info.at))?;
+    d.set_item("triggered", info.untriggered.is_none())?;
+    d.set_item("reason", info.untriggered.map(lib_rust::NoTrigger::name))?;
+    d.set_item("newest_age", info.newest_age)?;
+    d.set_item("oldest_age", info.oldest_age)?;
+    Ok(d.into_py(py))
+}
+
+fn cancel_of(token: Option<CancelToken>) -> cancel::Cancel {
+    token.map_or_else(cancel::Cancel::none, |t| t.inner)
+}
+
+// Which keys row_dict() fills in: one bit per FIELD_NAMES entry.
+#[derive(Debug, Clone, Copy)]
+struct Fields(u64);
+
+const FIELD_NAMES: [&str; 44] = [
+    "ssid",
+    "bssid",
+    "freq_mhz",
+    "signal_dbm",
+    "channel",
+    "security",
+    "width_mhz",
+    "country",
+    "fingerprint",
+    "wifi_gen",
+    "model",
+    "vendor",
+    "seen",
+    "p2p",
+    "ibss",
+    "mld",
+    "insecure",
+    "secondary_channel",
+    "center_channel",
+    "station_count",
+    "utilization",
+    "iface",
+    "status",
+    "hidden",
+    "ssid_bytes",
+    "generation",
+    "max_phy_rate_mbps",
+    "estimated_throughput_mbps",
+    "mesh_point",
+    "mesh_id",
+    "mesh_path_selection",
+    "wps_enabled",
+    "wps_configured",
+    "wps_locked",
+    "passpoint",
+    "access_network",
+    "venue",
+    "pmf",
+    "deauth_vulnerable",
+    "capability",
+    "ess",
+    "privacy",
+    "beacon_interval",
+    "dtim_period",
+];
+
+impl Fields {
+    // The first five, "seen", "iface", "status" and "hidden" need no IE
+    // parsing beyond the SSID.
+    const BASIC: Fields = Fields(0x1f | 1 << 12 | 1 << 21 | 1 << 22 | 1 << 23);
+    const ALL: Fields = Fields((1 << FIELD_NAMES.len()) - 1);
+
+    // `fields`, when given, wins over `details`.
+    fn from_args(details: bool, fields: Option<Vec<String>>) -> PyResult<Fields> {
+        let Some(names) = fields else {
+            return Ok(if details { Fields::ALL } else { Fields::BASIC });
+        };
+        let mut bits: u64 = 0;
+        for name in &names {
+            let i = FIELD_NAMES.iter().position(|f| f == name).ok_or_else(|| {
+                PyRuntimeError::new_err(format!(
+                    "unknown field {name:?} (expected one of {})",
+                    FIELD_NAMES.join(", ")
+                ))
+            })?;
+            bits |= 1 << i;
+        }
+        Ok(Fields(bits))
     }
 
-    Ok(d.into_py(py))
+    fn has(self, name: &str) -> bool {
+        FIELD_NAMES
+            .iter()
+            .position(|f| *f == name)
+            .is_some_and(|i| self.0 & (1 << i) != 0)
+    }
 }
 
-/// Python: compute_best_channel() -> int
-#[pyfunction]
-fn compute_best_channel() -> PyResult<u32> {
-    map_pyerr(compute_best_channel_internal())
+// Only the requested fields are looked at: IEs and the OUI database are
+// parsed for the ones that need them, the rest cost nothing.
+fn row_dict<'py>(py: Python<'py>, r: &BssRow, fields: Fields) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new_bound(py);
+
+    if let Some(ssid) = r.ssid.as_deref().filter(|_| fields.has("ssid")) {
+        d.set_item("ssid", ssid)?;
+    }
+    if fields.has("hidden") {
+        d.set_item("hidden", r.hidden)?;
+    }
+    if r.ssid.is_some() && fields.has("ssid_bytes") {
+        d.set_item("ssid_bytes", PyBytes::new_bound(py, &r.ssid_bytes))?;
+    }
+    if let Some(mac) = r.bssid.filter(|_| fields.has("bssid")) {
+        d.set_item("bssid", format_mac(&mac))?;
+    }
+    if let Some(freq) = r.freq_mhz.filter(|_| fields.has("freq_mhz")) {
+        d.set_item("freq_mhz", freq)?;
+    }
+    if let Some(sig) = r.signal_dbm.filter(|_| fields.has("signal_dbm")) {
+        d.set_item("signal_dbm", sig)?;
+    }
+    if let Some(ch) = r.channel.filter(|_| fields.has("channel")) {
+        d.set_item("channel", ch)?;
+    }
+    if fields.has("seen") {
+        set_stamp(&d, "seen_at", "seen_mono", r.seen)?;
+        d.set_item("age_ms", r.age_ms)?;
+        d.set_item("cached", r.cached)?;
+    }
+    if let Some(iface) = r.iface.as_deref().filter(|_| fields.has("iface")) {
+        d.set_item("iface", iface)?;
+    }
+    if let Some(status) = r.status.filter(|_| fields.has("status")) {
+        d.set_item("status", status.name())?;
+    }
+    if fields.0 & !Fields::BASIC.0 == 0 {
+        return Ok(d);
+    }
+
+    if fields.has("security") {
+        d.set_item("security", r.security().name())?;
+    }
+    if fields.has("insecure") {
+        d.set_item("insecure", r.security().is_insecure())?;
+    }
+    if let Some(p) = r.pmf().filter(|_| fields.has("pmf")) {
+        d.set_item("pmf", p.name())?;
+    }
+    if fields.has("deauth_vulnerable") {
+        d.set_item("deauth_vulnerable", r.deauth_vulnerable())?;
+    }
+    if let Some(w) = r.channel_width().filter(|_| fields.has("width_mhz")) {
+        d.set_item("width_mhz", w)?;
+    }
+    if let Some(c) = r.secondary_channel().filter(|_| fields.has("secondary_channel")) {
+        d.set_item("secondary_channel", c)?;
+    }
+    if let Some(c) = r.center_channel().filter(|_| fields.has("center_channel")) {
+        d.set_item("center_channel", c)?;
+    }
+    if let Some(cc) = r.country().filter(|_| fields.has("country")) {
+        d.set_item("country", String::from_utf8_lossy(&cc))?;
+    }
+    if fields.has("station_count") || fields.has("utilization") {
+        if let Some(load) = r.bss_load() {
+            if fields.has("station_count") {
+                d.set_item("station_count", load.stations)?;
+            }
+            if fields.has("utilization") {
+                d.set_item("utilization", load.utilization)?;
+            }
+        }
+    }
+    let model = fields.has("model");
+    let vendor = if model || fields.has("vendor") {
+        r.bssid.as_ref().and_then(oui::vendor)
+    } else {
+        None
+    };
+    let fp = if model || fields.has("fingerprint") {
+        r.fingerprint()
+    } else {
+        None
+    };
+    if let Some(fp) = fp.filter(|_| fields.has("fingerprint")) {
+        d.set_item("fingerprint", apmodel::format_fp(fp))?;
+    }
+    if let Some(g) = r.wifi_generation().filter(|_| fields.has("wifi_gen")) {
+        d.set_item("wifi_gen", g)?;
+    }
+    if let Some(g) = r.generation().filter(|_| fields.has("generation")) {
+        d.set_item("generation", g.name())?;
+    }
+    if let Some(m) = r.max_phy_rate_mbps().filter(|_| fields.has("max_phy_rate_mbps")) {
+        d.set_item("max_phy_rate_mbps", m)?;
+    }
+    if let Some(t) = r.estimated_throughput_mbps().filter(|_| fields.has("estimated_throughput_mbps")) {
+        d.set_item("estimated_throughput_mbps", t)?;
+    }
+    if model {
+        if let Some(m) = apmodel::friendly_name(fp, vendor.as_deref(), r.wifi_generation()) {
+            d.set_item("model", m)?;
+        }
+    }
+    if let Some(v) = vendor.filter(|_| fields.has("vendor")) {
+        d.set_item("vendor", v)?;
+    }
+    if fields.has("p2p") {
+        d.set_item("p2p", r.is_p2p())?;
+    }
+    if fields.has("ibss") {
+        d.set_item("ibss", r.is_ibss())?;
+    }
+    if let Some(cap) = r.capability.filter(|_| fields.has("capability")) {
+        d.set_item("capability", cap)?;
+    }
+    if fields.has("ess") {
+        d.set_item("ess", r.is_ess())?;
+    }
+    if fields.has("privacy") {
+        d.set_item("privacy", r.privacy())?;
+    }
+    if let Some(b) = r.beacon_interval.filter(|_| fields.has("beacon_interval")) {
+        d.set_item("beacon_interval", b)?;
+    }
+    if let Some(p) = r.dtim_period().filter(|_| fields.has("dtim_period")) {
+        d.set_item("dtim_period", p)?;
+    }
+    if let Some(m) = r.mld_addr().filter(|_| fields.has("mld")) {
+        d.set_item("mld", format_mac(&m))?;
+    }
+    if fields.has("mesh_point") {
+        d.set_item("mesh_point", r.is_mesh_point())?;
+    }
+    if let Some(id) = r.mesh_id().filter(|_| fields.has("mesh_id")) {
+        d.set_item("mesh_id", id)?;
+    }
+    if let Some(m) = r.mesh().filter(|_| fields.has("mesh_path_selection")) {
+        d.set_item("mesh_path_selection", m.path_selection.name())?;
+    }
+    if fields.has("wps_enabled") {
+        d.set_item("wps_enabled", r.wps_enabled())?;
+    }
+    if fields.has("passpoint") {
+        d.set_item("passpoint", r.is_passpoint())?;
+    }
+    if let Some(iw) = r.interworking() {
+        if fields.has("access_network") {
+            d.set_item("access_network", ies::access_network_name(iw.access_network_type))?;
+        }
+        if let Some((group, kind)) = iw.venue.filter(|_| fields.has("venue")) {
+            d.set_item("venue_group", ies::venue_group_name(group))?;
+            d.set_item("venue_type", kind)?;
+        }
+    }
+    if let Some(w) = r.wps() {
+        if fields.has("wps_configured") {
+            d.set_item("wps_configured", w.configured)?;
+        }
+        if fields.has("wps_locked") {
+            d.set_item("wps_locked", w.locked)?;
+        }
+    }
+
+    Ok(d)
 }
 
-/// Python: connected_bssid() -> str | None
-#[pyfunction]
-fn connected_bssid(py: Python<'_>) -> PyResult<PyObject> {
-    let maybe = map_pyerr(get_connected_bssid())?;
-    let obj = match maybe {
-        Some(mac) => format_mac(&mac).into_py(py),
-        None => py.None(),
+// Inverse of row_dict for scan dicts handed back from Python (saved room
+// scans, other probes). Missing keys stay None.
+fn row_from_dict(d: &Bound<'_, PyDict>) -> PyResult<BssRow> {
+    let bssid = match d.get_item("bssid")? {
+        Some(v) => Some(map_pyerr(parse_mac(&v.extract::<String>()?))?),
+        None => None,
     };
-    Ok(obj)
+    let freq_mhz = d.get_item("freq_mhz")?.map(|v| v.extract()).transpose()?;
+    let signal_dbm = d.get_item("signal_dbm")?.map(|v| v.extract()).transpose()?;
+
+    let mut row = BssRow::from_parts(bssid, freq_mhz, signal_dbm, None);
+    if let Some(v) = d.get_item("ssid")? {
+        row.set_ssid(v.extract::<String>()?.as_bytes());
+    }
+    // The bytes, where given, are the SSID; "ssid" may have lost some.
+    if let Some(v) = d.get_item("ssid_bytes")? {
+        row.set_ssid(&v.extract::<Vec<u8>>()?);
+    }
+    if let Some(v) = d.get_item("hidden")? {
+        row.hidden = v.extract()?;
+    }
+    if let Some(v) = d.get_item("channel")? {
+        row.channel = Some(v.extract()?);
+    }
+    row.seen = stamp_from_dict(d, "seen_at", "seen_mono")?;
+    if let Some(v) = d.get_item("age_ms")? {
+        row.age_ms = v.extract()?;
+    }
+    if let Some(v) = d.get_item("cached")? {
+        row.cached = v.extract()?;
+    }
+    if let Some(v) = d.get_item("iface")? {
+        row.iface = Some(Arc::from(v.extract::<String>()?));
+    }
+    if let Some(v) = d.get_item("status")? {
+        row.status = Some(map_pyerr(lib_rust::BssStatus::from_name(&v.extract::<String>()?))?);
+    }
+    if let Some(v) = d.get_item("p2p")? {
+        row = row.with_p2p(v.extract()?);
+    }
+    if let Some(v) = d.get_item("ibss")? {
+        row = row.with_ibss(v.extract()?);
+    }
+    if let Some(v) = d.get_item("capability")? {
+        row.capability = Some(v.extract()?);
+    }
+    if let Some(v) = d.get_item("beacon_interval")? {
+        row.beacon_interval = Some(v.extract()?);
+    }
+    if let Some(v) = d.get_item("dtim_period")? {
+        row = row.with_dtim_period(Some(v.extract()?));
+    }
+    if let Some(v) = d.get_item("mld")? {
+        row = row.with_mld(map_pyerr(parse_mac(&v.extract::<String>()?))?);
+    }
+    if d.get_item("mesh_point")?.map(|v| v.extract()).transpose()? == Some(true) {
+        let path_selection = match d.get_item("mesh_path_selection")? {
+            Some(v) => ies::PathSelection::from_name(&v.extract::<String>()?).unwrap_or_default(),
+            None => ies::PathSelection::default(),
+        };
+        row = row.with_mesh(ies::MeshConfig {
+            path_selection,
+            ..Default::default()
+        });
+    }
+    if let Some(v) = d.get_item("pmf")? {
+        let name: String = v.extract()?;
+        let pmf = ies::Pmf::from_name(&name).ok_or_else(|| PyRuntimeError::new_err(format!("unknown pmf {name:?}")))?;
+        row = row.with_pmf(Some(pmf));
+    }
+    if let Some(v) = d.get_item("passpoint")? {
+        // The release isn't in the dict; 1 stands for "some".
+        row = row.with_passpoint(v.extract::<bool>()?.then_some(1));
+    }
+    if let Some(v) = d.get_item("wps_enabled")? {
+        let flag = |key: &str| -> PyResult<bool> { Ok(d.get_item(key)?.map(|v| v.extract()).transpose()?.unwrap_or(false)) };
+        let wps = ies::Wps {
+            configured: flag("wps_configured")?,
+            locked: flag("wps_locked")?,
+            pin: true,
+        };
+        row = row.with_wps(v.extract::<bool>()?.then_some(wps));
+    }
+    Ok(row)
 }
 
-/// Module init. Name *must* be wifi_backend to match Cargo.toml [lib].name.
-#[pymodule]
-fn wifi_backend(_py: Python<'_>, m: &Bound<PyModule>) -> PyResult<()> {
+// `{at_key: wall, mono_key: monotonic}`, both None when unknown.
+fn set_stamp(d: &Bound<'_, PyDict>, at_key: &str, mono_key: &str, s: Option<Stamp>) -> PyResult<()> {
+    d.set_item(at_key, s.map(|s| s.wall))?;
+    d.set_item(mono_key, s.map(|s| s.mono))
+}
+
+// Inverse of set_stamp; a wall time alone is converted with Stamp::from_wall.
+fn stamp_from_dict(d: &Bound<'_, PyDict>, at_key: &str, mono_key: &str) -> PyResult<Option<Stamp>> {
+    let wall: Option<f64> = d.get_item(at_key)?.map(|v| v.extract()).transpose()?.flatten();
+    let mono: Option<f64> = d.get_item(mono_key)?.map(|v| v.extract()).transpose()?.flatten();
+    Ok(match (wall, mono) {
+        (Some(wall), Some(mono)) => Some(Stamp { wall, mono }),
+        (Some(wall), None) => Some(Stamp::from_wall(wall)),
+        _ => None,
+    })
+}
+
+// Scan dicts or BssEntry objects, as the caller has them.
+fn rows_from_list(list: &Bound<'_, PyList>) -> PyResult<Vec<BssRow>> {
+    list.iter()
+        .map(|item| match item.downcast::<BssEntry>() {
+            Ok(e) => Ok(e.borrow().row.clone()),
+            Err(_) => row_from_dict(item.downcast::<PyDict>()?),
+        })
+        .collect()
+}
+
+/// Python: BssEntry, one BSS of scan()
+/// Typed, read-only attributes: ssid, bssid, freq_mhz, signal_dbm,
+/// channel, band ("2.4GHz" / "5GHz" / "6GHz" / "other"), seen_at,
+/// seen_mono, age_ms, cached, status, hidden, ssid_bytes, and the details
+/// scan_dicts(details=True) has: security, insecure, pmf,
+/// deauth_vulnerable, width_mhz,
+/// secondary_channel, center_channel, station_count, utilization, country,
+/// vendor, fingerprint, wifi_gen, generation, max_phy_rate_mbps,
+/// estimated_throughput_mbps, model, p2p, ibss, capability, ess, privacy,
+/// beacon_interval, dtim_period, mld, mesh_point, mesh_id,
+/// mesh_path_selection, wps_enabled, wps_configured, wps_locked,
+/// passpoint, access_network, venue_group, venue_type, parsed from the
+/// IEs on first access. Unknown values are None.
+/// Entries compare equal when bssid, ssid, frequency, signal and channel
+/// match; to_dict() gives scan_dicts()' dict.
+#[pyclass(module = "wifi_backend")]
+struct BssEntry {
+    row: BssRow,
+}
+
+#[pymethods]
+impl BssEntry {
+    #[getter]
+    fn ssid(&self) -> Option<&str> {
+        self.row.ssid.as_deref()
+    }
+
+    /// The SSID as broadcast, for names that aren't valid UTF-8 (ssid
+    /// shows those bytes as U+FFFD).
+    #[getter]
+    fn ssid_bytes<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.row.ssid.as_ref()?;
+        Some(PyBytes::new_bound(py, &self.row.ssid_bytes))
+    }
+
+    #[getter]
+    fn bssid(&self) -> Option<String> {
+        self.row.bssid.as_ref().map(format_mac)
+    }
+
+    #[getter]
+    fn freq_mhz(&self) -> Option<u32> {
+        self.row.freq_mhz
+    }
+
+    #[getter]
+    fn signal_dbm(&self) -> Option<f32> {
+        self.row.signal_dbm
+    }
+
+    #[getter]
+    fn channel(&self) -> Option<u32> {
+        self.row.channel
+    }
+
+    #[getter]
+    fn band(&self) -> Option<&'static str> {
+        self.row.freq_mhz.map(|f| band_name(lib_rust::freq_band(f)))
+    }
+
+    #[getter]
+    fn seen_at(&self) -> Option<f64> {
+        self.row.seen.map(|s| s.wall)
+    }
+
+    #[getter]
+    fn seen_mono(&self) -> Option<f64> {
+        self.row.seen.map(|s| s.mono)
+    }
+
+    /// How long before the scan the BSS was last heard, ms.
+    #[getter]
+    fn age_ms(&self) -> Option<u32> {
+        self.row.age_ms
+    }
+
+    #[getter]
+    fn cached(&self) -> bool {
+        self.row.cached
+    }
+
+    /// The interface that heard it, for scan_merged() rows.
+    #[getter]
+    fn iface(&self) -> Option<&str> {
+        self.row.iface.as_deref()
+    }
+
+    /// The AP hides its SSID; ssid is "".
+    #[getter]
+    fn hidden(&self) -> bool {
+        self.row.hidden
+    }
+
+    /// "associated" (or "authenticated", "ibss_joined") on the BSS the
+    /// interface is in, where the backend reports it.
+    #[getter]
+    fn status(&self) -> Option<&'static str> {
+        self.row.status.map(lib_rust::BssStatus::name)
+    }
+
+    #[getter]
+    fn security(&self) -> &'static str {
+        self.row.security().name()
+    }
+
+    #[getter]
+    fn insecure(&self) -> bool {
+        self.row.security().is_insecure()
+    }
+
+    /// "disabled", "capable" or "required"; None without RSN.
+    #[getter]
+    fn pmf(&self) -> Option<&'static str> {
+        self.row.pmf().map(|p| p.name())
+    }
+
+    #[getter]
+    fn deauth_vulnerable(&self) -> bool {
+        self.row.deauth_vulnerable()
+    }
+
+    #[getter]
+    fn width_mhz(&self) -> Option<u32> {
+        self.row.channel_width()
+    }
+
+    #[getter]
+    fn secondary_channel(&self) -> Option<u32> {
+        self.row.secondary_channel()
+    }
+
+    #[getter]
+    fn center_channel(&self) -> Option<u32> {
+        self.row.center_channel()
+    }
+
+    #[getter]
+    fn station_count(&self) -> Option<u16> {
+        self.row.bss_load().map(|l| l.stations)
+    }
+
+    #[getter]
+    fn utilization(&self) -> Option<f32> {
+        self.row.bss_load().map(|l| l.utilization)
+    }
+
+    #[getter]
+    fn country(&self) -> Option<String> {
+        self.row.country().map(|cc| String::from_utf8_lossy(&cc).into_owned())
+    }
+
+    #[getter]
+    fn vendor(&self) -> Option<String> {
+        self.row.bssid.as_ref().and_then(oui::vendor).map(|v| v.to_string())
+    }
+
+    #[getter]
+    fn fingerprint(&self) -> Option<String> {
+        self.row.fingerprint().map(apmodel::format_fp)
+    }
+
+    #[getter]
+    fn wifi_gen(&self) -> Option<u8> {
+        self.row.wifi_generation()
+    }
+
+    #[getter]
+    fn generation(&self) -> Option<&'static str> {
+        self.row.generation().map(|g| g.name())
+    }
+
+    #[getter]
+    fn max_phy_rate_mbps(&self) -> Option<f32> {
+        self.row.max_phy_rate_mbps()
+    }
+
+    #[getter]
+    fn estimated_throughput_mbps(&self) -> Option<f32> {
+        self.row.estimated_throughput_mbps()
+    }
+
+    #[getter]
+    fn model(&self) -> Option<String> {
+        let vendor = self.row.bssid.as_ref().and_then(oui::vendor);
+        apmodel::friendly_name(self.row.fingerprint(), vendor.as_deref(), self.row.wifi_generation())
+            .map(|m| m.to_string())
+    }
+
+    #[getter]
+    fn p2p(&self) -> bool {
+        self.row.is_p2p()
+    }
+
+    #[getter]
+    fn ibss(&self) -> bool {
+        self.row.is_ibss()
+    }
+
+    /// The raw Capability Information field, where the backend reports it.
+    #[getter]
+    fn capability(&self) -> Option<u16> {
+        self.row.capability
+    }
+
+    #[getter]
+    fn ess(&self) -> bool {
+        self.row.is_ess()
+    }
+
+    #[getter]
+    fn privacy(&self) -> bool {
+        self.row.privacy()
+    }
+
+    /// In TUs (1.024 ms).
+    #[getter]
+    fn beacon_interval(&self) -> Option<u16> {
+        self.row.beacon_interval
+    }
+
+    #[getter]
+    fn dtim_period(&self) -> Option<u8> {
+        self.row.dtim_period()
+    }
+
+    #[getter]
+    fn mld(&self) -> Option<String> {
+        self.row.mld_addr().as_ref().map(format_mac)
+    }
+
+    /// An 802.11s mesh point rather than an AP.
+    #[getter]
+    fn mesh_point(&self) -> bool {
+        self.row.is_mesh_point()
+    }
+
+    #[getter]
+    fn mesh_id(&self) -> Option<String> {
+        self.row.mesh_id()
+    }
+
+    #[getter]
+    fn mesh_path_selection(&self) -> Option<&'static str> {
+        self.row.mesh().map(|m| m.path_selection.name())
+    }
+
+    #[getter]
+    fn wps_enabled(&self) -> bool {
+        self.row.wps_enabled()
+    }
+
+    /// WPS set up rather than waiting for its first configuration; None
+    /// without WPS.
+    #[getter]
+    fn wps_configured(&self) -> Option<bool> {
+        self.row.wps().map(|w| w.configured)
+    }
+
+    /// WPS stopped taking PINs; None without WPS.
+    #[getter]
+    fn wps_locked(&self) -> Option<bool> {
+        self.row.wps().map(|w| w.locked)
+    }
+
+    /// A Passpoint (Hotspot 2.0) carrier or venue hotspot.
+    #[getter]
+    fn passpoint(&self) -> bool {
+        self.row.is_passpoint()
+    }
+
+    #[getter]
+    fn access_network(&self) -> Option<&'static str> {
+        self.row.interworking().map(|iw| ies::access_network_name(iw.access_network_type))
+    }
+
+    #[getter]
+    fn venue_group(&self) -> Option<&'static str> {
+        self.row.interworking()?.venue.map(|(g, _)| ies::venue_group_name(g))
+    }
+
+    #[getter]
+    fn venue_type(&self) -> Option<u8> {
+        self.row.interworking()?.venue.map(|(_, t)| t)
+    }
+
+    /// The scan_dicts() dict of this BSS.
+    #[pyo3(signature = (details=false, fields=None))]
+    fn to_dict<'py>(&self, py: Python<'py>, details: bool, fields: Option<Vec<String>>) -> PyResult<Bound<'py, PyDict>> {
+        row_dict(py, &self.row, Fields::from_args(details, fields)?)
+    }
+
+    fn __repr__(&self) -> String {
+        let opt = |v: Option<String>| v.unwrap_or_else(|| "None".into());
+        format!(
+            "BssEntry(bssid={}, ssid={}, freq_mhz={}, channel={}, signal_dbm={})",
+            opt(self.bssid().map(|b| format!("{b:?}"))),
+            opt(self.row.ssid.as_deref().map(|s| format!("{s:?}"))),
+            opt(self.row.freq_mhz.map(|f| f.to_string())),
+            opt(self.row.channel.map(|c| c.to_string())),
+            opt(self.row.signal_dbm.map(|s| format!("{s:.1}"))),
+        )
+    }
+
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        let Ok(other) = other.downcast::<BssEntry>() else {
+            return false;
+        };
+        let (a, b) = (&self.row, &other.borrow().row);
+        a.bssid == b.bssid
+            && a.ssid == b.ssid
+            && a.freq_mhz == b.freq_mhz
+            && a.signal_dbm == b.signal_dbm
+            && a.channel == b.channel
+    }
+}
+
+fn entries_list(py: Python<'_>, rows: Vec<BssRow>) -> PyResult<PyObject> {
+    let list = PyList::empty_bound(py);
+    for row in rows {
+        list.append(Py::new(py, BssEntry { row })?)?;
+    }
+    Ok(list.into_py(py))
+}
+
+/// Python: scan(cancel: CancelToken | None = None,
+///              options: ScanOptions | None = None,
+///              iface: str | None = None) -> List[BssEntry]
+/// One BssEntry per BSS visible now. `iface` names the interface to scan
+/// on (see list_interfaces()); by default the first station interface.
+#[pyfunction]
+#[pyo3(signature = (cancel=None, options=None, iface=None))]
+fn scan(
+    py: Python<'_>,
+    cancel: Option<CancelToken>,
+    options: Option<ScanOptions>,
+    iface: Option<&str>,
+) -> PyResult<PyObject> {
+    if let Some(name) = iface {
+        return session_on(py, name)?.scan(py, cancel, options);
+    }
+    let (options, cancel) = (options_of(options), cancel_of(cancel));
+    let rows = map_pyerr(py.allow_threads(|| scan_with(&options, &cancel)))?;
+    entries_list(py, rows)
+}
+
+/// Python: scan_dicts(details: bool = False, fields: List[str] | None = None,
+///                    cancel: CancelToken | None = None,
+///                    options: ScanOptions | None = None,
+///                    iface: str | None = None) -> List[Dict]
+/// scan() as plain dicts, as it returned before BssEntry. Each dict:
+/// {ssid, bssid, freq_mhz, signal_dbm, channel, seen_at, seen_mono,
+/// age_ms, cached}: when the kernel last heard the BSS (unix and
+/// time.monotonic() seconds, and how many ms before the scan was read),
+/// and whether that was before this scan started, i.e. the entry came
+/// from the kernel's BSS cache.
+/// With details=True also {security, insecure, pmf, deauth_vulnerable, width_mhz,
+/// secondary_channel, center_channel, station_count, utilization, country,
+/// vendor, fingerprint, wifi_gen, generation, max_phy_rate_mbps,
+/// estimated_throughput_mbps, model, p2p, ibss, capability, ess, privacy,
+/// beacon_interval, dtim_period, mld, mesh_point, mesh_id,
+/// mesh_path_selection, wps_enabled, wps_configured, wps_locked,
+/// passpoint, access_network, venue_group, venue_type}, parsed from the
+/// IEs / OUI database only then. secondary_channel is the other 20 MHz
+/// channel of a 40 MHz or wider BSS and center_channel the middle of the
+/// whole channel, from the HT/VHT/HE operation elements. station_count
+/// and utilization (0.0 to 1.0) are what the AP advertises in its BSS
+/// Load element; a busier AP weighs more in channel scoring. security is one of "open", "wep",
+/// "wpa", "wpa2", "wpa2/wpa3", "wpa3", "enterprise", "owe"; insecure is
+/// true for open, WEP and WPA (TKIP) networks. pmf is the RSN element's
+/// management frame protection, "disabled", "capable" or "required" (None
+/// without RSN); deauth_vulnerable is true without any, when forged
+/// deauthentication frames can knock every client off. p2p marks Wi-Fi Direct groups, ibss ad-hoc
+/// networks and mld is the shared address of a Wi-Fi 7 AP's links.
+/// capability is the raw Capability Information field (from backends that
+/// report it); ess (an AP, not ad-hoc or mesh) and privacy (encryption
+/// required) are its bits, worked out from the IEs without it.
+/// beacon_interval is in TUs (1.024 ms) and dtim_period, from the TIM
+/// element, in beacons; a probe response has no TIM.
+/// mesh_point marks 802.11s mesh points, with their Mesh ID and active
+/// path selection protocol ("hwmp", "vendor" or "unknown").
+/// wps_enabled is true for a BSS with WPS on; wps_configured and
+/// wps_locked (only then) say whether it is set up and whether it stopped
+/// taking PINs. passpoint marks Passpoint (Hotspot 2.0) carrier and venue
+/// hotspots; access_network is the Interworking element's type
+/// ("private", "private_guest", "chargeable_public", "free_public",
+/// "personal_device", "emergency_only", ...) and venue_group /
+/// venue_type its venue ("business", "residential", ... and the type
+/// number within the group). `fields` takes "venue" for both venue keys.
+/// generation is "wifi4", "wifi5", "wifi6", "wifi6e" (Wi-Fi 6 on 6 GHz)
+/// or "wifi7" from the capability elements, "legacy" for 802.11a/b/g
+/// only, None when the backend kept no IEs. max_phy_rate_mbps is the
+/// fastest rate the Supported Rates and HT / VHT / HE / EHT capabilities
+/// allow at the BSS's operating width; estimated_throughput_mbps what a
+/// 2x2 client could expect from it at the signal it was heard with.
+/// `fields` picks exactly which of these keys to build (e.g. ["bssid",
+/// "channel", "signal_dbm"]; "seen" for the three timestamp keys) and
+/// overrides `details`. `iface` as for scan(). scan_merged() rows also
+/// have "iface", the interface that heard the BSS, and the BSS we're in
+/// has "status" ("associated", "authenticated" or "ibss_joined").
+/// hidden is true for an AP hiding its SSID (ssid is then ""); a mesh
+/// point's empty SSID doesn't count. With
+/// details=True, or asked for in `fields`, ssid_bytes is the SSID as
+/// broadcast (bytes), where ssid shows non-UTF-8 bytes as U+FFFD.
+#[pyfunction]
+#[pyo3(signature = (details=false, fields=None, cancel=None, options=None, iface=None))]
+fn scan_dicts(
+    py: Python<'_>,
+    details: bool,
+    fields: Option<Vec<String>>,
+    cancel: Option<CancelToken>,
+    options: Option<ScanOptions>,
+    iface: Option<&str>,
+) -> PyResult<PyObject> {
+    if let Some(name) = iface {
+        return session_on(py, name)?.scan_dicts(py, details, fields, cancel, options);
+    }
+    let fields = Fields::from_args(details, fields)?;
+    let (options, cancel) = (options_of(options), cancel_of(cancel));
+    let rows = map_pyerr(py.allow_threads(|| scan_with(&options, &cancel)))?;
+    rows_list(py, &rows, fields)
+}
+
+/// Python: scan_merged(details: bool = False, fields: List[str] | None = None,
+///                     cancel: CancelToken | None = None,
+///                     options: ScanOptions | None = None) -> List[Dict]
+/// scan_dicts() on every station interface at once (one per radio, e.g.
+/// separate 2.4 and 5 GHz radios), merged: a BSS more than one heard is
+/// listed once, with its strongest signal, and each dict's "iface" names
+/// the interface that heard it so. Interfaces whose scan fails are left
+/// out; raises RuntimeError only if all of them fail.
+#[pyfunction]
+#[pyo3(signature = (details=false, fields=None, cancel=None, options=None))]
+fn scan_merged(
+    py: Python<'_>,
+    details: bool,
+    fields: Option<Vec<String>>,
+    cancel: Option<CancelToken>,
+    options: Option<ScanOptions>,
+) -> PyResult<PyObject> {
+    let fields = Fields::from_args(details, fields)?;
+    let (options, cancel) = (options_of(options), cancel_of(cancel));
+    let rows = map_pyerr(py.allow_threads(|| lib_rust::scan_merged(&options, &cancel)))?;
+    rows_list(py, &rows, fields)
+}
+
+fn rows_list(py: Python<'_>, rows: &[BssRow], fields: Fields) -> PyResult<PyObject> {
+    let list = PyList::empty_bound(py);
+    for r in rows {
+        list.append(row_dict(py, r, fields)?)?;
+    }
+    Ok(list.into_py(py))
+}
+
+/// Python: scan_n(times: int = 3, interval: float = 1.0,
+///                details: bool = False, fields: List[str] | None = None,
+///                progress: Callable[[float, str, str], None] | None = None,
+///                cancel: CancelToken | None = None) -> List[Dict]
+/// `times` scans started `interval` seconds apart, in one call. One dict
+/// per BSS, strongest first: the scan_dicts() fields of its last sighting
+/// plus {seen, mean_dbm, max_dbm, min_dbm}; `seen` is out of `times`.
+/// `progress(percent, stage, message)` is called after every scan.
+#[pyfunction]
+#[pyo3(signature = (times=3, interval=1.0, details=false, fields=None, progress=None, cancel=None))]
+fn scan_n(
+    py: Python<'_>,
+    times: u32,
+    interval: f64,
+    details: bool,
+    fields: Option<Vec<String>>,
+    progress: Option<PyObject>,
+    cancel: Option<CancelToken>,
+) -> PyResult<PyObject> {
+    let fields = Fields::from_args(details, fields)?;
+    if times == 0 || !(interval >= 0.0 && interval.is_finite()) {
+        return Err(PyRuntimeError::new_err("times must be >= 1 and interval >= 0"));
+    }
+    let interval = std::time::Duration::from_secs_f64(interval);
+    let mut progress = py_progress(progress).with_cancel(cancel_of(cancel));
+    let aggs = map_pyerr(py.allow_threads(|| scan_n_internal(times, interval, &mut progress)))?;
+
+    let list = PyList::empty_bound(py);
+    for a in &aggs {
+        let d = row_dict(py, &a.row, fields)?;
+        d.set_item("seen", a.seen)?;
+        d.set_item("mean_dbm", a.mean_dbm)?;
+        d.set_item("max_dbm", a.max_dbm)?;
+        d.set_item("min_dbm", a.min_dbm)?;
+        list.append(d)?;
+    }
+    Ok(list.into_py(py))
+}
+
+/// Python: scan_average(n: int = 3, interval_ms: int = 1000,
+///                      alpha: float = 0.3,
+///                      progress: Callable[[float, str, str], None] | None = None,
+///                      cancel: CancelToken | None = None) -> ScanSnapshot
+/// `n` scans started `interval_ms` apart, merged into one snapshot: a row
+/// per BSSID whose signal_dbm is an exponentially weighted moving average
+/// over the scans (`alpha` the weight of each newer one), so a neighbour
+/// swinging 15 dB between samples doesn't flip the recommendation. Pass
+/// it to compute_best_channel(snapshot=...) or use its own methods.
+/// `progress(percent, stage, message)` is called after every scan.
+#[pyfunction]
+#[pyo3(signature = (n=3, interval_ms=1000, alpha=lib_rust::DEFAULT_EWMA_ALPHA, progress=None, cancel=None))]
+fn scan_average(
+    py: Python<'_>,
+    n: u32,
+    interval_ms: u64,
+    alpha: f32,
+    progress: Option<PyObject>,
+    cancel: Option<CancelToken>,
+) -> PyResult<ScanSnapshot> {
+    if n == 0 {
+        return Err(PyRuntimeError::new_err("n must be >= 1"));
+    }
+    let interval = std::time::Duration::from_millis(interval_ms);
+    let mut progress = py_progress(progress).with_cancel(cancel_of(cancel));
+    let inner = map_pyerr(py.allow_threads(|| lib_rust::scan_average(n, interval, alpha, &mut progress)))?;
+    Ok(ScanSnapshot { inner })
+}
+
+/// Iterator returned by scan_stream().
+#[pyclass(module = "wifi_backend")]
+struct ScanStream {
+    rx: mpsc::Receiver<ScanEvent>,
+    fields: Fields,
+    finished: bool,
+}
+
+#[pymethods]
+impl ScanStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let rx = &mut self.rx;
+        let ev = py.allow_threads(move || rx.recv());
+        match ev {
+            Ok(ScanEvent::Row(r)) => Ok(Some(row_dict(py, &r, self.fields)?.into_py(py))),
+            Ok(ScanEvent::Done) => {
+                self.finished = true;
+                Ok(None)
+            }
+            Ok(ScanEvent::Failed(e)) => {
+                self.finished = true;
+                Err(py_error(&e))
+            }
+            Err(_) => {
+                self.finished = true;
+                Err(PyRuntimeError::new_err("scan stream ended without completing"))
+            }
+        }
+    }
+}
+
+/// Python: scan_stream(details: bool = False, fields: List[str] | None = None,
+///                     cancel: CancelToken | None = None,
+///                     options: ScanOptions | None = None) -> Iterator[Dict]
+/// Same dicts as scan_dicts(), yielded as each BSS is parsed instead of after
+/// the whole dump. Iteration stops once the scan completes; a failed or
+/// cancelled scan raises RuntimeError from the iterator.
+#[pyfunction]
+#[pyo3(signature = (details=false, fields=None, cancel=None, options=None))]
+fn scan_stream(
+    details: bool,
+    fields: Option<Vec<String>>,
+    cancel: Option<CancelToken>,
+    options: Option<ScanOptions>,
+) -> PyResult<ScanStream> {
+    Ok(ScanStream {
+        fields: Fields::from_args(details, fields)?,
+        rx: scan_stream_internal(options_of(options), cancel_of(cancel)),
+        finished: false,
+    })
+}
+
+/// Python: compute_channels(band: str | None = None, detailed: bool = False,
+///                          iface: str | None = None)
+///     -> Dict[int, int] | List[Dict]
+/// APs per primary channel, optionally only in `band` ("2.4GHz", "5GHz",
+/// "6GHz", "other"). With detailed=True, one dict per channel sorted by band and
+/// channel: {band, channel, aps, p2p, ibss, passpoint, rnr, co_channel, adjacent, weight,
+/// utilization, busy, widths: {mhz: count}}. co_channel also counts wide APs whose
+/// block covers the channel, adjacent the partially overlapping ones (on
+/// 2.4 GHz they add to weight by how far their spectrum reaches over);
+/// channels that are only overlapped are listed with aps=0. p2p and ibss
+/// are how many of `aps` are Wi-Fi Direct groups and ad-hoc networks; see
+/// set_p2p_policy() and set_exclude_ibss() for how they count. passpoint
+/// is how many are Passpoint carrier hotspots, counted like any AP. rnr is how
+/// many are 6 GHz BSSs not heard but listed in a 2.4 / 5 GHz AP's Reduced
+/// Neighbor Report (see rnr_neighbors()); they weigh like the others. busy is the
+/// share of time a channel survey taken after the scan found the channel
+/// busy (None where the driver has no survey), and adds to weight. `iface`
+/// as for scan().
+#[pyfunction]
+#[pyo3(signature = (band=None, detailed=false, iface=None))]
+fn compute_channels(py: Python<'_>, band: Option<&str>, detailed: bool, iface: Option<&str>) -> PyResult<PyObject> {
+    if let Some(name) = iface {
+        return session_on(py, name)?.compute_channels(py, band, detailed);
+    }
+    let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+    if !detailed {
+        let map = map_pyerr(py.allow_threads(|| compute_channels_internal(band)))?;
+        return Ok(map.into_py_dict_bound(py).into_py(py));
+    }
+    let rows = map_pyerr(py.allow_threads(|| {
+        let rows = scan_all_bss()?;
+        chansurvey::refresh(backend(), None);
+        Ok(rows)
+    }))?;
+    channels_detailed(py, &rows, band)
+}
+
+// compute_channels(detailed=True)'s list.
+fn channels_detailed(py: Python<'_>, rows: &[BssRow], band: Option<u8>) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for st in channel_breakdown(rows, band, &chansurvey::recent_busy(), &ChannelConfig::DEFAULT) {
+        let d = PyDict::new_bound(py);
+        d.set_item("band", band_name(st.band))?;
+        d.set_item("channel", st.channel)?;
+        d.set_item("aps", st.aps)?;
+        d.set_item("p2p", st.p2p)?;
+        d.set_item("ibss", st.ibss)?;
+        d.set_item("passpoint", st.passpoint)?;
+        d.set_item("rnr", st.rnr)?;
+        d.set_item("co_channel", st.co_channel)?;
+        d.set_item("adjacent", st.adjacent)?;
+        d.set_item("weight", st.weight)?;
+        d.set_item("utilization", st.utilization)?;
+        d.set_item("busy", st.busy)?;
+        d.set_item("widths", st.widths.into_py_dict_bound(py))?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: compute_best_channel(candidates: List[int | Tuple[str, int]] | None = None,
+///                              config: ChannelConfig | None = None,
+///                              iface: str | None = None,
+///                              snapshot: ScanSnapshot | None = None)
+///     -> Tuple[str, int]
+/// The pick as (band, channel), band "2.4GHz", "5GHz" or "6GHz": 6 GHz
+/// reuses the 2.4 / 5 GHz channel numbers. With `candidates`, only those
+/// channels are considered (e.g. the ones the router's firmware allows):
+/// 2.4 / 5 GHz channel numbers or (band, channel) pairs, which 6 GHz
+/// channels need, in any mix. Busy time from a channel survey taken after
+/// the scan counts against a channel too, where the driver supports
+/// surveys. `config` tunes the heuristics; `iface` as for scan().
+/// `snapshot` (e.g. from scan_average()) is used instead of a fresh scan,
+/// and `iface` is then ignored.
+#[pyfunction]
+#[pyo3(signature = (candidates=None, config=None, iface=None, snapshot=None))]
+fn compute_best_channel(
+    py: Python<'_>,
+    candidates: Option<Vec<Candidate>>,
+    config: Option<PyChannelConfig>,
+    iface: Option<&str>,
+    snapshot: Option<PyRef<'_, ScanSnapshot>>,
+) -> PyResult<(&'static str, u32)> {
+    if let Some(snap) = snapshot {
+        return snap.best_channel(candidates, config);
+    }
+    if let Some(name) = iface {
+        return session_on(py, name)?.compute_best_channel(py, candidates, config);
+    }
+    let candidates = candidates_of(candidates)?;
+    let cfg = config_of(config);
+    map_pyerr(py.allow_threads(|| compute_best_channel_internal(candidates.as_deref(), &cfg))).map(pick_of)
+}
+
+// A candidates= entry: a 2.4 / 5 GHz channel number, or a (band, channel)
+// pair such as ("6GHz", 37).
+#[derive(FromPyObject)]
+enum Candidate {
+    Channel(u32),
+    Pair(String, u32),
+}
+
+// candidates= as (freq_band(), channel) pairs.
+fn candidates_of(candidates: Option<Vec<Candidate>>) -> PyResult<Option<Vec<(u8, u32)>>> {
+    let pair = |c: Candidate| match c {
+        Candidate::Channel(ch) => Ok(lib_rust::candidate_pairs(&[ch])?[0]),
+        Candidate::Pair(band, ch) => Ok((band_from_name(&band)?, ch)),
+    };
+    candidates
+        .map(|c| map_pyerr(c.into_iter().map(pair).collect::<anyhow::Result<Vec<_>>>()))
+        .transpose()
+}
+
+// A pick as Python gets it: (band name, channel).
+fn pick_of((band, ch): (u8, u32)) -> (&'static str, u32) {
+    (band_name(band), ch)
+}
+
+/// Python: compute_best_channel_plan(candidates: List[int | Tuple[str, int]] | None = None,
+///                                   config: ChannelConfig | None = None,
+///                                   iface: str | None = None,
+///                                   snapshot: ScanSnapshot | None = None)
+///     -> Tuple[str, int, int]
+/// compute_best_channel()'s (band, channel) and the width (20, 40, 80, 160 or 320
+/// MHz) to run it at: the widest block around it whose other 20 MHz
+/// channels are clean (weigh no more than the pick plus the config's
+/// margin) and that the regulatory domain and radio allow. On 2.4 GHz 40
+/// MHz needs a clean channel 4 away and no 40 MHz intolerant BSS in
+/// range; on 5 / 6 GHz the blocks are the aligned ones (36-48 for 80 MHz,
+/// ...). Arguments as for compute_best_channel().
+#[pyfunction]
+#[pyo3(signature = (candidates=None, config=None, iface=None, snapshot=None))]
+fn compute_best_channel_plan(
+    py: Python<'_>,
+    candidates: Option<Vec<Candidate>>,
+    config: Option<PyChannelConfig>,
+    iface: Option<&str>,
+    snapshot: Option<PyRef<'_, ScanSnapshot>>,
+) -> PyResult<(&'static str, u32, u32)> {
+    if let Some(snap) = snapshot {
+        return snap.best_channel_plan(candidates, config);
+    }
+    if let Some(name) = iface {
+        return session_on(py, name)?.compute_best_channel_plan(py, candidates, config);
+    }
+    let candidates = candidates_of(candidates)?;
+    let cfg = config_of(config);
+    let (band, ch, width) = map_pyerr(py.allow_threads(|| -> anyhow::Result<_> {
+        let rows = scan_all_bss()?;
+        chansurvey::refresh(backend(), None);
+        ChannelLimits::refresh(backend(), None);
+        let connected = lib_rust::connected_in(backend(), None, &rows)?;
+        let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+        lib_rust::best_channel_plan(&rows, connected.as_ref(), candidates.as_deref(), &busy, &limits, &cfg)
+    }))?;
+    Ok((band_name(band), ch, width))
+}
+
+/// Python: channel_scores(candidates: List[int | Tuple[str, int]] | None = None,
+///                        config: ChannelConfig | None = None,
+///                        iface: str | None = None) -> List[Dict]
+/// What compute_best_channel() chose from, best first: one dict per
+/// channel {band, channel, weight, aps, busy, recommended}. weight is the
+/// interference weight it compared, our own AP left out (lower is better),
+/// aps the APs on or overlapping the channel, busy the survey's busy share
+/// (or None), and recommended is True on exactly the channel
+/// compute_best_channel() returns. Without `candidates` (or a preferred
+/// band) those are the channels other APs weigh on, in the connected band
+/// if connected. `iface` and `snapshot` as for compute_best_channel().
+#[pyfunction]
+#[pyo3(signature = (candidates=None, config=None, iface=None, snapshot=None))]
+fn channel_scores(
+    py: Python<'_>,
+    candidates: Option<Vec<Candidate>>,
+    config: Option<PyChannelConfig>,
+    iface: Option<&str>,
+    snapshot: Option<PyRef<'_, ScanSnapshot>>,
+) -> PyResult<PyObject> {
+    if let Some(snap) = snapshot {
+        return snap.channel_scores(py, candidates, config);
+    }
+    if let Some(name) = iface {
+        return session_on(py, name)?.channel_scores(py, candidates, config);
+    }
+    let candidates = candidates_of(candidates)?;
+    let cfg = config_of(config);
+    let scores = map_pyerr(py.allow_threads(|| -> anyhow::Result<_> {
+        let rows = scan_all_bss()?;
+        chansurvey::refresh(backend(), None);
+        ChannelLimits::refresh(backend(), None);
+        let connected = lib_rust::connected_in(backend(), None, &rows)?;
+        let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+        lib_rust::channel_scores(&rows, connected.as_ref(), candidates.as_deref(), &busy, &limits, &cfg)
+    }))?;
+    scores_list(py, &scores)
+}
+
+/// Python: interference_matrix(band: str | None = None,
+///                             config: ChannelConfig | None = None,
+///                             iface: str | None = None,
+///                             snapshot: ScanSnapshot | None = None) -> List[Dict]
+/// The weights channel_scores() compares, taken apart: one dict per band
+/// (only `band` if given, "2.4GHz", "5GHz" or "6GHz") {band, observed,
+/// aps, candidates, matrix, busy, total}. observed are the primary
+/// channels APs were heard on and aps how many on each; candidates the
+/// channels weighed. matrix[i][j] is what the APs on observed[i] add to
+/// candidates[j], co-channel or overlapping, as `config` weighs them.
+/// busy[j] is the survey's part of candidates[j]'s weight and total[j] the
+/// whole: the column of matrix plus busy. Our own AP is left out as for
+/// compute_best_channel(). `iface` and `snapshot` as for it.
+#[pyfunction]
+#[pyo3(signature = (band=None, config=None, iface=None, snapshot=None))]
+fn interference_matrix(
+    py: Python<'_>,
+    band: Option<&str>,
+    config: Option<PyChannelConfig>,
+    iface: Option<&str>,
+    snapshot: Option<PyRef<'_, ScanSnapshot>>,
+) -> PyResult<PyObject> {
+    if let Some(snap) = snapshot {
+        return snap.interference_matrix(py, band, config);
+    }
+    if let Some(name) = iface {
+        return session_on(py, name)?.interference_matrix(py, band, config);
+    }
+    let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+    let cfg = config_of(config);
+    map_pyerr(cfg.validate())?;
+    let matrices = map_pyerr(py.allow_threads(|| -> anyhow::Result<_> {
+        let rows = scan_all_bss()?;
+        chansurvey::refresh(backend(), None);
+        let connected = lib_rust::connected_in(backend(), None, &rows)?;
+        let busy = chansurvey::recent_busy();
+        Ok(lib_rust::interference_matrix(&rows, connected.as_ref(), band, &busy, &cfg))
+    }))?;
+    matrix_list(py, &matrices)
+}
+
+fn matrix_list(py: Python<'_>, matrices: &[lib_rust::InterferenceMatrix]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for m in matrices {
+        let d = PyDict::new_bound(py);
+        d.set_item("band", band_name(m.band))?;
+        d.set_item("observed", &m.observed)?;
+        d.set_item("aps", &m.aps)?;
+        d.set_item("candidates", &m.candidates)?;
+        d.set_item("matrix", &m.weight)?;
+        d.set_item("busy", &m.busy)?;
+        d.set_item("total", &m.total)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+fn scores_list(py: Python<'_>, scores: &[lib_rust::ChannelScore]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for s in scores {
+        let d = PyDict::new_bound(py);
+        d.set_item("band", band_name(s.band))?;
+        d.set_item("channel", s.channel)?;
+        d.set_item("weight", s.weight)?;
+        d.set_item("aps", s.aps)?;
+        d.set_item("busy", s.busy)?;
+        d.set_item("recommended", s.recommended)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: ChannelConfig(threshold_dbm: float = -80.0, margin: float = 10.0,
+///                       prefer_band: str | None = None,
+///                       floor_dbm: float = -100.0,
+///                       max_age_ms: int | None = None,
+///                       legacy_weight: float = 1.0)
+/// Tuning for compute_best_channel() and the snapshot / session versions
+/// (`config=`). APs weaker than threshold_dbm are ignored; the others
+/// weigh one per dB above floor_dbm, so raising the floor makes strong
+/// neighbours count for relatively more. The current channel is kept
+/// unless another one has `margin` less weight. prefer_band ("2.4GHz",
+/// "5GHz", "6GHz") limits the choice to that band when not connected.
+/// max_age_ms ignores APs last heard longer ago than that (age_ms), so
+/// ones that have gone stop counting while the kernel still caches them;
+/// leave it None with the neli-wifi backend unless something scans
+/// regularly, or every entry may be too old. legacy_weight scales the
+/// weight of legacy-only (802.11a/b/g, generation "legacy") APs: above 1
+/// for the airtime their slow rates take, below 1 to discount them. The
+/// defaults are the built-in behaviour. Raises RuntimeError unless
+/// floor_dbm < threshold_dbm, margin >= 0 and legacy_weight >= 0.
+#[pyclass(name = "ChannelConfig", module = "wifi_backend")]
+#[derive(Clone)]
+struct PyChannelConfig {
+    inner: ChannelConfig,
+}
+
+#[pymethods]
+impl PyChannelConfig {
+    #[new]
+    #[pyo3(signature = (threshold_dbm=-80.0, margin=10.0, prefer_band=None, floor_dbm=-100.0, max_age_ms=None, legacy_weight=1.0))]
+    fn new(
+        threshold_dbm: f32,
+        margin: f32,
+        prefer_band: Option<&str>,
+        floor_dbm: f32,
+        max_age_ms: Option<u32>,
+        legacy_weight: f32,
+    ) -> PyResult<Self> {
+        let inner = ChannelConfig {
+            threshold_dbm,
+            margin,
+            prefer_band: prefer_band.map(|b| map_pyerr(band_from_name(b))).transpose()?,
+            floor_dbm,
+            max_age_ms,
+            legacy_weight,
+        };
+        map_pyerr(inner.validate())?;
+        Ok(PyChannelConfig { inner })
+    }
+
+    #[getter]
+    fn threshold_dbm(&self) -> f32 {
+        self.inner.threshold_dbm
+    }
+
+    #[getter]
+    fn margin(&self) -> f32 {
+        self.inner.margin
+    }
+
+    #[getter]
+    fn prefer_band(&self) -> Option<&'static str> {
+        self.inner.prefer_band.map(band_name)
+    }
+
+    #[getter]
+    fn floor_dbm(&self) -> f32 {
+        self.inner.floor_dbm
+    }
+
+    #[getter]
+    fn max_age_ms(&self) -> Option<u32> {
+        self.inner.max_age_ms
+    }
+
+    #[getter]
+    fn legacy_weight(&self) -> f32 {
+        self.inner.legacy_weight
+    }
+
+    fn __repr__(&self) -> String {
+        let c = &self.inner;
+        format!(
+            "ChannelConfig(threshold_dbm={:.1}, margin={:.1}, prefer_band={}, floor_dbm={:.1}, max_age_ms={}, legacy_weight={:.2})",
+            c.threshold_dbm,
+            c.margin,
+            c.prefer_band.map_or("None".into(), |b| format!("{:?}", band_name(b))),
+            c.floor_dbm,
+            c.max_age_ms.map_or("None".into(), |m| m.to_string()),
+            c.legacy_weight
+        )
+    }
+}
+
+fn config_of(config: Option<PyChannelConfig>) -> ChannelConfig {
+    config.map_or(ChannelConfig::DEFAULT, |c| c.inner)
+}
+
+/// Python: survey() -> List[Dict]
+/// Channel survey (nl80211 GET_SURVEY) of the Wi-Fi radio, one dict per
+/// frequency: {freq_mhz, channel, band, noise_dbm, in_use, active_ms,
+/// busy_ms, ext_busy_ms, rx_ms, tx_ms, scan_ms, busy}. Times are the
+/// driver's cumulative counters in ms, None where it doesn't keep them;
+/// off-channel ones only grow while scanning. busy is the share of
+/// active time the channel was busy with anything but our own
+/// transmissions, since the previous survey() when the counters moved.
+/// compute_best_channel() and compute_channels() weigh a recent survey in.
+#[pyfunction]
+#[pyo3(name = "survey")]
+fn channel_survey(py: Python<'_>) -> PyResult<PyObject> {
+    let surveys = map_pyerr(py.allow_threads(|| chansurvey::survey(backend(), None)))?;
+    surveys_list(py, surveys)
+}
+
+/// Python: regulatory_domain() -> Dict
+/// The regulatory domain the kernel enforces (nl80211 GET_REG):
+/// {country, dfs_region, rules, channels}. country is the ISO alpha2
+/// ("00" world, "99" driver-built), dfs_region "FCC" / "ETSI" / "JP" or
+/// None. rules: [{start_mhz, end_mhz, max_bandwidth_mhz,
+/// max_antenna_gain_dbi, max_eirp_dbm, flags, dfs_cac_ms}], flags as
+/// names ("no-ir", "dfs", "no-outdoor", ...). channels: every 20 MHz
+/// channel as {band, channel, freq_mhz, allowed, no_ir, dfs, max_eirp_dbm};
+/// the best-channel calls only pick ones allowed and not no_ir.
+#[pyfunction]
+fn regulatory_domain(py: Python<'_>) -> PyResult<PyObject> {
+    let reg = map_pyerr(py.allow_threads(|| regdom::query(backend())))?;
+    let rules = PyList::empty_bound(py);
+    for r in &reg.rules {
+        let d = PyDict::new_bound(py);
+        d.set_item("start_mhz", r.start_khz as f64 / 1000.0)?;
+        d.set_item("end_mhz", r.end_khz as f64 / 1000.0)?;
+        d.set_item("max_bandwidth_mhz", r.max_bw_khz as f64 / 1000.0)?;
+        d.set_item("max_antenna_gain_dbi", r.max_ant_gain_mbi.map(|g| g as f32 / 100.0))?;
+        d.set_item("max_eirp_dbm", r.max_eirp_mbm.map(|p| p as f32 / 100.0))?;
+        d.set_item("flags", r.flag_names())?;
+        d.set_item("dfs_cac_ms", r.dfs_cac_ms)?;
+        rules.append(d)?;
+    }
+    let channels = PyList::empty_bound(py);
+    for (band, ch, c) in reg.channels() {
+        let d = PyDict::new_bound(py);
+        d.set_item("band", band_name(band))?;
+        d.set_item("channel", ch)?;
+        d.set_item("freq_mhz", lib_rust::channel_to_freq(band, ch))?;
+        d.set_item("allowed", c.allowed)?;
+        d.set_item("no_ir", c.no_ir)?;
+        d.set_item("dfs", c.dfs)?;
+        d.set_item("max_eirp_dbm", c.max_eirp_dbm)?;
+        channels.append(d)?;
+    }
+    let out = PyDict::new_bound(py);
+    out.set_item("country", &reg.country)?;
+    out.set_item("dfs_region", reg.dfs_region.and_then(regdom::dfs_region_name))?;
+    out.set_item("rules", rules)?;
+    out.set_item("channels", channels)?;
+    Ok(out.into_py(py))
+}
+
+/// Python: phy_capabilities() -> List[Dict]
+/// What the Wi-Fi radio supports (nl80211 GET_WIPHY), one dict per band:
+/// {band, max_width_mhz, widths, ht, vht, he, eht, ht_capa, vht_capa,
+/// channels}. widths are the channel widths in MHz up to max_width_mhz;
+/// ht_capa / vht_capa the raw capability fields (None without HT / VHT).
+/// channels: [{channel, freq_mhz, disabled, no_ir, radar,
+/// max_tx_power_dbm}]. The best-channel calls only pick channels listed
+/// here and not disabled.
+#[pyfunction]
+fn phy_capabilities(py: Python<'_>) -> PyResult<PyObject> {
+    let caps = map_pyerr(py.allow_threads(|| phycaps::query(backend(), None)))?;
+    caps_list(py, &caps)
+}
+
+// phy_capabilities()'s list.
+fn caps_list(py: Python<'_>, caps: &phycaps::PhyCaps) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for b in &caps.bands {
+        let channels = PyList::empty_bound(py);
+        for c in &b.channels {
+            let d = PyDict::new_bound(py);
+            d.set_item("channel", lib_rust::freq_to_channel(c.freq_mhz))?;
+            d.set_item("freq_mhz", c.freq_mhz)?;
+            d.set_item("disabled", c.disabled)?;
+            d.set_item("no_ir", c.no_ir)?;
+            d.set_item("radar", c.radar)?;
+            d.set_item("max_tx_power_dbm", c.max_tx_power_dbm)?;
+            channels.append(d)?;
+        }
+        let d = PyDict::new_bound(py);
+        d.set_item("band", band_name(b.band))?;
+        d.set_item("max_width_mhz", b.max_width_mhz())?;
+        d.set_item("widths", b.widths())?;
+        d.set_item("ht", b.ht_capa.is_some())?;
+        d.set_item("vht", b.vht_capa.is_some())?;
+        d.set_item("he", b.he_phy.is_some())?;
+        d.set_item("eht", b.eht_phy.is_some())?;
+        d.set_item("ht_capa", b.ht_capa)?;
+        d.set_item("vht_capa", b.vht_capa)?;
+        d.set_item("channels", channels)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+// survey()'s list.
+fn surveys_list(py: Python<'_>, surveys: Vec<chansurvey::ChannelSurvey>) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for s in surveys {
+        let d = PyDict::new_bound(py);
+        d.set_item("freq_mhz", s.freq_mhz)?;
+        d.set_item("channel", lib_rust::freq_to_channel(s.freq_mhz))?;
+        d.set_item("band", band_name(lib_rust::freq_band(s.freq_mhz)))?;
+        d.set_item("noise_dbm", s.noise_dbm)?;
+        d.set_item("in_use", s.in_use)?;
+        d.set_item("active_ms", s.active_ms)?;
+        d.set_item("busy_ms", s.busy_ms)?;
+        d.set_item("ext_busy_ms", s.ext_busy_ms)?;
+        d.set_item("rx_ms", s.rx_ms)?;
+        d.set_item("tx_ms", s.tx_ms)?;
+        d.set_item("scan_ms", s.scan_ms)?;
+        d.set_item("busy", s.busy)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: connected_bssid(iface: str | None = None) -> str | None
+/// `iface` as for scan().
+#[pyfunction]
+#[pyo3(signature = (iface=None))]
+fn connected_bssid(py: Python<'_>, iface: Option<&str>) -> PyResult<PyObject> {
+    if let Some(name) = iface {
+        return Ok(session_on(py, name)?.connected_bssid(py)?.into_py(py));
+    }
+    let maybe = map_pyerr(py.allow_threads(get_connected_bssid))?;
+    let obj = match maybe {
+        Some(mac) => format_mac(&mac).into_py(py),
+        None => py.None(),
+    };
+    Ok(obj)
+}
+
+/// Python: link_info(iface: str | None = None) -> Dict | None
+/// How the link to the connected AP is doing (nl80211 GET_STATION), None
+/// when not connected: {bssid, signal_dbm, signal_avg_dbm,
+/// tx_bitrate_kbps, rx_bitrate_kbps, tx_mcs, rx_mcs, tx_packets,
+/// rx_packets, tx_retries, tx_failed, rx_drop_misc, retry_ratio,
+/// expected_throughput_kbps, estimated_throughput_mbps, connected_secs,
+/// at, mono}. Counters are since association; retry_ratio is tx_retries
+/// per packet sent. MCS is None for legacy rates,
+/// expected_throughput_kbps where the driver's rate control doesn't
+/// estimate it; estimated_throughput_mbps is that, else the tx bitrate
+/// less the MAC's overhead. `iface` as for scan().
+#[pyfunction]
+#[pyo3(signature = (iface=None))]
+fn link_info(py: Python<'_>, iface: Option<&str>) -> PyResult<PyObject> {
+    if let Some(name) = iface {
+        return session_on(py, name)?.link_info(py);
+    }
+    let link = map_pyerr(py.allow_threads(|| link::query(backend(), None)))?;
+    link_dict(py, link)
+}
+
+// link_info()'s dict.
+fn link_dict(py: Python<'_>, link: Option<link::LinkInfo>) -> PyResult<PyObject> {
+    let Some(l) = link else {
+        return Ok(py.None());
+    };
+    let d = PyDict::new_bound(py);
+    set_link(&d, &l)?;
+    Ok(d.into_py(py))
+}
+
+fn set_link(d: &Bound<'_, PyDict>, l: &link::LinkInfo) -> PyResult<()> {
+    d.set_item("bssid", format_mac(&l.bssid))?;
+    d.set_item("signal_dbm", l.signal_dbm)?;
+    d.set_item("signal_avg_dbm", l.signal_avg_dbm)?;
+    d.set_item("tx_bitrate_kbps", l.tx_bitrate_kbps)?;
+    d.set_item("rx_bitrate_kbps", l.rx_bitrate_kbps)?;
+    d.set_item("tx_mcs", l.tx_mcs)?;
+    d.set_item("rx_mcs", l.rx_mcs)?;
+    d.set_item("tx_packets", l.tx_packets)?;
+    d.set_item("rx_packets", l.rx_packets)?;
+    d.set_item("tx_retries", l.tx_retries)?;
+    d.set_item("tx_failed", l.tx_failed)?;
+    d.set_item("rx_drop_misc", l.rx_drop_misc)?;
+    d.set_item("retry_ratio", l.retry_ratio())?;
+    d.set_item("expected_throughput_kbps", l.expected_throughput_kbps)?;
+    d.set_item("estimated_throughput_mbps", l.estimated_throughput_mbps())?;
+    d.set_item("connected_secs", l.connected_secs)?;
+    set_stamp(d, "at", "mono", l.at)
+}
+
+// The *_async() functions: the call runs on the netlink runtime's
+// blocking pool (threads are reused, and capped by tokio) and its result
+// settles an asyncio future of the running loop, so awaiting it leaves
+// the loop (and the UI it drives) free. Cancelling the future cancels
+// `cancel`, which stops a scan still in progress.
+fn py_future<T, W, C>(py: Python<'_>, cancel: cancel::Cancel, work: W, convert: C) -> PyResult<PyObject>
+where
+    T: Send + 'static,
+    W: FnOnce() -> anyhow::Result<T> + Send + 'static,
+    C: FnOnce(Python<'_>, T) -> PyResult<PyObject> + Send + 'static,
+{
+    let event_loop = py.import_bound("asyncio")?.call_method0("get_running_loop")?;
+    let fut = event_loop.call_method0("create_future")?;
+    let on_done = PyCFunction::new_closure_bound(py, None, None, move |args, _| -> PyResult<()> {
+        if args.get_item(0)?.call_method0("cancelled")?.is_truthy()? {
+            cancel.cancel();
+        }
+        Ok(())
+    })?;
+    fut.call_method1("add_done_callback", (on_done,))?;
+    let (event_loop, future) = (event_loop.unbind(), fut.clone().unbind());
+    netlink::runtime().spawn_blocking(move || {
+        let res = work();
+        Python::with_gil(|py| {
+            let (exc, value) = match map_pyerr(res).and_then(|v| convert(py, v)) {
+                Ok(v) => (py.None(), v),
+                Err(e) => (e.into_value(py).into_py(py), py.None()),
+            };
+            // Fails only once the loop is closed, when nobody is waiting.
+            let _ = event_loop.call_method1(py, "call_soon_threadsafe", (settle_future(py), future, exc, value));
+        })
+    });
+    Ok(fut.unbind())
+}
+
+// settle(fut, exc, value) on the loop's thread: fut's exception if exc
+// isn't None, else its result; nothing if the task was cancelled meanwhile.
+fn settle_future(py: Python<'_>) -> PyObject {
+    let settle = PyCFunction::new_closure_bound(py, None, None, |args, _| -> PyResult<()> {
+        let (fut, exc, value): (Bound<'_, PyAny>, Bound<'_, PyAny>, Bound<'_, PyAny>) = args.extract()?;
+        if fut.call_method0("done")?.is_truthy()? {
+            return Ok(());
+        }
+        if exc.is_none() {
+            fut.call_method1("set_result", (value,))?;
+        } else {
+            fut.call_method1("set_exception", (exc,))?;
+        }
+        Ok(())
+    });
+    settle.map_or_else(|_| py.None(), |f| f.into_py(py))
+}
+
+// The token an async call checks: the caller's, or one only cancelling
+// the task sets.
+fn async_cancel(token: Option<CancelToken>) -> cancel::Cancel {
+    token.map_or_else(cancel::Cancel::new, |t| t.inner)
+}
+
+// The scan, the connected BSS and fresh survey / regulatory limits the
+// best-channel calls weigh, on `s`.
+fn best_channel_inputs(
+    s: &lib_rust::Session,
+    cancel: &cancel::Cancel,
+) -> anyhow::Result<(Vec<BssRow>, Option<[u8; 6]>)> {
+    let rows = s.scan(cancel)?;
+    chansurvey::refresh(s.backend(), s.ifindex());
+    ChannelLimits::refresh(s.backend(), s.ifindex());
+    let connected = s.connected_in(&rows)?;
+    Ok((rows, connected))
+}
+
+/// Python: scan_async(cancel: CancelToken | None = None,
+///                    options: ScanOptions | None = None,
+///                    iface: str | None = None) -> asyncio.Future[List[BssEntry]]
+/// scan() for asyncio: awaiting the future doesn't block the event loop.
+/// Must be called with a loop running. Cancelling the future (or a task
+/// awaiting it) stops the scan, and cancels `cancel` if given. It is a
+/// future, not a coroutine: asyncio.ensure_future() it to run it
+/// alongside other work. The other *_async() functions below work the
+/// same way and take their sync counterpart's arguments.
+#[pyfunction]
+#[pyo3(signature = (cancel=None, options=None, iface=None))]
+fn scan_async(
+    py: Python<'_>,
+    cancel: Option<CancelToken>,
+    options: Option<ScanOptions>,
+    iface: Option<String>,
+) -> PyResult<PyObject> {
+    let (options, cancel) = (options_of(options), async_cancel(cancel));
+    let token = cancel.clone();
+    let work = move || lib_rust::Session::open_on(backend(), iface.as_deref())?.scan_with(&options, &token);
+    py_future(py, cancel, work, entries_list)
+}
+
+/// Python: scan_dicts_async(details: bool = False,
+///                          fields: List[str] | None = None,
+///                          cancel: CancelToken | None = None,
+///                          options: ScanOptions | None = None,
+///                          iface: str | None = None) -> asyncio.Future[List[Dict]]
+#[pyfunction]
+#[pyo3(signature = (details=false, fields=None, cancel=None, options=None, iface=None))]
+fn scan_dicts_async(
+    py: Python<'_>,
+    details: bool,
+    fields: Option<Vec<String>>,
+    cancel: Option<CancelToken>,
+    options: Option<ScanOptions>,
+    iface: Option<String>,
+) -> PyResult<PyObject> {
+    let fields = Fields::from_args(details, fields)?;
+    let (options, cancel) = (options_of(options), async_cancel(cancel));
+    let token = cancel.clone();
+    let work = move || lib_rust::Session::open_on(backend(), iface.as_deref())?.scan_with(&options, &token);
+    py_future(py, cancel, work, move |py, rows| rows_list(py, &rows, fields))
+}
+
+/// Python: compute_channels_async(band: str | None = None,
+///                                detailed: bool = False,
+///                                iface: str | None = None)
+///     -> asyncio.Future[Dict[int, int] | List[Dict]]
+#[pyfunction]
+#[pyo3(signature = (band=None, detailed=false, iface=None))]
+fn compute_channels_async(py: Python<'_>, band: Option<&str>, detailed: bool, iface: Option<String>) -> PyResult<PyObject> {
+    let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+    let cancel = cancel::Cancel::new();
+    let token = cancel.clone();
+    let work = move || {
+        let s = lib_rust::Session::open_on(backend(), iface.as_deref())?;
+        let rows = s.scan(&token)?;
+        if detailed {
+            chansurvey::refresh(s.backend(), s.ifindex());
+        }
+        Ok(rows)
+    };
+    py_future(py, cancel, work, move |py, rows| {
+        if detailed {
+            channels_detailed(py, &rows, band)
+        } else {
+            Ok(lib_rust::channel_counts(&rows, band).into_py_dict_bound(py).into_py(py))
+        }
+    })
+}
+
+/// Python: compute_best_channel_async(candidates: List[int | Tuple[str, int]] | None = None,
+///                                    config: ChannelConfig | None = None,
+///                                    iface: str | None = None)
+///     -> asyncio.Future[Tuple[str, int]]
+#[pyfunction]
+#[pyo3(signature = (candidates=None, config=None, iface=None))]
+fn compute_best_channel_async(
+    py: Python<'_>,
+    candidates: Option<Vec<Candidate>>,
+    config: Option<PyChannelConfig>,
+    iface: Option<String>,
+) -> PyResult<PyObject> {
+    let candidates = candidates_of(candidates)?;
+    let cfg = config_of(config);
+    let cancel = cancel::Cancel::new();
+    let token = cancel.clone();
+    let work = move || {
+        let s = lib_rust::Session::open_on(backend(), iface.as_deref())?;
+        let (rows, connected) = best_channel_inputs(&s, &token)?;
+        lib_rust::best_channel_for(&rows, connected.as_ref(), candidates.as_deref(), &cfg)
+    };
+    py_future(py, cancel, work, |py, pick| Ok(pick_of(pick).into_py(py)))
+}
+
+/// Python: channel_scores_async(candidates: List[int | Tuple[str, int]] | None = None,
+///                              config: ChannelConfig | None = None,
+///                              iface: str | None = None) -> asyncio.Future[List[Dict]]
+#[pyfunction]
+#[pyo3(signature = (candidates=None, config=None, iface=None))]
+fn channel_scores_async(
+    py: Python<'_>,
+    candidates: Option<Vec<Candidate>>,
+    config: Option<PyChannelConfig>,
+    iface: Option<String>,
+) -> PyResult<PyObject> {
+    let candidates = candidates_of(candidates)?;
+    let cfg = config_of(config);
+    let cancel = cancel::Cancel::new();
+    let token = cancel.clone();
+    let work = move || {
+        let s = lib_rust::Session::open_on(backend(), iface.as_deref())?;
+        let (rows, connected) = best_channel_inputs(&s, &token)?;
+        let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+        lib_rust::channel_scores(&rows, connected.as_ref(), candidates.as_deref(), &busy, &limits, &cfg)
+    };
+    py_future(py, cancel, work, |py, scores| scores_list(py, &scores))
+}
+
+/// Python: connected_bssid_async(iface: str | None = None) -> asyncio.Future[str | None]
+#[pyfunction]
+#[pyo3(signature = (iface=None))]
+fn connected_bssid_async(py: Python<'_>, iface: Option<String>) -> PyResult<PyObject> {
+    let work = move || lib_rust::Session::open_on(backend(), iface.as_deref())?.connected_bssid();
+    py_future(py, cancel::Cancel::none(), work, |py, mac| Ok(mac.as_ref().map(format_mac).into_py(py)))
+}
+
+/// Python: link_info_async(iface: str | None = None) -> asyncio.Future[Dict | None]
+#[pyfunction]
+#[pyo3(signature = (iface=None))]
+fn link_info_async(py: Python<'_>, iface: Option<String>) -> PyResult<PyObject> {
+    let work = move || lib_rust::Session::open_on(backend(), iface.as_deref())?.link_info();
+    py_future(py, cancel::Cancel::none(), work, link_dict)
+}
+
+/// Python: LinkMonitor(interval: float = 1.0, capacity: int = 600,
+///                     iface: str | None = None, backend: str | None = None)
+/// Reads link_info() every `interval` seconds on a background thread and
+/// keeps the last `capacity` samples (600 at one a second: ten minutes),
+/// for plotting signal and bitrate without polling from Python.
+/// get_samples(since=None) returns them oldest first, only those taken
+/// after `since` (the mono of the last sample already fetched) if given:
+/// [{connected, at, mono, ...link_info() keys}], just {connected: False,
+/// at, mono} while not connected. stop() ends the polling, as does
+/// dropping the monitor; status() -> {running, polls, errors, last_error}.
+/// Raises RuntimeError if the interface isn't found.
+#[pyclass(module = "wifi_backend")]
+struct LinkMonitor {
+    inner: linkmon::LinkMonitor,
+}
+
+#[pymethods]
+impl LinkMonitor {
+    #[new]
+    #[pyo3(signature = (interval=1.0, capacity=600, iface=None, backend=None))]
+    fn new(
+        py: Python<'_>,
+        interval: f64,
+        capacity: usize,
+        iface: Option<String>,
+        backend: Option<&str>,
+    ) -> PyResult<Self> {
+        let cfg = linkmon::Config {
+            backend: match backend {
+                Some(name) => map_pyerr(Backend::from_name(name))?,
+                None => lib_rust::backend(),
+            },
+            iface,
+            interval: std::time::Duration::try_from_secs_f64(interval)
+                .map_err(|e| PyRuntimeError::new_err(format!("interval: {e}")))?,
+            capacity,
+        };
+        let inner = map_pyerr(py.allow_threads(|| linkmon::LinkMonitor::start(cfg)))?;
+        Ok(LinkMonitor { inner })
+    }
+
+    #[pyo3(signature = (since=None))]
+    fn get_samples(&self, py: Python<'_>, since: Option<f64>) -> PyResult<PyObject> {
+        let out = PyList::empty_bound(py);
+        for s in self.inner.samples(since) {
+            let d = PyDict::new_bound(py);
+            d.set_item("connected", s.link.is_some())?;
+            match &s.link {
+                Some(l) => set_link(&d, l)?,
+                None => set_stamp(&d, "at", "mono", Some(s.at))?,
+            }
+            out.append(d)?;
+        }
+        Ok(out.into_py(py))
+    }
+
+    fn stop(&mut self, py: Python<'_>) {
+        py.allow_threads(|| self.inner.stop());
+    }
+
+    /// Drop the samples kept so far.
+    fn clear(&self) {
+        self.inner.clear();
+    }
+
+    #[getter]
+    fn running(&self) -> bool {
+        self.inner.status().running
+    }
+
+    fn status(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let s = self.inner.status();
+        let d = PyDict::new_bound(py);
+        d.set_item("running", s.running)?;
+        d.set_item("polls", s.polls)?;
+        d.set_item("errors", s.errors)?;
+        d.set_item("last_error", s.last_error)?;
+        Ok(d.into_py(py))
+    }
+}
+
+// One event as the dict subscribe_events() and event callbacks get.
+fn event_dict<'py>(py: Python<'py>, ev: &events::WifiEvent) -> PyResult<Bound<'py, PyDict>> {
+    use events::EventKind;
+    let d = PyDict::new_bound(py);
+    d.set_item("type", ev.kind.name())?;
+    d.set_item("ifindex", ev.ifindex)?;
+    set_stamp(&d, "at", "mono", Some(ev.at))?;
+    match &ev.kind {
+        EventKind::Connected { bssid, status } => {
+            d.set_item("bssid", bssid.as_ref().map(format_mac))?;
+            d.set_item("status", status)?;
+        }
+        EventKind::Disconnected { reason, by_ap } => {
+            d.set_item("reason", reason)?;
+            d.set_item("by_ap", by_ap)?;
+        }
+        EventKind::Roamed { bssid } => d.set_item("bssid", bssid.as_ref().map(format_mac))?,
+        EventKind::ChannelSwitch { freq_mhz } => d.set_item("freq_mhz", freq_mhz)?,
+        _ => {}
+    }
+    Ok(d)
+}
+
+/// Iterator returned by subscribe_events().
+#[pyclass(module = "wifi_backend")]
+struct EventStream {
+    inner: Option<events::Subscription>,
+}
+
+#[pymethods]
+impl EventStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    // Waits in short slices so Ctrl-C still gets through.
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        loop {
+            if let Some(ev) = self.next(py, Some(0.2))? {
+                return Ok(Some(ev));
+            }
+            if self.inner.is_none() {
+                return Ok(None);
+            }
+            py.check_signals()?;
+        }
+    }
+
+    /// The next event, waiting at most `timeout` seconds (forever if
+    /// None); None if none came, or after close().
+    #[pyo3(signature = (timeout=None))]
+    fn next(&mut self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<PyObject>> {
+        let timeout = timeout
+            .map(|t| std::time::Duration::try_from_secs_f64(t).map_err(|e| PyRuntimeError::new_err(format!("timeout: {e}"))))
+            .transpose()?;
+        let Some(sub) = self.inner.as_mut() else {
+            return Ok(None);
+        };
+        let ev = map_pyerr(py.allow_threads(|| sub.next(timeout)))?;
+        ev.map(|ev| Ok(event_dict(py, &ev)?.into_py(py))).transpose()
+    }
+
+    /// Stop receiving events; iteration ends.
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    /// Events dropped because the stream wasn't read fast enough.
+    #[getter]
+    fn lost(&self) -> u64 {
+        self.inner.as_ref().map_or(0, |s| s.lost)
+    }
+}
+
+/// Python: subscribe_events(backend: str | None = None) -> EventStream
+/// nl80211 events from now on, instead of polling for them: each a dict
+/// {type, ifindex, at, mono} where type is "scan_started",
+/// "scan_finished", "scan_aborted", "connected" (+ bssid, status: the
+/// 802.11 status code, 0 on success), "disconnected" (+ reason: the
+/// 802.11 reason code, by_ap), "roamed" (+ bssid), "channel_switch"
+/// (+ freq_mhz) or "reg_change". Iterating blocks until the next event;
+/// EventStream.next(timeout) waits at most `timeout` seconds and returns
+/// None instead. close() ends the stream; lost counts events dropped
+/// because it wasn't read fast enough.
+#[pyfunction]
+#[pyo3(signature = (backend=None))]
+fn subscribe_events(backend: Option<&str>) -> PyResult<EventStream> {
+    let b = match backend {
+        Some(name) => map_pyerr(Backend::from_name(name))?,
+        None => lib_rust::backend(),
+    };
+    Ok(EventStream {
+        inner: Some(map_pyerr(events::Subscription::open(b))?),
+    })
+}
+
+/// Python: add_event_callback(callback: Callable[[Dict], None],
+///                            backend: str | None = None) -> int
+/// Call `callback` with every subscribe_events() dict from now on, from a
+/// background thread. Exceptions it raises are reported as unraisable and
+/// don't stop further calls. Returns the id for remove_event_callback().
+#[pyfunction]
+#[pyo3(signature = (callback, backend=None))]
+fn add_event_callback(callback: PyObject, backend: Option<&str>) -> PyResult<u64> {
+    let b = match backend {
+        Some(name) => map_pyerr(Backend::from_name(name))?,
+        None => lib_rust::backend(),
+    };
+    map_pyerr(events::watch(b, move |ev| {
+        Python::with_gil(|py| {
+            if let Err(e) = event_dict(py, &ev).and_then(|d| callback.call1(py, (d,))) {
+                e.write_unraisable_bound(py, Some(callback.bind(py)));
+            }
+        })
+    }))
+}
+
+/// Python: remove_event_callback(id: int | None = None) -> bool
+/// Stop calling the add_event_callback() `id`, every one if None. False
+/// if there was no such callback.
+#[pyfunction]
+#[pyo3(signature = (id=None))]
+fn remove_event_callback(py: Python<'_>, id: Option<u64>) -> bool {
+    // The callback thread needs the GIL to finish its current call.
+    py.allow_threads(|| match id {
+        Some(id) => events::unwatch(id),
+        None => {
+            events::unwatch_all();
+            true
+        }
+    })
+}
+
+/// Python: ScanSnapshot(rows: List[Dict], connected_bssid: str | None = None)
+/// One scan and the connected BSSID at the time, from snapshot() /
+/// WifiSession.snapshot() (or built from scan dicts). Its methods all
+/// answer from those same rows, so asking several questions costs one
+/// scan and the answers agree: rows(), channels(), best_channel(),
+/// best_channel_plan(), best_channel_for_band(), channel_scores(),
+/// interference_matrix(), connected_entry(),
+/// hidden_network_count(), rnr_neighbors(), group_by_ssid(),
+/// suggest_roam_target(), device_groups(), security_report(),
+/// regulatory_mismatches(). to_json() and ScanSnapshot.from_json() carry
+/// it to and from other programs (format in export.rs); the mesh planner
+/// takes one per node.
+#[pyclass(module = "wifi_backend")]
+struct ScanSnapshot {
+    inner: lib_rust::ScanSnapshot,
+}
+
+#[pymethods]
+impl ScanSnapshot {
+    #[new]
+    #[pyo3(signature = (rows, connected_bssid=None))]
+    fn new(rows: Bound<'_, PyList>, connected_bssid: Option<String>) -> PyResult<Self> {
+        let rows = rows_from_list(&rows)?;
+        let connected = connected_bssid.map(|s| map_pyerr(parse_mac(&s))).transpose()?;
+        Ok(ScanSnapshot {
+            inner: lib_rust::ScanSnapshot::new(rows, connected),
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.rows.len()
+    }
+
+    #[getter]
+    fn connected_bssid(&self) -> Option<String> {
+        self.inner.connected.as_ref().map(format_mac)
+    }
+
+    /// The row of the BSS we were connected to, None when not connected
+    /// (or it wasn't in the scan).
+    fn connected_entry(&self) -> Option<BssEntry> {
+        self.inner.connected_entry().map(|r| BssEntry { row: r.clone() })
+    }
+
+    /// When the snapshot was taken: unix seconds.
+    #[getter]
+    fn at(&self) -> f64 {
+        self.inner.at.wall
+    }
+
+    /// The same, in time.monotonic() seconds.
+    #[getter]
+    fn mono(&self) -> f64 {
+        self.inner.at.mono
+    }
+
+    /// The scan's dicts, as scan_dicts(details, fields) returns them.
+    #[pyo3(signature = (details=false, fields=None))]
+    fn rows(&self, py: Python<'_>, details: bool, fields: Option<Vec<String>>) -> PyResult<PyObject> {
+        rows_list(py, &self.inner.rows, Fields::from_args(details, fields)?)
+    }
+
+    /// rnr_neighbors() from this scan.
+    fn rnr_neighbors(&self, py: Python<'_>) -> PyResult<PyObject> {
+        rnr_list(py, &self.inner.rnr_neighbors())
+    }
+
+    /// suggest_roam_target() from this scan.
+    #[pyo3(signature = (hysteresis_db=roam::DEFAULT_HYSTERESIS_DB))]
+    fn suggest_roam_target(&self, py: Python<'_>, hysteresis_db: f32) -> PyResult<PyObject> {
+        roam_dict(py, self.inner.roam_advice(hysteresis_db))
+    }
+
+    /// device_groups() from this scan.
+    fn device_groups(&self, py: Python<'_>) -> PyResult<PyObject> {
+        devices_list(py, &self.inner.device_groups())
+    }
+
+    /// group_by_ssid() from this scan.
+    fn group_by_ssid(&self, py: Python<'_>) -> PyResult<PyObject> {
+        ess_list(py, &self.inner.group_by_ssid())
+    }
+
+    /// security_report() from this scan.
+    fn security_report(&self, py: Python<'_>) -> PyResult<PyObject> {
+        audit_list(py, &self.inner.security_report())
+    }
+
+    /// regulatory_mismatches() from this scan, against the latest
+    /// regulatory domain read.
+    fn regulatory_mismatches(&self, py: Python<'_>) -> PyResult<PyObject> {
+        regcheck_dict(py, &self.inner.regulatory_check())
+    }
+
+    /// BSSs of this scan hiding their SSID, on `channel` or on any.
+    #[pyo3(signature = (channel=None))]
+    fn hidden_network_count(&self, channel: Option<u32>) -> usize {
+        self.inner.hidden_network_count(channel)
+    }
+
+    /// compute_channels() on this scan.
+    #[pyo3(signature = (band=None, detailed=false))]
+    fn channels(&self, py: Python<'_>, band: Option<&str>, detailed: bool) -> PyResult<PyObject> {
+        let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+        if !detailed {
+            return Ok(self.inner.channels(band).into_py_dict_bound(py).into_py(py));
+        }
+        channels_detailed(py, &self.inner.rows, band)
+    }
+
+    /// compute_best_channel() on this scan: (band, channel).
+    #[pyo3(signature = (candidates=None, config=None))]
+    fn best_channel(
+        &self,
+        candidates: Option<Vec<Candidate>>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<(&'static str, u32)> {
+        let candidates = candidates_of(candidates)?;
+        map_pyerr(self.inner.best_channel(candidates.as_deref(), &config_of(config))).map(pick_of)
+    }
+
+    /// compute_best_channel_plan() on this scan: (band, channel, width_mhz).
+    #[pyo3(signature = (candidates=None, config=None))]
+    fn best_channel_plan(
+        &self,
+        candidates: Option<Vec<Candidate>>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<(&'static str, u32, u32)> {
+        let candidates = candidates_of(candidates)?;
+        let (band, ch, width) = map_pyerr(self.inner.best_channel_plan(candidates.as_deref(), &config_of(config)))?;
+        Ok((band_name(band), ch, width))
+    }
+
+    /// channel_scores() on this scan.
+    #[pyo3(signature = (candidates=None, config=None))]
+    fn channel_scores(
+        &self,
+        py: Python<'_>,
+        candidates: Option<Vec<Candidate>>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<PyObject> {
+        let candidates = candidates_of(candidates)?;
+        let scores = map_pyerr(self.inner.channel_scores(candidates.as_deref(), &config_of(config)))?;
+        scores_list(py, &scores)
+    }
+
+    /// interference_matrix() on this scan.
+    #[pyo3(signature = (band=None, config=None))]
+    fn interference_matrix(
+        &self,
+        py: Python<'_>,
+        band: Option<&str>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<PyObject> {
+        let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+        let matrices = map_pyerr(self.inner.interference_matrix(band, &config_of(config)))?;
+        matrix_list(py, &matrices)
+    }
+
+    /// Best 20 MHz channel in `band` ("2.4GHz", "5GHz" or "6GHz") whichever
+    /// band we're connected on; on 5 GHz the DFS channels only with
+    /// dfs=True, on 6 GHz only Preferred Scanning Channels.
+    #[pyo3(signature = (band, dfs=false, config=None))]
+    fn best_channel_for_band(&self, band: &str, dfs: bool, config: Option<PyChannelConfig>) -> PyResult<u32> {
+        let band = map_pyerr(band_from_name(band))?;
+        map_pyerr(self.inner.best_channel_for_band(band, dfs, &config_of(config)))
+    }
+
+    /// The scan as a JSON document: schema version, when it was taken, the
+    /// connected BSSID and every row with its IEs (hex) and what they say
+    /// (band, security, width_mhz, wifi_gen, generation, max_phy_rate_mbps,
+    /// estimated_throughput_mbps).
+    #[pyo3(signature = (pretty=false))]
+    fn to_json(&self, pretty: bool) -> PyResult<String> {
+        map_pyerr(export::to_json(&self.inner, pretty))
+    }
+
+    /// The snapshot in a to_json() document, from this or another machine.
+    #[staticmethod]
+    fn from_json(text: &str) -> PyResult<ScanSnapshot> {
+        Ok(ScanSnapshot {
+            inner: map_pyerr(export::from_json(text))?,
+        })
+    }
+
+    /// Write the scan to `path` as CSV for spreadsheets: SSID, BSSID, band,
+    /// channel, width, RSSI, security, last seen (UTC), strongest first.
+    /// SSIDs are escaped so commas, quotes, control characters and leading
+    /// = + - @ can't break the file or run as formulas (see export.rs).
+    /// Returns the number of BSSs.
+    fn export_csv(&self, py: Python<'_>, path: std::path::PathBuf) -> PyResult<usize> {
+        map_pyerr(py.allow_threads(|| export::write_csv(&self.inner, &path)))
+    }
+
+    /// Write the scan to `path` in WiGLE's CSV upload format (1.4), every
+    /// BSS placed at `lat`, `lon` (degrees, WGS84; `alt` metres,
+    /// `accuracy_m` the fix's accuracy): MAC, SSID, AuthMode as Android
+    /// spells it ("[WPA2-PSK-CCMP][ESS]"), FirstSeen in UTC, channel, RSSI.
+    /// Returns the number of BSSs written.
+    #[pyo3(signature = (path, lat, lon, alt=None, accuracy_m=None))]
+    fn export_wigle_csv(
+        &self,
+        py: Python<'_>,
+        path: std::path::PathBuf,
+        lat: f64,
+        lon: f64,
+        alt: Option<f64>,
+        accuracy_m: Option<f64>,
+    ) -> PyResult<usize> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(PyRuntimeError::new_err("lat must be within ±90 and lon within ±180"));
+        }
+        let pos = export::Position {
+            lat,
+            lon,
+            alt_m: alt,
+            accuracy_m,
+        };
+        map_pyerr(py.allow_threads(|| export::write_wigle_csv(&self.inner, &pos, &path)))
+    }
+}
+
+/// Python: snapshot(cancel: CancelToken | None = None) -> ScanSnapshot
+/// Scan once (and read the connected BSSID) for several computations.
+#[pyfunction]
+#[pyo3(signature = (cancel=None))]
+fn snapshot(py: Python<'_>, cancel: Option<CancelToken>) -> PyResult<ScanSnapshot> {
+    let cancel = cancel_of(cancel);
+    let inner = map_pyerr(py.allow_threads(|| lib_rust::ScanSnapshot::capture(&cancel)))?;
+    Ok(ScanSnapshot { inner })
+}
+
+/// Python: scan_json(pretty: bool = False, cancel: CancelToken | None = None,
+///                   iface: str | None = None) -> str
+/// snapshot().to_json(pretty): one scan, with the connected BSSID, as a
+/// versioned JSON document (format in export.rs).
+#[pyfunction]
+#[pyo3(signature = (pretty=false, cancel=None, iface=None))]
+fn scan_json(py: Python<'_>, pretty: bool, cancel: Option<CancelToken>, iface: Option<&str>) -> PyResult<String> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.to_json(pretty)
+}
+
+/// Python: diff_scans(old: ScanSnapshot, new: ScanSnapshot,
+///                     min_rssi_delta: float = 10.0) -> Dict
+/// What changed from `old` to `new`, matched by BSSID: {appeared,
+/// disappeared (scan dicts, strongest first), channel_changes: [{bssid,
+/// ssid, old_channel, new_channel, old_freq_mhz, new_freq_mhz}],
+/// rssi_changes: [{bssid, ssid, old_dbm, new_dbm, delta}] of at least
+/// `min_rssi_delta` dB, biggest first, elapsed_s, changed}. A neighbour
+/// moving channel shows up in channel_changes.
+#[pyfunction]
+#[pyo3(signature = (old, new, min_rssi_delta=scandiff::DEFAULT_MIN_RSSI_DELTA))]
+fn diff_scans(
+    py: Python<'_>,
+    old: PyRef<'_, ScanSnapshot>,
+    new: PyRef<'_, ScanSnapshot>,
+    min_rssi_delta: f32,
+) -> PyResult<PyObject> {
+    let d = scandiff::diff(&old.inner, &new.inner, min_rssi_delta);
+    let channel_changes = PyList::empty_bound(py);
+    for c in &d.channel_changes {
+        let e = PyDict::new_bound(py);
+        e.set_item("bssid", format_mac(&c.bssid))?;
+        e.set_item("ssid", c.ssid.as_deref())?;
+        e.set_item("old_channel", c.old_channel)?;
+        e.set_item("new_channel", c.new_channel)?;
+        e.set_item("old_freq_mhz", c.old_freq_mhz)?;
+        e.set_item("new_freq_mhz", c.new_freq_mhz)?;
+        channel_changes.append(e)?;
+    }
+    let rssi_changes = PyList::empty_bound(py);
+    for c in &d.rssi_changes {
+        let e = PyDict::new_bound(py);
+        e.set_item("bssid", format_mac(&c.bssid))?;
+        e.set_item("ssid", c.ssid.as_deref())?;
+        e.set_item("old_dbm", c.old_dbm)?;
+        e.set_item("new_dbm", c.new_dbm)?;
+        e.set_item("delta", c.delta())?;
+        rssi_changes.append(e)?;
+    }
+
+    let out = PyDict::new_bound(py);
+    out.set_item("appeared", rows_list(py, &d.appeared, Fields::BASIC)?)?;
+    out.set_item("disappeared", rows_list(py, &d.disappeared, Fields::BASIC)?)?;
+    out.set_item("channel_changes", channel_changes)?;
+    out.set_item("rssi_changes", rssi_changes)?;
+    out.set_item("elapsed_s", d.elapsed_s)?;
+    out.set_item("changed", !d.is_empty())?;
+    Ok(out.into_py(py))
+}
+
+/// Python: rnr_neighbors(cancel: CancelToken | None = None,
+///                       iface: str | None = None) -> List[Dict]
+/// 6 GHz BSSs a fresh scan didn't hear but found listed in the Reduced
+/// Neighbor Report of a 2.4 / 5 GHz beacon, as Wi-Fi 6E APs advertise
+/// their 6 GHz radio: scan dicts {ssid (when it shares the reporter's),
+/// bssid (when listed), freq_mhz, channel, signal_dbm (the reporter's less
+/// the extra 6 GHz path loss), seen_at, seen_mono, age_ms, cached, hidden,
+/// width_mhz} plus reported_by (the reporter's BSSID) and co_located (a
+/// radio of the reporting AP). The best-channel calls count them on
+/// 6 GHz.
+#[pyfunction]
+#[pyo3(signature = (cancel=None, iface=None))]
+fn rnr_neighbors(py: Python<'_>, cancel: Option<CancelToken>, iface: Option<&str>) -> PyResult<PyObject> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.rnr_neighbors(py)
+}
+
+fn rnr_list(py: Python<'_>, neighbors: &[rnr::Neighbor]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for n in neighbors {
+        let d = row_dict(py, &n.row, Fields::BASIC)?;
+        d.set_item("width_mhz", n.row.channel_width())?;
+        d.set_item("reported_by", format_mac(&n.reported_by))?;
+        d.set_item("co_located", n.co_located)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: group_by_ssid(cancel: CancelToken | None = None,
+///                       iface: str | None = None) -> List[Dict]
+/// The named networks of a fresh scan, strongest first: {ssid,
+/// ssid_bytes, bssids (strongest first), bands, best_dbm, devices, mesh}.
+/// bands maps "2.4GHz" / "5GHz" / "6GHz" to {bssids (how many), best_dbm,
+/// channels}. devices counts the APs serving the network (same_device(),
+/// shared MLD address); mesh is true for more than one, or an EasyMesh
+/// (Multi-AP) network, though neighbours who picked the same name look
+/// the same. Hidden networks are left out (see hidden_network_count()).
+#[pyfunction]
+#[pyo3(signature = (cancel=None, iface=None))]
+fn group_by_ssid(py: Python<'_>, cancel: Option<CancelToken>, iface: Option<&str>) -> PyResult<PyObject> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.group_by_ssid(py)
+}
+
+fn ess_list(py: Python<'_>, networks: &[ess::Ess]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for e in networks {
+        let d = PyDict::new_bound(py);
+        d.set_item("ssid", &*e.ssid)?;
+        d.set_item("ssid_bytes", PyBytes::new_bound(py, &e.ssid_bytes))?;
+        d.set_item("bssids", e.bssids.iter().map(format_mac).collect::<Vec<_>>())?;
+        let bands = PyDict::new_bound(py);
+        for b in &e.bands {
+            let bd = PyDict::new_bound(py);
+            bd.set_item("bssids", b.bssids)?;
+            bd.set_item("best_dbm", b.best_dbm)?;
+            bd.set_item("channels", b.channels.clone())?;
+            bands.set_item(band_name(b.band), bd)?;
+        }
+        d.set_item("bands", bands)?;
+        d.set_item("best_dbm", e.best_dbm)?;
+        d.set_item("devices", e.devices)?;
+        d.set_item("mesh", e.mesh)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: security_report(cancel: CancelToken | None = None,
+///                         iface: str | None = None) -> List[Dict]
+/// Security audit of the named networks of a fresh scan, worst grade
+/// first: {ssid, ssid_bytes, bssids, best_dbm, worst_security, tkip, pmf,
+/// wps, wps_pin, grade, issues, connected}. worst_security is the weakest
+/// any of its BSSs offers (names as scan dicts' security); tkip is true
+/// when one still offers TKIP (WPA, or WPA/WPA2 mixed mode); pmf the
+/// weakest management frame protection, "disabled", "capable" or
+/// "required" (None without RSN); wps whether any BSS runs WPS, wps_pin
+/// whether one takes PINs and isn't locked (open to PIN brute force).
+/// grade is "A" (WPA3, enterprise, WPA2 with PMF required) to "F" (open,
+/// WEP), one lower with WPS on and one more for wps_pin; issues lists
+/// what cost it: "open", "wep", "wpa_tkip", "no_pmf", "wps", "wps_pin".
+/// connected marks our own network.
+#[pyfunction]
+#[pyo3(signature = (cancel=None, iface=None))]
+fn security_report(py: Python<'_>, cancel: Option<CancelToken>, iface: Option<&str>) -> PyResult<PyObject> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.security_report(py)
+}
+
+fn audit_list(py: Python<'_>, networks: &[secaudit::EssAudit]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for e in networks {
+        let d = PyDict::new_bound(py);
+        d.set_item("ssid", &*e.ssid)?;
+        d.set_item("ssid_bytes", PyBytes::new_bound(py, &e.ssid_bytes))?;
+        d.set_item("bssids", e.bssids.iter().map(format_mac).collect::<Vec<_>>())?;
+        d.set_item("best_dbm", e.best_dbm)?;
+        d.set_item("worst_security", e.worst_security.name())?;
+        d.set_item("tkip", e.tkip)?;
+        d.set_item("pmf", e.pmf.map(|p| p.name()))?;
+        d.set_item("wps", e.wps)?;
+        d.set_item("wps_pin", e.wps_pin)?;
+        d.set_item("grade", e.grade.name())?;
+        d.set_item("issues", e.issues.iter().map(|i| i.name()).collect::<Vec<_>>())?;
+        d.set_item("connected", e.connected)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: regulatory_mismatches(cancel: CancelToken | None = None,
+///                               iface: str | None = None) -> Dict
+/// The countries the APs of a fresh scan claim in their Country elements,
+/// against the regulatory domain we enforce (regulatory_domain()):
+/// {local, claims, mismatches, off_domain}. local is our domain's
+/// country. claims, most BSSIDs first: {country, bssids, matches_local,
+/// extra_channels}; matches_local is None when our domain is the world
+/// ("00") or driver-built ("99") one, extra_channels the {band, channel}
+/// its elements list that our domain doesn't allow, e.g. 12 and 13 from a
+/// European AP in the US. mismatches lists the claimed countries other
+/// than ours; off_domain are scan dicts of the BSSs heard on channels our
+/// domain doesn't allow at all, strongest first.
+#[pyfunction]
+#[pyo3(signature = (cancel=None, iface=None))]
+fn regulatory_mismatches(py: Python<'_>, cancel: Option<CancelToken>, iface: Option<&str>) -> PyResult<PyObject> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.regulatory_mismatches(py)
+}
+
+fn regcheck_dict(py: Python<'_>, report: &regcheck::RegReport) -> PyResult<PyObject> {
+    let cc = |c: &[u8; 2]| String::from_utf8_lossy(c).into_owned();
+    let claims = PyList::empty_bound(py);
+    for c in &report.claims {
+        let d = PyDict::new_bound(py);
+        d.set_item("country", cc(&c.country))?;
+        d.set_item("bssids", c.bssids.iter().map(format_mac).collect::<Vec<_>>())?;
+        d.set_item("matches_local", c.matches_local)?;
+        let extra = PyList::empty_bound(py);
+        for &(band, ch) in &c.extra_channels {
+            let e = PyDict::new_bound(py);
+            e.set_item("band", band_name(band))?;
+            e.set_item("channel", ch)?;
+            extra.append(e)?;
+        }
+        d.set_item("extra_channels", extra)?;
+        claims.append(d)?;
+    }
+    let off_domain = PyList::empty_bound(py);
+    for r in &report.off_domain {
+        off_domain.append(row_dict(py, r, Fields::BASIC)?)?;
+    }
+    let out = PyDict::new_bound(py);
+    out.set_item("local", report.local.as_deref())?;
+    out.set_item("claims", claims)?;
+    out.set_item("mismatches", report.mismatches().map(|c| cc(&c.country)).collect::<Vec<_>>())?;
+    out.set_item("off_domain", off_domain)?;
+    Ok(out.into_py(py))
+}
+
+/// Python: suggest_roam_target(hysteresis_db: float = 8.0,
+///                             cancel: CancelToken | None = None,
+///                             iface: str | None = None) -> Dict | None
+/// Where to roam within the network we're connected to, from a fresh
+/// scan: {current, current_score, candidates, target, roam, sticky}.
+/// candidates are the other BSSs with our SSID, best first, as scan dicts
+/// plus score (signal, +5 dB on 5 GHz, +8 dB on 6 GHz), gain_db (score
+/// over the current BSS's) and same_device (a radio of the node we're
+/// on); 5 / 6 GHz ones weaker than -72 dBm are left out. roam is true and
+/// target the best candidate when it beats the current BSS by
+/// hysteresis_db; sticky is true when our signal is below -70 dBm and
+/// another node is heard hysteresis_db louder, i.e. the phone hangs on to
+/// a distant node. None when not connected or the connected BSS isn't in
+/// the scan or hides its SSID.
+#[pyfunction]
+#[pyo3(signature = (hysteresis_db=roam::DEFAULT_HYSTERESIS_DB, cancel=None, iface=None))]
+fn suggest_roam_target(
+    py: Python<'_>,
+    hysteresis_db: f32,
+    cancel: Option<CancelToken>,
+    iface: Option<&str>,
+) -> PyResult<PyObject> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.suggest_roam_target(py, hysteresis_db)
+}
+
+fn roam_dict(py: Python<'_>, advice: Option<roam::RoamAdvice>) -> PyResult<PyObject> {
+    let Some(a) = advice else {
+        return Ok(py.None());
+    };
+    let candidate = |c: &roam::Candidate| -> PyResult<Bound<'_, PyDict>> {
+        let d = row_dict(py, &c.row, Fields::BASIC)?;
+        d.set_item("score", c.score)?;
+        d.set_item("gain_db", c.gain_db)?;
+        d.set_item("same_device", c.same_device)?;
+        Ok(d)
+    };
+    let d = PyDict::new_bound(py);
+    d.set_item("current", row_dict(py, &a.current, Fields::BASIC)?)?;
+    d.set_item("current_score", a.current_score)?;
+    let candidates = PyList::empty_bound(py);
+    for c in &a.candidates {
+        candidates.append(candidate(c)?)?;
+    }
+    d.set_item("candidates", candidates)?;
+    d.set_item("target", a.target().map(candidate).transpose()?)?;
+    d.set_item("roam", a.roam)?;
+    d.set_item("sticky", a.sticky)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: device_groups(cancel: CancelToken | None = None,
+///                       iface: str | None = None) -> List[Dict]
+/// The BSSIDs of a fresh scan grouped by physical device, strongest first:
+/// {bssids, radios, channels, best_dbm, evidence}. radios are scan dicts
+/// by band, so a tri-band node shows each radio's channel; channels maps
+/// "2.4GHz" / "5GHz" / "6GHz" to the channels its radios use. evidence
+/// says what tied them together: "listed" (set_device_grouping()
+/// devices), "mbssid", "rnr" (a Reduced Neighbor Report listing the other
+/// as co-located), "mld", "pattern" (same SSID from nearby addresses of
+/// one OUI on different bands) or "rule" (same_device()'s rules and
+/// callback). The pairs a scan shows are remembered, so same_device() and
+/// the mesh planners group those radios from then on; a device's bssids
+/// are its node_bssids for assign_mesh_channels_*().
+#[pyfunction]
+#[pyo3(signature = (cancel=None, iface=None))]
+fn device_groups(py: Python<'_>, cancel: Option<CancelToken>, iface: Option<&str>) -> PyResult<PyObject> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    snap.device_groups(py)
+}
+
+fn devices_list(py: Python<'_>, devices: &[devgroup::Device]) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for dev in devices {
+        let d = PyDict::new_bound(py);
+        d.set_item("bssids", dev.bssids().iter().map(format_mac).collect::<Vec<_>>())?;
+        let radios = PyList::empty_bound(py);
+        let mut by_band: BTreeMap<u8, Vec<u32>> = BTreeMap::new();
+        for r in &dev.radios {
+            radios.append(row_dict(py, r, Fields::BASIC)?)?;
+            if let (Some(freq), Some(ch)) = (r.freq_mhz, r.channel) {
+                by_band.entry(lib_rust::freq_band(freq)).or_default().push(ch);
+            }
+        }
+        let channels = PyDict::new_bound(py);
+        for (band, mut list) in by_band {
+            list.sort_unstable();
+            list.dedup();
+            channels.set_item(band_name(band), list)?;
+        }
+        d.set_item("radios", radios)?;
+        d.set_item("channels", channels)?;
+        d.set_item("best_dbm", dev.best_dbm())?;
+        d.set_item("evidence", dev.evidence.clone())?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: detect_anomalies(own_ssids: List[str] = [], window: int | None = None,
+///                          cancel: CancelToken | None = None,
+///                          iface: str | None = None) -> List[Dict]
+/// A fresh scan checked for rogue APs and evil twins against the last
+/// `window` stored scans (all of them if None; see score_history()),
+/// most severe first: {kind, severity, bssid, ssid, row, ...}. kind is
+/// "unknown_oui" (one of own_ssids, by default the connected network's,
+/// from a BSSID whose OUI none of its earlier BSSIDs had; + oui, vendor),
+/// "security_downgrade" (an SSID weaker than in every earlier scan, e.g.
+/// WPA2 now open; + was, now) or "duplicate_bssid" (one BSSID on several
+/// channels in this scan; + freqs). severity is "high", "medium" or
+/// "low": a downgrade to open / WEP / WPA or an impostor that also
+/// downgrades is high, a duplicate where one entry is the kernel's cached
+/// copy (a channel switch, likely) low. row is the finding's scan dict.
+/// Without history only duplicate BSSIDs can be found.
+#[pyfunction]
+#[pyo3(signature = (own_ssids=Vec::new(), window=None, cancel=None, iface=None))]
+fn detect_anomalies(
+    py: Python<'_>,
+    own_ssids: Vec<String>,
+    window: Option<usize>,
+    cancel: Option<CancelToken>,
+    iface: Option<&str>,
+) -> PyResult<PyObject> {
+    // Taken before scanning, which records the new scan too.
+    let earlier = history::recent(window);
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    let own: Vec<Vec<u8>> = own_ssids.into_iter().map(String::into_bytes).collect();
+    let findings = py.allow_threads(|| snap.inner.anomalies(&earlier, &own));
+
+    let out = PyList::empty_bound(py);
+    for f in &findings {
+        let d = PyDict::new_bound(py);
+        d.set_item("kind", f.kind.name())?;
+        d.set_item("severity", f.severity.name())?;
+        d.set_item("bssid", format_mac(&f.bssid))?;
+        d.set_item("ssid", f.ssid.as_deref())?;
+        match &f.kind {
+            anomaly::Kind::UnknownOui { oui } => {
+                d.set_item("oui", format!("{:02x}:{:02x}:{:02x}", oui[0], oui[1], oui[2]))?;
+                d.set_item("vendor", oui::vendor(&f.bssid))?;
+            }
+            anomaly::Kind::SecurityDowngrade { was, now } => {
+                d.set_item("was", was.name())?;
+                d.set_item("now", now.name())?;
+            }
+            anomaly::Kind::DuplicateBssid { freqs } => d.set_item("freqs", freqs.clone())?,
+        }
+        d.set_item("row", row_dict(py, &f.row, Fields::BASIC)?)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: hidden_network_count(channel: int | None = None,
+///                              cancel: CancelToken | None = None,
+///                              iface: str | None = None) -> int
+/// How many BSSs in a fresh scan hide their SSID, on `channel` or on any
+/// ("3 hidden networks on channel 6"). Same as
+/// snapshot().hidden_network_count(channel).
+#[pyfunction]
+#[pyo3(signature = (channel=None, cancel=None, iface=None))]
+fn hidden_network_count(
+    py: Python<'_>,
+    channel: Option<u32>,
+    cancel: Option<CancelToken>,
+    iface: Option<&str>,
+) -> PyResult<usize> {
+    let snap = match iface {
+        Some(name) => session_on(py, name)?.snapshot(py, cancel)?,
+        None => snapshot(py, cancel)?,
+    };
+    Ok(snap.hidden_network_count(channel))
+}
+
+/// Python: WifiSession(backend: str | None = None, iface: str | None = None)
+/// The Wi-Fi interface (and backend, the selected one by default) looked
+/// up once, for apps that poll: scan(), scan_dicts(), compute_channels(),
+/// compute_best_channel(), compute_best_channel_plan(), channel_scores(),
+/// interference_matrix(), connected_bssid() and snapshot() work like the module functions but
+/// skip finding the interface every call. `iface` names it (see
+/// list_interfaces()); by default the first station interface. The
+/// netlink socket is shared by everything already. If the interface
+/// disappears, the next call looks it up again; refresh() forces that.
+#[pyclass(module = "wifi_backend")]
+struct WifiSession {
+    inner: lib_rust::Session,
+}
+
+#[pymethods]
+impl WifiSession {
+    #[new]
+    #[pyo3(signature = (backend=None, iface=None))]
+    fn new(py: Python<'_>, backend: Option<&str>, iface: Option<&str>) -> PyResult<Self> {
+        let b = match backend {
+            Some(name) => map_pyerr(Backend::from_name(name))?,
+            None => lib_rust::backend(),
+        };
+        let inner = map_pyerr(py.allow_threads(|| lib_rust::Session::open_on(b, iface)))?;
+        Ok(WifiSession { inner })
+    }
+
+    /// The backend's name.
+    #[getter]
+    fn backend(&self) -> &'static str {
+        self.inner.backend().name()
+    }
+
+    /// The interface name asked for; None for the default one.
+    #[getter]
+    fn iface(&self) -> Option<&str> {
+        self.inner.iface()
+    }
+
+    /// The interface index in use; None for the mock.
+    #[getter]
+    fn ifindex(&self) -> Option<u32> {
+        self.inner.ifindex()
+    }
+
+    /// Look the interface up again; True if it changed.
+    fn refresh(&self, py: Python<'_>) -> PyResult<bool> {
+        map_pyerr(py.allow_threads(|| self.inner.refresh()))
+    }
+
+    /// Same as the module's scan().
+    #[pyo3(signature = (cancel=None, options=None))]
+    fn scan(&self, py: Python<'_>, cancel: Option<CancelToken>, options: Option<ScanOptions>) -> PyResult<PyObject> {
+        let (options, cancel) = (options_of(options), cancel_of(cancel));
+        let rows = map_pyerr(py.allow_threads(|| self.inner.scan_with(&options, &cancel)))?;
+        entries_list(py, rows)
+    }
+
+    /// Same as the module's scan_dicts().
+    #[pyo3(signature = (details=false, fields=None, cancel=None, options=None))]
+    fn scan_dicts(
+        &self,
+        py: Python<'_>,
+        details: bool,
+        fields: Option<Vec<String>>,
+        cancel: Option<CancelToken>,
+        options: Option<ScanOptions>,
+    ) -> PyResult<PyObject> {
+        let fields = Fields::from_args(details, fields)?;
+        let (options, cancel) = (options_of(options), cancel_of(cancel));
+        let rows = map_pyerr(py.allow_threads(|| self.inner.scan_with(&options, &cancel)))?;
+        rows_list(py, &rows, fields)
+    }
+
+    /// Same as the module's compute_channels().
+    #[pyo3(signature = (band=None, detailed=false))]
+    fn compute_channels(&self, py: Python<'_>, band: Option<&str>, detailed: bool) -> PyResult<PyObject> {
+        let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+        let rows = map_pyerr(py.allow_threads(|| {
+            let rows = self.inner.scan(&cancel::Cancel::none())?;
+            if detailed {
+                chansurvey::refresh(self.inner.backend(), self.inner.ifindex());
+            }
+            Ok(rows)
+        }))?;
+        if !detailed {
+            return Ok(lib_rust::channel_counts(&rows, band).into_py_dict_bound(py).into_py(py));
+        }
+        channels_detailed(py, &rows, band)
+    }
+
+    /// Same as the module's compute_best_channel().
+    #[pyo3(signature = (candidates=None, config=None))]
+    fn compute_best_channel(
+        &self,
+        py: Python<'_>,
+        candidates: Option<Vec<Candidate>>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<(&'static str, u32)> {
+        let candidates = candidates_of(candidates)?;
+        let cfg = config_of(config);
+        map_pyerr(py.allow_threads(|| {
+            let (rows, connected) = best_channel_inputs(&self.inner, &cancel::Cancel::none())?;
+            lib_rust::best_channel_for(&rows, connected.as_ref(), candidates.as_deref(), &cfg)
+        }))
+        .map(pick_of)
+    }
+
+    /// Same as the module's compute_best_channel_plan().
+    #[pyo3(signature = (candidates=None, config=None))]
+    fn compute_best_channel_plan(
+        &self,
+        py: Python<'_>,
+        candidates: Option<Vec<Candidate>>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<(&'static str, u32, u32)> {
+        let candidates = candidates_of(candidates)?;
+        let cfg = config_of(config);
+        let (band, ch, width) = map_pyerr(py.allow_threads(|| {
+            let (rows, connected) = best_channel_inputs(&self.inner, &cancel::Cancel::none())?;
+            let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+            lib_rust::best_channel_plan(&rows, connected.as_ref(), candidates.as_deref(), &busy, &limits, &cfg)
+        }))?;
+        Ok((band_name(band), ch, width))
+    }
+
+    /// Same as the module's channel_scores().
+    #[pyo3(signature = (candidates=None, config=None))]
+    fn channel_scores(
+        &self,
+        py: Python<'_>,
+        candidates: Option<Vec<Candidate>>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<PyObject> {
+        let candidates = candidates_of(candidates)?;
+        let cfg = config_of(config);
+        let scores = map_pyerr(py.allow_threads(|| -> anyhow::Result<_> {
+            let (rows, connected) = best_channel_inputs(&self.inner, &cancel::Cancel::none())?;
+            let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+            lib_rust::channel_scores(&rows, connected.as_ref(), candidates.as_deref(), &busy, &limits, &cfg)
+        }))?;
+        scores_list(py, &scores)
+    }
+
+    /// Same as the module's interference_matrix().
+    #[pyo3(signature = (band=None, config=None))]
+    fn interference_matrix(
+        &self,
+        py: Python<'_>,
+        band: Option<&str>,
+        config: Option<PyChannelConfig>,
+    ) -> PyResult<PyObject> {
+        let band = band.map(|b| map_pyerr(band_from_name(b))).transpose()?;
+        let cfg = config_of(config);
+        map_pyerr(cfg.validate())?;
+        let matrices = map_pyerr(py.allow_threads(|| -> anyhow::Result<_> {
+            let (rows, connected) = best_channel_inputs(&self.inner, &cancel::Cancel::none())?;
+            let busy = chansurvey::recent_busy();
+            Ok(lib_rust::interference_matrix(&rows, connected.as_ref(), band, &busy, &cfg))
+        }))?;
+        matrix_list(py, &matrices)
+    }
+
+    /// Same as the module's connected_bssid().
+    fn connected_bssid(&self, py: Python<'_>) -> PyResult<Option<String>> {
+        let mac = map_pyerr(py.allow_threads(|| self.inner.connected_bssid()))?;
+        Ok(mac.as_ref().map(format_mac))
+    }
+
+    /// Same as the module's link_info().
+    fn link_info(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let link = map_pyerr(py.allow_threads(|| self.inner.link_info()))?;
+        link_dict(py, link)
+    }
+
+    /// Same as the module's survey().
+    fn survey(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let surveys = map_pyerr(py.allow_threads(|| self.inner.survey()))?;
+        surveys_list(py, surveys)
+    }
+
+    /// Same as the module's phy_capabilities(), for this interface's radio.
+    fn phy_capabilities(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let caps = map_pyerr(py.allow_threads(|| phycaps::query(self.inner.backend(), self.inner.ifindex())))?;
+        caps_list(py, &caps)
+    }
+
+    /// Same as the module's snapshot().
+    #[pyo3(signature = (cancel=None))]
+    fn snapshot(&self, py: Python<'_>, cancel: Option<CancelToken>) -> PyResult<ScanSnapshot> {
+        let cancel = cancel_of(cancel);
+        let inner = map_pyerr(py.allow_threads(|| self.inner.snapshot(&cancel)))?;
+        Ok(ScanSnapshot { inner })
+    }
+}
+
+// The session a module function's `iface=` asks for.
+fn session_on(py: Python<'_>, iface: &str) -> PyResult<WifiSession> {
+    WifiSession::new(py, None, Some(iface))
+}
+
+/// Python: list_interfaces() -> List[Dict]
+/// The Wi-Fi interfaces: {name, ifindex, mac, type, wiphy}, type being
+/// "station", "ap", "monitor", "p2p_go", ... Their names go to the
+/// `iface=` arguments and WifiSession(iface=...).
+#[pyfunction]
+fn list_interfaces(py: Python<'_>) -> PyResult<PyObject> {
+    let ifaces = map_pyerr(py.allow_threads(lib_rust::list_interfaces))?;
+    let out = PyList::empty_bound(py);
+    for i in ifaces {
+        let d = PyDict::new_bound(py);
+        d.set_item("name", &i.name)?;
+        d.set_item("ifindex", i.ifindex)?;
+        d.set_item("mac", i.mac.as_ref().map(format_mac))?;
+        d.set_item("type", netlink::iftype_name(i.iftype))?;
+        d.set_item("wiphy", i.wiphy)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: set_backend(name: str) -> None
+/// name is "neli-wifi" (read the kernel's BSS table), "raw-nl80211"
+/// (trigger a fresh scan first; needs CAP_NET_ADMIN), "mock" (serve the
+/// fixture from load_mock_fixture(), for tests without Wi-Fi hardware),
+/// "wpa-supplicant" (scan, the connected BSSID and link_info() through
+/// wpa_supplicant's control socket; $WPA_CTRL_DIR overrides where to look)
+/// or "android" (the same from what the app pushes with
+/// push_android_scan()). Without a call, when nl80211 can't be opened,
+/// "wpa-supplicant" is picked if a control socket is there and "android"
+/// on Android builds; otherwise "neli-wifi".
+#[pyfunction]
+fn set_backend(name: &str) -> PyResult<()> {
+    let b = map_pyerr(Backend::from_name(name))?;
+    if !b.built() {
+        let msg = format!("wifi_backend was built without the {name:?} backend");
+        return Err(exceptions::UnsupportedError::new_err(msg));
+    }
+    set_backend_internal(b);
+    Ok(())
+}
+
+/// Python: load_mock_fixture(path: str) -> int
+/// Load the JSON fixture the "mock" backend serves (format in mock.rs:
+/// scans, connected_bssid, radios, stations, surveys, regdomain, wiphy,
+/// delay_ms, faults), replacing the previous one and its call counts. Returns the number of scans.
+/// Select it with set_backend("mock").
+#[pyfunction]
+fn load_mock_fixture(path: &str) -> PyResult<usize> {
+    // Busy fractions are deltas; don't take them across fixtures.
+    chansurvey::clear();
+    regdom::clear();
+    phycaps::clear();
+    map_pyerr(mock::load(std::path::Path::new(path)))
+}
+
+/// Python: push_android_scan(results: List[Dict], connection: Dict | None = None) -> int
+/// Hand the "android" backend what WifiManager reports: results are
+/// ScanResults as dicts with their field names (BSSID, SSID, frequency,
+/// level, timestamp, capabilities, channelWidth, centerFreq0, and "ies" as
+/// hex when getInformationElements() is available), connection the
+/// WifiInfo (BSSID, rssi, linkSpeed, txLinkSpeedMbps, rxLinkSpeedMbps) or
+/// None when not connected. Replaces the previous push; later scans return
+/// these results until the next one. Returns the number of BSSs. JNI code
+/// can push the same as JSON through wifi_backend_android_push() (see
+/// android.rs).
+#[pyfunction]
+#[pyo3(signature = (results, connection=None))]
+fn push_android_scan(py: Python<'_>, results: &Bound<'_, PyAny>, connection: Option<&Bound<'_, PyAny>>) -> PyResult<usize> {
+    let doc = PyDict::new_bound(py);
+    doc.set_item("results", results)?;
+    doc.set_item("connection", connection)?;
+    let text: String = py.import_bound("json")?.call_method1("dumps", (doc,))?.extract()?;
+    map_pyerr(android::push_json(&text))
+}
+
+/// Python: mock_fault(op: str, error: str, call: int | None = None) -> None
+/// Make the mock backend fail `op` ("scan", "connected", "stations",
+/// "survey", "reg", "wiphy" or "link")
+/// with `error`: "ebusy", "enodev", "eperm", "timeout" or a message of
+/// its own. `call` picks one upcoming call (0 = the next); None fails
+/// every call from now on.
+#[pyfunction]
+#[pyo3(signature = (op, error, call=None))]
+fn mock_fault(op: &str, error: &str, call: Option<usize>) -> PyResult<()> {
+    let op = map_pyerr(mock::Op::from_name(op))?;
+    map_pyerr(mock::inject(op, call, error))
+}
+
+/// Python: get_backend() -> str
+#[pyfunction]
+fn get_backend() -> &'static str {
+    backend().name()
+}
+
+/// Python: set_p2p_policy(policy: str) -> None
+/// How Wi-Fi Direct groups (P2P element or "DIRECT-" SSID: cast targets,
+/// printers) count in compute_channels, compute_best_channel, history
+/// scoring and the mesh planners: "include" like any AP, "downweight"
+/// (the default) at a quarter of an AP's weight and left out of plain
+/// channel counts, or "exclude".
+#[pyfunction]
+fn set_p2p_policy(policy: &str) -> PyResult<()> {
+    lib_rust::set_p2p_policy(map_pyerr(P2pPolicy::from_name(policy))?);
+    Ok(())
+}
+
+/// Python: get_p2p_policy() -> str
+#[pyfunction]
+fn get_p2p_policy() -> &'static str {
+    lib_rust::p2p_policy().name()
+}
+
+/// Python: set_exclude_ibss(exclude: bool) -> None
+/// Leave ad-hoc (IBSS) networks out of compute_channels,
+/// compute_best_channel, history scoring and the mesh planners. They are
+/// counted by default; scan dicts say which rows they are ("ibss" field).
+#[pyfunction]
+fn set_exclude_ibss(exclude: bool) {
+    lib_rust::set_exclude_ibss(exclude);
+}
+
+/// Python: set_dry_run(enabled: bool) -> None
+/// Dry-run mode for the operations that reconfigure an interface or AP:
+/// while on, capture_beacons() checks its inputs and reports the interface
+/// type and frequency it would set without touching the interface, and
+/// pybackend's validate_channel_change() doesn't apply the channel. Off
+/// by default.
+#[pyfunction]
+fn set_dry_run(enabled: bool) {
+    lib_rust::set_dry_run(enabled);
+}
+
+/// Python: get_dry_run() -> bool
+#[pyfunction]
+fn get_dry_run() -> bool {
+    lib_rust::dry_run()
+}
+
+/// Python: set_device_grouping(rule: str = "middle_bytes",
+///                              vendors: Dict[str, str] | None = None,
+///                              devices: List[List[str]] | None = None,
+///                              callback: Callable[[str, str], bool | None]
+///                                  | None = None) -> None
+/// How BSSIDs are grouped into devices by the mesh planners, topology,
+/// survey, band steering and fingerprinting. `devices` lists known
+/// devices, each its BSSIDs ("my" nodes); a listed BSSID only groups with
+/// the others of its device. Otherwise BSSIDs of one Multiple BSSID set
+/// seen in a scan group, then callback(a, b) decides unless it returns
+/// None, then the rule for the vendor: `vendors` maps an OUI
+/// ("24:5a:4c") to a rule, `rule` covers the rest. Rules: "middle_bytes"
+/// (the middle four bytes match: Ubiquiti, the default), "last_nibble"
+/// (consecutive BSSIDs), "local_admin" (only the first byte differs) or
+/// "exact". An
+/// exception from the callback is reported as unraisable and the rules
+/// decide. Each call replaces the previous settings.
+#[pyfunction]
+#[pyo3(signature = (rule="middle_bytes", vendors=None, devices=None, callback=None))]
+fn set_device_grouping(
+    rule: &str,
+    vendors: Option<HashMap<String, String>>,
+    devices: Option<Vec<Vec<String>>>,
+    callback: Option<PyObject>,
+) -> PyResult<()> {
+    let mut g = devgroup::Grouping {
+        rule: map_pyerr(devgroup::Rule::from_name(rule))?,
+        ..Default::default()
+    };
+    for (oui, name) in vendors.unwrap_or_default() {
+        let mac = map_pyerr(parse_mac(&format!("{oui}:00:00:00")))?;
+        g.vendors.push(([mac[0], mac[1], mac[2]], map_pyerr(devgroup::Rule::from_name(&name))?));
+    }
+    g.devices = devices.unwrap_or_default().iter().map(|d| parse_macs(d)).collect::<PyResult<_>>()?;
+    g.callback = callback.map(|cb| -> devgroup::SameDeviceFn {
+        Arc::new(move |a, b| {
+            Python::with_gil(|py| {
+                match cb.call1(py, (format_mac(a), format_mac(b))).and_then(|v| v.extract::<Option<bool>>(py)) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        e.write_unraisable_bound(py, Some(cb.bind(py)));
+                        None
+                    }
+                }
+            })
+        })
+    });
+    devgroup::set(g);
+    Ok(())
+}
+
+/// Python: device_grouping() -> Dict
+/// {rule, vendors: {oui: rule}, devices: [[bssid]], callback: bool,
+///  mbssid: [{transmitter, size, bssids}], pairs: [{bssids, evidence}]}:
+/// the settings of set_device_grouping(), the Multiple BSSID sets learned
+/// from scans (bssids are the nontransmitted ones the beacons listed) and
+/// the device pairs scans showed (see device_groups()).
+#[pyfunction]
+fn device_grouping(py: Python<'_>) -> PyResult<PyObject> {
+    let g = devgroup::current();
+    let d = PyDict::new_bound(py);
+    d.set_item("rule", g.rule.name())?;
+    let vendors = PyDict::new_bound(py);
+    for (oui, rule) in &g.vendors {
+        vendors.set_item(format!("{:02x}:{:02x}:{:02x}", oui[0], oui[1], oui[2]), rule.name())?;
+    }
+    d.set_item("vendors", vendors)?;
+    let devices: Vec<Vec<String>> = g.devices.iter().map(|m| m.iter().map(format_mac).collect()).collect();
+    d.set_item("devices", devices)?;
+    d.set_item("callback", g.callback.is_some())?;
+    let sets = PyList::empty_bound(py);
+    for (tx, size, bssids) in devgroup::mbssid_sets() {
+        let s = PyDict::new_bound(py);
+        s.set_item("transmitter", format_mac(&tx))?;
+        s.set_item("size", size)?;
+        s.set_item("bssids", bssids.iter().map(format_mac).collect::<Vec<_>>())?;
+        sets.append(s)?;
+    }
+    d.set_item("mbssid", sets)?;
+    let pairs = PyList::empty_bound(py);
+    for (a, b, why) in devgroup::pairs() {
+        let p = PyDict::new_bound(py);
+        p.set_item("bssids", [format_mac(&a), format_mac(&b)])?;
+        p.set_item("evidence", why)?;
+        pairs.append(p)?;
+    }
+    d.set_item("pairs", pairs)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: same_device(a: str, b: str) -> bool
+/// Whether two BSSIDs count as radios of one device (see
+/// set_device_grouping()).
+#[pyfunction]
+fn same_device(py: Python<'_>, a: &str, b: &str) -> PyResult<bool> {
+    let (a, b) = (map_pyerr(parse_mac(a))?, map_pyerr(parse_mac(b))?);
+    // The callback takes the GIL itself.
+    Ok(py.allow_threads(|| lib_rust::same_device(&a, &b)))
+}
+
+/// Python: set_log_level(level: int | str) -> None
+/// Log what the Rust side does at `level` and above (a logging level or
+/// its name; also "TRACE", 5) to the "wifi_backend" logger, whose level is
+/// set to match: DEBUG shows every scan trigger, retry, netlink error reply
+/// and unparsable reply. WARNING by default. Records come from a thread of
+/// their own, shortly after the call that logged them; the loggers are
+/// named after the Rust module ("wifi_backend.nl_raw") and each record's
+/// `fields` attribute is a dict of its values.
+#[pyfunction]
+fn set_log_level(py: Python<'_>, level: &Bound<'_, PyAny>) -> PyResult<()> {
+    let level = match level.extract::<u32>() {
+        Ok(l) => l,
+        Err(_) => map_pyerr(logs::level_from_name(&level.extract::<String>()?))?,
+    };
+    logs::set_level(level);
+    let logger = py.import_bound("logging")?.call_method1("getLogger", ("wifi_backend",))?;
+    logger.call_method1("setLevel", (level,))?;
+    Ok(())
+}
+
+/// Python: get_log_level() -> int
+#[pyfunction]
+fn get_log_level() -> u32 {
+    logs::level()
+}
+
+// Hand the Records of logs.rs to Python's logging, on a thread that may
+// wait for the GIL without holding up a scan.
+fn forward_logs(records: mpsc::Receiver<logs::Record>) {
+    // Without the thread records pile up and are dropped; scans go on.
+    let _ = std::thread::Builder::new().name("wifi-log".into()).spawn(move || {
+        for r in records {
+            // SAFETY: only reads whether the interpreter is running.
+            if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+                break;
+            }
+            // A broken handler is Python's to report, not ours.
+            let _ = Python::with_gil(|py| log_record(py, &r));
+        }
+    });
+}
+
+fn log_record(py: Python<'_>, r: &logs::Record) -> PyResult<()> {
+    let fields = PyDict::new_bound(py);
+    for (k, v) in &r.fields {
+        let v = match v {
+            logs::Value::Str(s) => s.into_py(py),
+            logs::Value::Int(i) => i.into_py(py),
+            logs::Value::UInt(u) => u.into_py(py),
+            logs::Value::Float(f) => f.into_py(py),
+            logs::Value::Bool(b) => b.into_py(py),
+        };
+        fields.set_item(*k, v)?;
+    }
+    let extra = PyDict::new_bound(py);
+    extra.set_item("fields", fields)?;
+    let logger = py.import_bound("logging")?.call_method1("getLogger", (&r.logger,))?;
+    // "%s" so a '%' in the message isn't taken for a format.
+    logger.call_method("log", (r.level, "%s", &r.message), Some(&[("extra", extra)].into_py_dict_bound(py)))?;
+    Ok(())
+}
+
+/// Python: score_history(window: int | None = None,
+///                       progress: Callable[[float, str, str], None] | None = None,
+///                       cancel: CancelToken | None = None) -> Dict
+/// Runs the best-channel pipeline over the last `window` recorded scans
+/// (all of them by default) without holding the GIL. Returns:
+///   {scans, first_at, last_at, best_channel: (band, channel) | None,
+///    recommendations: {band: {ch: times_picked}},
+///    mean_weight: {band: {ch: weight}}}
+/// `progress(percent, stage, message)` is called every few hundred scans.
+#[pyfunction]
+#[pyo3(signature = (window=None, progress=None, cancel=None))]
+fn score_history(
+    py: Python<'_>,
+    window: Option<usize>,
+    progress: Option<PyObject>,
+    cancel: Option<CancelToken>,
+) -> PyResult<PyObject> {
+    let mut progress = py_progress(progress).with_cancel(cancel_of(cancel));
+    let score = map_pyerr(py.allow_threads(|| history::score_history(window, &mut progress)))?;
+
+    let d = PyDict::new_bound(py);
+    d.set_item("scans", score.scans)?;
+    d.set_item("first_at", score.first_at)?;
+    d.set_item("last_at", score.last_at)?;
+    d.set_item("best_channel", score.best_channel.map(pick_of))?;
+    d.set_item("recommendations", per_band(py, score.recommendations)?)?;
+    d.set_item("mean_weight", per_band(py, score.mean_weight)?)?;
+    Ok(d.into_py(py))
+}
+
+// {band name: {channel: value}} from values keyed by (freq_band(), channel).
+fn per_band<V: ToPyObject>(py: Python<'_>, values: HashMap<(u8, u32), V>) -> PyResult<Bound<'_, PyDict>> {
+    let by_band = PyDict::new_bound(py);
+    for ((band, ch), v) in values {
+        let name = band_name(band);
+        let inner = match by_band.get_item(name)? {
+            Some(inner) => inner.downcast_into::<PyDict>()?,
+            None => {
+                let inner = PyDict::new_bound(py);
+                by_band.set_item(name, &inner)?;
+                inner
+            }
+        };
+        inner.set_item(ch, v)?;
+    }
+    Ok(by_band)
+}
+
+fn parse_macs(list: &[String]) -> PyResult<Vec<[u8; 6]>> {
+    list.iter().map(|s| map_pyerr(parse_mac(s))).collect()
+}
+
+/// Python: mesh_node_rssi(nodes: List[List[Dict]], node_bssids: List[List[str]])
+///     -> List[List[float | None]]
+/// rssi[i][j]: strongest beacon of node j (any of its BSSIDs) heard in the
+/// scan taken at node i, made symmetric; None if never heard.
+#[pyfunction]
+fn mesh_node_rssi(nodes: Vec<Bound<'_, PyList>>, node_bssids: Vec<Vec<String>>) -> PyResult<Vec<Vec<Option<f32>>>> {
+    let rows = nodes.iter().map(rows_from_list).collect::<PyResult<Vec<_>>>()?;
+    let radios = node_bssids.iter().map(|b| parse_macs(b)).collect::<PyResult<Vec<_>>>()?;
+    Ok(plan::node_rssi(&rows, &radios))
+}
+
+/// Python: assign_mesh_channels_24(nodes: List[List[Dict]],
+///                                  own_bssids: List[str] = [],
+///                                  node_bssids: List[List[str]] | None = None,
+///                                  cancel: CancelToken | None = None) -> List[int]
+/// One 2.4 GHz channel (1/6/11) per mesh node, given the scan dicts taken
+/// at each node's location. `own_bssids` (and their sibling radios) aren't
+/// counted as interference.
+///
+/// Without `node_bssids` up to three nodes always get distinct channels.
+/// With it (each node's own BSSIDs), nodes that hear each other below
+/// -82 dBm are allowed to reuse a channel.
+#[pyfunction]
+#[pyo3(signature = (nodes, own_bssids=Vec::new(), node_bssids=None, cancel=None))]
+fn assign_mesh_channels_24(
+    py: Python<'_>,
+    nodes: Vec<Bound<'_, PyList>>,
+    own_bssids: Vec<String>,
+    node_bssids: Option<Vec<Vec<String>>>,
+    cancel: Option<CancelToken>,
+) -> PyResult<Vec<u32>> {
+    let MeshInputs { rows, own, coupling } = mesh_inputs(&nodes, &own_bssids, node_bssids)?;
+    let per_node: Vec<_> = rows.iter().map(|r| plan::interference_24(r, &own)).collect();
+    let cancel = cancel_of(cancel);
+    map_pyerr(py.allow_threads(|| plan::assign_24ghz(&per_node, coupling.as_deref(), &cancel)))
+}
+
+/// Python: assign_mesh_channels_5(nodes: List[List[Dict]],
+///                                 own_bssids: List[str] = [],
+///                                 node_bssids: List[List[str]] | None = None,
+///                                 follow: List[int | None] | None = None,
+///                                 dfs: bool = False,
+///                                 cancel: CancelToken | None = None) -> List[int]
+/// One 5 GHz 80 MHz block (by primary channel: 36 / 149, plus 52 / 100 /
+/// 116 / 132 with `dfs`) per node, like assign_mesh_channels_24.
+/// `follow[i]` is the index of the node that node i backhauls to over
+/// 5 GHz (None when wired): those two must share a channel, so wired
+/// nodes are the ones free to spread out.
+#[pyfunction]
+#[pyo3(signature = (nodes, own_bssids=Vec::new(), node_bssids=None, follow=None, dfs=false, cancel=None))]
+fn assign_mesh_channels_5(
+    py: Python<'_>,
+    nodes: Vec<Bound<'_, PyList>>,
+    own_bssids: Vec<String>,
+    node_bssids: Option<Vec<Vec<String>>>,
+    follow: Option<Vec<Option<usize>>>,
+    dfs: bool,
+    cancel: Option<CancelToken>,
+) -> PyResult<Vec<u32>> {
+    let mut channels = plan::CHANNELS_5_NON_DFS.to_vec();
+    if dfs {
+        channels.extend(plan::CHANNELS_5_DFS);
+        channels.sort_unstable();
+    }
+    let MeshInputs { rows, own, coupling } = mesh_inputs(&nodes, &own_bssids, node_bssids)?;
+    let follow = follow.unwrap_or_else(|| vec![None; rows.len()]);
+    let per_node: Vec<_> = rows.iter().map(|r| plan::interference_5(r, &own, &channels)).collect();
+    let cancel = cancel_of(cancel);
+    map_pyerr(py.allow_threads(|| {
+        plan::assign_5ghz(&per_node, &channels, coupling.as_deref(), &follow, &cancel)
+    }))
+}
+
+/// Python: assign_mesh_channels_6(nodes: List[List[Dict]],
+///                                 own_bssids: List[str] = [],
+///                                 node_bssids: List[List[str]] | None = None,
+///                                 follow: List[int | None] | None = None,
+///                                 cancel: CancelToken | None = None) -> List[int]
+/// assign_mesh_channels_5 on 6 GHz: one 80 MHz block per node, by its
+/// Preferred Scanning Channel (5, 21, 37, ... 213), which is also the
+/// primary channel to set so clients that only probe PSCs find the node.
+#[pyfunction]
+#[pyo3(signature = (nodes, own_bssids=Vec::new(), node_bssids=None, follow=None, cancel=None))]
+fn assign_mesh_channels_6(
+    py: Python<'_>,
+    nodes: Vec<Bound<'_, PyList>>,
+    own_bssids: Vec<String>,
+    node_bssids: Option<Vec<Vec<String>>>,
+    follow: Option<Vec<Option<usize>>>,
+    cancel: Option<CancelToken>,
+) -> PyResult<Vec<u32>> {
+    let channels = plan::CHANNELS_6_PSC_80;
+    let MeshInputs { rows, own, coupling } = mesh_inputs(&nodes, &own_bssids, node_bssids)?;
+    let follow = follow.unwrap_or_else(|| vec![None; rows.len()]);
+    let per_node: Vec<_> = rows.iter().map(|r| plan::interference_6(r, &own, &channels)).collect();
+    let cancel = cancel_of(cancel);
+    map_pyerr(py.allow_threads(|| {
+        plan::assign_5ghz(&per_node, &channels, coupling.as_deref(), &follow, &cancel)
+    }))
+}
+
+/// Python: plan_mesh_channels(nodes: List[List[Dict] | ScanSnapshot],
+///                             own_bssids: List[str] = [],
+///                             node_bssids: List[List[str]] | None = None,
+///                             bands: List[str] | None = None,
+///                             follow: Dict[str, List[int | None]] | None = None,
+///                             dfs: bool = False,
+///                             cancel: CancelToken | None = None) -> List[Dict]
+/// A channel and width for every mesh node on every band, planned jointly
+/// so the nodes avoid each other as well as the neighbours. `nodes` is
+/// the scan taken at each node: scan dicts, BssEntry objects or a
+/// ScanSnapshot (e.g. ScanSnapshot.from_json() of a node's export).
+/// Returns per node {band: {channel, width_mhz, cost}} for each of
+/// `bands` ("2.4GHz", "5GHz", "6GHz"; all three by default). cost is the
+/// interference the node sees on the block plus its own load, per 20 MHz
+/// of width: a wider channel wins while its extra 20 MHz channels are
+/// clean. 2.4 GHz stays on 1/6/11 at 20 MHz; 5 GHz picks from 20 to 160
+/// MHz (the DFS channels only with `dfs`), 6 GHz from 20 to 160 MHz
+/// around the Preferred Scanning Channels. Channels the regulatory
+/// domain rules out are skipped. `own_bssids` and `node_bssids` as for
+/// assign_mesh_channels_24(): with node_bssids, nodes that don't hear
+/// each other above -82 dBm may share. `follow` per band name as for
+/// assign_mesh_channels_5().
+#[pyfunction]
+#[pyo3(signature = (nodes, own_bssids=Vec::new(), node_bssids=None, bands=None, follow=None, dfs=false, cancel=None))]
+#[allow(clippy::too_many_arguments)]
+fn plan_mesh_channels(
+    py: Python<'_>,
+    nodes: Vec<Bound<'_, PyAny>>,
+    own_bssids: Vec<String>,
+    node_bssids: Option<Vec<Vec<String>>>,
+    bands: Option<Vec<String>>,
+    follow: Option<HashMap<String, Vec<Option<usize>>>>,
+    dfs: bool,
+    cancel: Option<CancelToken>,
+) -> PyResult<PyObject> {
+    let rows = nodes
+        .iter()
+        .map(|node| match node.downcast::<ScanSnapshot>() {
+            Ok(snap) => Ok(snap.borrow().inner.rows.to_vec()),
+            Err(_) => rows_from_list(node.downcast::<PyList>()?),
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let MeshInputs { rows, own, coupling } = mesh_inputs_of(rows, &own_bssids, node_bssids)?;
+    let bands = match bands {
+        Some(names) => names.iter().map(|b| map_pyerr(band_from_name(b))).collect::<PyResult<Vec<_>>>()?,
+        None => vec![1, 2, 4],
+    };
+    let mut follow_by_band: HashMap<u8, Vec<Option<usize>>> = HashMap::new();
+    for (name, f) in follow.unwrap_or_default() {
+        follow_by_band.insert(map_pyerr(band_from_name(&name))?, f);
+    }
+    let cancel = cancel_of(cancel);
+    let plans = map_pyerr(py.allow_threads(|| {
+        regdom::refresh(backend());
+        let limits = ChannelLimits {
+            reg: regdom::current(),
+            radio: None,
+        };
+        let options: Vec<_> = bands.iter().map(|&b| (b, plan::channel_options(b, dfs, &limits))).collect();
+        plan::plan_mesh(&rows, &own, coupling.as_deref(), &options, &follow_by_band, &cancel)
+    }))?;
+
+    let out = PyList::empty_bound(py);
+    for node in plans {
+        let d = PyDict::new_bound(py);
+        for p in node {
+            let pick = PyDict::new_bound(py);
+            pick.set_item("channel", p.channel)?;
+            pick.set_item("width_mhz", p.width_mhz)?;
+            pick.set_item("cost", p.cost)?;
+            d.set_item(band_name(p.band), pick)?;
+        }
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+// What the mesh planners start from: each node's scan, the BSSIDs that
+// aren't interference (`own_bssids` plus every node's radios) and, with
+// `node_bssids`, how well the nodes hear each other.
+struct MeshInputs {
+    rows: Vec<Vec<BssRow>>,
+    own: Vec<[u8; 6]>,
+    coupling: Option<Vec<Vec<Option<f32>>>>,
+}
+
+fn mesh_inputs(
+    nodes: &[Bound<'_, PyList>],
+    own_bssids: &[String],
+    node_bssids: Option<Vec<Vec<String>>>,
+) -> PyResult<MeshInputs> {
+    let rows = nodes.iter().map(rows_from_list).collect::<PyResult<Vec<_>>>()?;
+    mesh_inputs_of(rows, own_bssids, node_bssids)
+}
+
+fn mesh_inputs_of(
+    rows: Vec<Vec<BssRow>>,
+    own_bssids: &[String],
+    node_bssids: Option<Vec<Vec<String>>>,
+) -> PyResult<MeshInputs> {
+    let mut own = parse_macs(own_bssids)?;
+
+    let coupling = match node_bssids {
+        Some(nb) => {
+            let radios = nb.iter().map(|b| parse_macs(b)).collect::<PyResult<Vec<_>>>()?;
+            own.extend(radios.iter().flatten());
+            Some(plan::node_rssi(&rows, &radios))
+        }
+        None => None,
+    };
+    Ok(MeshInputs { rows, own, coupling })
+}
+
+/// Python: neighbor_mesh_systems(scan: List[Dict] | None = None,
+///                                own_bssids: List[str] = []) -> List[Dict]
+/// Neighbouring whole-home mesh systems, strongest first. Each dict:
+/// {system, ssids, nodes: [[{bssid, ssid, freq_mhz, channel, signal_dbm}]],
+/// evidence, strongest_dbm}. `system` is one of eero / orbi / deco / unifi /
+/// google_wifi / easymesh / unknown.
+///
+/// Without `scan`, runs a fresh scan, which also has the vendor IEs to go
+/// on; saved scan dicts only carry OUI and BSSID-pattern evidence. The
+/// connected AP's device is always skipped, as are `own_bssids`.
+#[pyfunction]
+#[pyo3(signature = (scan=None, own_bssids=Vec::new()))]
+fn neighbor_mesh_systems(
+    py: Python<'_>,
+    scan: Option<Bound<'_, PyList>>,
+    own_bssids: Vec<String>,
+) -> PyResult<PyObject> {
+    let rows = match scan {
+        Some(list) => rows_from_list(&list)?,
+        None => map_pyerr(py.allow_threads(scan_all_bss))?,
+    };
+    let mut own = parse_macs(&own_bssids)?;
+    if let Ok(Some(c)) = py.allow_threads(get_connected_bssid) {
+        own.push(c);
+    }
+
+    let out = PyList::empty_bound(py);
+    for sys in fingerprint::neighbor_systems(&rows, &own) {
+        let nodes = PyList::empty_bound(py);
+        for dev in &sys.devices {
+            let radios = PyList::empty_bound(py);
+            for r in dev {
+                let d = PyDict::new_bound(py);
+                d.set_item("bssid", format_mac(&r.bssid))?;
+                d.set_item("ssid", r.ssid.as_deref())?;
+                d.set_item("freq_mhz", r.freq_mhz)?;
+                d.set_item("channel", r.channel)?;
+                d.set_item("signal_dbm", r.signal_dbm)?;
+                radios.append(d)?;
+            }
+            nodes.append(radios)?;
+        }
+        let d = PyDict::new_bound(py);
+        d.set_item("system", sys.kind.name())?;
+        d.set_item("ssids", sys.ssids)?;
+        d.set_item("nodes", nodes)?;
+        d.set_item("evidence", sys.evidence)?;
+        d.set_item("strongest_dbm", sys.strongest_dbm)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: band_steering(stations: List[Dict] | None = None,
+///                        radios: List[Dict] | None = None,
+///                        include_unknown: bool = False) -> List[Dict]
+/// Suggest moving dual-band clients off 2.4 GHz onto a 5/6 GHz radio of
+/// the same AP node they'd hear at >= -70 dBm (estimated), best first.
+/// Each dict: {station, from_bssid, from_freq_mhz, to_bssid, to_freq_mhz,
+/// signal_dbm, expected_dbm, dual_band, at, mono}, the last two being when
+/// the station figures were read (unix and time.monotonic() seconds).
+///
+/// `stations` ({mac, bssid, freq_mhz, signal_dbm, dual_band?, at?}, e.g. from a
+/// UniFi controller) default to the clients of this machine's AP-mode
+/// interfaces. `radios` ({bssid, freq_mhz}) default to those interfaces,
+/// or to the radios the given stations are on.
+#[pyfunction]
+#[pyo3(signature = (stations=None, radios=None, include_unknown=false))]
+fn band_steering(
+    py: Python<'_>,
+    stations: Option<Vec<Bound<'_, PyDict>>>,
+    radios: Option<Vec<Bound<'_, PyDict>>>,
+    include_unknown: bool,
+) -> PyResult<PyObject> {
+    let (local_radios, stations) = match stations {
+        Some(list) => {
+            let st = list.iter().map(station_from_dict).collect::<PyResult<Vec<_>>>()?;
+            let mut seen: Vec<stations::ApRadio> = Vec::new();
+            for s in &st {
+                if let Some(f) = s.freq_mhz {
+                    if !seen.iter().any(|r| r.bssid == s.bssid) {
+                        seen.push(stations::ApRadio { bssid: s.bssid, freq_mhz: f });
+                    }
+                }
+            }
+            (seen, st)
+        }
+        None => map_pyerr(py.allow_threads(stations::local_stations))?,
+    };
+    let radios = match radios {
+        Some(list) => list
+            .iter()
+            .map(|d| {
+                let bssid: String = required(d, "bssid")?;
+                Ok(stations::ApRadio {
+                    bssid: map_pyerr(parse_mac(&bssid))?,
+                    freq_mhz: required(d, "freq_mhz")?,
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?,
+        None => local_radios,
+    };
+
+    let out = PyList::empty_bound(py);
+    for s in steer::suggest(&stations, &radios, include_unknown) {
+        let d = PyDict::new_bound(py);
+        d.set_item("station", format_mac(&s.station))?;
+        d.set_item("from_bssid", format_mac(&s.from_bssid))?;
+        d.set_item("from_freq_mhz", s.from_freq_mhz)?;
+        d.set_item("to_bssid", format_mac(&s.to_bssid))?;
+        d.set_item("to_freq_mhz", s.to_freq_mhz)?;
+        d.set_item("signal_dbm", s.signal_dbm)?;
+        d.set_item("expected_dbm", s.expected_dbm)?;
+        d.set_item("dual_band", s.dual_band)?;
+        set_stamp(&d, "at", "mono", s.at)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+fn required<'py, T: FromPyObject<'py>>(d: &Bound<'py, PyDict>, key: &str) -> PyResult<T> {
+    d.get_item(key)?
+        .ok_or_else(|| PyRuntimeError::new_err(format!("missing key {key:?}")))?
+        .extract()
+}
+
+fn station_from_dict(d: &Bound<'_, PyDict>) -> PyResult<stations::Station> {
+    let mac: String = required(d, "mac")?;
+    let bssid: String = required(d, "bssid")?;
+    let opt = |key: &str| -> PyResult<Option<Bound<'_, PyAny>>> { d.get_item(key) };
+    Ok(stations::Station {
+        mac: map_pyerr(parse_mac(&mac))?,
+        bssid: map_pyerr(parse_mac(&bssid))?,
+        freq_mhz: opt("freq_mhz")?.map(|v| v.extract()).transpose()?,
+        signal_dbm: opt("signal_dbm")?.map(|v| v.extract()).transpose()?,
+        dual_band: opt("dual_band")?.map(|v| v.extract()).transpose()?,
+        mcs: opt("mcs")?.map(|v| v.extract()).transpose()?,
+        tx_packets: opt("tx_packets")?.map(|v| v.extract()).transpose()?,
+        tx_retries: opt("tx_retries")?.map(|v| v.extract()).transpose()?,
+        at: stamp_from_dict(d, "at", "mono")?,
+        ..Default::default()
+    })
+}
+
+/// Python: record_backhaul_sample(from_node: str, to_node: str,
+///                                 rssi_dbm: float | None = None,
+///                                 mcs: int | None = None,
+///                                 tx_packets: int | None = None,
+///                                 tx_retries: int | None = None,
+///                                 freq_mhz: int | None = None,
+///                                 at: float | None = None) -> None
+/// Add one backhaul link sample collected elsewhere (SSH, controller).
+/// tx_packets / tx_retries are the station's cumulative counters; `at`
+/// defaults to now (Unix seconds).
+#[pyfunction]
+#[pyo3(signature = (from_node, to_node, rssi_dbm=None, mcs=None, tx_packets=None, tx_retries=None, freq_mhz=None, at=None))]
+#[allow(clippy::too_many_arguments)]
+fn record_backhaul_sample(
+    from_node: &str,
+    to_node: &str,
+    rssi_dbm: Option<f32>,
+    mcs: Option<u8>,
+    tx_packets: Option<u64>,
+    tx_retries: Option<u64>,
+    freq_mhz: Option<u32>,
+    at: Option<f64>,
+) {
+    backhaul::record(
+        from_node,
+        to_node,
+        backhaul::LinkSample {
+            at: at.unwrap_or_else(backhaul::now),
+            rssi_dbm,
+            mcs,
+            tx_packets,
+            tx_retries,
+            freq_mhz,
+        },
+    );
+}
+
+/// Python: sample_backhaul(peers: List[str]) -> int
+/// Sample the links from this machine's AP interfaces to the given peer
+/// node MACs. Returns the number of links found.
+#[pyfunction]
+fn sample_backhaul(py: Python<'_>, peers: Vec<String>) -> PyResult<usize> {
+    let peers = parse_macs(&peers)?;
+    map_pyerr(py.allow_threads(|| backhaul::sample_local(&peers)))
+}
+
+/// Python: backhaul_health() -> List[Dict]
+/// Per link, degraded first: {from, to, status ("ok" / "degraded"),
+/// samples, last_at, rssi_now, rssi_base, mcs_now, mcs_base,
+/// retry_now, retry_base, alerts: [str]}. *_base is the link's own
+/// history, *_now its last few samples.
+#[pyfunction]
+fn backhaul_health(py: Python<'_>) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for h in backhaul::health() {
+        let d = PyDict::new_bound(py);
+        d.set_item("from", &h.from)?;
+        d.set_item("to", &h.to)?;
+        d.set_item("status", h.status())?;
+        d.set_item("samples", h.samples)?;
+        d.set_item("last_at", h.last_at)?;
+        d.set_item("rssi_now", h.rssi_now)?;
+        d.set_item("rssi_base", h.rssi_base)?;
+        d.set_item("mcs_now", h.mcs_now)?;
+        d.set_item("mcs_base", h.mcs_base)?;
+        d.set_item("retry_now", h.retry_now)?;
+        d.set_item("retry_base", h.retry_base)?;
+        d.set_item("alerts", h.alerts)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: classify_backhaul(nodes: List[str], gateway: str | None = None,
+///                            overrides: Dict[str, str] = {},
+///                            max_age: float = 300.0) -> List[Dict]
+/// Wired or wireless backhaul per node, in `nodes` order: {node, uplink
+/// ("wired" / "wireless" / "unknown"), parent, rssi_dbm, freq_mhz, source}.
+/// `overrides` ({node: "wired" | "wireless"}, e.g. from a controller or
+/// the bridge table) win; otherwise nodes with backhaul samples in the
+/// last `max_age` seconds are wireless and the rest wired.
+#[pyfunction]
+#[pyo3(signature = (nodes, gateway=None, overrides=std::collections::HashMap::new(), max_age=300.0))]
+fn classify_backhaul(
+    py: Python<'_>,
+    nodes: Vec<String>,
+    gateway: Option<String>,
+    overrides: std::collections::HashMap<String, String>,
+    max_age: f64,
+) -> PyResult<PyObject> {
+    let overrides = overrides
+        .into_iter()
+        .map(|(n, u)| Ok((n, map_pyerr(backhaul::Uplink::from_name(&u))?)))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let out = PyList::empty_bound(py);
+    for b in backhaul::classify(&nodes, gateway.as_deref(), &overrides, max_age) {
+        let d = PyDict::new_bound(py);
+        d.set_item("node", b.node)?;
+        d.set_item("uplink", b.uplink.name())?;
+        d.set_item("parent", b.parent)?;
+        d.set_item("rssi_dbm", b.rssi_dbm)?;
+        d.set_item("freq_mhz", b.freq_mhz)?;
+        d.set_item("source", b.source)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: clear_backhaul() -> None
+#[pyfunction]
+fn clear_backhaul() {
+    backhaul::clear()
+}
+
+/// Python: airtime_report(window: float = 5.0, pcap: str | None = None,
+///                         own_bssids: List[str] = [],
+///                         progress: Callable[[float, str, str], None] | None = None,
+///                         cancel: CancelToken | None = None) -> List[Dict]
+/// How airtime on each own AP radio is split across its clients, busiest
+/// radio first: {bssid, freq_mhz, source ("durations" / "bitrate" /
+/// "capture"), window_s, at, mono, airtime_us, overhead_us, busy, clients:
+/// [{mac, airtime_us, bytes, share, rate_mbps, hog, slow}], alerts: [str]};
+/// at / mono are the end of the window.
+///
+/// Local AP interfaces are sampled twice `window` seconds apart (0 skips
+/// this); `pcap` adds a radiotap monitor capture for radios whose driver
+/// doesn't account airtime. `own_bssids` limits the capture to those BSSs.
+/// `progress(percent, stage, message)` is called about once a second while
+/// sampling.
+#[pyfunction]
+#[pyo3(signature = (window=5.0, pcap=None, own_bssids=Vec::new(), progress=None, cancel=None))]
+fn airtime_report(
+    py: Python<'_>,
+    window: f64,
+    pcap: Option<String>,
+    own_bssids: Vec<String>,
+    progress: Option<PyObject>,
+    cancel: Option<CancelToken>,
+) -> PyResult<PyObject> {
+    let own = parse_macs(&own_bssids)?;
+    let mut progress = py_progress(progress).with_cancel(cancel_of(cancel));
+    let reports = py.allow_threads(|| -> anyhow::Result<_> {
+        let local = if window > 0.0 {
+            let window = std::time::Duration::from_secs_f64(window);
+            match airtime::sample_local(window, &mut progress) {
+                Ok(r) => r,
+                // No AP interfaces here is fine when a capture covers them.
+                Err(e) if pcap.is_some() && !e.is::<PyErr>() && !e.is::<cancel::Cancelled>() => {
+                    Vec::new()
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            Vec::new()
+        };
+        let captured = match &pcap {
+            Some(p) => airtime::from_capture(std::path::Path::new(p), &own)?,
+            None => Vec::new(),
+        };
+        Ok(airtime::merge(local, captured))
+    });
+
+    let out = PyList::empty_bound(py);
+    for ap in map_pyerr(reports)? {
+        let clients = PyList::empty_bound(py);
+        for c in &ap.clients {
+            let d = PyDict::new_bound(py);
+            d.set_item("mac", format_mac(&c.mac))?;
+            d.set_item("airtime_us", c.airtime_us)?;
+            d.set_item("bytes", c.bytes)?;
+            d.set_item("share", c.share)?;
+            d.set_item("rate_mbps", c.rate_mbps)?;
+            d.set_item("hog", c.hog)?;
+            d.set_item("slow", c.slow)?;
+            clients.append(d)?;
+        }
+        let d = PyDict::new_bound(py);
+        d.set_item("bssid", format_mac(&ap.bssid))?;
+        d.set_item("freq_mhz", ap.freq_mhz)?;
+        d.set_item("source", ap.source)?;
+        d.set_item("window_s", ap.window_s)?;
+        set_stamp(&d, "at", "mono", Some(ap.at))?;
+        d.set_item("airtime_us", ap.airtime_us)?;
+        d.set_item("overhead_us", ap.overhead_us)?;
+        d.set_item("busy", ap.busy)?;
+        d.set_item("clients", clients)?;
+        d.set_item("alerts", ap.alerts)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: capture_beacons(iface: str, path: str, seconds: float = 10.0,
+///                          freq: int | None = None,
+///                          cancel: CancelToken | None = None) -> Dict
+/// Put `iface` in monitor mode and write the beacons and probe responses it
+/// hears for `seconds` to `path` as a radiotap pcap, for Wireshark or
+/// airtime_report(pcap=...): {dry_run: False, frames, beacons,
+/// probe_responses, skipped}. `freq` (MHz) parks the radio on one channel.
+/// The interface drops off its network meanwhile and gets its old mode
+/// back afterwards. Needs CAP_NET_ADMIN and CAP_NET_RAW, and a build with
+/// the capture feature (UnsupportedError otherwise). In dry-run mode
+/// (set_dry_run()) the interface, `freq` and `path`'s directory are
+/// checked and nothing is changed: {dry_run: True, iface, ifindex, iftype,
+/// to_iftype: "monitor", freq, path}.
+#[pyfunction]
+#[pyo3(signature = (iface, path, seconds=10.0, freq=None, cancel=None))]
+fn capture_beacons(
+    py: Python<'_>,
+    iface: String,
+    path: String,
+    seconds: f64,
+    freq: Option<u32>,
+    cancel: Option<CancelToken>,
+) -> PyResult<PyObject> {
+    if !(seconds > 0.0 && seconds.is_finite()) {
+        return Err(pyo3::exceptions::PyValueError::new_err("seconds must be positive"));
+    }
+    let opts = capture::CaptureOptions {
+        iface,
+        duration: std::time::Duration::from_secs_f64(seconds),
+        freq_mhz: freq,
+    };
+    let cancel = cancel_of(cancel);
+    let done = map_pyerr(py.allow_threads(|| capture::capture(&opts, std::path::Path::new(&path), &cancel)))?;
+    let d = PyDict::new_bound(py);
+    let stats = match done {
+        capture::Capture::Written(stats) => stats,
+        capture::Capture::DryRun(plan) => {
+            d.set_item("dry_run", true)?;
+            d.set_item("iface", plan.iface)?;
+            d.set_item("ifindex", plan.ifindex)?;
+            d.set_item("iftype", netlink::iftype_name(plan.iftype))?;
+            d.set_item("to_iftype", netlink::iftype_name(capture::IFTYPE_MONITOR))?;
+            d.set_item("freq", plan.freq_mhz)?;
+            d.set_item("path", path)?;
+            return Ok(d.into_py(py));
+        }
+    };
+    d.set_item("dry_run", false)?;
+    d.set_item("frames", stats.frames())?;
+    d.set_item("beacons", stats.beacons)?;
+    d.set_item("probe_responses", stats.probe_responses)?;
+    d.set_item("skipped", stats.skipped)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: survey_start(own_ssids: List[str] = [],
+///                      node_names: Dict[str, str] = {}) -> None
+/// Start a walk-through survey, dropping any previous one. `node_names`
+/// maps any BSSID of a node to a display name ("Living room"); own SSIDs
+/// default to the connected AP's.
+#[pyfunction]
+#[pyo3(signature = (own_ssids=Vec::new(), node_names=std::collections::HashMap::new()))]
+fn survey_start(
+    own_ssids: Vec<String>,
+    node_names: std::collections::HashMap<String, String>,
+) -> PyResult<()> {
+    let names = node_names
+        .into_iter()
+        .map(|(mac, name)| Ok((map_pyerr(parse_mac(&mac))?, name)))
+        .collect::<PyResult<Vec<_>>>()?;
+    survey::start(own_ssids, names);
+    Ok(())
+}
+
+/// Python: survey_sample(room: str | None = None, scan: List[Dict] | None = None,
+///                       connected_bssid: str | None = None,
+///                       progress: Callable[[float, str, str], None] | None = None,
+///                       cancel: CancelToken | None = None) -> Dict
+/// Record where we are: {room, at, mono, best_node, best_bssid, best_dbm,
+/// connected_bssid, connected_node, connected_dbm, on_best, margin_db}.
+/// Without `scan` a fresh scan is taken and the connected BSSID read from
+/// the interface, with `progress(percent, stage, message)` called before
+/// and after; with one, `connected_bssid` is taken as given.
+/// Without `room` the sample is labelled with the room locate() puts it
+/// in (set_floor_plan() with rooms needed) and the dict also has
+/// "position", locate()'s answer.
+#[pyfunction]
+#[pyo3(signature = (room=None, scan=None, connected_bssid=None, progress=None, cancel=None))]
+fn survey_sample(
+    py: Python<'_>,
+    room: Option<&str>,
+    scan: Option<Bound<'_, PyList>>,
+    connected_bssid: Option<String>,
+    progress: Option<PyObject>,
+    cancel: Option<CancelToken>,
+) -> PyResult<PyObject> {
+    let mut progress = py_progress(progress).with_cancel(cancel_of(cancel));
+    let (rows, connected) = match scan {
+        Some(list) => (
+            rows_from_list(&list)?,
+            connected_bssid.map(|s| map_pyerr(parse_mac(&s))).transpose()?,
+        ),
+        None => map_pyerr(py.allow_threads(|| -> anyhow::Result<_> {
+            let at = room.map_or(String::new(), |r| format!(" in {r}"));
+            progress.report(0.0, "scan", &format!("scanning{at}"))?;
+            let rows = scan_all_bss_until(progress.cancel())?;
+            let connected = lib_rust::connected_in(backend(), None, &rows)?;
+            progress.report(100.0, "scan", &format!("{} BSSs", rows.len()))?;
+            Ok((rows, connected))
+        }))?,
+    };
+    let position = match room {
+        Some(_) => None,
+        None => Some(map_pyerr(locate::locate(&rows, &[]))?),
+    };
+    let room = match (room, &position) {
+        (Some(r), _) => r,
+        (None, Some(locate::Position { room: Some(r), .. })) => r.as_str(),
+        (None, _) => {
+            return Err(PyRuntimeError::new_err(
+                "can't tell the room: the floor plan has no rooms (pass room=)",
+            ))
+        }
+    };
+    let Some(s) = survey::sample(room, &rows, connected) else {
+        return Err(PyRuntimeError::new_err("no survey running; call survey_start() first"));
+    };
+
+    let d = PyDict::new_bound(py);
+    d.set_item("room", &s.room)?;
+    set_stamp(&d, "at", "mono", Some(s.at))?;
+    d.set_item("best_node", s.best_node.map(survey::node_name))?;
+    d.set_item("best_bssid", s.best_bssid.as_ref().map(format_mac))?;
+    d.set_item("best_dbm", s.best_dbm)?;
+    d.set_item("connected_bssid", s.connected_bssid.as_ref().map(format_mac))?;
+    d.set_item("connected_node", s.connected_node.map(survey::node_name))?;
+    d.set_item("connected_dbm", s.connected_dbm)?;
+    d.set_item("on_best", s.on_best())?;
+    d.set_item("margin_db", s.margin_db())?;
+    if let Some(p) = &position {
+        d.set_item("position", position_dict(py, p)?)?;
+    }
+    Ok(d.into_py(py))
+}
+
+fn survey_table(py: Python<'_>, rooms: Vec<survey::RoomSummary>) -> PyResult<PyObject> {
+    let out = PyList::empty_bound(py);
+    for r in rooms {
+        let d = PyDict::new_bound(py);
+        d.set_item("room", r.room)?;
+        d.set_item("samples", r.samples)?;
+        d.set_item("expected_node", r.expected_node.map(survey::node_name))?;
+        d.set_item("expected_dbm", r.expected_dbm)?;
+        d.set_item("connected_node", r.connected_node.map(survey::node_name))?;
+        d.set_item("on_expected", r.on_expected)?;
+        d.set_item("alerts", r.alerts)?;
+        out.append(d)?;
+    }
+    Ok(out.into_py(py))
+}
+
+/// Python: survey_report() -> List[Dict]
+/// The per-room table so far, rooms in survey order: {room, samples,
+/// expected_node, expected_dbm, connected_node, on_expected, alerts}.
+/// expected_node is the node most often strongest in the room;
+/// on_expected the fraction of samples associated to it.
+#[pyfunction]
+fn survey_report(py: Python<'_>) -> PyResult<PyObject> {
+    survey_table(py, survey::report())
+}
+
+/// Python: survey_stop() -> List[Dict]
+/// End the survey and return its final survey_report() table.
+#[pyfunction]
+fn survey_stop(py: Python<'_>) -> PyResult<PyObject> {
+    let rooms = survey::report();
+    let out = survey_table(py, rooms)?;
+    survey::stop();
+    Ok(out)
+}
+
+/// Python: mesh_topology(scans: List[List[Dict]], connected_bssid: str | None = None,
+///                        own_ssids: List[str] = [], format: str = "json") -> str
+/// Our own mesh built from scans taken around the house: nodes (radios
+/// grouped per device), the client's node, inter-node beacon RSSI and the
+/// inferred backhaul tree. `format` is "json" or "dot" (Graphviz).
+/// Own SSIDs default to the connected AP's SSID.
+#[pyfunction]
+#[pyo3(signature = (scans, connected_bssid=None, own_ssids=Vec::new(), format="json"))]
+fn mesh_topology(
+    scans: Vec<Bound<'_, PyList>>,
+    connected_bssid: Option<String>,
+    own_ssids: Vec<String>,
+    format: &str,
+) -> PyResult<String> {
+    let rows = scans.iter().map(rows_from_list).collect::<PyResult<Vec<_>>>()?;
+    let connected = connected_bssid.map(|s| map_pyerr(parse_mac(&s))).transpose()?;
+    let topo = map_pyerr(topology::build(&rows, connected, &own_ssids))?;
+    match format {
+        "json" => Ok(topo.to_json().to_string()),
+        "dot" => Ok(topo.to_dot()),
+        other => Err(PyRuntimeError::new_err(format!(
+            "unknown format {other:?} (expected \"json\" or \"dot\")"
+        ))),
+    }
+}
+
+/// Python: set_location(lat: float, lon: float, alt: float | None = None,
+///                       accuracy_m: float | None = None) -> None
+/// Where we are now. Until clear_location(), every scan is also logged as
+/// one geotagged observation per BSS for export_geojson() / export_kml().
+#[pyfunction]
+#[pyo3(signature = (lat, lon, alt=None, accuracy_m=None))]
+fn set_location(lat: f64, lon: f64, alt: Option<f64>, accuracy_m: Option<f64>) -> PyResult<()> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(PyRuntimeError::new_err(format!("invalid position {lat}, {lon}")));
+    }
+    geo::set_fix(geo::Fix {
+        lat,
+        lon,
+        alt,
+        accuracy_m,
+        at: Stamp::now(),
+    });
+    Ok(())
+}
+
+/// Python: clear_location() -> None
+/// Stop geotagging scans; what was logged is kept.
+#[pyfunction]
+fn clear_location() {
+    geo::clear_fix();
+}
+
+/// Python: location() -> Dict | None
+///   {lat, lon, alt, accuracy_m, at, mono}, the position scans are tagged with.
+#[pyfunction]
+fn location(py: Python<'_>) -> PyResult<PyObject> {
+    let Some(fix) = geo::fix() else {
+        return Ok(py.None());
+    };
+    let d = PyDict::new_bound(py);
+    d.set_item("lat", fix.lat)?;
+    d.set_item("lon", fix.lon)?;
+    d.set_item("alt", fix.alt)?;
+    d.set_item("accuracy_m", fix.accuracy_m)?;
+    set_stamp(&d, "at", "mono", Some(fix.at))?;
+    Ok(d.into_py(py))
+}
+
+/// Python: export_geojson(observations: bool = True, aps: bool = True) -> str
+/// The geotagged observations as a GeoJSON FeatureCollection of points:
+/// properties.kind "observation" {bssid, ssid, freq_mhz, channel,
+/// signal_dbm, security, accuracy_m, at} per BSS per scan, and "ap"
+/// {bssid, ssid, channel, observations, best_dbm} at each BSS's estimated
+/// position (centroid of its sightings weighted by received power).
+#[pyfunction]
+#[pyo3(signature = (observations=true, aps=true))]
+fn export_geojson(py: Python<'_>, observations: bool, aps: bool) -> String {
+    py.allow_threads(|| geo::geojson(&geo::observations(), observations, aps).to_string())
+}
+
+/// Python: export_kml(observations: bool = True, aps: bool = True) -> str
+/// export_geojson() as a KML document, with "Access points" and
+/// "Observations" folders.
+#[pyfunction]
+#[pyo3(signature = (observations=true, aps=true))]
+fn export_kml(py: Python<'_>, observations: bool, aps: bool) -> String {
+    py.allow_threads(|| geo::kml(&geo::observations(), observations, aps))
+}
+
+/// Python: geo_len() -> int
+#[pyfunction]
+fn geo_len() -> usize {
+    geo::len()
+}
+
+/// Python: clear_geo() -> None
+#[pyfunction]
+fn clear_geo() {
+    geo::clear()
+}
+
+/// Python: gpsd_start(host: str = "127.0.0.1", port: int = 2947,
+///                     scan_interval: float | None = None) -> None
+/// Follow gpsd's position in the background: scans are geotagged with
+/// every 2D/3D fix (as with set_location()), and not at all while gpsd has
+/// no fix or can't be reached. With `scan_interval` (seconds) it also
+/// scans that often while there is a fix: walk / drive survey mode.
+/// Replaces a running client; connection problems are retried and show in
+/// gpsd_status().
+#[pyfunction]
+#[pyo3(signature = (host="127.0.0.1", port=2947, scan_interval=None))]
+fn gpsd_start(host: &str, port: u16, scan_interval: Option<f64>) -> PyResult<()> {
+    let scan_every = scan_interval
+        .map(|s| {
+            std::time::Duration::try_from_secs_f64(s)
+                .map_err(|e| PyRuntimeError::new_err(format!("scan_interval: {e}")))
+        })
+        .transpose()?;
+    map_pyerr(gpsd::start(gpsd::Config {
+        host: host.to_owned(),
+        port,
+        scan_every,
+    }))
+}
+
+/// Python: gpsd_stop() -> None
+/// Disconnect from gpsd, stop survey scanning and clear the position.
+#[pyfunction]
+fn gpsd_stop(py: Python<'_>) {
+    py.allow_threads(gpsd::stop);
+}
+
+/// Python: gpsd_status() -> Dict
+///   {running, connected, mode, fix: {lat, lon, alt, accuracy_m, at} | None,
+///    scans, scan_errors, last_error}
+/// mode is gpsd's: 0/1 no fix, 2 = 2D, 3 = 3D. scans counts survey-mode
+/// scans.
+#[pyfunction]
+fn gpsd_status(py: Python<'_>) -> PyResult<PyObject> {
+    let s = gpsd::status();
+    let d = PyDict::new_bound(py);
+    d.set_item("running", s.running)?;
+    d.set_item("connected", s.connected)?;
+    d.set_item("mode", s.mode)?;
+    match s.fix {
+        Some(fix) => {
+            let f = PyDict::new_bound(py);
+            f.set_item("lat", fix.lat)?;
+            f.set_item("lon", fix.lon)?;
+            f.set_item("alt", fix.alt)?;
+            f.set_item("accuracy_m", fix.accuracy_m)?;
+            f.set_item("at", fix.at.wall)?;
+            d.set_item("fix", f)?;
+        }
+        None => d.set_item("fix", py.None())?,
+    }
+    d.set_item("scans", s.scans)?;
+    d.set_item("scan_errors", s.scan_errors)?;
+    d.set_item("last_error", s.last_error)?;
+    Ok(d.into_py(py))
+}
+
+fn ap_pos_from_dict(d: &Bound<'_, PyDict>) -> PyResult<locate::ApPos> {
+    let bssid: String = required(d, "bssid")?;
+    Ok(locate::ApPos {
+        bssid: map_pyerr(parse_mac(&bssid))?,
+        x: required(d, "x")?,
+        y: required(d, "y")?,
+        floor: d.get_item("floor")?.map(|v| v.extract()).transpose()?.unwrap_or(0),
+        ref_dbm: d.get_item("ref_dbm")?.map(|v| v.extract()).transpose()?.unwrap_or(locate::REF_DBM),
+    })
+}
+
+fn room_from_dict(d: &Bound<'_, PyDict>) -> PyResult<locate::Room> {
+    let name: String = required(d, "name")?;
+    let points: Vec<Vec<f64>> = required(d, "polygon")?;
+    let polygon = points
+        .iter()
+        .map(|p| match p[..] {
+            [x, y] => Ok((x, y)),
+            _ => Err(PyRuntimeError::new_err(format!("room {name:?}: points are [x, y]"))),
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(locate::Room {
+        floor: d.get_item("floor")?.map(|v| v.extract()).transpose()?.unwrap_or(0),
+        name,
+        polygon,
+    })
+}
+
+/// Python: set_floor_plan(aps: List[Dict], rooms: List[Dict] = [],
+///                         exponent: float = 3.0) -> None
+/// Where our own APs are, for locate(): {bssid, x, y, floor=0,
+/// ref_dbm=-40} per AP, coordinates in metres and ref_dbm the RSSI 1 m
+/// away. Other radios of the same AP are matched through their BSSID.
+/// `rooms` are {name, polygon: [[x, y], ...], floor=0}; `exponent` is the
+/// path-loss exponent (2 in the open, 3-4 through walls).
+#[pyfunction]
+#[pyo3(signature = (aps, rooms=Vec::new(), exponent=3.0))]
+fn set_floor_plan(aps: Vec<Bound<'_, PyDict>>, rooms: Vec<Bound<'_, PyDict>>, exponent: f64) -> PyResult<()> {
+    let aps = aps.iter().map(ap_pos_from_dict).collect::<PyResult<Vec<_>>>()?;
+    let rooms = rooms.iter().map(room_from_dict).collect::<PyResult<Vec<_>>>()?;
+    map_pyerr(locate::set_map(locate::Map { aps, rooms, exponent }))
+}
+
+/// Python: clear_floor_plan() -> None
+#[pyfunction]
+fn clear_floor_plan() {
+    locate::clear_map()
+}
+
+fn position_dict<'py>(py: Python<'py>, p: &locate::Position) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new_bound(py);
+    d.set_item("x", p.x)?;
+    d.set_item("y", p.y)?;
+    d.set_item("floor", p.floor)?;
+    d.set_item("error_m", p.error_m)?;
+    d.set_item("aps", p.aps)?;
+    d.set_item("room", &p.room)?;
+    Ok(d)
+}
+
+/// Python: locate(scan: List[Dict] | None = None,
+///                 ranges: Dict[str, float] | None = None) -> Dict
+///   {x, y, floor, error_m, aps, room}
+/// Where we are on the floor plan (set_floor_plan()), from `scan` or a
+/// fresh scan. `ranges` are FTM distances in metres by BSSID (e.g. from
+/// Android's WifiRttManager) and are trusted over RSSI. error_m is the
+/// fit's RMS misfit; room is the room containing the position, else the
+/// nearest one on that floor, None without rooms.
+#[pyfunction]
+#[pyo3(name = "locate", signature = (scan=None, ranges=None))]
+fn locate_position(
+    py: Python<'_>,
+    scan: Option<Bound<'_, PyList>>,
+    ranges: Option<std::collections::HashMap<String, f64>>,
+) -> PyResult<PyObject> {
+    let rows = match scan {
+        Some(list) => rows_from_list(&list)?,
+        None => map_pyerr(py.allow_threads(scan_all_bss))?,
+    };
+    let ranges = ranges
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(mac, d)| Ok((map_pyerr(parse_mac(&mac))?, d)))
+        .collect::<PyResult<Vec<_>>>()?;
+    let pos = map_pyerr(locate::locate(&rows, &ranges))?;
+    Ok(position_dict(py, &pos)?.into_py(py))
+}
+
+/// Python: probe_start(target: str | None = None, interval: float = 1.0,
+///                      timeout: float = 1.0) -> None
+/// Ping `target` (IPv4; the default gateway, followed across network
+/// changes, when None) every `interval` seconds in the background. RTT and
+/// loss go to latency_report() and, with open_history_db(), the database's
+/// latency table. Uses ICMP when the process may, else DNS over UDP.
+#[pyfunction]
+#[pyo3(signature = (target=None, interval=1.0, timeout=1.0))]
+fn probe_start(target: Option<&str>, interval: f64, timeout: f64) -> PyResult<()> {
+    let target = target
+        .map(|t| t.parse().map_err(|e| PyRuntimeError::new_err(format!("target {t:?}: {e}"))))
+        .transpose()?;
+    let secs = |name: &str, v: f64| {
+        std::time::Duration::try_from_secs_f64(v).map_err(|e| PyRuntimeError::new_err(format!("{name}: {e}")))
+    };
+    map_pyerr(probe::start(probe::Config {
+        target,
+        interval: secs("interval", interval)?,
+        timeout: secs("timeout", timeout)?,
+    }))
+}
+
+/// Python: probe_stop() -> None
+#[pyfunction]
+fn probe_stop(py: Python<'_>) {
+    py.allow_threads(probe::stop);
+}
+
+/// Python: probe_status() -> Dict
+///   {running, target, method, sent, lost, last_error}
+/// method is "icmp", "icmp_raw" or "udp".
+#[pyfunction]
+fn probe_status(py: Python<'_>) -> PyResult<PyObject> {
+    let s = probe::status();
+    let d = PyDict::new_bound(py);
+    d.set_item("running", s.running)?;
+    d.set_item("target", s.target.map(|t| t.to_string()))?;
+    d.set_item("method", s.method.map(probe::Method::name))?;
+    d.set_item("sent", s.sent)?;
+    d.set_item("lost", s.lost)?;
+    d.set_item("last_error", s.last_error)?;
+    Ok(d.into_py(py))
+}
+
+fn latency_dict<'py>(py: Python<'py>, l: &probe::LatencyStats) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new_bound(py);
+    d.set_item("samples", l.samples)?;
+    d.set_item("lost", l.lost)?;
+    d.set_item("loss", l.loss)?;
+    d.set_item("mean_ms", l.mean_ms)?;
+    d.set_item("max_ms", l.max_ms)?;
+    d.set_item("jitter_ms", l.jitter_ms)?;
+    Ok(d)
+}
+
+/// Python: latency_report(window: int | None = None) -> Dict
+///   {overall: {samples, lost, loss, mean_ms, max_ms, jitter_ms},
+///    intervals: [{at, channel, weight, aps, latency: {...}}],
+///    weight_vs_rtt, weight_vs_jitter, weight_vs_loss}
+/// The probes lined up with the last `window` recorded scans: each
+/// interval is the probes since the scan before, next to that scan's
+/// interference weight and AP count on the connected channel. The
+/// weight_vs_* values are Pearson correlations over the intervals (None
+/// with fewer than 3), high when latency rises with interference.
+#[pyfunction]
+#[pyo3(signature = (window=None))]
+fn latency_report(py: Python<'_>, window: Option<usize>) -> PyResult<PyObject> {
+    let (samples, corr) = py.allow_threads(|| {
+        let samples = probe::samples();
+        let corr = probe::correlate(&history::recent(window), &samples);
+        (samples, corr)
+    });
+    let d = PyDict::new_bound(py);
+    d.set_item("overall", latency_dict(py, &probe::stats(&samples))?)?;
+    let intervals = PyList::empty_bound(py);
+    for i in &corr.intervals {
+        let e = PyDict::new_bound(py);
+        e.set_item("at", i.at)?;
+        e.set_item("channel", i.channel)?;
+        e.set_item("weight", i.weight)?;
+        e.set_item("aps", i.aps)?;
+        e.set_item("latency", latency_dict(py, &i.latency)?)?;
+        intervals.append(e)?;
+    }
+    d.set_item("intervals", intervals)?;
+    d.set_item("weight_vs_rtt", corr.weight_vs_rtt)?;
+    d.set_item("weight_vs_jitter", corr.weight_vs_jitter)?;
+    d.set_item("weight_vs_loss", corr.weight_vs_loss)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: clear_latency() -> None
+#[pyfunction]
+fn clear_latency() {
+    probe::clear()
+}
+
+/// Python: history_len() -> int
+#[pyfunction]
+fn history_len() -> usize {
+    history::len()
+}
+
+/// Python: clear_history() -> None
+#[pyfunction]
+fn clear_history() {
+    history::clear()
+}
+
+/// Python: open_history_db(path: str) -> None
+/// Also persist every scan (and latency probe) to the SQLite database at
+/// `path` (WAL mode), written by a background thread so scans never wait
+/// on disk.
+#[pyfunction]
+fn open_history_db(path: &str) -> PyResult<()> {
+    map_pyerr(history_db::open(std::path::Path::new(path)))
+}
+
+/// Python: close_history_db() -> None
+/// Flush whatever is still queued and stop the writer.
+#[pyfunction]
+fn close_history_db(py: Python<'_>) {
+    py.allow_threads(history_db::close)
+}
+
+/// Python: history_db_stats() -> Dict
+///   {path, pending, capacity, written, failed, dropped, batches,
+///    last_batch_ms, max_batch_ms}
+/// `dropped` counts scans and latency probes skipped because the writer
+/// fell behind.
+#[pyfunction]
+fn history_db_stats(py: Python<'_>) -> PyResult<PyObject> {
+    let s = history_db::stats();
+    let d = PyDict::new_bound(py);
+    d.set_item("path", s.path.map(|p| p.display().to_string()))?;
+    d.set_item("pending", s.pending)?;
+    d.set_item("capacity", s.capacity)?;
+    d.set_item("written", s.written)?;
+    d.set_item("failed", s.failed)?;
+    d.set_item("dropped", s.dropped)?;
+    d.set_item("batches", s.batches)?;
+    d.set_item("last_batch_ms", s.last_batch_ms)?;
+    d.set_item("max_batch_ms", s.max_batch_ms)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: open_ring(capacity: int = 4096) -> str
+/// Start streaming every scan's rows into a shared-memory ring of
+/// `capacity` fixed-size records. Returns the path to mmap; read it with
+/// pybackend.ring_reader.RingReader.
+#[pyfunction]
+#[pyo3(signature = (capacity=4096))]
+fn open_ring(capacity: u32) -> PyResult<String> {
+    let path = map_pyerr(ring::open(capacity))?;
+    Ok(path.display().to_string())
+}
+
+/// Python: close_ring() -> None
+#[pyfunction]
+fn close_ring() {
+    ring::close()
+}
+
+/// Python: oui_vendor(bssid: str) -> str | None
+/// Registered vendor for a MAC's OUI, from the memory-mapped database.
+#[pyfunction]
+fn oui_vendor(bssid: &str) -> PyResult<Option<String>> {
+    let mac = map_pyerr(parse_mac(bssid))?;
+    Ok(oui::vendor(&mac))
+}
+
+/// Python: load_oui_db(path: str) -> int
+/// Use the OUI database at `path` instead of the default one. Returns the
+/// number of OUIs it holds.
+#[pyfunction]
+fn load_oui_db(path: &str) -> PyResult<usize> {
+    map_pyerr(oui::load(std::path::Path::new(path)))
+}
+
+/// Python: load_ap_models(path: str) -> int
+/// Use the AP model table (JSON, fingerprint -> name) at `path` instead of
+/// the default one. Returns the number of models in it.
+#[pyfunction]
+fn load_ap_models(path: &str) -> PyResult<usize> {
+    map_pyerr(apmodel::load(std::path::Path::new(path)))
+}
+
+/// Python: learn_ap_model(fingerprint: str, name: str) -> None
+/// Record `name` for a fingerprint from scan_dicts(details=True) and save the
+/// table, so every AP with that beacon layout shows up as `name`.
+#[pyfunction]
+fn learn_ap_model(fingerprint: &str, name: &str) -> PyResult<()> {
+    let fp = map_pyerr(apmodel::parse_fp(fingerprint))?;
+    map_pyerr(apmodel::learn(fp, name))
+}
+
+/// Python: configure_threads(threads: int | None = None, pin: bool = False,
+///                            nice: int | None = None) -> None
+/// Rebuild the pool used for batch work (score_history, ...). Unset
+/// arguments keep their defaults: half the cores up to 4, nice 10.
+#[pyfunction]
+#[pyo3(signature = (threads=None, pin=false, nice=None))]
+fn configure_threads(threads: Option<usize>, pin: bool, nice: Option<i32>) -> PyResult<()> {
+    let default = pool::PoolConfig::default();
+    map_pyerr(pool::configure(pool::PoolConfig {
+        threads: threads.unwrap_or(default.threads),
+        pin,
+        nice: nice.unwrap_or(default.nice),
+    }))
+}
+
+/// Python: thread_config() -> Dict  {threads, pin, nice}
+#[pyfunction]
+fn thread_config(py: Python<'_>) -> PyResult<PyObject> {
+    let c = pool::config();
+    let d = PyDict::new_bound(py);
+    d.set_item("threads", c.threads)?;
+    d.set_item("pin", c.pin)?;
+    d.set_item("nice", c.nice)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: perf_stats() -> Dict
+/// Counters since start (or the last reset_perf_stats()):
+///   {latency: {op: {backend: {count, p50_ms, p90_ms, p99_ms, max_ms}}},
+///    netlink: {sent, recv, recv_bytes, events},
+///    parse: {bytes, seconds, mb_per_s},
+///    ssid_cache: {hits, misses, hit_rate}}
+/// Percentiles cover the most recent samples only.
+#[pyfunction]
+fn perf_stats(py: Python<'_>) -> PyResult<PyObject> {
+    let s = perf::snapshot();
+
+    let latency = PyDict::new_bound(py);
+    for l in &s.latency {
+        let by_backend = match latency.get_item(l.op)? {
+            Some(d) => d.downcast_into::<PyDict>()?,
+            None => {
+                let d = PyDict::new_bound(py);
+                latency.set_item(l.op, &d)?;
+                d
+            }
+        };
+        let d = PyDict::new_bound(py);
+        d.set_item("count", l.count)?;
+        d.set_item("p50_ms", l.p50_ms)?;
+        d.set_item("p90_ms", l.p90_ms)?;
+        d.set_item("p99_ms", l.p99_ms)?;
+        d.set_item("max_ms", l.max_ms)?;
+        by_backend.set_item(l.backend, d)?;
+    }
+
+    let netlink = PyDict::new_bound(py);
+    netlink.set_item("sent", s.nl_sent)?;
+    netlink.set_item("recv", s.nl_recv)?;
+    netlink.set_item("recv_bytes", s.nl_recv_bytes)?;
+    netlink.set_item("events", s.nl_events)?;
+
+    let parse = PyDict::new_bound(py);
+    parse.set_item("bytes", s.parse_bytes)?;
+    parse.set_item("seconds", s.parse_secs)?;
+    parse.set_item("mb_per_s", s.parse_mb_per_s())?;
+
+    let ssid_cache = PyDict::new_bound(py);
+    ssid_cache.set_item("hits", s.ssid_hits)?;
+    ssid_cache.set_item("misses", s.ssid_misses)?;
+    ssid_cache.set_item("hit_rate", s.ssid_hit_rate())?;
+
+    let d = PyDict::new_bound(py);
+    d.set_item("latency", latency)?;
+    d.set_item("netlink", netlink)?;
+    d.set_item("parse", parse)?;
+    d.set_item("ssid_cache", ssid_cache)?;
+    Ok(d.into_py(py))
+}
+
+/// Python: reset_perf_stats() -> None
+#[pyfunction]
+fn reset_perf_stats() {
+    perf::reset()
+}
+
+// Cargo features compiled into this build.
+const FEATURES: &[&str] = &[
+    #[cfg(feature = "backend-neli-wifi")]
+    "backend-neli-wifi",
+    #[cfg(feature = "backend-raw-nl80211")]
+    "backend-raw-nl80211",
+    #[cfg(feature = "capture")]
+    "capture",
+];
+
+// What the Python API of this build can do, so front ends can hide what
+// an older backend doesn't have. Extend with every user-visible addition.
+const CAPABILITIES: &[&str] = &[
+    "scan",
+    "scan_stream",
+    "scan_n",
+    "scan_average",
+    "scan_fields",
+    "progress",
+    "cancel",
+    "channels_detailed",
+    "best_channel_candidates",
+    "channel_width",
+    "history",
+    "history_db",
+    "ring",
+    "mesh_channels_24",
+    "mesh_channels_5",
+    "mesh_channels_6",
+    "mesh_channel_plan",
+    "interference_matrix",
+    "band_6ghz",
+    "channel_survey",
+    "regulatory_domain",
+    "phy_capabilities",
+    "interface_selection",
+    "merged_scan",
+    "link_info",
+    "link_monitor",
+    "events",
+    "asyncio",
+    "scan_random_mac",
+    "scan_busy_retry",
+    "scan_cached_only",
+    "mesh_topology",
+    "neighbor_mesh",
+    "band_steering",
+    "backhaul",
+    "airtime",
+    "airtime_pcap",
+    "survey",
+    "oui",
+    "ap_models",
+    "perf_stats",
+    "thread_config",
+    "mock",
+    "wpa_supplicant",
+    "android_push",
+    "typed_errors",
+    "logging",
+    "scan_json",
+    "scan_csv",
+    "wigle_csv",
+    "scan_diff",
+    "bss_status",
+    "hidden_ssid",
+    "device_grouping",
+    "rnr",
+    "generation",
+    "phy_rate",
+    "throughput_estimate",
+    "ess_groups",
+    "roam_advice",
+    "device_groups",
+    "anomalies",
+    "security_report",
+    "regulatory_mismatches",
+    #[cfg(feature = "capture")]
+    "capture",
+    "p2p",
+    "ibss",
+    "dry_run",
+    "mesh_point",
+    "wps",
+    "passpoint",
+    "pmf",
+    "mlo",
+    "geo",
+    "gpsd",
+    "locate",
+    "latency_probe",
+    "session",
+    "snapshot",
+    "bss_entry",
+];
+
+/// Python: about() -> Dict
+///   {version, target, features: [str],
+///    providers: [{name, selected, available}], capabilities: [str]}
+/// `available` means nl80211 could be reached from this process right
+/// now (for "mock": a fixture is loaded, for "wpa-supplicant": a control
+/// socket was found, for "android": a scan was pushed); capabilities name
+/// features of the API (see CAPABILITIES).
+#[pyfunction]
+fn about(py: Python<'_>) -> PyResult<PyObject> {
+    let reachable = py.allow_threads(|| netlink::Nl80211::shared().is_ok());
+    let providers = PyList::empty_bound(py);
+    for b in Backend::ALL {
+        let p = PyDict::new_bound(py);
+        p.set_item("name", b.name())?;
+        p.set_item("selected", b == backend())?;
+        let available = match b {
+            Backend::Mock => mock::loaded(),
+            Backend::WpaSupplicant => wpa_ctrl::available(),
+            Backend::Android => android::pushed(),
+            _ => reachable && b.built(),
+        };
+        p.set_item("available", available)?;
+        providers.append(p)?;
+    }
+
+    let d = PyDict::new_bound(py);
+    d.set_item("version", env!("CARGO_PKG_VERSION"))?;
+    d.set_item("target", format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH))?;
+    d.set_item("features", FEATURES.to_vec())?;
+    d.set_item("providers", providers)?;
+    d.set_item("capabilities", CAPABILITIES.to_vec())?;
+    Ok(d.into_py(py))
+}
+
+/// Module init. Name *must* be wifi_backend to match Cargo.toml [lib].name.
+#[pymodule]
+fn wifi_backend(_py: Python<'_>, m: &Bound<PyModule>) -> PyResult<()> {
+    if let Some(records) = logs::install() {
+        forward_logs(records);
+    }
     m.add_function(wrap_pyfunction!(scan, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_dicts, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_n, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_average, m)?)?;
+    m.add_class::<ScanStream>()?;
+    m.add_class::<CancelToken>()?;
+    m.add_class::<ScanOptions>()?;
+    m.add_function(wrap_pyfunction!(random_mac_supported, m)?)?;
+    m.add_function(wrap_pyfunction!(last_scan_info, m)?)?;
+    m.add_class::<WifiSession>()?;
+    m.add_class::<LinkMonitor>()?;
+    m.add_class::<EventStream>()?;
+    m.add_class::<ScanSnapshot>()?;
+    m.add_class::<BssEntry>()?;
     m.add_function(wrap_pyfunction!(compute_channels, m)?)?;
     m.add_function(wrap_pyfunction!(compute_best_channel, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_best_channel_plan, m)?)?;
+    m.add_function(wrap_pyfunction!(channel_scores, m)?)?;
+    m.add_function(wrap_pyfunction!(interference_matrix, m)?)?;
+    m.add_class::<PyChannelConfig>()?;
+    m.add_function(wrap_pyfunction!(channel_survey, m)?)?;
+    m.add_function(wrap_pyfunction!(regulatory_domain, m)?)?;
+    m.add_function(wrap_pyfunction!(phy_capabilities, m)?)?;
+    m.add_function(wrap_pyfunction!(list_interfaces, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_merged, m)?)?;
+    m.add_function(wrap_pyfunction!(snapshot, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_json, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_scans, m)?)?;
+    m.add_function(wrap_pyfunction!(hidden_network_count, m)?)?;
+    m.add_function(wrap_pyfunction!(rnr_neighbors, m)?)?;
+    m.add_function(wrap_pyfunction!(group_by_ssid, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest_roam_target, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_anomalies, m)?)?;
+    m.add_function(wrap_pyfunction!(security_report, m)?)?;
+    m.add_function(wrap_pyfunction!(regulatory_mismatches, m)?)?;
+    m.add_function(wrap_pyfunction!(device_groups, m)?)?;
     m.add_function(wrap_pyfunction!(connected_bssid, m)?)?;
+    m.add_function(wrap_pyfunction!(link_info, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_async, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_dicts_async, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_channels_async, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_best_channel_async, m)?)?;
+    m.add_function(wrap_pyfunction!(channel_scores_async, m)?)?;
+    m.add_function(wrap_pyfunction!(connected_bssid_async, m)?)?;
+    m.add_function(wrap_pyfunction!(link_info_async, m)?)?;
+    m.add_function(wrap_pyfunction!(subscribe_events, m)?)?;
+    m.add_function(wrap_pyfunction!(add_event_callback, m)?)?;
+    m.add_function(wrap_pyfunction!(remove_event_callback, m)?)?;
+    m.add_function(wrap_pyfunction!(set_backend, m)?)?;
+    m.add_function(wrap_pyfunction!(get_backend, m)?)?;
+    m.add_function(wrap_pyfunction!(set_p2p_policy, m)?)?;
+    m.add_function(wrap_pyfunction!(get_p2p_policy, m)?)?;
+    m.add_function(wrap_pyfunction!(set_exclude_ibss, m)?)?;
+    m.add_function(wrap_pyfunction!(set_dry_run, m)?)?;
+    m.add_function(wrap_pyfunction!(get_dry_run, m)?)?;
+    m.add_function(wrap_pyfunction!(set_device_grouping, m)?)?;
+    m.add_function(wrap_pyfunction!(device_grouping, m)?)?;
+    m.add_function(wrap_pyfunction!(same_device, m)?)?;
+    m.add_function(wrap_pyfunction!(set_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(get_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(load_mock_fixture, m)?)?;
+    m.add_function(wrap_pyfunction!(mock_fault, m)?)?;
+    m.add_function(wrap_pyfunction!(push_android_scan, m)?)?;
+    m.add_function(wrap_pyfunction!(score_history, m)?)?;
+    m.add_function(wrap_pyfunction!(assign_mesh_channels_24, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh_node_rssi, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh_topology, m)?)?;
+    m.add_function(wrap_pyfunction!(band_steering, m)?)?;
+    m.add_function(wrap_pyfunction!(record_backhaul_sample, m)?)?;
+    m.add_function(wrap_pyfunction!(sample_backhaul, m)?)?;
+    m.add_function(wrap_pyfunction!(backhaul_health, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_backhaul, m)?)?;
+    m.add_function(wrap_pyfunction!(classify_backhaul, m)?)?;
+    m.add_function(wrap_pyfunction!(airtime_report, m)?)?;
+    m.add_function(wrap_pyfunction!(capture_beacons, m)?)?;
+    m.add_function(wrap_pyfunction!(survey_start, m)?)?;
+    m.add_function(wrap_pyfunction!(survey_sample, m)?)?;
+    m.add_function(wrap_pyfunction!(survey_report, m)?)?;
+    m.add_function(wrap_pyfunction!(survey_stop, m)?)?;
+    m.add_function(wrap_pyfunction!(assign_mesh_channels_5, m)?)?;
+    m.add_function(wrap_pyfunction!(assign_mesh_channels_6, m)?)?;
+    m.add_function(wrap_pyfunction!(plan_mesh_channels, m)?)?;
+    m.add_function(wrap_pyfunction!(neighbor_mesh_systems, m)?)?;
+    m.add_function(wrap_pyfunction!(set_location, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_location, m)?)?;
+    m.add_function(wrap_pyfunction!(location, m)?)?;
+    m.add_function(wrap_pyfunction!(export_geojson, m)?)?;
+    m.add_function(wrap_pyfunction!(export_kml, m)?)?;
+    m.add_function(wrap_pyfunction!(geo_len, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_geo, m)?)?;
+    m.add_function(wrap_pyfunction!(gpsd_start, m)?)?;
+    m.add_function(wrap_pyfunction!(gpsd_stop, m)?)?;
+    m.add_function(wrap_pyfunction!(gpsd_status, m)?)?;
+    m.add_function(wrap_pyfunction!(set_floor_plan, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_floor_plan, m)?)?;
+    m.add_function(wrap_pyfunction!(locate_position, m)?)?;
+    m.add_function(wrap_pyfunction!(probe_start, m)?)?;
+    m.add_function(wrap_pyfunction!(probe_stop, m)?)?;
+    m.add_function(wrap_pyfunction!(probe_status, m)?)?;
+    m.add_function(wrap_pyfunction!(latency_report, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_latency, m)?)?;
+    m.add_function(wrap_pyfunction!(history_len, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_history, m)?)?;
+    m.add_function(wrap_pyfunction!(open_history_db, m)?)?;
+    m.add_function(wrap_pyfunction!(close_history_db, m)?)?;
+    m.add_function(wrap_pyfunction!(history_db_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(open_ring, m)?)?;
+    m.add_function(wrap_pyfunction!(close_ring, m)?)?;
+    m.add_function(wrap_pyfunction!(oui_vendor, m)?)?;
+    m.add_function(wrap_pyfunction!(load_oui_db, m)?)?;
+    m.add_function(wrap_pyfunction!(load_ap_models, m)?)?;
+    m.add_function(wrap_pyfunction!(learn_ap_model, m)?)?;
+    m.add_function(wrap_pyfunction!(configure_threads, m)?)?;
+    m.add_function(wrap_pyfunction!(thread_config, m)?)?;
+    m.add_function(wrap_pyfunction!(perf_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(reset_perf_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(about, m)?)?;
+    let py = m.py();
+    m.add("WifiError", py.get_type_bound::<exceptions::WifiError>())?;
+    m.add("NoInterfaceError", py.get_type_bound::<exceptions::NoInterfaceError>())?;
+    m.add("PermissionDeniedError", py.get_type_bound::<exceptions::PermissionDeniedError>())?;
+    m.add("ScanTimeoutError", py.get_type_bound::<exceptions::ScanTimeoutError>())?;
+    m.add("ScanAbortedError", py.get_type_bound::<exceptions::ScanAbortedError>())?;
+    m.add("NetlinkError", py.get_type_bound::<exceptions::NetlinkError>())?;
+    m.add("UnsupportedError", py.get_type_bound::<exceptions::UnsupportedError>())?;
     Ok(())
 }
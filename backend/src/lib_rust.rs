@@ -1,254 +1,2406 @@
+// src/lib_rust.rs
+//
+// Core of the Wi-Fi backend: the shared BssRow data model, the helpers both
+// netlink backends use, and the channel computations built on top of them.
+//
 // Exposes:
 //   - scan_all_bss() -> Result<Vec<BssRow>>
-//   - get_connected_bssid() -> Result<Option<[u8; 6]>>
-//   - compute_channels_internal() -> Result<HashMap<u32, u32>>
-//   - compute_best_channel_internal() -> Result<u32>
+//   - scan_with(options, cancel) -> Result<Vec<BssRow>>, a scan shaped by
+//     ScanOptions (passive, frequencies / band, SSIDs, flush, what to do
+//     when the radio is busy: BusyPolicy, cached_only)
+//   - last_scan() -> Option<ScanInfo>: whether the last scan was triggered
+//     or only read the kernel's BSS cache (NoTrigger), and how old its
+//     sightings were
+//   - scan_stream() -> Receiver<ScanEvent>, rows as they are parsed
+//   - scan_n(times, interval) -> Vec<BssAggregate>, per-BSS stats over scans
+//   - get_connected_bssid() -> Result<Option<[u8; 6]>>; from the scan's own
+//     BssStatus where the backend reports it (connected_in())
+//   - list_interfaces() -> Result<Vec<WifiIface>>; Session::open_on() picks
+//     one by name
+//   - compute_channels_internal(band) -> Result<HashMap<u32, u32>>
+//   - hidden_network_count(rows, channel) -> usize
+//   - channel_breakdown(rows, band, busy) -> Vec<ChannelStats>, co / adjacent
+//     overlap
+//   - compute_best_channel_internal(candidates, config) -> Result<(band, channel)>, also
+//     weighing the busy time of a fresh channel survey (chansurvey), tuned
+//     by ChannelConfig
+//   - channel_scores(rows, connected, candidates, busy, limits, config) -> ranked
+//     Vec<ChannelScore> behind that recommendation
+//   - interference_matrix(rows, connected, band, busy, config) ->
+//     Vec<InterferenceMatrix>: those weights per (observed, candidate)
+//     channel pair
+//   - recommendations stay on channels the regulatory domain (regdom)
+//     allows without NO-IR and the local radio (phycaps) can use:
+//     ChannelLimits
+//   - set_backend() / backend() to pick where scan data comes from
+//   - set_p2p_policy() / p2p_policy(): how Wi-Fi Direct groups count
+//   - set_exclude_ibss() / exclude_ibss(): whether ad-hoc networks count
+//   - set_dry_run() / dry_run(): whether control operations only report
+//     what they would change
 //
-// Uses a fresh neli-wifi Socket on each call, so every room scan is new.
+// Backends:
+//   - nl_wifi: neli-wifi dump of the kernel's BSS table (default)
+//   - nl_raw:  raw nl80211 TRIGGER_SCAN + GET_SCAN, parsed by hand
+//
+// Both run over the shared socket in netlink.rs; every call still issues
+// its own dump, so every room scan is new.
 
-use anyhow::{anyhow, Result};
-use neli_wifi::{Bss, Socket, Station};
-use std::collections::HashMap;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::cancel::Cancel;
+use crate::error::WifiError;
+use crate::export;
+use crate::ies::{self, BssLoad, Interworking, MeshConfig, MultipleBssid, Operation, PhyRates, Pmf, Security, Wps};
+use crate::link::{self, LinkInfo};
+use crate::netlink::{self, block_on, runtime, WifiIface};
+use crate::progress::Progress;
+use crate::phycaps::{self, PhyCaps};
+use crate::regdom::{self, RegDomain};
+use crate::stamp::Stamp;
+use crate::chansurvey::{self, ChannelSurvey};
+use crate::{android, anomaly, apmodel, devgroup, ess, geo, history, mock, nl_raw, perf, phyrate, ring, regcheck, rnr, roam, secaudit, wpa_ctrl};
+
+// Struct that will hold information collected from each BSS. The serde
+// form is the row of export.rs's scan document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BssRow {
+    /// "" for a hidden network; None when nothing said what it is.
+    /// Bytes that aren't UTF-8 show as U+FFFD.
+    pub ssid: Option<Arc<str>>,
+    /// The SSID exactly as broadcast, to tell apart networks whose names
+    /// only differ in bytes `ssid` can't show (GBK names, binary IoT
+    /// ones). Empty when there is no SSID.
+    #[serde(with = "export::ssid_hex")]
+    pub ssid_bytes: Vec<u8>,
+    /// The SSID element was empty or all zero bytes: the AP hides its
+    /// name. Not set for an 802.11s mesh point, whose wildcard SSID is
+    /// normal (its name is the Mesh ID).
+    pub hidden: bool,
+    #[serde(with = "export::mac")]
+    pub bssid: Option<[u8; 6]>,
+    pub freq_mhz: Option<u32>,
+    pub signal_dbm: Option<f32>,
+    pub channel: Option<u32>,
+    /// When the kernel last received a frame from this BSS.
+    pub seen: Option<Stamp>,
+    /// How old that was when the scan was read, ms: a BSS from the
+    /// kernel's cache can be minutes old.
+    pub age_ms: Option<u32>,
+    /// Seen before the scan that returned it started: an entry from the
+    /// kernel's table rather than something this scan heard.
+    pub cached: bool,
+    /// Capability Information field of the beacon / probe response, when
+    /// the backend reports it (raw nl80211 does, neli-wifi doesn't).
+    pub capability: Option<u16>,
+    /// Beacon interval in TUs (1.024 ms), when the backend reports it;
+    /// 100 on nearly every AP.
+    pub beacon_interval: Option<u16>,
+    /// Raw IE blob, kept so the rarer fields can be parsed on first use.
+    #[serde(with = "export::ies_hex")]
+    pub ies: Option<Arc<[u8]>>,
+    /// The interface that heard it, set by scan_merged().
+    pub iface: Option<Arc<str>>,
+    /// Whether this is the BSS the interface is in (NL80211_BSS_STATUS),
+    /// from backends that report it.
+    pub status: Option<BssStatus>,
+    #[serde(skip)]
+    lazy: IeCache,
+}
+
+/// NL80211_BSS_STATUS: how the scanning interface is tied to a BSS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BssStatus {
+    Authenticated,
+    Associated,
+    IbssJoined,
+}
+
+impl BssStatus {
+    /// From the attribute's u32 (NL80211_BSS_STATUS_*).
+    pub fn from_nl(v: u32) -> Option<BssStatus> {
+        match v {
+            0 => Some(BssStatus::Authenticated),
+            1 => Some(BssStatus::Associated),
+            2 => Some(BssStatus::IbssJoined),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BssStatus::Authenticated => "authenticated",
+            BssStatus::Associated => "associated",
+            BssStatus::IbssJoined => "ibss_joined",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<BssStatus> {
+        Ok(match name {
+            "authenticated" => BssStatus::Authenticated,
+            "associated" => BssStatus::Associated,
+            "ibss_joined" => BssStatus::IbssJoined,
+            _ => bail!("unknown BSS status {name:?}"),
+        })
+    }
+}
+
+/// The row of the BSS the interface is associated with (or has joined,
+/// for an ad-hoc network), by the rows' own status.
+pub fn connected_row(rows: &[BssRow]) -> Option<&BssRow> {
+    rows.iter()
+        .find(|r| r.status == Some(BssStatus::Associated))
+        .or_else(|| rows.iter().find(|r| r.status == Some(BssStatus::IbssJoined)))
+}
+
+// Capability Information bits set by APs and by ad-hoc stations.
+const CAP_ESS: u16 = 1 << 0;
+const CAP_IBSS: u16 = 1 << 1;
+// Set when the BSS requires encryption; with no RSN or WPA element, WEP.
+const CAP_PRIVACY: u16 = 1 << 4;
+
+// Fields parsed out of `ies` on first access, one element at a time.
+#[derive(Debug, Clone, Default)]
+struct IeCache {
+    security: OnceLock<Security>,
+    operation: OnceLock<Option<Operation>>,
+    country: OnceLock<Option<[u8; 2]>>,
+    fingerprint: OnceLock<Option<u64>>,
+    generation: OnceLock<Option<u8>>,
+    load: OnceLock<Option<BssLoad>>,
+    p2p: OnceLock<bool>,
+    ibss: OnceLock<bool>,
+    mld: OnceLock<Option<[u8; 6]>>,
+    rates: OnceLock<PhyRates>,
+    mesh: OnceLock<Option<MeshConfig>>,
+    wps: OnceLock<Option<Wps>>,
+    interworking: OnceLock<Option<Interworking>>,
+    hs20: OnceLock<Option<u8>>,
+    pmf: OnceLock<Option<Pmf>>,
+    dtim: OnceLock<Option<u8>>,
+    intolerant: OnceLock<bool>,
+}
+
+impl BssRow {
+    /// Build a row from the raw pieces every backend can provide.
+    /// The channel is derived from the frequency and the SSID from the IEs;
+    /// every other IE is left unparsed until asked for.
+    pub fn from_parts(
+        bssid: Option<[u8; 6]>,
+        freq_mhz: Option<u32>,
+        signal_dbm: Option<f32>,
+        ies: Option<&[u8]>,
+    ) -> Self {
+        let mut row = BssRow {
+            ssid: None,
+            ssid_bytes: Vec::new(),
+            hidden: false,
+            bssid,
+            freq_mhz,
+            signal_dbm,
+            channel: freq_mhz.and_then(freq_to_channel),
+            seen: None,
+            age_ms: None,
+            cached: false,
+            capability: None,
+            beacon_interval: None,
+            ies: ies.map(Arc::from),
+            iface: None,
+            status: None,
+            lazy: IeCache::default(),
+        };
+        if let Some(ssid) = ies.and_then(|b| ie_find(b, 0)) {
+            row.set_ssid(ssid);
+            row.hidden &= !ies.is_some_and(|b| ies::has_mesh_id(&ie_list(b)));
+        }
+        row
+    }
+
+    /// Set the SSID from its raw bytes. An empty or all-zero one (how
+    /// APs hide their name) sets `hidden` and leaves the SSID "";
+    /// `ssid_bytes` keeps them as they were either way.
+    pub fn set_ssid(&mut self, raw: &[u8]) {
+        self.hidden = raw.iter().all(|&b| b == 0);
+        self.ssid = Some(intern_ssid(if self.hidden { b"" } else { raw }));
+        self.ssid_bytes = raw.to_vec();
+    }
+
+    /// Set `seen` and `age_ms` from nl80211's "last seen this many ms
+    /// ago".
+    pub fn seen_ms_ago(mut self, ms: Option<u32>) -> Self {
+        self.seen = ms.map(Stamp::ago_ms);
+        self.age_ms = ms;
+        self
+    }
+
+    fn parse_lazy<T>(&self, cell: &OnceLock<T>, f: impl FnOnce(&IeList) -> T) -> T
+    where
+        T: Copy,
+    {
+        *cell.get_or_init(|| f(&ie_list(self.ies.as_deref().unwrap_or_default())))
+    }
+
+    /// Advertised authentication (RSN / WPA elements), or WEP when neither
+    /// is present but the capability field asks for privacy.
+    pub fn security(&self) -> Security {
+        match self.parse_lazy(&self.lazy.security, ies::parse_security) {
+            Security::Open if self.capability.is_some_and(|c| c & CAP_PRIVACY != 0) => Security::Wep,
+            s => s,
+        }
+    }
+
+    /// Set the security regardless of the IEs, like with_p2p().
+    pub fn with_security(self, security: Security) -> Self {
+        let _ = self.lazy.security.set(security);
+        self
+    }
+
+    /// Management Frame Protection from the RSN capabilities; None
+    /// without an RSN element (open, WEP, WPA), where there is none.
+    pub fn pmf(&self) -> Option<Pmf> {
+        self.parse_lazy(&self.lazy.pmf, ies::parse_pmf)
+    }
+
+    /// Anyone can kick its clients off with forged deauthentication or
+    /// disassociation frames: it doesn't do PMF at all. A PMF-capable BSS
+    /// protects the clients that negotiate it, which current phones do.
+    pub fn deauth_vulnerable(&self) -> bool {
+        self.pmf().is_none_or(|p| p == Pmf::Disabled)
+    }
+
+    /// Set the PMF state regardless of the IEs, like with_p2p().
+    pub fn with_pmf(self, pmf: Option<Pmf>) -> Self {
+        let _ = self.lazy.pmf.set(pmf);
+        self
+    }
+
+    /// Width, primary/secondary and centre channel from the HT/VHT/HE
+    /// operation elements.
+    pub fn operation(&self) -> Option<Operation> {
+        self.parse_lazy(&self.lazy.operation, ies::parse_operation)
+    }
+
+    /// Operating channel width in MHz.
+    pub fn channel_width(&self) -> Option<u32> {
+        self.operation().map(|o| o.width_mhz)
+    }
+
+    /// Set the operating channel regardless of the IEs, like with_p2p().
+    pub fn with_operation(self, op: Operation) -> Self {
+        let _ = self.lazy.operation.set(Some(op));
+        self
+    }
+
+    /// The secondary 20 MHz channel of a 40 MHz or wider BSS.
+    pub fn secondary_channel(&self) -> Option<u32> {
+        let op = self.operation()?;
+        let primary = op.primary.or(self.channel)?;
+        primary.checked_add_signed(4 * op.secondary? as i32)
+    }
+
+    /// Centre channel of the whole operating channel, 40 MHz and wider.
+    pub fn center_channel(&self) -> Option<u32> {
+        self.operation()?.center
+    }
+
+    /// Country code from the Country element, e.g. "US".
+    pub fn country(&self) -> Option<[u8; 2]> {
+        self.parse_lazy(&self.lazy.country, ies::parse_country)
+    }
+
+    /// The whole Country element, with the channels it lists. Not cached:
+    /// only regcheck.rs asks.
+    pub fn country_info(&self) -> Option<ies::CountryInfo> {
+        ies::parse_country_info(&ie_list(self.ies.as_deref()?))
+    }
+
+    /// Model fingerprint of the IE layout (see apmodel.rs).
+    pub fn fingerprint(&self) -> Option<u64> {
+        self.parse_lazy(&self.lazy.fingerprint, apmodel::fingerprint)
+    }
+
+    /// Advertised Wi-Fi generation, 4 to 7.
+    pub fn wifi_generation(&self) -> Option<u8> {
+        self.parse_lazy(&self.lazy.generation, apmodel::generation)
+    }
+
+    /// wifi_generation() as a Generation, 6E on 6 GHz. None without IEs,
+    /// as nothing says whether the BSS is legacy.
+    pub fn generation(&self) -> Option<apmodel::Generation> {
+        self.ies.as_ref()?;
+        let on_6ghz = self.freq_mhz.is_some_and(|f| freq_band(f) == 4);
+        Some(apmodel::Generation::of(self.wifi_generation(), on_6ghz))
+    }
+
+    /// The rates and MCSs the rate and capability elements list.
+    pub fn phy_rates(&self) -> PhyRates {
+        self.parse_lazy(&self.lazy.rates, ies::parse_phy_rates)
+    }
+
+    /// Fastest PHY rate the BSS supports at its operating width, Mbit/s
+    /// (phyrate::max_rate_mbps).
+    pub fn max_phy_rate_mbps(&self) -> Option<f32> {
+        let band = self.freq_mhz.map_or(0, freq_band);
+        phyrate::max_rate_mbps(&self.phy_rates(), self.channel_width().unwrap_or(20), band)
+    }
+
+    /// Throughput a typical client could expect from the BSS where it was
+    /// heard, Mbit/s (phyrate::estimate_mbps). None without a signal or
+    /// rate elements.
+    pub fn estimated_throughput_mbps(&self) -> Option<f32> {
+        let band = self.freq_mhz.map_or(0, freq_band);
+        phyrate::estimate_mbps(&self.phy_rates(), self.channel_width().unwrap_or(20), band, self.signal_dbm?)
+    }
+
+    /// Station count and channel utilization the AP advertises, if any.
+    pub fn bss_load(&self) -> Option<BssLoad> {
+        self.parse_lazy(&self.lazy.load, ies::parse_bss_load)
+    }
+
+    /// A Wi-Fi Direct group (P2P element, or a "DIRECT-" SSID when the IEs
+    /// weren't kept): a cast target or printer that comes and goes, not an AP.
+    pub fn is_p2p(&self) -> bool {
+        self.parse_lazy(&self.lazy.p2p, ies::has_p2p) || self.ssid.as_deref().is_some_and(|s| s.starts_with("DIRECT-"))
+    }
+
+    /// Mark the row as a Wi-Fi Direct group (or not) regardless of its IEs,
+    /// e.g. for rows rebuilt from saved dicts.
+    pub fn with_p2p(self, p2p: bool) -> Self {
+        let _ = self.lazy.p2p.set(p2p);
+        self
+    }
+
+    /// The WPS element's state, None when WPS is off.
+    pub fn wps(&self) -> Option<Wps> {
+        self.parse_lazy(&self.lazy.wps, ies::parse_wps)
+    }
+
+    pub fn wps_enabled(&self) -> bool {
+        self.wps().is_some()
+    }
+
+    /// Set the WPS state regardless of the IEs, like with_p2p().
+    pub fn with_wps(self, wps: Option<Wps>) -> Self {
+        let _ = self.lazy.wps.set(wps);
+        self
+    }
+
+    /// The Interworking (802.11u) element: access network type, venue,
+    /// HESSID.
+    pub fn interworking(&self) -> Option<Interworking> {
+        self.parse_lazy(&self.lazy.interworking, ies::parse_interworking)
+    }
+
+    /// Passpoint (Hotspot 2.0) release from the HS2.0 Indication element.
+    pub fn passpoint_release(&self) -> Option<u8> {
+        self.parse_lazy(&self.lazy.hs20, ies::parse_hs20)
+    }
+
+    /// A Passpoint (Hotspot 2.0) AP: a carrier or venue hotspot clients
+    /// join with their SIM or operator profile, not a home network.
+    pub fn is_passpoint(&self) -> bool {
+        self.passpoint_release().is_some()
+    }
+
+    /// Mark the row as Passpoint (or not) regardless of its IEs, like
+    /// with_p2p().
+    pub fn with_passpoint(self, release: Option<u8>) -> Self {
+        let _ = self.lazy.hs20.set(release);
+        self
+    }
+
+    /// The security the way Android's ScanResult.capabilities (and WiGLE's
+    /// AuthMode) spell it: "[WPA2-PSK-CCMP][ESS]", "[WEP][ESS]", "[IBSS]".
+    /// Without the IEs only security() is known: "[WPA2-PSK][ESS]".
+    pub fn auth_mode(&self) -> String {
+        let security = self.security();
+        let mut s = match self.ies.as_deref() {
+            Some(ies) => ies::auth_mode(&ie_list(ies)),
+            None => match security {
+                Security::Open | Security::Wep => "",
+                Security::Wpa => "[WPA-PSK]",
+                Security::Wpa2 => "[WPA2-PSK]",
+                Security::Wpa2Wpa3 => "[WPA2-PSK+SAE]",
+                Security::Wpa3 => "[WPA2-SAE]",
+                Security::Enterprise => "[WPA2-EAP]",
+                Security::Owe => "[WPA2-OWE]",
+            }
+            .to_string(),
+        };
+        if security == Security::Wep {
+            s.push_str("[WEP]");
+        }
+        s.push_str(if self.is_ibss() {
+            "[IBSS]"
+        } else if self.is_mesh_point() {
+            "[MESH]"
+        } else {
+            "[ESS]"
+        });
+        s
+    }
+
+    /// An ad-hoc (IBSS) network: the capability field's IBSS bit, or the
+    /// IBSS Parameter Set element when the capability is unknown.
+    pub fn is_ibss(&self) -> bool {
+        match self.capability {
+            Some(cap) => cap & CAP_IBSS != 0,
+            None => self.parse_lazy(&self.lazy.ibss, ies::has_ibss_params),
+        }
+    }
+
+    /// Mark the row as ad-hoc (or not) regardless of its IEs, like
+    /// with_p2p().
+    pub fn with_ibss(self, ibss: bool) -> Self {
+        let _ = self.lazy.ibss.set(ibss);
+        self
+    }
+
+    /// An infrastructure BSS (an AP): the capability field's ESS bit, or
+    /// neither ad-hoc nor a mesh point when the capability is unknown.
+    pub fn is_ess(&self) -> bool {
+        match self.capability {
+            Some(cap) => cap & CAP_ESS != 0,
+            None => !self.is_ibss() && !self.is_mesh_point(),
+        }
+    }
+
+    /// The capability field's privacy bit: the BSS requires encryption.
+    /// From the security elements when the capability is unknown.
+    pub fn privacy(&self) -> bool {
+        match self.capability {
+            Some(cap) => cap & CAP_PRIVACY != 0,
+            None => self.security() != Security::Open,
+        }
+    }
+
+    /// The DTIM period from the TIM element, in beacons; only beacons
+    /// carry one.
+    pub fn dtim_period(&self) -> Option<u8> {
+        self.parse_lazy(&self.lazy.dtim, ies::parse_dtim_period)
+    }
+
+    /// The BSS forbids 40 MHz channels around it on 2.4 GHz (HT 40 MHz
+    /// intolerant, or asking for 20 MHz in a 20/40 BSS Coexistence
+    /// element).
+    pub fn forty_mhz_intolerant(&self) -> bool {
+        self.parse_lazy(&self.lazy.intolerant, ies::forty_mhz_intolerant)
+    }
+
+    /// Set the DTIM period regardless of the IEs, like with_p2p().
+    pub fn with_dtim_period(self, period: Option<u8>) -> Self {
+        let _ = self.lazy.dtim.set(period);
+        self
+    }
+
+    /// MLD address shared by all links of a Wi-Fi 7 multi-link AP.
+    pub fn mld_addr(&self) -> Option<[u8; 6]> {
+        self.parse_lazy(&self.lazy.mld, ies::parse_mld_addr)
+    }
+
+    /// Set the MLD address regardless of the IEs, like with_p2p().
+    pub fn with_mld(self, mld: [u8; 6]) -> Self {
+        let _ = self.lazy.mld.set(Some(mld));
+        self
+    }
+
+    /// The Mesh Configuration of an 802.11s mesh point; None for an AP.
+    pub fn mesh(&self) -> Option<MeshConfig> {
+        self.parse_lazy(&self.lazy.mesh, ies::parse_mesh_config)
+    }
+
+    /// An 802.11s mesh point (Mesh Configuration element) rather than an
+    /// infrastructure AP.
+    pub fn is_mesh_point(&self) -> bool {
+        self.mesh().is_some()
+    }
+
+    /// Set the mesh configuration regardless of the IEs, like with_p2p().
+    pub fn with_mesh(self, mesh: MeshConfig) -> Self {
+        let _ = self.lazy.mesh.set(Some(mesh));
+        self
+    }
+
+    /// The Mesh ID of a mesh point, bytes that aren't UTF-8 as U+FFFD. Not
+    /// cached, and None once the IEs are dropped.
+    pub fn mesh_id(&self) -> Option<String> {
+        let id = ies::parse_mesh_id(&ie_list(self.ies.as_deref()?))?;
+        Some(String::from_utf8_lossy(&id).into_owned())
+    }
+
+    /// The Multiple BSSID set this BSS transmits for, if it is the
+    /// transmitting BSS of one. Not cached: only devgroup::learn() asks.
+    pub fn multiple_bssid(&self) -> Option<MultipleBssid> {
+        ies::parse_multiple_bssid(&ie_list(self.ies.as_deref()?))
+    }
+
+    /// Copy without the IE blob, for long-lived storage. Fields that were
+    /// already parsed stay cached.
+    pub fn without_ies(&self) -> BssRow {
+        BssRow {
+            ies: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// What to ask the radio for in a scan. The default is an active
+/// wildcard scan of every channel that keeps what the kernel already knew.
+/// Only the raw nl80211 backend triggers scans, so only it listens
+/// passively, probes fewer channels, flushes or randomizes its address;
+/// every backend leaves out the BSSs outside `freqs`, `band` and `ssids`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Listen for beacons only, sending no probe requests.
+    pub passive: bool,
+    /// Scan only these frequencies (MHz).
+    pub freqs: Vec<u32>,
+    /// Scan only this freq_band(), on the channels the radio supports.
+    pub band: Option<u8>,
+    /// Probe for these SSIDs instead of the wildcard; only they are kept.
+    pub ssids: Vec<Vec<u8>>,
+    /// Have the kernel drop its older BSS entries (NL80211_SCAN_FLAG_FLUSH),
+    /// so BSSs that went away don't linger in the results.
+    pub flush: bool,
+    /// Send probe requests from a random MAC address
+    /// (NL80211_SCAN_FLAG_RANDOM_ADDR) so they don't reveal the device's.
+    pub random_mac: Option<RandomMac>,
+    /// What to do when the radio is already scanning.
+    pub busy: BusyPolicy,
+    /// Don't trigger: return the kernel's cached BSS table, which needs no
+    /// CAP_NET_ADMIN.
+    pub cached_only: bool,
+}
+
+/// Random scan address: bits set in `mask` are taken from `addr`, the
+/// others are random. An all-zero mask leaves the whole address to the
+/// kernel (random, locally administered).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RandomMac {
+    pub addr: [u8; 6],
+    pub mask: [u8; 6],
+}
+
+/// What a scan does when its trigger finds the radio busy (EBUSY or
+/// EINPROGRESS: wpa_supplicant or the OS is scanning already): try again
+/// up to `retries` times, waiting `backoff`, then twice that, and so on,
+/// or with `join` take the results of the scan in progress instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusyPolicy {
+    pub retries: u32,
+    pub backoff: Duration,
+    pub join: bool,
+}
+
+impl Default for BusyPolicy {
+    fn default() -> Self {
+        BusyPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(250),
+            join: false,
+        }
+    }
+}
+
+// The longest wait between two tries; a scan takes about 4 s.
+const BUSY_BACKOFF_MAX: Duration = Duration::from_secs(4);
+
+/// What to do after a failed scan trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    /// Trigger again after this long.
+    After(Duration),
+    /// Wait for the scan in progress and use its results.
+    Join,
+}
+
+impl BusyPolicy {
+    /// What to do about try `attempt` (0-based) of a scan trigger failing
+    /// with `e`: Err(e) unless the radio was busy, and an error saying so
+    /// once the retries are used up.
+    pub fn retry(&self, e: anyhow::Error, attempt: u32) -> Result<Retry> {
+        if !is_busy(&e) {
+            return Err(e);
+        }
+        if self.join {
+            debug!("radio busy, joining the scan in progress");
+            return Ok(Retry::Join);
+        }
+        if attempt >= self.retries {
+            // Context, so the EBUSY stays visible to errno_of() / kind_of().
+            let msg = format!("the radio is busy with another scan; gave up after {attempt} retries ({e})");
+            return Err(e.context(msg));
+        }
+        let wait = self.backoff.saturating_mul(1 << attempt.min(16)).min(BUSY_BACKOFF_MAX);
+        debug!(attempt, wait_ms = wait.as_millis() as u64, "radio busy, retrying");
+        Ok(Retry::After(wait))
+    }
+}
+
+/// Whether `e` is the kernel refusing a scan because one is running.
+pub fn is_busy(e: &anyhow::Error) -> bool {
+    matches!(netlink::errno_of(e), Some(libc::EBUSY | libc::EINPROGRESS))
+}
+
+/// Whether `e` is the kernel refusing a scan for lack of privileges
+/// (CAP_NET_ADMIN).
+pub fn not_permitted(e: &anyhow::Error) -> bool {
+    matches!(netlink::errno_of(e), Some(libc::EPERM | libc::EACCES))
+}
+
+/// Why a scan returned the kernel's cached BSS table instead of
+/// triggering a new scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoTrigger {
+    /// ScanOptions::cached_only asked for it.
+    CachedOnly,
+    /// The trigger was refused with EPERM; not privileged enough.
+    NotPermitted,
+    /// The backend never triggers (neli-wifi).
+    Backend,
+}
+
+impl NoTrigger {
+    pub fn name(self) -> &'static str {
+        match self {
+            NoTrigger::CachedOnly => "cached_only",
+            NoTrigger::NotPermitted => "not_permitted",
+            NoTrigger::Backend => "backend",
+        }
+    }
+}
+
+/// How fresh the last scan was.
+#[derive(Debug, Clone, Copy)]
+pub struct ScanInfo {
+    /// When it started.
+    pub at: Stamp,
+    /// None if a scan was triggered, so every BSS was just heard.
+    pub untriggered: Option<NoTrigger>,
+    /// Seconds between the start and the newest / oldest sighting in it;
+    /// 0 for sightings made during the scan, None without any.
+    pub newest_age: Option<f64>,
+    pub oldest_age: Option<f64>,
+}
+
+static LAST_SCAN: Mutex<Option<ScanInfo>> = Mutex::new(None);
+
+/// The ScanInfo of the last scan that succeeded, if any. With several
+/// radios scanned at once, that of the last one to finish.
+pub fn last_scan() -> Option<ScanInfo> {
+    *LAST_SCAN.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl ScanOptions {
+    /// Err if the options contradict each other.
+    pub fn validate(&self) -> Result<()> {
+        if self.passive && !self.ssids.is_empty() {
+            bail!("a passive scan can't probe for SSIDs");
+        }
+        if let Some(s) = self.ssids.iter().find(|s| s.len() > 32) {
+            bail!("SSID {:?} is longer than 32 bytes", String::from_utf8_lossy(s));
+        }
+        if let Some(b) = self.band {
+            if let Some(f) = self.freqs.iter().find(|&&f| freq_band(f) != b) {
+                bail!("{f} MHz is not in the {} band", band_name(b));
+            }
+        }
+        // The kernel doesn't check: a fixed group bit makes every probe
+        // request invalid, a fixed global bit claims a vendor's address.
+        if let Some(r) = self.random_mac {
+            let fixed = r.mask[0] & 0x03;
+            if r.addr[0] & fixed != 0x02 & fixed {
+                bail!("a random scan MAC must be unicast and locally administered");
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether only some of the BSSs are asked for.
+    pub fn restricts(&self) -> bool {
+        !self.freqs.is_empty() || self.band.is_some() || !self.ssids.is_empty()
+    }
+
+    /// Whether `r` is one of the BSSs asked for.
+    pub fn wants(&self, r: &BssRow) -> bool {
+        let freq = match r.freq_mhz {
+            Some(f) => {
+                (self.freqs.is_empty() || self.freqs.contains(&f)) && self.band.is_none_or(|b| freq_band(f) == b)
+            }
+            None => self.freqs.is_empty() && self.band.is_none(),
+        };
+        let ssid = self.ssids.is_empty() || self.ssids.contains(&r.ssid_bytes);
+        freq && ssid
+    }
+}
+
+/// Where scan data comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// neli-wifi: reads the kernel's current BSS table without triggering.
+    NeliWifi,
+    /// Raw nl80211: triggers a fresh scan, waits for it, then dumps.
+    RawNl80211,
+    /// Fixture data instead of the radio (see mock.rs).
+    Mock,
+    /// wpa_supplicant's control socket (see wpa_ctrl.rs), for when nl80211
+    /// is off limits; scans, the connected BSSID and its link only.
+    WpaSupplicant,
+    /// What the Android app pushes from WifiManager (see android.rs), for
+    /// stock Android; same scope as WpaSupplicant.
+    Android,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 5] =
+        [Backend::NeliWifi, Backend::RawNl80211, Backend::Mock, Backend::WpaSupplicant, Backend::Android];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::NeliWifi => "neli-wifi",
+            Backend::RawNl80211 => "raw-nl80211",
+            Backend::Mock => "mock",
+            Backend::WpaSupplicant => "wpa-supplicant",
+            Backend::Android => "android",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "neli-wifi" => Ok(Backend::NeliWifi),
+            "raw-nl80211" => Ok(Backend::RawNl80211),
+            "mock" => Ok(Backend::Mock),
+            "wpa-supplicant" => Ok(Backend::WpaSupplicant),
+            "android" => Ok(Backend::Android),
+            other => bail!(
+                "unknown backend {other:?} (expected \"neli-wifi\", \"raw-nl80211\", \"mock\", \"wpa-supplicant\" \
+                 or \"android\")"
+            ),
+        }
+    }
+}
+
+// BACKEND_UNSET until set_backend() or the first backend() picks one.
+const BACKEND_UNSET: u8 = u8::MAX;
+static BACKEND: AtomicU8 = AtomicU8::new(BACKEND_UNSET);
+
+fn backend_code(b: Backend) -> u8 {
+    match b {
+        Backend::NeliWifi => 0,
+        Backend::RawNl80211 => 1,
+        Backend::Mock => 2,
+        Backend::WpaSupplicant => 3,
+        Backend::Android => 4,
+    }
+}
+
+/// Select the backend used by every following call.
+pub fn set_backend(b: Backend) {
+    BACKEND.store(backend_code(b), Ordering::Relaxed);
+}
+
+// neli-wifi (raw nl80211 in builds without it), unless nl80211 can't be
+// opened (SELinux on Android): then a wpa_supplicant control socket if
+// there is one, else on Android what the app pushes.
+fn default_backend() -> Backend {
+    let nl = if Backend::NeliWifi.built() { Backend::NeliWifi } else { Backend::RawNl80211 };
+    if netlink::Nl80211::shared().is_ok() {
+        nl
+    } else if wpa_ctrl::available() {
+        Backend::WpaSupplicant
+    } else if cfg!(target_os = "android") {
+        Backend::Android
+    } else {
+        nl
+    }
+}
+
+/// Currently selected backend; the first call without a set_backend()
+/// picks the default.
+pub fn backend() -> Backend {
+    let mut v = BACKEND.load(Ordering::Relaxed);
+    if v == BACKEND_UNSET {
+        let picked = backend_code(default_backend());
+        v = match BACKEND.compare_exchange(BACKEND_UNSET, picked, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => picked,
+            // set_backend() got there first.
+            Err(current) => current,
+        };
+    }
+    match v {
+        1 => Backend::RawNl80211,
+        2 => Backend::Mock,
+        3 => Backend::WpaSupplicant,
+        4 => Backend::Android,
+        _ => Backend::NeliWifi,
+    }
+}
+
+/// How Wi-Fi Direct groups (BssRow::is_p2p) count in channel scoring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum P2pPolicy {
+    /// Like any other AP.
+    Include,
+    /// At P2P_WEIGHT of an AP's weight, and left out of plain AP counts.
+    Downweight,
+    /// Not at all.
+    Exclude,
+}
+
+impl P2pPolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            P2pPolicy::Include => "include",
+            P2pPolicy::Downweight => "downweight",
+            P2pPolicy::Exclude => "exclude",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "include" => Ok(P2pPolicy::Include),
+            "downweight" => Ok(P2pPolicy::Downweight),
+            "exclude" => Ok(P2pPolicy::Exclude),
+            other => bail!("unknown P2P policy {other:?} (expected \"include\", \"downweight\" or \"exclude\")"),
+        }
+    }
+}
+
+static P2P_POLICY: AtomicU8 = AtomicU8::new(1);
+
+/// Select how every following computation weighs Wi-Fi Direct groups.
+pub fn set_p2p_policy(p: P2pPolicy) {
+    let v = match p {
+        P2pPolicy::Include => 0,
+        P2pPolicy::Downweight => 1,
+        P2pPolicy::Exclude => 2,
+    };
+    P2P_POLICY.store(v, Ordering::Relaxed);
+}
+
+/// Current P2P policy; Downweight unless changed.
+pub fn p2p_policy() -> P2pPolicy {
+    match P2P_POLICY.load(Ordering::Relaxed) {
+        0 => P2pPolicy::Include,
+        2 => P2pPolicy::Exclude,
+        _ => P2pPolicy::Downweight,
+    }
+}
+
+static EXCLUDE_IBSS: AtomicBool = AtomicBool::new(false);
+
+/// Leave ad-hoc networks (BssRow::is_ibss) out of channel counts and
+/// scoring from now on, or count them again.
+pub fn set_exclude_ibss(exclude: bool) {
+    EXCLUDE_IBSS.store(exclude, Ordering::Relaxed);
+}
+
+pub fn exclude_ibss() -> bool {
+    EXCLUDE_IBSS.load(Ordering::Relaxed)
+}
+
+/// Whether the P2P policy or the IBSS setting leaves `r` out of channel
+/// counts and scoring altogether.
+pub fn excluded(r: &BssRow) -> bool {
+    (p2p_policy() == P2pPolicy::Exclude && r.is_p2p()) || (exclude_ibss() && r.is_ibss())
+}
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Turn dry-run mode on or off for everything that follows. Operations
+/// that reconfigure an interface or AP (capture::capture()) check
+/// dry_run() first and, when set, validate their inputs and report what
+/// they would do without doing it.
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether dry-run mode is on; off unless set_dry_run() turned it on.
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+// Converts the first 6 bytes of a slice to a MAC array
+pub fn vec_to_mac(v: &[u8]) -> Option<[u8; 6]> {
+    if v.len() < 6 {
+        return None;
+    }
+    let mut out = [0u8; 6];
+    out.copy_from_slice(&v[..6]);
+    Some(out)
+}
+
+pub fn format_mac(bytes: &[u8; 6]) -> String {
+    let mut s = String::with_capacity(17);
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            let _ = write!(s, ":");
+        }
+        let _ = write!(s, "{:02x}", b);
+    }
+    s
+}
+
+/// Inverse of format_mac; also accepts '-' separators and upper case.
+pub fn parse_mac(s: &str) -> Result<[u8; 6]> {
+    let mut mac = [0u8; 6];
+    let mut parts = s.split([':', '-']);
+    for b in mac.iter_mut() {
+        let p = parts.next().unwrap_or_default();
+        *b = u8::from_str_radix(p, 16).map_err(|_| anyhow::anyhow!("invalid MAC address: {s:?}"))?;
+    }
+    if parts.next().is_some() {
+        bail!("invalid MAC address: {s:?}");
+    }
+    Ok(mac)
+}
+
+/// One information element out of a beacon / probe response.
+#[derive(Debug, Clone, Copy)]
+pub struct Ie<'a> {
+    pub id: u8,
+    pub data: &'a [u8],
+}
+
+/// Every IE of a BSS, in order. Beacons usually carry 15-25 elements, so
+/// the list lives on the stack.
+pub type IeList<'a> = SmallVec<[Ie<'a>; 32]>;
+
+/// Split an IE blob into its elements. A truncated tail is dropped.
+pub fn ie_list(mut ies: &[u8]) -> IeList<'_> {
+    let mut out = IeList::new();
+    // IEs are TLVs: [id, len, value...]
+    while ies.len() >= 2 {
+        let id = ies[0];
+        let len = ies[1] as usize;
+        ies = &ies[2..];
+
+        if len > ies.len() {
+            break;
+        }
+        out.push(Ie { id, data: &ies[..len] });
+        ies = &ies[len..];
+    }
+    out
+}
+
+// SSIDs repeat a lot (every radio of a mesh node, every poll of the same
+// street), so rows share one Arc<str> per distinct SSID. The table is
+// simply dropped if it ever grows past SSID_INTERN_MAX.
+const SSID_INTERN_MAX: usize = 4096;
+type SsidTable = HashMap<Box<[u8]>, Arc<str>>;
+static SSIDS: OnceLock<Mutex<SsidTable>> = OnceLock::new();
+
+pub fn intern_ssid(raw: &[u8]) -> Arc<str> {
+    let mut map = SSIDS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(s) = map.get(raw) {
+        perf::add(&perf::SSID_HITS, 1);
+        return s.clone();
+    }
+    perf::add(&perf::SSID_MISSES, 1);
+    if map.len() >= SSID_INTERN_MAX {
+        map.clear();
+    }
+    let s: Arc<str> = String::from_utf8_lossy(raw).into();
+    map.insert(raw.into(), s.clone());
+    s
+}
+
+/// Body of the first IE with `id`, without splitting the whole blob.
+/// The SSID (IE 0) is almost always first, so this is what scans use.
+pub fn ie_find(mut ies: &[u8], id: u8) -> Option<&[u8]> {
+    while ies.len() >= 2 {
+        let len = ies[1] as usize;
+        let body = ies.get(2..2 + len)?;
+        if ies[0] == id {
+            return Some(body);
+        }
+        ies = &ies[2 + len..];
+    }
+    None
+}
+
+// Channel mapping for 2.4, 5 and 6 GHz. Anything else has no channel.
+pub fn freq_to_channel(freq_mhz: u32) -> Option<u32> {
+    match freq_mhz {
+        2484 => Some(14),
+        2412..=2472 if freq_mhz % 5 == 2 => Some((freq_mhz - 2407) / 5),
+        5160..=5885 if freq_mhz.is_multiple_of(5) => Some((freq_mhz - 5000) / 5),
+        // 6 GHz channel 2 sits below channel 1.
+        5935 => Some(2),
+        5955..=7115 if freq_mhz.is_multiple_of(5) => Some((freq_mhz - 5950) / 5),
+        // Off the 5 MHz channel raster.
+        _ => None,
+    }
+}
+
+// Centre frequency of channel `ch` in freq_band() `band`; the inverse of
+// freq_to_channel().
+pub fn channel_to_freq(band: u8, ch: u32) -> Option<u32> {
+    match (band, ch) {
+        (1, 14) => Some(2484),
+        (1, 1..=13) => Some(2407 + ch * 5),
+        (2, 32..=177) => Some(5000 + ch * 5),
+        (4, 2) => Some(5935),
+        (4, 1..=233) => Some(5950 + ch * 5),
+        _ => None,
+    }
+}
+
+// Check which frequency we are on and correlate it to the correct band.
+pub fn freq_band(freq_mhz: u32) -> u8 {
+    // 1 = 2.4 GHz, 2 = 5 GHz, 3 = All others, 4 = 6 GHz
+    match freq_mhz {
+        2401..=2495 => 1,
+        5150..=5895 => 2,
+        5925..=7125 => 4,
+        _ => 3,
+    }
+}
+
+// Label for a freq_band() id, as shown to Python.
+pub fn band_name(band: u8) -> &'static str {
+    match band {
+        1 => "2.4GHz",
+        2 => "5GHz",
+        4 => "6GHz",
+        _ => "other",
+    }
+}
+
+/// freq_band() id for a band name as band_name() spells it; "2.4", "5"
+/// and "6" work too.
+pub fn band_from_name(name: &str) -> Result<u8> {
+    Ok(match name.trim_end_matches("GHz").trim_end_matches(' ') {
+        "2.4" => 1,
+        "5" => 2,
+        "6" => 4,
+        "other" => 3,
+        _ => bail!("unknown band {name:?} (expected \"2.4GHz\", \"5GHz\", \"6GHz\" or \"other\")"),
+    })
+}
+
+/// 6 GHz Preferred Scanning Channels: every fourth 20 MHz channel (one
+/// per 80 MHz block), the only ones clients without RNR probe on, so a
+/// 6 GHz AP that wants to be found should have its primary on one.
+pub const CHANNELS_6_PSC: [u32; 15] = [5, 21, 37, 53, 69, 85, 101, 117, 133, 149, 165, 181, 197, 213, 229];
+
+pub fn is_psc(ch: u32) -> bool {
+    CHANNELS_6_PSC.contains(&ch)
+}
+
+/// Whether two BSSIDs are likely radios of the same device: by the
+/// devices listed from Python, the Multiple BSSID sets seen in scans, a
+/// Python callback, then the vendor's rule (devgroup.rs). Unconfigured,
+/// bytes 1..=4 matching is enough (Rule::MiddleBytes: only the first and
+/// last differ on my Ubiquiti routers).
+pub fn same_device(a: &[u8; 6], b: &[u8; 6]) -> bool {
+    devgroup::same_device(a, b)
+}
+
+// MLD address of the connected AP, so its other links can be skipped as
+// interference too.
+fn connected_mld(rows: &[BssRow], connected: Option<&[u8; 6]>) -> Option<[u8; 6]> {
+    let c = connected?;
+    rows.iter().find(|r| r.bssid.as_ref() == Some(c))?.mld_addr()
+}
+
+// -------------------- Public internal APIs --------------------
+
+/// Called with each BSS of a scan as soon as it has been parsed.
+pub type RowSink = Box<dyn FnMut(&mut BssRow) + Send>;
+
+/// One item of a streamed scan, in the order the kernel reported them.
+#[derive(Debug)]
+pub enum ScanEvent {
+    Row(Box<BssRow>),
+    /// The scan finished; nothing follows.
+    Done,
+    /// The scan failed; nothing follows.
+    Failed(anyhow::Error),
+}
+
+// Run one scan on `b` (on `ifindex`, else the first Wi-Fi interface),
+// feeding the rows `opts` wants to `on_row` as they arrive, then
+// record_scan() it.
+async fn scan_each(b: Backend, ifindex: Option<u32>, opts: ScanOptions, on_row: RowSink) -> Result<Vec<BssRow>> {
+    let rows = scan_radio(b, ifindex, opts.clone(), on_row).await?;
+    record_scan(&rows, &opts);
+    Ok(rows)
+}
+
+// scan_each() without the recording.
+#[tracing::instrument(name = "scan", skip_all, fields(backend = b.name(), ifindex = ?ifindex))]
+async fn scan_radio(b: Backend, ifindex: Option<u32>, opts: ScanOptions, mut on_row: RowSink) -> Result<Vec<BssRow>> {
+    opts.validate()?;
+    let start = Instant::now();
+    let started = Stamp::now();
+    let keep = opts.clone();
+    let on_row: RowSink = Box::new(move |row| {
+        row.cached = row.seen.is_some_and(|s| s.mono < started.mono);
+        if keep.wants(row) {
+            on_row(row)
+        }
+    });
+    let (mut rows, untriggered) = b
+        .provider()
+        .scan(ifindex, &opts, on_row)
+        .await
+        .inspect_err(|e| debug!("failed: {e:#}"))?;
+    let total = rows.len();
+    rows.retain(|r| opts.wants(r));
+    perf::record("scan", b.name(), start.elapsed());
+    debug!(
+        rows = rows.len(),
+        filtered = total - rows.len(),
+        source = untriggered.map_or("triggered", NoTrigger::name),
+        ms = start.elapsed().as_millis() as u64,
+        "done"
+    );
+    let ages = rows.iter().filter_map(|r| r.seen).map(|s| (started.mono - s.mono).max(0.0));
+    let (newest_age, oldest_age) = ages.fold((None, None), |(lo, hi): (Option<f64>, Option<f64>), a| {
+        (Some(lo.map_or(a, |l| l.min(a))), Some(hi.map_or(a, |h| h.max(a))))
+    });
+    *LAST_SCAN.lock().unwrap_or_else(|e| e.into_inner()) = Some(ScanInfo {
+        at: started,
+        untriggered,
+        newest_age,
+        oldest_age,
+    });
+    Ok(rows)
+}
+
+// Every scan is recorded in the history store (unless `opts` left BSSs
+// out, which would look like they went away), geotagged while a position
+// is set and streamed to the shared-memory ring if one is open. Its
+// Multiple BSSID sets go to devgroup.
+fn record_scan(rows: &[BssRow], opts: &ScanOptions) {
+    if !opts.restricts() {
+        history::record(rows);
+    }
+    devgroup::learn(rows);
+    geo::record(rows);
+    ring::push_rows(rows);
+}
+
+/// Fresh scan of all BSSs visible from the Wi-Fi interface.
+pub fn scan_all_bss() -> Result<Vec<BssRow>> {
+    scan_all_bss_until(&Cancel::none())
+}
+
+/// scan_all_bss(), abandoned as soon as `cancel` is set.
+pub fn scan_all_bss_until(cancel: &Cancel) -> Result<Vec<BssRow>> {
+    scan_with(&ScanOptions::default(), cancel)
+}
+
+/// scan_all_bss_until() as `opts` asks.
+pub fn scan_with(opts: &ScanOptions, cancel: &Cancel) -> Result<Vec<BssRow>> {
+    block_on(cancel.run(scan_each(backend(), None, opts.clone(), Box::new(|_| {}))))
+}
+
+/// The interfaces scan_merged() scans: the station ones, one per radio
+/// (two interfaces of one wiphy hear the same thing, and the second
+/// trigger would only get EBUSY).
+pub fn scan_interfaces() -> Result<Vec<WifiIface>> {
+    let mut radios = Vec::new();
+    let mut out: Vec<WifiIface> = Vec::new();
+    for i in list_interfaces()? {
+        if i.iftype != netlink::IFTYPE_STATION {
+            continue;
+        }
+        if let Some(w) = i.wiphy {
+            if radios.contains(&w) {
+                continue;
+            }
+            radios.push(w);
+        }
+        out.push(i);
+    }
+    Ok(out)
+}
+
+/// scan_with() on every scan_interfaces() interface at once, merged into
+/// one list: a BSS heard on several keeps its strongest sighting, and
+/// every row's `iface` says which interface that was. Rows come in
+/// interface order. An interface whose scan fails is left out; only if
+/// all of them fail does this fail, with the first error. Recorded in the
+/// history store as one scan.
+pub fn scan_merged(opts: &ScanOptions, cancel: &Cancel) -> Result<Vec<BssRow>> {
+    let b = backend();
+    let ifaces = scan_interfaces()?;
+    if ifaces.is_empty() {
+        bail!(WifiError::NoInterface("no Wi-Fi station interface found".into()));
+    }
+    let start = Instant::now();
+    let scans = block_on(cancel.run(async {
+        let mut scans = Vec::with_capacity(ifaces.len());
+        if b == Backend::Mock {
+            // The fixture hands its scans out in call order; one after the
+            // other, each interface gets the same one every run.
+            for _ in &ifaces {
+                scans.push(scan_radio(b, None, opts.clone(), Box::new(|_| {})).await);
+            }
+        } else {
+            let tasks: Vec<_> = ifaces
+                .iter()
+                .map(|i| runtime().spawn(scan_radio(b, Some(i.ifindex), opts.clone(), Box::new(|_| {}))))
+                .collect();
+            for t in tasks {
+                scans.push(t.await.map_err(anyhow::Error::from).and_then(|r| r));
+            }
+        }
+        Ok(scans)
+    }))?;
+
+    let mut first_err = None;
+    let mut rows: Vec<BssRow> = Vec::new();
+    let mut by_bssid: HashMap<[u8; 6], usize> = HashMap::new();
+    for (iface, scan) in ifaces.iter().zip(scans) {
+        let scan = match scan {
+            Ok(scan) => scan,
+            Err(e) => {
+                first_err.get_or_insert(anyhow!("scan on {}: {e:#}", iface.name));
+                continue;
+            }
+        };
+        let name: Arc<str> = Arc::from(iface.name.as_str());
+        for mut r in scan {
+            r.iface = Some(name.clone());
+            let Some(i) = r.bssid.and_then(|m| by_bssid.get(&m).copied()) else {
+                if let Some(m) = r.bssid {
+                    by_bssid.insert(m, rows.len());
+                }
+                rows.push(r);
+                continue;
+            };
+            // Heard by this scan if any interface heard it.
+            r.cached &= rows[i].cached;
+            if r.signal_dbm > rows[i].signal_dbm {
+                rows[i] = r;
+            } else {
+                rows[i].cached = r.cached;
+            }
+        }
+    }
+    if rows.is_empty() {
+        if let Some(e) = first_err {
+            return Err(e);
+        }
+    }
+    perf::record("scan_merged", b.name(), start.elapsed());
+    record_scan(&rows, opts);
+    Ok(rows)
+}
+
+/// One BSS over the scans of scan_n().
+#[derive(Debug, Clone)]
+pub struct BssAggregate {
+    /// Its most recent sighting.
+    pub row: BssRow,
+    /// In how many of the scans it showed up.
+    pub seen: u32,
+    pub mean_dbm: Option<f32>,
+    pub max_dbm: Option<f32>,
+    pub min_dbm: Option<f32>,
+}
+
+/// `times` scans started `interval` apart (start to start, so a slow scan
+/// doesn't shift the rest), aggregated per BSSID, strongest mean first.
+/// Reports progress after every scan.
+pub fn scan_n(times: u32, interval: Duration, progress: &mut Progress) -> Result<Vec<BssAggregate>> {
+    let scans = scans_apart(times, interval, progress)?;
+
+    let mut by_bssid: HashMap<[u8; 6], (BssAggregate, Vec<f32>)> = HashMap::new();
+    for row in scans.into_iter().flatten() {
+        let Some(mac) = row.bssid else { continue };
+        let sig = row.signal_dbm;
+        let (agg, sigs) = by_bssid.entry(mac).or_insert_with(|| {
+            let agg = BssAggregate {
+                row: row.clone(),
+                seen: 0,
+                mean_dbm: None,
+                max_dbm: None,
+                min_dbm: None,
+            };
+            (agg, Vec::new())
+        });
+        agg.seen += 1;
+        agg.row = row;
+        sigs.extend(sig);
+    }
+
+    let mut out: Vec<BssAggregate> = by_bssid
+        .into_values()
+        .map(|(mut agg, sigs)| {
+            if !sigs.is_empty() {
+                agg.mean_dbm = Some(sigs.iter().sum::<f32>() / sigs.len() as f32);
+                agg.max_dbm = sigs.iter().copied().reduce(f32::max);
+                agg.min_dbm = sigs.iter().copied().reduce(f32::min);
+            }
+            agg
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        let key = |x: &BssAggregate| x.mean_dbm.unwrap_or(f32::NEG_INFINITY);
+        key(b).total_cmp(&key(a))
+    });
+    Ok(out)
+}
+
+/// Weight of the newest scan in scan_average()'s moving average.
+pub const DEFAULT_EWMA_ALPHA: f32 = 0.3;
+
+/// `times` scans started `interval` apart, as one snapshot: a row per
+/// BSSID, its latest sighting with the signal replaced by an exponentially
+/// weighted moving average over the scans that heard it (`alpha` the
+/// weight of each new sample, 0 < alpha <= 1), so one 15 dB swing doesn't
+/// flip the best channel. The channel survey and ChannelLimits are
+/// refreshed as by ScanSnapshot::capture().
+pub fn scan_average(times: u32, interval: Duration, alpha: f32, progress: &mut Progress) -> Result<ScanSnapshot> {
+    if !(alpha > 0.0 && alpha <= 1.0) {
+        bail!("alpha must be within (0, 1], not {alpha}");
+    }
+    let scans = scans_apart(times, interval, progress)?;
+
+    let mut rows: Vec<BssRow> = Vec::new();
+    let mut index: HashMap<[u8; 6], usize> = HashMap::new();
+    for row in scans.into_iter().flatten() {
+        let Some(mac) = row.bssid else { continue };
+        let Some(&i) = index.get(&mac) else {
+            index.insert(mac, rows.len());
+            rows.push(row);
+            continue;
+        };
+        let avg = match (rows[i].signal_dbm, row.signal_dbm) {
+            (Some(avg), Some(sig)) => Some(alpha * sig + (1.0 - alpha) * avg),
+            (avg, sig) => sig.or(avg),
+        };
+        rows[i] = BssRow { signal_dbm: avg, ..row };
+    }
+
+    chansurvey::refresh(backend(), None);
+    ChannelLimits::refresh(backend(), None);
+    let connected = connected_in(backend(), None, &rows)?;
+    Ok(ScanSnapshot::new(rows, connected))
+}
+
+// The rows of `times` scans started `interval` apart, reporting progress
+// after each.
+fn scans_apart(times: u32, interval: Duration, progress: &mut Progress) -> Result<Vec<Vec<BssRow>>> {
+    let b = backend();
+    let cancel = progress.cancel().clone();
+    block_on(async move {
+        let start = tokio::time::Instant::now();
+        let mut scans = Vec::with_capacity(times as usize);
+        progress.report(0.0, "scan", &format!("scan 1/{times}"))?;
+        for i in 0..times {
+            let next = start + interval * i;
+            cancel
+                .run(async {
+                    tokio::time::sleep_until(next).await;
+                    Ok(())
+                })
+                .await?;
+            let rows = cancel.run(scan_each(b, None, ScanOptions::default(), Box::new(|_| {}))).await?;
+            let msg = format!("scan {}/{times}: {} BSSs", i + 1, rows.len());
+            progress.report((i + 1) as f32 * 100.0 / times as f32, "scan", &msg)?;
+            scans.push(rows);
+        }
+        Ok::<_, anyhow::Error>(scans)
+    })
+}
+
+/// Start a scan (as `opts` asks) in the background and stream its rows as
+/// they are parsed, ending with `ScanEvent::Done` or `ScanEvent::Failed`
+/// (also when `cancel` is set).
+pub fn scan_stream(opts: ScanOptions, cancel: Cancel) -> mpsc::Receiver<ScanEvent> {
+    let (tx, rx) = mpsc::channel();
+    let rows_tx = tx.clone();
+    let sink: RowSink = Box::new(move |row| {
+        // The reader may have gone away; the scan still completes.
+        let _ = rows_tx.send(ScanEvent::Row(Box::new(row.clone())));
+    });
+
+    let b = backend();
+    runtime().spawn(async move {
+        let end = match cancel.run(scan_each(b, None, opts, sink)).await {
+            Ok(_) => ScanEvent::Done,
+            Err(e) => ScanEvent::Failed(e),
+        };
+        let _ = tx.send(end);
+    });
+    rx
+}
+
+// Currently connected AP's BSSID (if any), as raw bytes.
+pub fn get_connected_bssid() -> Result<Option<[u8; 6]>> {
+    connected_bssid_on(backend(), None)
+}
+
+/// Whether the selected backend's radio can scan from a random MAC
+/// address (ScanOptions::random_mac). The mock says yes; neli-wifi never
+/// triggers scans, so it reports what the radio could do.
+pub fn random_mac_supported() -> Result<bool> {
+    match backend() {
+        Backend::Mock => Ok(true),
+        _ => block_on(nl_raw::random_mac_supported_async(None)),
+    }
+}
+
+fn connected_bssid_on(b: Backend, ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+    let start = Instant::now();
+    let mac = b.provider().connected_bssid(ifindex)?;
+    perf::record("connected", b.name(), start.elapsed());
+    history::note_connected(mac);
+    Ok(mac)
+}
+
+/// The connected BSSID for `rows`, a full scan by `b` on `ifindex`: read
+/// off their BssStatus when the backend reports it (no second netlink
+/// round trip; no row associated means not connected), else asked for.
+pub fn connected_in(b: Backend, ifindex: Option<u32>, rows: &[BssRow]) -> Result<Option<[u8; 6]>> {
+    if !b.provider().reports_bss_status() {
+        return connected_bssid_on(b, ifindex);
+    }
+    let mac = connected_row(rows).and_then(|r| r.bssid);
+    history::note_connected(mac);
+    Ok(mac)
+}
+
+/// 20 MHz 5 GHz channels outside the DFS range (UNII-1 and UNII-3), and
+/// the DFS ones (UNII-2 / 2e).
+pub const CHANNELS_5_20: [u32; 9] = [36, 40, 44, 48, 149, 153, 157, 161, 165];
+pub const CHANNELS_5_20_DFS: [u32; 16] = [52, 56, 60, 64, 100, 104, 108, 112, 116, 120, 124, 128, 132, 136, 140, 144];
+
+/// One scan and the BSSID we were connected to when it was taken, shared
+/// by several computations so they answer from the same data and only
+/// one scan is paid for.
+#[derive(Debug, Clone)]
+pub struct ScanSnapshot {
+    pub rows: Arc<Vec<BssRow>>,
+    pub connected: Option<[u8; 6]>,
+    pub at: Stamp,
+}
+
+impl ScanSnapshot {
+    pub fn new(rows: Vec<BssRow>, connected: Option<[u8; 6]>) -> ScanSnapshot {
+        ScanSnapshot {
+            rows: Arc::new(rows),
+            connected,
+            at: Stamp::now(),
+        }
+    }
+
+    /// Scan now on the selected backend, and refresh the channel survey and
+    /// ChannelLimits.
+    pub fn capture(cancel: &Cancel) -> Result<ScanSnapshot> {
+        let rows = scan_all_bss_until(cancel)?;
+        chansurvey::refresh(backend(), None);
+        ChannelLimits::refresh(backend(), None);
+        let connected = connected_in(backend(), None, &rows)?;
+        Ok(ScanSnapshot::new(rows, connected))
+    }
+
+    /// The row of the BSS we were connected to: the one the scan marked
+    /// associated, else the one with the connected BSSID.
+    pub fn connected_entry(&self) -> Option<&BssRow> {
+        connected_row(&self.rows).or_else(|| {
+            let c = self.connected?;
+            self.rows.iter().find(|r| r.bssid == Some(c))
+        })
+    }
+
+    /// The 6 GHz BSSs this scan's Reduced Neighbor Reports list but it
+    /// didn't hear.
+    pub fn rnr_neighbors(&self) -> Vec<rnr::Neighbor> {
+        rnr::neighbors(&self.rows)
+    }
+
+    /// hidden_network_count() on this scan.
+    pub fn hidden_network_count(&self, channel: Option<u32>) -> usize {
+        hidden_network_count(&self.rows, channel)
+    }
+
+    /// Where to roam within the connected network (roam::advise). None
+    /// when not connected or the connected BSS isn't in the scan.
+    pub fn roam_advice(&self, hysteresis_db: f32) -> Option<roam::RoamAdvice> {
+        roam::advise(&self.rows, self.connected_entry()?, hysteresis_db)
+    }
+
+    /// Rogue AP / evil-twin findings against `history`, scans taken before
+    /// this one (anomaly::detect).
+    pub fn anomalies(&self, history: &[history::StoredScan], own_ssids: &[Vec<u8>]) -> Vec<anomaly::Finding> {
+        anomaly::detect(&self.rows, history, own_ssids, self.connected_entry())
+    }
+
+    /// This scan's BSSIDs by device (devgroup::groups).
+    pub fn device_groups(&self) -> Vec<devgroup::Device> {
+        devgroup::groups(&self.rows)
+    }
+
+    /// The countries this scan's APs claim against the regulatory domain
+    /// (regcheck::check), the latest regdom::current().
+    pub fn regulatory_check(&self) -> regcheck::RegReport {
+        regcheck::check(&self.rows, regdom::current().as_ref())
+    }
+
+    /// Security audit of this scan's networks (secaudit::audit).
+    pub fn security_report(&self) -> Vec<secaudit::EssAudit> {
+        secaudit::audit(&self.rows, self.connected_entry().and_then(|r| r.bssid).as_ref())
+    }
+
+    /// This scan's networks by SSID (ess::group).
+    pub fn group_by_ssid(&self) -> Vec<ess::Ess> {
+        ess::group(&self.rows)
+    }
+
+    /// compute_channels_internal() on this scan.
+    pub fn channels(&self, band: Option<u8>) -> HashMap<u32, u32> {
+        channel_counts(&self.rows, band)
+    }
+
+    /// compute_best_channel_internal() on this scan.
+    pub fn best_channel(&self, candidates: Option<&[(u8, u32)]>, cfg: &ChannelConfig) -> Result<(u8, u32)> {
+        best_channel_for(&self.rows, self.connected.as_ref(), candidates, cfg)
+    }
+
+    /// channel_scores() on this scan, with the latest survey and limits.
+    pub fn channel_scores(&self, candidates: Option<&[(u8, u32)]>, cfg: &ChannelConfig) -> Result<Vec<ChannelScore>> {
+        let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+        channel_scores(&self.rows, self.connected.as_ref(), candidates, &busy, &limits, cfg)
+    }
+
+    /// best_channel_plan() on this scan, with the latest survey and limits.
+    pub fn best_channel_plan(&self, candidates: Option<&[(u8, u32)]>, cfg: &ChannelConfig) -> Result<(u8, u32, u32)> {
+        let (busy, limits) = (chansurvey::recent_busy(), ChannelLimits::current());
+        best_channel_plan(&self.rows, self.connected.as_ref(), candidates, &busy, &limits, cfg)
+    }
+
+    pub fn interference_matrix(&self, band: Option<u8>, cfg: &ChannelConfig) -> Result<Vec<InterferenceMatrix>> {
+        cfg.validate()?;
+        let busy = chansurvey::recent_busy();
+        Ok(interference_matrix(&self.rows, self.connected.as_ref(), band, &busy, cfg))
+    }
+
+    /// Best 20 MHz channel in one freq_band() (1, 2 or 4), whatever band
+    /// we're connected on: 1/6/11 on 2.4 GHz, CHANNELS_5_20 (plus the DFS
+    /// ones with `dfs`) on 5 GHz, CHANNELS_6_PSC on 6 GHz.
+    pub fn best_channel_for_band(&self, band: u8, dfs: bool, cfg: &ChannelConfig) -> Result<u32> {
+        cfg.validate()?;
+        let Some(candidates) = band_plan(band, dfs) else {
+            bail!("no channel plan for band {band}");
+        };
+        let limits = ChannelLimits::current();
+        let (_, ch) =
+            best_channel_in(&self.rows, self.connected.as_ref(), &candidates, &chansurvey::recent_busy(), &limits, cfg)?;
+        Ok(ch)
+    }
+}
+
+// The 20 MHz channels worth recommending in a freq_band(), as pairs for
+// best_channel_in().
+fn band_plan(band: u8, dfs: bool) -> Option<Vec<(u8, u32)>> {
+    let channels: Vec<u32> = match band {
+        1 => vec![1, 6, 11],
+        2 if dfs => CHANNELS_5_20.iter().chain(&CHANNELS_5_20_DFS).copied().collect(),
+        2 => CHANNELS_5_20.to_vec(),
+        4 => CHANNELS_6_PSC.to_vec(),
+        _ => return None,
+    };
+    Some(channels.into_iter().map(|c| (band, c)).collect())
+}
+
+/// A backend and Wi-Fi interface looked up once, for callers that poll:
+/// the netlink socket is already shared process-wide, and a session also
+/// skips the GET_INTERFACE dump every call would otherwise start with. If
+/// the interface goes away (driver reload, USB replug) a failing call
+/// looks it up again and, if it changed, retries once.
+pub struct Session {
+    backend: Backend,
+    // The interface asked for by name; the default one if None.
+    iface: Option<String>,
+    // 0 until looked up; the mock has none.
+    ifindex: AtomicU32,
+}
+
+impl Session {
+    /// A session on the interface called `iface` (e.g. "wlan1"), or the
+    /// default one (netlink::first_ifindex) if None.
+    pub fn open_on(backend: Backend, iface: Option<&str>) -> Result<Session> {
+        let s = Session {
+            backend,
+            iface: iface.map(str::to_owned),
+            ifindex: AtomicU32::new(0),
+        };
+        s.refresh()?;
+        Ok(s)
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn iface(&self) -> Option<&str> {
+        self.iface.as_deref()
+    }
+
+    pub fn ifindex(&self) -> Option<u32> {
+        Some(self.ifindex.load(Ordering::Relaxed)).filter(|&i| i != 0)
+    }
+
+    /// Look the interface up again; true if it changed.
+    pub fn refresh(&self) -> Result<bool> {
+        let idx = match (self.backend, &self.iface) {
+            (Backend::Mock, None) => 0,
+            (Backend::Mock, Some(name)) => {
+                if !mock::interfaces()?.iter().any(|i| &i.name == name) {
+                    bail!(WifiError::NoInterface(format!("no Wi-Fi interface named {name:?}")));
+                }
+                0
+            }
+            (Backend::WpaSupplicant, None) => wpa_ctrl::interfaces()?.first().map_or(0, |i| i.ifindex),
+            (Backend::WpaSupplicant, Some(name)) => wpa_ctrl::interfaces()?
+                .into_iter()
+                .find(|i| &i.name == name)
+                .map(|i| i.ifindex)
+                .ok_or_else(|| WifiError::NoInterface(format!("wpa_supplicant doesn't control {name:?}")))?,
+            (Backend::Android, None) => 0,
+            (Backend::Android, Some(name)) => {
+                if !android::interfaces()?.iter().any(|i| &i.name == name) {
+                    bail!(WifiError::NoInterface(format!("no Wi-Fi interface named {name:?}")));
+                }
+                0
+            }
+            (_, None) => block_on(async { netlink::first_ifindex(&netlink::Nl80211::shared()?).await })?,
+            (_, Some(name)) => block_on(async { netlink::ifindex_by_name(&netlink::Nl80211::shared()?, name).await })?,
+        };
+        Ok(self.ifindex.swap(idx, Ordering::Relaxed) != idx)
+    }
+
+    fn with_retry<T>(&self, f: impl Fn(Option<u32>) -> Result<T>) -> Result<T> {
+        match f(self.ifindex()) {
+            Err(e) if self.backend != Backend::Mock => match self.refresh() {
+                Ok(true) => f(self.ifindex()),
+                _ => Err(e),
+            },
+            res => res,
+        }
+    }
+
+    pub fn scan(&self, cancel: &Cancel) -> Result<Vec<BssRow>> {
+        self.scan_with(&ScanOptions::default(), cancel)
+    }
+
+    pub fn scan_with(&self, opts: &ScanOptions, cancel: &Cancel) -> Result<Vec<BssRow>> {
+        self.with_retry(|i| block_on(cancel.run(scan_each(self.backend, i, opts.clone(), Box::new(|_| {})))))
+    }
+
+    pub fn connected_bssid(&self) -> Result<Option<[u8; 6]>> {
+        self.with_retry(|i| connected_bssid_on(self.backend, i))
+    }
+
+    /// connected_in() for `rows`, a scan() of this session.
+    pub fn connected_in(&self, rows: &[BssRow]) -> Result<Option<[u8; 6]>> {
+        self.with_retry(|i| connected_in(self.backend, i, rows))
+    }
+
+    /// This interface's link to its AP (link::query).
+    pub fn link_info(&self) -> Result<Option<LinkInfo>> {
+        self.with_retry(|i| link::query(self.backend, i))
+    }
+
+    /// Channel survey of this interface's radio (chansurvey::survey).
+    pub fn survey(&self) -> Result<Vec<ChannelSurvey>> {
+        self.with_retry(|i| chansurvey::survey(self.backend, i))
+    }
+
+    /// scan() and connected_bssid() as one ScanSnapshot, refreshing the
+    /// channel survey and ChannelLimits in between.
+    pub fn snapshot(&self, cancel: &Cancel) -> Result<ScanSnapshot> {
+        let rows = self.scan(cancel)?;
+        chansurvey::refresh(self.backend, self.ifindex());
+        ChannelLimits::refresh(self.backend, self.ifindex());
+        let connected = self.connected_in(&rows)?;
+        Ok(ScanSnapshot::new(rows, connected))
+    }
+}
+
+/// Simple channel count: how many APs per channel, optionally only in one
+/// freq_band(). Wi-Fi Direct groups are only counted under
+/// P2pPolicy::Include, ad-hoc networks unless exclude_ibss().
+pub fn compute_channels_internal(band: Option<u8>) -> Result<HashMap<u32, u32>> {
+    Ok(channel_counts(&scan_all_bss()?, band))
+}
+
+/// compute_channels_internal() on rows already scanned.
+pub fn channel_counts(rows: &[BssRow], band: Option<u8>) -> HashMap<u32, u32> {
+    let mut counts: HashMap<u32, u32> = HashMap::new();
+    let skip_p2p = p2p_policy() != P2pPolicy::Include;
+
+    for r in rows {
+        if band.is_some() && r.freq_mhz.map(freq_band) != band {
+            continue;
+        }
+        if (skip_p2p && r.is_p2p()) || excluded(r) {
+            continue;
+        }
+        if let Some(ch) = r.channel {
+            if ch > 0 {
+                *counts.entry(ch).or_insert(0) += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+/// How many BSSs in `rows` hide their SSID, on `channel` or in all. A
+/// BSSID heard by several radios (scan_merged()) counts once.
+pub fn hidden_network_count(rows: &[BssRow], channel: Option<u32>) -> usize {
+    let mut seen: HashSet<[u8; 6]> = HashSet::new();
+    rows.iter()
+        .filter(|r| r.hidden && (channel.is_none() || r.channel == channel))
+        .filter(|r| r.bssid.is_none_or(|b| seen.insert(b)))
+        .count()
+}
+
+/// One channel of channel_breakdown().
+#[derive(Debug, Clone, Default)]
+pub struct ChannelStats {
+    pub band: u8,
+    pub channel: u32,
+    /// APs whose primary channel this is.
+    pub aps: u32,
+    /// How many of `aps` are Wi-Fi Direct groups (none under
+    /// P2pPolicy::Exclude, which leaves them out altogether).
+    pub p2p: u32,
+    /// How many of `aps` are ad-hoc networks (none under exclude_ibss()).
+    pub ibss: u32,
+    /// How many of `aps` are Passpoint (Hotspot 2.0) carrier hotspots.
+    pub passpoint: u32,
+    /// How many of `aps` are 6 GHz BSSs only known from other APs'
+    /// Reduced Neighbor Reports.
+    pub rnr: u32,
+    /// APs whose occupied spectrum covers this channel, including wide
+    /// (40/80/160 MHz) APs with their primary elsewhere in the block.
+    pub co_channel: u32,
+    /// APs on partially overlapping neighbouring channels.
+    pub adjacent: u32,
+    /// row_weight() of the co-channel APs plus a share of that of the
+    /// adjacent ones (overlap_24() on 2.4 GHz, half elsewhere): the same
+    /// scale best_channel_from_rows scores with.
+    pub weight: f32,
+    /// Mean channel utilization (0.0 to 1.0) the co-channel APs report in
+    /// their BSS Load element.
+    pub utilization: Option<f32>,
+    /// Share of the time our radio found the channel busy in the latest
+    /// channel survey (0.0 to 1.0); `busy` * BUSY_WEIGHT is in `weight`.
+    pub busy: Option<f32>,
+    /// Primary APs by channel width in MHz.
+    pub widths: Vec<(u32, u32)>,
+}
+
+// 20 MHz channels an AP occupies and the ones it partially overlaps.
+// 2.4 GHz channels are 5 MHz apart and a 22 MHz signal reaches 4 either
+// side of each 20 MHz it uses; elsewhere wide channels span blocks of
+// 20 MHz channels numbered 4 apart, around the announced centre channel
+// (aligned blocks when there is none), and only the next channel out
+// overlaps.
+fn channel_span(band: u8, ch: u32, op: Option<Operation>) -> (Vec<u32>, Vec<u32>) {
+    let width = op.map_or(20, |o| o.width_mhz);
+    if band == 1 {
+        let mut co = vec![ch];
+        if let Some(sec) = op.and_then(|o| o.secondary).filter(|_| width >= 40) {
+            co.extend(ch.checked_add_signed(4 * sec as i32).filter(|c| (1..=14).contains(c)));
+        }
+        let (lo, hi) = (*co.iter().min().unwrap(), *co.iter().max().unwrap());
+        let adjacent = (lo.saturating_sub(4)..=hi + 4)
+            .filter(|c| !co.contains(c) && (1..=14).contains(c))
+            .collect();
+        return (co, adjacent);
+    }
+    let base = match (band, ch) {
+        (2, 149..) => 149,
+        (2, _) => 36,
+        _ => 1,
+    };
+    let span = (width / 20).max(1);
+    let Some(idx) = ch.checked_sub(base).map(|d| d / 4) else {
+        return (vec![ch], Vec::new());
+    };
+    // From the centre channel if it is consistent with the primary.
+    let from_center = op
+        .and_then(|o| o.center)
+        .and_then(|c| c.checked_sub((span - 1) * 2))
+        .filter(|&lo| lo <= ch && ch <= lo + (span - 1) * 4 && (ch - lo).is_multiple_of(4));
+    let lo = from_center.unwrap_or(base + idx / span * span * 4);
+    let hi = lo + (span - 1) * 4;
+    let co = (0..span).map(|i| lo + i * 4).collect();
+    let usable = |c: &u32| match band {
+        2 => matches!(c, 36..=64 | 100..=144 | 149..=177),
+        _ => (1..=233).contains(c),
+    };
+    let adjacent = [lo.checked_sub(4), Some(hi + 4)].into_iter().flatten().filter(usable).collect();
+    (co, adjacent)
+}
+
+/// Share of a 2.4 GHz AP's energy that lands on a channel `distance`
+/// channels (5 MHz steps) away: the overlap of two 22 MHz wide spectral
+/// masks, 1.0 on the same channel down to 0.09 four channels off and
+/// nothing from five on, which is why 1/6/11 don't interfere.
+pub fn overlap_24(distance: u32) -> f32 {
+    ((22.0 - 5.0 * distance as f32) / 22.0).max(0.0)
+}
+
+// Distance in channels from `ch` to the nearest of `co`.
+fn co_distance(ch: u32, co: &[u32]) -> Option<u32> {
+    co.iter().map(|&c| c.abs_diff(ch)).min()
+}
+
+// channel_span() with the share of an AP's weight each adjacent channel
+// takes: overlap_24() on 2.4 GHz, half elsewhere.
+fn weighted_span(band: u8, ch: u32, op: Option<Operation>) -> (Vec<u32>, Vec<(u32, f32)>) {
+    let (co, adjacent) = channel_span(band, ch, op);
+    let adjacent = adjacent
+        .into_iter()
+        .map(|c| {
+            let share = if band == 1 {
+                co_distance(c, &co).map_or(0.0, overlap_24)
+            } else {
+                0.5
+            };
+            (c, share)
+        })
+        .collect();
+    (co, adjacent)
+}
+
+fn channel_stat(stats: &mut HashMap<(u8, u32), ChannelStats>, band: u8, ch: u32) -> &mut ChannelStats {
+    stats.entry((band, ch)).or_insert_with(|| ChannelStats {
+        band,
+        channel: ch,
+        ..Default::default()
+    })
+}
+
+/// Per-channel breakdown of `rows`, optionally limited to one freq_band(),
+/// sorted by band then channel. Channels that are only overlapped are
+/// listed too, and so are the ones `busy` (chansurvey::recent_busy()) has
+/// a survey for. APs are weighed as `cfg` says; ones older than its
+/// max_age_ms are left out. 6 GHz BSSs the rows' Reduced Neighbor Reports
+/// list count as if heard (rnr::neighbors).
+pub fn channel_breakdown(
+    rows: &[BssRow],
+    band: Option<u8>,
+    busy: &HashMap<(u8, u32), f32>,
+    cfg: &ChannelConfig,
+) -> Vec<ChannelStats> {
+    let mut stats: HashMap<(u8, u32), ChannelStats> = HashMap::new();
+    let mut loads: HashMap<(u8, u32), Vec<f32>> = HashMap::new();
+    let reported = rnr::neighbors(rows);
+
+    let rows = rows.iter().map(|r| (r, false)).chain(reported.iter().map(|n| (&n.row, true)));
+    for (r, via_rnr) in rows {
+        let (Some(ch), Some(freq)) = (r.channel.filter(|&c| c > 0), r.freq_mhz) else {
+            continue;
+        };
+        let b = freq_band(freq);
+        if band.is_some_and(|want| want != b) {
+            continue;
+        }
+        if excluded(r) || cfg.stale(r) {
+            continue;
+        }
+        let op = r.operation();
+        let width = op.map_or(20, |o| o.width_mhz);
+        let w = cfg.row_weight(r).unwrap_or(0.0);
+        let primary = channel_stat(&mut stats, b, ch);
+        primary.aps += 1;
+        primary.p2p += u32::from(r.is_p2p());
+        primary.ibss += u32::from(r.is_ibss());
+        primary.passpoint += u32::from(r.is_passpoint());
+        primary.rnr += u32::from(via_rnr);
+        match primary.widths.iter_mut().find(|(wd, _)| *wd == width) {
+            Some((_, n)) => *n += 1,
+            None => primary.widths.push((width, 1)),
+        }
+
+        let (co, adjacent) = weighted_span(b, ch, op);
+        for &c in &co {
+            let e = channel_stat(&mut stats, b, c);
+            e.co_channel += 1;
+            e.weight += w;
+            if let Some(load) = r.bss_load() {
+                loads.entry((b, c)).or_default().push(load.utilization);
+            }
+        }
+        for (c, share) in adjacent {
+            let e = channel_stat(&mut stats, b, c);
+            e.adjacent += 1;
+            e.weight += w * share;
+        }
+    }
 
+    for (&(b, ch), &frac) in busy {
+        if band.is_some_and(|want| want != b) {
+            continue;
+        }
+        let e = channel_stat(&mut stats, b, ch);
+        e.busy = Some(frac);
+        e.weight += frac * chansurvey::BUSY_WEIGHT;
+    }
 
-// Struct that will hold information collected from each BSS
-#[derive(Debug, Clone)]
-pub struct BssRow {
-    pub ssid: Option<String>,
-    pub bssid: Option<[u8; 6]>,
-    pub freq_mhz: Option<u32>,
-    pub signal_dbm: Option<f32>,
-    pub channel: Option<u32>,
+    let mut out: Vec<ChannelStats> = stats.into_values().collect();
+    for st in &mut out {
+        if let Some(l) = loads.get(&(st.band, st.channel)) {
+            st.utilization = Some(l.iter().sum::<f32>() / l.len() as f32);
+        }
+        st.widths.sort_unstable();
+    }
+    out.sort_by_key(|st| (st.band, st.channel));
+    out
 }
 
-// Converts a u8 array to 
-fn vec_to_mac(v: &[u8]) -> Option<[u8; 6]> {
-    if v.len() < 6 {
-        return None;
-    }
-    let mut out = [0u8; 6];
-    out.copy_from_slice(&v[..6]);
-    Some(out)
+/// One band of interference_matrix().
+#[derive(Debug, Clone, Default)]
+pub struct InterferenceMatrix {
+    pub band: u8,
+    /// Primary channels of the APs heard, ascending.
+    pub observed: Vec<u32>,
+    /// How many APs are on each of `observed`.
+    pub aps: Vec<u32>,
+    /// The channels scored, ascending: channel_breakdown()'s.
+    pub candidates: Vec<u32>,
+    /// weight[i][j]: what the APs on observed[i] add to the weight of
+    /// candidates[j], co-channel or overlapping.
+    pub weight: Vec<Vec<f32>>,
+    /// The channel survey's part of each candidate's weight.
+    pub busy: Vec<f32>,
+    /// Each candidate's weight: its column of `weight` plus `busy`, the
+    /// same as channel_breakdown()'s.
+    pub total: Vec<f32>,
 }
 
-pub fn format_mac(bytes: &[u8; 6]) -> String {
-    let mut s = String::with_capacity(17);
-    for (i, b) in bytes.iter().enumerate() {
-        if i > 0 {
-            let _ = write!(s, ":");
+/// channel_breakdown()'s weights taken apart by where they come from: per
+/// freq_band() (only `band` if given), what the APs on each observed
+/// channel add to each candidate channel, plus survey busy time. Our own
+/// AP is left out as in the best-channel scoring.
+pub fn interference_matrix(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    band: Option<u8>,
+    busy: &HashMap<(u8, u32), f32>,
+    cfg: &ChannelConfig,
+) -> Vec<InterferenceMatrix> {
+    let foreign = foreign_rows(rows, connected);
+    let reported = rnr::neighbors(&foreign);
+    // (band, observed, candidate) -> weight; (band, observed) -> APs.
+    let mut cells: HashMap<(u8, u32, u32), f32> = HashMap::new();
+    let mut aps: HashMap<(u8, u32), u32> = HashMap::new();
+
+    for r in foreign.iter().chain(reported.iter().map(|n| &n.row)) {
+        let (Some(ch), Some(freq)) = (r.channel.filter(|&c| c > 0), r.freq_mhz) else {
+            continue;
+        };
+        let b = freq_band(freq);
+        if band.is_some_and(|want| want != b) || excluded(r) || cfg.stale(r) {
+            continue;
+        }
+        let w = cfg.row_weight(r).unwrap_or(0.0);
+        *aps.entry((b, ch)).or_default() += 1;
+        let (co, adjacent) = weighted_span(b, ch, r.operation());
+        for (c, share) in co.into_iter().map(|c| (c, 1.0)).chain(adjacent) {
+            *cells.entry((b, ch, c)).or_default() += w * share;
         }
-        let _ = write!(s, "{:02x}", b);
     }
-    s
+
+    let stats = channel_breakdown(&foreign, band, busy, cfg);
+    let mut bands: Vec<u8> = stats.iter().map(|s| s.band).collect();
+    bands.dedup();
+    bands
+        .into_iter()
+        .map(|b| {
+            let mut observed: Vec<u32> = aps.keys().filter(|k| k.0 == b).map(|k| k.1).collect();
+            observed.sort_unstable();
+            let scored: Vec<&ChannelStats> = stats.iter().filter(|s| s.band == b).collect();
+            let candidates: Vec<u32> = scored.iter().map(|s| s.channel).collect();
+            InterferenceMatrix {
+                band: b,
+                aps: observed.iter().map(|&o| aps[&(b, o)]).collect(),
+                weight: observed
+                    .iter()
+                    .map(|&o| {
+                        candidates
+                            .iter()
+                            .map(|&c| cells.get(&(b, o, c)).copied().unwrap_or(0.0))
+                            .collect()
+                    })
+                    .collect(),
+                busy: scored
+                    .iter()
+                    .map(|s| s.busy.map_or(0.0, |f| f * chansurvey::BUSY_WEIGHT))
+                    .collect(),
+                total: scored.iter().map(|s| s.weight).collect(),
+                observed,
+                candidates,
+            }
+        })
+        .collect()
 }
 
-//Collect information for each SSID scann.
-fn parse_ssid_ie(mut ies: &[u8]) -> Option<String> {
-    // IEs are TLVs: [id, len, value...]
-    while ies.len() >= 2 {
-        let id = ies[0];
-        let len = ies[1] as usize;
-        ies = &ies[2..];
+/// The Wi-Fi interfaces of the selected backend.
+pub fn list_interfaces() -> Result<Vec<WifiIface>> {
+    backend().provider().interfaces()
+}
 
-        if len > ies.len() {
-            break;
-        }
-        let val = &ies[..len];
-        ies = &ies[len..];
+/// Smart "best channel" computation on a fresh scan and channel survey:
+/// (freq_band(), channel). See `best_channel_from_rows` for the
+/// heuristics, and `best_channel_among` for `candidates`.
+pub fn compute_best_channel_internal(candidates: Option<&[(u8, u32)]>, cfg: &ChannelConfig) -> Result<(u8, u32)> {
+    //Collect all BSS
+    let rows = scan_all_bss()?;
+    //Busy time right after the scan covers every channel it visited
+    chansurvey::refresh(backend(), None);
+    //Which channels are legal here, and can our radio use them?
+    ChannelLimits::refresh(backend(), None);
+    //What is the BSSID we are on?
+    let connected = connected_in(backend(), None, &rows)?;
+    best_channel_for(&rows, connected.as_ref(), candidates, cfg)
+}
 
-        if id == 0 {
-            // SSID; may be empty for hidden
-            return Some(String::from_utf8_lossy(val).to_string());
-        }
-    }
-    None
+/// compute_best_channel_internal() on rows already scanned, with the
+/// latest channel survey if it is recent and the latest ChannelLimits.
+pub fn best_channel_for(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    candidates: Option<&[(u8, u32)]>,
+    cfg: &ChannelConfig,
+) -> Result<(u8, u32)> {
+    best_channel_with(rows, connected, candidates, &chansurvey::recent_busy(), &ChannelLimits::current(), cfg)
 }
 
-// Channel mapping, only goes to channel 165 before returning 0 as the channel since we are only looking at < 5G
-fn freq_to_channel(freq: &u32) -> u32 {
-    match *freq {
-        2412 => 1,
-        2417 => 2,
-        2422 => 3,
-        2427 => 4,
-        2432 => 5,
-        2437 => 6,
-        2442 => 7,
-        2447 => 8,
-        2452 => 9,
-        2457 => 10,
-        2462 => 11,
-        2467 => 12,
-        2472 => 13,
-        2482 => 14,
-        5180 => 36,
-        5200 => 40,
-        5220 => 44,
-        5240 => 48,
-        5260 => 52,
-        5280 => 56,
-        5300 => 60,
-        5320 => 64,
-        5500 => 100,
-        5520 => 104,
-        5540 => 108,
-        5560 => 112,
-        5580 => 116,
-        5600 => 120,
-        5620 => 124,
-        5640 => 128,
-        5660 => 132,
-        5680 => 136,
-        5700 => 140,
-        5745 => 149,
-        5765 => 153,
-        5785 => 157,
-        5805 => 161,
-        5825 => 165,
-        5845 => 169,
-        5865 => 173,
-        5885 => 177,
-        _ => 0,
-    }
-}
-
-// Check which frequency we are on and correlate it to the correct band. 
-fn freq_band(freq_mhz: u32) -> u8 {
-    // 1 = 2.4 GHz, 2 = 5 GHz, 3 = All others
-    match freq_mhz {
-        2401..=2495 => 1,
-        5150..=5895 => 2,
-        _ => 3,
+// best_channel_for() with the busy time and limits given.
+fn best_channel_with(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    candidates: Option<&[(u8, u32)]>,
+    busy: &HashMap<(u8, u32), f32>,
+    limits: &ChannelLimits,
+    cfg: &ChannelConfig,
+) -> Result<(u8, u32)> {
+    cfg.validate()?;
+    match candidates {
+        Some(c) => best_channel_among(rows, connected, c, busy, limits, cfg),
+        None => best_channel_from_rows(rows, connected, busy, limits, cfg),
     }
 }
 
-/// Heuristic: two BSSIDs are likely from the same device if
-/// bytes 1..=4 match Only first & last differ with my Ubiquiti routers.
-fn same_device(a: &[u8; 6], b: &[u8; 6]) -> bool {
-    a[1] == b[1] && a[2] == b[2] && a[3] == b[3] && a[4] == b[4]
+/// The channels a recommendation may use: those the regulatory domain
+/// allows without NO-IR and the local radio lists and hasn't disabled.
+/// Either part is None when it couldn't be queried, and limits nothing.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelLimits {
+    pub reg: Option<RegDomain>,
+    pub radio: Option<PhyCaps>,
 }
 
-/// -------------------- Public internal APIs --------------------
+impl ChannelLimits {
+    pub const NONE: ChannelLimits = ChannelLimits { reg: None, radio: None };
 
-/// Fresh scan of all BSSs visible from the Wi-Fi interface.
-pub fn scan_all_bss() -> Result<Vec<BssRow>> {
-    //Connect a socket
-    let mut sock = Socket::connect()?;
+    /// Query the regulatory domain and `ifindex`'s radio again.
+    pub fn refresh(b: Backend, ifindex: Option<u32>) {
+        regdom::refresh(b);
+        phycaps::refresh(b, ifindex);
+    }
 
-    //Gather interface information from socket
-    let iface = sock
-        .get_interfaces_info()?
-        .into_iter()
-        .next()
-        .ok_or_else(|| anyhow!("no Wi-Fi interface found"))?;
-
-    let ifindex = iface
-        .index
-        .ok_or_else(|| anyhow!("Wi-Fi interface index missing"))?;
-
-    // neli-wifi returns Vec<Bss> here
-    //Gather BSS info
-    let bsses: Vec<Bss> = sock.get_bss_info(ifindex)?;
-
-    let mut out = Vec::new();
-    //Iterate through in information collected from the BSS
-    for b in bsses {
-        let ssid = b
-            .information_elements
-            .as_deref()
-            .and_then(parse_ssid_ie);
-        //Collect BSSID
-        let bssid = b.bssid.as_deref().and_then(vec_to_mac);
-        //Collect Freq (in MHz)
-        let freq_mhz = b.frequency;
-        //Determine the channel being used used
-        let channel = freq_mhz.and_then(|f| {
-            let ch = freq_to_channel(&f);
-            if ch == 0 { None } else { Some(ch) }
-        });
+    /// The latest of each, as refresh() left them.
+    pub fn current() -> ChannelLimits {
+        ChannelLimits {
+            reg: regdom::current(),
+            radio: phycaps::current(),
+        }
+    }
 
-        // BSS signal is in mBm (1/100 dBm)
-        let signal_dbm = b.signal.map(|mbm| (mbm as f32) / 100.0);
-        //Store all the collected information in a Vec that will be returned.
-        out.push(BssRow {
-            ssid,
-            bssid,
-            freq_mhz,
-            signal_dbm,
-            channel,
-        });
+    pub fn allows(&self, band: u8, ch: u32) -> bool {
+        self.reg.as_ref().is_none_or(|r| r.usable(band, ch)) && self.radio.as_ref().is_none_or(|p| p.usable(band, ch))
     }
 
-    Ok(out)
+    /// Whether a `width_mhz` channel made of the 20 MHz channels `block`
+    /// may be used: every one of them allowed, no regulatory rule narrower
+    /// and the radio capable of the width.
+    pub fn allows_width(&self, band: u8, block: &[u32], width_mhz: u32) -> bool {
+        block.iter().all(|&c| self.allows(band, c))
+            && self
+                .reg
+                .as_ref()
+                .is_none_or(|r| block.iter().all(|&c| r.max_width_mhz(band, c).is_none_or(|w| w >= width_mhz)))
+            && self.radio.as_ref().is_none_or(|p| {
+                p.bands.iter().filter(|b| b.band == band).all(|b| b.max_width_mhz() >= width_mhz)
+            })
+    }
+
+    // What allows() checks, for errors.
+    fn describe(&self) -> String {
+        match (&self.reg, &self.radio) {
+            (Some(r), Some(_)) => format!("regulatory domain {} and this radio", r.country),
+            (Some(r), None) => format!("regulatory domain {}", r.country),
+            (None, _) => "this radio".to_owned(),
+        }
+    }
 }
 
-// Currently connected AP's BSSID (if any), as raw bytes.
-pub fn get_connected_bssid() -> Result<Option<[u8; 6]>> {
-    let mut sock = Socket::connect()?;
+/// Tuning of the best-channel computation. DEFAULT is what it has always
+/// used; the mesh planners, history scoring and the channel breakdown
+/// stick to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelConfig {
+    /// APs weaker than this (dBm) are ignored.
+    pub threshold_dbm: f32,
+    /// How much less weight another channel must have before we recommend
+    /// moving off the current one.
+    pub margin: f32,
+    /// When not connected, only pick channels in this freq_band().
+    pub prefer_band: Option<u8>,
+    /// Signal (dBm) at which an AP would weigh nothing; every dB above
+    /// adds one.
+    pub floor_dbm: f32,
+    /// APs last heard longer ago than this (BssRow::age_ms) are ignored,
+    /// so ones that have gone stop counting while the kernel still
+    /// caches them. None keeps them all: without a recent scan (the
+    /// neli-wifi backend only reads the cache) every entry may be old.
+    pub max_age_ms: Option<u32>,
+    /// Factor on the weight of legacy-only APs (Generation::Legacy):
+    /// above 1 counts the airtime their slow rates hold the channel for,
+    /// below 1 discounts them as the old gear nobody uses much.
+    pub legacy_weight: f32,
+}
 
-    let iface = sock
-        .get_interfaces_info()?
-        .into_iter()
-        .next()
-        .ok_or_else(|| anyhow!("no Wi-Fi interface found"))?;
+impl ChannelConfig {
+    pub const DEFAULT: ChannelConfig = ChannelConfig {
+        threshold_dbm: -80.0,
+        margin: 10.0,
+        prefer_band: None,
+        floor_dbm: -100.0,
+        max_age_ms: None,
+        legacy_weight: 1.0,
+    };
+
+    /// Err if the values make no sense together.
+    pub fn validate(&self) -> Result<()> {
+        if ![self.threshold_dbm, self.floor_dbm, self.margin, self.legacy_weight].iter().all(|v| v.is_finite()) {
+            bail!("channel config values must be finite");
+        }
+        if self.floor_dbm >= self.threshold_dbm {
+            bail!("floor_dbm ({}) must be below threshold_dbm ({})", self.floor_dbm, self.threshold_dbm);
+        }
+        if self.margin < 0.0 {
+            bail!("margin must not be negative");
+        }
+        if self.legacy_weight < 0.0 {
+            bail!("legacy_weight must not be negative");
+        }
+        if self.prefer_band.is_some_and(|b| ![1, 2, 4].contains(&b)) {
+            bail!("prefer_band must be 2.4, 5 or 6 GHz");
+        }
+        Ok(())
+    }
 
-    let ifindex = iface
-        .index
-        .ok_or_else(|| anyhow!("Wi-Fi interface index missing"))?;
+    /// Interference one AP contributes, or None if it is below
+    /// threshold_dbm. Stronger AP signal can have more interference if
+    /// they are near the channel we are on.
+    pub fn ap_weight(&self, signal_dbm: Option<f32>) -> Option<f32> {
+        let sig = signal_dbm.unwrap_or(-90.0);
+        (sig >= self.threshold_dbm).then(|| (sig - self.floor_dbm).max(0.0))
+    }
 
-    // For neli-wifi 0.5.x this returns a single Station
-    let st: Station = sock.get_station_info(ifindex)?;
-    //Translate the bytes collected to a readable MAC
-    if let Some(ref v) = st.bssid {
-        if let Some(mac) = vec_to_mac(v) {
-            return Ok(Some(mac));
+    /// ap_weight() of `r`, scaled by its load_factor(), legacy_weight for
+    /// legacy-only APs and for Wi-Fi Direct groups by the P2P policy. None
+    /// if it doesn't count at all (see also excluded()) or is older than
+    /// max_age_ms.
+    pub fn row_weight(&self, r: &BssRow) -> Option<f32> {
+        let w = self.ap_weight(r.signal_dbm)?;
+        if excluded(r) || self.stale(r) {
+            return None;
+        }
+        let mut w = w * load_factor(r.bss_load());
+        if r.generation() == Some(apmodel::Generation::Legacy) {
+            w *= self.legacy_weight;
+        }
+        if r.is_p2p() && p2p_policy() == P2pPolicy::Downweight {
+            return Some(w * P2P_WEIGHT);
         }
+        Some(w)
     }
 
-    Ok(None)
+    /// Whether `r` was last heard longer ago than max_age_ms. Rows of
+    /// unknown age are kept.
+    pub fn stale(&self, r: &BssRow) -> bool {
+        matches!((self.max_age_ms, r.age_ms), (Some(max), Some(age)) if age > max)
+    }
 }
 
-/// Simple channel count: how many APs per channel.
-pub fn compute_channels_internal() -> Result<HashMap<u32, u32>> {
-    let rows = scan_all_bss()?;
-    let mut counts: HashMap<u32, u32> = HashMap::new();
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfig::DEFAULT
+    }
+}
 
-    for r in rows {
-        if let Some(ch) = r.channel {
-            if ch > 0 {
-                *counts.entry(ch).or_insert(0) += 1;
+/// Share of an AP's weight a Wi-Fi Direct group gets under
+/// P2pPolicy::Downweight: it only transmits while something is casting.
+pub const P2P_WEIGHT: f32 = 0.25;
+
+/// How much an AP's load changes its weight, from the channel
+/// utilization it advertises in its BSS Load element: 0.5 when idle, 1.0
+/// at a third busy and 2.0 when saturated. APs that don't advertise it
+/// count as 1.0.
+pub fn load_factor(load: Option<BssLoad>) -> f32 {
+    load.map_or(1.0, |l| 0.5 + 1.5 * l.utilization.clamp(0.0, 1.0))
+}
+
+/// ChannelConfig::row_weight() with the default tuning.
+pub fn row_weight(r: &BssRow) -> Option<f32> {
+    ChannelConfig::DEFAULT.row_weight(r)
+}
+
+/// Interference weight per (band, channel) from the visible APs, tuned by
+/// `cfg`:
+///
+/// - Ignores APs weaker than threshold_dbm, or last heard longer ago
+///   than max_age_ms
+/// - Ignores your own AP and "same device" BSSIDs as interference, and
+///   the other links of your AP when it is a multi-link (Wi-Fi 7) AP
+/// - Stronger APs contribute more weight, and so do APs advertising a
+///   busy channel in their BSS Load element (load_factor)
+/// - Wide APs weigh on every 20 MHz channel they occupy, so an 80 MHz
+///   neighbour counts against all four of its channels
+/// - 2.4 GHz APs also weigh on the channels up to 4 away, scaled by
+///   overlap_24(), so a channel between two busy neighbours scores badly.
+///   Only 1 to 11, allowed everywhere, are scored that way; 12 to 14
+///   only count when an AP is on them
+/// - Wi-Fi Direct groups and ad-hoc networks count as the P2P policy and
+///   IBSS setting say (row_weight)
+/// - 6 GHz BSSs only known from other APs' Reduced Neighbor Reports count
+///   too (rnr::neighbors), unless your own AP reported them
+pub fn channel_weights(rows: &[BssRow], connected: Option<&[u8; 6]>, cfg: &ChannelConfig) -> HashMap<(u8, u32), f32> {
+    channel_tally(rows, connected, cfg).into_iter().map(|(k, (w, _))| (k, w)).collect()
+}
+
+// channel_weights(), with the number of APs adding to each weight.
+fn channel_tally(rows: &[BssRow], connected: Option<&[u8; 6]>, cfg: &ChannelConfig) -> HashMap<(u8, u32), (f32, u32)> {
+    let mut weight: HashMap<(u8, u32), (f32, u32)> = HashMap::new();
+    let own_mld = connected_mld(rows, connected);
+    let reported = rnr::neighbors(rows);
+    let own = |m: &[u8; 6]| connected.is_some_and(|c| m == c || same_device(c, m));
+    let reported = reported.iter().filter(|n| !own(&n.reported_by)).map(|n| &n.row);
+
+    for r in rows.iter().chain(reported) {
+        let ch = match r.channel {
+            Some(c) if c > 0 => c,
+            _ => continue,
+        };
+        let freq = match r.freq_mhz {
+            Some(f) => f,
+            None => continue,
+        };
+        let band = freq_band(freq);
+        let Some(w) = cfg.row_weight(r) else {
+            continue; // too weak or excluded, ignore
+        };
+
+        // Skip our own device BSSIDs as interference
+        if let (Some(cmac), Some(ref rbssid)) = (connected, &r.bssid) {
+            if rbssid == cmac || same_device(cmac, rbssid) {
+                continue;
             }
         }
+        if own_mld.is_some() && r.mld_addr() == own_mld {
+            continue;
+        }
+
+        let (co, adjacent) = channel_span(band, ch, r.operation());
+        if band == 1 {
+            for c in adjacent.into_iter().filter(|&c| c <= 11) {
+                let share = co_distance(c, &co).map_or(0.0, overlap_24);
+                let e = weight.entry((band, c)).or_insert((0.0, 0));
+                e.0 += w * share;
+                e.1 += (share > 0.0) as u32;
+            }
+        }
+        for c in co {
+            let e = weight.entry((band, c)).or_insert((0.0, 0));
+            e.0 += w;
+            e.1 += 1;
+        }
     }
 
-    Ok(counts)
+    weight
+}
+
+// channel_tally() plus `busy` times BUSY_WEIGHT, on the channels
+// channel_weights scores: what best_channel_from_rows compares.
+fn busy_tally(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    busy: &HashMap<(u8, u32), f32>,
+    cfg: &ChannelConfig,
+) -> HashMap<(u8, u32), (f32, u32)> {
+    let mut weight = channel_tally(rows, connected, cfg);
+    for (&(band, ch), &frac) in busy {
+        if band == 1 && ch > 11 && !weight.contains_key(&(band, ch)) {
+            continue;
+        }
+        weight.entry((band, ch)).or_insert((0.0, 0)).0 += frac * chansurvey::BUSY_WEIGHT;
+    }
+    weight
 }
 
 /// Smart "best channel" computation:
 ///
 /// - Uses connected BSSID if available
 /// - Only compares channels in the same band (2.4 vs 5GHz)
-/// - Ignores APs weaker than THRESH_DBM
-/// - Ignores your own AP and "same device" BSSIDs as interference
-/// - Prefers to stay on current channel if its interference is close
-///   to the best option.
-pub fn compute_best_channel_internal() -> Result<u32> {
-    //DBM threshold 
-    const THRESH_DBM: f32 = -80.0;
-    const MARGIN: f32 = 10.0; // how much worse than best before we recommend moving
-
-    //Collect all BSS
-    let rows = scan_all_bss()?;
-    //What is the BSSID we are on?
-    let connected = get_connected_bssid()?;
-
+/// - Scores channels with `channel_weights`, plus `busy` (the share of
+///   time a channel survey found each channel busy, see
+///   chansurvey::recent_busy()) times BUSY_WEIGHT, which catches traffic
+///   that sends no beacons. Pass an empty map to go by beacons alone
+/// - Prefers to stay on current channel if its interference is within
+///   `cfg.margin` of the best option.
+/// - On 6 GHz only recommends Preferred Scanning Channels
+///   (CHANNELS_6_PSC), so clients can find the AP.
+/// - When not connected and `cfg.prefer_band` is set, picks the best of
+///   that band's usual channels (see ScanSnapshot::best_channel_for_band,
+///   without DFS).
+/// - Never picks a channel `limits` rules out (illegal, passive-only or
+///   not usable by the local radio), and moves off the current channel if
+///   that is one. With nothing heard, the first legal channel of the band
+///   plans (1/6/11, then 5 GHz, then 6 GHz PSCs); an error if none is.
+///
+/// Returns the freq_band() with the channel: 6 GHz reuses the 2.4 and
+/// 5 GHz channel numbers, and a pick across bands may be in any of them.
+pub fn best_channel_from_rows(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    busy: &HashMap<(u8, u32), f32>,
+    limits: &ChannelLimits,
+    cfg: &ChannelConfig,
+) -> Result<(u8, u32)> {
     // Figure out which channel and band we're actually on (if connected).
     let mut current_ch: Option<u32> = None;
     let mut current_band: Option<u8> = None;
 
-    if let Some(ref cmac) = connected {
-        for r in &rows {
+    if let Some(cmac) = connected {
+        for r in rows {
             if let Some(ref rbssid) = r.bssid {
                 if rbssid == cmac {
                     if let (Some(ch), Some(freq)) = (r.channel, r.freq_mhz) {
@@ -262,33 +2414,17 @@ pub fn compute_best_channel_internal() -> Result<u32> {
     }
 
     // Build interference weights per (band, channel) from other visible APs.
-    let mut weight: HashMap<(u8, u32), f32> = HashMap::new();
-
-    for r in &rows {
-        let ch = match r.channel {
-            Some(c) if c > 0 => c,
-            _ => continue,
-        };
-        let freq = match r.freq_mhz {
-            Some(f) => f,
-            None => continue,
-        };
-        let band = freq_band(freq);
-        let sig = r.signal_dbm.unwrap_or(-90.0);
-        if sig < THRESH_DBM {
-            continue; // too weak, ignore
-        }
-
-        // Skip our own device BSSIDs as interference
-        if let (Some(ref cmac), Some(ref rbssid)) = (&connected, &r.bssid) {
-            if rbssid == cmac || same_device(cmac, rbssid) {
-                continue;
-            }
-        }
+    let weight: HashMap<(u8, u32), f32> = busy_tally(rows, connected, busy, cfg)
+        .into_iter()
+        .filter(|&((band, ch), _)| limits.allows(band, ch))
+        .map(|(k, (w, _))| (k, w))
+        .collect();
 
-        // Stronger AP signal can have more interference if they are near the channel we are on
-        let w = (sig + 100.0).max(0.0);
-        *weight.entry((band, ch)).or_insert(0.0) += w;
+    // On 6 GHz, any channel with a weight isn't good enough: pick among
+    // the PSCs, empty ones included.
+    if current_ch.is_some() && current_band == Some(4) {
+        let psc: Vec<(u8, u32)> = CHANNELS_6_PSC.iter().map(|&c| (4, c)).collect();
+        return best_channel_in(rows, connected, &psc, busy, limits, cfg);
     }
 
     // If we're connected and know our channel+band, try to stay put if it's good.
@@ -309,34 +2445,594 @@ pub fn compute_best_channel_internal() -> Result<u32> {
 
         // Interference on our current channel (0.0 if nobody above threshold)
         let cur_w = *weight.get(&(cur_band, cur_ch)).unwrap_or(&0.0);
+        // Can we stay at all? (e.g. channel 13 under a US domain)
+        let cur_ok = limits.allows(cur_band, cur_ch);
 
         if let Some((best_ch, best_w)) = best_opt {
-            // If our current channel is within MARGIN of the best, stay.
-            if cur_w <= best_w + MARGIN {
-                return Ok(cur_ch);
+            // If our current channel is within the margin of the best, stay.
+            if cur_ok && cur_w <= best_w + cfg.margin {
+                return Ok((cur_band, cur_ch));
             } else {
-                return Ok(best_ch);
+                return Ok((cur_band, best_ch));
             }
-        } else {
+        } else if cur_ok {
             // No neighbors above threshold in our band -> our channel is clean.
-            return Ok(cur_ch);
+            return Ok((cur_band, cur_ch));
+        }
+        // Nothing to compare with: the best legal channel of the band's plan.
+        let plan = band_plan(cur_band, false).unwrap_or_default();
+        return best_channel_in(rows, connected, &plan, busy, limits, cfg);
+    }
+
+    // Not connected but told which band to use.
+    if let Some(plan) = cfg.prefer_band.and_then(|b| band_plan(b, false)) {
+        // Unless none of them is legal here.
+        if let Ok(ch) = best_channel_in(rows, connected, &plan, busy, limits, cfg) {
+            return Ok(ch);
+        }
+    }
+
+    // If we don't know what we're connected to, pick global argmin across
+    // bands, the lower band and channel on a tie.
+    let mut best: Option<((u8, u32), f32)> = None;
+    for (&key, &w) in &weight {
+        if key.0 == 4 && !is_psc(key.1) {
+            continue;
+        }
+        if best.is_none_or(|(bk, bw)| w < bw || (w == bw && key < bk)) {
+            best = Some((key, w));
+        }
+    }
+    match best {
+        Some((key, _)) => Ok(key),
+        None => {
+            // No interference seen at all: all equal, so the first legal one.
+            let plans: Vec<(u8, u32)> = [1, 2, 4].into_iter().filter_map(|b| band_plan(b, false)).flatten().collect();
+            best_channel_in(rows, connected, &plans, busy, limits, cfg)
+        }
+    }
+}
+
+/// Best of `candidates`, the (freq_band(), channel) pairs the AP will
+/// actually accept (vendor restrictions, DFS disabled). They may mix bands;
+/// candidate_pairs() makes them from bare 2.4 / 5 GHz channel numbers.
+///
+/// - Scores with channel_breakdown's overlap-aware weight, so wide APs and
+///   neighbouring 2.4 GHz channels count against a candidate
+/// - Ignores your own AP and "same device" BSSIDs as interference, and
+///   the other links of your AP when it is a multi-link AP
+/// - Adds channel survey busy time like best_channel_from_rows
+/// - Stays on the current channel if it is a candidate within `cfg.margin`
+///   of the best
+/// - When not connected, only picks candidates in `cfg.prefer_band` if
+///   there are any
+/// - Skips candidates `limits` rules out; an error if that leaves none
+///
+/// Returns the pick's freq_band() with it, as best_channel_from_rows does.
+pub fn best_channel_among(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    candidates: &[(u8, u32)],
+    busy: &HashMap<(u8, u32), f32>,
+    limits: &ChannelLimits,
+    cfg: &ChannelConfig,
+) -> Result<(u8, u32)> {
+    best_channel_in(rows, connected, &checked_pairs(candidates)?, busy, limits, cfg)
+}
+
+/// Bare channel numbers as (freq_band(), channel) candidates: 1-14 are
+/// 2.4 GHz and 32-177 5 GHz. 6 GHz reuses those numbers, so its channels
+/// can only be given as pairs; anything else is an error.
+pub fn candidate_pairs(candidates: &[u32]) -> Result<Vec<(u8, u32)>> {
+    candidates
+        .iter()
+        .map(|&ch| match ch {
+            1..=14 => Ok((1, ch)),
+            32..=177 => Ok((2, ch)),
+            _ => bail!("channel {ch} is not a 2.4 or 5 GHz channel"),
+        })
+        .collect()
+}
+
+// `candidates`, or an error naming the first pair that isn't a channel.
+fn checked_pairs(candidates: &[(u8, u32)]) -> Result<Vec<(u8, u32)>> {
+    if candidates.is_empty() {
+        bail!("no candidate channels");
+    }
+    if let Some(&(band, ch)) = candidates.iter().find(|&&(b, c)| channel_to_freq(b, c).is_none()) {
+        bail!("channel {ch} is not a {} channel", band_name(band));
+    }
+    Ok(candidates.to_vec())
+}
+
+// `rows` without our own AP: the connected BSSID, its "same device"
+// siblings and the other links of a multi-link AP.
+fn foreign_rows(rows: &[BssRow], connected: Option<&[u8; 6]>) -> Vec<BssRow> {
+    let own_mld = connected_mld(rows, connected);
+    rows.iter()
+        .filter(|r| match (connected, &r.bssid) {
+            (Some(c), Some(b)) => b != c && !same_device(c, b),
+            _ => true,
+        })
+        .filter(|r| own_mld.is_none() || r.mld_addr() != own_mld)
+        .cloned()
+        .collect()
+}
+
+// best_channel_among() on (freq_band(), channel) pairs, which 6 GHz
+// channel numbers need since they reuse the 2.4 / 5 GHz ones.
+fn best_channel_in(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    candidates: &[(u8, u32)],
+    busy: &HashMap<(u8, u32), f32>,
+    limits: &ChannelLimits,
+    cfg: &ChannelConfig,
+) -> Result<(u8, u32)> {
+    let candidates = legal_channels(candidates, limits)?;
+    let candidates = &candidates[..];
+    let current = connected.and_then(|c| rows.iter().find(|r| r.bssid.as_ref() == Some(c)));
+    let preferred: Vec<(u8, u32)> = match cfg.prefer_band.filter(|_| current.is_none()) {
+        Some(b) => candidates.iter().copied().filter(|&(band, _)| band == b).collect(),
+        None => Vec::new(),
+    };
+    let candidates = if preferred.is_empty() { candidates } else { &preferred };
+
+    let stats = channel_breakdown(&foreign_rows(rows, connected), None, busy, cfg);
+    let weight = |band: u8, ch: u32| {
+        stats
+            .iter()
+            .find(|s| s.band == band && s.channel == ch)
+            .map_or(0.0, |s| s.weight)
+    };
+
+    let mut best: Option<((u8, u32), f32)> = None;
+    for &(band, ch) in candidates {
+        let w = weight(band, ch);
+        if best.is_none_or(|(_, bw)| w < bw) {
+            best = Some(((band, ch), w));
+        }
+    }
+    let (best_key, best_w) = best.unwrap_or_default();
+
+    if let Some((Some(ch), Some(freq))) = current.map(|r| (r.channel, r.freq_mhz)) {
+        let band = freq_band(freq);
+        if candidates.contains(&(band, ch)) && weight(band, ch) <= best_w + cfg.margin {
+            return Ok((band, ch));
+        }
+    }
+    Ok(best_key)
+}
+
+// `channels` without the ones `limits` rules out; an error if none is left.
+fn legal_channels(channels: &[(u8, u32)], limits: &ChannelLimits) -> Result<Vec<(u8, u32)>> {
+    if channels.is_empty() {
+        bail!("no candidate channels");
+    }
+    let legal: Vec<(u8, u32)> = channels.iter().copied().filter(|&(b, c)| limits.allows(b, c)).collect();
+    if legal.is_empty() {
+        bail!(
+            "none of channels {:?} may be used ({})",
+            channels.iter().map(|&(_, c)| c).collect::<Vec<_>>(),
+            limits.describe()
+        );
+    }
+    Ok(legal)
+}
+
+/// One channel of channel_scores().
+#[derive(Debug, Clone)]
+pub struct ChannelScore {
+    pub band: u8,
+    pub channel: u32,
+    /// The weight best_channel_for() compared, survey busy time included;
+    /// lower is better.
+    pub weight: f32,
+    /// APs adding to `weight`: on the channel or overlapping it.
+    pub aps: u32,
+    /// Busy share from the channel survey, if any.
+    pub busy: Option<f32>,
+    /// Whether best_channel_for() picks this channel.
+    pub recommended: bool,
+}
+
+/// The channels best_channel_for() chooses among, lowest weight first,
+/// scored the way it scores them, with its pick marked:
+///
+/// - `candidates`, a 6 GHz connection's PSCs or, not connected,
+///   `cfg.prefer_band`'s channels: channel_breakdown's weight
+/// - Otherwise the channels channel_weights has a weight for (plus the
+///   current one), in the connected band if any
+///
+/// Channels `limits` rules out aren't listed. `busy` and `limits` as for
+/// best_channel_from_rows; best_channel_for() uses the latest ones.
+pub fn channel_scores(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    candidates: Option<&[(u8, u32)]>,
+    busy: &HashMap<(u8, u32), f32>,
+    limits: &ChannelLimits,
+    cfg: &ChannelConfig,
+) -> Result<Vec<ChannelScore>> {
+    let best = best_channel_with(rows, connected, candidates, busy, limits, cfg)?;
+    let current = connected
+        .and_then(|c| rows.iter().find(|r| r.bssid.as_ref() == Some(c)))
+        .and_then(|r| Some((freq_band(r.freq_mhz?), r.channel?)));
+
+    let listed: Option<Vec<(u8, u32)>> = match (candidates, current) {
+        (Some(c), _) => {
+            let all = legal_channels(&checked_pairs(c)?, limits)?;
+            let preferred: Vec<(u8, u32)> = match cfg.prefer_band.filter(|_| current.is_none()) {
+                Some(b) => all.iter().copied().filter(|&(band, _)| band == b).collect(),
+                None => Vec::new(),
+            };
+            Some(if preferred.is_empty() { all } else { preferred })
+        }
+        (None, Some((4, _))) => {
+            let psc: Vec<(u8, u32)> = CHANNELS_6_PSC.iter().map(|&c| (4, c)).collect();
+            Some(legal_channels(&psc, limits).unwrap_or_default())
         }
+        (None, Some(_)) => None,
+        (None, None) => cfg
+            .prefer_band
+            .and_then(|b| band_plan(b, false))
+            .and_then(|plan| legal_channels(&plan, limits).ok()),
+    };
+
+    let mut out: Vec<ChannelScore> = match listed {
+        Some(mut pool) => {
+            pool.sort_unstable();
+            pool.dedup();
+            let stats = channel_breakdown(&foreign_rows(rows, connected), None, busy, cfg);
+            pool.into_iter()
+                .map(|(band, channel)| {
+                    let st = stats.iter().find(|s| s.band == band && s.channel == channel);
+                    ChannelScore {
+                        band,
+                        channel,
+                        weight: st.map_or(0.0, |s| s.weight),
+                        aps: st.map_or(0, |s| s.co_channel + s.adjacent),
+                        busy: busy.get(&(band, channel)).copied(),
+                        recommended: false,
+                    }
+                })
+                .collect()
+        }
+        None => {
+            let mut tally = busy_tally(rows, connected, busy, cfg);
+            if let Some(cur) = current {
+                tally.retain(|&(band, _), _| band == cur.0);
+                tally.entry(cur).or_insert((0.0, 0));
+            }
+            tally
+                .into_iter()
+                .filter(|&((band, ch), _)| (band != 4 || is_psc(ch)) && limits.allows(band, ch))
+                .map(|((band, channel), (weight, aps))| ChannelScore {
+                    band,
+                    channel,
+                    weight,
+                    aps,
+                    busy: busy.get(&(band, channel)).copied(),
+                    recommended: false,
+                })
+                .collect()
+        }
+    };
+    out.sort_by(|a, b| a.weight.total_cmp(&b.weight).then((a.band, a.channel).cmp(&(b.band, b.channel))));
+
+    match out.iter().position(|s| (s.band, s.channel) == best) {
+        Some(i) => out[i].recommended = true,
+        // Nothing seen at all: best_channel_from_rows took the first legal
+        // channel of the band plans.
+        None => out.insert(
+            0,
+            ChannelScore {
+                band: best.0,
+                channel: best.1,
+                weight: 0.0,
+                aps: 0,
+                busy: None,
+                recommended: true,
+            },
+        ),
+    }
+    Ok(out)
+}
+
+/// channel_scores()'s pick as (freq_band(), channel, width MHz): the
+/// widest channel around it whose other 20 MHz channels are clean, i.e.
+/// weigh no more than the pick itself plus `cfg.margin`, and that
+/// `limits` allows.
+///
+/// - 2.4 GHz: 40 MHz only when a clean channel 4 above or below is
+///   there and no BSS heard is 40 MHz intolerant (HT capabilities or a
+///   20/40 BSS Coexistence element), as 802.11 requires
+/// - 5 GHz: 40, 80 or 160 MHz over the aligned blocks (36-40, 36-48,
+///   36-64, ...)
+/// - 6 GHz: the same up to 320 MHz
+///
+/// Without the radio's capabilities, 160 MHz is the widest recommended.
+pub fn best_channel_plan(
+    rows: &[BssRow],
+    connected: Option<&[u8; 6]>,
+    candidates: Option<&[(u8, u32)]>,
+    busy: &HashMap<(u8, u32), f32>,
+    limits: &ChannelLimits,
+    cfg: &ChannelConfig,
+) -> Result<(u8, u32, u32)> {
+    let scores = channel_scores(rows, connected, candidates, busy, limits, cfg)?;
+    let pick = scores
+        .iter()
+        .find(|s| s.recommended)
+        .ok_or_else(|| anyhow!("no channel recommended"))?;
+    let (band, channel) = (pick.band, pick.channel);
+    let foreign = foreign_rows(rows, connected);
+    let stats = channel_breakdown(&foreign, Some(band), busy, cfg);
+    let weight = |ch: u32| stats.iter().find(|s| s.channel == ch).map_or(0.0, |s| s.weight);
+    let clean = |ch: u32| weight(ch) <= weight(channel) + cfg.margin;
+
+    if band == 1 {
+        let intolerant = foreign
+            .iter()
+            .filter(|r| r.freq_mhz.is_some_and(|f| freq_band(f) == 1) && !cfg.stale(r))
+            .any(BssRow::forty_mhz_intolerant);
+        let secondary = [channel.checked_sub(4).filter(|&c| c >= 1), Some(channel + 4).filter(|&c| c <= 13)]
+            .into_iter()
+            .flatten()
+            .filter(|&c| clean(c) && limits.allows_width(band, &[channel, c], 40))
+            .min_by(|&a, &b| weight(a).total_cmp(&weight(b)));
+        let width = if channel <= 13 && !intolerant && secondary.is_some() { 40 } else { 20 };
+        return Ok((band, channel, width));
+    }
+
+    let widest = if limits.radio.is_some() { 320 } else { 160 };
+    let widths: &[u32] = if band == 4 { &[320, 160, 80, 40] } else { &[160, 80, 40] };
+    for &width in widths.iter().filter(|&&w| w <= widest) {
+        let Some(block) = width_block(band, channel, width) else {
+            continue;
+        };
+        if block.iter().all(|&c| c == channel || clean(c)) && limits.allows_width(band, &block, width) {
+            return Ok((band, channel, width));
+        }
+    }
+    Ok((band, channel, 20))
+}
+
+/// The 20 MHz channels of the aligned `width_mhz` block of 5 / 6 GHz
+/// channel `ch`, like channel_span() without a centre; None when the
+/// block runs past the band's channels.
+pub fn width_block(band: u8, ch: u32, width_mhz: u32) -> Option<Vec<u32>> {
+    let base = match (band, ch) {
+        (2, 149..) => 149,
+        (2, _) => 36,
+        _ => 1,
+    };
+    let span = width_mhz / 20;
+    let idx = ch.checked_sub(base)? / 4;
+    let lo = base + idx / span * span * 4;
+    let block: Vec<u32> = (0..span).map(|i| lo + i * 4).collect();
+    let exists = |c: &u32| match band {
+        2 => CHANNELS_5_20.contains(c) || CHANNELS_5_20_DFS.contains(c),
+        _ => (1..=233).contains(c),
+    };
+    block.iter().all(exists).then_some(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::chansurvey::BUSY_WEIGHT;
+    use crate::phycaps::{BandCaps, PhyChannel};
+    use crate::regdom::RegRule;
+
+    const OWN: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+    const CFG: ChannelConfig = ChannelConfig::DEFAULT;
+
+    // Distinct middle bytes, so no two count as the same device.
+    fn mac(n: u8) -> [u8; 6] {
+        [0x02, n, n, n, n, 0x01]
+    }
+
+    fn ap(bssid: [u8; 6], freq_mhz: u32, dbm: f32) -> BssRow {
+        let mut r = BssRow::from_parts(Some(bssid), Some(freq_mhz), Some(dbm), None);
+        r.set_ssid(b"test");
+        r
+    }
+
+    fn best(rows: &[BssRow], connected: Option<[u8; 6]>, limits: &ChannelLimits) -> Result<(u8, u32)> {
+        best_channel_from_rows(rows, connected.as_ref(), &HashMap::new(), limits, &CFG)
+    }
+
+    fn plan(rows: &[BssRow], connected: Option<[u8; 6]>) -> Result<(u8, u32, u32)> {
+        best_channel_plan(rows, connected.as_ref(), None, &HashMap::new(), &ChannelLimits::NONE, &CFG)
+    }
+
+    fn radio(band: u8, freqs: &[u32], disabled: bool) -> ChannelLimits {
+        let channels = freqs
+            .iter()
+            .map(|&freq_mhz| PhyChannel {
+                freq_mhz,
+                disabled,
+                no_ir: false,
+                radar: false,
+                max_tx_power_dbm: None,
+            })
+            .collect();
+        let bands = vec![BandCaps {
+            band,
+            channels,
+            ht_capa: None,
+            vht_capa: None,
+            he_phy: None,
+            eht_phy: None,
+        }];
+        ChannelLimits {
+            reg: None,
+            radio: Some(PhyCaps { bands }),
+        }
+    }
+
+    fn reg(start_mhz: u32, end_mhz: u32) -> ChannelLimits {
+        let rules = vec![RegRule {
+            start_khz: start_mhz * 1000,
+            end_khz: end_mhz * 1000,
+            max_bw_khz: 40_000,
+            max_ant_gain_mbi: None,
+            max_eirp_mbm: None,
+            flags: 0,
+            dfs_cac_ms: None,
+        }];
+        ChannelLimits {
+            reg: Some(RegDomain {
+                country: "XX".into(),
+                dfs_region: None,
+                rules,
+            }),
+            radio: None,
+        }
+    }
+
+    #[test]
+    fn freq_to_channel_stays_on_the_raster() {
+        assert_eq!(freq_to_channel(2412), Some(1));
+        assert_eq!(freq_to_channel(2484), Some(14));
+        assert_eq!(freq_to_channel(5180), Some(36));
+        assert_eq!(freq_to_channel(5935), Some(2));
+        assert_eq!(freq_to_channel(5975), Some(5));
+        for freq_mhz in [2413, 2414, 2485, 5182, 5977] {
+            assert_eq!(freq_to_channel(freq_mhz), None, "{freq_mhz}");
+        }
+    }
+
+    #[test]
+    fn no_rows_picks_the_first_allowed_channel() {
+        assert_eq!(best(&[], None, &ChannelLimits::NONE).unwrap(), (1, 1));
+        assert_eq!(best(&[], None, &radio(1, &[2437, 2462], false)).unwrap(), (1, 6));
+        assert_eq!(best(&[], None, &radio(2, &[5180, 5200], false)).unwrap(), (2, 36));
+        assert_eq!(best(&[], None, &reg(5170, 5250)).unwrap(), (2, 36));
+    }
+
+    #[test]
+    fn no_rows_and_no_allowed_channel_is_an_error() {
+        assert!(best(&[], None, &radio(1, &[2412, 2437, 2462], true)).is_err());
+    }
+
+    #[test]
+    fn unconnected_picks_across_bands() {
+        let rows = [
+            ap(mac(1), 2412, -50.0),
+            ap(mac(2), 2437, -50.0),
+            ap(mac(3), 2462, -50.0),
+            ap(mac(4), 5180, -50.0),
+            ap(mac(5), 5975, -75.0),
+        ];
+        // The weakest AP heard is on a 6 GHz PSC.
+        assert_eq!(best(&rows, None, &ChannelLimits::NONE).unwrap(), (4, 5));
+    }
+
+    #[test]
+    fn connected_stays_on_a_clean_channel() {
+        let rows = [ap(OWN, 2437, -40.0), ap(mac(1), 2412, -60.0)];
+        assert_eq!(best(&rows, Some(OWN), &ChannelLimits::NONE).unwrap(), (1, 6));
     }
 
-    // If we don't know what we're connected to, pick global argmin across bands.
-    if weight.is_empty() {
-        // No interference seen at all
-        return Ok(1);
+    #[test]
+    fn connected_leaves_a_channel_the_limits_forbid() {
+        let rows = [ap(OWN, 2472, -40.0), ap(mac(1), 2412, -60.0)];
+        let (band, ch) = best(&rows, Some(OWN), &reg(2402, 2462)).unwrap();
+        assert_eq!(band, 1);
+        assert!(ch <= 11, "moved to {ch}");
     }
 
-    let mut best: Option<(u32, f32)> = None;
-    for (&(_band, ch), &w) in &weight {
-        match best {
-            None => best = Some((ch, w)),
-            Some((_, bw)) if w < bw => best = Some((ch, w)),
-            _ => {}
+    #[test]
+    fn candidates_can_name_6_ghz_channels() {
+        let rows = [ap(mac(1), 5975, -50.0), ap(mac(2), 2412, -50.0)];
+        let candidates = [(1, 1), (4, 5), (4, 21)];
+        let pick = best_channel_among(&rows, None, &candidates, &HashMap::new(), &ChannelLimits::NONE, &CFG);
+        assert_eq!(pick.unwrap(), (4, 21));
+
+        // Bare numbers only reach 2.4 and 5 GHz.
+        assert_eq!(candidate_pairs(&[6, 36]).unwrap(), vec![(1, 6), (2, 36)]);
+        assert!(candidate_pairs(&[233]).is_err());
+        for bad in [&[][..], &[(4, 234)][..], &[(1, 6), (2, 14)][..]] {
+            assert!(best_channel_among(&rows, None, bad, &HashMap::new(), &ChannelLimits::NONE, &CFG).is_err());
         }
     }
 
-    Ok(best.unwrap().0)
+    #[test]
+    fn breakdown_weighs_co_channel_and_overlapping_aps() {
+        let rows = [ap(mac(1), 2437, -50.0), ap(mac(2), 2412, -60.0)];
+        let stats = channel_breakdown(&rows, Some(1), &HashMap::new(), &CFG);
+        let on = |ch: u32| stats.iter().find(|s| s.channel == ch).unwrap();
+
+        assert_eq!((on(6).aps, on(6).co_channel, on(6).adjacent), (1, 1, 0));
+        assert_eq!(on(6).weight, 50.0);
+        assert_eq!((on(1).aps, on(1).co_channel, on(1).adjacent), (1, 1, 0));
+        assert_eq!(on(1).weight, 40.0);
+        // Two channels off ch 1, three off ch 6.
+        assert_eq!((on(3).aps, on(3).co_channel, on(3).adjacent), (0, 0, 2));
+        let expected = 40.0 * 12.0 / 22.0 + 50.0 * 7.0 / 22.0;
+        assert!((on(3).weight - expected).abs() < 1e-3, "{}", on(3).weight);
+        assert!(stats.iter().all(|s| s.band == 1 && (1..=10).contains(&s.channel)));
+    }
+
+    #[test]
+    fn plan_widens_while_the_block_is_clean() {
+        let rows = [ap(OWN, 5180, -40.0), ap(mac(1), 5500, -50.0)];
+        assert_eq!(plan(&rows, Some(OWN)).unwrap(), (2, 36, 160));
+
+        // Ch 52 sits in the 160 MHz block, ch 48 (its neighbour) in the
+        // 80 MHz one.
+        let rows = [ap(OWN, 5180, -40.0), ap(mac(1), 5260, -50.0)];
+        assert_eq!(plan(&rows, Some(OWN)).unwrap(), (2, 36, 40));
+    }
+
+    #[test]
+    fn plan_keeps_2_4_ghz_at_20_mhz_when_an_ap_is_40_mhz_intolerant() {
+        let rows = [ap(OWN, 2412, -40.0), ap(mac(1), 2462, -60.0)];
+        assert_eq!(plan(&rows, Some(OWN)).unwrap(), (1, 1, 40));
+
+        // HT Capabilities with Forty MHz Intolerant set.
+        let mut ht = vec![45, 26, 0x00, 0x40];
+        ht.resize(28, 0);
+        let intolerant = BssRow::from_parts(Some(mac(1)), Some(2462), Some(-60.0), Some(&ht));
+        assert_eq!(plan(&[ap(OWN, 2412, -40.0), intolerant], Some(OWN)).unwrap(), (1, 1, 20));
+    }
+
+    #[test]
+    fn plan_follows_the_limits_it_is_given() {
+        let rows = [ap(OWN, 5180, -40.0)];
+        let limits = radio(2, &[5180, 5200], false);
+        let (band, ch, _) =
+            best_channel_plan(&rows, Some(&OWN), None, &HashMap::new(), &limits, &CFG).unwrap();
+        assert_eq!((band, ch), (2, 36));
+        // A connected AP stays in its band, and 5 GHz is all forbidden.
+        assert!(best_channel_plan(&rows, Some(&OWN), None, &HashMap::new(), &reg(2402, 2482), &CFG).is_err());
+    }
+
+    #[test]
+    fn matrix_columns_add_up_to_the_breakdown() {
+        let rows = [ap(OWN, 2462, -40.0), ap(mac(1), 2437, -50.0), ap(mac(2), 2412, -60.0)];
+        let busy = HashMap::from([((1, 6), 0.1)]);
+        let matrices = interference_matrix(&rows, Some(&OWN), Some(1), &busy, &CFG);
+
+        assert_eq!(matrices.len(), 1);
+        let m = &matrices[0];
+        // Our own AP on ch 11 isn't interference.
+        assert_eq!((m.band, m.observed.as_slice(), m.aps.as_slice()), (1, &[1, 6][..], &[1, 1][..]));
+        assert_eq!(m.candidates, (1..=10).collect::<Vec<u32>>());
+
+        let stats = channel_breakdown(&rows[1..], Some(1), &busy, &CFG);
+        for (j, &ch) in m.candidates.iter().enumerate() {
+            let column: f32 = m.weight.iter().map(|row| row[j]).sum();
+            assert!((column + m.busy[j] - m.total[j]).abs() < 1e-3, "ch {ch}");
+            let s = stats.iter().find(|s| s.channel == ch).unwrap();
+            assert!((m.total[j] - s.weight).abs() < 1e-3, "ch {ch}");
+        }
+        assert_eq!(m.weight[0][0], 40.0);
+        assert_eq!(m.weight[1][5], 50.0);
+        assert_eq!(m.weight[1][0], 0.0);
+        assert!((m.busy[5] - 0.1 * BUSY_WEIGHT).abs() < 1e-3);
+    }
 }
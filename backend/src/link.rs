@@ -0,0 +1,80 @@
+// src/link.rs
+//
+// Statistics of the link to the AP we are associated with, from a
+// GET_STATION dump on the station interface: signal (last frame and
+// average), tx / rx bitrate and MCS, packet, retry, failure and drop
+// counters, and the driver's expected throughput (rate control's estimate
+// of what the link can carry, where the driver has one). More telling
+// than RSSI alone: a -60 dBm link that keeps retrying is worse than it
+// looks.
+//
+// Exposes:
+//   - LinkInfo, LinkInfo::retry_ratio(),
+//     LinkInfo::estimated_throughput_mbps()
+//   - query(backend, ifindex) -> Result<Option<LinkInfo>>, None when not
+//     associated
+
+use anyhow::Result;
+use std::time::Instant;
+
+use crate::lib_rust::Backend;
+use crate::stamp::Stamp;
+use crate::{perf, phyrate};
+
+/// The link to the associated AP. Counters are cumulative since
+/// association.
+#[derive(Debug, Clone, Default)]
+pub struct LinkInfo {
+    pub bssid: [u8; 6],
+    /// Signal of the last frame received from the AP.
+    pub signal_dbm: Option<f32>,
+    /// The driver's running average of it.
+    pub signal_avg_dbm: Option<f32>,
+    pub tx_bitrate_kbps: Option<u32>,
+    pub rx_bitrate_kbps: Option<u32>,
+    /// MCS index of the last rate used, whatever the PHY (HT, VHT, HE, EHT);
+    /// None for legacy rates.
+    pub tx_mcs: Option<u8>,
+    pub rx_mcs: Option<u8>,
+    pub tx_packets: Option<u64>,
+    pub rx_packets: Option<u64>,
+    pub tx_retries: Option<u64>,
+    /// Frames given up on after the last retry.
+    pub tx_failed: Option<u64>,
+    /// Received frames dropped for any reason but decryption or duplicates.
+    pub rx_drop_misc: Option<u64>,
+    /// NL80211_STA_INFO_EXPECTED_THROUGHPUT.
+    pub expected_throughput_kbps: Option<u32>,
+    pub connected_secs: Option<u32>,
+    /// When these figures were read.
+    pub at: Option<Stamp>,
+}
+
+impl LinkInfo {
+    /// Retries per frame sent, 0.0 for a clean link.
+    pub fn retry_ratio(&self) -> Option<f32> {
+        let sent = self.tx_packets.filter(|&p| p > 0)?;
+        Some(self.tx_retries? as f32 / sent as f32)
+    }
+
+    /// Throughput the link can carry, Mbit/s: the driver's expected
+    /// throughput where it has one, else the last tx bitrate less what
+    /// the MAC costs (phyrate::MAC_EFFICIENCY).
+    pub fn estimated_throughput_mbps(&self) -> Option<f32> {
+        match (self.expected_throughput_kbps, self.tx_bitrate_kbps) {
+            (Some(kbps), _) => Some(kbps as f32 / 1000.0),
+            (None, Some(kbps)) => Some(kbps as f32 / 1000.0 * phyrate::MAC_EFFICIENCY),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The link of `ifindex` (the first station interface if None), from `b`'s
+/// provider. None when not associated.
+pub fn query(b: Backend, ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+    let start = Instant::now();
+    let link = b.provider().link(ifindex)?;
+    perf::record("link", b.name(), start.elapsed());
+    let at = Stamp::now();
+    Ok(link.map(|l| LinkInfo { at: Some(at), ..l }))
+}
@@ -0,0 +1,159 @@
+// src/linkmon.rs
+//
+// Link monitor: a background thread reads the connected link's statistics
+// (link::query) every interval and keeps the latest samples in a bounded
+// ring, so the app can plot signal and bitrate over the last minutes by
+// fetching them now and then instead of polling from Python every second.
+// A poll while not associated stores a sample without a link, so
+// disconnects show up as gaps; failed polls are only counted.
+//
+// Exposes:
+//   - LinkMonitor::start(Config) -> Result<LinkMonitor>, stopped by stop()
+//     or drop
+//   - LinkMonitor::samples(since) / status() / clear()
+
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::lib_rust::{Backend, Session};
+use crate::link::LinkInfo;
+use crate::stamp::Stamp;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub backend: Backend,
+    /// The interface to watch, by name; the default one if None.
+    pub iface: Option<String>,
+    pub interval: Duration,
+    /// Samples kept; the oldest go first.
+    pub capacity: usize,
+}
+
+/// One poll.
+#[derive(Debug, Clone)]
+pub struct LinkSample {
+    pub at: Stamp,
+    /// None while not associated.
+    pub link: Option<LinkInfo>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MonitorStatus {
+    pub running: bool,
+    pub polls: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+struct Shared {
+    samples: Mutex<VecDeque<LinkSample>>,
+    status: Mutex<MonitorStatus>,
+}
+
+impl Shared {
+    fn samples(&self) -> std::sync::MutexGuard<'_, VecDeque<LinkSample>> {
+        self.samples.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn status(&self) -> std::sync::MutexGuard<'_, MonitorStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct LinkMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    shared: Arc<Shared>,
+}
+
+impl LinkMonitor {
+    /// Open the interface and start polling it. Fails if the interface
+    /// can't be found; later failures only show in status().
+    pub fn start(cfg: Config) -> Result<LinkMonitor> {
+        if cfg.interval.is_zero() || cfg.capacity == 0 {
+            bail!("interval and capacity must be positive");
+        }
+        let session = Session::open_on(cfg.backend, cfg.iface.as_deref())?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let shared = Arc::new(Shared {
+            samples: Mutex::new(VecDeque::with_capacity(cfg.capacity.min(4096))),
+            status: Mutex::new(MonitorStatus {
+                running: true,
+                ..Default::default()
+            }),
+        });
+        let thread = {
+            let (stop, shared) = (stop.clone(), shared.clone());
+            std::thread::Builder::new()
+                .name("wifi-linkmon".into())
+                .spawn(move || run(&cfg, &session, &stop, &shared))?
+        };
+        Ok(LinkMonitor {
+            stop,
+            thread: Some(thread),
+            shared,
+        })
+    }
+
+    /// Stop polling; the samples stay.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+
+    /// The samples taken after `since` (a Stamp::mono, i.e.
+    /// time.monotonic() seconds), every one kept if None; oldest first.
+    pub fn samples(&self, since: Option<f64>) -> Vec<LinkSample> {
+        let ring = self.shared.samples();
+        let from = since.map_or(0, |t| ring.partition_point(|s| s.at.mono <= t));
+        ring.range(from..).cloned().collect()
+    }
+
+    pub fn status(&self) -> MonitorStatus {
+        self.shared.status().clone()
+    }
+
+    pub fn clear(&self) {
+        self.shared.samples().clear();
+    }
+}
+
+impl Drop for LinkMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(cfg: &Config, session: &Session, stop: &AtomicBool, shared: &Shared) {
+    while !stop.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let res = session.link_info();
+        {
+            let mut s = shared.status();
+            s.polls += 1;
+            if let Err(e) = &res {
+                s.errors += 1;
+                s.last_error = Some(format!("{e:#}"));
+            }
+        }
+        if let Ok(link) = res {
+            let at = link.as_ref().and_then(|l| l.at).unwrap_or_else(Stamp::now);
+            let mut ring = shared.samples();
+            if ring.len() >= cfg.capacity {
+                ring.pop_front();
+            }
+            ring.push_back(LinkSample { at, link });
+        }
+        // Sleep out the interval, checking for stop now and then.
+        while started.elapsed() < cfg.interval && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(cfg.interval.saturating_sub(started.elapsed()).min(Duration::from_millis(200)));
+        }
+    }
+    shared.status().running = false;
+}
@@ -0,0 +1,260 @@
+// src/locate.rs
+//
+// Self-localization on the floor plan: given where our own APs are (from
+// the floor-plan UI), estimate where this device is from the RSSI of a
+// scan, optionally sharpened with FTM (802.11mc) ranges measured
+// elsewhere, and name the room that puts us in. Surveys use it to label
+// samples without the user tapping the room.
+//
+// RSSI becomes a distance through a log-distance path-loss model (ref_dbm
+// at 1 m, `exponent` per decade). The floor is the one whose APs are
+// heard loudest; the position on it is a weighted least-squares fit of the
+// distances (Gauss-Newton from the weighted centroid), with near APs
+// trusted more than far ones and FTM ranges more than RSSI.
+//
+// Exposes:
+//   - set_map(Map) / clear_map()
+//   - locate(rows, ranges) -> Result<Position>
+
+use anyhow::{bail, Result};
+use std::sync::Mutex;
+
+use crate::lib_rust::{same_device, BssRow};
+
+// Distances are clamped to this range: closer is noise, farther is
+// meaningless indoors.
+const MIN_DIST_M: f64 = 0.5;
+const MAX_DIST_M: f64 = 60.0;
+// How much more an FTM range counts than an RSSI estimate at the same
+// distance.
+const FTM_WEIGHT: f64 = 4.0;
+const ITERATIONS: usize = 30;
+/// RSSI 1 m from a typical home AP, when the plan doesn't say.
+pub const REF_DBM: f64 = -40.0;
+
+/// One of our APs on the plan. Coordinates are metres in the plan's frame.
+#[derive(Debug, Clone)]
+pub struct ApPos {
+    pub bssid: [u8; 6],
+    pub x: f64,
+    pub y: f64,
+    pub floor: i32,
+    /// RSSI heard 1 m from the AP.
+    pub ref_dbm: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Room {
+    pub name: String,
+    pub floor: i32,
+    /// Outline, in order; closed implicitly.
+    pub polygon: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub aps: Vec<ApPos>,
+    pub rooms: Vec<Room>,
+    /// Path-loss exponent: 2 in free space, about 3 through walls.
+    pub exponent: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub floor: i32,
+    /// RMS misfit of the distances, metres: a rough error radius.
+    pub error_m: f64,
+    /// APs the fit used.
+    pub aps: usize,
+    /// The room containing the position, else the one whose outline is
+    /// nearest on that floor.
+    pub room: Option<String>,
+}
+
+static MAP: Mutex<Option<Map>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Map>> {
+    MAP.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn set_map(map: Map) -> Result<()> {
+    if map.aps.is_empty() {
+        bail!("the floor plan has no APs");
+    }
+    if !(1.0..=6.0).contains(&map.exponent) {
+        bail!("path-loss exponent {} out of range (1 to 6)", map.exponent);
+    }
+    if let Some(r) = map.rooms.iter().find(|r| r.polygon.len() < 3) {
+        bail!("room {:?}: outline needs at least 3 points", r.name);
+    }
+    *lock() = Some(map);
+    Ok(())
+}
+
+pub fn clear_map() {
+    *lock() = None;
+}
+
+// One AP's measurement: where it is, how far we think we are, how much to
+// trust that.
+struct Range {
+    x: f64,
+    y: f64,
+    dist: f64,
+    weight: f64,
+}
+
+/// Where `rows` (a scan taken here) place us on the map set with set_map().
+/// `ranges` are FTM distances in metres by BSSID and win over RSSI for
+/// their AP.
+pub fn locate(rows: &[BssRow], ranges: &[([u8; 6], f64)]) -> Result<Position> {
+    let guard = lock();
+    let Some(map) = guard.as_ref() else {
+        bail!("no floor plan set (set_floor_plan)");
+    };
+
+    // Per AP: its own BSSID's signal, else the loudest sibling radio's.
+    let mut heard: Vec<(&ApPos, f64, bool)> = Vec::new();
+    for ap in &map.aps {
+        if let Some(&(_, d)) = ranges.iter().find(|(b, _)| *b == ap.bssid) {
+            heard.push((ap, d.clamp(MIN_DIST_M, MAX_DIST_M), true));
+            continue;
+        }
+        let exact = rows.iter().find(|r| r.bssid == Some(ap.bssid)).and_then(|r| r.signal_dbm);
+        let sibling = || {
+            rows.iter()
+                .filter(|r| r.bssid.is_some_and(|b| same_device(&b, &ap.bssid)))
+                .filter_map(|r| r.signal_dbm)
+                .max_by(f32::total_cmp)
+        };
+        if let Some(sig) = exact.or_else(sibling) {
+            let d = 10f64.powf((ap.ref_dbm - sig as f64) / (10.0 * map.exponent));
+            heard.push((ap, d.clamp(MIN_DIST_M, MAX_DIST_M), false));
+        }
+    }
+    if heard.is_empty() {
+        bail!("none of the floor plan's APs is in the scan");
+    }
+
+    // Floor whose APs are closest overall.
+    let mut floors: Vec<(i32, f64)> = Vec::new();
+    for (ap, d, _) in &heard {
+        match floors.iter_mut().find(|(f, _)| *f == ap.floor) {
+            Some((_, w)) => *w += 1.0 / (d * d),
+            None => floors.push((ap.floor, 1.0 / (d * d))),
+        }
+    }
+    let floor = floors
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |&(f, _)| f);
+
+    let ranges: Vec<Range> = heard
+        .iter()
+        .filter(|(ap, _, _)| ap.floor == floor)
+        .map(|&(ap, dist, ftm)| Range {
+            x: ap.x,
+            y: ap.y,
+            dist,
+            weight: if ftm { FTM_WEIGHT } else { 1.0 } / (dist * dist),
+        })
+        .collect();
+    let (x, y, error_m) = solve(&ranges);
+
+    let room = room_at(&map.rooms, floor, x, y);
+    Ok(Position {
+        x,
+        y,
+        floor,
+        error_m,
+        aps: ranges.len(),
+        room,
+    })
+}
+
+// Weighted least squares for sum w (|p - a| - d)^2.
+fn solve(ranges: &[Range]) -> (f64, f64, f64) {
+    let total: f64 = ranges.iter().map(|r| r.weight).sum();
+    let mut x = ranges.iter().map(|r| r.weight * r.x).sum::<f64>() / total;
+    let mut y = ranges.iter().map(|r| r.weight * r.y).sum::<f64>() / total;
+
+    if ranges.len() >= 2 {
+        for _ in 0..ITERATIONS {
+            // Normal equations J^T W J delta = -J^T W r.
+            let (mut a11, mut a12, mut a22, mut b1, mut b2) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for r in ranges {
+                let (dx, dy) = (x - r.x, y - r.y);
+                let norm = dx.hypot(dy).max(1e-6);
+                let (jx, jy) = (dx / norm, dy / norm);
+                let res = norm - r.dist;
+                a11 += r.weight * jx * jx;
+                a12 += r.weight * jx * jy;
+                a22 += r.weight * jy * jy;
+                b1 -= r.weight * jx * res;
+                b2 -= r.weight * jy * res;
+            }
+            // A little damping keeps two-AP (collinear) fits from blowing up.
+            let damp = 1e-3 * (a11 + a22).max(1e-9);
+            let (a11, a22) = (a11 + damp, a22 + damp);
+            let det = a11 * a22 - a12 * a12;
+            if det.abs() < 1e-12 {
+                break;
+            }
+            let step_x = (b1 * a22 - b2 * a12) / det;
+            let step_y = (a11 * b2 - a12 * b1) / det;
+            x += step_x;
+            y += step_y;
+            if step_x.hypot(step_y) < 1e-3 {
+                break;
+            }
+        }
+    }
+
+    let misfit = ranges
+        .iter()
+        .map(|r| r.weight * ((x - r.x).hypot(y - r.y) - r.dist).powi(2))
+        .sum::<f64>()
+        / total;
+    let error_m = if ranges.len() == 1 { ranges[0].dist } else { misfit.sqrt() };
+    (x, y, error_m)
+}
+
+fn contains(poly: &[(f64, f64)], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = poly.len() - 1;
+    for i in 0..poly.len() {
+        let ((xi, yi), (xj, yj)) = (poly[i], poly[j]);
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+// Distance from (x, y) to the outline of `poly`.
+fn edge_distance(poly: &[(f64, f64)], x: f64, y: f64) -> f64 {
+    let mut best = f64::INFINITY;
+    for (i, &(ax, ay)) in poly.iter().enumerate() {
+        let (bx, by) = poly[(i + 1) % poly.len()];
+        let (vx, vy) = (bx - ax, by - ay);
+        let len2 = vx * vx + vy * vy;
+        let t = if len2 > 0.0 {
+            (((x - ax) * vx + (y - ay) * vy) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        best = best.min((x - ax - t * vx).hypot(y - ay - t * vy));
+    }
+    best
+}
+
+fn room_at(rooms: &[Room], floor: i32, x: f64, y: f64) -> Option<String> {
+    let on_floor = || rooms.iter().filter(|r| r.floor == floor);
+    on_floor()
+        .find(|r| contains(&r.polygon, x, y))
+        .or_else(|| on_floor().min_by(|a, b| edge_distance(&a.polygon, x, y).total_cmp(&edge_distance(&b.polygon, x, y))))
+        .map(|r| r.name.clone())
+}
@@ -0,0 +1,248 @@
+// src/logs.rs
+//
+// Logging. The scan, trigger and parse paths emit tracing events (target
+// wifi_backend::<module>); the layer installed here turns those at or
+// above the current level into Records, named after their module the way
+// Python loggers are ("wifi_backend.nl_raw", a child of "wifi_backend")
+// and prefixed with the spans they happened in, the way
+// tracing-subscriber's fmt does ("scan{backend=mock}: ...").
+//
+// Records are only queued: tokio's workers log too, and one that waited
+// for the GIL could deadlock with the thread holding it, which may be
+// waiting on that worker. lib.rs drains the queue on a thread of its own
+// into Python's logging.
+//
+// Exposes:
+//   - install() -> Option<Receiver<Record>>, Record, Value
+//   - set_level(level) / level() / level_from_name(name): Python logging
+//     levels (10 = DEBUG ... 50 = CRITICAL; 5 = TRACE)
+
+use anyhow::{bail, Result};
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+pub const TRACE: u32 = 5;
+pub const DEBUG: u32 = 10;
+pub const INFO: u32 = 20;
+pub const WARNING: u32 = 30;
+pub const ERROR: u32 = 40;
+pub const CRITICAL: u32 = 50;
+
+// Records waiting for the Python side; more are dropped rather than
+// stalling the scan that logs them.
+const QUEUE_LEN: usize = 1024;
+
+// Python's default, so nothing new shows up until asked for.
+static LEVEL: AtomicU32 = AtomicU32::new(WARNING);
+
+/// A field value, typed as far as tracing records it.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => f.write_str(s),
+            Value::Int(v) => v.fmt(f),
+            Value::UInt(v) => v.fmt(f),
+            Value::Float(v) => v.fmt(f),
+            Value::Bool(v) => v.fmt(f),
+        }
+    }
+}
+
+/// One event, ready for logging.Logger.log().
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// "wifi_backend.nl_raw" and so on.
+    pub logger: String,
+    pub level: u32,
+    /// Spans, message and fields as one line.
+    pub message: String,
+    /// The event's fields other than the message.
+    pub fields: Vec<(&'static str, Value)>,
+}
+
+fn py_level(level: &Level) -> u32 {
+    match *level {
+        Level::TRACE => TRACE,
+        Level::DEBUG => DEBUG,
+        Level::INFO => INFO,
+        Level::WARN => WARNING,
+        Level::ERROR => ERROR,
+    }
+}
+
+/// The Python level of `name` ("debug", "WARNING", "trace", ...).
+pub fn level_from_name(name: &str) -> Result<u32> {
+    Ok(match name.to_ascii_uppercase().as_str() {
+        "TRACE" => TRACE,
+        "DEBUG" => DEBUG,
+        "INFO" => INFO,
+        "WARN" | "WARNING" => WARNING,
+        "ERROR" => ERROR,
+        "CRITICAL" | "FATAL" => CRITICAL,
+        _ => bail!("unknown log level {name:?}"),
+    })
+}
+
+/// Queue events at `level` and above (a Python logging level).
+pub fn set_level(level: u32) {
+    LEVEL.store(level, Ordering::Relaxed);
+    // The callsites cache the max_level_hint() they saw.
+    tracing::callsite::rebuild_interest_cache();
+}
+
+pub fn level() -> u32 {
+    LEVEL.load(Ordering::Relaxed)
+}
+
+/// Make this crate's events Records, if no tracing subscriber is set yet
+/// (an embedding Rust program may have its own). Returns their queue the
+/// first time.
+pub fn install() -> Option<Receiver<Record>> {
+    let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+    let subscriber = tracing_subscriber::registry().with(Queue { tx });
+    tracing::subscriber::set_global_default(subscriber).ok()?;
+    Some(rx)
+}
+
+// Fields as "k=v" after the message, and as values for Record::fields.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    pairs: Vec<(&'static str, Value)>,
+}
+
+impl Fields {
+    fn push(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.pairs.push((field.name(), value));
+        }
+    }
+
+    // "k=v k=v" of the fields other than the message.
+    fn pairs_text(&self) -> String {
+        let mut s = String::new();
+        for (k, v) in &self.pairs {
+            if !s.is_empty() {
+                s.push(' ');
+            }
+            let _ = write!(s, "{k}={v}");
+        }
+        s
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, Value::Str(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, Value::Str(value.into()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, Value::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, Value::UInt(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, Value::Float(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, Value::Bool(value));
+    }
+}
+
+// A span's fields as text, kept in its extensions for the events in it.
+struct SpanFields(String);
+
+struct Queue {
+    tx: SyncSender<Record>,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Queue {
+    fn register_callsite(&self, meta: &'static Metadata<'static>) -> Interest {
+        if meta.target().starts_with("wifi_backend") {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, meta: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        py_level(meta.level()) >= level()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(match level() {
+            l if l <= TRACE => LevelFilter::TRACE,
+            l if l <= DEBUG => LevelFilter::DEBUG,
+            l if l <= INFO => LevelFilter::INFO,
+            l if l <= WARNING => LevelFilter::WARN,
+            l if l <= CRITICAL => LevelFilter::ERROR,
+            _ => LevelFilter::OFF,
+        })
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields.pairs_text()));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let mut message = String::new();
+        for span in ctx.event_scope(event).into_iter().flat_map(|s| s.from_root()) {
+            message.push_str(span.name());
+            if let Some(SpanFields(f)) = span.extensions().get::<SpanFields>().filter(|f| !f.0.is_empty()) {
+                let _ = write!(message, "{{{f}}}");
+            }
+            message.push_str(": ");
+        }
+        message.push_str(&fields.message);
+        let pairs = fields.pairs_text();
+        if !pairs.is_empty() {
+            if !fields.message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&pairs);
+        }
+
+        // A full queue means Python isn't keeping up; drop the record.
+        let _ = self.tx.try_send(Record {
+            logger: meta.target().replace("::", "."),
+            level: py_level(meta.level()),
+            message,
+            fields: fields.pairs,
+        });
+    }
+}
@@ -0,0 +1,935 @@
+// src/mock.rs
+//
+// Mock provider (Backend::Mock, MockBackend): scans, the connected BSSID and its link
+// statistics, the AP station dumps, channel surveys, the regulatory domain
+// and the radio's capabilities come from a JSON fixture instead of
+// nl80211, and so do the events, so the app
+// and its tests run without radio hardware. Faults make chosen calls fail
+// the way the kernel does (EBUSY, ENODEV, EPERM, a scan timeout).
+//
+// Fixture:
+//   {
+//     "scans": [[{bssid, ssid?, freq_mhz?, signal_dbm?, seen_ms_ago?, capability?,
+//                 beacon_interval?, ies?,
+//                 status?: "associated" | "authenticated" | "ibss_joined"},
+//                ...], ...],
+//     "connected_bssid": "aa:bb:cc:dd:ee:ff" | null,
+//     "links": [{signal_dbm?, signal_avg_dbm?, tx_bitrate_kbps?,
+//                rx_bitrate_kbps?, tx_mcs?, rx_mcs?, tx_packets?, rx_packets?,
+//                tx_retries?, tx_failed?, rx_drop_misc?,
+//                expected_throughput_kbps?, connected_secs?}, ...],
+//     "interfaces": [{name, ifindex?, mac?, type?: "station" | "ap" | ..., wiphy?}],
+//     "radios": [{bssid, freq_mhz}],
+//     "stations": [[{mac, bssid, freq_mhz?, signal_dbm?, mcs?, tx_packets?,
+//                    tx_retries?, tx_bytes?, rx_bytes?, tx_duration_us?,
+//                    rx_duration_us?, tx_bitrate_kbps?, rx_bitrate_kbps?}, ...], ...],
+//     "surveys": [[{freq_mhz, noise_dbm?, in_use?, active_ms?, busy_ms?,
+//                   ext_busy_ms?, rx_ms?, tx_ms?, scan_ms?}, ...], ...],
+//     "regdomain": {country, dfs_region?, rules: [{start_mhz, end_mhz,
+//                   max_bandwidth_mhz?, max_eirp_dbm?, max_antenna_gain_dbi?,
+//                   flags?: ["no-ir", "dfs", ...], dfs_cac_ms?}]},
+//     "wiphy": {bands: [{band: "2.4GHz" | "5GHz" | "6GHz", channels:
+//               [{freq_mhz, disabled?, no_ir?, radar?, max_tx_power_dbm?}],
+//               ht_capa?, vht_capa?, he_phy?, eht_phy?}]},
+//     "events": [{type: "connected" | "disconnected" | "roamed" | ...,
+//                 ifindex?, bssid?, status?, reason?, by_ap?, freq_mhz?}],
+//     "delay_ms": 0,
+//     "faults": [{op: "scan" | "connected" | "stations" | "survey" | "reg" |
+//                 "wiphy" | "link", call?: n, error}]
+//   }
+//
+// Scans, links, station dumps and surveys are served in turn, the last one
+// repeating. Links are those of connected_bssid, none when it is null;
+// without "links" the link only has the BSSID. Without "regdomain" or "wiphy" those queries fail, as on
+// drivers that don't report them; max_bandwidth_mhz defaults to 160.
+// he_phy / eht_phy are hex like `ies`. "interfaces" defaults to one
+// station interface, wlan0; every interface serves the same data. Each
+// event subscription gets the fixture's events first, then a
+// scan_finished event after every scan.
+// `ies` is hex; `seen_ms_ago` defaults to 0, i.e. heard by this scan.
+// Once any row has a `status`, scans mark the connected BSS the way
+// nl80211's do and the snapshots read it from there. A
+// fault with `call` hits only that (0-based) call of `op`, one without
+// hits every call. `error` is "ebusy", "enodev", "eperm", "timeout" or any
+// other text, which is returned as is. Every call waits `delay_ms` first,
+// timeouts included. A scan retrying after "ebusy" makes a call per try.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::chansurvey::ChannelSurvey;
+use crate::error::{kind_of, ErrorKind, WifiError};
+use crate::events::{EventKind, Subscription, WifiEvent};
+use crate::lib_rust::{
+    band_from_name, is_busy, not_permitted, parse_mac, BssRow, BssStatus, NoTrigger, Retry, RowSink,
+    ScanOptions,
+};
+use crate::link::LinkInfo;
+use crate::netlink::{iftype_name, NlError, WifiIface, IFTYPE_STATION};
+use crate::phycaps::{BandCaps, PhyCaps, PhyChannel};
+use crate::provider::{ScanFuture, WifiBackend};
+use crate::regdom::{RegDomain, RegRule};
+use crate::stamp::Stamp;
+use crate::stations::{ApRadio, Station};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Scan,
+    Connected,
+    Stations,
+    Survey,
+    Reg,
+    Wiphy,
+    Link,
+}
+
+impl Op {
+    pub fn from_name(name: &str) -> Result<Op> {
+        match name {
+            "scan" => Ok(Op::Scan),
+            "connected" => Ok(Op::Connected),
+            "stations" => Ok(Op::Stations),
+            "survey" => Ok(Op::Survey),
+            "reg" => Ok(Op::Reg),
+            "wiphy" => Ok(Op::Wiphy),
+            "link" => Ok(Op::Link),
+            other => bail!(
+                "unknown mock op {other:?} (expected \"scan\", \"connected\", \"stations\", \"survey\", \"reg\", \"wiphy\" or \"link\")"
+            ),
+        }
+    }
+
+    // The nl80211 command the real providers would have failed in.
+    fn cmd(self) -> &'static str {
+        match self {
+            Op::Scan => "CmdTriggerScan",
+            Op::Connected | Op::Stations | Op::Link => "CmdGetStation",
+            Op::Survey => "CmdGetSurvey",
+            Op::Reg => "CmdGetReg",
+            Op::Wiphy => "CmdGetWiphy",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Fault {
+    op: Op,
+    call: Option<usize>,
+    error: String,
+}
+
+#[derive(Debug, Default)]
+struct Mock {
+    /// Rows with their fixture `seen_ms_ago`, stamped when served.
+    scans: Vec<Vec<(BssRow, u32)>>,
+    connected: Option<[u8; 6]>,
+    links: Vec<LinkInfo>,
+    interfaces: Vec<WifiIface>,
+    radios: Vec<ApRadio>,
+    stations: Vec<Vec<Station>>,
+    surveys: Vec<Vec<ChannelSurvey>>,
+    regdomain: Option<RegDomain>,
+    wiphy: Option<PhyCaps>,
+    events: Vec<WifiEvent>,
+    delay: Duration,
+    faults: Vec<Fault>,
+    /// Calls so far per Op, in Op order.
+    calls: [usize; 7],
+}
+
+static MOCK: Mutex<Option<Mock>> = Mutex::new(None);
+// Tests share MOCK: each holds this while its fixture is loaded.
+#[cfg(test)]
+pub(crate) static TEST_LOCK: Mutex<()> = Mutex::new(());
+// Live events for subscribers, kept across fixture loads.
+static EVENTS: OnceLock<broadcast::Sender<WifiEvent>> = OnceLock::new();
+
+fn event_tx() -> &'static broadcast::Sender<WifiEvent> {
+    EVENTS.get_or_init(|| broadcast::channel(256).0)
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Mock>> {
+    MOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Serve the fixture at `path` from now on. Returns the number of scans.
+pub fn load(path: &Path) -> Result<usize> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    load_str(&text).with_context(|| format!("parse {}", path.display()))
+}
+
+/// load() from the JSON text itself.
+pub fn load_str(text: &str) -> Result<usize> {
+    let root: Value = serde_json::from_str(text)?;
+    let Some(fx) = root.as_object() else {
+        bail!("expected a JSON object");
+    };
+    let list = |key: &str| -> Result<Vec<Value>> {
+        match fx.get(key) {
+            None | Some(Value::Null) => Ok(Vec::new()),
+            Some(Value::Array(items)) => Ok(items.clone()),
+            Some(_) => bail!("{key}: expected a list"),
+        }
+    };
+    let nested = |key: &str| -> Result<Vec<Vec<Value>>> {
+        list(key)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| match v {
+                Value::Array(items) => Ok(items),
+                _ => bail!("{key}[{i}]: expected a list"),
+            })
+            .collect()
+    };
+
+    let scans = nested("scans")?
+        .iter()
+        .map(|rows| rows.iter().map(row).collect::<Result<Vec<_>>>())
+        .collect::<Result<Vec<_>>>()?;
+    let links = list("links")?.iter().map(link_info).collect::<Result<Vec<_>>>()?;
+    let stations = nested("stations")?
+        .iter()
+        .map(|dump| dump.iter().map(station).collect::<Result<Vec<_>>>())
+        .collect::<Result<Vec<_>>>()?;
+    let surveys = nested("surveys")?
+        .iter()
+        .map(|dump| dump.iter().map(channel_survey).collect::<Result<Vec<_>>>())
+        .collect::<Result<Vec<_>>>()?;
+    let regdomain = match fx.get("regdomain") {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(reg_domain(v).context("regdomain")?),
+    };
+    let wiphy = match fx.get("wiphy") {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(wiphy_caps(v).context("wiphy")?),
+    };
+    let events = list("events")?.iter().map(wifi_event).collect::<Result<Vec<_>>>()?;
+    let mut interfaces = list("interfaces")?
+        .iter()
+        .enumerate()
+        .map(|(i, v)| wifi_iface(v, i))
+        .collect::<Result<Vec<_>>>()?;
+    if interfaces.is_empty() {
+        interfaces = default_interfaces();
+    }
+    let radios = list("radios")?
+        .iter()
+        .map(|r| {
+            Ok(ApRadio {
+                bssid: mac(r, "bssid")?.ok_or_else(|| anyhow!("radio without bssid"))?,
+                freq_mhz: num(r, "freq_mhz")?.ok_or_else(|| anyhow!("radio without freq_mhz"))? as u32,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let faults = list("faults")?
+        .iter()
+        .map(|f| {
+            Ok(Fault {
+                op: Op::from_name(f.get("op").and_then(Value::as_str).unwrap_or_default())?,
+                call: num(f, "call")?.map(|n| n as usize),
+                error: f
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("fault without error"))?
+                    .to_owned(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let n = scans.len();
+    *lock() = Some(Mock {
+        scans,
+        connected: mac(&root, "connected_bssid")?,
+        links,
+        interfaces,
+        radios,
+        stations,
+        surveys,
+        regdomain,
+        wiphy,
+        events,
+        delay: Duration::from_millis(num(&root, "delay_ms")?.unwrap_or(0.0) as u64),
+        faults,
+        calls: [0; 7],
+    });
+    Ok(n)
+}
+
+/// Serve canned rows instead of a fixture file, e.g. from Rust tests:
+/// `scans` in turn as fixture scans are, all heard by the scan that
+/// returns them, connected to `connected`. Returns the number of scans.
+pub fn load_rows(scans: Vec<Vec<BssRow>>, connected: Option<[u8; 6]>) -> usize {
+    let n = scans.len();
+    *lock() = Some(Mock {
+        scans: scans
+            .into_iter()
+            .map(|rows| rows.into_iter().map(|r| (r, 0)).collect())
+            .collect(),
+        connected,
+        interfaces: default_interfaces(),
+        ..Mock::default()
+    });
+    n
+}
+
+// One station interface, wlan0, for fixtures that don't list any.
+fn default_interfaces() -> Vec<WifiIface> {
+    vec![WifiIface {
+        ifindex: 1,
+        name: "wlan0".to_owned(),
+        iftype: IFTYPE_STATION,
+        ..WifiIface::default()
+    }]
+}
+
+/// Whether a fixture is loaded.
+pub fn loaded() -> bool {
+    lock().is_some()
+}
+
+/// Add a fault to the loaded fixture; `call` counts from the calls made so
+/// far, None fails every following call.
+pub fn inject(op: Op, call: Option<usize>, error: &str) -> Result<()> {
+    let mut guard = lock();
+    let mock = guard.as_mut().ok_or_else(not_loaded)?;
+    mock.faults.push(Fault {
+        op,
+        call: call.map(|c| c + mock.calls[op as usize]),
+        error: error.to_owned(),
+    });
+    Ok(())
+}
+
+fn not_loaded() -> anyhow::Error {
+    anyhow!("mock backend selected but no fixture loaded (load_mock_fixture)")
+}
+
+fn num(v: &Value, key: &str) -> Result<Option<f64>> {
+    match v.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(x) => x.as_f64().map(Some).ok_or_else(|| anyhow!("{key}: expected a number")),
+    }
+}
+
+fn mac(v: &Value, key: &str) -> Result<Option<[u8; 6]>> {
+    match v.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => parse_mac(s).map(Some),
+        Some(_) => bail!("{key}: expected a MAC address string"),
+    }
+}
+
+fn hex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        bail!("ies: odd number of hex digits");
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| anyhow!("ies: bad hex at {i}"))
+        })
+        .collect()
+}
+
+fn row(v: &Value) -> Result<(BssRow, u32)> {
+    let ies = v.get("ies").and_then(Value::as_str).map(hex).transpose()?;
+    let mut row = BssRow::from_parts(
+        mac(v, "bssid")?,
+        num(v, "freq_mhz")?.map(|f| f as u32),
+        num(v, "signal_dbm")?.map(|s| s as f32),
+        ies.as_deref(),
+    );
+    if let Some(ssid) = v.get("ssid").and_then(Value::as_str) {
+        row.set_ssid(ssid.as_bytes());
+    }
+    row.capability = num(v, "capability")?.map(|c| c as u16);
+    row.beacon_interval = num(v, "beacon_interval")?.map(|b| b as u16);
+    if let Some(status) = v.get("status").and_then(Value::as_str) {
+        row.status = Some(BssStatus::from_name(status)?);
+    }
+    Ok((row, num(v, "seen_ms_ago")?.map_or(0, |ms| ms as u32)))
+}
+
+fn station(v: &Value) -> Result<Station> {
+    let u64_of = |key: &str| -> Result<Option<u64>> { Ok(num(v, key)?.map(|x| x as u64)) };
+    Ok(Station {
+        mac: mac(v, "mac")?.ok_or_else(|| anyhow!("station without mac"))?,
+        bssid: mac(v, "bssid")?.ok_or_else(|| anyhow!("station without bssid"))?,
+        freq_mhz: num(v, "freq_mhz")?.map(|f| f as u32),
+        signal_dbm: num(v, "signal_dbm")?.map(|s| s as f32),
+        dual_band: v.get("dual_band").and_then(Value::as_bool),
+        mcs: num(v, "mcs")?.map(|m| m as u8),
+        tx_packets: u64_of("tx_packets")?,
+        tx_retries: u64_of("tx_retries")?,
+        tx_bytes: u64_of("tx_bytes")?,
+        rx_bytes: u64_of("rx_bytes")?,
+        tx_duration_us: u64_of("tx_duration_us")?,
+        rx_duration_us: u64_of("rx_duration_us")?,
+        tx_bitrate_kbps: num(v, "tx_bitrate_kbps")?.map(|k| k as u32),
+        rx_bitrate_kbps: num(v, "rx_bitrate_kbps")?.map(|k| k as u32),
+        at: None,
+    })
+}
+
+// The BSSID is the fixture's connected_bssid, filled in when served.
+fn link_info(v: &Value) -> Result<LinkInfo> {
+    let u64_of = |key: &str| -> Result<Option<u64>> { Ok(num(v, key)?.map(|x| x as u64)) };
+    let u32_of = |key: &str| -> Result<Option<u32>> { Ok(num(v, key)?.map(|x| x as u32)) };
+    let dbm = |key: &str| -> Result<Option<f32>> { Ok(num(v, key)?.map(|x| x as f32)) };
+    Ok(LinkInfo {
+        signal_dbm: dbm("signal_dbm")?,
+        signal_avg_dbm: dbm("signal_avg_dbm")?,
+        tx_bitrate_kbps: u32_of("tx_bitrate_kbps")?,
+        rx_bitrate_kbps: u32_of("rx_bitrate_kbps")?,
+        tx_mcs: num(v, "tx_mcs")?.map(|m| m as u8),
+        rx_mcs: num(v, "rx_mcs")?.map(|m| m as u8),
+        tx_packets: u64_of("tx_packets")?,
+        rx_packets: u64_of("rx_packets")?,
+        tx_retries: u64_of("tx_retries")?,
+        tx_failed: u64_of("tx_failed")?,
+        rx_drop_misc: u64_of("rx_drop_misc")?,
+        expected_throughput_kbps: u32_of("expected_throughput_kbps")?,
+        connected_secs: u32_of("connected_secs")?,
+        ..LinkInfo::default()
+    })
+}
+
+fn channel_survey(v: &Value) -> Result<ChannelSurvey> {
+    let ms = |key: &str| -> Result<Option<u64>> { Ok(num(v, key)?.map(|x| x as u64)) };
+    Ok(ChannelSurvey {
+        freq_mhz: num(v, "freq_mhz")?.ok_or_else(|| anyhow!("survey without freq_mhz"))? as u32,
+        noise_dbm: num(v, "noise_dbm")?.map(|n| n as f32),
+        in_use: v.get("in_use").and_then(Value::as_bool).unwrap_or(false),
+        active_ms: ms("active_ms")?,
+        busy_ms: ms("busy_ms")?,
+        ext_busy_ms: ms("ext_busy_ms")?,
+        rx_ms: ms("rx_ms")?,
+        tx_ms: ms("tx_ms")?,
+        scan_ms: ms("scan_ms")?,
+        busy: None,
+    })
+}
+
+fn reg_domain(v: &Value) -> Result<RegDomain> {
+    let country = v
+        .get("country")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("regdomain without country"))?;
+    let rules = match v.get("rules") {
+        Some(Value::Array(rules)) => rules.iter().map(reg_rule).collect::<Result<Vec<_>>>()?,
+        _ => bail!("regdomain without rules"),
+    };
+    Ok(RegDomain {
+        country: country.to_owned(),
+        dfs_region: num(v, "dfs_region")?.map(|r| r as u8),
+        rules,
+    })
+}
+
+fn reg_rule(v: &Value) -> Result<RegRule> {
+    let mhz = |key: &str| -> Result<Option<u32>> { Ok(num(v, key)?.map(|x| (x * 1000.0) as u32)) };
+    let flags = match v.get("flags") {
+        None | Some(Value::Null) => 0,
+        Some(Value::Array(names)) => names.iter().try_fold(0, |acc, n| {
+            let name = n.as_str().unwrap_or_default();
+            let bit = RegRule::flag_bit(name).ok_or_else(|| anyhow!("unknown rule flag {name:?}"))?;
+            Ok::<u32, anyhow::Error>(acc | bit)
+        })?,
+        Some(_) => bail!("flags: expected a list"),
+    };
+    Ok(RegRule {
+        start_khz: mhz("start_mhz")?.ok_or_else(|| anyhow!("rule without start_mhz"))?,
+        end_khz: mhz("end_mhz")?.ok_or_else(|| anyhow!("rule without end_mhz"))?,
+        max_bw_khz: mhz("max_bandwidth_mhz")?.unwrap_or(160_000),
+        max_ant_gain_mbi: num(v, "max_antenna_gain_dbi")?.map(|g| (g * 100.0) as u32),
+        max_eirp_mbm: num(v, "max_eirp_dbm")?.map(|p| (p * 100.0) as u32),
+        flags,
+        dfs_cac_ms: num(v, "dfs_cac_ms")?.map(|t| t as u32),
+    })
+}
+
+fn wifi_event(v: &Value) -> Result<WifiEvent> {
+    let kind = match v.get("type").and_then(Value::as_str).unwrap_or_default() {
+        "scan_started" => EventKind::ScanStarted,
+        "scan_finished" => EventKind::ScanFinished,
+        "scan_aborted" => EventKind::ScanAborted,
+        "connected" => EventKind::Connected {
+            bssid: mac(v, "bssid")?,
+            status: num(v, "status")?.map_or(0, |s| s as u16),
+        },
+        "disconnected" => EventKind::Disconnected {
+            reason: num(v, "reason")?.map(|r| r as u16),
+            by_ap: v.get("by_ap").and_then(Value::as_bool).unwrap_or(false),
+        },
+        "roamed" => EventKind::Roamed { bssid: mac(v, "bssid")? },
+        "channel_switch" => EventKind::ChannelSwitch {
+            freq_mhz: num(v, "freq_mhz")?.map(|f| f as u32),
+        },
+        "reg_change" => EventKind::RegChange,
+        other => bail!("unknown event type {other:?}"),
+    };
+    Ok(WifiEvent {
+        at: Stamp::now(),
+        ifindex: num(v, "ifindex")?.map(|i| i as u32),
+        kind,
+    })
+}
+
+// The i-th fixture interface; ifindex defaults to i + 1.
+fn wifi_iface(v: &Value, i: usize) -> Result<WifiIface> {
+    let iftype = match v.get("type").and_then(Value::as_str) {
+        None => IFTYPE_STATION,
+        Some(t) => (1..=12).find(|&n| iftype_name(n) == t).ok_or_else(|| anyhow!("unknown interface type {t:?}"))?,
+    };
+    Ok(WifiIface {
+        ifindex: num(v, "ifindex")?.map_or(i as u32 + 1, |n| n as u32),
+        name: v
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("interface without name"))?
+            .to_owned(),
+        mac: mac(v, "mac")?,
+        iftype,
+        wiphy: num(v, "wiphy")?.map(|n| n as u32),
+    })
+}
+
+fn wiphy_caps(v: &Value) -> Result<PhyCaps> {
+    let Some(Value::Array(bands)) = v.get("bands") else {
+        bail!("wiphy without bands");
+    };
+    let bands = bands.iter().map(band_caps).collect::<Result<Vec<_>>>()?;
+    Ok(PhyCaps { bands })
+}
+
+fn band_caps(v: &Value) -> Result<BandCaps> {
+    let band = v.get("band").and_then(Value::as_str).ok_or_else(|| anyhow!("band without name"))?;
+    let channels = match v.get("channels") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(channels)) => channels.iter().map(phy_channel).collect::<Result<Vec<_>>>()?,
+        Some(_) => bail!("channels: expected a list"),
+    };
+    let hex_of = |key: &str| -> Result<Option<Vec<u8>>> {
+        match v.get(key).and_then(Value::as_str) {
+            None => Ok(None),
+            Some(h) => Ok(Some(hex(h).with_context(|| key.to_owned())?)),
+        }
+    };
+    Ok(BandCaps {
+        band: band_from_name(band)?,
+        channels,
+        ht_capa: num(v, "ht_capa")?.map(|c| c as u16),
+        vht_capa: num(v, "vht_capa")?.map(|c| c as u32),
+        he_phy: hex_of("he_phy")?,
+        eht_phy: hex_of("eht_phy")?,
+    })
+}
+
+fn phy_channel(v: &Value) -> Result<PhyChannel> {
+    let flag = |key: &str| v.get(key).and_then(Value::as_bool).unwrap_or(false);
+    Ok(PhyChannel {
+        freq_mhz: num(v, "freq_mhz")?.ok_or_else(|| anyhow!("channel without freq_mhz"))? as u32,
+        disabled: flag("disabled"),
+        no_ir: flag("no_ir"),
+        radar: flag("radar"),
+        max_tx_power_dbm: num(v, "max_tx_power_dbm")?.map(|p| p as f32),
+    })
+}
+
+// Count the call; Err if a fault hits it, otherwise `serve` picks the
+// answer. The fixture's delay is returned to be waited out either way.
+fn begin<T>(op: Op, serve: impl FnOnce(&Mock, usize) -> Result<T>) -> Result<(Duration, Result<T>)> {
+    let mut guard = lock();
+    let mock = guard.as_mut().ok_or_else(not_loaded)?;
+    let call = mock.calls[op as usize];
+    mock.calls[op as usize] += 1;
+    let fault = mock
+        .faults
+        .iter()
+        .find(|f| f.op == op && f.call.is_none_or(|c| c == call));
+    let res = match fault {
+        Some(f) => {
+            debug!(op = op.cmd(), call, error = f.error.as_str(), "injected fault");
+            Err(fault_error(op, &f.error))
+        }
+        None => serve(mock, call),
+    };
+    Ok((mock.delay, res))
+}
+
+fn fault_error(op: Op, error: &str) -> anyhow::Error {
+    let errno = match error {
+        "ebusy" => libc::EBUSY,
+        "enodev" => libc::ENODEV,
+        "eperm" => libc::EPERM,
+        "timeout" if op == Op::Scan => return WifiError::ScanTimeout.into(),
+        "timeout" => libc::ETIMEDOUT,
+        other => return anyhow!("{other}"),
+    };
+    WifiError::NetlinkError(NlError {
+        cmd: op.cmd().into(),
+        errno,
+    })
+    .into()
+}
+
+fn nth<T: Clone>(items: &[T], call: usize) -> Option<T> {
+    items.get(call.min(items.len().saturating_sub(1))).cloned()
+}
+
+/// The next fixture scan, like the real providers' scan_all_bss_async().
+/// A busy fault is retried or joined as `opts.busy` says; joining serves
+/// the scan the failed call would have got, as does an "eperm" fault or,
+/// with `opts.cached_only`, which needs no trigger, any fault only a
+/// trigger can hit ("ebusy", "eperm", "timeout").
+pub async fn scan_all_bss_async(opts: &ScanOptions, mut on_row: RowSink) -> Result<(Vec<BssRow>, Option<NoTrigger>)> {
+    let mut attempt = 0;
+    let (rows, untriggered) = loop {
+        let (delay, rows) = begin(Op::Scan, |m, call| Ok(nth(&m.scans, call).unwrap_or_default()))?;
+        tokio::time::sleep(delay).await;
+        let Err(e) = rows else {
+            break (rows?, opts.cached_only.then_some(NoTrigger::CachedOnly));
+        };
+        let untriggered = if opts.cached_only {
+            Some(NoTrigger::CachedOnly)
+        } else if not_permitted(&e) {
+            Some(NoTrigger::NotPermitted)
+        } else {
+            None
+        };
+        let trigger_only = is_busy(&e) || not_permitted(&e) || kind_of(&e) == Some(ErrorKind::ScanTimeout);
+        if untriggered.is_some() && trigger_only {
+            break (last_scan_served()?, untriggered);
+        }
+        match opts.busy.retry(e, attempt)? {
+            Retry::After(wait) => tokio::time::sleep(wait).await,
+            Retry::Join => break (last_scan_served()?, None),
+        }
+        attempt += 1;
+    };
+    let rows = rows
+        .into_iter()
+        .map(|(row, ms)| {
+            let mut row = row.seen_ms_ago(Some(ms));
+            on_row(&mut row);
+            row
+        })
+        .collect();
+    // Nobody listening is fine.
+    let _ = event_tx().send(WifiEvent {
+        at: Stamp::now(),
+        ifindex: None,
+        kind: EventKind::ScanFinished,
+    });
+    Ok((rows, untriggered))
+}
+
+// The fixture scan of the last scan call, failed or not.
+fn last_scan_served() -> Result<Vec<(BssRow, u32)>> {
+    let guard = lock();
+    let m = guard.as_ref().ok_or_else(not_loaded)?;
+    Ok(nth(&m.scans, m.calls[Op::Scan as usize].saturating_sub(1)).unwrap_or_default())
+}
+
+/// The fixture's events, stamped now, and the live ones from now on, like
+/// netlink::Nl80211::subscribe().
+pub fn subscribe() -> Result<(Vec<WifiEvent>, broadcast::Receiver<WifiEvent>)> {
+    let rx = event_tx().subscribe();
+    let at = Stamp::now();
+    let events = lock().as_ref().ok_or_else(not_loaded)?.events.clone();
+    Ok((events.into_iter().map(|e| WifiEvent { at, ..e }).collect(), rx))
+}
+
+/// The fixture's interfaces, like netlink::list_interfaces().
+pub fn interfaces() -> Result<Vec<WifiIface>> {
+    Ok(lock().as_ref().ok_or_else(not_loaded)?.interfaces.clone())
+}
+
+pub fn get_connected_bssid() -> Result<Option<[u8; 6]>> {
+    let (delay, mac) = begin(Op::Connected, |m, _| Ok(m.connected))?;
+    std::thread::sleep(delay);
+    mac
+}
+
+/// The next fixture link of connected_bssid, like nl_raw::link_async().
+pub fn link() -> Result<Option<LinkInfo>> {
+    let (delay, res) = begin(Op::Link, |m, call| {
+        Ok(m.connected.map(|bssid| LinkInfo {
+            bssid,
+            ..nth(&m.links, call).unwrap_or_default()
+        }))
+    })?;
+    std::thread::sleep(delay);
+    res
+}
+
+/// The next fixture station dump, like nl_raw::ap_stations_async().
+pub fn ap_stations() -> Result<(Vec<ApRadio>, Vec<Station>)> {
+    let (delay, res) = begin(Op::Stations, |m, call| {
+        if m.radios.is_empty() {
+            bail!(WifiError::NoInterface("no AP-mode Wi-Fi interface found".into()));
+        }
+        Ok((m.radios.clone(), nth(&m.stations, call).unwrap_or_default()))
+    })?;
+    std::thread::sleep(delay);
+    res
+}
+
+/// The next fixture survey, like nl_raw::survey_async().
+pub fn survey() -> Result<Vec<ChannelSurvey>> {
+    let (delay, res) = begin(Op::Survey, |m, call| Ok(nth(&m.surveys, call).unwrap_or_default()))?;
+    std::thread::sleep(delay);
+    res
+}
+
+/// The fixture's regulatory domain, like nl_raw::regdomain_async().
+pub fn regdomain() -> Result<RegDomain> {
+    let (delay, res) = begin(Op::Reg, |m, _| {
+        m.regdomain
+            .clone()
+            .ok_or_else(|| WifiError::Unsupported("CmdGetReg: no regulatory domain in fixture".into()).into())
+    })?;
+    std::thread::sleep(delay);
+    res
+}
+
+/// The fixture's radio capabilities, like nl_raw::phy_caps_async().
+pub fn phy_caps() -> Result<PhyCaps> {
+    let (delay, res) = begin(Op::Wiphy, |m, _| {
+        m.wiphy
+            .clone()
+            .ok_or_else(|| WifiError::Unsupported("CmdGetWiphy: no wiphy in fixture".into()).into())
+    })?;
+    std::thread::sleep(delay);
+    res
+}
+
+/// Backend::Mock: every query from the fixture.
+pub struct MockBackend;
+
+impl WifiBackend for MockBackend {
+    fn scan<'a>(&'a self, _ifindex: Option<u32>, opts: &'a ScanOptions, on_row: RowSink) -> ScanFuture<'a> {
+        Box::pin(scan_all_bss_async(opts, on_row))
+    }
+
+    fn connected_bssid(&self, _ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+        get_connected_bssid()
+    }
+
+    fn reports_bss_status(&self) -> bool {
+        lock()
+            .as_ref()
+            .is_some_and(|m| m.scans.iter().flatten().any(|(r, _)| r.status.is_some()))
+    }
+
+    fn link(&self, _ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+        link()
+    }
+
+    fn interfaces(&self) -> Result<Vec<WifiIface>> {
+        interfaces()
+    }
+
+    fn station_info(&self) -> Result<(Vec<ApRadio>, Vec<Station>)> {
+        ap_stations()
+    }
+
+    fn survey(&self, _ifindex: Option<u32>) -> Result<Vec<ChannelSurvey>> {
+        survey()
+    }
+
+    fn regdomain(&self) -> Result<RegDomain> {
+        regdomain()
+    }
+
+    fn phy_caps(&self, _ifindex: Option<u32>) -> Result<PhyCaps> {
+        phy_caps()
+    }
+
+    fn events(&self) -> Result<Subscription> {
+        let (pending, rx) = subscribe()?;
+        Ok(Subscription::channel(pending, rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib_rust::{format_mac, BusyPolicy};
+    use crate::netlink::block_on;
+
+    const HOME: &str = include_str!("../fixtures/mock_home.json");
+
+    fn scan(opts: &ScanOptions) -> Result<(Vec<BssRow>, Option<NoTrigger>)> {
+        block_on(MockBackend.scan(None, opts, Box::new(|_| {})))
+    }
+
+    fn bssids(rows: &[BssRow]) -> Vec<String> {
+        rows.iter().filter_map(|r| r.bssid.as_ref().map(format_mac)).collect()
+    }
+
+    // Retries without the real backoff.
+    fn quick_retries(retries: u32) -> ScanOptions {
+        ScanOptions {
+            busy: BusyPolicy {
+                retries,
+                backoff: Duration::from_millis(1),
+                join: false,
+            },
+            ..ScanOptions::default()
+        }
+    }
+
+    #[test]
+    fn home_fixture_scans_in_turn() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/mock_home.json");
+        assert_eq!(load(&path).unwrap(), 2);
+
+        let (rows, untriggered) = scan(&ScanOptions::default()).unwrap();
+        assert_eq!(untriggered, None);
+        assert_eq!(bssids(&rows), ["aa:bb:cc:00:00:01", "aa:bb:cc:00:00:02", "11:22:33:44:55:66"]);
+        let home = &rows[0];
+        assert_eq!(home.ssid.as_deref(), Some("Home"));
+        assert_eq!(home.channel, Some(6));
+        assert_eq!(home.signal_dbm, Some(-48.0));
+        assert_eq!(home.age_ms, Some(1500));
+        // The 5 GHz row's SSID comes from its IEs.
+        assert_eq!(rows[1].ssid.as_deref(), Some("Home"));
+        assert_eq!(rows[1].channel, Some(36));
+        assert_eq!(rows[2].channel, Some(1));
+
+        // The last scan repeats.
+        for _ in 0..2 {
+            let (rows, _) = scan(&ScanOptions::default()).unwrap();
+            assert_eq!(bssids(&rows), ["aa:bb:cc:00:00:01"]);
+            assert_eq!(rows[0].signal_dbm, Some(-52.0));
+        }
+    }
+
+    #[test]
+    fn home_fixture_connected_bssid_and_stations() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        assert_eq!(MockBackend.connected_bssid(None).unwrap(), Some(parse_mac("aa:bb:cc:00:00:01").unwrap()));
+        assert!(!MockBackend.reports_bss_status());
+
+        let (radios, stations) = ap_stations().unwrap();
+        assert_eq!(radios.len(), 2);
+        assert_eq!(stations[0].tx_bytes, Some(0));
+        let (_, stations) = ap_stations().unwrap();
+        assert_eq!(stations[0].tx_bytes, Some(900_000));
+    }
+
+    #[test]
+    fn a_fault_hits_the_call_it_names() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, Some(0), "enodev").unwrap();
+
+        let e = scan(&ScanOptions::default()).unwrap_err();
+        assert!(e.root_cause().to_string().contains("CmdTriggerScan"), "{e:#}");
+        // The next call is not hit, and gets the second scan.
+        let (rows, _) = scan(&ScanOptions::default()).unwrap();
+        assert_eq!(bssids(&rows), ["aa:bb:cc:00:00:01"]);
+    }
+
+    #[test]
+    fn a_fault_without_a_call_hits_every_call() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Connected, None, "enodev").unwrap();
+        inject(Op::Scan, None, "timeout").unwrap();
+
+        for _ in 0..2 {
+            assert!(get_connected_bssid().is_err());
+            assert_eq!(kind_of(&scan(&ScanOptions::default()).unwrap_err()), Some(ErrorKind::ScanTimeout));
+        }
+        let calls = lock().as_ref().unwrap().calls;
+        assert_eq!((calls[Op::Scan as usize], calls[Op::Connected as usize]), (2, 2));
+    }
+
+    #[test]
+    fn ebusy_is_retried() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, Some(0), "ebusy").unwrap();
+
+        // The retry is the second scan call and gets the second scan.
+        let (rows, untriggered) = scan(&quick_retries(3)).unwrap();
+        assert_eq!(untriggered, None);
+        assert_eq!(bssids(&rows), ["aa:bb:cc:00:00:01"]);
+    }
+
+    #[test]
+    fn ebusy_gives_up_after_the_retries() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, None, "ebusy").unwrap();
+
+        let e = scan(&quick_retries(2)).unwrap_err();
+        assert!(is_busy(&e), "{e:#}");
+        // One call and two retries.
+        assert_eq!(lock().as_ref().unwrap().calls[Op::Scan as usize], 3);
+    }
+
+    #[test]
+    fn ebusy_joins_the_scan_in_progress() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, None, "ebusy").unwrap();
+
+        let mut opts = quick_retries(0);
+        opts.busy.join = true;
+        let (rows, untriggered) = scan(&opts).unwrap();
+        assert_eq!(untriggered, None);
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[test]
+    fn eperm_falls_back_to_the_cached_scan() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, Some(0), "eperm").unwrap();
+
+        let (rows, untriggered) = scan(&ScanOptions::default()).unwrap();
+        assert_eq!(untriggered, Some(NoTrigger::NotPermitted));
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[test]
+    fn timeout_fails_unless_cached_only() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, None, "timeout").unwrap();
+
+        let e = scan(&ScanOptions::default()).unwrap_err();
+        assert_eq!(kind_of(&e), Some(ErrorKind::ScanTimeout));
+
+        let cached = ScanOptions {
+            cached_only: true,
+            ..ScanOptions::default()
+        };
+        let (rows, untriggered) = scan(&cached).unwrap();
+        assert_eq!(untriggered, Some(NoTrigger::CachedOnly));
+        assert_eq!(bssids(&rows), ["aa:bb:cc:00:00:01"]);
+    }
+
+    #[test]
+    fn other_faults_are_not_retried() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_str(HOME).unwrap();
+        inject(Op::Scan, Some(0), "enodev").unwrap();
+
+        let e = scan(&quick_retries(3)).unwrap_err();
+        assert_eq!(kind_of(&e), Some(ErrorKind::NoInterface));
+        assert_eq!(lock().as_ref().unwrap().calls[Op::Scan as usize], 1);
+    }
+}
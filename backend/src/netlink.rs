@@ -0,0 +1,490 @@
+// src/netlink.rs
+//
+// Shared async netlink layer. Everything that talks nl80211 goes through
+// here instead of opening its own socket:
+//
+//   - one Tokio runtime for the whole module (runtime())
+//   - one command socket, owned by a task that serializes requests and
+//     matches replies by sequence number (Nl80211::dump / ::request)
+//   - replies are walked in place in that task's one receive buffer and
+//     handed to a per-request visitor, so hot paths (GET_SCAN) can pull out
+//     just what they need without building neli's attribute tree
+//   - one event socket joined to the nl80211 multicast groups, fanned out
+//     to any number of subscribers (Nl80211::subscribe)
+//
+// Sync callers (the pyfunctions) use block_on(); async callers can await
+// the same futures directly.
+
+use anyhow::{anyhow, bail, Context, Result};
+use neli::consts::nl::{NlmF, NlmFFlags, Nlmsg};
+use neli::consts::socket::NlFamily;
+use neli::genl::{Genlmsghdr, Nlattr};
+use neli::nl::{NlPayload, Nlmsghdr};
+use neli::socket::tokio::NlSocket;
+use neli::socket::NlSocketHandle;
+use neli::types::{Buffer, GenlBuffer, NlBuffer};
+use neli::FromBytesWithInput;
+use std::future::Future;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::runtime::Runtime;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{debug, warn};
+
+use crate::error::WifiError;
+use crate::nl_raw::{Attr, Cmd, NL_80211_GENL_NAME, NL_80211_GENL_VERSION};
+use crate::perf;
+
+pub type Attrs = GenlBuffer<Attr, Buffer>;
+pub type Genl = Genlmsghdr<Cmd, Attr>;
+
+// Multicast groups we listen on. Missing ones are skipped.
+const EVENT_GROUPS: &[&str] = &["scan", "mlme", "regulatory", "config"];
+
+// How many queued requests / undelivered events before callers wait or
+// slow subscribers start losing events.
+const REQUEST_QUEUE: usize = 32;
+const EVENT_BACKLOG: usize = 256;
+
+// One datagram from a GET_SCAN dump can be large with many IEs per BSS.
+const RECV_BUF: usize = 32 * 1024;
+// First pause after a failed event receive; it doubles with each failure
+// in a row, up to EVENT_MAX_FAILURES (about 5 s of retrying in all).
+const EVENT_RETRY: Duration = Duration::from_millis(10);
+const EVENT_MAX_FAILURES: u32 = 10;
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+static SHARED: Mutex<Option<Nl80211>> = Mutex::new(None);
+
+/// An error reply from the kernel. Kept typed so callers can act on the
+/// errno (errno_of()); displays as "CmdTriggerScan: Device or resource
+/// busy (os error 16)".
+#[derive(Debug)]
+pub struct NlError {
+    pub cmd: String,
+    pub errno: i32,
+}
+
+impl std::fmt::Display for NlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.cmd, std::io::Error::from_raw_os_error(self.errno))
+    }
+}
+
+impl std::error::Error for NlError {}
+
+/// The errno of the kernel error reply behind `e`, if that is what it is.
+pub fn errno_of(e: &anyhow::Error) -> Option<i32> {
+    e.chain().find_map(|c| c.downcast_ref::<NlError>()).map(|n| n.errno)
+}
+
+/// The runtime all netlink I/O runs on.
+pub fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("wifi-netlink")
+            .enable_all()
+            .build()
+            .expect("failed to start netlink runtime")
+    })
+}
+
+/// Run a future to completion on the shared runtime.
+pub fn block_on<F: Future>(f: F) -> F::Output {
+    runtime().block_on(f)
+}
+
+// Called with each reply payload (genl header + attributes) while it is
+// still sitting in the socket task's receive buffer.
+type Visitor = Box<dyn FnMut(&[u8]) -> Result<()> + Send>;
+
+struct Request {
+    cmd: Cmd,
+    attrs: Attrs,
+    dump: bool,
+    visit: Visitor,
+    reply: oneshot::Sender<Result<()>>,
+}
+
+/// Handle to the shared nl80211 sockets. Cheap to clone.
+#[derive(Clone)]
+pub struct Nl80211 {
+    tx: mpsc::Sender<Request>,
+    events: broadcast::Sender<Arc<Genl>>,
+}
+
+impl Nl80211 {
+    /// The process-wide handle, connecting on first use (or again after
+    /// the socket task died).
+    pub fn shared() -> Result<Nl80211> {
+        let mut guard = SHARED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(nl) = guard.as_ref() {
+            if !nl.tx.is_closed() {
+                return Ok(nl.clone());
+            }
+        }
+        let nl = Self::connect()?;
+        *guard = Some(nl.clone());
+        Ok(nl)
+    }
+
+    fn connect() -> Result<Nl80211> {
+        let _rt = runtime().enter();
+
+        let mut cmd_sock = NlSocketHandle::connect(NlFamily::Generic, None, &[])
+            .context("netlink socket")?;
+        let family_id = cmd_sock.resolve_genl_family(NL_80211_GENL_NAME)?;
+
+        let mut ev_sock = NlSocketHandle::connect(NlFamily::Generic, None, &[])
+            .context("netlink event socket")?;
+        for group in EVENT_GROUPS {
+            if let Ok(id) = ev_sock.resolve_nl_mcast_group(NL_80211_GENL_NAME, group) {
+                ev_sock.add_mcast_membership(&[id])?;
+            }
+        }
+
+        let (tx, rx) = mpsc::channel(REQUEST_QUEUE);
+        let (events, _) = broadcast::channel(EVENT_BACKLOG);
+
+        let cmd_sock = NlSocket::new(cmd_sock)?;
+        let ev_sock = NlSocket::new(ev_sock)?;
+        runtime().spawn(command_task(cmd_sock, family_id, rx));
+        runtime().spawn(event_task(ev_sock, events.clone()));
+
+        Ok(Nl80211 { tx, events })
+    }
+
+    async fn call(&self, cmd: Cmd, attrs: Attrs, dump: bool, visit: Visitor) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.tx
+            .send(Request { cmd, attrs, dump, visit, reply })
+            .await
+            .map_err(|_| anyhow!("netlink socket task is gone"))?;
+        rx.await.map_err(|_| anyhow!("netlink socket task is gone"))?
+    }
+
+    async fn collect<T, F>(&self, cmd: Cmd, attrs: Attrs, dump: bool, mut f: F) -> Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: FnMut(&[u8]) -> Result<Option<T>> + Send + 'static,
+    {
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let sink = out.clone();
+        let visit: Visitor = Box::new(move |payload| {
+            if let Some(v) = f(payload)? {
+                sink.lock().unwrap_or_else(|e| e.into_inner()).push(v);
+            }
+            Ok(())
+        });
+        self.call(cmd, attrs, dump, visit).await?;
+
+        let mut out = out.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(std::mem::take(&mut *out))
+    }
+
+    /// Dump request: every reply message up to NLMSG_DONE.
+    #[cfg_attr(not(feature = "backend-raw-nl80211"), allow(dead_code))]
+    pub async fn dump(&self, cmd: Cmd, attrs: Attrs) -> Result<Vec<Genl>> {
+        self.collect(cmd, attrs, true, |p| decode(p).map(Some)).await
+    }
+
+    /// Dump request where `f` sees each raw reply payload, as it arrives,
+    /// and keeps whatever it returns `Some` for. An error from `f` aborts
+    /// the dump.
+    pub async fn dump_with<T, F>(&self, cmd: Cmd, attrs: Attrs, f: F) -> Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: FnMut(&[u8]) -> Result<Option<T>> + Send + 'static,
+    {
+        self.collect(cmd, attrs, true, f).await
+    }
+
+    /// Acked request: any reply messages, once the kernel has ACKed.
+    #[cfg_attr(not(feature = "backend-raw-nl80211"), allow(dead_code))]
+    pub async fn request(&self, cmd: Cmd, attrs: Attrs) -> Result<Vec<Genl>> {
+        self.collect(cmd, attrs, false, |p| decode(p).map(Some)).await
+    }
+
+    /// request() where `f` sees each raw reply payload, like dump_with().
+    pub async fn request_with<T, F>(&self, cmd: Cmd, attrs: Attrs, f: F) -> Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: FnMut(&[u8]) -> Result<Option<T>> + Send + 'static,
+    {
+        self.collect(cmd, attrs, false, f).await
+    }
+
+    /// Receive every nl80211 multicast event from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Genl>> {
+        self.events.subscribe()
+    }
+}
+
+// NL80211_ATTR_* of a GET_INTERFACE reply.
+const ATTR_WIPHY: u16 = 1;
+const ATTR_IFINDEX: u16 = 3;
+const ATTR_IFNAME: u16 = 4;
+const ATTR_IFTYPE: u16 = 5;
+const ATTR_MAC: u16 = 6;
+/// NL80211_IFTYPE_STATION.
+pub const IFTYPE_STATION: u32 = 2;
+
+/// One Wi-Fi network interface (P2P-device and NAN wdevs, which have no
+/// netdev, aren't listed).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WifiIface {
+    pub ifindex: u32,
+    pub name: String,
+    pub mac: Option<[u8; 6]>,
+    /// NL80211_IFTYPE_*.
+    pub iftype: u32,
+    pub wiphy: Option<u32>,
+}
+
+/// Label for a NL80211_IFTYPE_* value.
+pub fn iftype_name(iftype: u32) -> &'static str {
+    match iftype {
+        1 => "adhoc",
+        2 => "station",
+        3 => "ap",
+        4 => "ap_vlan",
+        5 => "wds",
+        6 => "monitor",
+        7 => "mesh_point",
+        8 => "p2p_client",
+        9 => "p2p_go",
+        10 => "p2p_device",
+        11 => "ocb",
+        12 => "nan",
+        _ => "unspecified",
+    }
+}
+
+/// Every Wi-Fi interface, in the kernel's order.
+pub async fn list_interfaces(nl: &Nl80211) -> Result<Vec<WifiIface>> {
+    nl.dump_with(Cmd::CmdGetInterface, GenlBuffer::new(), |p| Ok(parse_iface(p)))
+        .await
+}
+
+fn parse_iface(payload: &[u8]) -> Option<WifiIface> {
+    let mut iface = WifiIface::default();
+    let mut ifindex = None;
+    for (ty, p) in nla_iter(payload.get(4..)?) {
+        let u32_of = |p: &[u8]| p.get(..4).map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]));
+        match ty {
+            ATTR_WIPHY => iface.wiphy = u32_of(p),
+            ATTR_IFINDEX => ifindex = u32_of(p),
+            // NUL-terminated.
+            ATTR_IFNAME => iface.name = String::from_utf8_lossy(p).trim_end_matches('\0').to_owned(),
+            ATTR_IFTYPE => iface.iftype = u32_of(p).unwrap_or(0),
+            ATTR_MAC => iface.mac = p.try_into().ok(),
+            _ => {}
+        }
+    }
+    iface.ifindex = ifindex?;
+    Some(iface)
+}
+
+/// Index of the Wi-Fi interface to use by default: the first station
+/// (client) interface, else the first one the kernel reports, so an AP
+/// or monitor interface listed before wlan0 isn't picked.
+pub async fn first_ifindex(nl: &Nl80211) -> Result<u32> {
+    let ifaces = list_interfaces(nl).await?;
+    ifaces
+        .iter()
+        .find(|i| i.iftype == IFTYPE_STATION)
+        .or(ifaces.first())
+        .map(|i| i.ifindex)
+        .ok_or_else(|| WifiError::NoInterface("no Wi-Fi interface found".into()).into())
+}
+
+/// Index of the Wi-Fi interface called `name`.
+pub async fn ifindex_by_name(nl: &Nl80211, name: &str) -> Result<u32> {
+    list_interfaces(nl)
+        .await?
+        .iter()
+        .find(|i| i.name == name)
+        .map(|i| i.ifindex)
+        .ok_or_else(|| WifiError::NoInterface(format!("no Wi-Fi interface named {name:?}")).into())
+}
+
+/// `ifindex`, else the first Wi-Fi interface's.
+pub async fn ifindex_or_first(nl: &Nl80211, ifindex: Option<u32>) -> Result<u32> {
+    match ifindex {
+        Some(i) => Ok(i),
+        None => first_ifindex(nl).await,
+    }
+}
+
+/// Build an attribute list holding just NL80211_ATTR_IFINDEX.
+pub fn ifindex_attrs(ifindex: u32) -> Result<Attrs> {
+    let mut attrs = GenlBuffer::new();
+    attrs.push(Nlattr::new(false, false, Attr::AttrIfindex, ifindex)?);
+    Ok(attrs)
+}
+
+/// Interface index carried by a message, if any.
+pub fn msg_ifindex(genl: &Genl) -> Option<u32> {
+    genl.get_attr_handle()
+        .get_attr_payload_as::<u32>(Attr::AttrIfindex)
+        .ok()
+}
+
+/// Walk netlink attributes (TLVs, 4-byte aligned) as (type, payload).
+/// The NLA_F_NESTED / NLA_F_NET_BYTEORDER bits are masked off the type.
+pub fn nla_iter(mut rem: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if rem.len() < 4 {
+            return None;
+        }
+        let len = u16::from_ne_bytes([rem[0], rem[1]]) as usize;
+        if len < 4 || len > rem.len() {
+            return None;
+        }
+        let ty = u16::from_ne_bytes([rem[2], rem[3]]) & 0x3fff;
+        let payload = &rem[4..len];
+        rem = rem.get((len + 3) & !3..).unwrap_or(&[]);
+        Some((ty, payload))
+    })
+}
+
+/// Decode one reply payload into neli's generic netlink header.
+pub fn decode(payload: &[u8]) -> Result<Genl> {
+    Ok(Genl::from_bytes_with_input(&mut Cursor::new(payload), payload.len())?)
+}
+
+// Owns the command socket. Requests run one at a time; replies carrying
+// another sequence number are leftovers from an abandoned request.
+async fn command_task(mut sock: NlSocket, family_id: u16, mut rx: mpsc::Receiver<Request>) {
+    let mut seq: u32 = 0;
+    let mut buf = vec![0u8; RECV_BUF];
+
+    while let Some(mut req) = rx.recv().await {
+        seq = seq.wrapping_add(1);
+        let res = run_request(&mut sock, family_id, seq, &mut req, &mut buf).await;
+        let _ = req.reply.send(res);
+    }
+}
+
+async fn run_request(
+    sock: &mut NlSocket,
+    family_id: u16,
+    seq: u32,
+    req: &mut Request,
+    buf: &mut [u8],
+) -> Result<()> {
+    let flags = if req.dump {
+        NlmFFlags::new(&[NlmF::Request, NlmF::Dump])
+    } else {
+        NlmFFlags::new(&[NlmF::Request, NlmF::Ack])
+    };
+    let attrs = std::mem::replace(&mut req.attrs, GenlBuffer::new());
+    let genlhdr = Genlmsghdr::new(req.cmd, NL_80211_GENL_VERSION, attrs);
+    let msg = Nlmsghdr::new(None, family_id, flags, Some(seq), None, NlPayload::Payload(genlhdr));
+    sock.send(&msg).await?;
+    perf::add(&perf::NL_SENT, 1);
+
+    loop {
+        let n = sock.read(buf).await?;
+        if n == 0 {
+            bail!("netlink socket closed");
+        }
+        perf::add(&perf::NL_RECV, 1);
+        perf::add(&perf::NL_RECV_BYTES, n as u64);
+
+        let start = Instant::now();
+        let done = walk_replies(&buf[..n], seq, req.cmd, &mut req.visit);
+        perf::add(&perf::PARSE_NS, start.elapsed().as_nanos() as u64);
+        perf::add(&perf::PARSE_BYTES, n as u64);
+        if done? {
+            return Ok(());
+        }
+    }
+}
+
+// Walk the netlink messages of one datagram, feeding payloads of our
+// sequence number to `visit`. Returns true once the request is finished
+// (NLMSG_DONE, ACK, or an error, which is returned instead).
+fn walk_replies(mut rem: &[u8], seq: u32, cmd: Cmd, visit: &mut Visitor) -> Result<bool> {
+    const NLMSG_HDRLEN: usize = 16;
+    const NLMSG_NOOP: u16 = 1;
+    const NLMSG_ERROR: u16 = 2;
+    const NLMSG_DONE: u16 = 3;
+
+    while rem.len() >= NLMSG_HDRLEN {
+        let len = u32::from_ne_bytes([rem[0], rem[1], rem[2], rem[3]]) as usize;
+        if len < NLMSG_HDRLEN || len > rem.len() {
+            warn!(cmd = ?cmd, len, have = rem.len(), "truncated netlink message");
+            bail!("{cmd:?}: truncated netlink message");
+        }
+        let ty = u16::from_ne_bytes([rem[4], rem[5]]);
+        let msg_seq = u32::from_ne_bytes([rem[8], rem[9], rem[10], rem[11]]);
+        let payload = &rem[NLMSG_HDRLEN..len];
+        rem = rem.get((len + 3) & !3..).unwrap_or(&[]);
+
+        if msg_seq != seq {
+            continue;
+        }
+        match ty {
+            NLMSG_NOOP => {}
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR => {
+                let errno = payload
+                    .get(..4)
+                    .map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                    .unwrap_or(0);
+                if errno == 0 {
+                    return Ok(true);
+                }
+                debug!(cmd = ?cmd, errno = -errno, "netlink error reply");
+                return Err(WifiError::NetlinkError(NlError {
+                    cmd: format!("{cmd:?}"),
+                    errno: -errno,
+                })
+                .into());
+            }
+            _ => visit(payload)?,
+        }
+    }
+    Ok(false)
+}
+
+// Owns the event socket and fans every message out to subscribers. A
+// failed receive (an overrun that dropped events, a message neli can't
+// parse) is logged and retried after a growing pause; after
+// EVENT_MAX_FAILURES in a row the socket is taken to be broken and the
+// task ends, leaving subscribers without further events.
+async fn event_task(mut sock: NlSocket, events: broadcast::Sender<Arc<Genl>>) {
+    let mut buf = Vec::new();
+    let mut failures = 0u32;
+    loop {
+        let msgs: NlBuffer<Nlmsg, Buffer> = match sock.recv(&mut buf).await {
+            Ok(m) => {
+                failures = 0;
+                m
+            }
+            Err(e) => {
+                failures += 1;
+                if failures >= EVENT_MAX_FAILURES {
+                    warn!(error = %e, failures, "netlink event socket keeps failing; no more events");
+                    return;
+                }
+                warn!(error = %e, failures, "netlink event receive failed");
+                tokio::time::sleep(EVENT_RETRY * 2u32.pow(failures - 1)).await;
+                continue;
+            }
+        };
+        for m in msgs {
+            if let NlPayload::Payload(p) = m.nl_payload {
+                if let Ok(genl) = decode(p.as_ref()) {
+                    // No subscribers is fine; the event is just dropped.
+                    perf::add(&perf::NL_EVENTS, 1);
+                    let _ = events.send(Arc::new(genl));
+                }
+            }
+        }
+    }
+}
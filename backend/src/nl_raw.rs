@@ -0,0 +1,875 @@
+// src/nl_raw.rs
+//
+// Raw nl80211 backend. Unlike nl_wifi this one asks the kernel for a fresh
+// scan over the shared netlink socket:
+//   TRIGGER_SCAN -> wait for NEW_SCAN_RESULTS on the "scan" multicast group
+//   -> GET_SCAN dump, with each nested NL80211_ATTR_BSS parsed by hand.
+//
+// Notes:
+// - We only need ONE valid ifindex to trigger the scan; the dump returns
+//   every BSS known to that phy.
+// - Triggering needs CAP_NET_ADMIN; dumping usually does not, so a
+//   trigger refused with EPERM falls back to the dump of the kernel's
+//   cached BSS table, as ScanOptions::cached_only asks for outright.
+// - A trigger refused with EBUSY / EINPROGRESS (wpa_supplicant or the OS
+//   is scanning) is retried with backoff, or joins that scan, as
+//   ScanOptions::busy says.
+// - ScanOptions shape the trigger: passive (no SCAN_SSIDS), a frequency
+//   list (a band's comes from the radio's GET_WIPHY bands), SSIDs to probe,
+//   the FLUSH flag and RANDOM_ADDR (with an optional MAC / MAC_MASK) when
+//   the radio advertises NL80211_FEATURE_SCAN_RANDOM_MAC_ADDR.
+// - Built with the backend-raw-nl80211 feature only: the triggered scan
+//   and RawNl80211Backend. Everything else is shared by every build.
+// - Attr / Cmd are the nl80211 attributes and commands the whole crate
+//   speaks (netlink, events, capture), so only the neli-wifi backend
+//   needs the neli-wifi crate.
+// - ap_stations_async() lists the clients of local AP-mode interfaces, for
+//   band steering, survey_async() dumps the channel survey,
+//   regdomain_async() reads the regulatory domain and phy_caps_async() the
+//   radio's bands; they are WifiBackend's defaults for every provider
+//   that has no source of its own.
+
+use anyhow::{bail, Result};
+use neli::consts::genl::{Cmd as GenlCmd, NlAttrType};
+use neli::genl::Nlattr;
+use neli::neli_enum;
+use neli::types::GenlBuffer;
+
+use crate::chansurvey::ChannelSurvey;
+use crate::error::WifiError;
+use crate::lib_rust::{vec_to_mac, BssRow, BssStatus};
+use crate::link::LinkInfo;
+use crate::netlink::{ifindex_attrs, ifindex_or_first, nla_iter, Nl80211};
+use crate::phycaps::{BandCaps, PhyCaps, PhyChannel};
+use crate::regdom::{RegDomain, RegRule};
+use crate::stations::{ApRadio, Station};
+
+// Triggered scans (Backend::RawNl80211) only.
+#[cfg(feature = "backend-raw-nl80211")]
+use {
+    crate::lib_rust::{band_name, freq_band, not_permitted, NoTrigger, Retry, RowSink, ScanOptions},
+    crate::netlink::{block_on, msg_ifindex},
+    crate::provider::{ScanFuture, WifiBackend},
+    std::time::Duration,
+    tracing::{debug, warn},
+    tokio::sync::broadcast::error::RecvError,
+};
+
+pub const NL_80211_GENL_NAME: &str = "nl80211";
+pub const NL_80211_GENL_VERSION: u8 = 1;
+
+/// enum nl80211_attrs, the attributes we send or read at the top level.
+/// Others decode as UnrecognizedConst. Variants keep neli-wifi's names.
+#[allow(clippy::enum_variant_names)]
+#[neli_enum(serialized_type = "u16")]
+pub enum Attr {
+    AttrWiphy = 1,
+    AttrIfindex = 3,
+    AttrIftype = 5,
+    AttrMac = 6,
+    AttrWiphyFreq = 38,
+    AttrScanFrequencies = 44,
+    AttrScanSsids = 45,
+    AttrBss = 47,
+    AttrReasonCode = 54,
+    AttrDisconnectedByAp = 71,
+    AttrStatusCode = 72,
+    AttrScanFlags = 158,
+    AttrSplitWiphyDump = 174,
+    AttrMacMask = 215,
+}
+
+impl NlAttrType for Attr {}
+
+/// enum nl80211_commands, the requests we make and the events we handle.
+#[allow(clippy::enum_variant_names)]
+#[neli_enum(serialized_type = "u8")]
+pub enum Cmd {
+    CmdGetWiphy = 1,
+    CmdSetWiphy = 2,
+    CmdGetInterface = 5,
+    CmdSetInterface = 6,
+    CmdGetStation = 17,
+    CmdGetReg = 31,
+    CmdGetScan = 32,
+    CmdTriggerScan = 33,
+    CmdNewScanResults = 34,
+    CmdScanAborted = 35,
+    CmdRegChange = 36,
+    CmdConnect = 46,
+    CmdRoam = 47,
+    CmdDisconnect = 48,
+    CmdGetSurvey = 50,
+    CmdChSwitchNotify = 88,
+}
+
+impl GenlCmd for Cmd {}
+
+// NL80211_ATTR_BSS; nested nl80211_bss attributes follow.
+const ATTR_BSS: u16 = 47;
+
+// NL80211_SCAN_FLAG_*, in NL80211_ATTR_SCAN_FLAGS.
+#[cfg(feature = "backend-raw-nl80211")]
+const SCAN_FLAG_FLUSH: u32 = 1 << 1;
+#[cfg(feature = "backend-raw-nl80211")]
+const SCAN_FLAG_RANDOM_ADDR: u32 = 1 << 3;
+// NL80211_ATTR_FEATURE_FLAGS and NL80211_FEATURE_SCAN_RANDOM_MAC_ADDR.
+const ATTR_FEATURE_FLAGS: u16 = 143;
+const FEATURE_SCAN_RANDOM_MAC_ADDR: u32 = 1 << 29;
+// NL80211_ATTR_WIPHY_BANDS, then the nl80211_band_attr /
+// nl80211_frequency_attr ids nested under it.
+const ATTR_WIPHY_BANDS: u16 = 22;
+const BAND_ATTR_FREQS: u16 = 1;
+const BAND_ATTR_HT_CAPA: u16 = 4;
+const BAND_ATTR_VHT_CAPA: u16 = 8;
+const BAND_ATTR_IFTYPE_DATA: u16 = 9;
+const FREQUENCY_ATTR_FREQ: u16 = 1;
+const FREQUENCY_ATTR_DISABLED: u16 = 2;
+const FREQUENCY_ATTR_NO_IR: u16 = 3;
+const FREQUENCY_ATTR_RADAR: u16 = 5;
+const FREQUENCY_ATTR_MAX_TX_POWER: u16 = 6;
+// nl80211_band_iftype_attr, in each BAND_ATTR_IFTYPE_DATA entry.
+const BAND_IFTYPE_ATTR_HE_CAP_PHY: u16 = 3;
+const BAND_IFTYPE_ATTR_EHT_CAP_PHY: u16 = 9;
+
+#[cfg(feature = "backend-raw-nl80211")]
+const SCAN_TIMEOUT: Duration = Duration::from_secs(4);
+
+#[cfg(feature = "backend-raw-nl80211")]
+/// Trigger a fresh scan on `ifindex` (the first interface if None) as
+/// `opts` asks, wait for it, then dump every BSS. `on_row` sees each BSS as
+/// soon as its reply is parsed. Only dumps, saying why, with
+/// opts.cached_only or when triggering isn't permitted.
+pub async fn scan_all_bss_async(
+    ifindex: Option<u32>,
+    opts: &ScanOptions,
+    on_row: RowSink,
+) -> Result<(Vec<BssRow>, Option<NoTrigger>)> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+
+    if opts.cached_only {
+        return Ok((dump_scan_results(&nl, ifindex, on_row).await?, Some(NoTrigger::CachedOnly)));
+    }
+    let mut attempt = 0;
+    let mut events = loop {
+        // Subscribe before triggering so the completion event can't be
+        // missed; anew for every try, so a busy scan's doesn't count.
+        let events = nl.subscribe();
+        let Err(e) = trigger_scan(&nl, ifindex, opts).await else {
+            break events;
+        };
+        // Without CAP_NET_ADMIN the kernel's BSS table is the best we get.
+        if not_permitted(&e) {
+            warn!("trigger not permitted, returning the cached BSS table: {e:#}");
+            return Ok((dump_scan_results(&nl, ifindex, on_row).await?, Some(NoTrigger::NotPermitted)));
+        }
+        match opts.busy.retry(e, attempt)? {
+            Retry::After(wait) => tokio::time::sleep(wait).await,
+            // The scan in progress ends with the same NEW_SCAN_RESULTS.
+            Retry::Join => break events,
+        }
+        attempt += 1;
+    };
+
+    tokio::time::timeout(SCAN_TIMEOUT, async {
+        loop {
+            let ev = match events.recv().await {
+                Ok(ev) => ev,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => bail!("netlink event socket closed"),
+            };
+            if msg_ifindex(&ev).is_some_and(|i| i != ifindex) {
+                continue;
+            }
+            match ev.cmd {
+                Cmd::CmdNewScanResults => return Ok(()),
+                Cmd::CmdScanAborted => {
+                    warn!(ifindex, "the driver aborted the scan");
+                    bail!(WifiError::ScanAborted("scan aborted".into()));
+                }
+                _ => {}
+            }
+        }
+    })
+    .await
+    .map_err(|_| {
+        warn!(ifindex, timeout_ms = SCAN_TIMEOUT.as_millis() as u64, "no scan results in time");
+        WifiError::ScanTimeout
+    })??;
+    debug!(ifindex, "scan results ready");
+
+    Ok((dump_scan_results(&nl, ifindex, on_row).await?, None))
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+/// BSSID of the associated AP, from a GET_STATION dump.
+pub async fn get_connected_bssid_async(ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+
+    let stations = nl.dump(Cmd::CmdGetStation, ifindex_attrs(ifindex)?).await?;
+    Ok(stations.iter().find_map(|genl| {
+        genl.get_attr_handle()
+            .get_attribute(Attr::AttrMac)
+            .and_then(|a| vec_to_mac(a.nla_payload.as_ref()))
+    }))
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+pub fn get_connected_bssid(ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+    block_on(get_connected_bssid_async(ifindex))
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+/// Backend::RawNl80211; the other queries are WifiBackend's defaults,
+/// which are the ones below.
+pub struct RawNl80211Backend;
+
+#[cfg(feature = "backend-raw-nl80211")]
+impl WifiBackend for RawNl80211Backend {
+    fn scan<'a>(&'a self, ifindex: Option<u32>, opts: &'a ScanOptions, on_row: RowSink) -> ScanFuture<'a> {
+        Box::pin(scan_all_bss_async(ifindex, opts, on_row))
+    }
+
+    fn connected_bssid(&self, ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+        get_connected_bssid(ifindex)
+    }
+
+    fn reports_bss_status(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+async fn trigger_scan(nl: &Nl80211, ifindex: u32, opts: &ScanOptions) -> Result<()> {
+    let mut attrs = ifindex_attrs(ifindex)?;
+
+    // NL80211_ATTR_SCAN_SSIDS holding one zero-length SSID => wildcard
+    // active scan. Leaving the attribute out makes it passive.
+    if !opts.passive {
+        let ssids: Vec<&[u8]> = match &opts.ssids[..] {
+            [] => vec![&[]],
+            some => some.iter().map(Vec::as_slice).collect(),
+        };
+        attrs.push(Nlattr::new(true, false, Attr::AttrScanSsids, nested_list(&ssids))?);
+    }
+
+    let mut freqs = opts.freqs.clone();
+    if let Some(band) = opts.band {
+        let supported = wiphy_freqs(nl, ifindex).await?;
+        if freqs.is_empty() {
+            freqs = supported.into_iter().filter(|&f| freq_band(f) == band).collect();
+            if freqs.is_empty() {
+                bail!("the radio has no {} channels", band_name(band));
+            }
+        }
+    }
+    if !freqs.is_empty() {
+        let freqs: Vec<[u8; 4]> = freqs.iter().map(|f| f.to_ne_bytes()).collect();
+        let freqs: Vec<&[u8]> = freqs.iter().map(|f| &f[..]).collect();
+        attrs.push(Nlattr::new(true, false, Attr::AttrScanFrequencies, nested_list(&freqs))?);
+    }
+
+    let mut flags = 0;
+    if opts.flush {
+        flags |= SCAN_FLAG_FLUSH;
+    }
+    if let Some(random) = opts.random_mac {
+        if wiphy_features(nl, ifindex).await? & FEATURE_SCAN_RANDOM_MAC_ADDR == 0 {
+            bail!("the Wi-Fi driver can't randomize the scan MAC address");
+        }
+        flags |= SCAN_FLAG_RANDOM_ADDR;
+        // Bits set in the mask come from the address, the rest are random;
+        // without them the kernel picks a random locally administered one.
+        if random.mask != [0; 6] {
+            attrs.push(Nlattr::new(false, false, Attr::AttrMac, random.addr.to_vec())?);
+            attrs.push(Nlattr::new(false, false, Attr::AttrMacMask, random.mask.to_vec())?);
+        }
+    }
+    if flags != 0 {
+        attrs.push(Nlattr::new(false, false, Attr::AttrScanFlags, flags)?);
+    }
+    debug!(
+        ifindex,
+        passive = opts.passive,
+        freqs = freqs.len(),
+        ssids = opts.ssids.len(),
+        flags,
+        "TRIGGER_SCAN"
+    );
+
+    nl.request(Cmd::CmdTriggerScan, attrs)
+        .await
+        .map_err(|e| {
+            let msg = format!("TRIGGER_SCAN failed: {e}");
+            e.context(msg)
+        })?;
+    Ok(())
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+// Nested attribute holding `items` as attributes 1, 2, ... (the kernel
+// doesn't look at their types).
+fn nested_list(items: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        out.extend_from_slice(&(4 + item.len() as u16).to_ne_bytes());
+        out.extend_from_slice(&(i as u16 + 1).to_ne_bytes());
+        out.extend_from_slice(item);
+        out.resize((out.len() + 3) & !3, 0);
+    }
+    out
+}
+
+// `parse` run on every reply of a split GET_WIPHY dump of the radio
+// behind `ifindex` (one unsplit reply doesn't fit every band).
+async fn wiphy_parts<T: Send + 'static>(nl: &Nl80211, ifindex: u32, parse: fn(&[u8]) -> T) -> Result<Vec<T>> {
+    let mut attrs = ifindex_attrs(ifindex)?;
+    attrs.push(Nlattr::new(false, false, Attr::AttrSplitWiphyDump, Vec::<u8>::new())?);
+    nl.dump_with(Cmd::CmdGetWiphy, attrs, move |p| Ok(Some(parse(p)))).await
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+// Enabled frequencies of the radio behind `ifindex`.
+async fn wiphy_freqs(nl: &Nl80211, ifindex: u32) -> Result<Vec<u32>> {
+    Ok(wiphy_parts(nl, ifindex, parse_wiphy_freqs).await?.into_iter().flatten().collect())
+}
+
+// NL80211_FEATURE_* flags of the radio behind `ifindex`.
+async fn wiphy_features(nl: &Nl80211, ifindex: u32) -> Result<u32> {
+    let parts = wiphy_parts(nl, ifindex, |p| {
+        let attrs = p.get(4..).unwrap_or(&[]);
+        nla_iter(attrs).find(|&(ty, _)| ty == ATTR_FEATURE_FLAGS).and_then(|(_, v)| le_u32(v))
+    })
+    .await?;
+    Ok(parts.into_iter().flatten().fold(0, |a, f| a | f))
+}
+
+/// Whether the radio behind `ifindex` (the first interface if None) can
+/// scan from a random MAC address.
+pub async fn random_mac_supported_async(ifindex: Option<u32>) -> Result<bool> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+    Ok(wiphy_features(&nl, ifindex).await? & FEATURE_SCAN_RANDOM_MAC_ADDR != 0)
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+fn parse_wiphy_freqs(payload: &[u8]) -> Vec<u32> {
+    let mut out = Vec::new();
+    let Some(attrs) = payload.get(4..) else {
+        return out;
+    };
+    let bands = nla_iter(attrs).filter(|&(ty, _)| ty == ATTR_WIPHY_BANDS);
+    for (_, bands) in bands {
+        for (_, band) in nla_iter(bands) {
+            for (_, freqs) in nla_iter(band).filter(|&(ty, _)| ty == BAND_ATTR_FREQS) {
+                for (_, freq) in nla_iter(freqs) {
+                    let mut f = None;
+                    let mut disabled = false;
+                    for (ty, p) in nla_iter(freq) {
+                        match ty {
+                            FREQUENCY_ATTR_FREQ => f = le_u32(p),
+                            FREQUENCY_ATTR_DISABLED => disabled = true,
+                            _ => {}
+                        }
+                    }
+                    out.extend(f.filter(|_| !disabled));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Bands, channels and HT / VHT / HE / EHT capabilities of the radio
+/// behind `ifindex` (the first interface if None).
+pub async fn phy_caps_async(ifindex: Option<u32>) -> Result<PhyCaps> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+    let mut bands: Vec<(u16, BandCaps)> = Vec::new();
+    for (idx, part) in wiphy_parts(&nl, ifindex, parse_wiphy_bands).await?.into_iter().flatten() {
+        match bands.iter_mut().find(|(i, _)| *i == idx) {
+            Some((_, b)) => {
+                b.channels.extend(part.channels);
+                b.ht_capa = b.ht_capa.or(part.ht_capa);
+                b.vht_capa = b.vht_capa.or(part.vht_capa);
+                b.he_phy = b.he_phy.take().or(part.he_phy);
+                b.eht_phy = b.eht_phy.take().or(part.eht_phy);
+            }
+            None => bands.push((idx, part)),
+        }
+    }
+    bands.sort_by_key(|&(idx, _)| idx);
+    Ok(PhyCaps {
+        bands: bands.into_iter().map(|(_, b)| b).collect(),
+    })
+}
+
+// Whatever one GET_WIPHY part holds of each band, by nl80211 band index.
+fn parse_wiphy_bands(payload: &[u8]) -> Vec<(u16, BandCaps)> {
+    let mut out = Vec::new();
+    let Some(attrs) = payload.get(4..) else {
+        return out;
+    };
+    for (_, bands) in nla_iter(attrs).filter(|&(ty, _)| ty == ATTR_WIPHY_BANDS) {
+        for (idx, band) in nla_iter(bands) {
+            let mut caps = BandCaps {
+                // NL80211_BAND_2GHZ, _5GHZ, _60GHZ, _6GHZ, ...
+                band: match idx {
+                    0 => 1,
+                    1 => 2,
+                    3 => 4,
+                    _ => 3,
+                },
+                ..BandCaps::default()
+            };
+            for (ty, p) in nla_iter(band) {
+                match ty {
+                    BAND_ATTR_FREQS => caps.channels.extend(nla_iter(p).filter_map(|(_, f)| parse_phy_channel(f))),
+                    BAND_ATTR_HT_CAPA => caps.ht_capa = p.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]])),
+                    BAND_ATTR_VHT_CAPA => caps.vht_capa = le_u32(p),
+                    BAND_ATTR_IFTYPE_DATA => {
+                        for (_, entry) in nla_iter(p) {
+                            for (ty, cap) in nla_iter(entry) {
+                                match ty {
+                                    BAND_IFTYPE_ATTR_HE_CAP_PHY if caps.he_phy.is_none() => {
+                                        caps.he_phy = Some(cap.to_vec())
+                                    }
+                                    BAND_IFTYPE_ATTR_EHT_CAP_PHY if caps.eht_phy.is_none() => {
+                                        caps.eht_phy = Some(cap.to_vec())
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            out.push((idx, caps));
+        }
+    }
+    out
+}
+
+fn parse_phy_channel(nested: &[u8]) -> Option<PhyChannel> {
+    let mut c = PhyChannel::default();
+    let mut freq = None;
+    for (ty, p) in nla_iter(nested) {
+        match ty {
+            FREQUENCY_ATTR_FREQ => freq = le_u32(p),
+            FREQUENCY_ATTR_DISABLED => c.disabled = true,
+            FREQUENCY_ATTR_NO_IR => c.no_ir = true,
+            FREQUENCY_ATTR_RADAR => c.radar = true,
+            // mBm
+            FREQUENCY_ATTR_MAX_TX_POWER => c.max_tx_power_dbm = le_u32(p).map(|p| p as f32 / 100.0),
+            _ => {}
+        }
+    }
+    c.freq_mhz = freq?;
+    Some(c)
+}
+
+#[cfg(feature = "backend-raw-nl80211")]
+async fn dump_scan_results(nl: &Nl80211, ifindex: u32, mut on_row: RowSink) -> Result<Vec<BssRow>> {
+    nl.dump_with(Cmd::CmdGetScan, ifindex_attrs(ifindex)?, move |p| {
+        let Some(mut row) = parse_scan_payload(p) else {
+            debug!(len = p.len(), "GET_SCAN reply without a BSS, skipped");
+            return Ok(None);
+        };
+        on_row(&mut row);
+        Ok(Some(row))
+    })
+    .await
+}
+
+/// Pull the BSS out of one GET_SCAN reply payload (genl header followed by
+/// attributes) straight from the receive buffer.
+pub fn parse_scan_payload(payload: &[u8]) -> Option<BssRow> {
+    // Skip the 4-byte genlmsghdr: cmd, version, reserved.
+    let attrs = payload.get(4..)?;
+    nla_iter(attrs)
+        .find(|&(ty, _)| ty == ATTR_BSS)
+        .map(|(_, nested)| parse_bss(nested))
+}
+
+/// Parse a nested NL80211_ATTR_BSS blob.
+///
+/// nl80211_bss attr IDs we care about:
+///  1 = NL80211_BSS_BSSID
+///  2 = NL80211_BSS_FREQUENCY (u32 MHz)
+///  6 = NL80211_BSS_INFORMATION_ELEMENTS (IEs; SSID is IE id=0)
+///  7 = NL80211_BSS_SIGNAL_MBM (i32 mBm)
+///  8 = NL80211_BSS_SIGNAL_UNSPEC (u8, 0..100)
+///  9 = NL80211_BSS_STATUS (u32; only on the BSS we're in)
+/// 10 = NL80211_BSS_SEEN_MS_AGO (u32)
+/// 15 = NL80211_BSS_LAST_SEEN_BOOTTIME (u64 ns, CLOCK_BOOTTIME; finer
+///      than SEEN_MS_AGO and preferred when the kernel sends it)
+pub fn parse_bss(nested: &[u8]) -> BssRow {
+    let mut bssid = None;
+    let mut freq_mhz = None;
+    let mut ies: Option<&[u8]> = None;
+    let mut signal_mbm: Option<i32> = None;
+    let mut signal_unspec: Option<u8> = None;
+    let mut seen_ms_ago: Option<u32> = None;
+    let mut last_seen_ns: Option<u64> = None;
+    let mut capability: Option<u16> = None;
+    let mut beacon_interval: Option<u16> = None;
+    let mut status: Option<BssStatus> = None;
+
+    for (attr_type, payload) in nla_iter(nested) {
+        match attr_type {
+            1 => bssid = vec_to_mac(payload),
+            2 => freq_mhz = le_u32(payload),
+            4 => beacon_interval = payload.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]])),
+            5 => capability = payload.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]])),
+            6 => ies = Some(payload),
+            7 => signal_mbm = le_u32(payload).map(|v| v as i32),
+            8 => signal_unspec = payload.first().copied(),
+            9 => status = le_u32(payload).and_then(BssStatus::from_nl),
+            10 => seen_ms_ago = le_u32(payload),
+            15 => last_seen_ns = le_u64(payload),
+            _ => {}
+        }
+    }
+
+    // SIGNAL_MBM is 1/100 dBm; SIGNAL_UNSPEC is a 0..100 quality value we
+    // map roughly onto dBm when that's all the driver gives us.
+    let signal_dbm = signal_mbm
+        .map(|mbm| mbm as f32 / 100.0)
+        .or(signal_unspec.map(|q| q as f32 - 100.0));
+
+    if let Some(ns) = last_seen_ns {
+        seen_ms_ago = Some((boottime_ns().saturating_sub(ns) / 1_000_000) as u32);
+    }
+    let mut row = BssRow::from_parts(bssid, freq_mhz, signal_dbm, ies).seen_ms_ago(seen_ms_ago);
+    row.capability = capability;
+    row.beacon_interval = beacon_interval;
+    row.status = status;
+    row
+}
+
+// CLOCK_BOOTTIME, the clock of NL80211_BSS_LAST_SEEN_BOOTTIME, in ns.
+fn boottime_ns() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid timespec to write to.
+    unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn le_u32(b: &[u8]) -> Option<u32> {
+    let tmp: [u8; 4] = b.get(..4)?.try_into().ok()?;
+    Some(u32::from_le_bytes(tmp))
+}
+
+// Attribute / enum ids for the station dumps.
+const ATTR_IFINDEX: u16 = 3;
+const ATTR_IFTYPE: u16 = 5;
+const ATTR_MAC: u16 = 6;
+const ATTR_STA_INFO: u16 = 21;
+const ATTR_WIPHY_FREQ: u16 = 38;
+const IFTYPE_AP: u32 = 3;
+const STA_INFO_RX_BYTES: u16 = 2;
+const STA_INFO_TX_BYTES: u16 = 3;
+const STA_INFO_SIGNAL: u16 = 7;
+const STA_INFO_TX_BITRATE: u16 = 8;
+const STA_INFO_RX_PACKETS: u16 = 9;
+const STA_INFO_TX_PACKETS: u16 = 10;
+const STA_INFO_TX_RETRIES: u16 = 11;
+const STA_INFO_TX_FAILED: u16 = 12;
+const STA_INFO_SIGNAL_AVG: u16 = 13;
+const STA_INFO_RX_BITRATE: u16 = 14;
+const STA_INFO_CONNECTED_TIME: u16 = 16;
+const STA_INFO_RX_BYTES64: u16 = 23;
+const STA_INFO_TX_BYTES64: u16 = 24;
+const STA_INFO_EXPECTED_THROUGHPUT: u16 = 27;
+const STA_INFO_RX_DROP_MISC: u16 = 28;
+const STA_INFO_RX_DURATION: u16 = 32;
+const STA_INFO_TX_DURATION: u16 = 39;
+// nl80211_rate_info: MCS index per PHY generation.
+const RATE_INFO_BITRATE: u16 = 1;
+const RATE_INFO_MCS: u16 = 2;
+const RATE_INFO_BITRATE32: u16 = 5;
+const RATE_INFO_VHT_MCS: u16 = 6;
+const RATE_INFO_HE_MCS: u16 = 13;
+const RATE_INFO_EHT_MCS: u16 = 19;
+
+#[derive(Clone, Copy)]
+struct ApIface {
+    ifindex: u32,
+    radio: ApRadio,
+}
+
+/// Radios of every local AP-mode interface, and the clients associated
+/// to each (GET_STATION dumps, parsed by hand).
+pub async fn ap_stations_async() -> Result<(Vec<ApRadio>, Vec<Station>)> {
+    let nl = Nl80211::shared()?;
+    let ifaces = nl
+        .dump_with(Cmd::CmdGetInterface, GenlBuffer::new(), |p| Ok(parse_ap_iface(p)))
+        .await?;
+    if ifaces.is_empty() {
+        bail!(WifiError::NoInterface("no AP-mode Wi-Fi interface found".into()));
+    }
+
+    let mut stations = Vec::new();
+    for iface in &ifaces {
+        let radio = iface.radio;
+        stations.extend(
+            nl.dump_with(Cmd::CmdGetStation, ifindex_attrs(iface.ifindex)?, move |p| {
+                Ok(parse_station(p, &radio))
+            })
+            .await?,
+        );
+    }
+    Ok((ifaces.into_iter().map(|i| i.radio).collect(), stations))
+}
+
+fn parse_ap_iface(payload: &[u8]) -> Option<ApIface> {
+    let (mut ifindex, mut iftype, mut mac, mut freq) = (None, None, None, None);
+    for (ty, p) in nla_iter(payload.get(4..)?) {
+        match ty {
+            ATTR_IFINDEX => ifindex = le_u32(p),
+            ATTR_IFTYPE => iftype = le_u32(p),
+            ATTR_MAC => mac = vec_to_mac(p),
+            ATTR_WIPHY_FREQ => freq = le_u32(p),
+            _ => {}
+        }
+    }
+    if iftype != Some(IFTYPE_AP) {
+        return None;
+    }
+    Some(ApIface {
+        ifindex: ifindex?,
+        radio: ApRadio {
+            bssid: mac?,
+            freq_mhz: freq?,
+        },
+    })
+}
+
+fn parse_station(payload: &[u8], radio: &ApRadio) -> Option<Station> {
+    let mut st = Station {
+        bssid: radio.bssid,
+        freq_mhz: Some(radio.freq_mhz),
+        ..Default::default()
+    };
+    let (mut mac, mut signal, mut signal_avg) = (None, None, None);
+    let (mut rx_bytes32, mut tx_bytes32) = (None, None);
+    for (ty, p) in nla_iter(payload.get(4..)?) {
+        match ty {
+            ATTR_MAC => mac = vec_to_mac(p),
+            ATTR_STA_INFO => {
+                for (ty, p) in nla_iter(p) {
+                    // u8 holding an s8 dBm value.
+                    let dbm = p.first().map(|&v| v as i8 as f32);
+                    match ty {
+                        STA_INFO_SIGNAL => signal = dbm,
+                        STA_INFO_SIGNAL_AVG => signal_avg = dbm,
+                        STA_INFO_TX_PACKETS => st.tx_packets = le_u32(p).map(u64::from),
+                        STA_INFO_TX_RETRIES => st.tx_retries = le_u32(p).map(u64::from),
+                        STA_INFO_RX_BYTES => rx_bytes32 = le_u32(p).map(u64::from),
+                        STA_INFO_TX_BYTES => tx_bytes32 = le_u32(p).map(u64::from),
+                        STA_INFO_RX_BYTES64 => st.rx_bytes = le_u64(p),
+                        STA_INFO_TX_BYTES64 => st.tx_bytes = le_u64(p),
+                        STA_INFO_RX_DURATION => st.rx_duration_us = le_u64(p),
+                        STA_INFO_TX_DURATION => st.tx_duration_us = le_u64(p),
+                        STA_INFO_TX_BITRATE => {
+                            st.mcs = rate_mcs(p);
+                            st.tx_bitrate_kbps = rate_kbps(p);
+                        }
+                        STA_INFO_RX_BITRATE => st.rx_bitrate_kbps = rate_kbps(p),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    st.mac = mac?;
+    st.signal_dbm = signal_avg.or(signal);
+    // The 32-bit counters wrap at 4 GiB; only fall back to them.
+    st.rx_bytes = st.rx_bytes.or(rx_bytes32);
+    st.tx_bytes = st.tx_bytes.or(tx_bytes32);
+    Some(st)
+}
+
+/// The link of the station interface `ifindex` (the first interface if
+/// None) to its AP: the one entry of its GET_STATION dump, None when not
+/// associated.
+pub async fn link_async(ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+    let links = nl
+        .dump_with(Cmd::CmdGetStation, ifindex_attrs(ifindex)?, |p| Ok(parse_link(p)))
+        .await?;
+    Ok(links.into_iter().next())
+}
+
+fn parse_link(payload: &[u8]) -> Option<LinkInfo> {
+    let mut link = LinkInfo::default();
+    let mut bssid = None;
+    // u8 holding an s8 dBm value.
+    let dbm = |p: &[u8]| p.first().map(|&v| v as i8 as f32);
+    for (ty, p) in nla_iter(payload.get(4..)?) {
+        match ty {
+            ATTR_MAC => bssid = vec_to_mac(p),
+            ATTR_STA_INFO => {
+                for (ty, p) in nla_iter(p) {
+                    match ty {
+                        STA_INFO_SIGNAL => link.signal_dbm = dbm(p),
+                        STA_INFO_SIGNAL_AVG => link.signal_avg_dbm = dbm(p),
+                        STA_INFO_TX_BITRATE => {
+                            link.tx_mcs = rate_mcs(p);
+                            link.tx_bitrate_kbps = rate_kbps(p);
+                        }
+                        STA_INFO_RX_BITRATE => {
+                            link.rx_mcs = rate_mcs(p);
+                            link.rx_bitrate_kbps = rate_kbps(p);
+                        }
+                        STA_INFO_TX_PACKETS => link.tx_packets = le_u32(p).map(u64::from),
+                        STA_INFO_RX_PACKETS => link.rx_packets = le_u32(p).map(u64::from),
+                        STA_INFO_TX_RETRIES => link.tx_retries = le_u32(p).map(u64::from),
+                        STA_INFO_TX_FAILED => link.tx_failed = le_u32(p).map(u64::from),
+                        STA_INFO_RX_DROP_MISC => link.rx_drop_misc = le_u64(p),
+                        // In kbit/s; 0 when rate control has no estimate.
+                        STA_INFO_EXPECTED_THROUGHPUT => link.expected_throughput_kbps = le_u32(p).filter(|&t| t > 0),
+                        STA_INFO_CONNECTED_TIME => link.connected_secs = le_u32(p),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    link.bssid = bssid?;
+    Some(link)
+}
+
+// MCS index out of a nested nl80211_rate_info, whatever the PHY.
+fn rate_mcs(rate: &[u8]) -> Option<u8> {
+    nla_iter(rate).find_map(|(ty, p)| match ty {
+        RATE_INFO_MCS | RATE_INFO_VHT_MCS | RATE_INFO_HE_MCS | RATE_INFO_EHT_MCS => p.first().copied(),
+        _ => None,
+    })
+}
+
+// Bitrate of a nested nl80211_rate_info, which counts in 100 kbit/s.
+fn rate_kbps(rate: &[u8]) -> Option<u32> {
+    let (mut b16, mut b32) = (None, None);
+    for (ty, p) in nla_iter(rate) {
+        match ty {
+            RATE_INFO_BITRATE => b16 = p.get(..2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32),
+            RATE_INFO_BITRATE32 => b32 = le_u32(p),
+            _ => {}
+        }
+    }
+    b32.or(b16).map(|r| r * 100)
+}
+
+fn le_u64(b: &[u8]) -> Option<u64> {
+    let tmp: [u8; 8] = b.get(..8)?.try_into().ok()?;
+    Some(u64::from_le_bytes(tmp))
+}
+
+// Attribute ids for the survey dump.
+const ATTR_SURVEY_INFO: u16 = 84;
+const SURVEY_INFO_FREQUENCY: u16 = 1;
+const SURVEY_INFO_NOISE: u16 = 2;
+const SURVEY_INFO_IN_USE: u16 = 3;
+const SURVEY_INFO_TIME: u16 = 4;
+const SURVEY_INFO_TIME_BUSY: u16 = 5;
+const SURVEY_INFO_TIME_EXT_BUSY: u16 = 6;
+const SURVEY_INFO_TIME_RX: u16 = 7;
+const SURVEY_INFO_TIME_TX: u16 = 8;
+const SURVEY_INFO_TIME_SCAN: u16 = 9;
+
+/// Per-frequency survey counters of the radio behind `ifindex` (the first
+/// interface if None), from a GET_SURVEY dump. `busy` is left for
+/// chansurvey to fill in.
+pub async fn survey_async(ifindex: Option<u32>) -> Result<Vec<ChannelSurvey>> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+    nl.dump_with(Cmd::CmdGetSurvey, ifindex_attrs(ifindex)?, |p| Ok(parse_survey(p)))
+        .await
+}
+
+fn parse_survey(payload: &[u8]) -> Option<ChannelSurvey> {
+    let (_, info) = nla_iter(payload.get(4..)?).find(|&(ty, _)| ty == ATTR_SURVEY_INFO)?;
+    let mut s = ChannelSurvey::default();
+    let mut freq = None;
+    for (ty, p) in nla_iter(info) {
+        match ty {
+            SURVEY_INFO_FREQUENCY => freq = le_u32(p),
+            // u8 holding an s8 dBm value.
+            SURVEY_INFO_NOISE => s.noise_dbm = p.first().map(|&v| v as i8 as f32),
+            SURVEY_INFO_IN_USE => s.in_use = true,
+            SURVEY_INFO_TIME => s.active_ms = le_u64(p),
+            SURVEY_INFO_TIME_BUSY => s.busy_ms = le_u64(p),
+            SURVEY_INFO_TIME_EXT_BUSY => s.ext_busy_ms = le_u64(p),
+            SURVEY_INFO_TIME_RX => s.rx_ms = le_u64(p),
+            SURVEY_INFO_TIME_TX => s.tx_ms = le_u64(p),
+            SURVEY_INFO_TIME_SCAN => s.scan_ms = le_u64(p),
+            _ => {}
+        }
+    }
+    s.freq_mhz = freq?;
+    Some(s)
+}
+
+const ATTR_REG_ALPHA2: u16 = 33;
+const ATTR_REG_RULES: u16 = 34;
+const ATTR_DFS_REGION: u16 = 146;
+// nl80211_reg_rule_attr
+const REG_RULE_FLAGS: u16 = 1;
+const FREQ_RANGE_START: u16 = 2;
+const FREQ_RANGE_END: u16 = 3;
+const FREQ_RANGE_MAX_BW: u16 = 4;
+const POWER_RULE_MAX_ANT_GAIN: u16 = 5;
+const POWER_RULE_MAX_EIRP: u16 = 6;
+const DFS_CAC_TIME: u16 = 7;
+
+/// The global regulatory domain (NL80211_CMD_GET_REG).
+pub async fn regdomain_async() -> Result<RegDomain> {
+    let nl = Nl80211::shared()?;
+    nl.request_with(Cmd::CmdGetReg, GenlBuffer::new(), |p| Ok(parse_regdomain(p)))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| WifiError::Unsupported("CmdGetReg: no regulatory domain in reply".into()).into())
+}
+
+fn parse_regdomain(payload: &[u8]) -> Option<RegDomain> {
+    let mut reg = RegDomain::default();
+    let mut country = None;
+    for (ty, p) in nla_iter(payload.get(4..)?) {
+        match ty {
+            // NUL-terminated two letters.
+            ATTR_REG_ALPHA2 => {
+                country = Some(String::from_utf8_lossy(p).trim_end_matches('\0').to_owned());
+            }
+            ATTR_DFS_REGION => reg.dfs_region = p.first().copied(),
+            ATTR_REG_RULES => reg.rules = nla_iter(p).map(|(_, rule)| parse_reg_rule(rule)).collect(),
+            _ => {}
+        }
+    }
+    reg.country = country?;
+    Some(reg)
+}
+
+fn parse_reg_rule(nested: &[u8]) -> RegRule {
+    let mut r = RegRule::default();
+    for (ty, p) in nla_iter(nested) {
+        match ty {
+            REG_RULE_FLAGS => r.flags = le_u32(p).unwrap_or(0),
+            FREQ_RANGE_START => r.start_khz = le_u32(p).unwrap_or(0),
+            FREQ_RANGE_END => r.end_khz = le_u32(p).unwrap_or(0),
+            FREQ_RANGE_MAX_BW => r.max_bw_khz = le_u32(p).unwrap_or(0),
+            POWER_RULE_MAX_ANT_GAIN => r.max_ant_gain_mbi = le_u32(p),
+            POWER_RULE_MAX_EIRP => r.max_eirp_mbm = le_u32(p),
+            DFS_CAC_TIME => r.dfs_cac_ms = le_u32(p),
+            _ => {}
+        }
+    }
+    r
+}
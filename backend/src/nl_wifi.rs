@@ -0,0 +1,96 @@
+// src/nl_wifi.rs
+//
+// neli-wifi backend. Reads the kernel's BSS table with GET_SCAN and the
+// associated station with GET_STATION over the shared netlink socket,
+// decoding replies with neli-wifi's Bss/Station types (decode_wifi()).
+// It never triggers a scan itself, so results are whatever the kernel
+// (or wpa_supplicant / NetworkManager) last collected.
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use neli::genl::Genlmsghdr;
+use neli::FromBytesWithInput;
+use neli_wifi::{Bss, Nl80211Attr, Nl80211Cmd, Station};
+use tracing::debug;
+
+use crate::lib_rust::{vec_to_mac, BssRow, BssStatus, NoTrigger, RowSink, ScanOptions};
+use crate::netlink::{block_on, ifindex_attrs, ifindex_or_first, Nl80211};
+use crate::nl_raw::Cmd;
+use crate::provider::{ScanFuture, WifiBackend};
+
+
+impl From<Bss> for BssRow {
+    fn from(b: Bss) -> Self {
+        // BSS signal is in mBm (1/100 dBm)
+        let mut row = BssRow::from_parts(
+            b.bssid.as_deref().and_then(vec_to_mac),
+            b.frequency,
+            b.signal.map(|mbm| (mbm as f32) / 100.0),
+            b.information_elements.as_deref(),
+        )
+        .seen_ms_ago(b.seen_ms_ago);
+        row.status = b.status.and_then(BssStatus::from_nl);
+        row
+    }
+}
+
+// A reply decoded with neli-wifi's attribute type, which its Bss and
+// Station conversions take; netlink::decode() uses the crate's own.
+fn decode_wifi(payload: &[u8]) -> Result<Genlmsghdr<Nl80211Cmd, Nl80211Attr>> {
+    Ok(Genlmsghdr::from_bytes_with_input(&mut Cursor::new(payload), payload.len())?)
+}
+
+/// All BSSs currently in the kernel's scan table of `ifindex` (the first
+/// interface if None). `on_row` sees each BSS as soon as its reply is
+/// decoded.
+pub async fn scan_all_bss_async(ifindex: Option<u32>, mut on_row: RowSink) -> Result<Vec<BssRow>> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+    debug!(ifindex, "reading the kernel's BSS table");
+
+    nl.dump_with(Cmd::CmdGetScan, ifindex_attrs(ifindex)?, move |p| {
+        let mut row = BssRow::from(Bss::try_from(decode_wifi(p)?.get_attr_handle())?);
+        on_row(&mut row);
+        Ok(Some(row))
+    })
+    .await
+}
+
+/// BSSID of the AP we are associated with, if any.
+pub async fn get_connected_bssid_async(ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+    let nl = Nl80211::shared()?;
+    let ifindex = ifindex_or_first(&nl, ifindex).await?;
+
+    let bssids = nl
+        .dump_with(Cmd::CmdGetStation, ifindex_attrs(ifindex)?, |p| {
+            //Translate the bytes collected to a readable MAC
+            let genl = decode_wifi(p)?;
+            let station = Station::try_from(genl.get_attr_handle()).ok();
+            Ok(station.and_then(|st| st.bssid.as_deref().and_then(vec_to_mac)))
+        })
+        .await?;
+    Ok(bssids.into_iter().next())
+}
+
+pub fn get_connected_bssid(ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+    block_on(get_connected_bssid_async(ifindex))
+}
+
+/// Backend::NeliWifi; everything but the scan and the connected BSSID is
+/// WifiBackend's nl80211 default.
+pub struct NeliWifiBackend;
+
+impl WifiBackend for NeliWifiBackend {
+    fn scan<'a>(&'a self, ifindex: Option<u32>, _opts: &'a ScanOptions, on_row: RowSink) -> ScanFuture<'a> {
+        Box::pin(async move { Ok((scan_all_bss_async(ifindex, on_row).await?, Some(NoTrigger::Backend))) })
+    }
+
+    fn connected_bssid(&self, ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+        get_connected_bssid(ifindex)
+    }
+
+    fn reports_bss_status(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,116 @@
+// src/oui.rs
+//
+// OUI -> vendor lookups against a compact binary database that is
+// memory-mapped and binary-searched in place, so nothing is parsed or
+// allocated up front and only the pages a lookup touches are read in.
+// The file is built from the IEEE list by scripts/build_oui_db.py.
+//
+// Layout (little endian):
+//   0   magic   "OUI1"
+//   4   count   u32
+//   8   entries count * 8 bytes, sorted by OUI:
+//         oui [u8; 3], name_len u8, name_off u32 (from start of file)
+//   ..  names   UTF-8, not terminated
+//
+// The database is opened on first lookup from $WIFI_BACKEND_OUI_DB or
+// ~/.local/share/wifi_backend/oui.bin; load() swaps in another file.
+
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const MAGIC: &[u8; 4] = b"OUI1";
+const HEADER_LEN: usize = 8;
+const ENTRY_LEN: usize = 8;
+
+pub struct OuiDb {
+    map: Mmap,
+    count: usize,
+}
+
+impl OuiDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        // SAFETY: read-only mapping; the file is only ever replaced, not
+        // rewritten in place, by the build script.
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < HEADER_LEN || &map[..4] != MAGIC {
+            bail!("{}: not an OUI database", path.display());
+        }
+        let count = u32::from_le_bytes([map[4], map[5], map[6], map[7]]) as usize;
+        if map.len() < HEADER_LEN + count * ENTRY_LEN {
+            bail!("{}: truncated OUI database", path.display());
+        }
+        Ok(OuiDb { map, count })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    fn entry(&self, i: usize) -> &[u8] {
+        let off = HEADER_LEN + i * ENTRY_LEN;
+        &self.map[off..off + ENTRY_LEN]
+    }
+
+    /// Vendor name registered for the first three bytes of `mac`.
+    pub fn lookup(&self, mac: &[u8; 6]) -> Option<&str> {
+        let key = &mac[..3];
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let e = self.entry(mid);
+            match e[..3].cmp(key) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    let len = e[3] as usize;
+                    let off = u32::from_le_bytes([e[4], e[5], e[6], e[7]]) as usize;
+                    let name = self.map.get(off..off + len)?;
+                    return std::str::from_utf8(name).ok();
+                }
+            }
+        }
+        None
+    }
+}
+
+// None until the first lookup tries the default path; Some(None) if that
+// failed, so we don't retry on every call.
+static DB: Mutex<Option<Option<Arc<OuiDb>>>> = Mutex::new(None);
+
+fn default_path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os("WIFI_BACKEND_OUI_DB") {
+        return Some(p.into());
+    }
+    let home = std::env::var_os("HOME")?;
+    Some(Path::new(&home).join(".local/share/wifi_backend/oui.bin"))
+}
+
+fn db() -> Option<Arc<OuiDb>> {
+    let mut guard = DB.lock().unwrap_or_else(|e| e.into_inner());
+    guard
+        .get_or_insert_with(|| default_path().and_then(|p| OuiDb::open(&p).ok()).map(Arc::new))
+        .clone()
+}
+
+/// Map `path` as the database for every following lookup. Returns the
+/// number of OUIs in it.
+pub fn load(path: &Path) -> Result<usize> {
+    let db = OuiDb::open(path)?;
+    let n = db.len();
+    *DB.lock().unwrap_or_else(|e| e.into_inner()) = Some(Some(Arc::new(db)));
+    Ok(n)
+}
+
+/// Vendor of the device behind `mac`. Locally administered (randomized)
+/// addresses have no registered vendor.
+pub fn vendor(mac: &[u8; 6]) -> Option<String> {
+    if mac[0] & 0x02 != 0 {
+        return None;
+    }
+    db()?.lookup(mac).map(str::to_owned)
+}
@@ -0,0 +1,278 @@
+// src/pcap.rs
+//
+// Minimal reader for classic pcap files of 802.11 monitor captures
+// (linktype 127, radiotap; or 105, bare 802.11). The file is memory-mapped
+// and walked in place. Of the radiotap header we only decode what airtime
+// accounting needs: flags, legacy rate, channel, and the HT / VHT rate
+// fields. Writer produces the radiotap kind, for capture.rs (capture
+// feature only).
+
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use std::fs::File;
+#[cfg(feature = "capture")]
+use std::io::Write;
+use std::path::Path;
+
+const LINKTYPE_IEEE802_11: u32 = 105;
+const LINKTYPE_RADIOTAP: u32 = 127;
+
+const FLAG_SHORT_PREAMBLE: u8 = 0x02;
+
+// (size, alignment) of radiotap fields 0..=23, in bit order.
+const RT_FIELDS: [(usize, usize); 24] = [
+    (8, 8),  // 0 TSFT
+    (1, 1),  // 1 flags
+    (1, 1),  // 2 rate
+    (4, 2),  // 3 channel
+    (2, 1),  // 4 FHSS
+    (1, 1),  // 5 dBm antenna signal
+    (1, 1),  // 6 dBm antenna noise
+    (2, 2),  // 7 lock quality
+    (2, 2),  // 8 TX attenuation
+    (2, 2),  // 9 dB TX attenuation
+    (1, 1),  // 10 dBm TX power
+    (1, 1),  // 11 antenna
+    (1, 1),  // 12 dB antenna signal
+    (1, 1),  // 13 dB antenna noise
+    (2, 2),  // 14 RX flags
+    (2, 2),  // 15 TX flags
+    (1, 1),  // 16 RTS retries
+    (1, 1),  // 17 data retries
+    (8, 4),  // 18 XChannel
+    (3, 1),  // 19 MCS
+    (8, 4),  // 20 A-MPDU status
+    (12, 2), // 21 VHT
+    (12, 8), // 22 timestamp
+    (12, 2), // 23 HE
+];
+
+// Mbit/s for one spatial stream, long GI, per MCS 0..=9, by width.
+const VHT_RATES: [[f32; 10]; 4] = [
+    [6.5, 13.0, 19.5, 26.0, 39.0, 52.0, 58.5, 65.0, 78.0, 86.7],
+    [13.5, 27.0, 40.5, 54.0, 81.0, 108.0, 121.5, 135.0, 162.0, 180.0],
+    [29.3, 58.5, 87.8, 117.0, 175.5, 234.0, 263.3, 292.5, 351.0, 390.0],
+    [58.5, 117.0, 175.5, 234.0, 351.0, 468.0, 526.5, 585.0, 702.0, 780.0],
+];
+
+/// How a frame was sent, as far as the radiotap header says.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phy {
+    /// 802.11b DSSS/CCK.
+    Dsss { short_preamble: bool },
+    /// 802.11a/g OFDM.
+    Ofdm,
+    /// 802.11n/ac.
+    HtVht,
+}
+
+/// One captured 802.11 frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    pub ts_us: u64,
+    /// 802.11 header and body as captured (possibly truncated).
+    pub data: &'a [u8],
+    /// Length on the air, from the original (untruncated) length.
+    pub len: u32,
+    pub rate_kbps: Option<u32>,
+    pub phy: Option<Phy>,
+    pub freq_mhz: Option<u16>,
+}
+
+pub struct Pcap {
+    map: Mmap,
+    big_endian: bool,
+    nanos: bool,
+    linktype: u32,
+}
+
+impl Pcap {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        // SAFETY: read-only mapping of a capture that isn't being written.
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < 24 {
+            bail!("{}: not a pcap file", path.display());
+        }
+        let magic = u32::from_le_bytes([map[0], map[1], map[2], map[3]]);
+        let (big_endian, nanos) = match magic {
+            0xa1b2_c3d4 => (false, false),
+            0xa1b2_3c4d => (false, true),
+            0xd4c3_b2a1 => (true, false),
+            0x4d3c_b2a1 => (true, true),
+            _ => bail!("{}: not a pcap file (pcapng isn't supported)", path.display()),
+        };
+        let mut p = Pcap {
+            map,
+            big_endian,
+            nanos,
+            linktype: 0,
+        };
+        p.linktype = p.u32_at(20).unwrap_or(0);
+        if p.linktype != LINKTYPE_RADIOTAP && p.linktype != LINKTYPE_IEEE802_11 {
+            bail!("{}: linktype {} is not 802.11 / radiotap", path.display(), p.linktype);
+        }
+        Ok(p)
+    }
+
+    fn u32_at(&self, off: usize) -> Option<u32> {
+        let b: [u8; 4] = self.map.get(off..off + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    /// Every frame in the file. Stops at the first truncated record.
+    pub fn frames(&self) -> impl Iterator<Item = Frame<'_>> {
+        let mut off = 24;
+        std::iter::from_fn(move || loop {
+            let secs = self.u32_at(off)? as u64;
+            let frac = self.u32_at(off + 4)? as u64;
+            let incl = self.u32_at(off + 8)? as usize;
+            let orig = self.u32_at(off + 12)?;
+            let data = self.map.get(off + 16..off + 16 + incl)?;
+            off += 16 + incl;
+
+            let ts_us = secs * 1_000_000 + if self.nanos { frac / 1000 } else { frac };
+            if self.linktype == LINKTYPE_IEEE802_11 {
+                return Some(Frame {
+                    ts_us,
+                    data,
+                    len: orig,
+                    rate_kbps: None,
+                    phy: None,
+                    freq_mhz: None,
+                });
+            }
+            // Skip records with a broken radiotap header.
+            if let Some(f) = radiotap(ts_us, data, orig) {
+                return Some(f);
+            }
+        })
+    }
+}
+
+#[cfg(feature = "capture")]
+/// Writes a classic (microsecond, little-endian) radiotap pcap.
+pub struct Writer<W: Write> {
+    out: W,
+}
+
+#[cfg(feature = "capture")]
+// Longest record kept; the radiotap header and any 802.11 frame fit.
+const SNAPLEN: u32 = 65_535;
+
+#[cfg(feature = "capture")]
+impl<W: Write> Writer<W> {
+    /// Start the file: writes the global header.
+    pub fn new(mut out: W) -> Result<Self> {
+        let mut head = Vec::with_capacity(24);
+        head.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        head.extend_from_slice(&2u16.to_le_bytes());
+        head.extend_from_slice(&4u16.to_le_bytes());
+        head.extend_from_slice(&[0; 8]); // thiszone, sigfigs
+        head.extend_from_slice(&SNAPLEN.to_le_bytes());
+        head.extend_from_slice(&LINKTYPE_RADIOTAP.to_le_bytes());
+        out.write_all(&head)?;
+        Ok(Writer { out })
+    }
+
+    /// Append one record: `data` is the radiotap header and the frame.
+    pub fn write(&mut self, ts_us: u64, data: &[u8]) -> Result<()> {
+        let incl = data.len().min(SNAPLEN as usize);
+        let mut head = [0u8; 16];
+        head[0..4].copy_from_slice(&((ts_us / 1_000_000) as u32).to_le_bytes());
+        head[4..8].copy_from_slice(&((ts_us % 1_000_000) as u32).to_le_bytes());
+        head[8..12].copy_from_slice(&(incl as u32).to_le_bytes());
+        head[12..16].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        self.out.write_all(&head)?;
+        self.out.write_all(&data[..incl])?;
+        Ok(())
+    }
+
+    /// Flush and hand back the output.
+    pub fn finish(mut self) -> Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn radiotap(ts_us: u64, data: &[u8], orig: u32) -> Option<Frame<'_>> {
+    let rt_len = u16::from_le_bytes([*data.get(2)?, *data.get(3)?]) as usize;
+    let frame = data.get(rt_len..)?;
+
+    // Present words: the first one is ours; more follow while bit 31 is set.
+    let present = u32::from_le_bytes(data.get(4..8)?.try_into().ok()?);
+    let mut off = 8;
+    let mut word = present;
+    while word & 0x8000_0000 != 0 {
+        word = u32::from_le_bytes(data.get(off..off + 4)?.try_into().ok()?);
+        off += 4;
+    }
+
+    let (mut flags, mut rate, mut freq) = (0u8, None, None);
+    let (mut mcs, mut vht) = (None, None);
+    for (bit, &(size, align)) in RT_FIELDS.iter().enumerate() {
+        if present & (1 << bit) == 0 {
+            continue;
+        }
+        off = (off + align - 1) & !(align - 1);
+        let f = data.get(off..off + size)?;
+        match bit {
+            1 => flags = f[0],
+            2 => rate = Some(f[0] as u32 * 500),
+            3 => freq = Some(u16::from_le_bytes([f[0], f[1]])),
+            19 => mcs = Some((f[1], f[2])),
+            21 => vht = Some((f[2], f[3], f[4])),
+            _ => {}
+        }
+        off += size;
+    }
+
+    let (rate_kbps, phy) = if let Some((vflags, bw, mcs_nss)) = vht {
+        let width = match bw {
+            0 => 0,
+            1..=3 => 1,
+            4..=10 => 2,
+            _ => 3,
+        };
+        let (m, nss) = ((mcs_nss >> 4) as usize, (mcs_nss & 0x0f) as f32);
+        let mbps = VHT_RATES[width].get(m).map(|r| r * nss.max(1.0) * sgi(vflags & 0x04 != 0));
+        (mbps.map(|r| (r * 1000.0) as u32), Some(Phy::HtVht))
+    } else if let Some((mflags, index)) = mcs {
+        let width = usize::from(mflags & 0x03 == 1);
+        let streams = (index / 8 + 1) as f32;
+        let mbps = VHT_RATES[width][(index % 8) as usize] * streams * sgi(mflags & 0x04 != 0);
+        (Some((mbps * 1000.0) as u32), Some(Phy::HtVht))
+    } else if let Some(r) = rate {
+        let phy = if matches!(r, 1000 | 2000 | 5500 | 11000) {
+            Phy::Dsss {
+                short_preamble: flags & FLAG_SHORT_PREAMBLE != 0,
+            }
+        } else {
+            Phy::Ofdm
+        };
+        (Some(r), Some(phy))
+    } else {
+        (None, None)
+    };
+
+    Some(Frame {
+        ts_us,
+        data: frame,
+        len: orig.saturating_sub(rt_len as u32),
+        rate_kbps,
+        phy,
+        freq_mhz: freq,
+    })
+}
+
+fn sgi(short: bool) -> f32 {
+    if short {
+        10.0 / 9.0
+    } else {
+        1.0
+    }
+}
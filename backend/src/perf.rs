@@ -0,0 +1,176 @@
+// src/perf.rs
+//
+// Process-wide performance counters, cheap enough to leave on:
+//   - scan / connected-BSSID latency per backend (recent samples kept for
+//     percentiles)
+//   - netlink requests sent, datagrams / bytes received, events fanned out
+//   - time spent walking and parsing reply datagrams
+//   - SSID interner hits and misses
+//
+// Everything is reset together by reset().
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Latency samples kept per (op, backend) for percentiles.
+const MAX_SAMPLES: usize = 1024;
+
+pub static NL_SENT: AtomicU64 = AtomicU64::new(0);
+pub static NL_RECV: AtomicU64 = AtomicU64::new(0);
+pub static NL_RECV_BYTES: AtomicU64 = AtomicU64::new(0);
+pub static NL_EVENTS: AtomicU64 = AtomicU64::new(0);
+pub static PARSE_BYTES: AtomicU64 = AtomicU64::new(0);
+pub static PARSE_NS: AtomicU64 = AtomicU64::new(0);
+pub static SSID_HITS: AtomicU64 = AtomicU64::new(0);
+pub static SSID_MISSES: AtomicU64 = AtomicU64::new(0);
+
+static COUNTERS: [&AtomicU64; 8] = [
+    &NL_SENT,
+    &NL_RECV,
+    &NL_RECV_BYTES,
+    &NL_EVENTS,
+    &PARSE_BYTES,
+    &PARSE_NS,
+    &SSID_HITS,
+    &SSID_MISSES,
+];
+
+pub fn add(c: &AtomicU64, n: u64) {
+    c.fetch_add(n, Ordering::Relaxed);
+}
+
+fn get(c: &AtomicU64) -> u64 {
+    c.load(Ordering::Relaxed)
+}
+
+struct Timing {
+    op: &'static str,
+    backend: &'static str,
+    count: u64,
+    // Microseconds, most recent last.
+    samples: VecDeque<u32>,
+}
+
+static TIMINGS: Mutex<Vec<Timing>> = Mutex::new(Vec::new());
+
+fn timings() -> std::sync::MutexGuard<'static, Vec<Timing>> {
+    TIMINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Record how long one `op` ("scan", "connected") took on `backend`.
+pub fn record(op: &'static str, backend: &'static str, took: Duration) {
+    let us = took.as_micros().min(u32::MAX as u128) as u32;
+    let mut t = timings();
+    let i = match t.iter().position(|t| t.op == op && t.backend == backend) {
+        Some(i) => i,
+        None => {
+            t.push(Timing {
+                op,
+                backend,
+                count: 0,
+                samples: VecDeque::new(),
+            });
+            t.len() - 1
+        }
+    };
+    let t = &mut t[i];
+    t.count += 1;
+    if t.samples.len() >= MAX_SAMPLES {
+        t.samples.pop_front();
+    }
+    t.samples.push_back(us);
+}
+
+#[derive(Debug, Clone)]
+pub struct LatencyStats {
+    pub op: &'static str,
+    pub backend: &'static str,
+    pub count: u64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PerfStats {
+    pub latency: Vec<LatencyStats>,
+    pub nl_sent: u64,
+    pub nl_recv: u64,
+    pub nl_recv_bytes: u64,
+    pub nl_events: u64,
+    pub parse_bytes: u64,
+    pub parse_secs: f64,
+    pub ssid_hits: u64,
+    pub ssid_misses: u64,
+}
+
+impl PerfStats {
+    /// Reply parsing throughput in MB/s (0 if nothing was parsed).
+    pub fn parse_mb_per_s(&self) -> f64 {
+        if self.parse_secs > 0.0 {
+            self.parse_bytes as f64 / 1e6 / self.parse_secs
+        } else {
+            0.0
+        }
+    }
+
+    /// SSID interner hit rate in 0..=1 (0 if it was never used).
+    pub fn ssid_hit_rate(&self) -> f64 {
+        let total = self.ssid_hits + self.ssid_misses;
+        if total > 0 {
+            self.ssid_hits as f64 / total as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+// Nearest-rank percentile over sorted samples, in ms.
+fn percentile(sorted: &[u32], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1] as f64 / 1000.0
+}
+
+pub fn snapshot() -> PerfStats {
+    let latency = timings()
+        .iter()
+        .map(|t| {
+            let mut s: Vec<u32> = t.samples.iter().copied().collect();
+            s.sort_unstable();
+            LatencyStats {
+                op: t.op,
+                backend: t.backend,
+                count: t.count,
+                p50_ms: percentile(&s, 50.0),
+                p90_ms: percentile(&s, 90.0),
+                p99_ms: percentile(&s, 99.0),
+                max_ms: s.last().map_or(0.0, |&us| us as f64 / 1000.0),
+            }
+        })
+        .collect();
+
+    PerfStats {
+        latency,
+        nl_sent: get(&NL_SENT),
+        nl_recv: get(&NL_RECV),
+        nl_recv_bytes: get(&NL_RECV_BYTES),
+        nl_events: get(&NL_EVENTS),
+        parse_bytes: get(&PARSE_BYTES),
+        parse_secs: get(&PARSE_NS) as f64 / 1e9,
+        ssid_hits: get(&SSID_HITS),
+        ssid_misses: get(&SSID_MISSES),
+    }
+}
+
+pub fn reset() {
+    for c in COUNTERS {
+        c.store(0, Ordering::Relaxed);
+    }
+    timings().clear();
+}
@@ -0,0 +1,141 @@
+// src/phycaps.rs
+//
+// Radio capabilities from a split NL80211_CMD_GET_WIPHY dump: per band,
+// the channels the driver lists (and which it has disabled, made NO-IR or
+// marked for radar detection), the HT / VHT / HE / EHT capabilities and the
+// widest channel they allow. Channel recommendations only propose channels
+// the radio lists and hasn't disabled.
+//
+// A split dump may spread one band over several messages (frequencies in
+// one, capabilities in another), so parts are merged by nl80211 band index.
+//
+// Exposes:
+//   - PhyCaps { bands } / BandCaps / PhyChannel, PhyCaps::usable(band, ch)
+//   - query(backend, ifindex) -> Result<PhyCaps>, kept as the latest
+//   - refresh(backend, ifindex): query() where it is only an extra input
+//   - current() -> the latest capabilities, if any
+//   - clear()
+
+use anyhow::Result;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::lib_rust::{freq_to_channel, Backend};
+use crate::perf;
+
+// HT Capabilities Info: Supported Channel Width Set (20/40 MHz).
+const HT_CAP_SUP_WIDTH_20_40: u16 = 1 << 1;
+// VHT Capabilities Info: Supported Channel Width Set (bits 2-3), nonzero
+// for 160 MHz (and 80+80).
+const VHT_CAP_SUP_WIDTH_MASK: u32 = 0b11 << 2;
+// HE PHY Capabilities byte 0, Channel Width Set.
+const HE_PHY_40_IN_2G: u8 = 1 << 1;
+const HE_PHY_40_80_IN_5G_6G: u8 = 1 << 2;
+const HE_PHY_160_IN_5G_6G: u8 = 1 << 3;
+// EHT PHY Capabilities byte 0: 320 MHz in 6 GHz.
+const EHT_PHY_320_IN_6G: u8 = 1 << 1;
+
+/// One channel the radio lists.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhyChannel {
+    pub freq_mhz: u32,
+    pub disabled: bool,
+    /// Passive-only: the radio won't beacon or probe here.
+    pub no_ir: bool,
+    /// Radar detection required.
+    pub radar: bool,
+    pub max_tx_power_dbm: Option<f32>,
+}
+
+/// One band of the radio.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BandCaps {
+    /// freq_band() id: 1 = 2.4 GHz, 2 = 5 GHz, 4 = 6 GHz, 3 = others.
+    pub band: u8,
+    pub channels: Vec<PhyChannel>,
+    /// HT Capabilities Info, if the band does HT (802.11n).
+    pub ht_capa: Option<u16>,
+    /// VHT Capabilities Info, if the band does VHT (802.11ac).
+    pub vht_capa: Option<u32>,
+    /// HE PHY Capabilities Information (802.11ax), if any.
+    pub he_phy: Option<Vec<u8>>,
+    /// EHT PHY Capabilities Information (802.11be), if any.
+    pub eht_phy: Option<Vec<u8>>,
+}
+
+impl BandCaps {
+    /// The widest channel (MHz) the band's capabilities allow.
+    pub fn max_width_mhz(&self) -> u32 {
+        let ht = self.ht_capa.is_some_and(|c| c & HT_CAP_SUP_WIDTH_20_40 != 0);
+        let he = self.he_phy.as_ref().and_then(|p| p.first()).copied().unwrap_or(0);
+        let eht = self.eht_phy.as_ref().and_then(|p| p.first()).copied().unwrap_or(0);
+        match self.band {
+            1 if ht || he & HE_PHY_40_IN_2G != 0 => 40,
+            1 => 20,
+            4 if eht & EHT_PHY_320_IN_6G != 0 => 320,
+            _ if self.vht_capa.is_some_and(|c| c & VHT_CAP_SUP_WIDTH_MASK != 0) => 160,
+            _ if he & HE_PHY_160_IN_5G_6G != 0 => 160,
+            _ if self.vht_capa.is_some() || he & HE_PHY_40_80_IN_5G_6G != 0 => 80,
+            _ if ht => 40,
+            _ => 20,
+        }
+    }
+
+    /// Every width up to max_width_mhz(), narrowest first.
+    pub fn widths(&self) -> Vec<u32> {
+        let max = self.max_width_mhz();
+        [20, 40, 80, 160, 320].into_iter().filter(|&w| w <= max).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhyCaps {
+    pub bands: Vec<BandCaps>,
+}
+
+impl PhyCaps {
+    /// Whether the radio lists channel `ch` of freq_band() `band` and
+    /// hasn't disabled it.
+    pub fn usable(&self, band: u8, ch: u32) -> bool {
+        self.bands.iter().filter(|b| b.band == band).any(|b| {
+            b.channels
+                .iter()
+                .any(|c| !c.disabled && freq_to_channel(c.freq_mhz) == Some(ch))
+        })
+    }
+}
+
+static LAST: Mutex<Option<PhyCaps>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<PhyCaps>> {
+    LAST.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Capabilities of `ifindex`'s radio (the first Wi-Fi interface if None),
+/// from `b`'s provider.
+pub fn query(b: Backend, ifindex: Option<u32>) -> Result<PhyCaps> {
+    let start = Instant::now();
+    let caps = b.provider().phy_caps(ifindex)?;
+    perf::record("phy_caps", b.name(), start.elapsed());
+    *lock() = Some(caps.clone());
+    Ok(caps)
+}
+
+/// query(), ignoring failures like regdom::refresh(): a failed query
+/// drops the previous capabilities, so nothing is filtered.
+pub fn refresh(b: Backend, ifindex: Option<u32>) {
+    if query(b, ifindex).is_err() {
+        clear();
+    }
+}
+
+/// The latest capabilities query() returned.
+pub fn current() -> Option<PhyCaps> {
+    lock().clone()
+}
+
+/// Forget them, so recommendations aren't limited to a radio until the
+/// next query.
+pub fn clear() {
+    *lock() = None;
+}
@@ -0,0 +1,202 @@
+// src/phyrate.rs
+//
+// PHY rates from the 802.11 MCS tables. A rate is streams x data
+// subcarriers (per PHY and width) x bits per subcarrier x coding rate (per
+// MCS), per OFDM symbol: 4 µs for HT / VHT (3.6 µs with the short guard
+// interval), 13.6 µs for HE / EHT (0.8 µs guard interval).
+//
+// A BSS's maximum rate is the best its rate elements (ies::PhyRates) allow
+// at the width it operates on: an 802.11b-only AP tops out at 11 Mbit/s
+// and holds the channel far longer per byte than an 802.11ax one.
+//
+// What a client can expect from it at a spot is rougher: the SNR over a
+// nominal noise floor picks the MCS, a 2x2 client uses at most two
+// streams, and a fixed share of the rate is lost to the MAC. Good enough
+// to tell "~180 Mbit/s here" from "~20 Mbit/s here" on a walk-around.
+//
+// Exposes:
+//   - Phy
+//   - rate_mbps(phy, mcs, streams, width_mhz, short_gi) -> Option<f32>
+//   - max_rate_mbps(rates, width_mhz, band) -> Option<f32>
+//   - estimate_mbps(rates, width_mhz, band, signal_dbm) -> Option<f32>
+
+use crate::ies::PhyRates;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phy {
+    Ht,
+    Vht,
+    He,
+    Eht,
+}
+
+/// Noise floor of a 20 MHz channel, dBm: thermal noise (-101 dBm) plus a
+/// typical receiver's noise figure.
+pub const NOISE_FLOOR_20_DBM: f32 = -95.0;
+/// Spatial streams of a typical client (phones and laptops are 2x2).
+pub const CLIENT_STREAMS: u8 = 2;
+/// Share of the PHY rate left as throughput once preambles, contention,
+/// acknowledgements and protocol headers are paid for, with aggregation.
+pub const MAC_EFFICIENCY: f32 = 0.65;
+
+// SNR (dB) a receiver needs for MCS 0 to 13 at a low error rate.
+const MIN_SNR_DB: [f32; 14] = [5.0, 8.0, 11.0, 14.0, 17.0, 21.0, 23.0, 25.0, 29.0, 31.0, 34.0, 37.0, 40.0, 43.0];
+
+// 802.11b and 802.11a/g rates (Mbit/s) and the SNR (dB) each needs.
+const LEGACY_MIN_SNR_DB: [(f32, f32); 12] = [
+    (1.0, 0.0),
+    (2.0, 3.0),
+    (5.5, 6.0),
+    (6.0, 5.0),
+    (9.0, 6.0),
+    (11.0, 9.0),
+    (12.0, 8.0),
+    (18.0, 11.0),
+    (24.0, 14.0),
+    (36.0, 18.0),
+    (48.0, 22.0),
+    (54.0, 24.0),
+];
+
+// Bits per subcarrier and coding rate of MCS 0 to 13: BPSK 1/2 up to
+// 4096-QAM 5/6.
+const MCS: [(u32, f32); 14] = [
+    (1, 1.0 / 2.0),
+    (2, 1.0 / 2.0),
+    (2, 3.0 / 4.0),
+    (4, 1.0 / 2.0),
+    (4, 3.0 / 4.0),
+    (6, 2.0 / 3.0),
+    (6, 3.0 / 4.0),
+    (6, 5.0 / 6.0),
+    (8, 3.0 / 4.0),
+    (8, 5.0 / 6.0),
+    (10, 3.0 / 4.0),
+    (10, 5.0 / 6.0),
+    (12, 3.0 / 4.0),
+    (12, 5.0 / 6.0),
+];
+
+fn data_subcarriers(phy: Phy, width_mhz: u32) -> Option<u32> {
+    Some(match (phy, width_mhz) {
+        (Phy::Ht | Phy::Vht, 20) => 52,
+        (Phy::Ht | Phy::Vht, 40) => 108,
+        (Phy::Vht, 80) => 234,
+        (Phy::Vht, 160) => 468,
+        (Phy::He | Phy::Eht, 20) => 234,
+        (Phy::He | Phy::Eht, 40) => 468,
+        (Phy::He | Phy::Eht, 80) => 980,
+        (Phy::He | Phy::Eht, 160) => 1960,
+        (Phy::Eht, 320) => 3920,
+        _ => return None,
+    })
+}
+
+/// Data rate of one MCS, Mbit/s. `short_gi` only matters for HT / VHT.
+/// None for an MCS past 13 or a width the PHY doesn't have.
+pub fn rate_mbps(phy: Phy, mcs: u8, streams: u8, width_mhz: u32, short_gi: bool) -> Option<f32> {
+    let (bits, coding) = *MCS.get(mcs as usize)?;
+    let subcarriers = data_subcarriers(phy, width_mhz)?;
+    let symbol_us = match phy {
+        Phy::Ht | Phy::Vht if short_gi => 3.6,
+        Phy::Ht | Phy::Vht => 4.0,
+        Phy::He | Phy::Eht => 13.6,
+    };
+    Some(streams as f32 * subcarriers as f32 * bits as f32 * coding / symbol_us)
+}
+
+// (streams, highest MCS) of each stream count a VHT / HE MCS map supports:
+// 2 bits per count, 3 for not supported, `top` the highest MCS of a value.
+fn map_entries(map: u16, top: fn(u16) -> u8) -> impl Iterator<Item = (u8, u8)> {
+    (0..8u8).filter_map(move |i| {
+        let v = map >> (2 * i) & 3;
+        (v != 3).then(|| (i + 1, top(v)))
+    })
+}
+
+/// Fastest rate `rates` allow at `width_mhz`, the BSS's operating width,
+/// in freq_band() `band`, Mbit/s. VHT only counts outside 2.4 GHz; an EHT
+/// BSS is taken to reach MCS 13 on the streams its HE map lists. None
+/// when the elements list no rate at all.
+pub fn max_rate_mbps(rates: &PhyRates, width_mhz: u32, band: u8) -> Option<f32> {
+    best_rate(rates, width_mhz, band, None, 8)
+}
+
+/// Throughput a typical client (CLIENT_STREAMS streams) can expect from a
+/// BSS heard at `signal_dbm`, Mbit/s: the fastest rate whose MCS the SNR
+/// over the width's noise floor supports, times MAC_EFFICIENCY. 0.0 when
+/// even the slowest rate is out of reach, None when the elements list no
+/// rate.
+pub fn estimate_mbps(rates: &PhyRates, width_mhz: u32, band: u8, signal_dbm: f32) -> Option<f32> {
+    max_rate_mbps(rates, width_mhz, band)?;
+    let snr_20 = signal_dbm - NOISE_FLOOR_20_DBM;
+    let rate = best_rate(rates, width_mhz, band, Some(snr_20), CLIENT_STREAMS);
+    Some(rate.unwrap_or(0.0) * MAC_EFFICIENCY)
+}
+
+// Best rate over the PHYs `rates` lists, each stream count up to
+// `max_streams` at the highest MCS both it and the SNR (dB over the 20 MHz
+// noise floor; None for no limit) allow.
+fn best_rate(rates: &PhyRates, width_mhz: u32, band: u8, snr_20: Option<f32>, max_streams: u8) -> Option<f32> {
+    // Twice the width, twice the noise.
+    let snr = |w: u32| snr_20.map(|s| s - 10.0 * (w as f32 / 20.0).log10());
+    let mut best = rates.legacy_mbps.and_then(|top| legacy_rate(top, snr_20));
+    let mut offer = |rate: Option<f32>| {
+        if rate > best {
+            best = rate;
+        }
+    };
+    if rates.ht_streams > 0 {
+        let w = width_mhz.min(40);
+        let sgi = if w == 40 { rates.ht_sgi40 } else { rates.ht_sgi20 };
+        if let Some(mcs) = reachable_mcs(7, snr(w)) {
+            offer(rate_mbps(Phy::Ht, mcs, rates.ht_streams.min(max_streams), w, sgi));
+        }
+    }
+    if let Some(map) = rates.vht_mcs_map.filter(|_| band != 1) {
+        let w = width_mhz.min(160);
+        let sgi = match w {
+            160 => rates.vht_sgi160,
+            80 => rates.vht_sgi80,
+            40 => rates.ht_sgi40,
+            _ => rates.ht_sgi20,
+        };
+        for (streams, top) in map_entries(map, |v| 7 + v as u8).filter(|&(n, _)| n <= max_streams) {
+            // MCS 9 at 20 MHz only exists for 3 and 6 streams.
+            let top = if w == 20 && streams % 3 != 0 { top.min(8) } else { top };
+            if let Some(mcs) = reachable_mcs(top, snr(w)) {
+                offer(rate_mbps(Phy::Vht, mcs, streams, w, sgi));
+            }
+        }
+    }
+    if let Some(map) = rates.he_mcs_map {
+        let (phy, w) = if rates.eht { (Phy::Eht, width_mhz.min(320)) } else { (Phy::He, width_mhz.min(160)) };
+        for (streams, top) in map_entries(map, |v| 7 + 2 * v as u8).filter(|&(n, _)| n <= max_streams) {
+            let top = if rates.eht { 13 } else { top };
+            if let Some(mcs) = reachable_mcs(top, snr(w)) {
+                offer(rate_mbps(phy, mcs, streams, w, false));
+            }
+        }
+    }
+    best
+}
+
+// Highest MCS up to `top` that `snr` supports; `top` without an SNR.
+fn reachable_mcs(top: u8, snr: Option<f32>) -> Option<u8> {
+    let Some(snr) = snr else {
+        return Some(top);
+    };
+    (0..=top).rev().find(|&m| MIN_SNR_DB.get(m as usize).is_some_and(|&need| snr >= need))
+}
+
+// Fastest 802.11a/b/g rate up to `top` that `snr_20` supports.
+fn legacy_rate(top: f32, snr_20: Option<f32>) -> Option<f32> {
+    let Some(snr) = snr_20 else {
+        return Some(top);
+    };
+    LEGACY_MIN_SNR_DB
+        .iter()
+        .filter(|&&(rate, need)| rate <= top && snr >= need)
+        .map(|&(rate, _)| rate)
+        .reduce(f32::max)
+}
@@ -0,0 +1,812 @@
+// src/plan.rs
+//
+// Channel planning for a mesh of several nodes, where picking the single
+// best channel (lib_rust::best_channel_from_rows) for every node would put
+// them all on top of each other.
+//
+// Exposes:
+//   - interference_24(rows, own) -> cost per candidate 2.4 GHz channel
+//   - node_rssi(per_node_rows, node_bssids) -> how well nodes hear each other
+//   - assign_24ghz(per_node, coupling) -> one channel per node
+//   - interference_5(rows, own, channels) / assign_5ghz(..., follow) -> the
+//     same for 80 MHz blocks on 5 GHz, where nodes on wireless backhaul
+//     have to stay on their parent's channel (see backhaul::classify)
+//   - interference_6(rows, own, channels) -> the same on 6 GHz, where the
+//     blocks are numbered by their Preferred Scanning Channel
+//   - channel_options(band, dfs, limits) / plan_mesh(per_node_rows, own,
+//     coupling, bands, follow) -> a channel and width per node on every
+//     band, chosen jointly
+//
+// The exhaustive searches look at their Cancel token every
+// CANCEL_CHECK_EVERY assignments.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::cancel::Cancel;
+use crate::lib_rust::{
+    channel_breakdown, freq_band, row_weight, same_device, width_block, BssRow, ChannelConfig, ChannelLimits,
+    CHANNELS_5_20, CHANNELS_5_20_DFS, CHANNELS_6_PSC,
+};
+
+/// 2.4 GHz channels that don't overlap each other (20 MHz, FCC).
+pub const CHANNELS_24: [u32; 3] = [1, 6, 11];
+
+// Cost added for every pair of own nodes sharing a channel once there are
+// more nodes than non-overlapping channels, when we don't know how well
+// they hear each other. On the same scale as ChannelConfig::ap_weight():
+// one AP heard at -50 dBm.
+const REUSE_PENALTY: f32 = 50.0;
+
+/// Below this, a sibling node's beacons are under the usual -82 dBm CCA
+/// threshold: the two nodes don't defer to each other and can share a
+/// channel for free.
+pub const REUSE_DBM: f32 = -82.0;
+
+const CANCEL_CHECK_EVERY: usize = 4096;
+
+// Exhaustive search up to this many nodes (3^12 ~ 530k assignments).
+const MAX_EXHAUSTIVE: usize = 12;
+
+/// How much foreign 2.4 GHz traffic a node would see on each of
+/// CHANNELS_24. APs on neighbouring channels count partially: their 22 MHz
+/// spectrum overlaps ours until they are 5 channels away.
+///
+/// `own` lists our own BSSIDs; those (and other radios of the same
+/// devices) are not interference since they are being planned too.
+pub fn interference_24(rows: &[BssRow], own: &[[u8; 6]]) -> [f32; 3] {
+    let mut cost = [0.0f32; 3];
+
+    for r in rows {
+        let (Some(ch), Some(freq)) = (r.channel, r.freq_mhz) else {
+            continue;
+        };
+        if freq_band(freq) != 1 {
+            continue;
+        }
+        if let Some(b) = &r.bssid {
+            if own.iter().any(|o| o == b || same_device(o, b)) {
+                continue;
+            }
+        }
+        let Some(w) = row_weight(r) else {
+            continue;
+        };
+
+        for (c, &target) in cost.iter_mut().zip(&CHANNELS_24) {
+            let overlap = 1.0 - ch.abs_diff(target) as f32 / 5.0;
+            if overlap > 0.0 {
+                *c += w * overlap;
+            }
+        }
+    }
+    cost
+}
+
+/// Strongest beacon of node j heard in node i's scan, for every pair:
+/// `rssi[i][j]`, None if i never heard j. Made symmetric by taking the
+/// stronger direction, since either side deferring costs airtime.
+pub fn node_rssi(per_node_rows: &[Vec<BssRow>], node_bssids: &[Vec<[u8; 6]>]) -> Vec<Vec<Option<f32>>> {
+    let n = per_node_rows.len();
+    let mut rssi: Vec<Vec<Option<f32>>> = vec![vec![None; n]; n];
+
+    for (i, rows) in per_node_rows.iter().enumerate() {
+        for r in rows {
+            let (Some(b), Some(sig)) = (&r.bssid, r.signal_dbm) else {
+                continue;
+            };
+            for (j, radios) in node_bssids.iter().enumerate().take(n) {
+                if i == j || !radios.iter().any(|o| o == b || same_device(o, b)) {
+                    continue;
+                }
+                let cur = &mut rssi[i][j];
+                *cur = Some(cur.map_or(sig, |c| c.max(sig)));
+            }
+        }
+    }
+
+    let heard = rssi.clone();
+    for (i, row) in rssi.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = match (heard[i][j], heard[j][i]) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+    }
+    rssi
+}
+
+// What it costs for nodes i and j to share a channel.
+fn pair_penalty(coupling: Option<&[Vec<Option<f32>>]>, i: usize, j: usize) -> f32 {
+    let Some(rssi) = coupling else {
+        return REUSE_PENALTY;
+    };
+    match rssi.get(i).and_then(|row| row.get(j)).copied().flatten() {
+        Some(sig) if sig >= REUSE_DBM => (sig + 100.0).max(0.0),
+        // Not heard, or too weak to defer to: free reuse.
+        _ => 0.0,
+    }
+}
+
+/// Assign each node one of CHANNELS_24 given its interference_24() costs.
+///
+/// Without `coupling` (node_rssi() output), up to three nodes always get
+/// distinct channels, picking the permutation with the least total
+/// interference; with more nodes some must share and the search also
+/// minimises the number of sharing pairs.
+///
+/// With `coupling`, nodes that can't hear each other above REUSE_DBM may
+/// share a channel for free, and sharing between nodes that do is charged
+/// by how loud they are to each other. Ties go to the assignment that
+/// comes first in node order.
+pub fn assign_24ghz(
+    per_node: &[[f32; 3]],
+    coupling: Option<&[Vec<Option<f32>>]>,
+    cancel: &Cancel,
+) -> Result<Vec<u32>> {
+    let n = per_node.len();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    let idx = if n <= MAX_EXHAUSTIVE {
+        exhaustive(per_node, coupling, cancel)?
+    } else {
+        greedy(per_node, coupling)
+    };
+    Ok(idx.into_iter().map(|i| CHANNELS_24[i]).collect())
+}
+
+fn total_cost(per_node: &[[f32; 3]], coupling: Option<&[Vec<Option<f32>>]>, pick: &[usize]) -> f32 {
+    let mut cost: f32 = pick.iter().zip(per_node).map(|(&c, w)| w[c]).sum();
+    for i in 0..pick.len() {
+        for j in i + 1..pick.len() {
+            if pick[i] == pick[j] {
+                cost += pair_penalty(coupling, i, j);
+            }
+        }
+    }
+    cost
+}
+
+fn exhaustive(
+    per_node: &[[f32; 3]],
+    coupling: Option<&[Vec<Option<f32>>]>,
+    cancel: &Cancel,
+) -> Result<Vec<usize>> {
+    let n = per_node.len();
+    let mut pick = vec![0usize; n];
+    let mut best = (f32::INFINITY, pick.clone());
+
+    let mut step = 0usize;
+    loop {
+        if step.is_multiple_of(CANCEL_CHECK_EVERY) {
+            cancel.check()?;
+        }
+        step += 1;
+        // Without coupling data and at most three nodes, only
+        // permutations are allowed.
+        let distinct = n > 3 || coupling.is_some() || {
+            let mut seen = [false; 3];
+            pick.iter().all(|&c| !std::mem::replace(&mut seen[c], true))
+        };
+        if distinct {
+            let cost = total_cost(per_node, coupling, &pick);
+            if cost < best.0 {
+                best = (cost, pick.clone());
+            }
+        }
+
+        // Next assignment, counting in base 3.
+        let mut i = 0;
+        loop {
+            if i == n {
+                return Ok(best.1);
+            }
+            pick[i] += 1;
+            if pick[i] < 3 {
+                break;
+            }
+            pick[i] = 0;
+            i += 1;
+        }
+    }
+}
+
+// Large meshes: place the nodes with the strongest preference first, each
+// on its cheapest channel counting reuse with the nodes placed so far.
+fn greedy(per_node: &[[f32; 3]], coupling: Option<&[Vec<Option<f32>>]>) -> Vec<usize> {
+    let spread = |w: &[f32; 3]| {
+        let max = w.iter().cloned().fold(f32::MIN, f32::max);
+        let min = w.iter().cloned().fold(f32::MAX, f32::min);
+        max - min
+    };
+    let mut order: Vec<usize> = (0..per_node.len()).collect();
+    order.sort_by(|&a, &b| spread(&per_node[b]).total_cmp(&spread(&per_node[a])));
+
+    let mut pick: Vec<Option<usize>> = vec![None; per_node.len()];
+    for i in order {
+        let cost = |c: usize| {
+            let reuse: f32 = pick
+                .iter()
+                .enumerate()
+                .filter(|&(_, p)| *p == Some(c))
+                .map(|(j, _)| pair_penalty(coupling, i, j))
+                .sum();
+            per_node[i][c] + reuse
+        };
+        let c = (0..3).min_by(|&a, &b| cost(a).total_cmp(&cost(b))).unwrap_or(0);
+        pick[i] = Some(c);
+    }
+    pick.into_iter().map(|p| p.unwrap_or(0)).collect()
+}
+
+/// 80 MHz blocks for a 5 GHz mesh, by primary channel. The DFS ones can be
+/// vacated at any time on radar detection, so they're opt-in.
+pub const CHANNELS_5_NON_DFS: [u32; 2] = [36, 149];
+pub const CHANNELS_5_DFS: [u32; 4] = [52, 100, 116, 132];
+
+/// 80 MHz blocks for a 6 GHz mesh, by primary channel: the PSC in each
+/// block, so clients that only probe PSCs still find the nodes. The
+/// partial block above 221 has no 80 MHz channel.
+pub const CHANNELS_6_PSC_80: [u32; 14] = [5, 21, 37, 53, 69, 85, 101, 117, 133, 149, 165, 181, 197, 213];
+
+// Exhaustive search over per-group choices up to this many combinations.
+const MAX_COMBINATIONS: usize = 600_000;
+
+// 80 MHz block a 5 GHz channel belongs to (36-48, 52-64, 100-112, ...).
+fn block_5(ch: u32) -> Option<u32> {
+    match ch {
+        36..=64 => Some((ch - 36) / 16),
+        100..=144 => Some(2 + (ch - 100) / 16),
+        149..=177 => Some(5 + (ch - 149) / 16),
+        _ => None,
+    }
+}
+
+// 80 MHz block a 6 GHz channel belongs to (1-13, 17-29, ...).
+fn block_6(ch: u32) -> Option<u32> {
+    (1..=233).contains(&ch).then(|| (ch - 1) / 16)
+}
+
+/// interference_24() for 5 GHz: cost of each of `channels` (80 MHz block
+/// primaries) from the foreign APs heard in the same block.
+pub fn interference_5(rows: &[BssRow], own: &[[u8; 6]], channels: &[u32]) -> Vec<f32> {
+    interference_blocks(rows, own, channels, 2, block_5)
+}
+
+/// interference_5() for 6 GHz, e.g. on CHANNELS_6_PSC_80.
+pub fn interference_6(rows: &[BssRow], own: &[[u8; 6]], channels: &[u32]) -> Vec<f32> {
+    interference_blocks(rows, own, channels, 4, block_6)
+}
+
+fn interference_blocks(
+    rows: &[BssRow],
+    own: &[[u8; 6]],
+    channels: &[u32],
+    band: u8,
+    block: fn(u32) -> Option<u32>,
+) -> Vec<f32> {
+    let mut cost = vec![0.0f32; channels.len()];
+
+    for r in rows {
+        let (Some(ch), Some(freq)) = (r.channel, r.freq_mhz) else {
+            continue;
+        };
+        if freq_band(freq) != band {
+            continue;
+        }
+        if let Some(b) = &r.bssid {
+            if own.iter().any(|o| o == b || same_device(o, b)) {
+                continue;
+            }
+        }
+        let Some(w) = row_weight(r) else {
+            continue;
+        };
+        for (c, &target) in cost.iter_mut().zip(channels) {
+            if block(ch).is_some_and(|b| block(target) == Some(b)) {
+                *c += w;
+            }
+        }
+    }
+    cost
+}
+
+/// Assign each node one of `channels` given its interference_5() costs.
+///
+/// `follow[i] = Some(p)` means node i's backhaul is a wireless link to node
+/// p on this band, so both must use the same channel; wired nodes (None)
+/// are free to pick their own. Nodes sharing a channel otherwise are
+/// charged as in assign_24ghz().
+pub fn assign_5ghz(
+    per_node: &[Vec<f32>],
+    channels: &[u32],
+    coupling: Option<&[Vec<Option<f32>>]>,
+    follow: &[Option<usize>],
+    cancel: &Cancel,
+) -> Result<Vec<u32>> {
+    let n = per_node.len();
+    let k = channels.len();
+    if n == 0 || k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let (member_of, g) = backhaul_groups(n, follow);
+
+    let mut costs = vec![vec![0.0f32; k]; g];
+    for (i, w) in per_node.iter().enumerate() {
+        for (c, x) in costs[member_of[i]].iter_mut().zip(w) {
+            *c += x;
+        }
+    }
+    let mut penalty = vec![vec![0.0f32; g]; g];
+    for i in 0..n {
+        for j in i + 1..n {
+            let (a, b) = (member_of[i], member_of[j]);
+            if a != b {
+                let p = pair_penalty(coupling, i, j);
+                penalty[a][b] += p;
+                penalty[b][a] += p;
+            }
+        }
+    }
+
+    let pick = if (k as f64).powi(g as i32) <= MAX_COMBINATIONS as f64 {
+        search_exhaustive(&costs, &penalty, cancel)?
+    } else {
+        search_greedy(&costs, &penalty)
+    };
+    Ok(member_of.into_iter().map(|grp| channels[pick[grp]]).collect())
+}
+
+// Nodes tied by wireless backhaul move as one group: each node's group
+// index, and how many groups there are.
+fn backhaul_groups(n: usize, follow: &[Option<usize>]) -> (Vec<usize>, usize) {
+    let mut group: Vec<usize> = (0..n).collect();
+    fn root(group: &mut [usize], mut i: usize) -> usize {
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    for (i, p) in follow.iter().enumerate().take(n) {
+        if let Some(p) = p.filter(|&p| p < n && p != i) {
+            let (a, b) = (root(&mut group, i), root(&mut group, p));
+            group[a] = b;
+        }
+    }
+    let mut ids: Vec<usize> = Vec::new();
+    let member_of: Vec<usize> = (0..n)
+        .map(|i| {
+            let r = root(&mut group, i);
+            ids.iter().position(|&x| x == r).unwrap_or_else(|| {
+                ids.push(r);
+                ids.len() - 1
+            })
+        })
+        .collect();
+    let g = ids.len();
+    (member_of, g)
+}
+
+// Counting in base k over every group's choice.
+fn search_exhaustive(costs: &[Vec<f32>], penalty: &[Vec<f32>], cancel: &Cancel) -> Result<Vec<usize>> {
+    let (g, k) = (costs.len(), costs[0].len());
+    let mut pick = vec![0usize; g];
+    let mut best = (f32::INFINITY, pick.clone());
+    let mut step = 0usize;
+    loop {
+        if step.is_multiple_of(CANCEL_CHECK_EVERY) {
+            cancel.check()?;
+        }
+        step += 1;
+        let mut cost: f32 = pick.iter().zip(costs).map(|(&c, w)| w[c]).sum();
+        for a in 0..g {
+            for b in a + 1..g {
+                if pick[a] == pick[b] {
+                    cost += penalty[a][b];
+                }
+            }
+        }
+        if cost < best.0 {
+            best = (cost, pick.clone());
+        }
+
+        let mut i = 0;
+        loop {
+            if i == g {
+                return Ok(best.1);
+            }
+            pick[i] += 1;
+            if pick[i] < k {
+                break;
+            }
+            pick[i] = 0;
+            i += 1;
+        }
+    }
+}
+
+fn search_greedy(costs: &[Vec<f32>], penalty: &[Vec<f32>]) -> Vec<usize> {
+    let k = costs[0].len();
+    let mut pick: Vec<Option<usize>> = vec![None; costs.len()];
+    for i in 0..costs.len() {
+        let cost = |c: usize| {
+            let reuse: f32 = pick
+                .iter()
+                .enumerate()
+                .filter(|&(_, p)| *p == Some(c))
+                .map(|(j, _)| penalty[i][j])
+                .sum();
+            costs[i][c] + reuse
+        };
+        pick[i] = (0..k).min_by(|&a, &b| cost(a).total_cmp(&cost(b)));
+    }
+    pick.into_iter().map(|p| p.unwrap_or(0)).collect()
+}
+
+/// One way to run a node's radio on a band: the primary channel, the width
+/// and the 20 MHz channels that covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelOption {
+    pub channel: u32,
+    pub width_mhz: u32,
+    pub block: Vec<u32>,
+}
+
+impl ChannelOption {
+    // Share of the wider of the two that both cover: 1.0 on the same
+    // block, 0.25 for a 20 MHz node inside an 80 MHz one, 0.0 apart.
+    fn overlap(&self, other: &ChannelOption) -> f32 {
+        let shared = self.block.iter().filter(|c| other.block.contains(c)).count();
+        shared as f32 / self.block.len().max(other.block.len()) as f32
+    }
+}
+
+/// A node's pick on one band.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeChannel {
+    pub band: u8,
+    pub channel: u32,
+    pub width_mhz: u32,
+    /// The option's cost at the node: foreign interference on its block
+    /// plus OWN_LOAD, per 20 MHz of width.
+    pub cost: f32,
+}
+
+/// What a channel costs a node with no neighbours at all, on the scale of
+/// ChannelConfig::ap_weight(): the airtime its own clients take. Dividing
+/// it by the width makes a clean wide channel beat a clean narrow one,
+/// while foreign APs on the extra 20 MHz channels can tip it back.
+pub const OWN_LOAD: f32 = REUSE_PENALTY;
+
+// Improvement rounds of the greedy search after placing every group.
+const MAX_ROUNDS: usize = 32;
+
+/// The options plan_mesh() chooses from on a freq_band(): 1/6/11 at 20 MHz
+/// on 2.4 GHz (there is no room for 40 MHz next to other nodes); every
+/// 20 MHz channel and the aligned 40, 80 and 160 MHz blocks on 5 GHz, the
+/// DFS ones only with `dfs`; on 6 GHz the 20 to 160 MHz blocks around
+/// each Preferred Scanning Channel, which is the primary. `limits` drops
+/// the ones its regulatory domain or radio rules out.
+pub fn channel_options(band: u8, dfs: bool, limits: &ChannelLimits) -> Vec<ChannelOption> {
+    let mut out = Vec::new();
+    match band {
+        1 => out.extend(CHANNELS_24.iter().map(|&ch| ChannelOption {
+            channel: ch,
+            width_mhz: 20,
+            block: vec![ch],
+        })),
+        2 => {
+            let mut channels: Vec<u32> = CHANNELS_5_20.to_vec();
+            if dfs {
+                channels.extend(CHANNELS_5_20_DFS);
+                channels.sort_unstable();
+            }
+            for width in [20, 40, 80, 160] {
+                for &ch in &channels {
+                    let Some(block) = width_block(2, ch, width) else { continue };
+                    // One option per block, by its lowest channel.
+                    if block[0] == ch && block.iter().all(|c| channels.contains(c)) {
+                        out.push(ChannelOption { channel: ch, width_mhz: width, block });
+                    }
+                }
+            }
+        }
+        4 => {
+            for width in [20, 40, 80, 160] {
+                for &ch in &CHANNELS_6_PSC {
+                    if let Some(block) = width_block(4, ch, width) {
+                        out.push(ChannelOption { channel: ch, width_mhz: width, block });
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    out.retain(|o| limits.allows_width(band, &o.block, o.width_mhz));
+    out
+}
+
+/// Cost of each of `options` at a node that heard `rows`: OWN_LOAD plus
+/// the interference weight (lib_rust::channel_breakdown) of the foreign
+/// APs on every 20 MHz channel of the block, per 20 MHz of width. On
+/// 2.4 GHz the overlap-aware interference_24() stands in for the weight.
+pub fn option_costs(rows: &[BssRow], own: &[[u8; 6]], band: u8, options: &[ChannelOption]) -> Vec<f32> {
+    let foreign: Vec<BssRow> = rows
+        .iter()
+        .filter(|r| r.bssid.is_none_or(|b| !own.iter().any(|o| *o == b || same_device(o, &b))))
+        .cloned()
+        .collect();
+    let weight: HashMap<u32, f32> = if band == 1 {
+        CHANNELS_24.iter().copied().zip(interference_24(&foreign, &[])).collect()
+    } else {
+        channel_breakdown(&foreign, Some(band), &HashMap::new(), &ChannelConfig::DEFAULT)
+            .into_iter()
+            .map(|s| (s.channel, s.weight))
+            .collect()
+    };
+    options
+        .iter()
+        .map(|o| {
+            let foreign: f32 = o.block.iter().map(|c| weight.get(c).copied().unwrap_or(0.0)).sum();
+            (OWN_LOAD + foreign) / o.block.len() as f32
+        })
+        .collect()
+}
+
+/// Channel and width for every node on each of `bands` (a freq_band() and
+/// its channel_options()), chosen jointly so the nodes stay off each
+/// other as well as off the neighbours: `result[node]` has one
+/// NodeChannel per band that has options.
+///
+/// Each node's options are scored with option_costs() on its own scan.
+/// Two nodes whose blocks overlap are charged as in assign_24ghz() (by
+/// `coupling`, node_rssi() output, or REUSE_PENALTY without it), times
+/// how much of the wider block they share. `follow[band]` ties nodes on
+/// wireless backhaul to their parent's channel as in assign_5ghz().
+///
+/// The search is exhaustive while the combinations stay under
+/// MAX_COMBINATIONS. Beyond that it colours the graph greedily, the
+/// nodes that interfere most with the others first, each on its cheapest
+/// option given those placed so far, then lets every node move to a
+/// better option until none does.
+pub fn plan_mesh(
+    per_node_rows: &[Vec<BssRow>],
+    own: &[[u8; 6]],
+    coupling: Option<&[Vec<Option<f32>>]>,
+    bands: &[(u8, Vec<ChannelOption>)],
+    follow: &HashMap<u8, Vec<Option<usize>>>,
+    cancel: &Cancel,
+) -> Result<Vec<Vec<NodeChannel>>> {
+    let n = per_node_rows.len();
+    let mut out: Vec<Vec<NodeChannel>> = vec![Vec::new(); n];
+    if n == 0 {
+        return Ok(out);
+    }
+    for (band, options) in bands {
+        let band = *band;
+        if options.is_empty() {
+            continue;
+        }
+        let per_node: Vec<Vec<f32>> = per_node_rows
+            .iter()
+            .map(|rows| option_costs(rows, own, band, options))
+            .collect();
+        let (member_of, g) = backhaul_groups(n, follow.get(&band).map_or(&[][..], Vec::as_slice));
+
+        let k = options.len();
+        let mut costs = vec![vec![0.0f32; k]; g];
+        for (i, w) in per_node.iter().enumerate() {
+            for (c, x) in costs[member_of[i]].iter_mut().zip(w) {
+                *c += x;
+            }
+        }
+        let mut penalty = vec![vec![0.0f32; g]; g];
+        for i in 0..n {
+            for j in i + 1..n {
+                let (a, b) = (member_of[i], member_of[j]);
+                if a != b {
+                    let p = pair_penalty(coupling, i, j);
+                    penalty[a][b] += p;
+                    penalty[b][a] += p;
+                }
+            }
+        }
+        let overlap: Vec<Vec<f32>> = options
+            .iter()
+            .map(|a| options.iter().map(|b| a.overlap(b)).collect())
+            .collect();
+
+        let pick = if (k as f64).powi(g as i32) <= MAX_COMBINATIONS as f64 {
+            joint_exhaustive(&costs, &penalty, &overlap, cancel)?
+        } else {
+            joint_greedy(&costs, &penalty, &overlap, cancel)?
+        };
+        for (i, node) in out.iter_mut().enumerate() {
+            let o = &options[pick[member_of[i]]];
+            node.push(NodeChannel {
+                band,
+                channel: o.channel,
+                width_mhz: o.width_mhz,
+                cost: per_node[i][pick[member_of[i]]],
+            });
+        }
+    }
+    Ok(out)
+}
+
+fn joint_cost(costs: &[Vec<f32>], penalty: &[Vec<f32>], overlap: &[Vec<f32>], pick: &[usize]) -> f32 {
+    let mut cost: f32 = pick.iter().zip(costs).map(|(&c, w)| w[c]).sum();
+    for a in 0..pick.len() {
+        for b in a + 1..pick.len() {
+            cost += penalty[a][b] * overlap[pick[a]][pick[b]];
+        }
+    }
+    cost
+}
+
+// search_exhaustive() with partly overlapping options.
+fn joint_exhaustive(
+    costs: &[Vec<f32>],
+    penalty: &[Vec<f32>],
+    overlap: &[Vec<f32>],
+    cancel: &Cancel,
+) -> Result<Vec<usize>> {
+    let (g, k) = (costs.len(), costs[0].len());
+    let mut pick = vec![0usize; g];
+    let mut best = (f32::INFINITY, pick.clone());
+    let mut step = 0usize;
+    loop {
+        if step.is_multiple_of(CANCEL_CHECK_EVERY) {
+            cancel.check()?;
+        }
+        step += 1;
+        let cost = joint_cost(costs, penalty, overlap, &pick);
+        if cost < best.0 {
+            best = (cost, pick.clone());
+        }
+
+        let mut i = 0;
+        loop {
+            if i == g {
+                return Ok(best.1);
+            }
+            pick[i] += 1;
+            if pick[i] < k {
+                break;
+            }
+            pick[i] = 0;
+            i += 1;
+        }
+    }
+}
+
+// Largest-degree-first colouring, then best-response rounds.
+fn joint_greedy(
+    costs: &[Vec<f32>],
+    penalty: &[Vec<f32>],
+    overlap: &[Vec<f32>],
+    cancel: &Cancel,
+) -> Result<Vec<usize>> {
+    let (g, k) = (costs.len(), costs[0].len());
+    let degree = |a: usize| penalty[a].iter().sum::<f32>();
+    let mut order: Vec<usize> = (0..g).collect();
+    order.sort_by(|&a, &b| degree(b).total_cmp(&degree(a)));
+
+    // Cost of group `a` on option `c` against the groups placed in `pick`.
+    let cost_of = |pick: &[Option<usize>], a: usize, c: usize| {
+        let shared: f32 = pick
+            .iter()
+            .enumerate()
+            .filter(|&(b, _)| b != a)
+            .filter_map(|(b, p)| p.map(|p| penalty[a][b] * overlap[c][p]))
+            .sum();
+        costs[a][c] + shared
+    };
+    let best_for = |pick: &[Option<usize>], a: usize| {
+        (0..k)
+            .min_by(|&x, &y| cost_of(pick, a, x).total_cmp(&cost_of(pick, a, y)))
+            .unwrap_or(0)
+    };
+
+    let mut pick: Vec<Option<usize>> = vec![None; g];
+    for &a in &order {
+        pick[a] = Some(best_for(&pick, a));
+    }
+    for _ in 0..MAX_ROUNDS {
+        cancel.check()?;
+        let mut moved = false;
+        for &a in &order {
+            let c = best_for(&pick, a);
+            let cur = pick[a].unwrap_or(0);
+            if cost_of(&pick, a, c) < cost_of(&pick, a, cur) {
+                pick[a] = Some(c);
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    Ok(pick.into_iter().map(|p| p.unwrap_or(0)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cancelled() -> Cancel {
+        let c = Cancel::new();
+        c.cancel();
+        c
+    }
+
+    #[test]
+    fn up_to_three_nodes_without_coupling_get_a_permutation() {
+        // Everyone would rather be on channel 1.
+        for n in 1..=3 {
+            let per_node = vec![[0.0, 10.0, 20.0]; n];
+            let mut picks = assign_24ghz(&per_node, None, &Cancel::none()).unwrap();
+            picks.sort();
+            picks.dedup();
+            assert_eq!(picks.len(), n);
+        }
+        // The permutation with the least total interference.
+        let per_node = [[0.0, 10.0, 20.0], [0.0, 100.0, 100.0], [100.0, 100.0, 0.0]];
+        assert_eq!(assign_24ghz(&per_node, None, &Cancel::none()).unwrap(), [6, 1, 11]);
+
+        // Nodes that don't hear each other may all share.
+        let coupling = vec![vec![None; 3]; 3];
+        let picks = assign_24ghz(&[[0.0, 10.0, 20.0]; 3], Some(&coupling), &Cancel::none()).unwrap();
+        assert_eq!(picks, [1, 1, 1]);
+    }
+
+    #[test]
+    fn more_nodes_than_channels_share_as_little_as_possible() {
+        let picks = assign_24ghz(&[[0.0, 10.0, 20.0]; 4], None, &Cancel::none()).unwrap();
+        for ch in CHANNELS_24 {
+            assert!(picks.contains(&ch), "{picks:?}");
+        }
+    }
+
+    #[test]
+    fn exhaustive_search_stops_at_max_exhaustive_nodes() {
+        // Only the exhaustive search looks at the token.
+        let per_node = vec![[0.0, 10.0, 20.0]; MAX_EXHAUSTIVE];
+        assert!(assign_24ghz(&per_node, None, &cancelled()).is_err());
+
+        let per_node = vec![[0.0, 10.0, 20.0]; MAX_EXHAUSTIVE + 1];
+        let picks = assign_24ghz(&per_node, None, &cancelled()).unwrap();
+        let greedy: Vec<u32> = greedy(&per_node, None).into_iter().map(|i| CHANNELS_24[i]).collect();
+        assert_eq!(picks, greedy);
+    }
+
+    #[test]
+    fn greedy_places_the_pickiest_node_first() {
+        // Node 1 cares most about its channel, so it gets 1 and node 0
+        // moves over instead of sharing.
+        let per_node = [[0.0, 1.0, 2.0], [0.0, 100.0, 100.0]];
+        assert_eq!(greedy(&per_node, None), [1, 0]);
+    }
+
+    #[test]
+    fn backhaul_followers_share_their_parents_channel() {
+        let channels = CHANNELS_5_NON_DFS;
+        // Node 1 hangs off node 0 over the air and would rather be on 149;
+        // node 2 is wired and goes there.
+        let per_node = vec![vec![0.0, 100.0], vec![60.0, 0.0], vec![80.0, 0.0]];
+        let follow = [None, Some(0), None];
+        let picks = assign_5ghz(&per_node, &channels, None, &follow, &Cancel::none()).unwrap();
+        assert_eq!(picks, [36, 36, 149]);
+
+        // A chain follows all the way up.
+        let follow = [Some(2), Some(0), None];
+        let picks = assign_5ghz(&per_node, &channels, None, &follow, &Cancel::none()).unwrap();
+        assert!(picks.iter().all(|&ch| ch == picks[0]), "{picks:?}");
+    }
+}
@@ -0,0 +1,169 @@
+// src/pool.rs
+//
+// The one thread pool for CPU-bound batch work (history scoring and
+// anything else that fans out with rayon). Nothing in the crate uses
+// rayon's global pool for this, so the size, CPU pinning and priority set
+// here bound all of it, which matters for battery and thermals on a phone.
+//
+// It doesn't bound the rest: netlink I/O and the *_async() calls run on
+// the runtime in netlink.rs, and the long-lived loops that mostly sleep
+// or wait on a socket have a thread each (gpsd.rs, probe.rs, linkmon.rs,
+// events.rs, history_db.rs's writer and lib.rs's log drain).
+
+use anyhow::{bail, Context, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, Mutex};
+
+/// How the batch pool is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub threads: usize,
+    /// Pin worker i to the i-th CPU (mod their count) of those the
+    /// process may run on when the pool is built.
+    pub pin: bool,
+    /// Nice value for the workers, -20..=19. Raising priority (negative)
+    /// needs CAP_SYS_NICE; lowering it is always allowed.
+    pub nice: i32,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        PoolConfig {
+            // Half the cores, at most four: batch work shouldn't light up
+            // every big core on a phone.
+            threads: (cpus / 2).clamp(1, 4),
+            pin: false,
+            nice: 10,
+        }
+    }
+}
+
+struct Pool {
+    config: PoolConfig,
+    pool: Arc<ThreadPool>,
+}
+
+static POOL: Mutex<Option<Pool>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Pool>> {
+    POOL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn build(config: PoolConfig) -> Result<ThreadPool> {
+    if config.threads == 0 {
+        bail!("thread count must be > 0");
+    }
+    if !(-20..=19).contains(&config.nice) {
+        bail!("nice must be in -20..=19, got {}", config.nice);
+    }
+    let cpus = if config.pin { allowed_cpus()? } else { Vec::new() };
+
+    ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .thread_name(|i| format!("wifi-batch-{i}"))
+        .start_handler(move |i| {
+            // Best effort: a worker that couldn't be pinned or reniced
+            // still does its job.
+            if config.pin {
+                let _ = pin_current_thread(cpus[i % cpus.len()]);
+            }
+            let _ = set_current_thread_nice(config.nice);
+        })
+        .build()
+        .context("failed to start batch thread pool")
+}
+
+// The CPUs in this thread's affinity mask (inherited from whoever
+// started the process: taskset, a cgroup cpuset, Android's cpusets), in
+// order. Never empty.
+fn allowed_cpus() -> Result<Vec<usize>> {
+    // SAFETY: cpu_set_t is plain data, sized as passed; CPU_ISSET only
+    // reads indices below CPU_SETSIZE.
+    let cpus: Vec<usize> = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(std::io::Error::last_os_error()).context("sched_getaffinity");
+        }
+        (0..libc::CPU_SETSIZE as usize).filter(|&cpu| libc::CPU_ISSET(cpu, &set)).collect()
+    };
+    if cpus.is_empty() {
+        bail!("empty CPU affinity mask");
+    }
+    Ok(cpus)
+}
+
+fn pin_current_thread(cpu: usize) -> std::io::Result<()> {
+    // SAFETY: cpu_set_t is plain data; `cpu` comes from allowed_cpus(),
+    // so CPU_SET stays below CPU_SETSIZE.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn set_current_thread_nice(nice: i32) -> std::io::Result<()> {
+    // On Linux, PRIO_PROCESS with a thread id renices just that thread.
+    // SAFETY: plain syscalls on the calling thread.
+    unsafe {
+        let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        if libc::setpriority(libc::PRIO_PROCESS, tid, nice) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Replace the pool. Work already running finishes on the old one.
+pub fn configure(config: PoolConfig) -> Result<()> {
+    let pool = Arc::new(build(config)?);
+    *lock() = Some(Pool { config, pool });
+    Ok(())
+}
+
+/// Current configuration (the default until configure() is called).
+pub fn config() -> PoolConfig {
+    lock().as_ref().map_or_else(PoolConfig::default, |p| p.config)
+}
+
+fn pool() -> Arc<ThreadPool> {
+    let mut guard = lock();
+    if let Some(p) = guard.as_ref() {
+        return p.pool.clone();
+    }
+    let config = PoolConfig::default();
+    let pool = Arc::new(build(config).expect("failed to start batch thread pool"));
+    *guard = Some(Pool {
+        config,
+        pool: pool.clone(),
+    });
+    pool
+}
+
+/// Run `f` inside the batch pool, so any rayon work it starts is bounded
+/// by the configured threads.
+pub fn install<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    pool().install(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_workers_stay_inside_the_affinity_mask() {
+        let allowed = allowed_cpus().unwrap();
+        let config = PoolConfig {
+            threads: allowed.len() + 1,
+            pin: true,
+            nice: 19,
+        };
+        let pool = build(config).unwrap();
+        let on: Vec<usize> = pool.broadcast(|_| allowed_cpus().unwrap()).into_iter().flatten().collect();
+        assert!(on.iter().all(|cpu| allowed.contains(cpu)), "{on:?} outside {allowed:?}");
+    }
+}
@@ -0,0 +1,486 @@
+// src/probe.rs
+//
+// Latency prober: a background thread pings the default gateway (or a
+// given IPv4 host) about once a second and keeps RTT / loss samples next
+// to the scan history, so "my calls stutter" can be checked against what
+// the channel looked like at the time.
+//
+// Probes are ICMP echo over an unprivileged ping socket (SOCK_DGRAM,
+// allowed by net.ipv4.ping_group_range), else a raw ICMP socket
+// (CAP_NET_RAW), else a DNS query to UDP port 53, where a port-unreachable
+// answer counts as a reply too. Samples go to an in-memory log and, when
+// one is open, the history database's latency table.
+//
+// Exposes:
+//   - start(Config) -> Result<()> / stop() / status() -> ProbeStatus
+//   - samples() / clear() / default_gateway()
+//   - stats(samples) -> LatencyStats
+//   - correlate(scans, samples) -> Correlation: latency per scan interval
+//     against interference on the connected channel
+
+use anyhow::{bail, Context, Result};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, UdpSocket};
+use std::os::fd::FromRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::history::StoredScan;
+use crate::history_db;
+use crate::lib_rust::{channel_weights, freq_band, ChannelConfig};
+use crate::stamp::Stamp;
+
+// A day at one probe a second.
+const MAX_SAMPLES: usize = 86_400;
+const ICMP_ECHO: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Icmp,
+    IcmpRaw,
+    Udp,
+}
+
+impl Method {
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Icmp => "icmp",
+            Method::IcmpRaw => "icmp_raw",
+            Method::Udp => "udp",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// None follows the default gateway, re-read before every probe.
+    pub target: Option<Ipv4Addr>,
+    pub interval: Duration,
+    /// A reply later than this counts as lost.
+    pub timeout: Duration,
+}
+
+/// One probe: RTT in ms, None if lost.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub at: Stamp,
+    pub target: Ipv4Addr,
+    pub rtt_ms: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProbeStatus {
+    pub running: bool,
+    pub target: Option<Ipv4Addr>,
+    pub method: Option<Method>,
+    pub sent: u64,
+    pub lost: u64,
+    pub last_error: Option<String>,
+}
+
+struct Prober {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+    status: Arc<Mutex<ProbeStatus>>,
+}
+
+static PROBER: Mutex<Option<Prober>> = Mutex::new(None);
+static SAMPLES: Mutex<VecDeque<Sample>> = Mutex::new(VecDeque::new());
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Prober>> {
+    PROBER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn lock_samples() -> std::sync::MutexGuard<'static, VecDeque<Sample>> {
+    SAMPLES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn lock_status(s: &Mutex<ProbeStatus>) -> std::sync::MutexGuard<'_, ProbeStatus> {
+    s.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The IPv4 default route's gateway, from /proc/net/route.
+pub fn default_gateway() -> Result<Ipv4Addr> {
+    let table = std::fs::read_to_string("/proc/net/route").context("read /proc/net/route")?;
+    // Iface Destination Gateway Flags ..., addresses in hex, host order.
+    for line in table.lines().skip(1) {
+        let f: Vec<&str> = line.split_whitespace().collect();
+        if f.len() < 4 || f[1] != "00000000" {
+            continue;
+        }
+        let flags = u32::from_str_radix(f[3], 16).unwrap_or(0);
+        // RTF_UP | RTF_GATEWAY
+        if flags & 0x3 != 0x3 {
+            continue;
+        }
+        if let Ok(gw) = u32::from_str_radix(f[2], 16) {
+            return Ok(Ipv4Addr::from(u32::from_be(gw)));
+        }
+    }
+    bail!("no IPv4 default route")
+}
+
+/// Start probing, replacing a prober started before.
+pub fn start(cfg: Config) -> Result<()> {
+    if cfg.interval.is_zero() || cfg.timeout.is_zero() {
+        bail!("interval and timeout must be positive");
+    }
+    if cfg.target.is_none() {
+        default_gateway()?;
+    }
+    stop();
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let status = Arc::new(Mutex::new(ProbeStatus {
+        running: true,
+        ..Default::default()
+    }));
+    let thread = {
+        let (stop_flag, status) = (stop_flag.clone(), status.clone());
+        std::thread::Builder::new()
+            .name("wifi-probe".into())
+            .spawn(move || run(cfg, &stop_flag, &status))?
+    };
+    *lock() = Some(Prober {
+        stop: stop_flag,
+        thread,
+        status,
+    });
+    Ok(())
+}
+
+pub fn stop() {
+    let Some(p) = lock().take() else {
+        return;
+    };
+    p.stop.store(true, Ordering::Relaxed);
+    let _ = p.thread.join();
+}
+
+pub fn status() -> ProbeStatus {
+    match lock().as_ref() {
+        Some(p) => lock_status(&p.status).clone(),
+        None => ProbeStatus::default(),
+    }
+}
+
+/// Every sample kept, oldest first.
+pub fn samples() -> Vec<Sample> {
+    lock_samples().iter().copied().collect()
+}
+
+pub fn clear() {
+    lock_samples().clear();
+}
+
+fn record(s: Sample) {
+    let mut log = lock_samples();
+    if log.len() >= MAX_SAMPLES {
+        log.pop_front();
+    }
+    log.push_back(s);
+    drop(log);
+    history_db::submit_latency(&s);
+}
+
+// An open probe socket to one target.
+struct Pinger {
+    sock: UdpSocket,
+    method: Method,
+    target: Ipv4Addr,
+    ident: u16,
+}
+
+fn icmp_socket(kind: libc::c_int) -> io::Result<UdpSocket> {
+    // SAFETY: plain socket(2) call; the fd is owned by the UdpSocket.
+    let fd = unsafe { libc::socket(libc::AF_INET, kind | libc::SOCK_CLOEXEC, libc::IPPROTO_ICMP) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Connected ICMP sockets take send/recv like a UDP one.
+    Ok(unsafe { UdpSocket::from_raw_fd(fd) })
+}
+
+impl Pinger {
+    fn open(target: Ipv4Addr) -> Result<Pinger> {
+        let ident = std::process::id() as u16;
+        let tries = [
+            (Method::Icmp, icmp_socket(libc::SOCK_DGRAM)),
+            (Method::IcmpRaw, icmp_socket(libc::SOCK_RAW)),
+            (Method::Udp, UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))),
+        ];
+        let mut last = None;
+        for (method, sock) in tries {
+            let port = if method == Method::Udp { 53 } else { 0 };
+            match sock.and_then(|s| s.connect((target, port)).map(|_| s)) {
+                Ok(sock) => {
+                    return Ok(Pinger {
+                        sock,
+                        method,
+                        target,
+                        ident,
+                    })
+                }
+                Err(e) => last = Some(e),
+            }
+        }
+        Err(last.map_or_else(|| anyhow::anyhow!("no probe socket"), anyhow::Error::from))
+            .with_context(|| format!("open probe socket to {target}"))
+    }
+
+    fn request(&self, seq: u16) -> Vec<u8> {
+        match self.method {
+            Method::Icmp | Method::IcmpRaw => {
+                let mut p = vec![ICMP_ECHO, 0, 0, 0];
+                p.extend_from_slice(&self.ident.to_be_bytes());
+                p.extend_from_slice(&seq.to_be_bytes());
+                p.extend_from_slice(b"wifimesh");
+                let sum = checksum(&p);
+                p[2..4].copy_from_slice(&sum.to_be_bytes());
+                p
+            }
+            // DNS query for the root's NS records, id = seq.
+            Method::Udp => {
+                let mut p = seq.to_be_bytes().to_vec();
+                p.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 1]);
+                p
+            }
+        }
+    }
+
+    fn is_reply(&self, buf: &[u8], seq: u16) -> bool {
+        let icmp = match self.method {
+            Method::Udp => return buf.len() >= 2 && buf[..2] == seq.to_be_bytes(),
+            // Ping sockets strip the IP header and rewrite the identifier.
+            Method::Icmp => buf,
+            Method::IcmpRaw => {
+                let ihl = buf.first().map_or(0, |b| (b & 0x0f) as usize * 4);
+                match buf.get(ihl..) {
+                    Some(rest) if rest.len() >= 8 && rest[4..6] == self.ident.to_be_bytes() => rest,
+                    _ => return false,
+                }
+            }
+        };
+        icmp.len() >= 8 && icmp[0] == ICMP_ECHO_REPLY && icmp[6..8] == seq.to_be_bytes()
+    }
+
+    /// RTT of one probe, None if lost; Err when the socket itself fails.
+    fn probe(&self, seq: u16, timeout: Duration) -> io::Result<Option<f32>> {
+        let sent = Instant::now();
+        self.sock.send(&self.request(seq))?;
+        let mut buf = [0u8; 1500];
+        loop {
+            let left = timeout.saturating_sub(sent.elapsed());
+            if left.is_zero() {
+                return Ok(None);
+            }
+            self.sock.set_read_timeout(Some(left))?;
+            match self.sock.recv(&mut buf) {
+                Ok(n) if self.is_reply(&buf[..n], seq) => {}
+                Ok(_) => continue,
+                // Port unreachable from the target: it answered.
+                Err(e) if self.method == Method::Udp && e.kind() == ErrorKind::ConnectionRefused => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            return Ok(Some(sent.elapsed().as_secs_f32() * 1000.0));
+        }
+    }
+}
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|c| u32::from(u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn run(cfg: Config, stop: &AtomicBool, status: &Mutex<ProbeStatus>) {
+    let mut pinger: Option<Pinger> = None;
+    let mut seq: u16 = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let target = match cfg.target.map_or_else(default_gateway, Ok) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                lock_status(status).last_error = Some(format!("{e:#}"));
+                None
+            }
+        };
+        if let Some(target) = target {
+            // New gateway (roamed to another network) or no socket yet.
+            if pinger.as_ref().is_none_or(|p| p.target != target) {
+                pinger = match Pinger::open(target) {
+                    Ok(p) => {
+                        let mut s = lock_status(status);
+                        s.target = Some(target);
+                        s.method = Some(p.method);
+                        Some(p)
+                    }
+                    Err(e) => {
+                        lock_status(status).last_error = Some(format!("{e:#}"));
+                        None
+                    }
+                };
+            }
+        }
+        if let Some(p) = &pinger {
+            seq = seq.wrapping_add(1);
+            let at = Stamp::now();
+            match p.probe(seq, cfg.timeout) {
+                Ok(rtt_ms) => {
+                    record(Sample {
+                        at,
+                        target: p.target,
+                        rtt_ms,
+                    });
+                    let mut s = lock_status(status);
+                    s.sent += 1;
+                    s.lost += u64::from(rtt_ms.is_none());
+                }
+                Err(e) => {
+                    lock_status(status).last_error = Some(format!("probe {}: {e}", p.target));
+                    pinger = None;
+                }
+            }
+        }
+        // Sleep out the interval, checking for stop now and then.
+        while started.elapsed() < cfg.interval && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(cfg.interval.saturating_sub(started.elapsed()).min(Duration::from_millis(200)));
+        }
+    }
+    lock_status(status).running = false;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub lost: usize,
+    pub loss: f32,
+    pub mean_ms: Option<f32>,
+    pub max_ms: Option<f32>,
+    /// Mean absolute difference of consecutive RTTs (RFC 3550 style).
+    pub jitter_ms: Option<f32>,
+}
+
+pub fn stats(samples: &[Sample]) -> LatencyStats {
+    let rtts: Vec<f32> = samples.iter().filter_map(|s| s.rtt_ms).collect();
+    let lost = samples.len() - rtts.len();
+    let mean = |v: &[f32]| (!v.is_empty()).then(|| v.iter().sum::<f32>() / v.len() as f32);
+    let diffs: Vec<f32> = rtts.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+    LatencyStats {
+        samples: samples.len(),
+        lost,
+        loss: if samples.is_empty() { 0.0 } else { lost as f32 / samples.len() as f32 },
+        mean_ms: mean(&rtts),
+        max_ms: rtts.iter().copied().reduce(f32::max),
+        jitter_ms: mean(&diffs),
+    }
+}
+
+/// Latency while one scan's picture of the air held: the probes between
+/// the previous scan and this one.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    /// Unix time of the scan closing the interval.
+    pub at: f64,
+    pub channel: Option<u32>,
+    /// Interference weight on the connected channel (channel_weights).
+    pub weight: Option<f32>,
+    /// Other BSSs on the connected channel.
+    pub aps: usize,
+    pub latency: LatencyStats,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Correlation {
+    pub intervals: Vec<Interval>,
+    /// Pearson correlation of the interference weight with each interval's
+    /// mean RTT, jitter and loss; None with fewer than 3 usable intervals
+    /// or no variation.
+    pub weight_vs_rtt: Option<f64>,
+    pub weight_vs_jitter: Option<f64>,
+    pub weight_vs_loss: Option<f64>,
+}
+
+fn wall(scan: &StoredScan) -> f64 {
+    scan.at.duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 3 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let (mx, my) = pairs.iter().fold((0.0, 0.0), |(a, b), (x, y)| (a + x / n, b + y / n));
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        sxy += (x - mx) * (y - my);
+        sxx += (x - mx) * (x - mx);
+        syy += (y - my) * (y - my);
+    }
+    (sxx > 0.0 && syy > 0.0).then(|| sxy / (sxx * syy).sqrt())
+}
+
+/// Pair each scan (oldest first) with the probes taken since the scan
+/// before it. Intervals without probes are left out.
+pub fn correlate(scans: &[StoredScan], samples: &[Sample]) -> Correlation {
+    let mut out = Correlation::default();
+    let mut prev: Option<f64> = None;
+    for scan in scans {
+        let at = wall(scan);
+        let from = prev.unwrap_or(f64::NEG_INFINITY);
+        prev = Some(at);
+        let inside: Vec<Sample> = samples
+            .iter()
+            .filter(|s| s.at.wall > from && s.at.wall <= at)
+            .copied()
+            .collect();
+        if inside.is_empty() {
+            continue;
+        }
+
+        let mine = scan
+            .connected
+            .and_then(|c| scan.rows.iter().find(|r| r.bssid == Some(c)));
+        let key = mine.and_then(|r| Some((freq_band(r.freq_mhz?), r.channel?)));
+        let (weight, aps) = match key {
+            Some(k) => {
+                let w = channel_weights(&scan.rows, scan.connected.as_ref(), &ChannelConfig::DEFAULT);
+                let aps = scan
+                    .rows
+                    .iter()
+                    .filter(|r| r.bssid != scan.connected && r.freq_mhz.map(freq_band) == Some(k.0) && r.channel == Some(k.1))
+                    .count();
+                (Some(w.get(&k).copied().unwrap_or(0.0)), aps)
+            }
+            None => (None, 0),
+        };
+        out.intervals.push(Interval {
+            at,
+            channel: key.map(|k| k.1),
+            weight,
+            aps,
+            latency: stats(&inside),
+        });
+    }
+
+    let pairs = |f: &dyn Fn(&LatencyStats) -> Option<f32>| -> Vec<(f64, f64)> {
+        out.intervals
+            .iter()
+            .filter_map(|i| Some((i.weight? as f64, f(&i.latency)? as f64)))
+            .collect()
+    };
+    out.weight_vs_rtt = pearson(&pairs(&|l| l.mean_ms));
+    out.weight_vs_jitter = pearson(&pairs(&|l| l.jitter_ms));
+    out.weight_vs_loss = pearson(&pairs(&|l| Some(l.loss)));
+    out
+}
@@ -0,0 +1,53 @@
+// src/progress.rs
+//
+// Progress reporting for the calls that can run for a long time (scan_n,
+// score_history, airtime_report, survey samples). Each takes a
+// `&mut Progress` and reports (percent, stage, message) at its safe
+// points. An error from the sink stops the operation right there and is
+// returned by it. A Progress also carries the operation's Cancel token,
+// so every report is a cancellation point too.
+
+use anyhow::Result;
+
+use crate::cancel::Cancel;
+
+type Sink<'a> = Box<dyn FnMut(f32, &str, &str) -> Result<()> + Send + 'a>;
+
+#[derive(Default)]
+pub struct Progress<'a> {
+    sink: Option<Sink<'a>>,
+    cancel: Cancel,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(sink: impl FnMut(f32, &str, &str) -> Result<()> + Send + 'a) -> Self {
+        Progress {
+            sink: Some(Box::new(sink)),
+            cancel: Cancel::none(),
+        }
+    }
+
+    /// Reports go nowhere.
+    pub fn none() -> Self {
+        Progress::default()
+    }
+
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn cancel(&self) -> &Cancel {
+        &self.cancel
+    }
+
+    /// `percent` of the whole operation (0 to 100) is done; `stage` is a
+    /// short machine-readable name, `message` for humans.
+    pub fn report(&mut self, percent: f32, stage: &str, message: &str) -> Result<()> {
+        self.cancel.check()?;
+        match &mut self.sink {
+            Some(sink) => sink(percent.clamp(0.0, 100.0), stage, message),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// src/provider.rs
+//
+// The data sources behind Backend, as one trait. Scanning, the link,
+// station dumps, surveys, the regulatory domain, radio capabilities and
+// events all go through Backend::provider() instead of matching on the
+// backend, so the scoring code above them runs the same on real radios,
+// wpa_supplicant, pushed Android results and canned rows (mock.rs).
+//
+// Only scan and connected_bssid are required; everything else defaults to
+// nl80211, which is what the wpa_supplicant and Android providers fall
+// back to for what they can't answer.
+//
+// Implementations: nl_wifi::NeliWifiBackend, nl_raw::RawNl80211Backend,
+// mock::MockBackend, wpa_ctrl::WpaCtrlBackend, android::AndroidBackend.
+// The first two are cargo features (backend-neli-wifi,
+// backend-raw-nl80211, both on by default); a build without one answers
+// its scans and connected BSSID with an error naming the feature.
+//
+// Exposes:
+//   - WifiBackend, ScanFuture
+//   - Backend::provider() -> &'static dyn WifiBackend, Backend::built()
+
+use anyhow::Result;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::chansurvey::ChannelSurvey;
+#[cfg(not(all(feature = "backend-neli-wifi", feature = "backend-raw-nl80211")))]
+use crate::error::WifiError;
+use crate::events::Subscription;
+use crate::lib_rust::{Backend, BssRow, NoTrigger, RowSink, ScanOptions};
+use crate::link::LinkInfo;
+use crate::netlink::{self, block_on, Nl80211, WifiIface};
+use crate::phycaps::PhyCaps;
+use crate::regdom::RegDomain;
+use crate::stations::{ApRadio, Station};
+use crate::{android, mock, nl_raw, wpa_ctrl};
+#[cfg(feature = "backend-neli-wifi")]
+use crate::nl_wifi;
+
+/// What WifiBackend::scan() resolves to: the rows, and why no scan was
+/// triggered if none was.
+pub type ScanFuture<'a> = Pin<Box<dyn Future<Output = Result<(Vec<BssRow>, Option<NoTrigger>)>> + Send + 'a>>;
+
+/// One source of Wi-Fi data. `ifindex` None means the first station
+/// interface; providers with a single interface ignore it.
+pub trait WifiBackend: Send + Sync {
+    /// Scan as `opts` asks (as far as the provider can), feeding every row
+    /// to `on_row` as it arrives. Filtering by `opts` is up to the caller.
+    fn scan<'a>(&'a self, ifindex: Option<u32>, opts: &'a ScanOptions, on_row: RowSink) -> ScanFuture<'a>;
+
+    /// The BSSID the station interface is associated with.
+    fn connected_bssid(&self, ifindex: Option<u32>) -> Result<Option<[u8; 6]>>;
+
+    /// Whether scan() marks the connected BSS (BssRow::status), so a full
+    /// scan also answers connected_bssid().
+    fn reports_bss_status(&self) -> bool {
+        false
+    }
+
+    /// The link to the associated AP; None when not associated.
+    fn link(&self, ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+        block_on(nl_raw::link_async(ifindex))
+    }
+
+    fn interfaces(&self) -> Result<Vec<WifiIface>> {
+        block_on(async { netlink::list_interfaces(&Nl80211::shared()?).await })
+    }
+
+    /// Radios and associated clients of this machine's AP interfaces.
+    fn station_info(&self) -> Result<(Vec<ApRadio>, Vec<Station>)> {
+        block_on(nl_raw::ap_stations_async())
+    }
+
+    /// The channel survey of `ifindex`'s radio, busy fractions not yet
+    /// filled in.
+    fn survey(&self, ifindex: Option<u32>) -> Result<Vec<ChannelSurvey>> {
+        block_on(nl_raw::survey_async(ifindex))
+    }
+
+    fn regdomain(&self) -> Result<RegDomain> {
+        block_on(nl_raw::regdomain_async())
+    }
+
+    fn phy_caps(&self, ifindex: Option<u32>) -> Result<PhyCaps> {
+        block_on(nl_raw::phy_caps_async(ifindex))
+    }
+
+    /// Scan / connect / disconnect / roam events from now on.
+    fn events(&self) -> Result<Subscription> {
+        Subscription::netlink()
+    }
+}
+
+// Stands in for a provider this build was compiled without; names its
+// cargo feature.
+#[cfg(not(all(feature = "backend-neli-wifi", feature = "backend-raw-nl80211")))]
+struct NotBuilt(&'static str);
+
+#[cfg(not(all(feature = "backend-neli-wifi", feature = "backend-raw-nl80211")))]
+impl WifiBackend for NotBuilt {
+    fn scan<'a>(&'a self, _ifindex: Option<u32>, _opts: &'a ScanOptions, _on_row: RowSink) -> ScanFuture<'a> {
+        Box::pin(async move { Err(not_built(self.0)) })
+    }
+
+    fn connected_bssid(&self, _ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+        Err(not_built(self.0))
+    }
+}
+
+#[cfg(not(all(feature = "backend-neli-wifi", feature = "backend-raw-nl80211")))]
+fn not_built(feature: &str) -> anyhow::Error {
+    WifiError::Unsupported(format!("wifi_backend was built without the {feature} feature")).into()
+}
+
+#[cfg(feature = "backend-neli-wifi")]
+static NELI_WIFI: nl_wifi::NeliWifiBackend = nl_wifi::NeliWifiBackend;
+#[cfg(not(feature = "backend-neli-wifi"))]
+static NELI_WIFI: NotBuilt = NotBuilt("backend-neli-wifi");
+#[cfg(feature = "backend-raw-nl80211")]
+static RAW_NL80211: nl_raw::RawNl80211Backend = nl_raw::RawNl80211Backend;
+#[cfg(not(feature = "backend-raw-nl80211"))]
+static RAW_NL80211: NotBuilt = NotBuilt("backend-raw-nl80211");
+static MOCK: mock::MockBackend = mock::MockBackend;
+static WPA_SUPPLICANT: wpa_ctrl::WpaCtrlBackend = wpa_ctrl::WpaCtrlBackend;
+static ANDROID: android::AndroidBackend = android::AndroidBackend;
+
+impl Backend {
+    /// The provider that answers for this backend.
+    pub fn provider(self) -> &'static dyn WifiBackend {
+        match self {
+            Backend::NeliWifi => &NELI_WIFI,
+            Backend::RawNl80211 => &RAW_NL80211,
+            Backend::Mock => &MOCK,
+            Backend::WpaSupplicant => &WPA_SUPPLICANT,
+            Backend::Android => &ANDROID,
+        }
+    }
+
+    /// Whether this build has the backend (see the cargo features).
+    // Without the features both arms are `false`, which clippy reads as
+    // a matches!().
+    #[allow(clippy::match_like_matches_macro)]
+    pub fn built(self) -> bool {
+        match self {
+            Backend::NeliWifi => cfg!(feature = "backend-neli-wifi"),
+            Backend::RawNl80211 => cfg!(feature = "backend-raw-nl80211"),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::lib_rust::{best_channel_from_rows, ChannelConfig, ChannelLimits};
+    use crate::mock::{MockBackend, TEST_LOCK};
+
+    const OWN: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+    fn ap(bssid: [u8; 6], freq_mhz: u32, dbm: f32) -> BssRow {
+        let mut r = BssRow::from_parts(Some(bssid), Some(freq_mhz), Some(dbm), None);
+        r.set_ssid(b"test");
+        r
+    }
+
+    // The scoring itself is tested in lib_rust; this checks that rows
+    // and the connected BSSID come back from MockBackend in a shape it
+    // scores the same.
+    #[test]
+    fn mock_scan_scores_like_the_rows_it_was_given() {
+        let _serial = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let rows = vec![ap(OWN, 2437, -40.0), ap([0x02, 1, 1, 1, 1, 0x01], 2412, -60.0)];
+        mock::load_rows(vec![rows.clone()], Some(OWN));
+        let (scanned, _) = block_on(MockBackend.scan(None, &ScanOptions::default(), Box::new(|_| {}))).unwrap();
+        let connected = MockBackend.connected_bssid(None).unwrap();
+        assert_eq!(connected, Some(OWN));
+
+        let cfg = ChannelConfig::DEFAULT;
+        let best = |rows: &[BssRow]| {
+            best_channel_from_rows(rows, connected.as_ref(), &HashMap::new(), &ChannelLimits::NONE, &cfg).unwrap()
+        };
+        assert_eq!(best(&scanned), best(&rows));
+        assert_eq!(best(&scanned), (1, 6));
+    }
+}
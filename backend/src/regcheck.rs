@@ -0,0 +1,115 @@
+// src/regcheck.rs
+//
+// The countries neighbouring APs claim in their Country elements, against
+// the regulatory domain we enforce (regdom.rs). An AP set to a more
+// permissive country can sit on channels that aren't legal here (12-13 in
+// the US, say), and makes the spectrum look busier or freer than it
+// really is where a legal AP could go.
+//
+// Per claimed country: the BSSIDs claiming it, whether it is ours, and
+// the channels its elements list that our domain doesn't allow. Per BSS:
+// the ones heard on a channel our domain doesn't allow at all.
+//
+// Exposes:
+//   - check(rows, local) -> RegReport
+//   - RegReport, CountryClaim
+
+use std::collections::BTreeMap;
+
+use crate::ies::CountryInfo;
+use crate::lib_rust::{freq_band, BssRow};
+use crate::regdom::RegDomain;
+
+#[derive(Debug, Clone)]
+pub struct CountryClaim {
+    pub country: [u8; 2],
+    /// Strongest first.
+    pub bssids: Vec<[u8; 6]>,
+    /// Our domain's country; None when it is the world ("00") or a
+    /// driver-built ("99") domain, or unknown.
+    pub matches_local: Option<bool>,
+    /// (band, channel) its Country elements list that our domain doesn't
+    /// allow.
+    pub extra_channels: Vec<(u8, u32)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RegReport {
+    /// Our domain's country, as regdom reports it.
+    pub local: Option<String>,
+    /// Most BSSIDs first.
+    pub claims: Vec<CountryClaim>,
+    /// BSSs heard on a channel our domain doesn't allow, strongest first.
+    pub off_domain: Vec<BssRow>,
+}
+
+impl RegReport {
+    /// Claims of another country than ours.
+    pub fn mismatches(&self) -> impl Iterator<Item = &CountryClaim> {
+        self.claims.iter().filter(|c| c.matches_local == Some(false))
+    }
+}
+
+/// Compare the Country elements and channels in `rows` with `local`.
+/// Without a domain only the claims are listed.
+pub fn check(rows: &[BssRow], local: Option<&RegDomain>) -> RegReport {
+    let mut rows: Vec<&BssRow> = rows.iter().filter(|r| r.bssid.is_some()).collect();
+    rows.sort_by(|a, b| signal(b).total_cmp(&signal(a)).then(a.bssid.cmp(&b.bssid)));
+    // A real country code, not "00" / "99".
+    let local_cc = local
+        .map(|d| d.country.as_bytes())
+        .filter(|c| c.len() == 2 && c.iter().all(u8::is_ascii_alphabetic));
+
+    let mut claims: BTreeMap<[u8; 2], CountryClaim> = BTreeMap::new();
+    let mut off_domain: Vec<BssRow> = Vec::new();
+    for r in rows {
+        let Some(bssid) = r.bssid else { continue };
+        if let (Some(reg), Some(freq), Some(ch)) = (local, r.freq_mhz, r.channel) {
+            let band = freq_band(freq);
+            let listed = off_domain.iter().any(|o| o.bssid == r.bssid);
+            if matches!(band, 1 | 2 | 4) && !reg.channel(band, ch).allowed && !listed {
+                off_domain.push(r.clone());
+            }
+        }
+        // Rows without IEs still have the code if it was parsed before.
+        let info = r.country_info().or_else(|| {
+            r.country().map(|code| CountryInfo {
+                code,
+                environment: b' ',
+                channels: Vec::new(),
+            })
+        });
+        let Some(info) = info else { continue };
+        let claim = claims.entry(info.code).or_insert_with(|| CountryClaim {
+            country: info.code,
+            bssids: Vec::new(),
+            matches_local: local_cc.map(|c| c.eq_ignore_ascii_case(&info.code)),
+            extra_channels: Vec::new(),
+        });
+        if !claim.bssids.contains(&bssid) {
+            claim.bssids.push(bssid);
+        }
+        let Some(reg) = local else { continue };
+        for &ch in &info.channels {
+            let band = if ch <= 14 { 1 } else { 2 };
+            if !reg.channel(band, ch).allowed && !claim.extra_channels.contains(&(band, ch)) {
+                claim.extra_channels.push((band, ch));
+            }
+        }
+    }
+
+    let mut claims: Vec<CountryClaim> = claims.into_values().collect();
+    for c in &mut claims {
+        c.extra_channels.sort_unstable();
+    }
+    claims.sort_by(|a, b| b.bssids.len().cmp(&a.bssids.len()).then(a.country.cmp(&b.country)));
+    RegReport {
+        local: local.map(|d| d.country.clone()),
+        claims,
+        off_domain,
+    }
+}
+
+fn signal(r: &BssRow) -> f32 {
+    r.signal_dbm.unwrap_or(f32::NEG_INFINITY)
+}
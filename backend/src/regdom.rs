@@ -0,0 +1,197 @@
+// src/regdom.rs
+//
+// Regulatory domain (NL80211_CMD_GET_REG): the country the kernel enforces
+// and its frequency rules. A 20 MHz channel is allowed when one rule covers
+// all of it; the rule's NO-IR flag makes it passive-only (no beaconing, so
+// an AP can't start there), DFS means radar detection first. Channel
+// recommendations skip channels that aren't allowed or are NO-IR.
+//
+// This is the global domain, which drivers that manage their own
+// (self-managed wiphys) may override per radio.
+//
+// Exposes:
+//   - RegDomain { country, dfs_region, rules } / RegRule, and
+//     RegDomain::channel(band, channel) -> ChannelReg
+//   - query(backend) -> Result<RegDomain>, kept as the latest
+//   - refresh(backend): query() where it is only an extra input
+//   - current() -> the latest domain, if any
+//   - clear()
+
+use anyhow::Result;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::lib_rust::{channel_to_freq, Backend, CHANNELS_5_20, CHANNELS_5_20_DFS};
+use crate::perf;
+
+// NL80211_RRF_* rule flags.
+pub const RRF_NO_OFDM: u32 = 1 << 0;
+pub const RRF_NO_CCK: u32 = 1 << 1;
+pub const RRF_NO_INDOOR: u32 = 1 << 2;
+pub const RRF_NO_OUTDOOR: u32 = 1 << 3;
+pub const RRF_DFS: u32 = 1 << 4;
+pub const RRF_PTP_ONLY: u32 = 1 << 5;
+pub const RRF_PTMP_ONLY: u32 = 1 << 6;
+pub const RRF_NO_IR: u32 = 1 << 7;
+pub const RRF_AUTO_BW: u32 = 1 << 11;
+
+// Names for RegRule::flag_names(), as iw prints them.
+const FLAG_NAMES: [(u32, &str); 9] = [
+    (RRF_NO_OFDM, "no-ofdm"),
+    (RRF_NO_CCK, "no-cck"),
+    (RRF_NO_INDOOR, "no-indoor"),
+    (RRF_NO_OUTDOOR, "no-outdoor"),
+    (RRF_DFS, "dfs"),
+    (RRF_PTP_ONLY, "ptp-only"),
+    (RRF_PTMP_ONLY, "ptmp-only"),
+    (RRF_NO_IR, "no-ir"),
+    (RRF_AUTO_BW, "auto-bw"),
+];
+
+/// One frequency rule. Frequencies in kHz and power in mBm / mBi, as the
+/// kernel reports them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegRule {
+    pub start_khz: u32,
+    pub end_khz: u32,
+    pub max_bw_khz: u32,
+    pub max_ant_gain_mbi: Option<u32>,
+    pub max_eirp_mbm: Option<u32>,
+    /// NL80211_RRF_* bits.
+    pub flags: u32,
+    pub dfs_cac_ms: Option<u32>,
+}
+
+impl RegRule {
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        FLAG_NAMES
+            .iter()
+            .filter(|&&(bit, _)| self.flags & bit != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+
+    /// The NL80211_RRF_* bit of a flag_names() name.
+    pub fn flag_bit(name: &str) -> Option<u32> {
+        FLAG_NAMES.iter().find(|&&(_, n)| n == name).map(|&(bit, _)| bit)
+    }
+
+    // Whether the 20 MHz channel centred on `freq_mhz` fits in the rule.
+    fn covers(&self, freq_mhz: u32) -> bool {
+        let (lo, hi) = ((freq_mhz - 10) * 1000, (freq_mhz + 10) * 1000);
+        self.start_khz <= lo && hi <= self.end_khz && self.max_bw_khz >= 20_000
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegDomain {
+    /// ISO 3166 alpha2, "00" for the world domain, "99" when a driver
+    /// built it from several.
+    pub country: String,
+    /// NL80211_DFS_*: 1 FCC, 2 ETSI, 3 JP (0 unset).
+    pub dfs_region: Option<u8>,
+    pub rules: Vec<RegRule>,
+}
+
+/// What the domain says about one 20 MHz channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelReg {
+    /// Some rule covers the channel.
+    pub allowed: bool,
+    /// Passive-only: no beaconing / probing initiated here.
+    pub no_ir: bool,
+    /// Radar detection (DFS) required.
+    pub dfs: bool,
+    pub max_eirp_dbm: Option<f32>,
+}
+
+impl ChannelReg {
+    /// Allowed and not passive-only: a channel an AP may be moved to.
+    pub fn usable(&self) -> bool {
+        self.allowed && !self.no_ir
+    }
+}
+
+impl RegDomain {
+    /// The rule covering channel `ch` of freq_band() `band`, if any.
+    pub fn channel(&self, band: u8, ch: u32) -> ChannelReg {
+        let rule = channel_to_freq(band, ch).and_then(|f| self.rules.iter().find(|r| r.covers(f)));
+        match rule {
+            Some(r) => ChannelReg {
+                allowed: true,
+                no_ir: r.flags & RRF_NO_IR != 0,
+                dfs: r.flags & RRF_DFS != 0,
+                max_eirp_dbm: r.max_eirp_mbm.map(|p| p as f32 / 100.0),
+            },
+            None => ChannelReg::default(),
+        }
+    }
+
+    pub fn usable(&self, band: u8, ch: u32) -> bool {
+        self.channel(band, ch).usable()
+    }
+
+    /// The widest channel (MHz) the rule covering `ch` allows; None when
+    /// no rule does, or it lets adjacent rules add up (auto-bw).
+    pub fn max_width_mhz(&self, band: u8, ch: u32) -> Option<u32> {
+        let rule = channel_to_freq(band, ch).and_then(|f| self.rules.iter().find(|r| r.covers(f)))?;
+        (rule.flags & RRF_AUTO_BW == 0).then_some(rule.max_bw_khz / 1000)
+    }
+
+    /// channel() for every 20 MHz channel: 1 to 14, the 5 GHz ones in
+    /// CHANNELS_5_20 / CHANNELS_5_20_DFS and 6 GHz 1 to 233.
+    pub fn channels(&self) -> Vec<(u8, u32, ChannelReg)> {
+        let mut five: Vec<u32> = CHANNELS_5_20.iter().chain(&CHANNELS_5_20_DFS).copied().collect();
+        five.sort_unstable();
+        (1..=14)
+            .map(|c| (1, c))
+            .chain(five.into_iter().map(|c| (2, c)))
+            .chain((1..=233).step_by(4).map(|c| (4, c)))
+            .map(|(band, ch)| (band, ch, self.channel(band, ch)))
+            .collect()
+    }
+}
+
+pub fn dfs_region_name(region: u8) -> Option<&'static str> {
+    match region {
+        1 => Some("FCC"),
+        2 => Some("ETSI"),
+        3 => Some("JP"),
+        _ => None,
+    }
+}
+
+static LAST: Mutex<Option<RegDomain>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<RegDomain>> {
+    LAST.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Ask `b`'s provider (the kernel, or the fixture under Backend::Mock) for
+/// the regulatory domain.
+pub fn query(b: Backend) -> Result<RegDomain> {
+    let start = Instant::now();
+    let reg = b.provider().regdomain()?;
+    perf::record("regdomain", b.name(), start.elapsed());
+    *lock() = Some(reg.clone());
+    Ok(reg)
+}
+
+/// query(), ignoring failures: without a domain nothing is filtered, so a
+/// failed query also drops the previous one (which may be another
+/// backend's).
+pub fn refresh(b: Backend) {
+    if query(b).is_err() {
+        clear();
+    }
+}
+
+/// The latest domain query() returned.
+pub fn current() -> Option<RegDomain> {
+    lock().clone()
+}
+
+/// Forget it, so recommendations are unfiltered until the next query.
+pub fn clear() {
+    *lock() = None;
+}
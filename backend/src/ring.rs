@@ -0,0 +1,235 @@
+// src/ring.rs
+//
+// Shared-memory ring buffer for streaming scan rows to Python without
+// building a PyObject per item: every scan's rows are pushed while a ring
+// is open (push_rows()). Only scan rows go through it; nothing else
+// (monitor-mode frames included) is streamed this way yet. The ring lives
+// in a file under /dev/shm (or the temp dir where that doesn't exist,
+// e.g. Android) that Python mmaps read-only; see pybackend/ring_reader.py.
+// The file is created fresh with O_EXCL and O_NOFOLLOW, mode 0600, so
+// nobody else can read it or plant a file or symlink at the path first.
+//
+// Layout (native endian):
+//   header, 64 bytes:
+//     0  magic        u32  "WFRB"
+//     4  version      u32
+//     8  record_size  u32
+//     12 capacity     u32  (records)
+//     16 write_seq    u64  total records ever written
+//   data: capacity * record_size bytes; record `seq` lives in slot
+//         seq % capacity.
+//
+// Single producer: writers take the process-wide Mutex around the ring,
+// so only the reader side is lock-free. The writer fills a slot and then
+// publishes it by storing write_seq with Release. A reader copies
+// [read_seq, write_seq), then re-reads write_seq and drops anything the
+// writer may have lapped while it was copying.
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapMut;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::lib_rust::{freq_band, BssRow};
+
+const MAGIC: u32 = u32::from_le_bytes(*b"WFRB");
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 64;
+const WRITE_SEQ_OFF: usize = 16;
+
+/// One streamed BSS observation. Python struct format: "=Q6sBxIIf4x".
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RingRecord {
+    /// Unix time in milliseconds.
+    pub t_ms: u64,
+    pub bssid: [u8; 6],
+    /// freq_band() id, 0 if unknown.
+    pub band: u8,
+    _pad: u8,
+    /// 0 if unknown.
+    pub freq_mhz: u32,
+    /// 0 if unknown.
+    pub channel: u32,
+    /// NaN if unknown.
+    pub signal_dbm: f32,
+    _reserved: u32,
+}
+
+pub const RECORD_SIZE: usize = std::mem::size_of::<RingRecord>();
+const _: () = assert!(RECORD_SIZE == 32);
+
+impl RingRecord {
+    pub fn from_row(t_ms: u64, r: &BssRow) -> Self {
+        RingRecord {
+            t_ms,
+            bssid: r.bssid.unwrap_or_default(),
+            band: r.freq_mhz.map_or(0, freq_band),
+            _pad: 0,
+            freq_mhz: r.freq_mhz.unwrap_or(0),
+            channel: r.channel.unwrap_or(0),
+            signal_dbm: r.signal_dbm.unwrap_or(f32::NAN),
+            _reserved: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8; RECORD_SIZE] {
+        // SAFETY: repr(C), no implicit padding (asserted size), plain data.
+        unsafe { &*(self as *const Self as *const [u8; RECORD_SIZE]) }
+    }
+}
+
+pub struct Ring {
+    map: MmapMut,
+    path: PathBuf,
+    capacity: u64,
+    seq: u64,
+}
+
+impl Ring {
+    /// Create the backing file, owner-only, and map it. A file left at
+    /// `path` (by a crashed run whose pid we reuse) is removed first;
+    /// if something else gets there in between, this fails rather than
+    /// open it.
+    pub fn create(path: &Path, capacity: u32) -> Result<Self> {
+        if capacity == 0 {
+            bail!("ring capacity must be > 0");
+        }
+        let len = HEADER_LEN + capacity as usize * RECORD_SIZE;
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("remove stale {}", path.display()));
+            }
+            _ => {}
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+            .open(path)
+            .with_context(|| format!("open {}", path.display()))?;
+        file.set_len(len as u64)?;
+
+        // SAFETY: we own the file; readers only ever map it read-only.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[0..4].copy_from_slice(&MAGIC.to_ne_bytes());
+        map[4..8].copy_from_slice(&VERSION.to_ne_bytes());
+        map[8..12].copy_from_slice(&(RECORD_SIZE as u32).to_ne_bytes());
+        map[12..16].copy_from_slice(&capacity.to_ne_bytes());
+
+        let ring = Ring {
+            map,
+            path: path.to_owned(),
+            capacity: capacity as u64,
+            seq: 0,
+        };
+        ring.write_seq().store(0, Ordering::Release);
+        Ok(ring)
+    }
+
+    fn write_seq(&self) -> &AtomicU64 {
+        // SAFETY: the offset is 8-aligned within a page-aligned mapping that
+        // lives as long as `self`.
+        unsafe { &*(self.map.as_ptr().add(WRITE_SEQ_OFF) as *const AtomicU64) }
+    }
+
+    pub fn push(&mut self, rec: &RingRecord) {
+        let slot = (self.seq % self.capacity) as usize;
+        let off = HEADER_LEN + slot * RECORD_SIZE;
+        self.map[off..off + RECORD_SIZE].copy_from_slice(rec.as_bytes());
+        self.seq += 1;
+        self.write_seq().store(self.seq, Ordering::Release);
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+static RING: Mutex<Option<Ring>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Ring>> {
+    RING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn default_path() -> PathBuf {
+    let dir = Path::new("/dev/shm");
+    let dir = if dir.is_dir() {
+        dir.to_owned()
+    } else {
+        std::env::temp_dir()
+    };
+    dir.join(format!("wifi_backend-{}.ring", std::process::id()))
+}
+
+/// Open the process-wide ring (replacing any previous one) and return the
+/// path Python should map.
+pub fn open(capacity: u32) -> Result<PathBuf> {
+    let mut guard = lock();
+    // Drop the old ring first so its file is gone before we reuse the path.
+    *guard = None;
+    let ring = Ring::create(&default_path(), capacity)?;
+    let path = ring.path().to_owned();
+    *guard = Some(ring);
+    Ok(path)
+}
+
+pub fn close() {
+    *lock() = None;
+}
+
+/// Stream a scan into the ring, if one is open.
+pub fn push_rows(rows: &[BssRow]) {
+    let mut guard = lock();
+    let Some(ring) = guard.as_mut() else {
+        return;
+    };
+    let t_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+    for r in rows {
+        ring.push(&RingRecord::from_row(t_ms, r));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn create_replaces_stale_files_and_symlinks_owner_only() {
+        let dir = std::env::temp_dir().join(format!("wifi_backend-ring-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (path, target) = (dir.join("r.ring"), dir.join("target"));
+        fs::write(&target, b"keep").unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        let ring = Ring::create(&path, 4).unwrap();
+        let meta = fs::symlink_metadata(&path).unwrap();
+        assert!(meta.is_file());
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read(&target).unwrap(), b"keep");
+        drop(ring);
+
+        fs::write(&path, b"stale").unwrap();
+        let ring = Ring::create(&path, 4).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), (HEADER_LEN + 4 * RECORD_SIZE) as u64);
+        drop(ring);
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
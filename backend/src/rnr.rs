@@ -0,0 +1,173 @@
+// src/rnr.rs
+//
+// 6 GHz neighbours from Reduced Neighbor Reports. A Wi-Fi 6E AP lists its
+// 6 GHz BSS (and sometimes other APs') in the RNR element of its 2.4 and
+// 5 GHz beacons, so clients can find it without scanning all of 6 GHz.
+// Radios that scan 6 GHz poorly, or not at all, still hear those beacons;
+// the BSSs listed but not heard directly become rows of their own here,
+// for the channel weighting to count on 6 GHz.
+//
+// Such a row has the reporter's signal less the extra path loss at 6 GHz
+// (the co-located radio is in the same box), its SSID when the report says
+// it shares the reporter's, and the width of its operating class. It has
+// no IEs.
+//
+// Exposes:
+//   - neighbors(rows) -> Vec<Neighbor>
+
+use std::collections::HashMap;
+
+use crate::ies::{self, Operation};
+use crate::lib_rust::{freq_band, freq_to_channel, ie_list, BssRow};
+
+// Extra path loss at 6 GHz compared to the reporter's band, dB (as in
+// steer.rs: 10 dB from 2.4 GHz, 3 dB from 5 GHz).
+const LOSS_FROM_24_DB: f32 = 10.0;
+const LOSS_FROM_5_DB: f32 = 3.0;
+
+/// A 6 GHz BSS known only from another BSS's RNR.
+#[derive(Debug, Clone)]
+pub struct Neighbor {
+    pub row: BssRow,
+    /// BSSID of the BSS whose beacon listed it (the strongest, if several
+    /// did).
+    pub reported_by: [u8; 6],
+    /// A radio of the reporting AP rather than an AP it has heard of.
+    pub co_located: bool,
+}
+
+// One listed BSS: BSSID, frequency, short SSID.
+type Key = (Option<[u8; 6]>, u32, Option<u32>);
+
+/// The 6 GHz BSSs the RNRs in `rows` list that no row of `rows` is,
+/// matched by BSSID, else by channel and short SSID. Each is listed once.
+pub fn neighbors(rows: &[BssRow]) -> Vec<Neighbor> {
+    let heard_6ghz: Vec<&BssRow> = rows.iter().filter(|r| r.freq_mhz.is_some_and(|f| freq_band(f) == 4)).collect();
+    let heard = |n: &ies::RnrNeighbor, freq: u32| match n.bssid {
+        Some(b) => rows.iter().any(|r| r.bssid == Some(b)),
+        None => heard_6ghz
+            .iter()
+            .any(|r| r.freq_mhz == Some(freq) && n.short_ssid.is_some_and(|s| s == short_ssid(&r.ssid_bytes))),
+    };
+
+    let mut out: HashMap<Key, Neighbor> = HashMap::new();
+    for r in rows {
+        let (Some(reporter), Some(ies), Some(freq), Some(dbm)) = (r.bssid, r.ies.as_deref(), r.freq_mhz, r.signal_dbm) else {
+            continue;
+        };
+        let loss = match freq_band(freq) {
+            1 => LOSS_FROM_24_DB,
+            2 => LOSS_FROM_5_DB,
+            _ => 0.0,
+        };
+        for n in ies::parse_rnr(&ie_list(ies)) {
+            let Some((nfreq, width)) = ies::op_class_6ghz(n.op_class, n.channel) else {
+                continue;
+            };
+            if heard(&n, nfreq) {
+                continue;
+            }
+            let signal = dbm - loss;
+            let key = (n.bssid, nfreq, n.short_ssid);
+            if out.get(&key).is_some_and(|kept| kept.row.signal_dbm >= Some(signal)) {
+                continue;
+            }
+            let channel = freq_to_channel(nfreq);
+            let mut row = BssRow::from_parts(n.bssid, Some(nfreq), Some(signal), None)
+                .with_operation(Operation {
+                    width_mhz: width,
+                    primary: channel,
+                    secondary: None,
+                    center: None,
+                })
+                .seen_ms_ago(r.age_ms);
+            row.seen = r.seen;
+            row.cached = r.cached;
+            if n.same_ssid {
+                if let Some(ssid) = r.ssid.as_ref().filter(|_| !r.hidden) {
+                    row.ssid = Some(ssid.clone());
+                    row.ssid_bytes = r.ssid_bytes.clone();
+                }
+            }
+            out.insert(
+                key,
+                Neighbor {
+                    row,
+                    reported_by: reporter,
+                    co_located: n.co_located,
+                },
+            );
+        }
+    }
+    let mut out: Vec<Neighbor> = out.into_values().collect();
+    out.sort_by_key(|n| (n.row.channel, n.row.bssid));
+    out
+}
+
+// The short SSID RNRs carry: the SSID's CRC-32 (IEEE 802.3).
+fn short_ssid(ssid: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in ssid {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIX_GHZ: [u8; 6] = [0x02, 0x66, 0x66, 0x66, 0x66, 0x01];
+
+    // A 5 GHz (or 2.4 GHz) beacon whose RNR lists SIX_GHZ on 6 GHz
+    // channel 37, a radio of the same AP with the same SSID.
+    fn reporter(bssid: [u8; 6], freq_mhz: u32, dbm: f32) -> BssRow {
+        let info = [&[0][..], &SIX_GHZ, &[0x42]].concat();
+        let rnr = [&[201, 12, 0x00, 8, 131, 37][..], &info].concat();
+        let mut r = BssRow::from_parts(Some(bssid), Some(freq_mhz), Some(dbm), Some(&rnr));
+        r.set_ssid(b"home");
+        r
+    }
+
+    #[test]
+    fn unheard_6ghz_radio_becomes_a_row() {
+        let rows = [reporter([0x02, 5, 5, 5, 5, 1], 5180, -50.0)];
+        let found = neighbors(&rows);
+        assert_eq!(found.len(), 1);
+        let n = &found[0];
+        assert_eq!((n.reported_by, n.co_located), (rows[0].bssid.unwrap(), true));
+        assert_eq!((n.row.bssid, n.row.freq_mhz, n.row.channel), (Some(SIX_GHZ), Some(6135), Some(37)));
+        assert_eq!(n.row.signal_dbm, Some(-53.0));
+        assert_eq!(n.row.ssid.as_deref(), Some("home"));
+        assert_eq!(n.row.operation().map(|o| o.width_mhz), Some(20));
+    }
+
+    #[test]
+    fn listed_once_from_the_strongest_reporter() {
+        let rows = [
+            reporter([0x02, 2, 2, 2, 2, 1], 2437, -45.0),
+            reporter([0x02, 5, 5, 5, 5, 1], 5180, -50.0),
+        ];
+        let found = neighbors(&rows);
+        assert_eq!(found.len(), 1);
+        // 2.4 GHz loses 10 dB to 6 GHz, 5 GHz only 3.
+        assert_eq!(found[0].reported_by, [0x02, 5, 5, 5, 5, 1]);
+        assert_eq!(found[0].row.signal_dbm, Some(-53.0));
+    }
+
+    #[test]
+    fn heard_6ghz_bss_is_not_added_again() {
+        let heard = BssRow::from_parts(Some(SIX_GHZ), Some(6135), Some(-70.0), None);
+        let rows = [reporter([0x02, 5, 5, 5, 5, 1], 5180, -50.0), heard];
+        assert!(neighbors(&rows).is_empty());
+    }
+
+    #[test]
+    fn short_ssid_is_crc32() {
+        assert_eq!(short_ssid(b""), 0);
+        assert_eq!(short_ssid(b"123456789"), 0xcbf4_3926);
+    }
+}
@@ -0,0 +1,140 @@
+// src/roam.rs
+//
+// Roaming advice from the client's side: which BSS of the network we are
+// connected to (same SSID) would serve this spot best, and whether we are
+// sticking to a distant node while a much closer one is heard. Clients
+// roam late, so a mesh can look badly placed when it is only the phone
+// that didn't move on; this tells the two apart.
+//
+// Candidates are ranked by signal plus a bonus for the faster bands, and
+// 5 / 6 GHz ones only count when heard well enough to hold a link there.
+// Roaming is advised once the best beats the current BSS by the
+// hysteresis margin, so two nodes heard about as well don't flap.
+//
+// Exposes:
+//   - advise(rows, connected, hysteresis_db) -> Option<RoamAdvice>
+//   - RoamAdvice, Candidate
+
+use crate::lib_rust::{freq_band, same_device, BssRow};
+
+/// Margin (dB, after band bonuses) a sibling BSS must beat the current one
+/// by before roaming is worth it.
+pub const DEFAULT_HYSTERESIS_DB: f32 = 8.0;
+/// Below this the current link is weak: a much stronger sibling on another
+/// node means we are sticking to a distant one.
+pub const STICKY_DBM: f32 = -70.0;
+/// 5 / 6 GHz candidates weaker than this would not hold the link.
+pub const HIGH_BAND_MIN_DBM: f32 = -72.0;
+
+// What the faster bands are worth over 2.4 GHz at the same signal, dB.
+const BONUS_5GHZ_DB: f32 = 5.0;
+const BONUS_6GHZ_DB: f32 = 8.0;
+
+/// A BSS of our network we could roam to.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub row: BssRow,
+    /// Signal plus band bonus, dB.
+    pub score: f32,
+    /// score less the current BSS's score.
+    pub gain_db: f32,
+    /// Another radio of the node we are on, per same_device().
+    pub same_device: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoamAdvice {
+    pub current: BssRow,
+    pub current_score: f32,
+    /// The current BSS's siblings that could hold a link, best first.
+    pub candidates: Vec<Candidate>,
+    /// The best candidate clears the hysteresis margin.
+    pub roam: bool,
+    /// The current link is weaker than STICKY_DBM and another node is
+    /// heard at least the margin louder.
+    pub sticky: bool,
+}
+
+impl RoamAdvice {
+    /// The BSS to roam to, when roaming is advised.
+    pub fn target(&self) -> Option<&Candidate> {
+        self.candidates.first().filter(|_| self.roam)
+    }
+}
+
+/// Roaming advice for `connected`, the row of the BSS we are on, against
+/// the rest of `rows`. None when it has no BSSID or signal, or hides its
+/// SSID.
+pub fn advise(rows: &[BssRow], connected: &BssRow, hysteresis_db: f32) -> Option<RoamAdvice> {
+    if connected.hidden || connected.ssid_bytes.is_empty() {
+        return None;
+    }
+    let current_bssid = connected.bssid?;
+    let current_dbm = connected.signal_dbm?;
+    let current_score = score(connected)?;
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for r in rows {
+        let Some(bssid) = r.bssid else { continue };
+        if bssid == current_bssid || r.ssid_bytes != connected.ssid_bytes {
+            continue;
+        }
+        let Some(score) = score(r).filter(|_| holds_link(r)) else {
+            continue;
+        };
+        match candidates.iter_mut().find(|c| c.row.bssid == r.bssid) {
+            // scan_merged() can list a BSSID once per radio.
+            Some(c) if c.score >= score => continue,
+            Some(c) => *c = candidate(r, score, current_score, &current_bssid),
+            None => candidates.push(candidate(r, score, current_score, &current_bssid)),
+        }
+    }
+    // Ties go to the faster band.
+    let band = |c: &Candidate| c.row.freq_mhz.map_or(0, freq_band);
+    candidates.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| band(b).cmp(&band(a)))
+            .then(a.row.bssid.cmp(&b.row.bssid))
+    });
+
+    let roam = candidates.first().is_some_and(|c| c.gain_db >= hysteresis_db);
+    let sticky = current_dbm < STICKY_DBM
+        && candidates
+            .iter()
+            .any(|c| !c.same_device && c.row.signal_dbm.is_some_and(|s| s >= current_dbm + hysteresis_db));
+    Some(RoamAdvice {
+        current: connected.clone(),
+        current_score,
+        candidates,
+        roam,
+        sticky,
+    })
+}
+
+fn candidate(r: &BssRow, score: f32, current_score: f32, current: &[u8; 6]) -> Candidate {
+    Candidate {
+        row: r.clone(),
+        score,
+        gain_db: score - current_score,
+        same_device: r.bssid.is_some_and(|b| same_device(&b, current)),
+    }
+}
+
+// Signal plus band bonus; None without a signal.
+fn score(r: &BssRow) -> Option<f32> {
+    let bonus = match r.freq_mhz.map(freq_band) {
+        Some(2) => BONUS_5GHZ_DB,
+        Some(4) => BONUS_6GHZ_DB,
+        _ => 0.0,
+    };
+    Some(r.signal_dbm? + bonus)
+}
+
+// Heard well enough to move to: 5 / 6 GHz fades faster.
+fn holds_link(r: &BssRow) -> bool {
+    match r.freq_mhz.map(freq_band) {
+        Some(2 | 4) => r.signal_dbm.is_some_and(|s| s >= HIGH_BAND_MIN_DBM),
+        _ => true,
+    }
+}
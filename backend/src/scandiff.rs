@@ -0,0 +1,145 @@
+// src/scandiff.rs
+//
+// What changed between two scans: BSSs that appeared or vanished, moved to
+// another channel, or whose signal moved by at least a threshold. A
+// neighbour's mesh re-channelling shows up as channel changes on its
+// BSSIDs, which is what a channel advisor watches to know when to plan
+// again.
+//
+// BSSs are matched by BSSID; rows without one are left out. A BSSID heard
+// more than once in a scan (scan_merged(), one row per radio) counts with
+// its strongest row.
+//
+// Exposes:
+//   - diff(old, new, min_rssi_delta) -> ScanDiff
+//   - ScanDiff, ChannelChange, RssiChange
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::lib_rust::{BssRow, ScanSnapshot};
+
+/// Signal changes smaller than this (dB) are noise between two scans.
+pub const DEFAULT_MIN_RSSI_DELTA: f32 = 10.0;
+
+#[derive(Debug, Clone)]
+pub struct ChannelChange {
+    pub bssid: [u8; 6],
+    pub ssid: Option<Arc<str>>,
+    pub old_channel: Option<u32>,
+    pub new_channel: Option<u32>,
+    pub old_freq_mhz: u32,
+    pub new_freq_mhz: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RssiChange {
+    pub bssid: [u8; 6],
+    pub ssid: Option<Arc<str>>,
+    pub old_dbm: f32,
+    pub new_dbm: f32,
+}
+
+impl RssiChange {
+    /// New minus old, dB.
+    pub fn delta(&self) -> f32 {
+        self.new_dbm - self.old_dbm
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanDiff {
+    /// In the new scan only, strongest first.
+    pub appeared: Vec<BssRow>,
+    /// In the old scan only, strongest (as last heard) first.
+    pub disappeared: Vec<BssRow>,
+    pub channel_changes: Vec<ChannelChange>,
+    /// Biggest change first.
+    pub rssi_changes: Vec<RssiChange>,
+    /// Seconds from the old snapshot to the new one.
+    pub elapsed_s: f64,
+}
+
+impl ScanDiff {
+    pub fn is_empty(&self) -> bool {
+        self.appeared.is_empty()
+            && self.disappeared.is_empty()
+            && self.channel_changes.is_empty()
+            && self.rssi_changes.is_empty()
+    }
+}
+
+/// How `new` differs from `old`. Signal changes count from
+/// `min_rssi_delta` dB either way.
+pub fn diff(old: &ScanSnapshot, new: &ScanSnapshot, min_rssi_delta: f32) -> ScanDiff {
+    let before = by_bssid(&old.rows);
+    let after = by_bssid(&new.rows);
+
+    let mut d = ScanDiff {
+        elapsed_s: new.at.wall - old.at.wall,
+        ..ScanDiff::default()
+    };
+    for (bssid, &row) in &after {
+        let Some(&prev) = before.get(bssid) else {
+            d.appeared.push(row.clone());
+            continue;
+        };
+        if let (Some(old_freq), Some(new_freq)) = (prev.freq_mhz, row.freq_mhz) {
+            if old_freq != new_freq {
+                d.channel_changes.push(ChannelChange {
+                    bssid: *bssid,
+                    ssid: row.ssid.clone(),
+                    old_channel: prev.channel,
+                    new_channel: row.channel,
+                    old_freq_mhz: old_freq,
+                    new_freq_mhz: new_freq,
+                });
+            }
+        }
+        if let (Some(old_dbm), Some(new_dbm)) = (prev.signal_dbm, row.signal_dbm) {
+            if (new_dbm - old_dbm).abs() >= min_rssi_delta {
+                d.rssi_changes.push(RssiChange {
+                    bssid: *bssid,
+                    ssid: row.ssid.clone(),
+                    old_dbm,
+                    new_dbm,
+                });
+            }
+        }
+    }
+    d.disappeared = before
+        .iter()
+        .filter(|(bssid, _)| !after.contains_key(*bssid))
+        .map(|(_, &row)| row.clone())
+        .collect();
+
+    d.appeared.sort_by(strongest_first);
+    d.disappeared.sort_by(strongest_first);
+    d.channel_changes.sort_by_key(|c| c.bssid);
+    d.rssi_changes.sort_by(|a, b| b.delta().abs().total_cmp(&a.delta().abs()).then(a.bssid.cmp(&b.bssid)));
+    d
+}
+
+// Each BSSID's strongest row.
+fn by_bssid(rows: &[BssRow]) -> HashMap<[u8; 6], &BssRow> {
+    let mut map: HashMap<[u8; 6], &BssRow> = HashMap::new();
+    for row in rows {
+        let Some(bssid) = row.bssid else { continue };
+        map.entry(bssid)
+            .and_modify(|kept| {
+                if signal(row) > signal(kept) {
+                    *kept = row;
+                }
+            })
+            .or_insert(row);
+    }
+    map
+}
+
+fn signal(row: &BssRow) -> f32 {
+    row.signal_dbm.unwrap_or(f32::NEG_INFINITY)
+}
+
+fn strongest_first(a: &BssRow, b: &BssRow) -> std::cmp::Ordering {
+    signal(b).total_cmp(&signal(a)).then(a.bssid.cmp(&b.bssid))
+}
@@ -0,0 +1,102 @@
+// src/scandump.rs
+//
+// GET_SCAN dumps kept in a file: the raw reply payloads of one dump
+// (genl header plus attributes, as nl_raw::parse_scan_payload() takes
+// them), so benches/ and tests can parse the bytes a kernel sent instead
+// of rows built by hand. examples/record_scan_dump.rs records one off a
+// real interface.
+//
+// File layout, little endian like the attributes inside on every target
+// we build for:
+//   "WFSD", u32 version, u32 payload count, then per payload its u32
+//   length and bytes.
+//
+// Exposes:
+//   - record(ifindex) -> Result<Vec<Vec<u8>>>
+//   - encode(payloads) -> Vec<u8>, decode(bytes) -> Result<Vec<Vec<u8>>>
+
+use anyhow::{bail, Result};
+
+use crate::netlink::{block_on, ifindex_attrs, ifindex_or_first, Nl80211};
+use crate::nl_raw::Cmd;
+
+const MAGIC: &[u8; 4] = b"WFSD";
+const VERSION: u32 = 1;
+
+/// Every GET_SCAN reply payload for `ifindex` (the first interface if
+/// None), as the kernel sent them. This is the kernel's BSS table as it
+/// stands: trigger a scan first for a fresh one.
+pub fn record(ifindex: Option<u32>) -> Result<Vec<Vec<u8>>> {
+    block_on(async {
+        let nl = Nl80211::shared()?;
+        let ifindex = ifindex_or_first(&nl, ifindex).await?;
+        nl.dump_with(Cmd::CmdGetScan, ifindex_attrs(ifindex)?, |p| Ok(Some(p.to_vec()))).await
+    })
+}
+
+pub fn encode(payloads: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(payloads.len() as u32).to_le_bytes());
+    for p in payloads {
+        out.extend_from_slice(&(p.len() as u32).to_le_bytes());
+        out.extend_from_slice(p);
+    }
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let u32_at = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    if bytes.get(..4) != Some(MAGIC) {
+        bail!("not a scan dump");
+    }
+    if u32_at(4) != Some(VERSION) {
+        bail!("scan dump version {:?}, expected {VERSION}", u32_at(4));
+    }
+    let Some(count) = u32_at(8) else {
+        bail!("scan dump truncated in its header");
+    };
+    let mut at = 12;
+    let mut out = Vec::new();
+    for i in 0..count {
+        let Some(payload) = u32_at(at).and_then(|len| bytes.get(at + 4..at + 4 + len as usize)) else {
+            bail!("scan dump truncated in payload {i} of {count}");
+        };
+        out.push(payload.to_vec());
+        at += 4 + payload.len();
+    }
+    if at != bytes.len() {
+        bail!("{} bytes after the last payload of the scan dump", bytes.len() - at);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nl_raw::parse_scan_payload;
+
+    #[test]
+    fn round_trips_and_rejects_damage() {
+        let payloads = vec![vec![34, 1, 0, 0], Vec::new(), vec![7; 300]];
+        let bytes = encode(&payloads);
+        assert_eq!(decode(&bytes).unwrap(), payloads);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&[&bytes[..], &[0]].concat()).is_err());
+        assert!(decode(b"WFSD").is_err());
+        assert!(decode(b"").is_err());
+    }
+
+    #[test]
+    fn checked_in_fixture_parses() {
+        let payloads = decode(include_bytes!("../fixtures/scan_dump_synthetic.bin")).unwrap();
+        let rows: Vec<_> = payloads.iter().filter_map(|p| parse_scan_payload(p)).collect();
+        assert_eq!(rows.len(), payloads.len());
+        assert!(rows.iter().all(|r| r.bssid.is_some() && r.freq_mhz.is_some()));
+        assert!(rows.iter().any(|r| r.hidden));
+        assert!(rows.iter().any(|r| r.ssid.as_deref().is_some_and(|s| s.contains('\u{fffd}'))));
+        for band in [1, 2, 4] {
+            assert!(rows.iter().any(|r| r.freq_mhz.map(crate::lib_rust::freq_band) == Some(band)), "band {band}");
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// src/secaudit.rs
+//
+// Security audit of the networks around, for the app's environment health
+// screen: per ESS (one SSID) the weakest security any of its BSSs offers,
+// whether they protect management frames (PMF), whether WPS is on, and a
+// letter grade summing that up.
+//
+// Worst case is what matters: a client (or an attacker) can pick the
+// weakest BSS of a network, and a WPA2 AP that still offers TKIP is a
+// WPA/TKIP network to them.
+//
+// Grades, from the weakest security:
+//   A  WPA3, enterprise, or WPA2 with PMF required
+//   B  WPA2 (and WPA2/WPA3 transition)
+//   C  OWE: encrypted, but anyone can join
+//   D  WPA or TKIP offered
+//   F  open or WEP
+// WPS on costs one grade, two when it takes PINs and isn't locked (open to
+// PIN brute force).
+//
+// Exposes:
+//   - audit(rows, connected) -> Vec<EssAudit>
+//   - EssAudit, Grade, Issue
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ies::{self, Pmf, Security};
+use crate::lib_rust::{ie_list, BssRow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    F,
+    D,
+    C,
+    B,
+    A,
+}
+
+impl Grade {
+    pub fn name(self) -> &'static str {
+        match self {
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::F => "F",
+        }
+    }
+
+    fn lower(self) -> Grade {
+        match self {
+            Grade::A => Grade::B,
+            Grade::B => Grade::C,
+            Grade::C => Grade::D,
+            Grade::D | Grade::F => Grade::F,
+        }
+    }
+}
+
+/// What cost an ESS its grade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    Open,
+    Wep,
+    /// WPA, or TKIP offered next to WPA2.
+    WpaTkip,
+    /// Some BSS with RSN doesn't do PMF: clients can be deauthenticated
+    /// by anyone.
+    NoPmf,
+    Wps,
+    /// WPS takes PINs and isn't locked.
+    WpsPin,
+}
+
+impl Issue {
+    pub fn name(self) -> &'static str {
+        match self {
+            Issue::Open => "open",
+            Issue::Wep => "wep",
+            Issue::WpaTkip => "wpa_tkip",
+            Issue::NoPmf => "no_pmf",
+            Issue::Wps => "wps",
+            Issue::WpsPin => "wps_pin",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EssAudit {
+    pub ssid: Arc<str>,
+    pub ssid_bytes: Vec<u8>,
+    pub bssids: Vec<[u8; 6]>,
+    pub best_dbm: Option<f32>,
+    /// The weakest security of its BSSs.
+    pub worst_security: Security,
+    /// Some BSS offers TKIP (WPA, or WPA/WPA2 mixed mode).
+    pub tkip: bool,
+    /// The weakest PMF of its BSSs with an RSN element; None when none
+    /// has one.
+    pub pmf: Option<Pmf>,
+    pub wps: bool,
+    /// Some BSS's WPS takes PINs and isn't locked.
+    pub wps_pin: bool,
+    pub grade: Grade,
+    pub issues: Vec<Issue>,
+    /// The network we are connected to.
+    pub connected: bool,
+}
+
+/// The named networks in `rows`, worst grade first, then strongest.
+/// Hidden BSSs have no name to group by and are left out.
+pub fn audit(rows: &[BssRow], connected: Option<&[u8; 6]>) -> Vec<EssAudit> {
+    let mut by_ssid: HashMap<&[u8], Vec<&BssRow>> = HashMap::new();
+    for r in rows {
+        if r.hidden || r.ssid_bytes.is_empty() || r.bssid.is_none() {
+            continue;
+        }
+        let members = by_ssid.entry(&r.ssid_bytes).or_default();
+        // scan_merged() lists a BSSID once per radio; the IEs are the same.
+        if !members.iter().any(|m| m.bssid == r.bssid) {
+            members.push(r);
+        }
+    }
+    let mut out: Vec<EssAudit> = by_ssid.into_values().map(|m| audit_of(&m, connected)).collect();
+    out.sort_by(|a, b| {
+        let best = |e: &EssAudit| e.best_dbm.unwrap_or(f32::NEG_INFINITY);
+        a.grade
+            .cmp(&b.grade)
+            .then_with(|| best(b).total_cmp(&best(a)))
+            .then_with(|| a.ssid_bytes.cmp(&b.ssid_bytes))
+    });
+    out
+}
+
+fn audit_of(members: &[&BssRow], connected: Option<&[u8; 6]>) -> EssAudit {
+    let mut worst = Security::Enterprise;
+    let mut tkip = false;
+    let mut pmf: Option<Pmf> = None;
+    let mut wps = false;
+    let mut wps_pin = false;
+    for r in members {
+        let sec = r.security();
+        if sec.strength() < worst.strength() {
+            worst = sec;
+        }
+        if let Some(w) = r.wps() {
+            wps = true;
+            wps_pin |= w.pin_vulnerable();
+        }
+        if let Some(p) = r.pmf() {
+            pmf = Some(pmf.map_or(p, |q| q.min(p)));
+        }
+        tkip |= r.ies.as_deref().is_some_and(|ies| ies::offers_tkip(&ie_list(ies)));
+    }
+
+    let mut issues = Vec::new();
+    let mut grade = match worst {
+        Security::Open => {
+            issues.push(Issue::Open);
+            Grade::F
+        }
+        Security::Wep => {
+            issues.push(Issue::Wep);
+            Grade::F
+        }
+        Security::Wpa => Grade::D,
+        Security::Owe => Grade::C,
+        Security::Wpa2 | Security::Wpa2Wpa3 if pmf == Some(Pmf::Required) && !tkip => Grade::A,
+        Security::Wpa2 | Security::Wpa2Wpa3 => Grade::B,
+        Security::Wpa3 | Security::Enterprise => Grade::A,
+    };
+    if worst == Security::Wpa || (tkip && grade > Grade::D) {
+        issues.push(Issue::WpaTkip);
+        grade = grade.min(Grade::D);
+    }
+    if pmf == Some(Pmf::Disabled) {
+        issues.push(Issue::NoPmf);
+    }
+    if wps {
+        issues.push(Issue::Wps);
+        grade = grade.lower();
+    }
+    if wps_pin {
+        issues.push(Issue::WpsPin);
+        grade = grade.lower();
+    }
+
+    let mut bssids: Vec<&BssRow> = members.to_vec();
+    bssids.sort_by(|a, b| signal(b).total_cmp(&signal(a)).then(a.bssid.cmp(&b.bssid)));
+    EssAudit {
+        ssid: members[0].ssid.clone().unwrap_or_default(),
+        ssid_bytes: members[0].ssid_bytes.clone(),
+        bssids: bssids.iter().filter_map(|r| r.bssid).collect(),
+        best_dbm: bssids[0].signal_dbm,
+        worst_security: worst,
+        tkip,
+        pmf,
+        wps,
+        wps_pin,
+        grade,
+        issues,
+        connected: connected.is_some_and(|c| members.iter().any(|r| r.bssid.as_ref() == Some(c))),
+    }
+}
+
+fn signal(r: &BssRow) -> f32 {
+    r.signal_dbm.unwrap_or(f32::NEG_INFINITY)
+}
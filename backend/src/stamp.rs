@@ -0,0 +1,81 @@
+// src/stamp.rs
+//
+// When a result was captured. Every stamp carries both clocks: wall time
+// (unix seconds) to show and store, and CLOCK_MONOTONIC seconds, which is
+// what Python's time.monotonic() reads on Linux, so consumers can compute
+// a result's age without being fooled by NTP or manual clock changes.
+
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stamp {
+    /// Unix time, seconds.
+    pub wall: f64,
+    /// CLOCK_MONOTONIC, seconds.
+    pub mono: f64,
+}
+
+impl Stamp {
+    pub fn now() -> Stamp {
+        Stamp {
+            wall: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64()),
+            mono: monotonic(),
+        }
+    }
+
+    /// The stamp of something that happened `ms` milliseconds before now.
+    pub fn ago_ms(ms: u32) -> Stamp {
+        let now = Stamp::now();
+        let secs = ms as f64 / 1000.0;
+        Stamp {
+            wall: now.wall - secs,
+            mono: now.mono - secs,
+        }
+    }
+
+    /// The stamp of a wall-clock time (e.g. from a capture file), with the
+    /// monotonic side assuming the clock hasn't been changed since.
+    pub fn from_wall(wall: f64) -> Stamp {
+        let now = Stamp::now();
+        Stamp {
+            wall,
+            mono: now.mono - (now.wall - wall),
+        }
+    }
+}
+
+impl Stamp {
+    /// The wall time as "2026-10-15 12:34:56", UTC, the way spreadsheets
+    /// and WiGLE read dates.
+    pub fn utc(&self) -> String {
+        let secs = self.wall.floor() as i64;
+        let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+        let (y, m, d) = civil_from_days(days);
+        format!("{y:04}-{m:02}-{d:02} {:02}:{:02}:{:02}", rem / 3600, rem / 60 % 60, rem % 60)
+    }
+}
+
+// Days since 1970-01-01 to (year, month, day), proleptic Gregorian
+// (Howard Hinnant's civil_from_days).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+fn monotonic() -> f64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid timespec to write to.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as f64 + ts.tv_nsec as f64 / 1e9
+}
@@ -0,0 +1,58 @@
+// src/stations.rs
+//
+// Clients associated to this machine's AP-mode interfaces, as reported by
+// GET_STATION dumps (nl_raw::ap_stations_async), or by the mock provider
+// when that is the selected backend. Shared by band steering
+// (steer.rs), backhaul monitoring (backhaul.rs) and airtime accounting
+// (airtime.rs); each reads the fields it needs.
+
+use anyhow::Result;
+use std::time::Instant;
+
+use crate::lib_rust::backend;
+use crate::stamp::Stamp;
+use crate::perf;
+
+/// One local AP radio.
+#[derive(Debug, Clone, Copy)]
+pub struct ApRadio {
+    pub bssid: [u8; 6],
+    pub freq_mhz: u32,
+}
+
+/// One associated client, as seen by the AP. Counters are cumulative
+/// since association.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Station {
+    pub mac: [u8; 6],
+    /// The AP radio it is associated to.
+    pub bssid: [u8; 6],
+    pub freq_mhz: Option<u32>,
+    pub signal_dbm: Option<f32>,
+    /// Known capability; None to go by what we've seen.
+    pub dual_band: Option<bool>,
+    pub mcs: Option<u8>,
+    pub tx_packets: Option<u64>,
+    pub tx_retries: Option<u64>,
+    pub tx_bytes: Option<u64>,
+    pub rx_bytes: Option<u64>,
+    /// Time spent sending to / receiving from the client, where the
+    /// driver accounts it.
+    pub tx_duration_us: Option<u64>,
+    pub rx_duration_us: Option<u64>,
+    pub tx_bitrate_kbps: Option<u32>,
+    pub rx_bitrate_kbps: Option<u32>,
+    /// When these figures were read.
+    pub at: Option<Stamp>,
+}
+
+/// Radios and associated clients of this machine's AP-mode interfaces.
+pub fn local_stations() -> Result<(Vec<ApRadio>, Vec<Station>)> {
+    let start = Instant::now();
+    let b = backend();
+    let (radios, mut stations) = b.provider().station_info()?;
+    perf::record("ap_stations", b.name(), start.elapsed());
+    let at = Stamp::now();
+    stations.iter_mut().for_each(|s| s.at = Some(at));
+    Ok((radios, stations))
+}
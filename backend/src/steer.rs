@@ -0,0 +1,119 @@
+// src/steer.rs
+//
+// Band-steering suggestions: dual-band clients camped on 2.4 GHz whose AP
+// node has a 5 or 6 GHz radio they would hear well. Channel choice spreads
+// APs across the spectrum; this spreads the clients of one AP across its
+// bands.
+//
+// Input is a list of associated stations per AP radio, either from the
+// local AP-mode interfaces (stations::local_stations) or handed in from
+// Python (e.g. a UniFi controller's client list).
+//
+// A client counts as dual-band when the caller says so or once it has been
+// seen associated on 5/6 GHz to any of our radios; nl80211 doesn't expose
+// the client's capabilities directly. Its 5/6 GHz signal is estimated from
+// the 2.4 GHz one with a fixed extra path loss.
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use crate::lib_rust::{freq_band, same_device};
+use crate::stamp::Stamp;
+use crate::stations::{ApRadio, Station};
+
+/// Worse than this on the target band after the path-loss offset isn't
+/// worth moving for: the client would just fall back to 2.4 GHz.
+pub const STEER_MIN_DBM: f32 = -70.0;
+
+// Typical extra free-space + wall loss compared to 2.4 GHz at the same spot.
+const LOSS_5GHZ_DB: f32 = 7.0;
+const LOSS_6GHZ_DB: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Suggestion {
+    pub station: [u8; 6],
+    pub from_bssid: [u8; 6],
+    pub from_freq_mhz: u32,
+    pub to_bssid: [u8; 6],
+    pub to_freq_mhz: u32,
+    pub signal_dbm: f32,
+    /// Estimated signal on the target radio.
+    pub expected_dbm: f32,
+    /// None when we only guess the client could move.
+    pub dual_band: Option<bool>,
+    /// When the station figures this is based on were read.
+    pub at: Option<Stamp>,
+}
+
+// Clients seen associated on 5/6 GHz at some point, i.e. known dual-band.
+static SEEN_HIGH_BAND: Mutex<BTreeSet<[u8; 6]>> = Mutex::new(BTreeSet::new());
+
+fn lock() -> std::sync::MutexGuard<'static, BTreeSet<[u8; 6]>> {
+    SEEN_HIGH_BAND.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Extra loss on a 5/6 GHz radio compared to 2.4 GHz; None for 2.4 GHz.
+fn high_band_loss(freq_mhz: u32) -> Option<f32> {
+    match freq_mhz {
+        5925..=7125 => Some(LOSS_6GHZ_DB),
+        f if freq_band(f) == 2 => Some(LOSS_5GHZ_DB),
+        _ => None,
+    }
+}
+
+/// Steering suggestions for `stations`, best improvement first. Also
+/// remembers every client currently on 5/6 GHz as dual-band.
+///
+/// With `include_unknown`, clients never seen on 5/6 GHz and of unknown
+/// capability are suggested too, with `dual_band: None`.
+pub fn suggest(stations: &[Station], radios: &[ApRadio], include_unknown: bool) -> Vec<Suggestion> {
+    let mut seen = lock();
+    for st in stations {
+        if st.freq_mhz.and_then(high_band_loss).is_some() {
+            seen.insert(st.mac);
+        }
+    }
+
+    let mut out = Vec::new();
+    for st in stations {
+        let (Some(freq), Some(sig)) = (st.freq_mhz, st.signal_dbm) else {
+            continue;
+        };
+        if freq_band(freq) != 1 {
+            continue;
+        }
+        let dual_band = st.dual_band.or(seen.contains(&st.mac).then_some(true));
+        if dual_band == Some(false) || (dual_band.is_none() && !include_unknown) {
+            continue;
+        }
+
+        // Only radios of the AP node the client is on: its 2.4 GHz signal
+        // says nothing about how it would hear another node.
+        let best = radios
+            .iter()
+            .filter(|r| r.bssid == st.bssid || same_device(&r.bssid, &st.bssid))
+            .filter_map(|r| high_band_loss(r.freq_mhz).map(|loss| (r, sig - loss)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((to, expected_dbm)) = best else {
+            continue;
+        };
+        if expected_dbm < STEER_MIN_DBM {
+            continue;
+        }
+
+        out.push(Suggestion {
+            station: st.mac,
+            from_bssid: st.bssid,
+            from_freq_mhz: freq,
+            to_bssid: to.bssid,
+            to_freq_mhz: to.freq_mhz,
+            signal_dbm: sig,
+            expected_dbm,
+            dual_band,
+            at: st.at,
+        });
+    }
+
+    out.sort_by(|a, b| b.expected_dbm.total_cmp(&a.expected_dbm));
+    out
+}
@@ -0,0 +1,263 @@
+// src/survey.rs
+//
+// Walk-through survey: while the user walks from room to room, each scan
+// says which of our own nodes is strongest at that spot and whether the
+// device is actually associated to it. Per room this adds up to the
+// "expected node" table homeowners want from a walk-test, plus the rooms
+// where clients stick to a worse node or no node covers well.
+//
+// Own nodes are found like in topology.rs: radios broadcasting one of our
+// SSIDs (by default the connected AP's) and their sibling radios, grouped
+// per device with same_device.
+
+use std::sync::Mutex;
+
+use crate::backhaul::median;
+use crate::lib_rust::{format_mac, same_device, BssRow};
+use crate::stamp::Stamp;
+
+/// Being associated to a node this much weaker than the best one counts as
+/// sticking to the wrong node.
+pub const STICKY_DB: f32 = 8.0;
+/// A room whose best node is weaker than this isn't really covered.
+pub const WEAK_DBM: f32 = -70.0;
+
+/// What one scan at one spot says.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub room: String,
+    pub at: Stamp,
+    /// Strongest own node here and its signal.
+    pub best_node: Option<usize>,
+    pub best_bssid: Option<[u8; 6]>,
+    pub best_dbm: Option<f32>,
+    pub connected_bssid: Option<[u8; 6]>,
+    pub connected_node: Option<usize>,
+    /// Signal of the connected node's strongest radio in this scan.
+    pub connected_dbm: Option<f32>,
+}
+
+impl Sample {
+    pub fn on_best(&self) -> Option<bool> {
+        Some(self.connected_node? == self.best_node?)
+    }
+
+    /// How much stronger the best node is than the one we're on.
+    pub fn margin_db(&self) -> Option<f32> {
+        Some(self.best_dbm? - self.connected_dbm?)
+    }
+}
+
+/// One row of the per-room table.
+#[derive(Debug, Clone)]
+pub struct RoomSummary {
+    pub room: String,
+    pub samples: usize,
+    /// The node most often strongest here.
+    pub expected_node: Option<usize>,
+    pub expected_dbm: Option<f32>,
+    /// The node the device was most often associated to here.
+    pub connected_node: Option<usize>,
+    /// Fraction of samples associated to the expected node.
+    pub on_expected: Option<f32>,
+    pub alerts: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    own_ssids: Vec<String>,
+    /// Radios per node, in order of discovery.
+    nodes: Vec<Vec<[u8; 6]>>,
+    /// User-given names, by any radio of the node.
+    names: Vec<([u8; 6], String)>,
+    samples: Vec<Sample>,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Session>> {
+    SESSION.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start a new survey, dropping the previous one. `own_ssids` empty means
+/// the SSID of whatever we're connected to at the first sample.
+pub fn start(own_ssids: Vec<String>, names: Vec<([u8; 6], String)>) {
+    *lock() = Some(Session {
+        own_ssids,
+        names,
+        ..Default::default()
+    });
+}
+
+impl Session {
+    fn node_of(&mut self, mac: &[u8; 6]) -> usize {
+        let owns = |radios: &Vec<[u8; 6]>| radios.iter().any(|r| r == mac || same_device(r, mac));
+        match self.nodes.iter().position(owns) {
+            Some(i) => {
+                if !self.nodes[i].contains(mac) {
+                    self.nodes[i].push(*mac);
+                }
+                i
+            }
+            None => {
+                self.nodes.push(vec![*mac]);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn node_name(&self, node: usize) -> String {
+        let radios = &self.nodes[node];
+        self.names
+            .iter()
+            .find(|(mac, _)| radios.iter().any(|r| r == mac || same_device(r, mac)))
+            .map(|(_, name)| name.clone())
+            .or_else(|| radios.iter().min().map(format_mac))
+            .unwrap_or_default()
+    }
+}
+
+/// Record one scan taken in `room`. None when no survey is running.
+pub fn sample(room: &str, rows: &[BssRow], connected: Option<[u8; 6]>) -> Option<Sample> {
+    let mut guard = lock();
+    let s = guard.as_mut()?;
+
+    if s.own_ssids.is_empty() {
+        if let Some(ssid) = rows
+            .iter()
+            .filter(|r| connected.is_some() && r.bssid == connected)
+            .find_map(|r| r.ssid.as_deref().filter(|s| !s.is_empty()))
+        {
+            s.own_ssids.push(ssid.to_owned());
+        }
+    }
+
+    // Own radios in this scan: our SSIDs, the connected AP, and siblings
+    // of either (hidden backhaul BSSs).
+    let seeds: Vec<[u8; 6]> = rows
+        .iter()
+        .filter(|r| {
+            r.bssid.is_some()
+                && (r.bssid == connected
+                    || r.ssid.as_deref().is_some_and(|x| s.own_ssids.iter().any(|o| o == x)))
+        })
+        .filter_map(|r| r.bssid)
+        .collect();
+    let mut best: Option<(usize, [u8; 6], f32)> = None;
+    let mut per_node: Vec<(usize, f32)> = Vec::new();
+    for r in rows {
+        let (Some(b), Some(sig)) = (r.bssid, r.signal_dbm) else {
+            continue;
+        };
+        let known = s.nodes.iter().flatten().any(|m| same_device(m, &b));
+        if !known && !seeds.iter().any(|x| *x == b || same_device(x, &b)) {
+            continue;
+        }
+        let node = s.node_of(&b);
+        match per_node.iter_mut().find(|(n, _)| *n == node) {
+            Some((_, best_sig)) => *best_sig = best_sig.max(sig),
+            None => per_node.push((node, sig)),
+        }
+        if best.is_none_or(|(_, _, bs)| sig > bs) {
+            best = Some((node, b, sig));
+        }
+    }
+
+    let connected_node = connected.map(|c| s.node_of(&c));
+    let sample = Sample {
+        room: room.to_owned(),
+        at: Stamp::now(),
+        best_node: best.map(|b| b.0),
+        best_bssid: best.map(|b| b.1),
+        best_dbm: best.map(|b| b.2),
+        connected_bssid: connected,
+        connected_node,
+        connected_dbm: connected_node
+            .and_then(|c| per_node.iter().find(|(n, _)| *n == c).map(|(_, sig)| *sig)),
+    };
+    s.samples.push(sample.clone());
+    Some(sample)
+}
+
+/// Display name of a node: the name given at start() or its lowest BSSID.
+pub fn node_name(node: usize) -> String {
+    lock().as_ref().map(|s| s.node_name(node)).unwrap_or_default()
+}
+
+// The most frequent value, ties going to the first seen.
+fn mode(values: impl Iterator<Item = usize>) -> Option<usize> {
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+    for v in values {
+        match counts.iter_mut().find(|(x, _)| *x == v) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((v, 1)),
+        }
+    }
+    counts.iter().rev().max_by_key(|(_, n)| *n).map(|(v, _)| *v)
+}
+
+/// The per-room table, rooms in the order they were first surveyed.
+pub fn report() -> Vec<RoomSummary> {
+    let guard = lock();
+    let Some(s) = guard.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut rooms: Vec<&str> = Vec::new();
+    for smp in &s.samples {
+        if !rooms.contains(&smp.room.as_str()) {
+            rooms.push(&smp.room);
+        }
+    }
+
+    rooms
+        .into_iter()
+        .map(|room| {
+            let samples: Vec<&Sample> = s.samples.iter().filter(|x| x.room == room).collect();
+            let expected = mode(samples.iter().filter_map(|x| x.best_node));
+            let expected_dbm = median(
+                samples
+                    .iter()
+                    .filter(|x| x.best_node.is_some() && x.best_node == expected)
+                    .filter_map(|x| x.best_dbm)
+                    .collect(),
+            );
+            let connected_node = mode(samples.iter().filter_map(|x| x.connected_node));
+            let associated: Vec<&&Sample> =
+                samples.iter().filter(|x| x.connected_node.is_some()).collect();
+            let on_expected = (!associated.is_empty() && expected.is_some()).then(|| {
+                let hits = associated.iter().filter(|x| x.connected_node == expected).count();
+                hits as f32 / associated.len() as f32
+            });
+
+            let mut alerts = Vec::new();
+            if let Some(dbm) = expected_dbm.filter(|d| *d < WEAK_DBM) {
+                alerts.push(format!("weak coverage ({dbm:.0} dBm at best)"));
+            }
+            let margin = median(samples.iter().filter_map(|x| x.margin_db()).collect());
+            if let (Some(c), Some(e), Some(m)) = (connected_node, expected, margin) {
+                if c != e && m >= STICKY_DB {
+                    alerts.push(format!(
+                        "associated to {} although {} is {m:.0} dB stronger",
+                        s.node_name(c),
+                        s.node_name(e)
+                    ));
+                }
+            }
+
+            RoomSummary {
+                room: room.to_owned(),
+                samples: samples.len(),
+                expected_node: expected,
+                expected_dbm,
+                connected_node,
+                on_expected,
+                alerts,
+            }
+        })
+        .collect()
+}
+
+pub fn stop() {
+    *lock() = None;
+}
@@ -0,0 +1,286 @@
+// src/topology.rs
+//
+// Structured view of *our* mesh, as opposed to the neighbourhood:
+//
+//   - nodes: own radios grouped per device (same_device heuristic, or a
+//     shared MLD address for the links of a Wi-Fi 7 multi-link AP)
+//   - which node the client is associated with
+//   - inter-node beacon RSSI, from scans taken right next to a node
+//   - inferred backhaul: the strongest-RSSI spanning tree over the nodes
+//
+// "Own" radios are the ones broadcasting one of our SSIDs (by default the
+// SSID of the AP we're connected to) plus sibling radios of those devices,
+// which catches hidden backhaul BSSs.
+//
+// Exports as JSON or Graphviz DOT.
+
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use crate::lib_rust::{band_name, format_mac, freq_band, same_device, BssRow};
+
+/// A scan's strongest own node counts as "where the scan was taken" when it
+/// is heard at least this loud; that scan then tells us what the node hears.
+const NEAR_DBM: f32 = -45.0;
+
+#[derive(Debug, Clone)]
+pub struct Radio {
+    pub bssid: [u8; 6],
+    pub ssid: Option<String>,
+    pub freq_mhz: Option<u32>,
+    pub channel: Option<u32>,
+    /// Strongest signal it was seen with across all scans.
+    pub signal_dbm: Option<f32>,
+    /// MLD address, for a link of a multi-link AP.
+    pub mld: Option<[u8; 6]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub id: usize,
+    pub radios: Vec<Radio>,
+}
+
+impl Node {
+    fn owns(&self, mac: &[u8; 6]) -> bool {
+        self.radios.iter().any(|r| &r.bssid == mac || same_device(&r.bssid, mac))
+    }
+
+    fn owns_radio(&self, radio: &Radio) -> bool {
+        self.owns(&radio.bssid) || radio.mld.is_some_and(|m| self.radios.iter().any(|r| r.mld == Some(m)))
+    }
+
+    /// Lowest BSSID, used as the node's label.
+    pub fn label(&self) -> String {
+        self.radios.iter().map(|r| r.bssid).min().map_or_else(String::new, |m| format_mac(&m))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Link {
+    pub a: usize,
+    pub b: usize,
+    pub rssi_dbm: f32,
+    /// freq_band() of the radio that was heard best.
+    pub band: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    pub nodes: Vec<Node>,
+    pub client_node: Option<usize>,
+    pub client_bssid: Option<[u8; 6]>,
+    /// Every pair of nodes that heard each other.
+    pub links: Vec<Link>,
+    /// Subset of `links` forming the inferred backhaul tree.
+    pub backhaul: Vec<Link>,
+}
+
+/// Build the topology from scans taken around the house (each a list of
+/// rows seen at one spot).
+pub fn build(scans: &[Vec<BssRow>], connected: Option<[u8; 6]>, own_ssids: &[String]) -> Result<Topology> {
+    // Which SSIDs are ours.
+    let mut ssids: Vec<&str> = own_ssids.iter().map(String::as_str).collect();
+    if ssids.is_empty() {
+        let Some(c) = connected else {
+            bail!("need the connected BSSID or a list of own SSIDs");
+        };
+        ssids.extend(
+            scans
+                .iter()
+                .flatten()
+                .filter(|r| r.bssid == Some(c))
+                .filter_map(|r| r.ssid.as_deref())
+                .filter(|s| !s.is_empty()),
+        );
+    }
+
+    // Every own radio, strongest sighting wins.
+    let mut radios: BTreeMap<[u8; 6], Radio> = BTreeMap::new();
+    let mut seed: Vec<[u8; 6]> = Vec::new();
+    let mut seed_mlds: Vec<[u8; 6]> = Vec::new();
+    for r in scans.iter().flatten() {
+        let Some(b) = r.bssid else { continue };
+        if r.ssid.as_deref().is_some_and(|s| ssids.contains(&s)) || connected == Some(b) {
+            seed.push(b);
+            seed_mlds.extend(r.mld_addr());
+        }
+    }
+    for r in scans.iter().flatten() {
+        let Some(b) = r.bssid else { continue };
+        let mld = r.mld_addr();
+        if !seed.iter().any(|s| *s == b || same_device(s, &b)) && !mld.is_some_and(|m| seed_mlds.contains(&m)) {
+            continue;
+        }
+        let e = radios.entry(b).or_insert_with(|| Radio {
+            bssid: b,
+            ssid: r.ssid.as_deref().map(str::to_owned),
+            freq_mhz: r.freq_mhz,
+            channel: r.channel,
+            signal_dbm: None,
+            mld,
+        });
+        if let Some(sig) = r.signal_dbm {
+            e.signal_dbm = Some(e.signal_dbm.map_or(sig, |s| s.max(sig)));
+        }
+    }
+
+    // Group radios into devices.
+    let mut nodes: Vec<Node> = Vec::new();
+    for radio in radios.into_values() {
+        match nodes.iter_mut().find(|n| n.owns_radio(&radio)) {
+            Some(n) => n.radios.push(radio),
+            None => nodes.push(Node {
+                id: nodes.len(),
+                radios: vec![radio],
+            }),
+        }
+    }
+
+    let client_node = connected.and_then(|c| nodes.iter().find(|n| n.owns(&c)).map(|n| n.id));
+
+    // Inter-node RSSI from scans taken next to a node.
+    let mut heard: BTreeMap<(usize, usize), (f32, u8)> = BTreeMap::new();
+    for scan in scans {
+        let own: Vec<(usize, &BssRow, f32)> = scan
+            .iter()
+            .filter_map(|r| {
+                let b = r.bssid?;
+                let n = nodes.iter().find(|n| n.owns(&b))?;
+                Some((n.id, r, r.signal_dbm?))
+            })
+            .collect();
+        let Some(&(at, _, near)) = own.iter().max_by(|a, b| a.2.total_cmp(&b.2)) else {
+            continue;
+        };
+        if near < NEAR_DBM {
+            continue;
+        }
+        for &(other, r, sig) in &own {
+            if other == at {
+                continue;
+            }
+            let key = (at.min(other), at.max(other));
+            let band = r.freq_mhz.map_or(3, freq_band);
+            let e = heard.entry(key).or_insert((sig, band));
+            if sig > e.0 {
+                *e = (sig, band);
+            }
+        }
+    }
+    let links: Vec<Link> = heard
+        .into_iter()
+        .map(|((a, b), (rssi_dbm, band))| Link { a, b, rssi_dbm, band })
+        .collect();
+
+    let backhaul = spanning_tree(nodes.len(), &links);
+
+    Ok(Topology {
+        nodes,
+        client_node,
+        client_bssid: connected,
+        links,
+        backhaul,
+    })
+}
+
+// Maximum spanning forest by RSSI (Kruskal): each node's backhaul is the
+// loudest sibling path that doesn't form a loop.
+fn spanning_tree(n: usize, links: &[Link]) -> Vec<Link> {
+    let mut sorted = links.to_vec();
+    sorted.sort_by(|x, y| y.rssi_dbm.total_cmp(&x.rssi_dbm));
+
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut out = Vec::new();
+    for l in sorted {
+        let (ra, rb) = (root(&mut parent, l.a), root(&mut parent, l.b));
+        if ra != rb {
+            parent[ra] = rb;
+            out.push(l);
+        }
+    }
+    out
+}
+
+impl Topology {
+    pub fn to_json(&self) -> Value {
+        let link = |l: &Link| {
+            json!({
+                "a": l.a,
+                "b": l.b,
+                "rssi_dbm": l.rssi_dbm,
+                "band": band_name(l.band),
+            })
+        };
+        json!({
+            "nodes": self.nodes.iter().map(|n| json!({
+                "id": n.id,
+                "label": n.label(),
+                "client_connected": self.client_node == Some(n.id),
+                "radios": n.radios.iter().map(|r| json!({
+                    "bssid": format_mac(&r.bssid),
+                    "ssid": r.ssid,
+                    "freq_mhz": r.freq_mhz,
+                    "channel": r.channel,
+                    "band": r.freq_mhz.map(|f| band_name(freq_band(f))),
+                    "signal_dbm": r.signal_dbm,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "client": {
+                "node": self.client_node,
+                "bssid": self.client_bssid.as_ref().map(format_mac),
+            },
+            "links": self.links.iter().map(link).collect::<Vec<_>>(),
+            "backhaul": self.backhaul.iter().map(link).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Graphviz: backhaul links solid, other sibling links dashed, the
+    /// client as its own box attached to its node.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::from("graph mesh {\n    node [shape=ellipse];\n");
+        for n in &self.nodes {
+            let channels: Vec<String> = n
+                .radios
+                .iter()
+                .filter_map(|r| r.channel)
+                .map(|c| c.to_string())
+                .collect();
+            let _ = writeln!(
+                s,
+                "    n{} [label=\"{}\\nch {}\"];",
+                n.id,
+                n.label(),
+                channels.join("/")
+            );
+        }
+        for l in &self.links {
+            let backhaul = self.backhaul.iter().any(|b| b.a == l.a && b.b == l.b);
+            let _ = writeln!(
+                s,
+                "    n{} -- n{} [label=\"{:.0} dBm {}\"{}];",
+                l.a,
+                l.b,
+                l.rssi_dbm,
+                band_name(l.band),
+                if backhaul { ", penwidth=2" } else { ", style=dashed" }
+            );
+        }
+        if let Some(c) = self.client_node {
+            s.push_str("    client [shape=box];\n");
+            let _ = writeln!(s, "    client -- n{c} [style=dotted];");
+        }
+        s.push_str("}\n");
+        s
+    }
+}
@@ -0,0 +1,474 @@
+// src/wpa_ctrl.rs
+//
+// wpa_supplicant control-interface backend (Backend::WpaSupplicant), for
+// Android builds where SELinux keeps the app off nl80211 but the
+// supplicant's control socket is reachable. Speaks the text protocol
+// wpa_cli does, over a Unix datagram socket per request stream:
+//   SCAN -> CTRL-EVENT-SCAN-RESULTS on a second, ATTACHed socket ->
+//   BSS FIRST / BSS NEXT-<id>, each mapped into a BssRow (the "ie" hex
+//   carries everything the other backends parse out of IEs)
+//   STATUS for the connected BSSID, SIGNAL_POLL / PKTCNT_POLL for the link
+//
+// Interfaces are the sockets in the control directory ($WPA_CTRL_DIR, else
+// the first of CTRL_DIRS that has any). Surveys, the regulatory domain and
+// the radio's capabilities have no control-interface command and still
+// need nl80211. FAIL-BUSY comes back as EBUSY, so ScanOptions::busy
+// applies; the supplicant picks the scan MAC itself, so random_mac has no
+// effect here.
+//
+// Exposes:
+//   - available() -> bool, interfaces() -> Result<Vec<WifiIface>>
+//   - scan_all_bss_async(ifindex, opts, on_row)
+//   - get_connected_bssid(ifindex), link(ifindex)
+//   - WpaCtrlBackend, the WifiBackend of all of the above
+
+use anyhow::{bail, Context, Result};
+use std::ffi::{CStr, CString, OsString};
+use std::io::ErrorKind;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::error::WifiError;
+use crate::lib_rust::{parse_mac, BssRow, NoTrigger, Retry, RowSink, ScanOptions};
+use crate::link::LinkInfo;
+use crate::netlink::{NlError, WifiIface, IFTYPE_STATION};
+use crate::provider::{ScanFuture, WifiBackend};
+
+// Where wpa_supplicant puts its sockets: desktop Linux, then Android's
+// vendor and legacy locations.
+const CTRL_DIRS: &[&str] = &[
+    "/var/run/wpa_supplicant",
+    "/data/vendor/wifi/wpa/sockets",
+    "/data/misc/wifi/sockets",
+];
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+// The supplicant's own scans run longer than the kernel's 4 s.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(15);
+
+// WPA_BSS_MASK_*: ID, BSSID, FREQ, CAPABILITIES, LEVEL, AGE, IE, SSID.
+const BSS_MASK: u32 = 0x1 | 0x2 | 0x4 | 0x10 | 0x80 | 0x200 | 0x400 | 0x1000;
+
+// Control sockets of the interfaces, sorted by name. P2P device sockets
+// ("p2p-dev-wlan0") aren't for scanning.
+fn sockets() -> Vec<PathBuf> {
+    let env = std::env::var_os("WPA_CTRL_DIR").map(PathBuf::from);
+    let dirs = env.into_iter().chain(CTRL_DIRS.iter().map(PathBuf::from));
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_socket()))
+            .filter(|e| !e.file_name().to_string_lossy().starts_with("p2p-"))
+            .map(|e| e.path())
+            .collect();
+        if !found.is_empty() {
+            found.sort();
+            return found;
+        }
+    }
+    Vec::new()
+}
+
+fn no_socket() -> WifiError {
+    WifiError::NoInterface("no wpa_supplicant control socket found".into())
+}
+
+/// Whether a wpa_supplicant control socket can be found.
+pub fn available() -> bool {
+    !sockets().is_empty()
+}
+
+fn name_of(path: &Path) -> String {
+    path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned())
+}
+
+/// The interfaces wpa_supplicant controls, as station interfaces.
+pub fn interfaces() -> Result<Vec<WifiIface>> {
+    let socks = sockets();
+    if socks.is_empty() {
+        bail!(no_socket());
+    }
+    Ok(socks
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let name = name_of(p);
+            let ifindex = std::ffi::CString::new(name.clone())
+                .ok()
+                .map(|c| unsafe { libc::if_nametoindex(c.as_ptr()) })
+                .filter(|&i| i != 0)
+                .unwrap_or(i as u32 + 1);
+            WifiIface {
+                ifindex,
+                name,
+                mac: None,
+                iftype: IFTYPE_STATION,
+                wiphy: None,
+            }
+        })
+        .collect())
+}
+
+// The socket of `ifindex`, the first one if None.
+fn socket_for(ifindex: Option<u32>) -> Result<PathBuf> {
+    let socks = sockets();
+    let Some(i) = ifindex else {
+        return socks.into_iter().next().ok_or_else(|| no_socket().into());
+    };
+    let name = interfaces()?
+        .into_iter()
+        .find(|w| w.ifindex == i)
+        .map(|w| w.name)
+        .or_else(|| index_to_name(i))
+        .ok_or_else(|| WifiError::NoInterface(format!("no interface with index {i}")))?;
+    socks
+        .into_iter()
+        .find(|p| name_of(p) == name)
+        .ok_or_else(|| WifiError::NoInterface(format!("wpa_supplicant doesn't control {name}")).into())
+}
+
+fn index_to_name(ifindex: u32) -> Option<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let p = unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) };
+    if p.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned())
+}
+
+// One client socket for the replies, bound in a directory of its own
+// that only we can enter (mkdtemp, 0700) under $XDG_RUNTIME_DIR or the
+// temp dir, so no other user can send to it or plant anything at its
+// path. Both are removed again on drop.
+struct Ctrl {
+    sock: UnixDatagram,
+    dir: PathBuf,
+}
+
+impl Ctrl {
+    fn open(path: &Path) -> Result<Ctrl> {
+        let dir = private_dir()?;
+        let local = dir.join("client");
+        let sock = match UnixDatagram::bind(&local) {
+            Ok(sock) => sock,
+            Err(e) => {
+                let _ = std::fs::remove_dir(&dir);
+                return Err(e).with_context(|| format!("bind {}", local.display()));
+            }
+        };
+        let ctrl = Ctrl { sock, dir };
+        match ctrl.sock.connect(path) {
+            Ok(()) => {}
+            // The socket is usually root's or the wifi group's.
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => bail!(WifiError::PermissionDenied(format!(
+                "not allowed to use wpa_supplicant's control socket {}",
+                path.display()
+            ))),
+            Err(e) => return Err(e).with_context(|| format!("connect to {}", path.display())),
+        }
+        ctrl.sock.set_read_timeout(Some(REPLY_TIMEOUT))?;
+        Ok(ctrl)
+    }
+
+    // Send `cmd` and return its reply; FAIL-BUSY is an EBUSY NetlinkError.
+    fn request(&self, cmd: &str) -> Result<String> {
+        let verb = cmd.split(' ').next().unwrap_or(cmd);
+        debug!(cmd = verb, "wpa_supplicant request");
+        self.sock.send(cmd.as_bytes()).with_context(|| format!("send {cmd}"))?;
+        let mut buf = vec![0u8; 8192];
+        loop {
+            let n = self
+                .sock
+                .recv(&mut buf)
+                .with_context(|| format!("{cmd}: no reply from wpa_supplicant"))?;
+            let reply = String::from_utf8_lossy(&buf[..n]);
+            // Events to an ATTACHed socket start with "<level>".
+            if reply.starts_with('<') {
+                continue;
+            }
+            return match reply.trim_end() {
+                "FAIL-BUSY" => Err(WifiError::NetlinkError(NlError {
+                    cmd: verb.into(),
+                    errno: libc::EBUSY,
+                })
+                .into()),
+                r @ ("FAIL" | "UNKNOWN COMMAND") => {
+                    debug!(cmd = verb, reply = r, "wpa_supplicant refused");
+                    bail!("{verb}: wpa_supplicant says {r}")
+                }
+                _ => Ok(reply.into_owned()),
+            };
+        }
+    }
+
+    // Wait for the first of `names` among the events, returning it.
+    fn wait_event(&self, names: &[&'static str], timeout: Duration) -> Result<&'static str> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                bail!(WifiError::ScanTimeout);
+            }
+            self.sock.set_read_timeout(Some(left))?;
+            let n = match self.sock.recv(&mut buf) {
+                Ok(n) => n,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    bail!(WifiError::ScanTimeout)
+                }
+                Err(e) => return Err(e).context("waiting for wpa_supplicant events"),
+            };
+            let ev = String::from_utf8_lossy(&buf[..n]);
+            // "<3>CTRL-EVENT-SCAN-RESULTS "
+            let body = ev.split_once('>').map_or(&*ev, |(_, b)| b);
+            if let Some(name) = names.iter().find(|n| body.starts_with(**n)) {
+                return Ok(name);
+            }
+        }
+    }
+}
+
+impl Drop for Ctrl {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(self.dir.join("client"));
+        let _ = std::fs::remove_dir(&self.dir);
+    }
+}
+
+// A new, empty directory only we can use, made by mkdtemp(3).
+fn private_dir() -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|d| d.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    let template = base.join("wpa_ctrl-XXXXXX");
+    let mut template = CString::new(template.as_os_str().as_bytes())?.into_bytes_with_nul();
+    // SAFETY: a NUL-terminated template ending in XXXXXX, which mkdtemp
+    // overwrites in place.
+    if unsafe { libc::mkdtemp(template.as_mut_ptr() as *mut libc::c_char) }.is_null() {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("mkdtemp in {}", base.display()));
+    }
+    template.pop();
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+// The SCAN command for `opts`. A band alone can't be passed on (the
+// channel list would need nl80211); its BSSs are filtered afterwards.
+fn scan_command(opts: &ScanOptions) -> String {
+    let mut cmd = String::from("SCAN");
+    if opts.passive {
+        cmd.push_str(" passive=1");
+    }
+    if !opts.freqs.is_empty() {
+        let freqs: Vec<String> = opts.freqs.iter().map(u32::to_string).collect();
+        cmd.push_str(&format!(" freq={}", freqs.join(",")));
+    }
+    for ssid in &opts.ssids {
+        cmd.push_str(" ssid ");
+        for b in ssid {
+            cmd.push_str(&format!("{b:02x}"));
+        }
+    }
+    cmd
+}
+
+// Trigger a scan as `opts` asks and wait until it is done.
+fn trigger(ctrl: &Ctrl, path: &Path, opts: &ScanOptions) -> Result<()> {
+    if opts.flush {
+        ctrl.request("BSS_FLUSH 0")?;
+    }
+    let cmd = scan_command(opts);
+    let mut attempt = 0;
+    // Attached anew for every try, so a busy scan's results don't count.
+    let events = loop {
+        let events = Ctrl::open(path)?;
+        events.request("ATTACH")?;
+        let Err(e) = ctrl.request(&cmd) else {
+            break events;
+        };
+        match opts.busy.retry(e, attempt)? {
+            Retry::After(wait) => std::thread::sleep(wait),
+            // The scan in progress ends with the same event.
+            Retry::Join => break events,
+        }
+        attempt += 1;
+    };
+    match events.wait_event(&["CTRL-EVENT-SCAN-RESULTS", "CTRL-EVENT-SCAN-FAILED"], SCAN_TIMEOUT)? {
+        "CTRL-EVENT-SCAN-FAILED" => bail!(WifiError::ScanAborted("scan failed".into())),
+        _ => Ok(()),
+    }
+}
+
+/// Scan on `ifindex`'s interface (the first one if None) through
+/// wpa_supplicant and read its BSS table, like nl_raw's. Only reads the
+/// table with opts.cached_only.
+pub async fn scan_all_bss_async(
+    ifindex: Option<u32>,
+    opts: &ScanOptions,
+    on_row: RowSink,
+) -> Result<(Vec<BssRow>, Option<NoTrigger>)> {
+    let opts = opts.clone();
+    // Blocking socket I/O: off the runtime's workers.
+    tokio::task::spawn_blocking(move || scan(ifindex, &opts, on_row)).await?
+}
+
+fn scan(ifindex: Option<u32>, opts: &ScanOptions, mut on_row: RowSink) -> Result<(Vec<BssRow>, Option<NoTrigger>)> {
+    let path = socket_for(ifindex)?;
+    let ctrl = Ctrl::open(&path)?;
+    let untriggered = if opts.cached_only {
+        Some(NoTrigger::CachedOnly)
+    } else {
+        trigger(&ctrl, &path, opts)?;
+        None
+    };
+    let mut rows = Vec::new();
+    let mut cmd = format!("BSS FIRST MASK=0x{BSS_MASK:x}");
+    // An empty reply ends the table.
+    while let Some((id, mut row)) = parse_bss(&ctrl.request(&cmd)?) {
+        on_row(&mut row);
+        rows.push(row);
+        cmd = format!("BSS NEXT-{id} MASK=0x{BSS_MASK:x}");
+    }
+    Ok((rows, untriggered))
+}
+
+// The "key=value" lines of a reply.
+fn fields(reply: &str) -> impl Iterator<Item = (&str, &str)> {
+    reply.lines().filter_map(|l| l.split_once('='))
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// One BSS reply: its id and row.
+fn parse_bss(reply: &str) -> Option<(u32, BssRow)> {
+    let (mut id, mut bssid, mut freq, mut level) = (None, None, None, None);
+    let (mut capability, mut beacon_interval, mut age, mut ies) = (None, None, None, None);
+    for (k, v) in fields(reply) {
+        match k {
+            "id" => id = v.parse().ok(),
+            "bssid" => bssid = parse_mac(v).ok(),
+            "freq" => freq = v.parse().ok(),
+            // dBm with every nl80211 driver; positive values are
+            // driver-specific quality figures.
+            "level" => level = v.parse::<f32>().ok().filter(|&l| l <= 0.0),
+            "capabilities" => capability = u16::from_str_radix(v.trim_start_matches("0x"), 16).ok(),
+            // TUs.
+            "beacon_int" => beacon_interval = v.parse().ok(),
+            // Seconds since last seen.
+            "age" => age = v.parse::<u32>().ok().map(|s| s.saturating_mul(1000)),
+            "ie" => ies = unhex(v),
+            _ => {}
+        }
+    }
+    let mut row = BssRow::from_parts(bssid, freq, level, ies.as_deref()).seen_ms_ago(age);
+    row.capability = capability;
+    row.beacon_interval = beacon_interval;
+    Some((id?, row))
+}
+
+fn status(ctrl: &Ctrl) -> Result<Option<[u8; 6]>> {
+    let reply = ctrl.request("STATUS")?;
+    let completed = fields(&reply).any(|(k, v)| k == "wpa_state" && v == "COMPLETED");
+    let bssid = fields(&reply).find(|(k, _)| *k == "bssid").and_then(|(_, v)| parse_mac(v).ok());
+    Ok(bssid.filter(|_| completed))
+}
+
+/// The BSSID wpa_supplicant is associated with on `ifindex`'s interface.
+pub fn get_connected_bssid(ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+    status(&Ctrl::open(&socket_for(ifindex)?)?)
+}
+
+/// The link to the associated AP from SIGNAL_POLL, with PKTCNT_POLL's
+/// packet counters where the driver has them; None when not associated.
+pub fn link(ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+    let ctrl = Ctrl::open(&socket_for(ifindex)?)?;
+    let Some(bssid) = status(&ctrl)? else {
+        return Ok(None);
+    };
+    let mut l = LinkInfo {
+        bssid,
+        ..Default::default()
+    };
+    for (k, v) in fields(&ctrl.request("SIGNAL_POLL")?) {
+        match k {
+            "RSSI" => l.signal_dbm = v.parse().ok(),
+            "AVG_RSSI" => l.signal_avg_dbm = v.parse().ok(),
+            // Mbps.
+            "LINKSPEED" => l.tx_bitrate_kbps = v.parse::<u32>().ok().map(|m| m * 1000),
+            _ => {}
+        }
+    }
+    if let Ok(reply) = ctrl.request("PKTCNT_POLL") {
+        for (k, v) in fields(&reply) {
+            match k {
+                "TXGOOD" => l.tx_packets = v.parse().ok(),
+                "TXBAD" => l.tx_failed = v.parse().ok(),
+                "RXGOOD" => l.rx_packets = v.parse().ok(),
+                _ => {}
+            }
+        }
+    }
+    Ok(Some(l))
+}
+
+/// Backend::WpaSupplicant; surveys, the regulatory domain, capabilities,
+/// stations and events are WifiBackend's nl80211 defaults.
+pub struct WpaCtrlBackend;
+
+impl WifiBackend for WpaCtrlBackend {
+    fn scan<'a>(&'a self, ifindex: Option<u32>, opts: &'a ScanOptions, on_row: RowSink) -> ScanFuture<'a> {
+        Box::pin(scan_all_bss_async(ifindex, opts, on_row))
+    }
+
+    fn connected_bssid(&self, ifindex: Option<u32>) -> Result<Option<[u8; 6]>> {
+        get_connected_bssid(ifindex)
+    }
+
+    fn link(&self, ifindex: Option<u32>) -> Result<Option<LinkInfo>> {
+        link(ifindex)
+    }
+
+    fn interfaces(&self) -> Result<Vec<WifiIface>> {
+        interfaces()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn client_dir_is_private_and_removed() {
+        let dir = private_dir().unwrap();
+        let meta = std::fs::symlink_metadata(&dir).unwrap();
+        assert!(meta.is_dir());
+        assert_eq!(meta.permissions().mode() & 0o777, 0o700);
+        let (sock, _peer) = UnixDatagram::pair().unwrap();
+        drop(Ctrl { sock, dir: dir.clone() });
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn quiet_socket_is_a_scan_timeout() {
+        let (sock, _peer) = UnixDatagram::pair().unwrap();
+        let ctrl = Ctrl { sock, dir: private_dir().unwrap() };
+        let e = ctrl.wait_event(&["CTRL-EVENT-SCAN-RESULTS"], Duration::from_millis(20)).unwrap_err();
+        assert!(matches!(e.downcast_ref::<WifiError>(), Some(WifiError::ScanTimeout)), "{e:#}");
+    }
+}
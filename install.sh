@@ -88,6 +88,13 @@ else
   log "wifi_backend will not be built. Adjust BACKEND_DIR in install.sh."
 fi
 
+# ---- 5b) OUI vendor database (optional) ----
+# Built into ~/.local/share/wifi_backend/oui.bin and memory-mapped by the
+# backend; vendor lookups just return None without it.
+log "Building OUI vendor database from the IEEE registry..."
+python "${BACKEND_DIR}/scripts/build_oui_db.py" \
+  || log "WARNING: could not build the OUI database; vendor names will be missing."
+
 # ---- 6) Sanity checks ----
 log "Sanity checks (imports + PATH)..."
 python - <<'PY'
@@ -0,0 +1,168 @@
+# pybackend/backhaul_ssh.py
+"""
+Collect mesh backhaul link stats from nodes reachable over SSH (OpenWrt and
+other Linux nodes with `iw`) and feed them to wifi_backend's backhaul
+monitor (backend/src/backhaul.rs).
+
+    nodes = {
+        "living": BackhaulNode("192.168.1.1", "wlan1"),
+        "office": BackhaulNode("192.168.1.2", "wlan1"),
+    }
+    peers = {"aa:bb:cc:00:00:02": "office", "aa:bb:cc:00:00:01": "living"}
+    poll_nodes(nodes, peers)             # call every ~30 s
+    wifi_backend.backhaul_health()
+
+Key-based SSH login is assumed (BatchMode, no password prompts). Stations
+not listed in `peers` (ordinary clients) are ignored.
+
+detect_uplinks() reads each node's bridge table to tell wired from wireless
+backhaul, for wifi_backend.classify_backhaul(overrides=...).
+"""
+
+from __future__ import annotations
+
+import re
+import shlex
+import subprocess
+from dataclasses import dataclass
+from typing import Any, Dict, List, Mapping, Optional
+
+import wifi_backend  # compiled PyO3 module
+
+_STATION = re.compile(r"^Station ([0-9a-fA-F:]{17})")
+_SIGNAL = re.compile(r"^\s*signal avg:\s*(-?\d+)|^\s*signal:\s*(-?\d+)")
+_COUNTER = re.compile(r"^\s*tx (packets|retries):\s*(\d+)")
+_MCS = re.compile(r"^\s*tx bitrate:.*?\b(?:(?:VHT|HE|EHT)-)?MCS (\d+)")
+
+
+@dataclass
+class BackhaulNode:
+    host: str
+    iface: str
+    user: str = "root"
+    timeout: float = 10.0
+    # Backhaul channel, recorded with the samples when known.
+    freq_mhz: Optional[int] = None
+
+
+def parse_station_dump(text: str) -> List[Dict[str, Any]]:
+    """Parse `iw dev <iface> station dump` output into one dict per station."""
+    out: List[Dict[str, Any]] = []
+    cur: Dict[str, Any] = {}
+    for line in text.splitlines():
+        m = _STATION.match(line)
+        if m:
+            cur = {"mac": m.group(1).lower()}
+            out.append(cur)
+            continue
+        if not cur:
+            continue
+        m = _SIGNAL.match(line)
+        if m:
+            # "signal avg" wins over the instantaneous "signal".
+            if m.group(1) is not None:
+                cur["rssi_dbm"] = float(m.group(1))
+            else:
+                cur.setdefault("rssi_dbm", float(m.group(2)))
+            continue
+        m = _COUNTER.match(line)
+        if m:
+            cur["tx_" + m.group(1)] = int(m.group(2))
+            continue
+        m = _MCS.match(line)
+        if m:
+            cur["mcs"] = int(m.group(1))
+    return out
+
+
+def _ssh(node: BackhaulNode, *cmd: str) -> str:
+    res = subprocess.run(
+        [
+            "ssh",
+            "-o", "BatchMode=yes",
+            "-o", f"ConnectTimeout={int(node.timeout)}",
+            f"{node.user}@{node.host}",
+            *cmd,
+        ],
+        capture_output=True,
+        text=True,
+        timeout=node.timeout + 5,
+    )
+    if res.returncode != 0:
+        raise RuntimeError(f"{node.host}: {res.stderr.strip() or 'ssh failed'}")
+    return res.stdout
+
+
+def station_dump(node: BackhaulNode) -> List[Dict[str, Any]]:
+    """Run `iw station dump` on the node over SSH."""
+    return parse_station_dump(_ssh(node, "iw", "dev", node.iface, "station", "dump"))
+
+
+# Prints the bridge port the default gateway's MAC was learned on.
+_UPLINK_PORT = (
+    "set -- $(ip route show default); gw=$3; dev=$5; "
+    "mac=$(ip neigh show \"$gw\" | sed -n 's/.*lladdr \\([0-9a-f:]*\\).*/\\1/p'); "
+    "[ -n \"$mac\" ] && bridge fdb show br \"$dev\" | "
+    "sed -n \"s/^$mac dev \\([^ ]*\\).*/\\1/p\" | head -n1"
+)
+_WIRED_PORTS = ("eth", "lan", "wan", "en")
+_WIRELESS_PORTS = ("wlan", "phy", "mesh", "wds", "ath", "ra")
+
+
+def uplink_kind(port: str) -> Optional[str]:
+    """'wired' / 'wireless' for a bridge port name, None if unsure."""
+    if port.startswith(_WIRELESS_PORTS):
+        return "wireless"
+    if port.startswith(_WIRED_PORTS):
+        return "wired"
+    return None
+
+
+def detect_uplinks(nodes: Mapping[str, BackhaulNode]) -> Dict[str, str]:
+    """
+    {node: "wired" | "wireless"} from which bridge port each node reaches
+    its default gateway through. Nodes that can't be reached or whose port
+    name says nothing (and the gateway itself) are left out.
+    """
+    out: Dict[str, str] = {}
+    for name, node in nodes.items():
+        try:
+            port = _ssh(node, "sh -c " + shlex.quote(_UPLINK_PORT)).strip()
+        except (OSError, RuntimeError, subprocess.TimeoutExpired):
+            continue
+        kind = uplink_kind(port) if port else None
+        if kind:
+            out[name] = kind
+    return out
+
+
+def poll_nodes(
+    nodes: Mapping[str, BackhaulNode], peers: Mapping[str, str]
+) -> Dict[str, str]:
+    """
+    Sample every node's links to the other nodes once. `peers` maps each
+    node's backhaul MAC to its name. Returns {node: error} for nodes that
+    couldn't be reached; the rest are recorded.
+    """
+    peers = {mac.lower(): name for mac, name in peers.items()}
+    errors: Dict[str, str] = {}
+    for name, node in nodes.items():
+        try:
+            stations = station_dump(node)
+        except (OSError, RuntimeError, subprocess.TimeoutExpired) as e:
+            errors[name] = str(e)
+            continue
+        for st in stations:
+            peer = peers.get(st["mac"])
+            if peer is None or peer == name:
+                continue
+            wifi_backend.record_backhaul_sample(
+                name,
+                peer,
+                rssi_dbm=st.get("rssi_dbm"),
+                mcs=st.get("mcs"),
+                tx_packets=st.get("tx_packets"),
+                tx_retries=st.get("tx_retries"),
+                freq_mhz=node.freq_mhz,
+            )
+    return errors
@@ -0,0 +1,169 @@
+# pybackend/ha_mqtt.py
+"""
+Home Assistant MQTT discovery for the Wi-Fi health wifi_backend computes:
+channel quality, the connected AP's RSSI and backhaul alerts show up as HA
+entities without any YAML.
+
+    client = paho.mqtt.client.Client()
+    client.connect("homeassistant.local")
+    ha = HaPublisher(client)
+    ha.announce()                 # once per connection (retained configs)
+    ha.publish_state()            # every scan / minute
+
+Any client with paho-mqtt's publish(topic, payload, qos=0, retain=False)
+works; this module doesn't import an MQTT library itself. Entities (all
+under one "Wi-Fi mesh" device):
+
+    sensor.<node>_connected_rssi     dBm of the AP we're associated with
+    sensor.<node>_channel            channel we're on
+    sensor.<node>_best_channel       compute_best_channel()'s channel
+    sensor.<node>_best_band          and its band ("2.4GHz", "5GHz", "6GHz")
+    sensor.<node>_channel_quality    0-100, 100 = no interference on our channel
+    binary_sensor.<node>_channel_ok  on when we're on the best channel
+    binary_sensor.<node>_backhaul    problem when any backhaul link is degraded,
+                                     with the alerts as attributes
+"""
+
+from __future__ import annotations
+
+import json
+from dataclasses import dataclass
+from typing import Any, Dict, List, Optional, Tuple
+
+from pybackend import rust_bridge
+
+# Interference weight (compute_channels' scale) at which quality hits 0:
+# three APs at -60 dBm on our channel.
+_WORST_WEIGHT = 120.0
+
+# (component, object id, name, extra config)
+_ENTITIES: List[Tuple[str, str, str, Dict[str, Any]]] = [
+    ("sensor", "connected_rssi", "Connected RSSI",
+     {"device_class": "signal_strength", "unit_of_measurement": "dBm", "state_class": "measurement"}),
+    ("sensor", "channel", "Channel", {"icon": "mdi:wifi"}),
+    ("sensor", "best_channel", "Best channel", {"icon": "mdi:wifi-star"}),
+    ("sensor", "best_band", "Best band", {"icon": "mdi:wifi-star"}),
+    ("sensor", "channel_quality", "Channel quality",
+     {"unit_of_measurement": "%", "state_class": "measurement", "icon": "mdi:wifi-check"}),
+    ("binary_sensor", "channel_ok", "On best channel", {"payload_on": True, "payload_off": False}),
+    ("binary_sensor", "backhaul", "Backhaul",
+     {"device_class": "problem", "payload_on": True, "payload_off": False,
+      "json_attributes_topic": "{base}/backhaul_alerts"}),
+]
+
+
+@dataclass
+class HaPublisher:
+    client: Any
+    node_id: str = "wifi_mesh"
+    discovery_prefix: str = "homeassistant"
+    # State topics live under <base_topic>/<node_id>.
+    base_topic: str = "wifi_mesh"
+    qos: int = 0
+
+    @property
+    def base(self) -> str:
+        return f"{self.base_topic}/{self.node_id}"
+
+    def discovery_payloads(self) -> List[Tuple[str, str]]:
+        """(topic, JSON config) per entity, for HA's MQTT discovery."""
+        device = {
+            "identifiers": [self.node_id],
+            "name": "Wi-Fi mesh",
+            "manufacturer": "wifi_backend",
+            "sw_version": rust_bridge.backend_about().get("version"),
+        }
+        out = []
+        for component, oid, name, extra in _ENTITIES:
+            cfg: Dict[str, Any] = {
+                "name": name,
+                "unique_id": f"{self.node_id}_{oid}",
+                "object_id": f"{self.node_id}_{oid}",
+                "state_topic": f"{self.base}/state",
+                "value_template": "{{ value_json.%s }}" % oid,
+                "availability_topic": f"{self.base}/status",
+                "device": device,
+            }
+            for k, v in extra.items():
+                cfg[k] = v.format(base=self.base) if isinstance(v, str) and "{base}" in v else v
+            topic = f"{self.discovery_prefix}/{component}/{self.node_id}/{oid}/config"
+            out.append((topic, json.dumps(cfg)))
+        return out
+
+    def announce(self) -> None:
+        """Publish the retained discovery configs and mark the node online."""
+        for topic, payload in self.discovery_payloads():
+            self.client.publish(topic, payload, qos=self.qos, retain=True)
+        self.client.publish(f"{self.base}/status", "online", qos=self.qos, retain=True)
+
+    def withdraw(self) -> None:
+        """Remove the entities from HA (empty retained configs)."""
+        for topic, _ in self.discovery_payloads():
+            self.client.publish(topic, "", qos=self.qos, retain=True)
+        self.client.publish(f"{self.base}/status", "offline", qos=self.qos, retain=True)
+
+    def publish_state(self, state: Optional[Dict[str, Any]] = None) -> Dict[str, Any]:
+        """Publish `state` (collect_state() by default) and return it."""
+        if state is None:
+            state = collect_state()
+        values = {k: v for k, v in state.items() if k != "backhaul_alerts"}
+        self.client.publish(f"{self.base}/state", json.dumps(values), qos=self.qos)
+        self.client.publish(
+            f"{self.base}/backhaul_alerts",
+            json.dumps({"alerts": state.get("backhaul_alerts", [])}),
+            qos=self.qos,
+        )
+        return state
+
+
+def channel_quality(weight: float) -> int:
+    """0-100 from an interference weight, 100 meaning none at all."""
+    return max(0, round(100 * (1 - weight / _WORST_WEIGHT)))
+
+
+def collect_state() -> Dict[str, Any]:
+    """
+    One scan's worth of entity values: {connected_rssi, channel,
+    best_channel, best_band, channel_quality, channel_ok, backhaul,
+    backhaul_alerts}.
+    Values that can't be known (not connected) are None.
+    """
+    rows = rust_bridge.run_wifi_scan("ha")
+    bssid = rust_bridge.get_connected_bssid()
+    mine = next((r for r in rows if bssid and r.get("bssid") == bssid), None)
+    channel = mine.get("channel") if mine else None
+    band = rust_bridge.band_of(mine.get("freq_mhz")) if mine else None
+
+    quality = None
+    if channel is not None:
+        weight = next(
+            (
+                c["weight"]
+                for c in rust_bridge.channel_breakdown()
+                if (c["band"], c["channel"]) == (band, channel)
+            ),
+            0.0,
+        )
+        # The breakdown counts our own AP too (ap_weight(): dBm + 100 from
+        # -80 dBm up); it isn't interference.
+        sig = mine.get("signal_dbm")
+        if sig is not None and sig >= -80:
+            weight -= sig + 100
+        quality = channel_quality(max(0.0, weight))
+    best_band, best = rust_bridge.compute_best_channel()
+
+    alerts = [
+        f"{h['from']}->{h['to']}: {a}"
+        for h in rust_bridge.backhaul_health()
+        for a in h["alerts"]
+    ]
+    return {
+        "connected_rssi": mine.get("signal_dbm") if mine else None,
+        "channel": channel,
+        "best_channel": best,
+        "best_band": best_band,
+        "channel_quality": quality,
+        "channel_ok": None if channel is None else (band, channel) == (best_band, best),
+        "backhaul": bool(alerts),
+        "backhaul_alerts": alerts,
+    }
@@ -0,0 +1,236 @@
+# pybackend/history_analytics.py
+"""
+Canned analyses of the scan history database (wifi_backend.open_history_db,
+schema in backend/src/history_db.rs) and an export to DuckDB / Parquet for
+anything heavier.
+
+    wifi_backend.open_history_db("history.sqlite")
+    ...
+    busiest_hours("history.sqlite")        # when the neighbourhood is loudest
+    volatile_neighbors("history.sqlite")   # APs whose signal / channel jump around
+    channel_occupancy("history.sqlite")    # APs per channel per day
+    latency_by_occupancy("history.sqlite") # gateway RTT / loss vs. APs on our channel
+    export_duckdb("history.sqlite", "history.duckdb")
+    export_parquet("history.sqlite", "history_parquet/")
+
+The queries run on the SQLite file with the standard library, so they need
+nothing extra; the exports need the `duckdb` package. Times are bucketed in
+local time. Scans the writer hasn't flushed yet (history_db_stats()
+"pending") are not included.
+"""
+
+from __future__ import annotations
+
+import math
+import os
+import pathlib
+import sqlite3
+from contextlib import closing
+from typing import Any, Dict, List, Optional
+
+# Scans of the last `days` days only, when given.
+_SINCE = "(? IS NULL OR s.at >= strftime('%s', 'now') - ? * 86400)"
+
+
+def _connect(db_path: str) -> sqlite3.Connection:
+    if not os.path.exists(db_path):
+        raise RuntimeError(f"{db_path}: no such history database")
+    # as_uri() percent-encodes '?', '#' and '%' in the path, which would
+    # otherwise end or garble a URI written by hand.
+    con = sqlite3.connect(pathlib.Path(db_path).resolve().as_uri() + "?mode=ro", uri=True)
+    con.row_factory = sqlite3.Row
+    return con
+
+
+def busiest_hours(db_path: str, days: Optional[float] = None) -> List[Dict[str, Any]]:
+    """
+    Per hour of the day (0-23), busiest first: {hour, scans, mean_aps,
+    mean_strong_aps}. strong means heard at -70 dBm or better.
+    """
+    sql = f"""
+        SELECT CAST(strftime('%H', s.at, 'unixepoch', 'localtime') AS INTEGER) AS hour,
+               COUNT(*) AS scans,
+               AVG(n) AS mean_aps,
+               AVG(strong) AS mean_strong_aps
+        FROM scans s
+        JOIN (SELECT scan_id, COUNT(*) AS n,
+                     SUM(CASE WHEN signal_dbm >= -70 THEN 1 ELSE 0 END) AS strong
+              FROM bss GROUP BY scan_id) b ON b.scan_id = s.id
+        WHERE {_SINCE}
+        GROUP BY hour
+        ORDER BY mean_aps DESC, hour
+    """
+    with closing(_connect(db_path)) as con:
+        return [dict(r) for r in con.execute(sql, (days, days))]
+
+
+def volatile_neighbors(
+    db_path: str,
+    limit: int = 10,
+    min_samples: int = 5,
+    connected_too: bool = False,
+    days: Optional[float] = None,
+) -> List[Dict[str, Any]]:
+    """
+    BSSs whose signal varies the most, most volatile first: {bssid, ssid,
+    samples, mean_dbm, stddev_dbm, min_dbm, max_dbm, channels}. channels is
+    how many different channels it was seen on (channel hopping, e.g.
+    auto-channel APs). The AP we were connected to is left out unless
+    `connected_too`.
+    """
+    sql = f"""
+        SELECT b.bssid, MAX(b.ssid) AS ssid, COUNT(*) AS samples,
+               AVG(b.signal_dbm) AS mean_dbm,
+               AVG(b.signal_dbm * b.signal_dbm) AS mean_sq,
+               MIN(b.signal_dbm) AS min_dbm, MAX(b.signal_dbm) AS max_dbm,
+               COUNT(DISTINCT b.channel) AS channels
+        FROM bss b JOIN scans s ON s.id = b.scan_id
+        WHERE b.bssid IS NOT NULL AND b.signal_dbm IS NOT NULL
+          AND (? OR s.connected IS NULL OR s.connected != b.bssid)
+          AND {_SINCE}
+        GROUP BY b.bssid
+        HAVING COUNT(*) >= ?
+    """
+    with closing(_connect(db_path)) as con:
+        rows = [dict(r) for r in con.execute(sql, (connected_too, days, days, min_samples))]
+    for r in rows:
+        # SQLite has no STDDEV; population variance from the two means.
+        r["stddev_dbm"] = math.sqrt(max(0.0, r.pop("mean_sq") - r["mean_dbm"] ** 2))
+    rows.sort(key=lambda r: (-r["stddev_dbm"], -r["channels"], r["bssid"]))
+    return rows[:limit]
+
+
+def channel_occupancy(
+    db_path: str, bucket: str = "day", days: Optional[float] = None
+) -> List[Dict[str, Any]]:
+    """
+    Mean APs per scan on each channel per `bucket` ("hour", "day" or
+    "week"), oldest first: {bucket, channel, scans, mean_aps, mean_dbm}.
+    The trend of one channel over time is the rows with that channel.
+    """
+    fmt = {"hour": "%Y-%m-%d %H:00", "day": "%Y-%m-%d", "week": "%Y-W%W"}.get(bucket)
+    if fmt is None:
+        raise ValueError(f"bucket must be 'hour', 'day' or 'week', not {bucket!r}")
+    sql = f"""
+        WITH t AS (
+            SELECT s.id, strftime('{fmt}', s.at, 'unixepoch', 'localtime') AS bucket
+            FROM scans s WHERE {_SINCE}
+        ),
+        per_bucket AS (SELECT bucket, COUNT(*) AS scans FROM t GROUP BY bucket)
+        SELECT t.bucket, b.channel, p.scans,
+               CAST(COUNT(*) AS REAL) / p.scans AS mean_aps,
+               AVG(b.signal_dbm) AS mean_dbm
+        FROM t
+        JOIN bss b ON b.scan_id = t.id
+        JOIN per_bucket p ON p.bucket = t.bucket
+        WHERE b.channel IS NOT NULL AND b.channel > 0
+        GROUP BY t.bucket, b.channel
+        ORDER BY t.bucket, b.channel
+    """
+    with closing(_connect(db_path)) as con:
+        return [dict(r) for r in con.execute(sql, (days, days))]
+
+
+def latency_by_occupancy(db_path: str, days: Optional[float] = None) -> List[Dict[str, Any]]:
+    """
+    Gateway latency (wifi_backend.probe_start() samples) grouped by how many
+    other APs shared the connected AP's channel in the scan before the probe:
+    {aps, probes, loss, mean_ms, max_ms}, fewest APs first. Rising RTT or
+    loss with `aps` points at co-channel interference.
+    """
+    sql = f"""
+        WITH own AS (
+            SELECT s.id, s.at,
+                   (SELECT COUNT(*) FROM bss o
+                    WHERE o.scan_id = s.id AND o.channel = b.channel
+                      AND o.bssid != s.connected) AS aps
+            FROM scans s JOIN bss b ON b.scan_id = s.id AND b.bssid = s.connected
+            WHERE {_SINCE}
+        ),
+        tagged AS (
+            SELECT l.rtt_ms,
+                   (SELECT o.aps FROM own o WHERE o.at <= l.at
+                    ORDER BY o.at DESC LIMIT 1) AS aps
+            FROM latency l
+        )
+        SELECT aps, COUNT(*) AS probes,
+               1.0 - CAST(COUNT(rtt_ms) AS REAL) / COUNT(*) AS loss,
+               AVG(rtt_ms) AS mean_ms, MAX(rtt_ms) AS max_ms
+        FROM tagged
+        WHERE aps IS NOT NULL
+        GROUP BY aps
+        ORDER BY aps
+    """
+    with closing(_connect(db_path)) as con:
+        has_latency = con.execute(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'latency'"
+        ).fetchone()
+        if not has_latency:
+            return []
+        return [dict(r) for r in con.execute(sql, (days, days))]
+
+
+def _duckdb():
+    try:
+        import duckdb  # type: ignore
+    except ImportError as e:
+        raise RuntimeError("DuckDB export needs the duckdb package (pip install duckdb)") from e
+    return duckdb
+
+
+def _attach(con: Any, db_path: str) -> None:
+    # ATTACH takes no bound parameters, so the path goes in as a quoted
+    # literal.
+    quoted = os.path.abspath(db_path).replace("'", "''")
+    con.execute("INSTALL sqlite; LOAD sqlite;")
+    con.execute(f"ATTACH '{quoted}' AS hist (TYPE sqlite, READ_ONLY)")
+
+
+def export_duckdb(db_path: str, out_path: str) -> Dict[str, int]:
+    """
+    Copy the history (tables scans and bss, plus a bss_at view joining each
+    sighting to its scan time as a TIMESTAMP) into the DuckDB file
+    `out_path`, replacing what was there. Returns {table: rows}.
+    """
+    duckdb = _duckdb()
+    _connect(db_path).close()
+    con = duckdb.connect(out_path)
+    try:
+        _attach(con, db_path)
+        counts = {}
+        for table in ("scans", "bss"):
+            con.execute(f"CREATE OR REPLACE TABLE {table} AS SELECT * FROM hist.{table}")
+            counts[table] = con.execute(f"SELECT COUNT(*) FROM {table}").fetchone()[0]
+        con.execute(
+            """
+            CREATE OR REPLACE VIEW bss_at AS
+            SELECT to_timestamp(s.at) AS at, s.connected, b.*
+            FROM bss b JOIN scans s ON s.id = b.scan_id
+            """
+        )
+        con.execute("DETACH hist")
+        return counts
+    finally:
+        con.close()
+
+
+def export_parquet(db_path: str, out_dir: str) -> List[str]:
+    """
+    Write scans.parquet and bss.parquet into `out_dir` (created if needed),
+    for DuckDB, pandas or Spark to register directly. Returns the paths.
+    """
+    duckdb = _duckdb()
+    _connect(db_path).close()
+    os.makedirs(out_dir, exist_ok=True)
+    con = duckdb.connect()
+    try:
+        _attach(con, db_path)
+        out = []
+        for table in ("scans", "bss"):
+            path = os.path.join(out_dir, f"{table}.parquet")
+            quoted = path.replace("'", "''")
+            con.execute(f"COPY (SELECT * FROM hist.{table}) TO '{quoted}' (FORMAT parquet)")
+            out.append(path)
+        return out
+    finally:
+        con.close()
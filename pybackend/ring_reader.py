@@ -0,0 +1,113 @@
+# pybackend/ring_reader.py
+"""
+Reader for the shared-memory ring that wifi_backend streams scan rows into
+(see backend/src/ring.rs for the layout).
+
+    path = wifi_backend.open_ring(4096)
+    reader = RingReader(path)
+    ...
+    buf = reader.read()          # memoryview over N * RECORD_SIZE bytes
+    arr = numpy.frombuffer(buf, dtype=RECORD_DTYPE)   # no per-row objects
+    # or, without numpy:
+    for t_ms, bssid, band, freq, ch, sig in reader.records(): ...
+
+The reader never locks: it copies everything published since the last call
+and drops records the writer lapped while the copy was in progress.
+"""
+
+from __future__ import annotations
+
+import mmap
+import struct
+from typing import Iterator, Tuple
+
+MAGIC = int.from_bytes(b"WFRB", "little")
+VERSION = 1
+HEADER_LEN = 64
+
+_HEADER = struct.Struct("=IIII")
+_WRITE_SEQ = struct.Struct("=Q")
+_WRITE_SEQ_OFF = 16
+
+# t_ms, bssid, band, (pad), freq_mhz, channel, signal_dbm, (reserved)
+RECORD = struct.Struct("=Q6sBxIIf4x")
+RECORD_SIZE = RECORD.size
+
+# numpy dtype spec matching RECORD, for numpy.frombuffer().
+RECORD_DTYPE = [
+    ("t_ms", "=u8"),
+    ("bssid", "V6"),
+    ("band", "u1"),
+    ("_pad", "u1"),
+    ("freq_mhz", "=u4"),
+    ("channel", "=u4"),
+    ("signal_dbm", "=f4"),
+    ("_reserved", "=u4"),
+]
+
+
+class RingReader:
+    def __init__(self, path: str, start_at_oldest: bool = False):
+        self._file = open(path, "rb")
+        self._map = mmap.mmap(self._file.fileno(), 0, access=mmap.ACCESS_READ)
+        self._view = memoryview(self._map)
+
+        magic, version, record_size, capacity = _HEADER.unpack_from(self._view, 0)
+        if magic != MAGIC or version != VERSION or record_size != RECORD_SIZE:
+            self.close()
+            raise RuntimeError(f"{path}: not a wifi_backend ring (v{VERSION})")
+        self.capacity = capacity
+
+        seq = self._write_seq()
+        self._read_seq = max(0, seq - capacity) if start_at_oldest else seq
+        self.dropped = 0
+
+    def _write_seq(self) -> int:
+        return _WRITE_SEQ.unpack_from(self._view, _WRITE_SEQ_OFF)[0]
+
+    def _slots(self, start: int, end: int) -> bytes:
+        # Copy records [start, end) out of the ring, handling wrap-around.
+        cap = self.capacity
+        out = bytearray()
+        while start < end:
+            slot = start % cap
+            n = min(end - start, cap - slot)
+            off = HEADER_LEN + slot * RECORD_SIZE
+            out += self._view[off:off + n * RECORD_SIZE]
+            start += n
+        return bytes(out)
+
+    def read(self) -> memoryview:
+        """Everything published since the last read, as packed records."""
+        end = self._write_seq()
+        start = max(self._read_seq, end - self.capacity)
+        self.dropped += start - self._read_seq
+
+        data = self._slots(start, end)
+
+        # The writer may have overwritten the oldest slots (including the one
+        # it is filling now) while we copied; those bytes can be torn.
+        after = self._write_seq()
+        first_ok = after + 1 - self.capacity
+        if first_ok > start:
+            skip = min(first_ok, end) - start
+            self.dropped += skip
+            data = data[skip * RECORD_SIZE:]
+
+        self._read_seq = end
+        return memoryview(data)
+
+    def records(self) -> Iterator[Tuple[int, bytes, int, int, int, float]]:
+        """read(), unpacked into tuples. Slower; prefer read() + numpy."""
+        return RECORD.iter_unpack(self.read())
+
+    def close(self) -> None:
+        self._view.release()
+        self._map.close()
+        self._file.close()
+
+    def __enter__(self) -> "RingReader":
+        return self
+
+    def __exit__(self, *exc) -> None:
+        self.close()
@@ -3,20 +3,142 @@
 Python ↔ Rust bridge for wifi_backend (PyO3 module).
 
 Exposes:
-    - run_wifi_scan(room_name: str) -> list[dict]
-    - compute_best_channel() -> int
-    - get_connected_bssid() -> str | None
+    - CancelToken (wifi_backend.CancelToken), passed as `cancel=` below
+    - ScanOptions (wifi_backend.ScanOptions), passed as `options=` below
+    - WifiError and NoInterfaceError, PermissionDeniedError,
+      ScanTimeoutError, ScanAbortedError, NetlinkError, UnsupportedError
+      (wifi_backend's), raised by the calls below
+    - list_interfaces() -> list[dict]
+    - run_wifi_scan(room_name: str, fields=None, cancel=None, options=None, iface=None) -> list[dict]
+    - last_scan_info() -> dict | None
+    - push_android_scan(results, connection=None) -> int
+    - async run_wifi_scan_async(room_name: str, fields=None, cancel=None, options=None, iface=None) -> list[dict]
+    - run_merged_scan(room_name: str, fields=None, cancel=None, options=None) -> list[dict]
+    - stream_wifi_scan(room_name: str, fields=None, cancel=None, options=None) -> iterator of dict
+    - run_wifi_scan_n(room_name: str, times=3, interval=1.0, progress=None, cancel=None) -> list[dict]
+    - scan_average(n=3, interval_ms=1000, alpha=0.3, progress=None, cancel=None) -> ScanSnapshot
+    - ChannelConfig (wifi_backend.ChannelConfig), passed as `config=` below
+    - compute_best_channel(candidates=None, config=None, iface=None, snapshot=None) -> (str, int)
+    - band_of(freq_mhz) -> str | None: a scan dict's band, as the picks name it
+    - compute_best_channel_plan(candidates=None, config=None, iface=None, snapshot=None) -> (str, int, int)
+    - channel_scores(candidates=None, config=None, iface=None, snapshot=None) -> list[dict]
+    - interference_matrix(band=None, config=None, iface=None, snapshot=None) -> list[dict]
+    - async compute_best_channel_async(...) -> (str, int) / channel_scores_async(...) -> list[dict]
+    - channel_breakdown(band=None) -> list[dict]
+    - channel_survey() -> list[dict]
+    - regulatory_domain() -> dict
+    - phy_capabilities() -> list[dict]
+    - set_p2p_policy(policy: str) -> None
+    - set_exclude_ibss(exclude: bool) -> None
+    - set_device_grouping(rule="middle_bytes", vendors=None, devices=None, callback=None) -> None
+    - set_log_level(level: int | str) -> None
+    - get_connected_bssid(iface=None) -> str | None
+    - link_info(iface=None) -> dict | None
+    - async link_info_async(iface=None) -> dict | None
+    - LinkMonitor (wifi_backend.LinkMonitor): get_samples(since=None) -> list[dict]
+    - wifi_events() -> iterator of dict
+    - add_event_callback(callback) -> int / remove_event_callback(id=None) -> bool
+    - score_history(window: int | None = None, progress=None, cancel=None) -> dict
+    - assign_mesh_channels_24(node_scans, own_bssids=(), node_bssids=None, cancel=None) -> list[int]
+    - neighbor_mesh_systems(scan=None, own_bssids=()) -> list[dict]
+    - learn_ap_model(fingerprint, name) -> None
+    - band_steering(stations=None, radios=None, include_unknown=False) -> list[dict]
+    - backhaul_health() -> list[dict]
+    - airtime_report(window=5.0, pcap=None, own_bssids=(), progress=None, cancel=None) -> list[dict]
+    - capture_beacons(iface, path, seconds=10.0, freq=None, cancel=None) -> dict
+    - survey_room(room_name | None, scan=None, connected_bssid=None, progress=None, cancel=None) -> dict
+    - survey_table() -> list[dict]
+    - assign_mesh_channels_5(node_names, node_scans, gateway=None, uplinks=None, ..., cancel=None) -> list[int]
+    - assign_mesh_channels_6(node_names, node_scans, gateway=None, uplinks=None, ..., cancel=None) -> list[int]
+    - plan_mesh_channels(node_names, node_scans, gateway=None, uplinks=None, ..., cancel=None) -> list[dict]
+    - mesh_topology(scans, connected_bssid=None, own_ssids=()) -> dict
+    - mesh_topology_dot(scans, connected_bssid=None, own_ssids=()) -> str
+    - set_location(lat, lon, alt=None, accuracy_m=None) / clear_location()
+    - export_geojson(path=None, observations=True, aps=True) -> str
+    - export_kml(path=None, observations=True, aps=True) -> str
+    - export_scan_json(path=None, pretty=False, iface=None) -> str
+    - export_scan_csv(path, iface=None) -> int
+    - export_wigle_csv(path, lat, lon, alt=None, accuracy_m=None, iface=None) -> int
+    - diff_scans(old, new, min_rssi_delta=10.0) -> dict
+    - hidden_network_count(channel=None, iface=None) -> int
+    - rnr_neighbors(iface=None) -> list[dict]
+    - group_by_ssid(iface=None) -> list[dict]
+    - suggest_roam_target(hysteresis_db=8.0, iface=None) -> dict | None
+    - device_groups(iface=None) -> list[dict]
+    - detect_anomalies(own_ssids=(), window=None, iface=None) -> list[dict]
+    - security_report(iface=None) -> list[dict]
+    - regulatory_mismatches(iface=None) -> dict
+    - set_floor_plan(aps, rooms=(), exponent=3.0) / locate(scan=None, ranges=None) -> dict
+    - start_wardriving(interval=2.0, host="127.0.0.1", port=2947) / stop_wardriving() -> dict
+    - backend_about() -> dict
+    - use_mock_backend(fixture_path) -> int
+    - set_dry_run(enabled: bool) -> None / get_dry_run() -> bool
+
+Setting $WIFI_BACKEND_MOCK to a fixture path (e.g.
+backend/fixtures/mock_home.json) selects the mock backend on import, so
+the app runs without Wi-Fi hardware.
 """
 
 from __future__ import annotations
-from typing import List, Dict, Any, Optional
+import json
+import os
+from typing import List, Dict, Any, Callable, Iterator, Optional, Sequence, Tuple, Union
 
 import wifi_backend  # compiled PyO3 module
 
+# progress(percent, stage, message), called from the long-running calls
+# below; raising from it aborts the call with that exception.
+ProgressCallback = Callable[[float, str, str], None]
+
+# Cancelling one from another thread makes the call it was passed to raise
+# RuntimeError("operation cancelled") at its next safe point.
+CancelToken = wifi_backend.CancelToken
+
+# Passive scans, a frequency list or band, SSIDs to probe, flushing the
+# kernel's stale BSS entries and probing from a random MAC address (check
+# wifi_backend.random_mac_supported() first); see wifi_backend.ScanOptions.
+ScanOptions = wifi_backend.ScanOptions
+
+# Best-channel tuning (threshold, stay-put margin, band preference, weak-AP
+# floor) for power users; see wifi_backend.ChannelConfig.
+ChannelConfig = wifi_backend.ChannelConfig
+
+# What the calls below raise when Wi-Fi itself fails, e.g. ScanTimeoutError
+# to retry later or PermissionDeniedError to ask for privileges. All are
+# WifiError, a RuntimeError; other failures stay plain RuntimeError.
+WifiError = wifi_backend.WifiError
+NoInterfaceError = wifi_backend.NoInterfaceError
+PermissionDeniedError = wifi_backend.PermissionDeniedError
+ScanTimeoutError = wifi_backend.ScanTimeoutError
+ScanAbortedError = wifi_backend.ScanAbortedError
+NetlinkError = wifi_backend.NetlinkError
+UnsupportedError = wifi_backend.UnsupportedError
 
-def run_wifi_scan(room_name: str) -> List[Dict[str, Any]]:
+# Polls link_info() on a Rust thread into a ring of samples for the link
+# graphs: LinkMonitor(interval=1.0, capacity=600), then get_samples(since)
+# now and then; see wifi_backend.LinkMonitor.
+LinkMonitor = wifi_backend.LinkMonitor
+
+
+def list_interfaces() -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's list_interfaces(): the device's Wi-Fi interfaces
+    ({name, ifindex, mac, type, wiphy}, type e.g. "station", "ap",
+    "p2p_device"). Pass a name as `iface=` to scan a specific one; by
+    default the first station interface is used.
     """
-    Call Rust wifi_backend.scan() and return a list of AP dictionaries.
+    return list(wifi_backend.list_interfaces())
+
+
+def run_wifi_scan(
+    room_name: str,
+    fields: Optional[Sequence[str]] = None,
+    cancel: Optional[CancelToken] = None,
+    options: Optional[ScanOptions] = None,
+    iface: Optional[str] = None,
+) -> List[Dict[str, Any]]:
+    """
+    Call Rust wifi_backend.scan_dicts() and return a list of AP dictionaries.
 
     Expected Rust dict keys:
         ssid: str (optional)
@@ -24,11 +146,25 @@ def run_wifi_scan(room_name: str) -> List[Dict[str, Any]]:
         freq_mhz: int (optional)
         signal_dbm: float (optional)
         channel: int (optional)
+        seen_at / seen_mono: float, when the kernel last heard the AP
+            (unix time / time.monotonic()), None if unknown
+        cached: bool, heard before this scan started (kernel BSS cache)
+
+    `fields` limits the dicts to those keys (e.g. ("bssid", "channel",
+    "signal_dbm") for screens that refresh often). `options` shapes the
+    scan, e.g. ScanOptions(band="5GHz", flush=True). `iface` picks the
+    interface by name (see list_interfaces()).
     """
-    rows = wifi_backend.scan()
+    rows = wifi_backend.scan_dicts(
+        False,
+        None if fields is None else list(fields),
+        cancel=cancel,
+        options=options,
+        iface=iface,
+    )
 
     if not isinstance(rows, list):
-        raise RuntimeError(f"wifi_backend.scan() returned invalid type: {type(rows)!r}")
+        raise RuntimeError(f"wifi_backend.scan_dicts() returned invalid type: {type(rows)!r}")
 
     out: List[Dict[str, Any]] = []
     for idx, ap in enumerate(rows):
@@ -40,27 +176,383 @@ def run_wifi_scan(room_name: str) -> List[Dict[str, Any]]:
     return out
 
 
-def compute_best_channel() -> int:
+def last_scan_info() -> Optional[Dict[str, Any]]:
+    """
+    Proxy to Rust's last_scan_info(): whether the last scan was a real one
+    ("triggered") or only the kernel's cached BSS table ("reason":
+    "cached_only", "not_permitted" without root, ...), and the age in
+    seconds of its newest and oldest entries, for a "results may be
+    stale" hint.
+    """
+    return wifi_backend.last_scan_info()
+
+
+def push_android_scan(
+    results: List[Dict[str, Any]], connection: Optional[Dict[str, Any]] = None
+) -> int:
+    """
+    Proxy to Rust's push_android_scan(): on stock Android, where the
+    backend can't reach the radio, hand it WifiManager's scan results
+    (ScanResult fields as dict keys) and connection info; scans and
+    compute_best_channel() then use them. Returns the number of BSSs.
+    """
+    return wifi_backend.push_android_scan(results, connection=connection)
+
+
+async def run_wifi_scan_async(
+    room_name: str,
+    fields: Optional[Sequence[str]] = None,
+    cancel: Optional[CancelToken] = None,
+    options: Optional[ScanOptions] = None,
+    iface: Optional[str] = None,
+) -> List[Dict[str, Any]]:
+    """
+    run_wifi_scan() for asyncio (wifi_backend.scan_dicts_async()): the
+    scan runs off the event loop, so the UI keeps drawing meanwhile.
+    Cancelling the task stops the scan.
+    """
+    rows = await wifi_backend.scan_dicts_async(
+        False,
+        None if fields is None else list(fields),
+        cancel=cancel,
+        options=options,
+        iface=iface,
+    )
+    return [ap for ap in rows if isinstance(ap, dict)]
+
+
+def run_merged_scan(
+    room_name: str,
+    fields: Optional[Sequence[str]] = None,
+    cancel: Optional[CancelToken] = None,
+    options: Optional[ScanOptions] = None,
+) -> List[Dict[str, Any]]:
+    """
+    Like run_wifi_scan(), but scanning on every radio at once
+    (wifi_backend.scan_merged()), for nodes with separate 2.4 and 5 GHz
+    radios. Each AP is listed once, with its strongest signal, and
+    "iface" names the interface that heard it.
+    """
+    return list(
+        wifi_backend.scan_merged(
+            False, None if fields is None else list(fields), cancel=cancel, options=options
+        )
+    )
+
+
+def insecure_neighbors(room_name: str) -> List[Dict[str, Any]]:
+    """
+    APs in range that are open, WEP or WPA (TKIP): {ssid, bssid, channel,
+    signal_dbm, security}, strongest first, for the "insecure networks
+    nearby" warning.
+    """
+    rows = run_wifi_scan(
+        room_name, fields=("ssid", "bssid", "channel", "signal_dbm", "security", "insecure")
+    )
+    out = [r for r in rows if r.pop("insecure", False)]
+    out.sort(key=lambda r: r.get("signal_dbm", -200.0), reverse=True)
+    return out
+
+
+def stream_wifi_scan(
+    room_name: str,
+    fields: Optional[Sequence[str]] = None,
+    cancel: Optional[CancelToken] = None,
+    options: Optional[ScanOptions] = None,
+) -> Iterator[Dict[str, Any]]:
+    """
+    Like run_wifi_scan(), but yields each AP dict as soon as Rust has
+    parsed it (wifi_backend.scan_stream()). Iteration ends when the scan
+    completes; a failed scan raises RuntimeError mid-iteration.
+    """
+    stream = wifi_backend.scan_stream(
+        False, None if fields is None else list(fields), cancel=cancel, options=options
+    )
+    for idx, ap in enumerate(stream):
+        if not isinstance(ap, dict):
+            print(f"WARNING: scan result entry {idx} is not a dict: {ap!r}")
+            continue
+        yield ap
+
+
+def run_wifi_scan_n(
+    room_name: str,
+    times: int = 3,
+    interval: float = 1.0,
+    progress: Optional[ProgressCallback] = None,
+    cancel: Optional[CancelToken] = None,
+) -> List[Dict[str, Any]]:
+    """
+    Several scans `interval` seconds apart in one Rust call
+    (wifi_backend.scan_n()), aggregated per BSS: the run_wifi_scan() keys
+    plus seen (out of `times`), mean_dbm, max_dbm and min_dbm. Steadier
+    than a single scan for per-room readings. `progress` is called after
+    every scan.
+    """
+    return wifi_backend.scan_n(times, interval, progress=progress, cancel=cancel)
+
+
+def scan_average(
+    n: int = 3,
+    interval_ms: int = 1000,
+    alpha: float = 0.3,
+    progress: Optional[ProgressCallback] = None,
+    cancel: Optional[CancelToken] = None,
+) -> Any:
+    """
+    `n` scans `interval_ms` apart as one wifi_backend.ScanSnapshot, each
+    BSS's signal smoothed with an exponentially weighted moving average
+    (`alpha` the weight of the newest scan). Pass it as `snapshot=` to
+    compute_best_channel() / channel_scores() for a recommendation that
+    doesn't flip on one noisy sample.
+    """
+    return wifi_backend.scan_average(n, interval_ms, alpha, progress=progress, cancel=cancel)
+
+
+# A channel to choose from: a 2.4 / 5 GHz channel number, or (band, channel)
+# such as ("6GHz", 37), which 6 GHz channels need.
+Candidate = Union[int, Tuple[str, int]]
+
+
+def band_of(freq_mhz: Optional[int]) -> Optional[str]:
+    """
+    "2.4GHz", "5GHz" or "6GHz" for a scan dict's freq_mhz, the band names
+    compute_best_channel() and channel_scores() use; None for other
+    frequencies.
+    """
+    if freq_mhz is None:
+        return None
+    if 2401 <= freq_mhz <= 2495:
+        return "2.4GHz"
+    if 5150 <= freq_mhz <= 5895:
+        return "5GHz"
+    if 5925 <= freq_mhz <= 7125:
+        return "6GHz"
+    return None
+
+
+def compute_best_channel(
+    candidates: Optional[Sequence[Candidate]] = None,
+    config: Optional[ChannelConfig] = None,
+    iface: Optional[str] = None,
+    snapshot: Any = None,
+) -> Tuple[str, int]:
     """
     Proxy to Rust's compute_best_channel(), which uses its own scan +
-    heuristics to pick a good channel.
+    heuristics to pick a good channel. Returns (band, channel), band
+    "2.4GHz", "5GHz" or "6GHz": 6 GHz reuses the other bands' numbers.
+
+    `candidates` limits the answer to channels the router accepts (e.g.
+    [36, 40, 44, 48, 149, 153] with DFS disabled, or ("6GHz", 37)).
+    `config` tunes how aggressive it is, e.g. ChannelConfig(margin=20.0)
+    to move less often. `iface` picks the interface that scans, as for
+    run_wifi_scan(). `snapshot` (e.g. from scan_average()) is used instead
+    of scanning.
     """
-    best = wifi_backend.compute_best_channel()
-    if not isinstance(best, int):
+    best = wifi_backend.compute_best_channel(
+        None if candidates is None else list(candidates), config, iface=iface, snapshot=snapshot
+    )
+    if not (isinstance(best, tuple) and len(best) == 2):
         raise RuntimeError(f"wifi_backend.compute_best_channel() returned {best!r}")
-    return best
+    band, channel = best
+    return str(band), int(channel)
 
 
-def get_connected_bssid() -> Optional[str]:
+def compute_best_channel_plan(
+    candidates: Optional[Sequence[Candidate]] = None,
+    config: Optional[ChannelConfig] = None,
+    iface: Optional[str] = None,
+    snapshot: Any = None,
+) -> Tuple[str, int, int]:
     """
-    Proxy to Rust's connected_bssid().
+    Proxy to Rust's compute_best_channel_plan(): compute_best_channel()'s
+    (band, channel) and the width in MHz (20/40/80/160/320) to run it at,
+    as wide as the clean channels around it, the regulatory domain and the
+    radio allow. 2.4 GHz stays at 20 MHz when a neighbour is 40 MHz
+    intolerant. Arguments as for compute_best_channel().
+    """
+    band, channel, width = wifi_backend.compute_best_channel_plan(
+        None if candidates is None else list(candidates), config, iface=iface, snapshot=snapshot
+    )
+    return str(band), int(channel), int(width)
+
+
+def channel_scores(
+    candidates: Optional[Sequence[Candidate]] = None,
+    config: Optional[ChannelConfig] = None,
+    iface: Optional[str] = None,
+    snapshot: Any = None,
+) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's channel_scores(): the channels compute_best_channel()
+    chooses among, lowest interference weight first, each with the APs
+    adding to it and the survey's busy share. The one it would pick has
+    "recommended": True, for the channel graph to highlight. `snapshot`
+    as for compute_best_channel().
+    """
+    return list(
+        wifi_backend.channel_scores(
+            None if candidates is None else list(candidates), config, iface=iface, snapshot=snapshot
+        )
+    )
+
+
+def interference_matrix(
+    band: Optional[str] = None,
+    config: Optional[ChannelConfig] = None,
+    iface: Optional[str] = None,
+    snapshot: Any = None,
+) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's interference_matrix(): per band, the weights
+    channel_scores() compares taken apart for a heatmap. "matrix"[i][j] is
+    what the APs on "observed"[i] add to "candidates"[j]; "busy" and
+    "total" are each candidate's survey share and whole weight. `band` is
+    "2.4GHz", "5GHz" or "6GHz" (all bands when None); `snapshot` as for
+    compute_best_channel().
+    """
+    return list(wifi_backend.interference_matrix(band, config, iface=iface, snapshot=snapshot))
+
+
+async def compute_best_channel_async(
+    candidates: Optional[Sequence[Candidate]] = None,
+    config: Optional[ChannelConfig] = None,
+    iface: Optional[str] = None,
+) -> Tuple[str, int]:
+    """
+    compute_best_channel() for asyncio (wifi_backend.compute_best_channel_async()).
+    """
+    return await wifi_backend.compute_best_channel_async(
+        None if candidates is None else list(candidates), config, iface=iface
+    )
+
+
+async def channel_scores_async(
+    candidates: Optional[Sequence[Candidate]] = None,
+    config: Optional[ChannelConfig] = None,
+    iface: Optional[str] = None,
+) -> List[Dict[str, Any]]:
+    """
+    channel_scores() for asyncio (wifi_backend.channel_scores_async()).
+    """
+    return list(
+        await wifi_backend.channel_scores_async(
+            None if candidates is None else list(candidates), config, iface=iface
+        )
+    )
+
+
+async def link_info_async(iface: Optional[str] = None) -> Optional[Dict[str, Any]]:
+    """
+    link_info() for asyncio (wifi_backend.link_info_async()).
+    """
+    return await wifi_backend.link_info_async(iface=iface)
+
+
+def channel_breakdown(band: Optional[str] = None) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's compute_channels(band, detailed=True): per channel, the
+    APs on it, co-channel and adjacent-channel counts, interference weight
+    and the utilization APs report. `band` is "2.4GHz", "5GHz", "6GHz" or
+    None for all bands. `p2p`, `ibss` and `passpoint` count the Wi-Fi
+    Direct groups, ad-hoc networks and Passpoint carrier hotspots among
+    the APs. `busy` is the share of time the radio
+    found the channel busy (None without a channel survey).
+    """
+    return wifi_backend.compute_channels(band, True)
+
+
+def channel_survey() -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's survey(): per frequency, the noise floor (noise_dbm)
+    and the share of time the channel was busy (busy, 0.0 to 1.0), which
+    also catches traffic that doesn't beacon. Drivers without survey
+    support raise RuntimeError; callers should treat that as "no data".
+    """
+    return wifi_backend.survey()
+
+
+def regulatory_domain() -> Dict[str, Any]:
+    """
+    Proxy to Rust's regulatory_domain(): the active country code, its
+    frequency rules and, per channel, whether it is allowed, passive-only
+    (no_ir), DFS and its max EIRP. compute_best_channel() already keeps to
+    the legal channels; this is for showing why a channel is missing.
+    """
+    return wifi_backend.regulatory_domain()
+
+
+def phy_capabilities() -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's phy_capabilities(): per band the local radio supports,
+    its channels (and which are disabled), HT / VHT / HE / EHT support and
+    the widest channel width. compute_best_channel() only proposes
+    channels the radio can use.
+    """
+    return list(wifi_backend.phy_capabilities())
+
+
+def set_p2p_policy(policy: str) -> None:
+    """
+    How Wi-Fi Direct groups (Chromecast, Miracast, printers; "DIRECT-"
+    SSIDs) count in channel scoring: "include", "downweight" (default) or
+    "exclude". Scan dicts with fields=["p2p"] (or details) say which rows
+    are such groups.
+    """
+    wifi_backend.set_p2p_policy(policy)
+
+
+def set_exclude_ibss(exclude: bool) -> None:
+    """
+    Leave ad-hoc (IBSS) networks out of channel counts and scoring, or
+    count them again (the default). Scan dicts with fields=["ibss"] (or
+    details) say which rows are ad-hoc.
+    """
+    wifi_backend.set_exclude_ibss(exclude)
+
+
+def set_device_grouping(
+    rule: str = "middle_bytes",
+    vendors: Optional[Dict[str, str]] = None,
+    devices: Optional[Sequence[Sequence[str]]] = None,
+    callback: Optional[Callable[[str, str], Optional[bool]]] = None,
+) -> None:
+    """
+    Proxy to Rust's set_device_grouping(): which BSSIDs the planners and
+    topology treat as one device. `devices` lists our own nodes' BSSIDs
+    outright; `callback(a, b)` answers True / False or None to leave it to
+    the rules; `vendors` maps OUIs to a rule ("middle_bytes",
+    "last_nibble", "local_admin", "exact") and `rule` covers the rest.
+    Multiple BSSID sets advertised in scans always group.
+    """
+    wifi_backend.set_device_grouping(
+        rule,
+        dict(vendors) if vendors else None,
+        [list(d) for d in devices] if devices else None,
+        callback,
+    )
+
+
+def set_log_level(level) -> None:
+    """
+    Have the Rust side log at `level` and above (logging.DEBUG, "info",
+    ...; WARNING by default) to the "wifi_backend" logger: scan triggers,
+    busy retries, netlink error replies. Attach a handler (or call
+    logging.basicConfig()) to see them.
+    """
+    wifi_backend.set_log_level(level)
+
+
+def get_connected_bssid(iface: Optional[str] = None) -> Optional[str]:
+    """
+    Proxy to Rust's connected_bssid(), on `iface` if given.
 
     Returns:
         - "aa:bb:cc:dd:ee:ff" string, or
         - None if not associated / not detectable.
     """
     try:
-        val = wifi_backend.connected_bssid()
+        val = wifi_backend.connected_bssid(iface=iface)
     except AttributeError:
         # Older native lib that doesn't expose this yet
         return None
@@ -72,3 +564,632 @@ def get_connected_bssid() -> Optional[str]:
 
     # Defensive: Rust *should* always return string or None
     return str(val) or None
+
+
+def link_info(iface: Optional[str] = None) -> Optional[Dict[str, Any]]:
+    """
+    Proxy to Rust's link_info(): signal (last and average), tx/rx bitrate
+    and MCS, retries, failures, drops and the driver's expected throughput
+    of the link to the connected AP, for a link-quality view beyond RSSI
+    bars. None when not connected.
+    """
+    return wifi_backend.link_info(iface=iface)
+
+
+def wifi_events() -> Iterator[Dict[str, Any]]:
+    """
+    Proxy to Rust's subscribe_events(): scan, connect, disconnect, roam,
+    channel switch and regulatory events as they happen, each a dict with
+    "type" ("connected", "disconnected", "roamed", ...). Blocks between
+    events; run it on a worker thread, or use add_event_callback().
+    """
+    return iter(wifi_backend.subscribe_events())
+
+
+def add_event_callback(callback: Callable[[Dict[str, Any]], None]) -> int:
+    """
+    Have Rust call `callback` with every wifi_events() dict, from a
+    background thread (schedule UI work onto the main thread from it).
+    Returns the id for remove_event_callback().
+    """
+    return wifi_backend.add_event_callback(callback)
+
+
+def remove_event_callback(callback_id: Optional[int] = None) -> bool:
+    """Stop an add_event_callback() callback; every one if None."""
+    return wifi_backend.remove_event_callback(callback_id)
+
+
+def score_history(
+    window: Optional[int] = None,
+    progress: Optional[ProgressCallback] = None,
+    cancel: Optional[CancelToken] = None,
+) -> Dict[str, Any]:
+    """
+    Proxy to Rust's score_history(): best-channel scoring over the last
+    `window` scans the native module has recorded (all of them if None).
+    Runs in Rust without holding the GIL, so the UI thread keeps going;
+    `progress` is called every few hundred scans.
+
+    Returns:
+        scans: int
+        first_at / last_at: float unix time, or None if no scans
+        best_channel: (band, channel) | None
+        recommendations: {band: {channel: times_picked}}
+        mean_weight: {band: {channel: weight}}
+    """
+    result = wifi_backend.score_history(window, progress, cancel)
+    if not isinstance(result, dict):
+        raise RuntimeError(f"wifi_backend.score_history() returned {result!r}")
+    return result
+
+
+def assign_mesh_channels_24(
+    node_scans: Sequence[List[Dict[str, Any]]],
+    own_bssids: Sequence[str] = (),
+    node_bssids: Optional[Sequence[Sequence[str]]] = None,
+    cancel: Optional[CancelToken] = None,
+) -> List[int]:
+    """
+    Proxy to Rust's assign_mesh_channels_24(): one 2.4 GHz channel per mesh
+    node, given the AP dicts scanned at each node's location (e.g. a room's
+    "scan_data").
+
+    Without node_bssids, three nodes or fewer always get distinct channels
+    from 1/6/11. With node_bssids (each node's own BSSIDs), nodes that only
+    hear each other below -82 dBm may reuse a channel.
+    """
+    return wifi_backend.assign_mesh_channels_24(
+        [list(rows) for rows in node_scans],
+        list(own_bssids),
+        None if node_bssids is None else [list(b) for b in node_bssids],
+        cancel,
+    )
+
+
+def mesh_topology(
+    scans: Sequence[List[Dict[str, Any]]],
+    connected_bssid: Optional[str] = None,
+    own_ssids: Sequence[str] = (),
+) -> Dict[str, Any]:
+    """
+    Proxy to Rust's mesh_topology(): our own mesh, for drawing "your
+    network" apart from the neighbourhood.
+
+    Returns {"nodes": [...], "client": {"node", "bssid"}, "links": [...],
+    "backhaul": [...]}. Own SSIDs default to the connected AP's SSID, so
+    pass connected_bssid or own_ssids. Inter-node RSSI only comes from
+    scans taken right next to a node.
+    """
+    return json.loads(
+        wifi_backend.mesh_topology(
+            [list(rows) for rows in scans], connected_bssid, list(own_ssids), "json"
+        )
+    )
+
+
+def mesh_topology_dot(
+    scans: Sequence[List[Dict[str, Any]]],
+    connected_bssid: Optional[str] = None,
+    own_ssids: Sequence[str] = (),
+) -> str:
+    """Same as mesh_topology(), as Graphviz DOT text."""
+    return wifi_backend.mesh_topology(
+        [list(rows) for rows in scans], connected_bssid, list(own_ssids), "dot"
+    )
+
+
+def neighbor_mesh_systems(
+    scan: Optional[List[Dict[str, Any]]] = None,
+    own_bssids: Sequence[str] = (),
+) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's neighbor_mesh_systems(): neighbouring whole-home mesh
+    systems (eero, Orbi, Deco, UniFi, Google Wifi, EasyMesh) with their
+    radios grouped per node, strongest first.
+
+    Without scan, a fresh scan is run, which also has the vendor IEs; saved
+    scan dicts are fingerprinted from OUI and BSSID patterns only.
+    """
+    return wifi_backend.neighbor_mesh_systems(
+        None if scan is None else list(scan), list(own_bssids)
+    )
+
+
+def learn_ap_model(fingerprint: str, name: str) -> None:
+    """
+    Proxy to Rust's learn_ap_model(): remember `name` for a beacon
+    fingerprint (the "fingerprint" key of a detailed scan dict), so every AP
+    with the same IE layout gets that "model" from now on.
+    """
+    wifi_backend.learn_ap_model(fingerprint, name)
+
+
+def band_steering(
+    stations: Optional[Sequence[Dict[str, Any]]] = None,
+    radios: Optional[Sequence[Dict[str, Any]]] = None,
+    include_unknown: bool = False,
+) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's band_steering(): dual-band clients on 2.4 GHz that
+    would hear a 5/6 GHz radio of the same AP well, best first.
+
+    stations ({mac, bssid, freq_mhz, signal_dbm, dual_band?}, e.g. from a
+    UniFi controller) default to the clients of local AP-mode interfaces.
+    """
+    return wifi_backend.band_steering(
+        None if stations is None else list(stations),
+        None if radios is None else list(radios),
+        include_unknown,
+    )
+
+
+def backhaul_health() -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's backhaul_health(): per backhaul link, degraded first,
+    the recent RSSI / MCS / retry rate against the link's own history and
+    any alerts. Samples come from pybackend.backhaul_ssh.poll_nodes() or
+    wifi_backend.sample_backhaul().
+    """
+    return wifi_backend.backhaul_health()
+
+
+def airtime_report(
+    window: float = 5.0,
+    pcap: Optional[str] = None,
+    own_bssids: Sequence[str] = (),
+    progress: Optional[ProgressCallback] = None,
+    cancel: Optional[CancelToken] = None,
+) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's airtime_report(): per own AP radio, how its airtime is
+    split across clients, with alerts for clients hogging it.
+
+    Local AP interfaces are sampled over `window` seconds; `pcap` is a
+    radiotap monitor capture for radios whose driver doesn't report
+    per-station airtime. `progress` is called about once a second while
+    sampling.
+    """
+    return wifi_backend.airtime_report(window, pcap, list(own_bssids), progress, cancel)
+
+
+def capture_beacons(
+    iface: str,
+    path: str,
+    seconds: float = 10.0,
+    freq: Optional[int] = None,
+    cancel: Optional[CancelToken] = None,
+) -> Dict[str, Any]:
+    """
+    Proxy to Rust's capture_beacons(): puts `iface` in monitor mode and
+    writes the beacons and probe responses it hears for `seconds` to `path`
+    as a radiotap pcap, to open in Wireshark or pass to airtime_report().
+
+    The interface leaves its network while capturing. Needs root (or
+    CAP_NET_ADMIN and CAP_NET_RAW) and a wifi_backend built with the
+    capture feature; UnsupportedError otherwise. Under set_dry_run(True)
+    nothing is touched and the dict says what would be (dry_run True).
+    """
+    return wifi_backend.capture_beacons(iface, path, seconds, freq, cancel)
+
+def survey_room(
+    room_name: Optional[str],
+    scan: Optional[Sequence[Dict[str, Any]]] = None,
+    connected_bssid: Optional[str] = None,
+    progress: Optional[ProgressCallback] = None,
+    cancel: Optional[CancelToken] = None,
+) -> Dict[str, Any]:
+    """
+    One walk-through sample in `room_name`: which own node is strongest
+    here and whether we're associated to it. Starts a survey if none is
+    running (wifi_backend.survey_start() to name nodes or reset).
+    `progress` is called around the scan when `scan` isn't given.
+    room_name=None names the room from locate() (set_floor_plan() first).
+    """
+    try:
+        return wifi_backend.survey_sample(
+            room_name, None if scan is None else list(scan), connected_bssid, progress, cancel
+        )
+    except RuntimeError as e:
+        if "no survey running" not in str(e):
+            raise
+    wifi_backend.survey_start()
+    return wifi_backend.survey_sample(
+        room_name, None if scan is None else list(scan), connected_bssid, progress, cancel
+    )
+
+
+def survey_table() -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's survey_report(): per room, the node expected to serve
+    it, how often we were actually on it, and alerts for weak coverage or
+    clients sticking to a worse node.
+    """
+    return wifi_backend.survey_report()
+
+
+def assign_mesh_channels_5(
+    node_names: Sequence[str],
+    node_scans: Sequence[List[Dict[str, Any]]],
+    gateway: Optional[str] = None,
+    uplinks: Optional[Dict[str, str]] = None,
+    node_bssids: Optional[Sequence[Sequence[str]]] = None,
+    dfs: bool = False,
+    cancel: Optional[CancelToken] = None,
+) -> List[int]:
+    """
+    One 5 GHz 80 MHz block per mesh node, keeping nodes on wireless
+    backhaul on their parent's channel.
+
+    Wired/wireless comes from wifi_backend.classify_backhaul(): `uplinks`
+    ({node: "wired" | "wireless"}, e.g. from backhaul_ssh.detect_uplinks()
+    or a controller) override what the recorded backhaul links suggest.
+    """
+    follow = _backhaul_follow(node_names, gateway, uplinks, 5150, 5895)
+    return wifi_backend.assign_mesh_channels_5(
+        [list(rows) for rows in node_scans],
+        [],
+        None if node_bssids is None else [list(nb) for nb in node_bssids],
+        follow,
+        dfs,
+        cancel,
+    )
+
+
+def assign_mesh_channels_6(
+    node_names: Sequence[str],
+    node_scans: Sequence[List[Dict[str, Any]]],
+    gateway: Optional[str] = None,
+    uplinks: Optional[Dict[str, str]] = None,
+    node_bssids: Optional[Sequence[Sequence[str]]] = None,
+    cancel: Optional[CancelToken] = None,
+) -> List[int]:
+    """
+    assign_mesh_channels_5() on 6 GHz: one 80 MHz block per node, returned
+    as its Preferred Scanning Channel, the primary to configure.
+    """
+    follow = _backhaul_follow(node_names, gateway, uplinks, 5925, 7125)
+    return wifi_backend.assign_mesh_channels_6(
+        [list(rows) for rows in node_scans],
+        [],
+        None if node_bssids is None else [list(nb) for nb in node_bssids],
+        follow,
+        cancel,
+    )
+
+
+# Band name -> the frequencies _backhaul_follow() counts as that band.
+_BAND_MHZ = {"2.4GHz": (2400, 2500), "5GHz": (5150, 5895), "6GHz": (5925, 7125)}
+
+
+def plan_mesh_channels(
+    node_names: Sequence[str],
+    node_scans: Sequence[Any],
+    gateway: Optional[str] = None,
+    uplinks: Optional[Dict[str, str]] = None,
+    node_bssids: Optional[Sequence[Sequence[str]]] = None,
+    bands: Optional[Sequence[str]] = None,
+    dfs: bool = False,
+    cancel: Optional[CancelToken] = None,
+) -> List[Dict[str, Dict[str, Any]]]:
+    """
+    Proxy to Rust's plan_mesh_channels(): a channel and width for every
+    mesh node on every band, planned jointly so the nodes don't land on
+    top of each other. `node_scans` are each node's AP dicts or a
+    wifi_backend.ScanSnapshot (e.g. imported with from_json()). Returns
+    per node {band: {"channel", "width_mhz", "cost"}}.
+
+    Nodes on wireless backhaul stay on their parent's channel in the band
+    of the link, as in assign_mesh_channels_5().
+    """
+    names = list(bands) if bands is not None else list(_BAND_MHZ)
+    follow = {
+        band: _backhaul_follow(node_names, gateway, uplinks, *_BAND_MHZ[band])
+        for band in names
+        if band in _BAND_MHZ
+    }
+    return wifi_backend.plan_mesh_channels(
+        [rows if isinstance(rows, wifi_backend.ScanSnapshot) else list(rows) for rows in node_scans],
+        [],
+        None if node_bssids is None else [list(nb) for nb in node_bssids],
+        names,
+        follow,
+        dfs,
+        cancel,
+    )
+
+
+def _backhaul_follow(
+    node_names: Sequence[str],
+    gateway: Optional[str],
+    uplinks: Optional[Dict[str, str]],
+    lo_mhz: int,
+    hi_mhz: int,
+) -> List[Optional[int]]:
+    # Per node, the index of the parent it backhauls to wirelessly in
+    # lo_mhz..hi_mhz (or on an unknown frequency), else None.
+    backhaul = wifi_backend.classify_backhaul(
+        list(node_names), gateway, dict(uplinks or {})
+    )
+    index = {name: i for i, name in enumerate(node_names)}
+    follow: List[Optional[int]] = []
+    for b in backhaul:
+        in_band = b["freq_mhz"] is None or lo_mhz <= b["freq_mhz"] <= hi_mhz
+        if b["uplink"] == "wireless" and b["parent"] in index and in_band:
+            follow.append(index[b["parent"]])
+        else:
+            follow.append(None)
+    return follow
+
+
+def set_location(
+    lat: float,
+    lon: float,
+    alt: Optional[float] = None,
+    accuracy_m: Optional[float] = None,
+) -> None:
+    """
+    Tag every following scan with this position (one observation per BSS)
+    until clear_location(), for export_geojson() / export_kml().
+    """
+    wifi_backend.set_location(lat, lon, alt, accuracy_m)
+
+
+def clear_location() -> None:
+    """Stop geotagging scans; the observations logged so far are kept."""
+    wifi_backend.clear_location()
+
+
+def _export(text: str, path: Optional[str]) -> str:
+    if path is not None:
+        with open(path, "w", encoding="utf-8") as f:
+            f.write(text)
+    return text
+
+
+def export_geojson(
+    path: Optional[str] = None, observations: bool = True, aps: bool = True
+) -> str:
+    """
+    Geotagged observations and estimated AP positions as GeoJSON (written
+    to `path` too when given), for QGIS, geojson.io and the like.
+    """
+    return _export(wifi_backend.export_geojson(observations, aps), path)
+
+
+def export_kml(
+    path: Optional[str] = None, observations: bool = True, aps: bool = True
+) -> str:
+    """Same as export_geojson(), as KML for Google Earth."""
+    return _export(wifi_backend.export_kml(observations, aps), path)
+
+
+def export_scan_json(
+    path: Optional[str] = None, pretty: bool = False, iface: Optional[str] = None
+) -> str:
+    """
+    Scan now and return the scan (and connected BSSID) as wifi_backend's
+    versioned JSON document, written to `path` too when given; the
+    desktop tool reads these. wifi_backend.ScanSnapshot.from_json() reads
+    one back.
+    """
+    return _export(wifi_backend.scan_json(pretty=pretty, iface=iface), path)
+
+
+def export_scan_csv(path: str, iface: Optional[str] = None) -> int:
+    """
+    Scan now and write the results to `path` as CSV for spreadsheets
+    (SSID, BSSID, band, channel, width, RSSI, security, last seen in UTC).
+    Returns the number of networks written.
+    """
+    if iface is not None:
+        snap = wifi_backend.WifiSession(iface=iface).snapshot()
+    else:
+        snap = wifi_backend.snapshot()
+    return snap.export_csv(path)
+
+
+def export_wigle_csv(
+    path: str,
+    lat: float,
+    lon: float,
+    alt: Optional[float] = None,
+    accuracy_m: Optional[float] = None,
+    iface: Optional[str] = None,
+) -> int:
+    """
+    Scan now and write the results to `path` in WiGLE's CSV upload format,
+    every network placed at `lat`, `lon` (and `alt` metres, with the fix's
+    `accuracy_m`). Returns the number of networks written.
+    """
+    if iface is not None:
+        snap = wifi_backend.WifiSession(iface=iface).snapshot()
+    else:
+        snap = wifi_backend.snapshot()
+    return snap.export_wigle_csv(path, lat, lon, alt=alt, accuracy_m=accuracy_m)
+
+
+def diff_scans(old: Any, new: Any, min_rssi_delta: float = 10.0) -> Dict[str, Any]:
+    """
+    Proxy to Rust's diff_scans(): what changed between two snapshots
+    (wifi_backend.snapshot() or ScanSnapshot.from_json()): networks that
+    appeared or disappeared, moved channel, or changed signal by at least
+    `min_rssi_delta` dB. A non-empty channel_changes is the cue to run the
+    channel advisor again.
+    """
+    return wifi_backend.diff_scans(old, new, min_rssi_delta)
+
+
+def hidden_network_count(channel: Optional[int] = None, iface: Optional[str] = None) -> int:
+    """
+    Proxy to Rust's hidden_network_count(): how many APs around hide their
+    SSID, on `channel` or on any, for "3 hidden networks on channel 6".
+    Scan rows carry the same as `hidden` (with ssid "").
+    """
+    return wifi_backend.hidden_network_count(channel, iface=iface)
+
+
+def rnr_neighbors(iface: Optional[str] = None) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's rnr_neighbors(): 6 GHz networks that 2.4 / 5 GHz
+    beacons advertise in their Reduced Neighbor Report but the scan didn't
+    hear itself, with an estimated signal. Channel recommendations already
+    count them on 6 GHz; this is for showing them.
+    """
+    return wifi_backend.rnr_neighbors(iface=iface)
+
+
+def group_by_ssid(iface: Optional[str] = None) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's group_by_ssid(): the networks around, strongest first,
+    each with its BSSIDs, the bands it uses and the strongest signal on
+    each, how many APs serve it and whether it looks like a mesh.
+    """
+    return wifi_backend.group_by_ssid(iface=iface)
+
+
+def suggest_roam_target(hysteresis_db: float = 8.0, iface: Optional[str] = None) -> Optional[Dict[str, Any]]:
+    """
+    Proxy to Rust's suggest_roam_target(): the best BSS of the network
+    we're connected to for this spot (`target`, when it beats ours by
+    `hysteresis_db`), every sibling ranked, and `sticky` when the phone
+    hangs on to a distant node while a closer one is heard. None when not
+    connected.
+    """
+    return wifi_backend.suggest_roam_target(hysteresis_db, iface=iface)
+
+
+def device_groups(iface: Optional[str] = None) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's device_groups(): the BSSIDs around grouped by physical
+    AP, each radio with its band and channel, and the evidence that tied
+    them together. A group's `bssids` is what the mesh planners take as
+    one node's BSSIDs.
+    """
+    return wifi_backend.device_groups(iface=iface)
+
+
+def detect_anomalies(
+    own_ssids: Sequence[str] = (),
+    window: Optional[int] = None,
+    iface: Optional[str] = None,
+) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's detect_anomalies(): a fresh scan checked against the
+    scan history for rogue APs and evil twins, most severe first. Each
+    finding has `kind` ("unknown_oui", "security_downgrade",
+    "duplicate_bssid"), `severity` ("high", "medium", "low"), the BSSID
+    and SSID, and the scan dict as `row`. `own_ssids` are the networks to
+    guard (the connected one by default).
+    """
+    return wifi_backend.detect_anomalies(list(own_ssids), window, iface=iface)
+
+
+def security_report(iface: Optional[str] = None) -> List[Dict[str, Any]]:
+    """
+    Proxy to Rust's security_report(): per network around, the weakest
+    security its BSSs offer, PMF and WPS status (`wps_pin` for WPS open to
+    PIN brute force), and a grade from "A" to "F" with the `issues` behind
+    it, worst first. For the environment health screen; `connected` marks
+    our own network. Scan dicts with fields=["wps_enabled", "wps_locked"]
+    (or details) show WPS per BSS.
+    """
+    return wifi_backend.security_report(iface=iface)
+
+
+def regulatory_mismatches(iface: Optional[str] = None) -> Dict[str, Any]:
+    """
+    Proxy to Rust's regulatory_mismatches(): the countries nearby APs
+    claim against our regulatory domain (`local`). `mismatches` lists the
+    foreign ones, each claim's `extra_channels` the channels it lists that
+    aren't legal here, and `off_domain` the APs actually heard on such
+    channels; those distort which channels look usable.
+    """
+    return wifi_backend.regulatory_mismatches(iface=iface)
+
+
+def set_floor_plan(
+    aps: Sequence[Dict[str, Any]],
+    rooms: Sequence[Dict[str, Any]] = (),
+    exponent: float = 3.0,
+) -> None:
+    """
+    Our APs' positions ({bssid, x, y, floor=0, ref_dbm=-40}, metres) and
+    the rooms ({name, polygon: [[x, y], ...], floor=0}) from the floor-plan
+    screen, for locate() and survey_room(None).
+    """
+    wifi_backend.set_floor_plan([dict(a) for a in aps], [dict(r) for r in rooms], exponent)
+
+
+def locate(
+    scan: Optional[Sequence[Dict[str, Any]]] = None,
+    ranges: Optional[Dict[str, float]] = None,
+) -> Dict[str, Any]:
+    """
+    Where we are on the floor plan: {x, y, floor, error_m, aps, room},
+    from `scan` (a fresh scan by default) and any FTM `ranges` in metres
+    by BSSID.
+    """
+    return wifi_backend.locate(
+        None if scan is None else list(scan), None if ranges is None else dict(ranges)
+    )
+
+
+def start_wardriving(
+    interval: Optional[float] = 2.0, host: str = "127.0.0.1", port: int = 2947
+) -> None:
+    """
+    Walk / drive survey: follow gpsd's position and scan every `interval`
+    seconds while there is a fix (interval=None only geotags the scans the
+    app runs itself). wifi_backend.gpsd_status() shows the fix and counts.
+    """
+    wifi_backend.gpsd_start(host, port, interval)
+
+
+def stop_wardriving() -> Dict[str, Any]:
+    """Stop the gpsd client and survey scanning; returns the final status."""
+    status = wifi_backend.gpsd_status()
+    wifi_backend.gpsd_stop()
+    return status
+
+
+def backend_about() -> Dict[str, Any]:
+    """
+    Proxy to Rust's about(): version, cargo features, scan providers and
+    whether nl80211 is reachable, and the capability names of this build,
+    e.g. to hide screens an older backend can't serve:
+
+        if "airtime" not in backend_about()["capabilities"]: ...
+    """
+    return wifi_backend.about()
+
+
+def use_mock_backend(fixture_path: str) -> int:
+    """
+    Serve scans, the connected BSSID and AP station dumps from a JSON
+    fixture (format in backend/src/mock.rs) instead of the radio. Returns
+    the number of scans in it. wifi_backend.mock_fault() injects errors.
+    """
+    n = wifi_backend.load_mock_fixture(fixture_path)
+    wifi_backend.set_backend("mock")
+    return n
+
+
+def set_dry_run(enabled: bool) -> None:
+    """
+    Dry-run mode: while on, capture_beacons() and
+    throughput.validate_channel_change() only validate their inputs and
+    report what they would do. Off by default.
+    """
+    wifi_backend.set_dry_run(enabled)
+
+
+def get_dry_run() -> bool:
+    """
+    Whether dry-run mode is on.
+    """
+    return bool(wifi_backend.get_dry_run())
+
+
+if os.environ.get("WIFI_BACKEND_MOCK"):
+    use_mock_backend(os.environ["WIFI_BACKEND_MOCK"])
@@ -0,0 +1,192 @@
+# pybackend/throughput.py
+"""
+Throughput validation for channel changes: run iperf3 (client mode against
+a server the user runs, e.g. on the NAS) before and after applying a
+recommendation, and record both results next to the recommendation in an
+audit log, so "channel 6 scores better" comes with measured Mbit/s.
+
+    server = IperfServer("192.168.1.10")
+    log = AuditLog("audit.jsonl")
+    entry = validate_channel_change(server, apply=lambda band, ch: router.set_channel(band, ch), log=log)
+    entry["improved"], entry["delta_mbps"]
+
+wifi_backend can't change an AP's channel itself, so `apply` is whatever
+does (controller API, SSH, asking the user and waiting). Under
+rust_bridge.set_dry_run(True) it isn't called: the entry only says what
+would be applied. The iperf3 binary must be on PATH; nothing else is
+needed.
+"""
+
+from __future__ import annotations
+
+import json
+import os
+import shutil
+import subprocess
+import time
+from dataclasses import dataclass
+from typing import Any, Callable, Dict, List, Optional
+
+from pybackend import rust_bridge
+
+
+@dataclass
+class IperfServer:
+    host: str
+    port: int = 5201
+    # Seconds per test, and parallel TCP streams (-P).
+    duration: int = 10
+    streams: int = 4
+    # Server sends, we receive (-R): the direction most clients care about.
+    reverse: bool = True
+    # Extra seconds on top of `duration` before giving up on iperf3.
+    timeout: float = 15.0
+
+
+def parse_iperf3_json(text: str) -> Dict[str, Any]:
+    """
+    {sent_mbps, received_mbps, retransmits, mean_rtt_ms} from `iperf3 -J`
+    output. received_mbps is the goodput the receiver saw; retransmits and
+    RTT are TCP sender stats and None when iperf3 didn't report them.
+    """
+    try:
+        doc = json.loads(text)
+    except ValueError as e:
+        raise RuntimeError(f"iperf3: unreadable output ({e})") from e
+    if doc.get("error"):
+        raise RuntimeError(f"iperf3: {doc['error']}")
+    end = doc.get("end") or {}
+    sent = end.get("sum_sent") or end.get("sum") or {}
+    received = end.get("sum_received") or end.get("sum") or {}
+    rtts = [
+        s["sender"]["mean_rtt"]
+        for s in end.get("streams", [])
+        if isinstance(s.get("sender"), dict) and "mean_rtt" in s["sender"]
+    ]
+    return {
+        "sent_mbps": sent.get("bits_per_second", 0.0) / 1e6,
+        "received_mbps": received.get("bits_per_second", 0.0) / 1e6,
+        "retransmits": sent.get("retransmits"),
+        # iperf3 reports RTT in microseconds.
+        "mean_rtt_ms": sum(rtts) / len(rtts) / 1000 if rtts else None,
+    }
+
+
+def run_iperf3(server: IperfServer) -> Dict[str, Any]:
+    """One iperf3 test against `server`: parse_iperf3_json() plus {at}."""
+    binary = shutil.which("iperf3")
+    if binary is None:
+        raise RuntimeError("iperf3 not found on PATH")
+    cmd = [
+        binary, "-c", server.host, "-p", str(server.port),
+        "-t", str(server.duration), "-P", str(server.streams), "-J",
+    ]
+    if server.reverse:
+        cmd.append("-R")
+    at = time.time()
+    try:
+        res = subprocess.run(
+            cmd, capture_output=True, text=True, timeout=server.duration + server.timeout
+        )
+    except subprocess.TimeoutExpired as e:
+        raise RuntimeError(f"iperf3 to {server.host}: timed out") from e
+    # iperf3 -J reports its own errors in the JSON, exit status 1.
+    if res.returncode != 0 and not res.stdout.strip():
+        raise RuntimeError(f"iperf3 to {server.host}: {res.stderr.strip() or 'failed'}")
+    out = parse_iperf3_json(res.stdout)
+    out["at"] = at
+    return out
+
+
+class AuditLog:
+    """Append-only JSON Lines file of what was recommended, applied and measured."""
+
+    def __init__(self, path: str) -> None:
+        self.path = path
+
+    def record(self, entry: Dict[str, Any]) -> Dict[str, Any]:
+        entry = {"at": time.time(), **entry}
+        with open(self.path, "a", encoding="utf-8") as f:
+            f.write(json.dumps(entry) + "\n")
+        return entry
+
+    def entries(self, kind: Optional[str] = None) -> List[Dict[str, Any]]:
+        """Every entry, oldest first; only those of `kind` when given."""
+        if not os.path.exists(self.path):
+            return []
+        with open(self.path, encoding="utf-8") as f:
+            out = [json.loads(line) for line in f if line.strip()]
+        return [e for e in out if kind is None or e.get("kind") == kind]
+
+
+def recommendation() -> Dict[str, Any]:
+    """
+    The channel change the backend recommends now: {from_band,
+    from_channel, to_band, to_channel}, bands as rust_bridge.band_of()
+    names them.
+    """
+    bssid = rust_bridge.get_connected_bssid()
+    rows = rust_bridge.run_wifi_scan("throughput")
+    mine = next((r for r in rows if bssid and r.get("bssid") == bssid), None)
+    to_band, to_channel = rust_bridge.compute_best_channel()
+    return {
+        "from_band": rust_bridge.band_of(mine.get("freq_mhz")) if mine else None,
+        "from_channel": mine.get("channel") if mine else None,
+        "to_band": to_band,
+        "to_channel": to_channel,
+    }
+
+
+def validate_channel_change(
+    server: IperfServer,
+    apply: Callable[[str, int], None],
+    rec: Optional[Dict[str, Any]] = None,
+    log: Optional[AuditLog] = None,
+    settle: float = 20.0,
+) -> Dict[str, Any]:
+    """
+    Measure, apply(rec["to_band"], rec["to_channel"]), wait `settle`
+    seconds for clients to reassociate, measure again. Returns (and
+    records in `log`) {kind: "channel_change", recommendation, dry_run,
+    before, after, delta_mbps, improved}. `rec` defaults to
+    recommendation(). A failed "after" test is recorded with error set
+    rather than raised, since the change has been made.
+
+    In dry-run mode (rust_bridge.get_dry_run()) the recommendation is
+    checked and "before" measured, but apply() isn't called: the entry has
+    dry_run True and no "after".
+    """
+    if rec is None:
+        rec = recommendation()
+    band, channel = rec.get("to_band"), rec.get("to_channel")
+    if not isinstance(band, str) or not isinstance(channel, int):
+        raise ValueError(f"no channel to apply in {rec!r}")
+    dry_run = rust_bridge.get_dry_run()
+    before = run_iperf3(server)
+
+    entry: Dict[str, Any] = {
+        "kind": "channel_change",
+        "recommendation": rec,
+        "dry_run": dry_run,
+        "server": server.host,
+        "before": before,
+        "after": None,
+        "delta_mbps": None,
+        "improved": None,
+        "error": None,
+    }
+    if dry_run:
+        return log.record(entry) if log is not None else entry
+
+    apply(band, channel)
+    time.sleep(settle)
+    try:
+        after = run_iperf3(server)
+    except RuntimeError as e:
+        entry["error"] = str(e)
+    else:
+        delta = after["received_mbps"] - before["received_mbps"]
+        entry.update(after=after, delta_mbps=delta, improved=delta > 0)
+    if log is not None:
+        entry = log.record(entry)
+    return entry
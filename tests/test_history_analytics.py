@@ -0,0 +1,94 @@
+# tests/test_history_analytics.py
+"""
+pybackend.history_analytics against a small history database written here
+with backend/src/history_db.rs's schema. Run from the repository root:
+
+    python -m unittest discover tests
+
+The export tests skip without the duckdb and pyarrow packages, or when
+DuckDB can't load its sqlite extension (it is downloaded on first use).
+"""
+
+import os
+import sqlite3
+import tempfile
+import time
+import unittest
+
+from pybackend import history_analytics
+
+SCHEMA = """
+    CREATE TABLE scans (id INTEGER PRIMARY KEY, at REAL NOT NULL, connected TEXT);
+    CREATE TABLE bss (scan_id INTEGER NOT NULL REFERENCES scans(id), bssid TEXT, ssid TEXT,
+                      freq_mhz INTEGER, signal_dbm REAL, channel INTEGER);
+    CREATE TABLE latency (at REAL NOT NULL, target TEXT NOT NULL, rtt_ms REAL);
+"""
+
+
+def _write_history(path):
+    now = time.time()
+    with sqlite3.connect(path) as con:
+        con.executescript(SCHEMA)
+        for i in range(3):
+            con.execute("INSERT INTO scans VALUES (?, ?, ?)", (i + 1, now - 60 * i, "02:00:00:00:00:01"))
+            con.executemany(
+                "INSERT INTO bss VALUES (?, ?, ?, ?, ?, ?)",
+                [
+                    (i + 1, "02:00:00:00:00:01", "home", 2437, -40.0, 6),
+                    (i + 1, "02:11:11:11:11:01", "next door", 2412, -60.0 - 5 * i, 1),
+                ],
+            )
+
+
+def _duckdb_with_sqlite():
+    try:
+        import duckdb  # noqa: F401
+        import pyarrow.parquet  # noqa: F401
+    except ImportError as e:
+        raise unittest.SkipTest(f"needs duckdb and pyarrow ({e.name} missing)")
+    try:
+        duckdb.connect().execute("INSTALL sqlite; LOAD sqlite;")
+    except duckdb.Error as e:
+        raise unittest.SkipTest(f"DuckDB can't load its sqlite extension: {e}")
+
+
+class HistoryAnalyticsTest(unittest.TestCase):
+    def setUp(self):
+        tmp = tempfile.TemporaryDirectory()
+        self.addCleanup(tmp.cleanup)
+        self.tmp = tmp.name
+        # '?' and '#' would end a hand-written file: URI early.
+        self.db = os.path.join(self.tmp, "hist?#1.sqlite")
+        _write_history(self.db)
+
+    def test_queries_open_paths_that_need_quoting(self):
+        hours = history_analytics.busiest_hours(self.db)
+        self.assertEqual(sum(h["scans"] for h in hours), 3)
+        self.assertEqual(hours[0]["mean_aps"], 2)
+
+    def test_missing_database_is_an_error(self):
+        with self.assertRaises(RuntimeError):
+            history_analytics.busiest_hours(os.path.join(self.tmp, "missing.sqlite"))
+
+    def test_export_duckdb(self):
+        _duckdb_with_sqlite()
+        import duckdb
+
+        out = os.path.join(self.tmp, "it's.duckdb")
+        self.assertEqual(history_analytics.export_duckdb(self.db, out), {"scans": 3, "bss": 6})
+        with duckdb.connect(out, read_only=True) as con:
+            (n,) = con.execute("SELECT COUNT(*) FROM bss_at WHERE ssid = 'home'").fetchone()
+        self.assertEqual(n, 3)
+
+    def test_export_parquet(self):
+        _duckdb_with_sqlite()
+        import pyarrow.parquet as pq
+
+        paths = history_analytics.export_parquet(self.db, os.path.join(self.tmp, "par'quet"))
+        self.assertEqual([os.path.basename(p) for p in paths], ["scans.parquet", "bss.parquet"])
+        self.assertEqual([pq.read_table(p).num_rows for p in paths], [3, 6])
+        self.assertEqual(pq.read_table(paths[1]).column("channel").to_pylist().count(6), 3)
+
+
+if __name__ == "__main__":
+    unittest.main()